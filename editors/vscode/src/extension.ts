@@ -39,6 +39,70 @@ const DEBOUNCE_DELAY_MS = 150;
 // Virtual document scheme for embedded Modelica
 const EMBEDDED_MODELICA_SCHEME = 'embedded-modelica';
 
+// ============================================================================
+// Compile statistics status bar item (rumoca/compileStats notifications)
+// ============================================================================
+
+interface CompileStats {
+    uri: string;
+    parseMs: number;
+    flattenMs: number;
+    daeMs: number;
+    totalMs: number;
+    classes: number;
+    compiledModels: number;
+    cachedModels: number;
+    diagnostics: { errors: number; warnings: number; information: number; hints: number };
+}
+
+/**
+ * Show the statistics of the last analysis pass of the active document in the status bar
+ */
+function registerCompileStatsStatusBar(context: vscode.ExtensionContext, languageClient: LanguageClient) {
+    const item = vscode.window.createStatusBarItem(vscode.StatusBarAlignment.Right, 100);
+    context.subscriptions.push(item);
+    const statsByUri = new Map<string, CompileStats>();
+
+    const update = () => {
+        const enabled = vscode.workspace.getConfiguration('rumoca').get<boolean>('compileStats') ?? true;
+        const uri = vscode.window.activeTextEditor?.document.uri.toString();
+        const stats = uri ? statsByUri.get(uri) : undefined;
+        if (!enabled || !stats) {
+            item.hide();
+            return;
+        }
+        const ms = (value: number) => `${value.toFixed(1)} ms`;
+        const counts = stats.diagnostics;
+        item.text = `$(pulse) ${ms(stats.totalMs)}`;
+        item.tooltip = [
+            `Rumoca analysis: ${ms(stats.totalMs)}`,
+            `Parse: ${ms(stats.parseMs)}, flatten: ${ms(stats.flattenMs)}, DAE: ${ms(stats.daeMs)}`,
+            `Classes: ${stats.classes} (${stats.compiledModels} compiled, ${stats.cachedModels} cached)`,
+            `Diagnostics: ${counts.errors} errors, ${counts.warnings} warnings, ` +
+                `${counts.information} information, ${counts.hints} hints`
+        ].join('\n');
+        item.show();
+    };
+
+    context.subscriptions.push(
+        languageClient.onNotification('rumoca/compileStats', (stats: CompileStats) => {
+            // The server sends URIs in its own (normalized) form
+            statsByUri.set(vscode.Uri.parse(stats.uri).toString(), stats);
+            update();
+        }),
+        vscode.window.onDidChangeActiveTextEditor(update),
+        vscode.workspace.onDidCloseTextDocument(document => {
+            statsByUri.delete(document.uri.toString());
+            update();
+        }),
+        vscode.workspace.onDidChangeConfiguration(event => {
+            if (event.affectsConfiguration('rumoca.compileStats')) {
+                update();
+            }
+        })
+    );
+}
+
 /**
  * Parse a Python cell to find %%modelica blocks and compile_source() calls
  */
@@ -895,7 +959,15 @@ export async function activate(context: vscode.ExtensionContext) {
         outputChannelName: 'Rumoca LSP',
         initializationOptions: {
             debug: debug,
-            modelicaPath: modelicaPath
+            modelicaPath: modelicaPath,
+            format: config.get('format'),
+            lint: config.get('lint'),
+            limits: config.get('limits'),
+            balanceLens: config.get<boolean>('balanceLens') ?? true
+        },
+        // Send workspace/didChangeConfiguration when rumoca.* settings change
+        synchronize: {
+            configurationSection: 'rumoca'
         }
     };
 
@@ -922,6 +994,8 @@ export async function activate(context: vscode.ExtensionContext) {
         return;
     }
 
+    registerCompileStatsStatusBar(context, client);
+
     // Create notebook controller for Modelica cells in Jupyter notebooks
     // This allows executing Modelica code and getting JSON output for Python interop
     const rumocaExecutable = serverPath.replace('-lsp', '');
@@ -21,6 +21,7 @@
 //! indent_size = 2
 //! use_tabs = false
 //! max_line_length = 100
+//! exponent_style = "lower"  # or "upper", "preserve" (default)
 //! ```
 //!
 //! CLI options override config file settings.
@@ -48,6 +49,9 @@
 //! # Use tabs for indentation
 //! rumoca-fmt --config use_tabs=true
 //!
+//! # Write exponents of real literals as 1.5e5 rather than 1.5E+05
+//! rumoca-fmt --config exponent_style=lower
+//!
 //! # Print to stdout instead of modifying files
 //! rumoca-fmt --emit stdout file.mo
 //!
@@ -57,6 +61,8 @@
 
 use anyhow::{Context, Result, bail};
 use clap::{Parser, ValueEnum};
+use rumoca::compiler::source::decode_source;
+use rumoca::fmt::ExponentStyle;
 use rumoca::{CONFIG_FILE_NAMES, FormatOptions, format_modelica};
 use std::fs;
 use std::io::{self, Read, Write};
@@ -229,6 +235,7 @@ struct CliConfigOptions {
     use_tabs: Option<bool>,
     max_line_length: Option<usize>,
     blank_lines_between_classes: Option<usize>,
+    exponent_style: Option<ExponentStyle>,
 }
 
 /// Parse configuration options from --config flag
@@ -238,6 +245,7 @@ fn parse_cli_config(config: &Option<String>) -> Result<CliConfigOptions> {
         use_tabs: None,
         max_line_length: None,
         blank_lines_between_classes: None,
+        exponent_style: None,
     };
 
     if let Some(config_str) = config {
@@ -279,8 +287,16 @@ fn parse_cli_config(config: &Option<String>) -> Result<CliConfigOptions> {
                             format!("Invalid blank_lines_between_classes value: {}", value)
                         })?);
                 }
+                "exponent_style" => {
+                    options.exponent_style = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .map_err(|e: String| anyhow::anyhow!(e))?,
+                    );
+                }
                 _ => bail!(
-                    "Unknown config option: {}. Available: indent_size, use_tabs, max_line_length, blank_lines_between_classes",
+                    "Unknown config option: {}. Available: indent_size, use_tabs, max_line_length, blank_lines_between_classes, exponent_style",
                     key
                 ),
             }
@@ -333,6 +349,9 @@ fn load_format_options(
         cli_config.max_line_length,
         cli_config.blank_lines_between_classes,
     );
+    if let Some(style) = cli_config.exponent_style {
+        options.exponent_style = style;
+    }
 
     options
 }
@@ -401,8 +420,15 @@ fn collect_mo_files(dir: &Path, files: &mut Vec<PathBuf>, recursive: bool) -> Re
 /// Process a file
 /// Returns Ok(true) if file was already formatted, Ok(false) if it needed formatting
 fn process_file(path: &PathBuf, options: &FormatOptions, args: &Args) -> Result<bool> {
-    let input = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let (input, fallback) = decode_source(bytes);
+    if fallback {
+        eprintln!(
+            "warning: {} is not valid UTF-8, decoding it as ISO-8859-1",
+            path.display()
+        );
+    }
 
     let formatted = format_modelica(&input, options);
     let already_formatted = formatted == input;
@@ -453,10 +479,14 @@ fn process_file(path: &PathBuf, options: &FormatOptions, args: &Args) -> Result<
 
 /// Process stdin
 fn process_stdin(options: &FormatOptions, args: &Args) -> Result<bool> {
-    let mut input = String::new();
+    let mut bytes = Vec::new();
     io::stdin()
-        .read_to_string(&mut input)
+        .read_to_end(&mut bytes)
         .context("Failed to read from stdin")?;
+    let (input, fallback) = decode_source(bytes);
+    if fallback {
+        eprintln!("warning: stdin is not valid UTF-8, decoding it as ISO-8859-1");
+    }
 
     let formatted = format_modelica(&input, options);
     let already_formatted = formatted == input;
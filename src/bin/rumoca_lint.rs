@@ -32,14 +32,25 @@
 //! # Output as JSON
 //! rumoca-lint --format json
 //!
+//! # Output as SARIF, e.g. for GitHub code scanning
+//! rumoca-lint --format sarif > rumoca-lint.sarif
+//!
+//! # Fail if there are more than 10 warnings
+//! rumoca-lint --max-warnings 10
+//!
 //! # List available rules
 //! rumoca-lint --list-rules
+//!
+//! # Apply the fixes of fixable issues in place, then report the rest
+//! rumoca-lint --fix
 //! ```
 
 use anyhow::{Context, Result};
 use clap::{Parser, ValueEnum};
 use rumoca::LINT_CONFIG_FILE_NAMES;
-use rumoca::lint::{LINT_RULES, LintConfig, LintLevel, LintResult, lint_file};
+use rumoca::lint::{
+    LINT_RULES, LintConfig, LintLevel, LintResult, apply_fixes, lint_file, lint_str, to_sarif,
+};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -48,6 +59,8 @@ enum OutputFormat {
     Text,
     Json,
     Compact,
+    /// SARIF 2.1.0, e.g. for GitHub code scanning
+    Sarif,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
@@ -113,9 +126,17 @@ struct Args {
     #[arg(long = "deny-warnings")]
     deny_warnings: bool,
 
+    /// Exit with error code if more than this many warnings are found
+    #[arg(long = "max-warnings", value_name = "N")]
+    max_warnings: Option<usize>,
+
     /// Recursively lint directories
     #[arg(long, default_value = "true")]
     recursive: bool,
+
+    /// Apply the fixes of fixable issues to the files, then report the remaining issues
+    #[arg(long)]
+    fix: bool,
 }
 
 fn main() -> Result<()> {
@@ -150,7 +171,11 @@ fn main() -> Result<()> {
 
     let mut all_results = Vec::new();
     for path in &files {
-        let result = lint_file(path, &config);
+        let result = if args.fix {
+            fix_file(path, &config, &args)?
+        } else {
+            lint_file(path, &config)
+        };
         all_results.push(result);
     }
 
@@ -158,18 +183,35 @@ fn main() -> Result<()> {
         OutputFormat::Text => output_text(&all_results, &args, &config),
         OutputFormat::Json => output_json(&all_results)?,
         OutputFormat::Compact => output_compact(&all_results, &config),
+        OutputFormat::Sarif => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&to_sarif(&all_results, &config))?
+            );
+        }
     }
 
-    let total_errors: usize = all_results
-        .iter()
-        .map(|r| r.count_by_level(LintLevel::Error))
-        .sum();
-    let total_warnings: usize = all_results
-        .iter()
-        .map(|r| r.count_by_level(LintLevel::Warning))
-        .sum();
+    // Only reported messages count, not those of disabled rules
+    let reported = |level: LintLevel| {
+        all_results
+            .iter()
+            .flat_map(|r| &r.messages)
+            .filter(|m| m.level == level && config.should_report(m))
+            .count()
+    };
+    let total_errors = reported(LintLevel::Error);
+    let total_warnings = reported(LintLevel::Warning);
+
+    let too_many_warnings = args.max_warnings.is_some_and(|max| total_warnings > max);
+    if too_many_warnings && !args.quiet {
+        eprintln!(
+            "Too many warnings: {} (at most {} allowed)",
+            total_warnings,
+            args.max_warnings.unwrap_or_default()
+        );
+    }
 
-    if total_errors > 0 || (config.deny_warnings && total_warnings > 0) {
+    if total_errors > 0 || (config.deny_warnings && total_warnings > 0) || too_many_warnings {
         std::process::exit(1);
     }
 
@@ -229,6 +271,31 @@ fn load_lint_config(start_dir: &Path, args: &Args) -> LintConfig {
     config
 }
 
+/// Apply the fixes of the reported issues of a file, and lint the fixed file
+fn fix_file(path: &Path, config: &LintConfig, args: &Args) -> Result<LintResult> {
+    // Files that are not valid UTF-8 are only linted, so their encoding is kept
+    let Ok(source) = fs::read_to_string(path) else {
+        return Ok(lint_file(path, config));
+    };
+    let file_path = path.to_string_lossy();
+    let result = lint_str(&source, &file_path, config);
+    let fixes = result
+        .messages
+        .iter()
+        .filter(|m| config.should_report(m))
+        .filter_map(|m| m.fix.as_ref());
+    let (fixed, applied) = apply_fixes(&source, fixes);
+    if applied == 0 {
+        return Ok(result);
+    }
+
+    fs::write(path, &fixed).with_context(|| format!("Failed to write {}", path.display()))?;
+    if !args.quiet {
+        eprintln!("Fixed {} issue(s) in {}", applied, path.display());
+    }
+    Ok(lint_str(&fixed, &file_path, config))
+}
+
 fn collect_files(args: &Args) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
     let paths = if args.files.is_empty() {
@@ -297,6 +364,9 @@ fn output_text(results: &[LintResult], args: &Args, config: &LintConfig) {
             if let Some(ref suggestion) = msg.suggestion {
                 println!("   = suggestion: {}", suggestion);
             }
+            if let Some(ref fix) = msg.fix {
+                println!("   = fix: {} (apply with --fix)", fix.description);
+            }
             println!();
         }
     }
@@ -327,6 +397,7 @@ fn output_json(results: &[LintResult]) -> Result<()> {
                     "line": m.line,
                     "column": m.column,
                     "suggestion": m.suggestion,
+                    "fix": m.fix,
                 })
             })
         })
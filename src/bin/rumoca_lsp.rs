@@ -20,39 +20,55 @@
 //! - Code actions (quick fixes)
 //! - Inlay hints
 //! - Multi-file workspace support
-//! - Code formatting
+//! - Code formatting (documents and ranges)
 //! - Code lenses
 //! - Call hierarchy
 //! - Document links
+//! - Analyze command (balance per component instance)
+//! - Workspace settings via initialization options and didChangeConfiguration
+//! - Persistent workspace index (instant symbols on startup, reconciled in the background)
+//! - Compile statistics after each analysis pass (`rumoca/compileStats` notifications)
 
 use crossbeam_channel::{Select, unbounded};
 use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::notification::Notification as NotificationTrait;
 use lsp_types::{
     CallHierarchyServerCapability, CodeActionProviderCapability, CodeLensOptions,
-    CompletionOptions, Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
-    DidOpenTextDocumentParams, DocumentLinkOptions, HoverProviderCapability, InitializeParams,
-    RenameOptions, SemanticTokensFullOptions, SemanticTokensOptions,
-    SemanticTokensServerCapabilities, ServerCapabilities, SignatureHelpOptions,
-    TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
-    notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Initialized},
+    CompletionOptions, Diagnostic, DidChangeConfigurationParams, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentLinkOptions,
+    ExecuteCommandOptions, HoverProviderCapability, InitializeParams, RenameOptions,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensServerCapabilities,
+    ServerCapabilities, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Uri,
+    notification::{
+        DidChangeConfiguration, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Initialized,
+    },
     request::{
         CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare,
         CodeActionRequest, CodeLensRequest, Completion, DocumentLinkRequest, DocumentSymbolRequest,
-        FoldingRangeRequest, Formatting, GotoDefinition, GotoTypeDefinition, HoverRequest,
-        PrepareRenameRequest, References, Rename, SemanticTokensFullRequest, SignatureHelpRequest,
-        WorkspaceSymbolRequest,
+        ExecuteCommand, FoldingRangeRequest, Formatting, GotoDefinition, GotoTypeDefinition,
+        HoverRequest, PrepareRenameRequest, RangeFormatting, References, Rename,
+        SemanticTokensFullRequest, SignatureHelpRequest, WorkspaceSymbolRequest,
     },
 };
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher, event::EventKind};
+use rumoca::compiler::paths::file_uri_to_path;
+use rumoca::lsp::analyze::{ANALYZE_COMMAND, handle_execute_command};
+use rumoca::lsp::evaluate::EVALUATE_COMMAND;
+use rumoca::lsp::index_cache::index_files;
+use rumoca::lsp::stats::CompileStatsNotification;
+use rumoca::lsp::utils::{
+    normalize_uris, positions_from_utf16, positions_to_utf16, request_document,
+};
 use rumoca::lsp::{
-    WorkspaceState, compute_diagnostics, get_semantic_token_legend, handle_code_action,
-    handle_code_lens, handle_completion_workspace, handle_document_links, handle_document_symbols,
-    handle_folding_range, handle_formatting, handle_goto_definition_workspace,
-    handle_hover_workspace, handle_incoming_calls, handle_outgoing_calls,
-    handle_prepare_call_hierarchy, handle_prepare_rename, handle_references,
-    handle_rename_workspace, handle_semantic_tokens, handle_signature_help, handle_type_definition,
-    handle_workspace_symbol,
+    LspSettings, WorkspaceState, compute_diagnostics, get_semantic_token_legend,
+    handle_code_action, handle_code_lens, handle_completion_workspace, handle_document_links,
+    handle_document_symbols, handle_folding_range, handle_formatting_with_settings,
+    handle_goto_definition_workspace, handle_hover_workspace, handle_incoming_calls,
+    handle_outgoing_calls, handle_prepare_call_hierarchy, handle_prepare_rename,
+    handle_range_formatting_with_settings, handle_references, handle_rename_workspace,
+    handle_semantic_tokens, handle_signature_help, handle_type_definition, handle_workspace_symbol,
 };
 use std::collections::HashMap;
 use std::error::Error;
@@ -72,6 +88,9 @@ struct PendingDiagnostic {
 /// Global debug flag, set from initialization options
 static DEBUG_MODE: AtomicBool = AtomicBool::new(false);
 
+/// Whether the client accepts `workspace/codeLens/refresh` requests
+static CODE_LENS_REFRESH: AtomicBool = AtomicBool::new(false);
+
 /// Check if debug mode is enabled
 fn is_debug() -> bool {
     DEBUG_MODE.load(Ordering::Relaxed)
@@ -107,7 +126,9 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     let (connection, io_threads) = Connection::stdio();
 
     let server_capabilities = serde_json::to_value(ServerCapabilities {
-        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
         completion_provider: Some(CompletionOptions {
             trigger_characters: Some(vec![".".to_string(), "(".to_string(), ",".to_string()]),
             resolve_provider: Some(false),
@@ -141,6 +162,7 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
         // Inlay hints disabled - can be distracting
         inlay_hint_provider: None,
         document_formatting_provider: Some(lsp_types::OneOf::Left(true)),
+        document_range_formatting_provider: Some(lsp_types::OneOf::Left(true)),
         code_lens_provider: Some(CodeLensOptions {
             resolve_provider: Some(false),
         }),
@@ -149,6 +171,10 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
             resolve_provider: Some(false),
             work_done_progress_options: Default::default(),
         }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![ANALYZE_COMMAND.to_string(), EVALUATE_COMMAND.to_string()],
+            work_done_progress_options: Default::default(),
+        }),
         ..Default::default()
     })?;
 
@@ -164,37 +190,31 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
 
     let init_params: InitializeParams = serde_json::from_value(init_params)?;
 
-    // Check for debug flag in initialization options
-    let mut extra_library_paths: Vec<PathBuf> = Vec::new();
-    let mut debug_enabled = false;
-    if let Some(options) = &init_params.initialization_options {
-        if let Some(debug) = options.get("debug").and_then(|v| v.as_bool()) {
-            debug_enabled = debug;
-            DEBUG_MODE.store(debug, Ordering::Relaxed);
-        }
-        // Extract modelicaPath from initialization options
-        if let Some(paths) = options.get("modelicaPath").and_then(|v| v.as_array()) {
-            for path in paths {
-                if let Some(path_str) = path.as_str() {
-                    extra_library_paths.push(PathBuf::from(path_str));
-                }
-            }
-        }
-    }
+    // Read workspace settings (debug flag, library paths, ...) from initialization options
+    let settings = init_params
+        .initialization_options
+        .as_ref()
+        .and_then(LspSettings::from_value)
+        .unwrap_or_default();
+    DEBUG_MODE.store(settings.debug, Ordering::Relaxed);
+    let debug_enabled = settings.debug;
+    let extra_library_paths = settings.modelica_path.clone();
+
+    let code_lens_refresh = init_params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|w| w.code_lens.as_ref())
+        .and_then(|c| c.refresh_support)
+        .unwrap_or(false);
+    CODE_LENS_REFRESH.store(code_lens_refresh, Ordering::Relaxed);
 
     // Extract workspace folders for multi-file support
     let workspace_folders: Vec<PathBuf> = init_params
         .workspace_folders
         .unwrap_or_default()
         .into_iter()
-        .filter_map(|folder| {
-            let uri_str = folder.uri.as_str();
-            if uri_str.starts_with("file://") {
-                Some(PathBuf::from(folder.uri.path().as_str()))
-            } else {
-                None
-            }
-        })
+        .filter_map(|folder| file_uri_to_path(folder.uri.as_str()).map(PathBuf::from))
         .collect();
 
     // Echo configuration at startup (always visible in Output panel)
@@ -225,7 +245,7 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     debug_log!("[rumoca-lsp] Server initialized (debug mode enabled)");
     debug_log!("[rumoca-lsp] Starting main_loop (will initialize workspace)...");
 
-    main_loop(connection, workspace_folders, extra_library_paths)?;
+    main_loop(connection, workspace_folders, settings)?;
     io_threads.join()?;
 
     eprintln!("Shutting down rumoca-lsp server");
@@ -235,15 +255,33 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
 fn main_loop(
     connection: Connection,
     workspace_folders: Vec<PathBuf>,
-    extra_library_paths: Vec<PathBuf>,
+    settings: LspSettings,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let extra_library_paths = settings.modelica_path.clone();
     // Create workspace state for multi-file support
     debug_log!("[rumoca-lsp] Creating WorkspaceState...");
     let mut workspace = WorkspaceState::new();
     workspace.set_debug(is_debug());
     debug_log!("[rumoca-lsp] Calling workspace.initialize() - this scans for Modelica packages...");
     let init_start = std::time::Instant::now();
-    workspace.initialize(workspace_folders.clone(), extra_library_paths.clone());
+    let restored =
+        workspace.initialize_from_index(workspace_folders.clone(), extra_library_paths.clone());
+
+    // With a persisted index, symbols are available right away and the files are
+    // re-parsed in the background; otherwise index everything before serving requests
+    let mut index_rx = None;
+    let files = workspace.files_to_index();
+    if restored > 0 {
+        let (index_tx, rx) = unbounded();
+        std::thread::spawn(move || {
+            let _ = index_tx.send(index_files(&files));
+        });
+        index_rx = Some(rx);
+        eprintln!("Restored {} files from persisted index", restored);
+    } else {
+        workspace.apply_indexed_files(index_files(&files));
+    }
+    workspace.set_settings(settings);
     let init_elapsed = init_start.elapsed();
 
     // Report what was discovered (always visible)
@@ -290,41 +328,76 @@ fn main_loop(
             .min()
             .unwrap_or(Duration::from_secs(60)); // Long timeout if no pending
 
-        let mut sel = Select::new();
-        sel.recv(&connection.receiver);
-        sel.recv(&fs_rx);
-
-        let oper = sel.select_timeout(timeout);
-        match oper {
-            Ok(oper) => match oper.index() {
-                // LSP message received
-                0 => {
-                    let msg = match oper.recv(&connection.receiver) {
-                        Ok(msg) => msg,
-                        Err(_) => return Ok(()), // Channel closed, shutdown
-                    };
-                    if handle_lsp_message_debounced(
-                        &connection,
-                        &mut workspace,
-                        msg,
-                        &mut pending_diagnostics,
-                    )? {
-                        return Ok(()); // Shutdown requested
+        let mut index_finished = false;
+        {
+            let mut sel = Select::new();
+            sel.recv(&connection.receiver);
+            sel.recv(&fs_rx);
+            if let Some(rx) = &index_rx {
+                sel.recv(rx);
+            }
+
+            let oper = sel.select_timeout(timeout);
+            match oper {
+                Ok(oper) => match oper.index() {
+                    // LSP message received
+                    0 => {
+                        let mut msg = match oper.recv(&connection.receiver) {
+                            Ok(msg) => msg,
+                            Err(_) => {
+                                // Channel closed, shutdown
+                                workspace.save_index();
+                                return Ok(());
+                            }
+                        };
+                        // A file has a single URI, whatever form the client uses
+                        match &mut msg {
+                            Message::Request(req) => normalize_uris(&mut req.params),
+                            Message::Notification(notif) => normalize_uris(&mut notif.params),
+                            Message::Response(_) => {}
+                        }
+                        if handle_lsp_message_debounced(
+                            &connection,
+                            &mut workspace,
+                            msg,
+                            &mut pending_diagnostics,
+                        )? {
+                            workspace.save_index();
+                            return Ok(()); // Shutdown requested
+                        }
                     }
-                }
-                // File system event received
-                1 => {
-                    if let Ok(event) = oper.recv(&fs_rx) {
-                        handle_file_event(&mut workspace, event);
+                    // File system event received
+                    1 => {
+                        if let Ok(event) = oper.recv(&fs_rx) {
+                            handle_file_event(&mut workspace, event);
+                        }
+                    }
+                    // Background indexing finished - reconcile the restored index
+                    2 => {
+                        index_finished = true;
+                        if let Some(Ok(files)) = index_rx.as_ref().map(|rx| oper.recv(rx)) {
+                            let start = Instant::now();
+                            workspace.apply_indexed_files(files);
+                            eprintln!(
+                                "Background indexing complete in {:?} - {} packages indexed",
+                                start.elapsed(),
+                                workspace.symbol_count()
+                            );
+                        }
                     }
+                    _ => {}
+                },
+                Err(_) => {
+                    // Timeout - check for ready diagnostics
                 }
-                _ => {}
-            },
-            Err(_) => {
-                // Timeout - check for ready diagnostics
             }
         }
 
+        // The index sender is dropped after one message, so stop selecting on it
+        if index_finished {
+            index_rx = None;
+        }
+
         // Process any ready diagnostics
         let now = Instant::now();
         let ready_uris: Vec<Uri> = pending_diagnostics
@@ -336,7 +409,7 @@ fn main_loop(
         for uri in ready_uris {
             if let Some(pending) = pending_diagnostics.remove(&uri) {
                 let diagnostics = compute_diagnostics(&uri, &pending.text, &mut workspace);
-                if let Err(e) = publish_diagnostics(&connection, uri, diagnostics) {
+                if let Err(e) = publish_analysis(&connection, &workspace, uri, diagnostics) {
                     debug_log!("[rumoca-lsp] Failed to publish diagnostics: {}", e);
                 }
             }
@@ -421,16 +494,20 @@ fn handle_lsp_message(
     msg: Message,
 ) -> Result<bool, Box<dyn Error + Sync + Send>> {
     match msg {
-        Message::Request(req) => {
+        Message::Request(mut req) => {
             if connection.handle_shutdown(&req)? {
                 return Ok(true); // Shutdown requested
             }
 
+            // Positions are UTF-16 based on the wire and character based in the handlers
+            let uri = request_document(&req.params);
+            positions_from_utf16(&mut req.params, workspace.documents());
+
             let req = match cast_request::<GotoDefinition>(req) {
                 Ok((id, params)) => {
                     let result = handle_goto_definition_workspace(workspace, params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -444,7 +521,7 @@ fn handle_lsp_message(
                         params.text_document_position_params,
                     );
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -455,7 +532,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_completion_workspace(workspace, params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -466,7 +543,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_signature_help(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -477,7 +554,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_hover_workspace(workspace, params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -488,7 +565,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_document_symbols(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -499,7 +576,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_references(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -510,7 +587,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_semantic_tokens(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -521,7 +598,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_workspace_symbol(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -532,7 +609,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_prepare_rename(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -543,7 +620,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_rename_workspace(workspace, params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -554,7 +631,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_folding_range(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -565,7 +642,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_code_action(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -574,9 +651,28 @@ fn handle_lsp_message(
 
             let req = match cast_request::<Formatting>(req) {
                 Ok((id, params)) => {
-                    let result = handle_formatting(workspace.documents(), params);
+                    let result = handle_formatting_with_settings(
+                        workspace.documents(),
+                        params,
+                        workspace.settings(),
+                    );
+                    let resp = Response::new_ok(id, result);
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
+                    return Ok(false);
+                }
+                Err(ExtractError::JsonError { .. }) => return Ok(false),
+                Err(ExtractError::MethodMismatch(req)) => req,
+            };
+
+            let req = match cast_request::<RangeFormatting>(req) {
+                Ok((id, params)) => {
+                    let result = handle_range_formatting_with_settings(
+                        workspace.documents(),
+                        params,
+                        workspace.settings(),
+                    );
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -587,7 +683,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_code_lens(workspace, params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -598,7 +694,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_prepare_call_hierarchy(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -609,7 +705,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_incoming_calls(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -620,7 +716,18 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_outgoing_calls(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
+                    return Ok(false);
+                }
+                Err(ExtractError::JsonError { .. }) => return Ok(false),
+                Err(ExtractError::MethodMismatch(req)) => req,
+            };
+
+            let req = match cast_request::<ExecuteCommand>(req) {
+                Ok((id, params)) => {
+                    let result = handle_execute_command(workspace, params);
+                    let resp = Response::new_ok(id, result);
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -631,7 +738,7 @@ fn handle_lsp_message(
                 Ok((id, params)) => {
                     let result = handle_document_links(workspace.documents(), params);
                     let resp = Response::new_ok(id, result);
-                    connection.sender.send(Message::Response(resp))?;
+                    send_response(connection, workspace.documents(), uri.as_ref(), resp)?;
                 }
                 Err(ExtractError::JsonError { .. }) => {}
                 Err(ExtractError::MethodMismatch(_req)) => {
@@ -652,6 +759,15 @@ fn handle_lsp_message(
                 Err(ExtractError::MethodMismatch(notif)) => notif,
             };
 
+            let notif = match cast_notification::<DidChangeConfiguration>(notif) {
+                Ok(params) => {
+                    handle_did_change_configuration(connection, workspace, params)?;
+                    return Ok(false);
+                }
+                Err(ExtractError::JsonError { .. }) => return Ok(false),
+                Err(ExtractError::MethodMismatch(notif)) => notif,
+            };
+
             let notif = match cast_notification::<DidOpenTextDocument>(notif) {
                 Ok(params) => {
                     handle_did_open(connection, workspace, params)?;
@@ -694,14 +810,31 @@ fn handle_lsp_message_debounced(
 ) -> Result<bool, Box<dyn Error + Sync + Send>> {
     match msg {
         Message::Notification(notif) => {
-            // Handle DidOpen - compute diagnostics immediately (first open)
+            // Handle DidOpen - compute diagnostics immediately (first open), or publish
+            // persisted diagnostics of unchanged content and refresh them debounced
             let notif = match cast_notification::<DidOpenTextDocument>(notif) {
                 Ok(params) => {
                     let uri = params.text_document.uri.clone();
                     let text = params.text_document.text.clone();
                     workspace.open_document(uri.clone(), text.clone());
-                    let diagnostics = compute_diagnostics(&uri, &text, workspace);
-                    publish_diagnostics(connection, uri, diagnostics)?;
+                    if let Some(diagnostics) = workspace.cached_diagnostics(&uri, &text) {
+                        publish_diagnostics(
+                            connection,
+                            workspace.documents(),
+                            uri.clone(),
+                            diagnostics,
+                        )?;
+                        pending_diagnostics.insert(
+                            uri,
+                            PendingDiagnostic {
+                                text,
+                                changed_at: Instant::now(),
+                            },
+                        );
+                    } else {
+                        let diagnostics = compute_diagnostics(&uri, &text, workspace);
+                        publish_analysis(connection, workspace, uri, diagnostics)?;
+                    }
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -712,18 +845,15 @@ fn handle_lsp_message_debounced(
             let notif = match cast_notification::<DidChangeTextDocument>(notif) {
                 Ok(params) => {
                     let uri = params.text_document.uri.clone();
-                    if let Some(change) = params.content_changes.into_iter().next() {
-                        let text = change.text;
-                        workspace.update_document(uri.clone(), text.clone());
-                        // Queue for debounced diagnostics
-                        pending_diagnostics.insert(
-                            uri,
-                            PendingDiagnostic {
-                                text,
-                                changed_at: Instant::now(),
-                            },
-                        );
-                    }
+                    let text = workspace.change_document(uri.clone(), params.content_changes);
+                    // Queue for debounced diagnostics
+                    pending_diagnostics.insert(
+                        uri,
+                        PendingDiagnostic {
+                            text,
+                            changed_at: Instant::now(),
+                        },
+                    );
                     return Ok(false);
                 }
                 Err(ExtractError::JsonError { .. }) => return Ok(false),
@@ -777,7 +907,7 @@ fn handle_did_open(
     workspace.open_document(uri.clone(), text.clone());
 
     let diagnostics = compute_diagnostics(&uri, &text, workspace);
-    publish_diagnostics(connection, uri, diagnostics)?;
+    publish_analysis(connection, workspace, uri, diagnostics)?;
 
     Ok(())
 }
@@ -789,14 +919,54 @@ fn handle_did_change(
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
     let uri = params.text_document.uri.clone();
 
-    if let Some(change) = params.content_changes.into_iter().next() {
-        let text = change.text;
+    // Update document in workspace (this also re-parses and re-indexes the file)
+    let text = workspace.change_document(uri.clone(), params.content_changes);
+
+    let diagnostics = compute_diagnostics(&uri, &text, workspace);
+    publish_analysis(connection, workspace, uri, diagnostics)?;
 
-        // Update document in workspace (this also re-parses and re-indexes the file)
-        workspace.update_document(uri.clone(), text.clone());
+    Ok(())
+}
 
+/// Apply new workspace settings and refresh diagnostics of open documents
+fn handle_did_change_configuration(
+    connection: &Connection,
+    workspace: &mut WorkspaceState,
+    params: DidChangeConfigurationParams,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let Some(settings) = LspSettings::from_value(&params.settings) else {
+        debug_log!("[rumoca-lsp] Ignoring unrecognized configuration payload");
+        return Ok(());
+    };
+
+    DEBUG_MODE.store(settings.debug, Ordering::Relaxed);
+    if workspace.set_settings(settings) {
+        eprintln!(
+            "Library paths changed - {} files, {} packages indexed",
+            workspace.discovered_files().len(),
+            workspace.symbol_count()
+        );
+    }
+
+    let open: Vec<(Uri, String)> = workspace
+        .documents()
+        .iter()
+        .map(|(uri, text)| (uri.clone(), text.clone()))
+        .collect();
+    for (uri, text) in open {
         let diagnostics = compute_diagnostics(&uri, &text, workspace);
-        publish_diagnostics(connection, uri, diagnostics)?;
+        publish_analysis(connection, workspace, uri, diagnostics)?;
+    }
+
+    // Balance lenses depend on the settings, so ask the client to re-request them
+    if CODE_LENS_REFRESH.load(Ordering::Relaxed) {
+        let refresh = Request::new(
+            RequestId::from("rumoca/codeLensRefresh".to_string()),
+            <lsp_types::request::CodeLensRefresh as lsp_types::request::Request>::METHOD
+                .to_string(),
+            (),
+        );
+        connection.sender.send(Message::Request(refresh))?;
     }
 
     Ok(())
@@ -806,8 +976,42 @@ fn handle_did_close(workspace: &mut WorkspaceState, params: DidCloseTextDocument
     workspace.close_document(&params.text_document.uri);
 }
 
+/// Send a response, converting its positions to UTF-16.
+///
+/// `uri` is the document of the request being answered.
+fn send_response(
+    connection: &Connection,
+    documents: &HashMap<Uri, String>,
+    uri: Option<&Uri>,
+    mut resp: Response,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    if let Some(result) = resp.result.as_mut() {
+        positions_to_utf16(result, documents, uri);
+    }
+    connection.sender.send(Message::Response(resp))?;
+    Ok(())
+}
+
+/// Publish the diagnostics computed for a document, followed by the
+/// statistics of the pass (`rumoca/compileStats`)
+fn publish_analysis(
+    connection: &Connection,
+    workspace: &WorkspaceState,
+    uri: Uri,
+    diagnostics: Vec<Diagnostic>,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let stats = workspace.compile_stats(&uri).cloned();
+    publish_diagnostics(connection, workspace.documents(), uri, diagnostics)?;
+    if let Some(stats) = stats {
+        let notif = Notification::new(CompileStatsNotification::METHOD.to_string(), stats);
+        connection.sender.send(Message::Notification(notif))?;
+    }
+    Ok(())
+}
+
 fn publish_diagnostics(
     connection: &Connection,
+    documents: &HashMap<Uri, String>,
     uri: Uri,
     diagnostics: Vec<Diagnostic>,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
@@ -816,10 +1020,11 @@ fn publish_diagnostics(
         diagnostics,
         version: None,
     };
-    let notif = Notification::new(
+    let mut notif = Notification::new(
         <lsp_types::notification::PublishDiagnostics as NotificationTrait>::METHOD.to_string(),
         params,
     );
+    positions_to_utf16(&mut notif.params, documents, None);
     connection.sender.send(Message::Notification(notif))?;
     Ok(())
 }
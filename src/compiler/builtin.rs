@@ -0,0 +1,183 @@
+//! Libraries shipped with rumoca.
+//!
+//! Library paths of the form `builtin:<name>`, e.g. `--lib builtin:msl-mini`,
+//! refer to libraries embedded in rumoca. On first use they are written to a
+//! versioned directory under the system temp directory, so they are found on
+//! the library path like any other library directory.
+//!
+//! - `msl-mini`: a curated subset of the Modelica Standard Library 4 (SI units,
+//!   basic blocks, analog electrical and 1D rotational mechanical components,
+//!   with examples), so examples, tests and new users can compile connected
+//!   models without downloading the full library
+
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+
+/// Prefix of library paths referring to a builtin library
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+/// A library embedded in rumoca
+struct BuiltinLibrary {
+    name: &'static str,
+    /// Files of the library: path relative to the library directory, and source
+    files: &'static [(&'static str, &'static str)],
+}
+
+const BUILTIN_LIBRARIES: &[BuiltinLibrary] = &[BuiltinLibrary {
+    name: "msl-mini",
+    files: &[(
+        "Modelica/package.mo",
+        include_str!("../../libraries/msl-mini/Modelica/package.mo"),
+    )],
+}];
+
+/// Names of the builtin libraries
+pub fn builtin_library_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN_LIBRARIES.iter().map(|library| library.name)
+}
+
+/// Resolve a library path: `builtin:<name>` to the directory of the builtin
+/// library, any other path to itself
+pub fn resolve_library_path(path: &str) -> Result<PathBuf> {
+    match path.strip_prefix(BUILTIN_PREFIX) {
+        Some(name) => builtin_library_path(name),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+/// The directory of a builtin library, written on first use
+#[cfg(not(target_arch = "wasm32"))]
+pub fn builtin_library_path(name: &str) -> Result<PathBuf> {
+    let library = BUILTIN_LIBRARIES
+        .iter()
+        .find(|library| library.name == name)
+        .ok_or_else(|| {
+            Error::Package(format!(
+                "Unknown builtin library '{}' (available: {})",
+                name,
+                builtin_library_names().collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+
+    let dir = std::env::temp_dir()
+        .join(format!("rumoca-{}", env!("CARGO_PKG_VERSION")))
+        .join("builtin")
+        .join(library.name);
+    for (relative, source) in library.files {
+        let path = dir.join(relative);
+        if std::fs::read_to_string(&path).is_ok_and(|existing| existing == *source) {
+            continue;
+        }
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Write to a temporary file first, so concurrent compilations
+            // never read a partially written file
+            let temp = path.with_extension(format!("mo.{}.tmp", std::process::id()));
+            std::fs::write(&temp, source)?;
+            std::fs::rename(&temp, &path)
+        };
+        write()
+            .map_err(|e| Error::Package(format!("Failed to write {}: {}", path.display(), e)))?;
+    }
+    Ok(dir)
+}
+
+/// Builtin libraries need a filesystem
+#[cfg(target_arch = "wasm32")]
+pub fn builtin_library_path(name: &str) -> Result<PathBuf> {
+    Err(Error::Package(format!(
+        "Builtin library '{}' is not available in WebAssembly",
+        name
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    #[test]
+    fn test_resolve_library_path() {
+        let dir = resolve_library_path("builtin:msl-mini").unwrap();
+        assert!(dir.join("Modelica").join("package.mo").is_file());
+        // Resolving again reuses the written files
+        assert_eq!(resolve_library_path("builtin:msl-mini").unwrap(), dir);
+
+        assert_eq!(
+            resolve_library_path("/opt/libs").unwrap(),
+            PathBuf::from("/opt/libs")
+        );
+        let err = resolve_library_path("builtin:msl-full").unwrap_err();
+        assert!(err.to_string().contains("available: msl-mini"), "{}", err);
+    }
+
+    /// Compile a model of a source with the builtin MSL subset on the library path
+    fn compile_with_msl_mini(model: &str, source: &str) -> crate::CompilationResult {
+        Compiler::new()
+            .model(model)
+            .modelica_path(&["builtin:msl-mini"])
+            .include_from_modelica_path("Modelica")
+            .unwrap()
+            .compile_str(source, "test.mo")
+            .unwrap_or_else(|e| panic!("{}: {}", model, e))
+    }
+
+    #[test]
+    fn test_msl_mini_constants_match_builtins() {
+        use crate::ir::analysis::division_check::evaluate;
+        use crate::ir::transform::constants::get_modelica_constant;
+
+        let (_, source) = BUILTIN_LIBRARIES[0].files[0];
+        let definition = crate::compiler::parse_source(source, "package.mo").unwrap();
+        let constants = &definition.class_list["Modelica"].classes["Constants"];
+        for (name, comp) in &constants.components {
+            let declared = evaluate(&comp.start, &Default::default())
+                .unwrap_or_else(|| panic!("{} = {}", name, comp.start));
+            let builtin = get_modelica_constant(name).unwrap();
+            assert!(
+                (declared - builtin).abs() <= f64::EPSILON * builtin.abs(),
+                "Modelica.Constants.{}: {} in msl-mini, {} built in",
+                name,
+                declared,
+                builtin
+            );
+        }
+    }
+
+    #[test]
+    fn test_msl_mini_models_are_balanced() {
+        for model in [
+            "Modelica.Electrical.Analog.Examples.RCCircuit",
+            "Modelica.Mechanics.Rotational.Examples.TwoInertias",
+        ] {
+            let result = compile_with_msl_mini(model, "model Empty\nend Empty;\n");
+            let balance = result.dae.check_balance();
+            assert!(
+                balance.is_balanced,
+                "{}: {}",
+                model,
+                balance.status_message()
+            );
+        }
+
+        // A control loop of blocks in a user model
+        let source = r#"
+model Loop
+  Modelica.Blocks.Sources.Step step(startTime = 0.5);
+  Modelica.Blocks.Math.Feedback feedback;
+  Modelica.Blocks.Continuous.PI controller(k = 2, T = 0.5);
+  Modelica.Blocks.Continuous.FirstOrder plant(T = 0.2);
+equation
+  connect(step.y, feedback.u1);
+  connect(plant.y, feedback.u2);
+  connect(feedback.y, controller.u);
+  connect(controller.y, plant.u);
+end Loop;
+"#;
+        let balance = compile_with_msl_mini("Loop", source).dae.check_balance();
+        assert!(balance.is_balanced, "{}", balance.status_message());
+    }
+}
@@ -26,7 +26,7 @@ mod disk {
     use std::path::PathBuf;
 
     /// Cache format version - increment when cache file format or AST structure changes
-    const CACHE_VERSION: u32 = 1;
+    const CACHE_VERSION: u32 = 5;
 
     /// Rumoca version at compile time - used for automatic cache invalidation
     const RUMOCA_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,296 @@
+//! Lightweight queries of the classes of a source, for tools browsing models.
+//!
+//! [`Compiler::list_classes`] lists the classes of a source, nested ones
+//! included, and [`Compiler::class_info`] describes a single class: its kind,
+//! declared components and parameters, the classes it extends and, for
+//! models and blocks, its balance. Both only parse the source: nothing is rendered
+//! and no DAE is created, except by the cached balance check of
+//! [`Compiler::check_balance`].
+//!
+//! ```
+//! use rumoca::Compiler;
+//! use rumoca::ir::ast::ClassType;
+//!
+//! let source = "package P\n  model M \"A model\"\n    parameter Real k = 2;\n    Real x;\n  equation\n    der(x) = -k * x;\n  end M;\nend P;\n";
+//! let compiler = Compiler::new();
+//! let classes = compiler.list_classes(source, "p.mo")?;
+//! assert_eq!(classes[1].name, "P.M");
+//! assert_eq!(classes[1].class_type, ClassType::Model);
+//!
+//! let info = compiler.class_info(source, "p.mo", "P.M")?;
+//! assert_eq!(info.summary.description, "A model");
+//! assert_eq!(info.parameters().next().unwrap().name, "k");
+//! assert!(info.balance.unwrap().is_balanced);
+//! # Ok::<(), rumoca::Error>(())
+//! ```
+
+use crate::Compiler;
+use crate::compiler::explain::declared_start;
+use crate::compiler::limits::Limits;
+use crate::compiler::pipeline::check_balance_only;
+use crate::dae::balance::BalanceResult;
+use crate::error::{Error, Result};
+use crate::ir::ast::{
+    Causality, ClassDefinition, ClassType, Component, Location, StoredDefinition, Token,
+    Variability,
+};
+
+/// A class of a source, as listed by [`Compiler::list_classes`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSummary {
+    /// Full name of the class, e.g. `P.M`, including the `within` prefix
+    pub name: String,
+    pub class_type: ClassType,
+    pub partial: bool,
+    /// Description string, without quotes
+    pub description: String,
+    pub location: Location,
+}
+
+/// Metadata of a class, as given by [`Compiler::class_info`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassInfo {
+    pub summary: ClassSummary,
+    /// Names of the classes it extends, as written
+    pub extends: Vec<String>,
+    /// Components declared by the class itself, in declaration order
+    pub components: Vec<ComponentInfo>,
+    /// Short names of the nested classes
+    pub classes: Vec<String>,
+    /// Balance of non-partial models, blocks and classes, unless they fail to
+    /// compile
+    pub balance: Option<BalanceResult>,
+}
+
+/// A component declared by a class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentInfo {
+    pub name: String,
+    pub type_name: String,
+    /// `constant`, `parameter`, `discrete` or `continuous`
+    pub variability: &'static str,
+    /// `input` or `output`, if declared
+    pub causality: Option<&'static str>,
+    /// Array dimensions, empty for scalars
+    pub shape: Vec<usize>,
+    /// Binding or start value, if one is given
+    pub start: Option<String>,
+    /// Description string, without quotes
+    pub description: String,
+    pub location: Location,
+}
+
+impl ClassInfo {
+    /// The parameters and constants of the class
+    pub fn parameters(&self) -> impl Iterator<Item = &ComponentInfo> {
+        self.components
+            .iter()
+            .filter(|c| matches!(c.variability, "parameter" | "constant"))
+    }
+}
+
+impl Compiler {
+    /// List the classes of a source, nested ones included, parents before
+    /// their children, see the [module docs](crate::compiler::class_info)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source fails to parse.
+    pub fn list_classes(&self, source: &str, file_name: &str) -> Result<Vec<ClassSummary>> {
+        let def = self.parse_source(source, file_name)?;
+        let mut classes = Vec::new();
+        for class in def.class_list.values() {
+            collect_classes(class, &within_prefix(&def), &mut classes);
+        }
+        Ok(classes)
+    }
+
+    /// Describe a class of a source by its full name, see the
+    /// [module docs](crate::compiler::class_info)
+    ///
+    /// Included files are parsed as well, for the balance of classes using
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source or an included file fails to parse, or
+    /// if the source has no class of this name.
+    pub fn class_info(&self, source: &str, file_name: &str, path: &str) -> Result<ClassInfo> {
+        let (all_definitions, _, _) = self.parse_with_includes(source, file_name)?;
+        let def = self.merge_definitions(all_definitions)?;
+        let prefix = within_prefix(&def);
+        let local_path = path.strip_prefix(&prefix).unwrap_or(path);
+        let class = find_class(&def, local_path).ok_or_else(|| {
+            Error::Flatten(format!("Class '{}' not found in {}", path, file_name))
+        })?;
+        let parent = match local_path.rsplit_once('.') {
+            Some((parent, _)) => format!("{}{}.", prefix, parent),
+            None => prefix,
+        };
+
+        let balance = (matches!(
+            class.class_type,
+            ClassType::Model | ClassType::Block | ClassType::Class
+        ) && !class.partial)
+            .then(|| check_balance_only(&def, Some(local_path), &Limits::default()).ok())
+            .flatten();
+        Ok(ClassInfo {
+            summary: summary(class, &parent),
+            extends: class.extends.iter().map(|e| e.comp.to_string()).collect(),
+            components: class.components.values().map(component_info).collect(),
+            classes: class.classes.keys().cloned().collect(),
+            balance,
+        })
+    }
+}
+
+/// The prefix of the full names of the classes of a definition, e.g. `Lib.`
+/// for `within Lib;`
+fn within_prefix(def: &StoredDefinition) -> String {
+    match &def.within {
+        Some(within) if !within.name.is_empty() => format!("{}.", within),
+        _ => String::new(),
+    }
+}
+
+fn find_class<'a>(def: &'a StoredDefinition, path: &str) -> Option<&'a ClassDefinition> {
+    let mut parts = path.split('.');
+    let mut class = def.class_list.get(parts.next()?)?;
+    for part in parts {
+        class = class.classes.get(part)?;
+    }
+    Some(class)
+}
+
+fn collect_classes(class: &ClassDefinition, prefix: &str, classes: &mut Vec<ClassSummary>) {
+    let class_summary = summary(class, prefix);
+    let nested_prefix = format!("{}.", class_summary.name);
+    classes.push(class_summary);
+    for nested in class.classes.values() {
+        collect_classes(nested, &nested_prefix, classes);
+    }
+}
+
+fn summary(class: &ClassDefinition, prefix: &str) -> ClassSummary {
+    ClassSummary {
+        name: format!("{}{}", prefix, class.name.text),
+        class_type: class.class_type.clone(),
+        partial: class.partial,
+        description: description(&class.description),
+        location: class.location.clone(),
+    }
+}
+
+fn component_info(comp: &Component) -> ComponentInfo {
+    ComponentInfo {
+        name: comp.name.clone(),
+        type_name: comp.type_name.to_string(),
+        variability: match comp.variability {
+            Variability::Constant(_) => "constant",
+            Variability::Parameter(_) => "parameter",
+            Variability::Discrete(_) => "discrete",
+            Variability::Empty => "continuous",
+        },
+        causality: match comp.causality {
+            Causality::Input(_) => Some("input"),
+            Causality::Output(_) => Some("output"),
+            Causality::Empty => None,
+        },
+        shape: comp.shape.clone(),
+        start: declared_start(comp),
+        description: description(&comp.description),
+        location: comp.location.clone(),
+    }
+}
+
+/// The text of description strings, without quotes
+fn description(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| t.text.trim_matches('"'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+    use crate::ir::ast::ClassType;
+
+    #[test]
+    fn test_class_info() {
+        let source = r#"within Lib;
+package Sys "Systems"
+  partial model Base
+    Real y;
+  end Base;
+  model Plant "A plant"
+    extends Base;
+    parameter Real k(start = 1) "Gain";
+    constant Integer n = 2;
+    input Real u;
+    Real x[n](each start = 0);
+  equation
+    der(x) = -k * x;
+    y = x[1] + u;
+  end Plant;
+  function f
+    input Real a;
+    output Real b;
+  algorithm
+    b := a;
+  end f;
+end Sys;
+"#;
+        let compiler = Compiler::new();
+        let classes = compiler.list_classes(source, "sys.mo").unwrap();
+        let names: Vec<(&str, &ClassType)> = classes
+            .iter()
+            .map(|c| (c.name.as_str(), &c.class_type))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Lib.Sys", &ClassType::Package),
+                ("Lib.Sys.Base", &ClassType::Model),
+                ("Lib.Sys.Plant", &ClassType::Model),
+                ("Lib.Sys.f", &ClassType::Function),
+            ]
+        );
+        assert!(classes[1].partial);
+        assert_eq!(classes[0].description, "Systems");
+
+        let plant = compiler
+            .class_info(source, "sys.mo", "Lib.Sys.Plant")
+            .unwrap();
+        assert_eq!(plant.summary.name, "Lib.Sys.Plant");
+        assert_eq!(plant.summary.location.file_position(), "sys.mo:6:9");
+        assert_eq!(plant.extends, ["Base"]);
+        let parameters: Vec<(&str, &str, Option<&str>)> = plant
+            .parameters()
+            .map(|p| (p.name.as_str(), p.variability, p.start.as_deref()))
+            .collect();
+        assert_eq!(
+            parameters,
+            [("k", "parameter", Some("1")), ("n", "constant", Some("2"))]
+        );
+        assert_eq!(plant.components[0].description, "Gain");
+        assert_eq!(plant.components[2].causality, Some("input"));
+        assert_eq!(plant.components[3].type_name, "Real");
+        assert!(plant.balance.unwrap().is_balanced);
+
+        // Partial models and functions have no balance
+        let base = compiler
+            .class_info(source, "sys.mo", "Lib.Sys.Base")
+            .unwrap();
+        assert!(base.balance.is_none());
+        let sys = compiler.class_info(source, "sys.mo", "Lib.Sys").unwrap();
+        assert_eq!(sys.classes, ["Base", "Plant", "f"]);
+        assert!(sys.balance.is_none());
+
+        let err = compiler
+            .class_info(source, "sys.mo", "Lib.Sys.Nope")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Class 'Lib.Sys.Nope' not found in sys.mo");
+    }
+}
@@ -0,0 +1,260 @@
+//! Relaxations of the Modelica specification, and the strict mode refusing
+//! them.
+//!
+//! By default rumoca accepts a few constructs the specification doesn't, to
+//! compile models written for it or for other tools. Each is a
+//! [`Relaxation`], recorded in
+//! [`CompilationResult::relaxations`](super::CompilationResult::relaxations) when a
+//! model uses it. With [`Compiler::strict`](crate::Compiler::strict)
+//! (`--strict`) the compilation fails with an [`Error::Strict`] listing them
+//! instead, and `rumoca --explain-relaxations` lists what each relaxation
+//! accepts and what the specification requires:
+//!
+//! ```
+//! use rumoca::{Compiler, Error};
+//!
+//! let source = "model M\n  parameter Real k;\n  Real x;\nequation\n  der(x) = -k * x;\nend M;";
+//! let result = Compiler::new().model("M").compile_str(source, "M.mo").unwrap();
+//! assert_eq!(result.relaxations.len(), 1);
+//!
+//! let err = Compiler::new()
+//!     .model("M")
+//!     .strict(true)
+//!     .compile_str(source, "M.mo")
+//!     .unwrap_err();
+//! assert!(matches!(err, Error::Strict(_)));
+//! ```
+//!
+//! [`Error::Strict`]: crate::Error::Strict
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ir::ast::{ClassDefinition, Expression, Variability};
+use crate::ir::transform::constants::TYPE_BOOL;
+
+/// A construct accepted outside of the Modelica specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Relaxation {
+    /// A parameter or constant without a value gets the default start value
+    DefaultStart,
+    /// The `shape` modifier declares the dimensions of an array
+    ShapeModifier,
+    /// `Bool` is a synonym of `Boolean`
+    BoolType,
+}
+
+impl Relaxation {
+    /// All relaxations
+    pub const ALL: &[Relaxation] = &[
+        Relaxation::DefaultStart,
+        Relaxation::ShapeModifier,
+        Relaxation::BoolType,
+    ];
+
+    /// Name of the relaxation, e.g. `shape-modifier`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relaxation::DefaultStart => "default-start",
+            Relaxation::ShapeModifier => "shape-modifier",
+            Relaxation::BoolType => "bool-type",
+        }
+    }
+
+    /// What rumoca accepts by default
+    pub fn relaxed(&self) -> &'static str {
+        match self {
+            Relaxation::DefaultStart => {
+                "A parameter or constant declared without a binding or start value, e.g. \
+                 `parameter Real k;`, has the default start value of its type (0 for Real \
+                 and Integer) as its value."
+            }
+            Relaxation::ShapeModifier => {
+                "A `shape` modifier declares the dimensions of an array, e.g. \
+                 `Real x(shape = 3)` for `Real x[3]`."
+            }
+            Relaxation::BoolType => "`Bool` is accepted as a synonym of the type `Boolean`.",
+        }
+    }
+
+    /// What the Modelica specification requires
+    pub fn specification(&self) -> &'static str {
+        match self {
+            Relaxation::DefaultStart => {
+                "Parameters and constants need a value: a binding equation, or for \
+                 parameters a start value (or `fixed = false` and an initial equation)."
+            }
+            Relaxation::ShapeModifier => {
+                "`shape` is not an attribute of the built-in types; array dimensions are \
+                 subscripts of the declaration or the type."
+            }
+            Relaxation::BoolType => "The Boolean type is only named `Boolean`.",
+        }
+    }
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A use of a relaxation by a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelaxedConstruct {
+    pub relaxation: Relaxation,
+    /// Location and description of the construct
+    pub message: String,
+}
+
+impl fmt::Display for RelaxedConstruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.relaxation)
+    }
+}
+
+/// Find the relaxations a flattened class uses, before the compiler adds
+/// components of its own
+pub(crate) fn find_relaxations(class: &ClassDefinition) -> Vec<RelaxedConstruct> {
+    let mut found = Vec::new();
+    for (name, comp) in &class.components {
+        let mut relaxed = |relaxation, message: String| {
+            found.push(RelaxedConstruct {
+                relaxation,
+                message: format!("{}: {}", comp.location.file_position(), message),
+            });
+        };
+        if comp.type_name.to_string() == TYPE_BOOL {
+            relaxed(
+                Relaxation::BoolType,
+                format!("'{}' has the type 'Bool', use 'Boolean'", name),
+            );
+        }
+        if comp.shape_is_modification {
+            relaxed(
+                Relaxation::ShapeModifier,
+                format!(
+                    "'{}' is declared with a shape modifier, use subscripts like '{}[{}]'",
+                    name,
+                    name,
+                    comp.shape
+                        .iter()
+                        .map(usize::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+        }
+        let is_known = matches!(
+            comp.variability,
+            Variability::Parameter(_) | Variability::Constant(_)
+        );
+        let fixed_false = comp
+            .modifications
+            .get("fixed")
+            .is_some_and(|fixed| fixed.to_string() == "false");
+        if is_known && !fixed_false && !comp.start_is_modification && has_default_start(&comp.start)
+        {
+            relaxed(
+                Relaxation::DefaultStart,
+                format!("'{}' has no value, it defaults to its start value", name),
+            );
+        }
+    }
+    found
+}
+
+/// Whether a start value is the default the parser gives declarations
+/// without a binding, which has no location in the source
+fn has_default_start(start: &Expression) -> bool {
+    match start {
+        Expression::Empty => true,
+        Expression::Terminal { token, .. } => token.location.start_line == 0,
+        _ => false,
+    }
+}
+
+/// The relaxations, with what rumoca accepts and what the specification
+/// requires, as a Markdown document
+pub fn explain_relaxations() -> String {
+    let mut out = String::from(
+        "# Relaxations of the Modelica specification\n\n\
+         rumoca accepts these constructs by default; `--strict` rejects them.\n",
+    );
+    for relaxation in Relaxation::ALL {
+        out += &format!(
+            "\n## {}\n\nRelaxed: {}\n\nSpecification: {}\n",
+            relaxation,
+            relaxation.relaxed(),
+            relaxation.specification()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Error};
+
+    #[test]
+    fn test_relaxations() {
+        let source = "model M
+  parameter Real k;
+  parameter Real tau(start = 1);
+  parameter Real x0(fixed = false);
+  Bool on = true;
+  Real x(shape = 2);
+equation
+  for i in 1:2 loop
+    der(x[i]) = if on then -k * x[i] / tau else 0;
+  end for;
+initial equation
+  x0 = 1;
+  x[1] = x0;
+  x[2] = x0;
+end M;";
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        let messages: Vec<String> = result.relaxations.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "m.mo:2:13: 'k' has no value, it defaults to its start value [default-start]",
+                "m.mo:5:3: 'on' has the type 'Bool', use 'Boolean' [bool-type]",
+                "m.mo:6:3: 'x' is declared with a shape modifier, use subscripts like 'x[2]' [shape-modifier]",
+            ]
+        );
+
+        let err = Compiler::new()
+            .model("M")
+            .strict(true)
+            .compile_str(source, "m.mo")
+            .unwrap_err();
+        match &err {
+            Error::Strict(relaxations) => assert_eq!(relaxations, &result.relaxations),
+            other => panic!("expected relaxations, got {other:?}"),
+        }
+        assert!(
+            err.to_string()
+                .starts_with("strict: m.mo:2:13: 'k' has no value")
+        );
+
+        // Bindings from modifications are values
+        let source = "model A\n  parameter Real k;\nend A;\nmodel B\n  A a(k = 2);\nend B;";
+        let result = Compiler::new()
+            .model("B")
+            .strict(true)
+            .compile_str(source, "b.mo");
+        assert!(result.is_ok());
+
+        let explanation = explain_relaxations();
+        for relaxation in Relaxation::ALL {
+            assert!(explanation.contains(&format!("## {}", relaxation)));
+        }
+    }
+}
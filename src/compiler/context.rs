@@ -0,0 +1,417 @@
+//! Versioned description of the template context.
+//!
+//! Templates get the compiled model as the context objects `dae`,
+//! `provenance` and `topology`, whose layout follows the Rust structures.
+//! [`CONTEXT_VERSION`] is the version of that layout, `major.minor`: adding
+//! to the context bumps the minor version, changing or removing anything
+//! bumps the major version. Each version is listed in [`CHANGELOG`], and the
+//! context is described field by field in [`context_schema`], printed by
+//! `rumoca --emit context-schema` (Markdown, or JSON with `--json`).
+//!
+//! A template declares the version it was written for, and fails with a
+//! clear error before anything is rendered if rumoca provides an incompatible
+//! one, i.e. a different major version or an older minor version:
+//!
+//! ```jinja
+//! {{- require_context_version("1.0") -}}
+//! ```
+//!
+//! Templates can also read the version as `context_version`.
+
+use serde::Serialize;
+
+/// Version of the template context, see the [module docs](self)
+pub const CONTEXT_VERSION: &str = "1.5";
+
+/// Changes of the template context, by version, newest last
+pub const CHANGELOG: &[ContextChange] = &[
+    ContextChange {
+        version: "1.0",
+        description: "First versioned context: `dae`, `provenance` and `topology`",
+    },
+    ContextChange {
+        version: "1.1",
+        description: "Added `dae.parameter_uses`",
+    },
+    ContextChange {
+        version: "1.2",
+        description: "Added `dae.outputs`",
+    },
+    ContextChange {
+        version: "1.3",
+        description: "Added `dae.when_clauses`",
+    },
+    ContextChange {
+        version: "1.4",
+        description: "Added `dae.sort`",
+    },
+    ContextChange {
+        version: "1.5",
+        description: "Added the `description` string tokens of equations and statements, read \
+                      with the `description` filter, e.g. `{{ eq | description }}`, instead of \
+                      `dae.eq_ids.descriptions`",
+    },
+];
+
+/// A version of the template context and what changed in it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextChange {
+    pub version: &'static str,
+    pub description: &'static str,
+}
+
+/// A field of the template context
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextField {
+    /// Dotted path from the context, e.g. `dae.x`
+    pub path: &'static str,
+    /// Type of the value, e.g. `map of name to Component`
+    #[serde(rename = "type")]
+    pub type_name: &'static str,
+    pub description: &'static str,
+}
+
+/// Description of the template context, see [`context_schema`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextSchema {
+    pub version: &'static str,
+    pub changelog: &'static [ContextChange],
+    pub fields: &'static [ContextField],
+}
+
+const fn field(
+    path: &'static str,
+    type_name: &'static str,
+    description: &'static str,
+) -> ContextField {
+    ContextField {
+        path,
+        type_name,
+        description,
+    }
+}
+
+/// Fields of the template context
+const FIELDS: &[ContextField] = &[
+    field(
+        "context_version",
+        "string",
+        "Version of the template context, e.g. `1.0`",
+    ),
+    field("dae", "Dae", "The compiled model as a DAE"),
+    field("dae.model_name", "string", "Name of the compiled model"),
+    field("dae.rumoca_version", "string", "Version of rumoca"),
+    field("dae.git_version", "string", "Git version of rumoca"),
+    field("dae.model_hash", "string", "MD5 hash of the model source"),
+    field("dae.template_hash", "string", "MD5 hash of the template"),
+    field("dae.t", "Component", "Time"),
+    field(
+        "dae.p",
+        "map of name to Component",
+        "Parameters, with their values as `start`",
+    ),
+    field("dae.cp", "map of name to Component", "Constants"),
+    field(
+        "dae.x",
+        "map of name to Component",
+        "Continuous states, whose derivatives are `der(x)` calls in the equations",
+    ),
+    field("dae.y", "map of name to Component", "Algebraic variables"),
+    field("dae.u", "map of name to Component", "Inputs"),
+    field(
+        "dae.pre_z",
+        "map of name to Component",
+        "Real discrete variables before an event",
+    ),
+    field(
+        "dae.pre_x",
+        "map of name to Component",
+        "States before an event",
+    ),
+    field(
+        "dae.pre_m",
+        "map of name to Component",
+        "Discrete-valued variables before an event",
+    ),
+    field(
+        "dae.z",
+        "map of name to Component",
+        "Real discrete variables, changing only at events",
+    ),
+    field(
+        "dae.m",
+        "map of name to Component",
+        "Discrete-valued (Integer, Boolean) variables",
+    ),
+    field(
+        "dae.c",
+        "map of name to Component",
+        "Conditions of if-expressions, if-equations, when-clauses and min()/max(); `description` is the kind",
+    ),
+    field(
+        "dae.fx",
+        "list of Equation",
+        "Continuous-time equations, sorted; each variant has the `description` string tokens of the equation",
+    ),
+    field("dae.fx_init", "list of Equation", "Initial equations"),
+    field("dae.fz", "list of Equation", "Event update equations"),
+    field("dae.fm", "list of Equation", "Discrete update equations"),
+    field(
+        "dae.fr",
+        "map of condition to Statement",
+        "Resets (`reinit`) when a condition becomes true",
+    ),
+    field(
+        "dae.fc",
+        "map of condition to Expression",
+        "Expressions of the conditions",
+    ),
+    field("dae.eq_ids", "EquationIds", "Stable ids of the equations"),
+    field(
+        "dae.eq_ids.fx",
+        "list of string",
+        "Ids of `dae.fx`, by position (likewise `fx_init`, `fz`, `fm`)",
+    ),
+    field(
+        "dae.eq_ids.fx_init",
+        "list of string",
+        "Ids of `dae.fx_init`",
+    ),
+    field("dae.eq_ids.fz", "list of string", "Ids of `dae.fz`"),
+    field("dae.eq_ids.fm", "list of string", "Ids of `dae.fm`"),
+    field(
+        "dae.eq_ids.sources",
+        "map of id to string",
+        "Source location `file:line:column` of each equation",
+    ),
+    field(
+        "dae.asserts",
+        "list of Equation",
+        "Runtime guards, e.g. nonzero denominators with `--guard-divisions`",
+    ),
+    field(
+        "dae.singular",
+        "list of SingularEquations",
+        "Equations only solvable for a parameter or an input",
+    ),
+    field(
+        "dae.structural",
+        "list of string",
+        "Parameters whose values must be known at compile time",
+    ),
+    field(
+        "dae.parameter_uses",
+        "map of name to ParameterUses",
+        "Ids of the `equations` and names of the `components` depending on each parameter, through bindings",
+    ),
+    field(
+        "dae.outputs",
+        "list of string",
+        "Selected output variables, empty if all variables are outputs",
+    ),
+    field(
+        "dae.when_clauses",
+        "list of list of string",
+        "Conditions of the branches of each when-equation (when, then elsewhen): of the branches becoming true at an event, only the first fires",
+    ),
+    field(
+        "dae.sort",
+        "string",
+        "Order of `dae.fx`: `blt` (sorted and solved where possible) or `none` (as declared)",
+    ),
+    field(
+        "provenance",
+        "Provenance",
+        "What the model was compiled from",
+    ),
+    field("provenance.model", "string", "Name of the compiled model"),
+    field("provenance.rumoca_version", "string", "Version of rumoca"),
+    field("provenance.git_version", "string", "Git version of rumoca"),
+    field(
+        "provenance.compiled_at",
+        "string",
+        "Compile date, RFC 3339 in UTC",
+    ),
+    field(
+        "provenance.source",
+        "SourceFile",
+        "The main source file, with `file` and `md5`",
+    ),
+    field(
+        "provenance.libraries",
+        "list of SourceFile",
+        "Library files compiled with the main source",
+    ),
+    field(
+        "provenance.libraries_md5",
+        "string",
+        "MD5 digest of the library hashes, empty if unknown",
+    ),
+    field(
+        "provenance.license",
+        "string",
+        "Custom header text, e.g. a license notice",
+    ),
+    field(
+        "provenance.header",
+        "string",
+        "License text and provenance lines for a comment at the top of generated code",
+    ),
+    field(
+        "topology",
+        "Topology",
+        "Component hierarchy and connections",
+    ),
+    field(
+        "topology.components",
+        "map of name to TopologyComponent",
+        "Components whose type is a class, parents first, with `type_name`, `class_type`, `parent`, `children` and `conditional`",
+    ),
+    field(
+        "topology.connections",
+        "list of Connection",
+        "Connect equations, with `from` and `to`",
+    ),
+];
+
+/// Description of the template context: its version, changelog and fields
+pub fn context_schema() -> ContextSchema {
+    ContextSchema {
+        version: CONTEXT_VERSION,
+        changelog: CHANGELOG,
+        fields: FIELDS,
+    }
+}
+
+impl ContextSchema {
+    /// The schema as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Template context {}\n\n## Changelog\n\n", self.version);
+        for change in self.changelog.iter().rev() {
+            out += &format!("- {}: {}\n", change.version, change.description);
+        }
+        out += "\n## Fields\n\n| Field | Type | Description |\n|---|---|---|\n";
+        for field in self.fields {
+            out += &format!(
+                "| `{}` | {} | {} |\n",
+                field.path, field.type_name, field.description
+            );
+        }
+        out
+    }
+}
+
+/// Check that a template written for a context version can be rendered
+pub fn check_context_version(required: &str) -> Result<(), String> {
+    let parse = |version: &str| -> Option<(u32, u32)> {
+        let (major, minor) = version
+            .trim()
+            .split_once('.')
+            .unwrap_or((version.trim(), "0"));
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    };
+    let Some((major, minor)) = parse(required) else {
+        return Err(format!(
+            "invalid template context version '{}', expected e.g. \"{}\"",
+            required, CONTEXT_VERSION
+        ));
+    };
+    let (current_major, current_minor) = parse(CONTEXT_VERSION).unwrap_or_default();
+    if major != current_major || minor > current_minor {
+        return Err(format!(
+            "the template requires context version {}, but rumoca {} provides version {} \
+             (see `rumoca --emit context-schema`)",
+            required,
+            env!("CARGO_PKG_VERSION"),
+            CONTEXT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Check the `require_context_version(...)` directives of a template source,
+/// so an incompatible template fails before rendering
+pub fn check_template_source(source: &str) -> Result<(), String> {
+    const DIRECTIVE: &str = "require_context_version(";
+    let mut rest = source;
+    while let Some(start) = rest.find(DIRECTIVE) {
+        rest = &rest[start + DIRECTIVE.len()..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let required = rest[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+        check_context_version(required)?;
+        rest = &rest[end..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{Provenance, Topology};
+    use crate::dae::ast::Dae;
+
+    #[test]
+    fn test_context_versions() {
+        assert_eq!(check_context_version("1.0"), Ok(()));
+        assert_eq!(check_context_version("1"), Ok(()));
+        assert!(check_context_version("1.9").is_err());
+        assert!(
+            check_context_version("2.0")
+                .unwrap_err()
+                .contains("provides version 1.5")
+        );
+        assert!(
+            check_context_version("one")
+                .unwrap_err()
+                .contains("invalid")
+        );
+
+        let template = "{{- require_context_version(\"2.0\") -}}\n{{ dae.model_name }}";
+        assert!(check_template_source(template).is_err());
+        assert_eq!(check_template_source("{{ dae.model_name }}"), Ok(()));
+        assert_eq!(CHANGELOG.last().unwrap().version, CONTEXT_VERSION);
+    }
+
+    /// Every field of the context objects is described, so changes to their
+    /// layout show up in the schema (and need a new version)
+    #[test]
+    fn test_context_schema_is_complete() {
+        let objects = [
+            ("dae", serde_json::to_value(Dae::default()).unwrap()),
+            (
+                "provenance",
+                serde_json::to_value(Provenance::default()).unwrap(),
+            ),
+            (
+                "topology",
+                serde_json::to_value(Topology::default()).unwrap(),
+            ),
+        ];
+        let described: Vec<&str> = FIELDS.iter().map(|field| field.path).collect();
+        for (object, value) in &objects {
+            for key in value.as_object().unwrap().keys() {
+                let path = format!("{}.{}", object, key);
+                assert!(
+                    described.contains(&path.as_str()),
+                    "{} is not described",
+                    path
+                );
+            }
+        }
+        for field in FIELDS {
+            let mut parts = field.path.split('.');
+            let (object, key) = (parts.next().unwrap(), parts.next());
+            let known = match object {
+                "context_version" => true,
+                "dae" | "provenance" | "topology" => key.is_none_or(|key| {
+                    let value = &objects.iter().find(|(name, _)| *name == object).unwrap().1;
+                    value.get(key).is_some()
+                }),
+                _ => false,
+            };
+            assert!(known, "{} is not in the context", field.path);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Warnings of a compilation and the codes to deny them with.
+//!
+//! A model that compiles may still have problems worth a warning, e.g. it is
+//! unbalanced or declares variables it never uses. They are reported in
+//! [`CompilationResult::warnings`], and teams can make the compilation fail
+//! on the ones they don't accept with [`Compiler::deny`](crate::Compiler::deny):
+//!
+//! ```
+//! use rumoca::{Compiler, DiagnosticCode, Error};
+//!
+//! let source = "model M\n  Real x;\n  Real unused;\nequation\n  x = 1;\nend M;";
+//! let err = Compiler::new()
+//!     .model("M")
+//!     .deny(&[DiagnosticCode::UnusedVariable])
+//!     .compile_str(source, "M.mo")
+//!     .unwrap_err();
+//! assert!(matches!(err, Error::Denied(_)));
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::CompilationResult;
+use crate::dae::balance::BalanceStatus;
+use crate::ir::ast::ClassType;
+use crate::ir::transform::constants::global_builtins;
+use crate::lint::{LintResult, lint_unused_variables};
+
+/// Code of a compilation warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    /// The model has more or fewer equations than unknowns
+    Unbalanced,
+    /// A variable is declared but never used
+    UnusedVariable,
+    /// Equations could only be solved for a parameter or an input, see
+    /// [`Compiler::permissive`](crate::Compiler::permissive)
+    Singular,
+    /// A component has the name of a built-in, e.g. `time` or `pre`, which
+    /// references to the name may resolve to instead
+    ShadowedBuiltin,
+    /// A `modelica://` URI names a package or file that isn't found, see
+    /// [`resources`](crate::compiler::resources)
+    MissingResource,
+    /// A source file is not valid UTF-8 and was decoded as ISO-8859-1, see
+    /// [`read_source_with_warning`](crate::compiler::read_source_with_warning)
+    Encoding,
+}
+
+impl DiagnosticCode {
+    /// All diagnostic codes
+    pub const ALL: &[DiagnosticCode] = &[
+        DiagnosticCode::Unbalanced,
+        DiagnosticCode::UnusedVariable,
+        DiagnosticCode::Singular,
+        DiagnosticCode::ShadowedBuiltin,
+        DiagnosticCode::MissingResource,
+        DiagnosticCode::Encoding,
+    ];
+
+    /// Name of the code, e.g. `unused-variable`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::Unbalanced => "unbalanced",
+            DiagnosticCode::UnusedVariable => "unused-variable",
+            DiagnosticCode::Singular => "singular",
+            DiagnosticCode::ShadowedBuiltin => "shadowed-builtin",
+            DiagnosticCode::MissingResource => "missing-resource",
+            DiagnosticCode::Encoding => "encoding",
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DiagnosticCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiagnosticCode::ALL
+            .iter()
+            .find(|code| code.as_str() == s)
+            .copied()
+            .ok_or_else(|| {
+                let codes: Vec<&str> = DiagnosticCode::ALL.iter().map(|c| c.as_str()).collect();
+                format!(
+                    "unknown diagnostic code '{}' (expected one of: {})",
+                    s,
+                    codes.join(", ")
+                )
+            })
+    }
+}
+
+/// A warning of a compilation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompileWarning {
+    pub code: DiagnosticCode,
+    pub message: String,
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+/// Message of a component named like a built-in, e.g. `Real time;`
+pub(crate) fn shadowed_builtin_message(name: &str) -> String {
+    format!(
+        "Component '{name}' shadows the built-in of the same name; rename it, as references to it may resolve to the built-in"
+    )
+}
+
+/// Collect the warnings of a compilation
+pub(crate) fn collect_warnings(result: &CompilationResult) -> Vec<CompileWarning> {
+    let mut warnings = Vec::new();
+    let warn = |code, message| CompileWarning { code, message };
+
+    if result.balance.status == BalanceStatus::Unbalanced {
+        warnings.push(warn(
+            DiagnosticCode::Unbalanced,
+            format!(
+                "model '{}' is {}",
+                result.expanded_class.name.text,
+                result.balance.status_message()
+            ),
+        ));
+    }
+
+    // Fields of records and connectors are used where they are instantiated
+    let class = &result.expanded_class;
+    if !matches!(class.class_type, ClassType::Record | ClassType::Connector) {
+        let file = &class.location.file_name;
+        let mut lint = LintResult::new(file);
+        lint_unused_variables(class, file, &HashSet::new(), &mut lint);
+        for msg in lint.messages {
+            warnings.push(warn(
+                DiagnosticCode::UnusedVariable,
+                format!("{}:{}:{}: {}", msg.file, msg.line, msg.column, msg.message),
+            ));
+        }
+    }
+
+    for singular in &result.dae.singular {
+        warnings.push(warn(DiagnosticCode::Singular, singular.to_string()));
+    }
+
+    // Components named like built-ins, once per declaration with its declared
+    // name (the components of arrays of components share theirs)
+    let builtins: HashSet<String> = global_builtins().into_iter().collect();
+    let mut declarations = HashSet::new();
+    for (name, comp) in &class.components {
+        let short_name = name.rsplit('.').next().unwrap_or(name);
+        if builtins.contains(short_name) && declarations.insert(comp.location.file_position()) {
+            warnings.push(warn(
+                DiagnosticCode::ShadowedBuiltin,
+                format!(
+                    "{}: {}",
+                    comp.location.file_position(),
+                    shadowed_builtin_message(short_name)
+                ),
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Error};
+
+    const SOURCE: &str = "model M
+  Real x;
+  Real y;
+  Real unused;
+equation
+  x = 1;
+  y = x;
+end M;";
+
+    #[test]
+    fn test_warnings() {
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(SOURCE, "m.mo")
+            .unwrap();
+        let codes: Vec<DiagnosticCode> = result.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(
+            codes,
+            [DiagnosticCode::Unbalanced, DiagnosticCode::UnusedVariable]
+        );
+        assert_eq!(
+            result.warnings[1].to_string(),
+            "m.mo:4:3: Variable 'unused' is declared but never used [unused-variable]"
+        );
+    }
+
+    #[test]
+    fn test_deny() {
+        let err = Compiler::new()
+            .model("M")
+            .deny(&[DiagnosticCode::UnusedVariable])
+            .compile_str(SOURCE, "m.mo")
+            .unwrap_err();
+        match err {
+            Error::Denied(warnings) => {
+                assert_eq!(warnings.len(), 1);
+                assert_eq!(warnings[0].code, DiagnosticCode::UnusedVariable);
+            }
+            other => panic!("expected denied warnings, got {other:?}"),
+        }
+
+        // Codes without warnings don't fail the compilation
+        let result = Compiler::new()
+            .model("M")
+            .deny(&[DiagnosticCode::Singular])
+            .compile_str(SOURCE, "m.mo");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_shadowed_builtins_once_per_declaration() {
+        let source = "model Sub
+  Real sin;
+equation
+  sin = 2;
+end Sub;
+
+model M
+  Real time;
+  Real x(start = 1);
+  Sub s[2];
+equation
+  der(x) = -x;
+  time = 2;
+end M;";
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        let shadowed: Vec<String> = result
+            .warnings
+            .iter()
+            .filter(|w| w.code == DiagnosticCode::ShadowedBuiltin)
+            .map(|w| w.message.clone())
+            .collect();
+        // `s[1].sin` and `s[2].sin` are both declared by `Sub.sin`, which is
+        // reported once at its declaration
+        assert_eq!(
+            shadowed,
+            [
+                "m.mo:8:3: Component 'time' shadows the built-in of the same name; rename it, as references to it may resolve to the built-in",
+                "m.mo:2:3: Component 'sin' shadows the built-in of the same name; rename it, as references to it may resolve to the built-in",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_code() {
+        for code in DiagnosticCode::ALL {
+            assert_eq!(code.as_str().parse::<DiagnosticCode>(), Ok(*code));
+        }
+        assert!("unused".parse::<DiagnosticCode>().is_err());
+    }
+}
@@ -42,10 +42,7 @@ pub struct UndefinedVariableError {
 /// Error for parse/syntax errors in Modelica code
 #[derive(Error, Debug, Diagnostic)]
 #[error("Syntax error")]
-#[diagnostic(
-    code(rumoca::syntax_error),
-    help("Check the {syntax} near the highlighted location", syntax = "Modelica syntax".cyan())
-)]
+#[diagnostic(code(rumoca::syntax_error))]
 #[allow(unused_assignments)] // False positive from miette derive macro
 pub struct SyntaxError {
     /// The source code being compiled
@@ -58,6 +55,10 @@ pub struct SyntaxError {
 
     /// Error message from the parser
     pub message: String,
+
+    /// Suggested fix, or a generic hint if there is none
+    #[help]
+    pub help: String,
 }
 
 // =============================================================================
@@ -65,181 +66,489 @@ pub struct SyntaxError {
 // =============================================================================
 
 /// Create a syntax error diagnostic from a parse error using structured error data
+///
+/// The diagnostic renders as a code frame with the offending token underlined,
+/// the expected tokens in user terms and a suggested fix if one is known.
 pub fn create_syntax_error(error: &ParolError, source: &str) -> SyntaxError {
-    // Check for user errors (from anyhow::bail! in grammar actions)
-    if let ParolError::UserError(user_error) = error {
-        let message = user_error.to_string();
-        // Try to extract line/column from the error message
-        let (line, col) = extract_line_col_from_error(&message).unwrap_or((1, 1));
-        let byte_offset = line_col_to_byte_offset(source, line, col);
-        let remaining = source.len().saturating_sub(byte_offset);
-        let span_len = remaining.min(10);
-
-        return SyntaxError {
-            src: source.to_string(),
-            span: SourceSpan::new(byte_offset.into(), span_len),
-            message,
-        };
+    let problem = describe_parse_error(error, source);
+    let help = problem.help.unwrap_or_else(|| {
+        format!(
+            "Check the {} near the highlighted location",
+            "Modelica syntax".cyan()
+        )
+    });
+    SyntaxError {
+        src: source.to_string(),
+        span: SourceSpan::new(problem.offset.into(), problem.len),
+        message: problem.message,
+        help,
     }
+}
 
-    // Try to extract structured error information from ParolError
-    if let ParolError::ParserError(parser_error) = error
-        && let Some((line, col, message)) = extract_from_parser_error(parser_error, source)
-    {
-        let byte_offset = line_col_to_byte_offset(source, line, col);
-        let remaining = source.len().saturating_sub(byte_offset);
-        let span_len = remaining.min(10);
+/// A syntax error described in user terms
+struct ParseProblem {
+    /// Byte offset of the offending text
+    offset: usize,
+    /// Byte length of the offending text
+    len: usize,
+    message: String,
+    /// Suggested fix, e.g. a missing semicolon
+    help: Option<String>,
+}
 
-        return SyntaxError {
-            src: source.to_string(),
-            span: SourceSpan::new(byte_offset.into(), span_len),
+/// Describe a parse error by its location, a message and a suggested fix
+fn describe_parse_error(error: &ParolError, source: &str) -> ParseProblem {
+    let at_line_col = |line: usize, col: usize, message: String| {
+        let offset = line_col_to_byte_offset(source, line, col).min(source.len());
+        ParseProblem {
+            offset,
+            len: source.len().saturating_sub(offset).min(10),
             message,
+            help: None,
+        }
+    };
+
+    match error {
+        // User errors come from anyhow::bail! in grammar actions
+        ParolError::UserError(user_error) => {
+            let message = user_error.to_string();
+            let (line, col) = extract_line_col_from_error(&message).unwrap_or((1, 1));
+            at_line_col(line, col, message)
+        }
+        ParolError::ParserError(ParserError::SyntaxErrors { entries }) if !entries.is_empty() => {
+            describe_syntax_error(&entries[0], source)
+        }
+        ParolError::ParserError(ParserError::UnprocessedInput { last_token, .. }) => at_line_col(
+            last_token.start_line as usize,
+            last_token.start_column as usize,
+            "Unexpected input after valid syntax".to_string(),
+        ),
+        ParolError::ParserError(ParserError::PredictionError { cause }) => {
+            // The cause contains the ugly message, but we can try to extract location
+            let (line, col) = extract_location_from_cause(cause).unwrap_or((1, 1));
+            at_line_col(line, col, "Unexpected token".to_string())
+        }
+        _ => ParseProblem {
+            offset: 0,
+            len: 1.min(source.len()),
+            message: "Syntax error".to_string(),
+            help: None,
+        },
+    }
+}
+
+/// Describe a syntax error by the unexpected tokens and the expected ones
+///
+/// With several lookahead tokens the last one is reported, since the earlier
+/// ones are only unexpected in combination with it, e.g. `x` in `Real x Real y;`.
+fn describe_syntax_error(err: &parol_runtime::SyntaxError, source: &str) -> ParseProblem {
+    let Some(found) = err.unexpected_tokens.last() else {
+        return ParseProblem {
+            offset: 0,
+            len: 1.min(source.len()),
+            message: "Syntax error".to_string(),
+            help: None,
         };
+    };
+    // Point at the end of the last line rather than past the trailing newline
+    let offset = if found.token_type == "EndOfInput" {
+        source.trim_end().len()
+    } else {
+        (found.token.start as usize).min(source.len())
+    };
+    let end = (found.token.end as usize).clamp(offset, source.len());
+    let token_text = |token: &parol_runtime::UnexpectedToken| {
+        if token.token_type == "EndOfInput" {
+            return "end of file".to_string();
+        }
+        source
+            .get(token.token.start as usize..token.token.end as usize)
+            .filter(|text| !text.is_empty())
+            .map(|text| format!("'{text}'"))
+            .unwrap_or_else(|| clean_token_name(&token.token_type))
+    };
+
+    let mut message = format!("Unexpected {}", token_text(found));
+    let before = &err.unexpected_tokens[..err.unexpected_tokens.len() - 1];
+    if !before.is_empty() {
+        let before: Vec<String> = before.iter().map(token_text).collect();
+        message.push_str(&format!(" after {}", before.join(" ")));
+    }
+    let unclosed = unclosed_brackets(&source[..offset]);
+    let expected = expected_tokens(err, &unclosed);
+    if !expected.is_empty() {
+        message.push_str(&format!(", expected {}", join_expected(&expected)));
     }
 
-    // Fallback for other error types
-    SyntaxError {
-        src: source.to_string(),
-        span: SourceSpan::new(0.into(), 1_usize),
-        message: "Syntax error".to_string(),
+    // Text before the offending token, for locating a missing semicolon
+    let preceding_end = before
+        .first()
+        .map(|token| token.token.start as usize)
+        .unwrap_or(offset)
+        .min(offset);
+    let help = suggest_fix(err, source, preceding_end, offset, &unclosed);
+    ParseProblem {
+        offset,
+        len: end - offset,
+        message,
+        help,
     }
 }
 
-/// Extract location and message from a ParserError
-fn extract_from_parser_error(error: &ParserError, source: &str) -> Option<(usize, usize, String)> {
-    match error {
-        ParserError::SyntaxErrors { entries } => {
-            if let Some(first) = entries.first() {
-                let line = first.error_location.start_line as usize;
-                let col = first.error_location.start_column as usize;
-
-                // Build a clean message from expected/unexpected tokens
-                let message = build_clean_message(first, source);
-                return Some((line, col, message));
-            }
-        }
-        ParserError::UnprocessedInput { last_token, .. } => {
-            let line = last_token.start_line as usize;
-            let col = last_token.start_column as usize;
-            return Some((line, col, "Unexpected input after valid syntax".to_string()));
+/// Maximum number of expected tokens listed in a message
+const MAX_EXPECTED: usize = 4;
+
+/// Expected tokens in user terms, most likely first
+///
+/// Operators are summarized as "operator" and the tokens that can start an
+/// expression as "expression". Closing brackets are only listed if a bracket of
+/// that kind is open.
+fn expected_tokens(err: &parol_runtime::SyntaxError, unclosed: &[(char, usize)]) -> Vec<String> {
+    let names: Vec<&str> = err.expected_tokens.iter().map(String::as_str).collect();
+    let is_expression = names.iter().any(|name| is_identifier_token(name))
+        && names.iter().any(|name| is_number_token(name));
+    let has_binary_operator = names
+        .iter()
+        .any(|name| is_operator_token(name) && !is_unary_operator_token(name));
+
+    let mut expected: Vec<(u8, String)> = Vec::new();
+    let mut push = |priority: u8, text: String| {
+        if !expected.iter().any(|(_, existing)| *existing == text) {
+            expected.push((priority, text));
         }
-        ParserError::PredictionError { cause } => {
-            // The cause contains the ugly message, but we can try to extract location
-            if let Some((line, col)) = extract_location_from_cause(cause) {
-                return Some((line, col, "Unexpected token".to_string()));
+    };
+    for name in names {
+        if let Some(close) = closing_bracket(name) {
+            if unclosed
+                .iter()
+                .any(|(open, _)| matching_bracket(*open) == close)
+            {
+                push(1, format!("'{close}'"));
             }
+        } else if is_expression && starts_expression(name) {
+            push(3, "expression".to_string());
+        } else if is_operator_token(name) {
+            if has_binary_operator || !is_expression {
+                push(2, "operator".to_string());
+            }
+        } else if name == "Semicolon" {
+            push(0, "';'".to_string());
+        } else if is_identifier_token(name) {
+            push(3, "identifier".to_string());
+        } else if is_number_token(name) {
+            push(3, "number".to_string());
+        } else if name == "String" {
+            push(5, "string".to_string());
+        } else if let Some(punctuation) = punctuation(name) {
+            push(4, format!("'{punctuation}'"));
+        } else if name != "Error" {
+            push(5, format!("'{}'", name.to_lowercase()));
         }
-        _ => {}
     }
-    None
+    expected.sort_by_key(|(priority, _)| *priority);
+    expected.into_iter().map(|(_, text)| text).collect()
 }
 
-/// Build a clean error message from a SyntaxError, using source to extract actual token text
-fn build_clean_message(err: &parol_runtime::SyntaxError, source: &str) -> String {
-    // If there are unexpected tokens, describe what was found
-    if !err.unexpected_tokens.is_empty() {
-        let unexpected = &err.unexpected_tokens[0];
-        // Extract actual token text from source using byte offsets
-        let start = unexpected.token.start as usize;
-        let end = unexpected.token.end as usize;
-        let token_text = if start < source.len() && end <= source.len() && start < end {
-            &source[start..end]
-        } else {
-            // Fallback to cleaned token_type if extraction fails
-            return build_fallback_message(err);
-        };
+/// Join expected tokens as "a, b or c", abbreviating long lists
+fn join_expected(expected: &[String]) -> String {
+    match expected {
+        [] => String::new(),
+        [single] => single.clone(),
+        _ if expected.len() > MAX_EXPECTED => {
+            format!("one of {}, ...", expected[..MAX_EXPECTED].join(", "))
+        }
+        [init @ .., last] => format!("{} or {last}", init.join(", ")),
+    }
+}
 
-        // Build expected tokens list (limit to first few), cleaning up internal names
-        let expected: Vec<String> = err
+/// Suggest a fix for common mistakes: a missing `end X;` at the end of the
+/// file, an unclosed bracket and a missing semicolon at the end of a line
+fn suggest_fix(
+    err: &parol_runtime::SyntaxError,
+    source: &str,
+    preceding_end: usize,
+    offset: usize,
+    unclosed: &[(char, usize)],
+) -> Option<String> {
+    let expects = |name: &str| err.expected_tokens.iter().any(|token| token == name);
+    let at_end = err
+        .unexpected_tokens
+        .last()
+        .is_some_and(|token| token.token_type == "EndOfInput");
+
+    if at_end && expects("End") {
+        let (kind, name, start) = unclosed_classes(source).pop()?;
+        return Some(format!(
+            "Add 'end {name};' to close {kind} '{name}' declared on line {}",
+            line_col(source, start).0
+        ));
+    }
+
+    if let Some((open, start)) = unclosed.last()
+        && let close = matching_bracket(*open)
+        && err
             .expected_tokens
             .iter()
-            .take(5)
-            .map(|s| clean_token_name(s))
-            .collect();
-
-        if expected.is_empty() {
-            return format!("Unexpected '{}'", token_text);
-        } else if expected.len() == 1 {
-            return format!("Unexpected '{}', expected {}", token_text, expected[0]);
-        } else {
-            return format!(
-                "Unexpected '{}', expected one of: {}",
-                token_text,
-                expected.join(", ")
-            );
+            .any(|name| closing_bracket(name) == Some(close))
+    {
+        return Some(format!(
+            "Close the '{open}' opened on line {} with '{close}'",
+            line_col(source, *start).0
+        ));
+    }
+
+    if expects("Semicolon") {
+        // The offending token, or the lookahead before it, starts a new line
+        for start in [preceding_end, offset] {
+            let previous = source[..start].trim_end();
+            let line = line_col(source, previous.len()).0;
+            if !previous.is_empty() && line < line_col(source, start).0 {
+                return Some(format!("Add the missing ';' at the end of line {line}"));
+            }
         }
     }
+    None
+}
 
-    // Fall back to a generic message
-    "Syntax error".to_string()
+/// Brackets that are still open at the end of the text, with their byte
+/// offsets, skipping strings, quoted identifiers and comments
+fn unclosed_brackets(text: &str) -> Vec<(char, usize)> {
+    let mut open = Vec::new();
+    for (offset, c) in code_chars(text) {
+        match c {
+            '(' | '[' | '{' => open.push((c, offset)),
+            ')' | ']' | '}' if open.last().is_some_and(|(o, _)| matching_bracket(*o) == c) => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open
 }
 
-/// Fallback message builder when source extraction fails
-fn build_fallback_message(err: &parol_runtime::SyntaxError) -> String {
-    if !err.unexpected_tokens.is_empty() {
-        let token_name = clean_token_name(&err.unexpected_tokens[0].token_type);
-        let expected: Vec<String> = err
-            .expected_tokens
-            .iter()
-            .take(5)
-            .map(|s| clean_token_name(s))
-            .collect();
+/// Class definitions that are not closed by `end` at the end of the source,
+/// as (restriction, name, byte offset), innermost last
+fn unclosed_classes(source: &str) -> Vec<(String, String, usize)> {
+    const RESTRICTIONS: &[&str] = &[
+        "block",
+        "class",
+        "connector",
+        "function",
+        "model",
+        "operator",
+        "package",
+        "record",
+        "type",
+    ];
+    let words = code_words(source);
+    let mut classes: Vec<(String, String, usize)> = Vec::new();
+    for (i, (offset, word)) in words.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|j| words[j].1.as_str());
+        let next = words.get(i + 1).map(|(_, word)| word.as_str());
+        if *word == "end" {
+            if let Some(next) = next
+                && classes.last().is_some_and(|(_, name, _)| name == next)
+            {
+                classes.pop();
+            }
+        } else if RESTRICTIONS.contains(&word.as_str())
+            && previous != Some("end")
+            && let Some((name_offset, name)) = words.get(i + 1)
+            && !RESTRICTIONS.contains(&name.as_str())
+            && name != "extends"
+        {
+            // Short class definitions like `type Voltage = Real;` have no `end`
+            let after_name = source[name_offset + name.len()..].trim_start();
+            if !after_name.starts_with('=') {
+                classes.push((word.clone(), name.clone(), *offset));
+            }
+        }
+    }
+    classes
+}
 
-        if expected.is_empty() {
-            return format!("Unexpected {}", token_name);
+/// Words of the source outside of strings, quoted identifiers and comments,
+/// with their byte offsets
+fn code_words(source: &str) -> Vec<(usize, String)> {
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut previous_end = 0;
+    for (offset, c) in code_chars(source) {
+        let contiguous = offset == previous_end;
+        previous_end = offset + c.len_utf8();
+        if c.is_alphanumeric() || c == '_' {
+            match &mut current {
+                Some((_, word)) if contiguous => word.push(c),
+                _ => {
+                    words.extend(current.take());
+                    current = Some((offset, c.to_string()));
+                }
+            }
         } else {
-            return format!(
-                "Unexpected {}, expected one of: {}",
-                token_name,
-                expected.join(", ")
-            );
+            words.extend(current.take());
         }
     }
-    "Syntax error".to_string()
+    words.extend(current);
+    words
 }
 
-/// Clean up internal token names to be more user-friendly
-fn clean_token_name(name: &str) -> String {
-    // Map common punctuation and keywords
-    match name {
-        // Punctuation
-        "Semicolon" => "';'".to_string(),
-        "Comma" => "','".to_string(),
-        "LParen" => "'('".to_string(),
-        "RParen" => "')'".to_string(),
-        "LBrace" => "'{'".to_string(),
-        "RBrace" => "'}'".to_string(),
-        "LBracket" => "'['".to_string(),
-        "RBracket" => "']'".to_string(),
-        "Assign" => "':='".to_string(),
-        "Equals" => "'='".to_string(),
-        "Colon" => "':'".to_string(),
-        "Dot" => "'.'".to_string(),
-        "Plus" => "'+'".to_string(),
-        "Minus" => "'-'".to_string(),
-        "Star" => "'*'".to_string(),
-        "Slash" => "'/'".to_string(),
-        "Caret" => "'^'".to_string(),
-        "Less" => "'<'".to_string(),
-        "Greater" => "'>'".to_string(),
-        "LessEqual" => "'<='".to_string(),
-        "GreaterEqual" => "'>='".to_string(),
-        "NotEqual" => "'<>'".to_string(),
-
-        // Regex-based identifier patterns (parol internal names)
-        s if s.starts_with("LBracketUnderscore") && s.contains("AMinusZ") => {
-            "identifier".to_string()
+/// Characters of Modelica source outside of strings, quoted identifiers and
+/// comments, with their byte offsets
+fn code_chars(text: &str) -> Vec<(usize, char)> {
+    let mut chars = Vec::new();
+    let mut iter = text.char_indices().peekable();
+    while let Some((offset, c)) = iter.next() {
+        match c {
+            '"' | '\'' => {
+                while let Some((_, d)) = iter.next() {
+                    if d == '\\' {
+                        iter.next();
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            '/' if iter.peek().is_some_and(|(_, d)| *d == '/') => {
+                for (_, d) in iter.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if iter.peek().is_some_and(|(_, d)| *d == '*') => {
+                iter.next();
+                let mut star = false;
+                for (_, d) in iter.by_ref() {
+                    if star && d == '/' {
+                        break;
+                    }
+                    star = d == '*';
+                }
+            }
+            _ => chars.push((offset, c)),
         }
-        s if s.contains("AMinusZ") && s.contains("0Minus9") => "identifier".to_string(),
+    }
+    chars
+}
 
-        // Number patterns
-        s if s.contains("0Minus9") => "number".to_string(),
+/// 1-indexed line and column of a byte offset
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
 
-        // String patterns
-        s if s.contains("QuotationMark") || s.contains("DoubleQuote") => "string".to_string(),
+fn matching_bracket(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
 
-        // Clean up CamelCase keywords to lowercase
-        s => s.to_lowercase(),
+fn closing_bracket(name: &str) -> Option<char> {
+    match name {
+        "RParen" => Some(')'),
+        "RBracket" => Some(']'),
+        "RBrace" => Some('}'),
+        _ => None,
+    }
+}
+
+fn punctuation(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Equ" => "=",
+        "ColonEqu" => ":=",
+        "Colon" => ":",
+        "Comma" => ",",
+        "Dot" => ".",
+        "LParen" => "(",
+        "LBracket" => "[",
+        "LBrace" => "{",
+        _ => return None,
+    })
+}
+
+fn is_operator_token(name: &str) -> bool {
+    matches!(
+        name,
+        "Plus"
+            | "Minus"
+            | "Star"
+            | "Slash"
+            | "Circumflex"
+            | "DotPlus"
+            | "DotMinus"
+            | "DotStar"
+            | "DotSlash"
+            | "DotCircumflex"
+            | "LT"
+            | "LTEqu"
+            | "GT"
+            | "GTEqu"
+            | "EquEqu"
+            | "LTGT"
+            | "And"
+            | "Or"
+    )
+}
+
+fn is_unary_operator_token(name: &str) -> bool {
+    matches!(name, "Plus" | "Minus" | "DotPlus" | "DotMinus")
+}
+
+/// Whether a token can start an expression
+fn starts_expression(name: &str) -> bool {
+    is_identifier_token(name)
+        || is_number_token(name)
+        || is_unary_operator_token(name)
+        || matches!(
+            name,
+            "String"
+                | "Der"
+                | "True"
+                | "False"
+                | "Not"
+                | "If"
+                | "Initial"
+                | "Pure"
+                | "End"
+                | "LParen"
+                | "LBracket"
+                | "LBrace"
+                | "Dot"
+        )
+}
+
+/// Whether a token is an identifier, including quoted identifiers
+fn is_identifier_token(name: &str) -> bool {
+    name.contains("AMinusZ")
+}
+
+/// Whether a token is an integer or real number
+fn is_number_token(name: &str) -> bool {
+    name == "UnsignedInteger" || (name.contains("0Minus9") && !name.contains("AMinusZ"))
+}
+
+/// Clean up internal token names to be more user-friendly
+fn clean_token_name(name: &str) -> String {
+    if let Some(close) = closing_bracket(name) {
+        format!("'{close}'")
+    } else if let Some(punctuation) = punctuation(name) {
+        format!("'{punctuation}'")
+    } else if name == "Semicolon" {
+        "';'".to_string()
+    } else if is_identifier_token(name) {
+        "identifier".to_string()
+    } else if is_number_token(name) {
+        "number".to_string()
+    } else if name == "String" {
+        "string".to_string()
+    } else if name == "EndOfInput" {
+        "end of file".to_string()
+    } else {
+        name.to_lowercase()
     }
 }
 
@@ -266,23 +575,13 @@ fn extract_location_from_cause(cause: &str) -> Option<(usize, usize)> {
 /// This is useful for LSP diagnostics and other error reporting that needs
 /// line/column information without the full miette diagnostic.
 pub fn extract_parse_error(error: &ParolError, source: &str) -> (u32, u32, String) {
-    // Check for user errors (from anyhow::bail! in grammar actions)
-    if let ParolError::UserError(user_error) = error {
-        let message = user_error.to_string();
-        if let Some((line, col)) = extract_line_col_from_error(&message) {
-            return (line as u32, col as u32, message);
-        }
-        return (1, 1, message);
-    }
-
-    if let ParolError::ParserError(parser_error) = error
-        && let Some((line, col, message)) = extract_from_parser_error(parser_error, source)
-    {
-        return (line as u32, col as u32, message);
-    }
-
-    // Fallback
-    (1, 1, "Syntax error".to_string())
+    let problem = describe_parse_error(error, source);
+    let (line, col) = line_col(source, problem.offset);
+    let message = match problem.help {
+        Some(help) => format!("{}\n{help}", problem.message),
+        None => problem.message,
+    };
+    (line as u32, col as u32, message)
 }
 
 /// Convert line/column (1-indexed) to byte offset
@@ -327,3 +626,75 @@ pub fn extract_line_col_from_error(error_msg: &str) -> Option<(usize, usize)> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modelica_grammar::ModelicaGrammar;
+    use crate::modelica_parser::parse;
+
+    fn parse_error(source: &str) -> (u32, u32, String) {
+        let mut grammar = ModelicaGrammar::new();
+        let error = parse(source, "m.mo", &mut grammar).unwrap_err();
+        extract_parse_error(&error, source)
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let (line, col, message) =
+            parse_error("model M\n  Real x;\nequation\n  x = 1\n  der(x) = 2;\nend M;\n");
+        assert_eq!((line, col), (5, 3));
+        assert_eq!(
+            message,
+            "Unexpected 'der', expected one of ';', operator, '=', ':', ...\n\
+             Add the missing ';' at the end of line 4"
+        );
+
+        // The declaration is only invalid together with the next one
+        let (line, _, message) = parse_error("model M\n  Real x\n  Real y;\nend M;\n");
+        assert_eq!(line, 3);
+        assert!(
+            message.starts_with("Unexpected 'Real' after 'x'"),
+            "{message}"
+        );
+        assert!(message.ends_with("Add the missing ';' at the end of line 2"));
+    }
+
+    #[test]
+    fn test_missing_end() {
+        let (line, col, message) = parse_error("model M\n  Real x;\nequation\n  x = 1;\n");
+        assert_eq!((line, col), (4, 9));
+        assert!(message.starts_with("Unexpected end of file"), "{message}");
+        assert!(message.ends_with("Add 'end M;' to close model 'M' declared on line 1"));
+
+        let (_, _, message) = parse_error(
+            "package P\n  type V = Real;\n  model M\n    V x;\n  equation\n    x = 1;\n  end M;\n",
+        );
+        assert!(message.ends_with("Add 'end P;' to close package 'P' declared on line 1"));
+    }
+
+    #[test]
+    fn test_unclosed_bracket() {
+        let (_, _, message) = parse_error("model M\n  Real x;\nequation\n  x = (1 + 2;\nend M;\n");
+        assert_eq!(
+            message,
+            "Unexpected ';', expected ')' or ','\nClose the '(' opened on line 4 with ')'"
+        );
+    }
+
+    #[test]
+    fn test_expected_expression() {
+        let (_, _, message) = parse_error("model M\n  Real x;\nequation\n  x = 1 +* 2;\nend M;\n");
+        assert_eq!(message, "Unexpected '*', expected expression");
+    }
+
+    #[test]
+    fn test_report_help() {
+        let source = "model M\n  Real x\n  Real y;\nend M;\n";
+        let mut grammar = ModelicaGrammar::new();
+        let error = parse(source, "m.mo", &mut grammar).unwrap_err();
+        let diagnostic = create_syntax_error(&error, source);
+        assert_eq!(diagnostic.help, "Add the missing ';' at the end of line 2");
+        assert_eq!(&source[diagnostic.span.offset()..][..4], "Real");
+    }
+}
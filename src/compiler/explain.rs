@@ -0,0 +1,300 @@
+//! Explanations of single variables, for debugging models.
+//!
+//! `rumoca explain model.mo -m Model --var motor.w` prints where a variable is
+//! declared, its start value or binding, the equations involving it, the
+//! equation BLT solves for it and its classification:
+//!
+//! ```text
+//! motor.w: state Real
+//!   declared at model.mo:12:5
+//!   start: 0
+//!   equations:
+//!     model.mo:20:5: der(motor.w) = motor.tau / motor.J
+//!     model.mo:24:3: y = 2 * motor.w
+//!   solved by model.mo:20:5 for der(motor.w), block 2 of 3
+//! ```
+//!
+//! States are solved through their derivatives. The equations are those of the
+//! DAE, after flattening and BLT, with the locations of the declared equations
+//! they come from.
+
+use std::fmt;
+
+use crate::compiler::CompilationResult;
+use crate::ir::ast::{Component, ComponentReference, Equation, Expression, Location};
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// Explanation of a variable of a compiled model, see the
+/// [module docs](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// Name of the variable, e.g. `motor.w`
+    pub name: String,
+    /// Classification: state, algebraic, input, discrete, parameter,
+    /// constant or condition
+    pub kind: &'static str,
+    pub type_name: String,
+    /// Location of the declaration, if the variable is declared in the source
+    pub declaration: Option<Location>,
+    /// Start value, or value of parameters and constants, if one is given
+    pub start: Option<String>,
+    /// Equations involving the variable, the continuous ones in BLT order
+    pub equations: Vec<ExplainedEquation>,
+    /// How BLT solves the variable (through its derivative for states)
+    pub solved_by: Option<Solution>,
+}
+
+/// An equation involving the explained variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainedEquation {
+    /// Location of the declared equation it comes from, if any
+    pub location: Option<Location>,
+    /// The equation, as solved by BLT
+    pub equation: String,
+    /// `initial` or `event` for equations outside of the continuous ones
+    pub section: Option<&'static str>,
+}
+
+/// The equation BLT solves for a variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Index of the equation in [`Explanation::equations`]
+    pub equation: usize,
+    /// The variable, or `der(x)` for a state `x`
+    pub unknown: String,
+    /// Index of the block of the equation, starting at 0
+    pub block: usize,
+    /// Number of blocks
+    pub blocks: usize,
+    /// Number of equations of the block, more than one for an algebraic loop
+    pub block_size: usize,
+}
+
+impl CompilationResult {
+    /// Explain a variable of the model, see the [module docs](self), or
+    /// None if the model has no variable of this name
+    pub fn explain(&self, name: &str) -> Option<Explanation> {
+        let dae = &self.dae;
+        let kinds = [
+            (&dae.x, "state"),
+            (&dae.y, "algebraic"),
+            (&dae.u, "input"),
+            (&dae.z, "discrete"),
+            (&dae.m, "discrete"),
+            (&dae.p, "parameter"),
+            (&dae.cp, "constant"),
+            (&dae.c, "condition"),
+        ];
+        let (comp, kind) = kinds
+            .into_iter()
+            .find_map(|(components, kind)| components.get(name).map(|comp| (comp, kind)))?;
+
+        let unknown = if kind == "state" {
+            format!("der({})", name)
+        } else {
+            name.to_string()
+        };
+        let mut equations = Vec::new();
+        let mut solved_by = None;
+        let blt = &self.blt;
+        for (eq, &source) in blt.equations.iter().zip(&blt.source_indices) {
+            if !references(eq, name) {
+                continue;
+            }
+            if blt.matching.get(&source) == Some(&unknown) {
+                let block = blt
+                    .sccs
+                    .iter()
+                    .position(|scc| scc.contains(&source))
+                    .unwrap_or_default();
+                solved_by = Some(Solution {
+                    equation: equations.len(),
+                    unknown: unknown.clone(),
+                    block,
+                    blocks: blt.sccs.len(),
+                    block_size: blt.sccs.get(block).map_or(1, Vec::len),
+                });
+            }
+            equations.push(ExplainedEquation {
+                location: blt.locations.get(source).cloned().flatten(),
+                equation: eq.to_string(),
+                section: None,
+            });
+        }
+        let others = [
+            (&dae.fx_init, "initial"),
+            (&dae.fz, "event"),
+            (&dae.fm, "event"),
+        ];
+        for (partition, section) in others {
+            for eq in partition.iter().filter(|eq| references(eq, name)) {
+                equations.push(ExplainedEquation {
+                    location: eq.get_location().cloned(),
+                    equation: eq.to_string(),
+                    section: Some(section),
+                });
+            }
+        }
+
+        Some(Explanation {
+            name: name.to_string(),
+            kind,
+            type_name: comp.type_name.to_string(),
+            declaration: source_location(&comp.location).cloned(),
+            start: declared_start(comp),
+            equations,
+            solved_by,
+        })
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {} {}", self.name, self.kind, self.type_name)?;
+        if let Some(location) = &self.declaration {
+            writeln!(f, "  declared at {}", location.file_position())?;
+        }
+        let is_known = matches!(self.kind, "parameter" | "constant");
+        match (&self.start, is_known) {
+            (Some(start), true) => writeln!(f, "  value: {}", start)?,
+            (Some(start), false) => writeln!(f, "  start: {}", start)?,
+            (None, _) => {}
+        }
+        if self.equations.is_empty() {
+            writeln!(f, "  equations: none")?;
+        } else {
+            writeln!(f, "  equations:")?;
+        }
+        for eq in &self.equations {
+            write!(f, "    ")?;
+            if let Some(section) = eq.section {
+                write!(f, "[{}] ", section)?;
+            }
+            if let Some(location) = source_location_opt(&eq.location) {
+                write!(f, "{}: ", location.file_position())?;
+            }
+            writeln!(f, "{}", eq.equation)?;
+        }
+        match &self.solved_by {
+            Some(solution) => {
+                write!(f, "  solved by ")?;
+                let eq = &self.equations[solution.equation];
+                match source_location_opt(&eq.location) {
+                    Some(location) => write!(f, "{}", location.file_position())?,
+                    None => write!(f, "'{}'", eq.equation)?,
+                }
+                write!(
+                    f,
+                    " for {}, block {} of {}",
+                    solution.unknown,
+                    solution.block + 1,
+                    solution.blocks
+                )?;
+                if solution.block_size > 1 {
+                    write!(f, " (algebraic loop of {} equations)", solution.block_size)?;
+                }
+                Ok(())
+            }
+            None if is_known => write!(f, "  known, not solved for"),
+            None if self.kind == "input" => write!(f, "  given from outside the model"),
+            None => write!(f, "  not solved by the continuous equations"),
+        }
+    }
+}
+
+/// A location, unless the compiler generated the node without one
+fn source_location(location: &Location) -> Option<&Location> {
+    (location.start_line > 0).then_some(location)
+}
+
+fn source_location_opt(location: &Option<Location>) -> Option<&Location> {
+    location.as_ref().and_then(source_location)
+}
+
+/// The start value or binding of a component, unless it's the default the
+/// parser gives declarations without one
+pub(super) fn declared_start(comp: &Component) -> Option<String> {
+    match &comp.start {
+        Expression::Empty => None,
+        Expression::Terminal { token, .. }
+            if token.location.start_line == 0 && !comp.start_is_modification =>
+        {
+            None
+        }
+        start => Some(start.to_string()),
+    }
+}
+
+/// Whether an equation references a variable
+fn references(eq: &Equation, name: &str) -> bool {
+    struct Finder<'a> {
+        name: &'a str,
+        found: bool,
+    }
+    impl Visitor for Finder<'_> {
+        fn enter_component_reference(&mut self, node: &ComponentReference) {
+            self.found |= node.to_string() == self.name;
+        }
+    }
+    let mut finder = Finder { name, found: false };
+    eq.accept(&mut finder);
+    finder.found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    #[test]
+    fn test_explain() {
+        let source = r#"model Motor
+  parameter Real J = 0.1;
+  Real w(start = 1);
+  Real tau;
+equation
+  J * der(w) = tau - w;
+  tau = 2;
+end Motor;
+
+model M
+  Motor motor;
+  Real y;
+  Real a, b;
+equation
+  y = 2 * motor.w;
+  a + b = y;
+  a - b = 1;
+end M;
+"#;
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+
+        let w = result.explain("motor.w").unwrap();
+        assert_eq!(w.kind, "state");
+        assert_eq!(w.declaration.as_ref().unwrap().file_position(), "m.mo:3:3");
+        assert_eq!(w.start.as_deref(), Some("1"));
+        assert_eq!(w.equations.len(), 2);
+        let solution = w.solved_by.as_ref().unwrap();
+        assert_eq!(solution.unknown, "der(motor.w)");
+        let text = w.to_string();
+        assert!(text.starts_with("motor.w: state Real\n  declared at m.mo:3:3\n  start: 1\n"));
+        assert!(text.contains("    m.mo:6:3: der(motor.w) = "), "{}", text);
+        assert!(
+            text.contains("  solved by m.mo:6:3 for der(motor.w), block"),
+            "{}",
+            text
+        );
+
+        let a = result.explain("a").unwrap().to_string();
+        assert!(a.starts_with("a: algebraic Real\n"), "{}", a);
+        assert!(a.contains("(algebraic loop of 2 equations)"), "{}", a);
+
+        let j = result.explain("motor.J").unwrap().to_string();
+        assert!(j.contains("  value: 0.1\n"), "{}", j);
+        assert!(j.ends_with("  known, not solved for"), "{}", j);
+
+        assert!(result.explain("nope").is_none());
+    }
+}
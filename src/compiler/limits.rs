@@ -0,0 +1,188 @@
+//! Resource limits of the compilation.
+//!
+//! Pathological models, e.g. with huge arrays or for-loops, could otherwise
+//! make the compiler (and the language server) run out of memory or time.
+//! Compilations exceeding a limit fail with an [`Error::Limit`](crate::Error::Limit):
+//!
+//! - the number of elements an array, a for-equation or an array equation
+//!   expands to, see [`Compiler::max_expansion_size`](crate::Compiler::max_expansion_size)
+//! - the depth of nested components, see
+//!   [`Compiler::max_instance_depth`](crate::Compiler::max_instance_depth)
+//! - the wall-clock time of the compilation of one class, see
+//!   [`Compiler::timeout`](crate::Compiler::timeout)
+//!
+//! The limits of a compilation are passed down the pipeline as [`Limits`].
+//! The timeout is checked between the stages of the pipeline and while
+//! flattening components and expanding equations, so the compilation is
+//! aborted soon after the deadline rather than exactly on it.
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::ir::ast::Location;
+
+// Use web_time on WASM for Instant::now() polyfill
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Default maximum number of elements of an expansion, see [`Limits::max_expansion_size`]
+pub const DEFAULT_MAX_EXPANSION_SIZE: usize = 1_000_000;
+
+/// Default maximum depth of nested components, see [`Limits::max_instance_depth`]
+pub const DEFAULT_MAX_INSTANCE_DEPTH: usize = 100;
+
+/// Resource limits of one compilation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Maximum number of elements an array, a for-equation or an array
+    /// equation may expand to, and of scalar equations of a model
+    pub max_expansion_size: usize,
+    /// Maximum depth of nested components when flattening.
+    /// Recursive instantiation is reported as soon as a class contains
+    /// itself, the limit is a safety net for unreasonably deep hierarchies.
+    pub max_instance_depth: usize,
+    /// Wall-clock time after which the compilation fails, see [`with_timeout`]
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_expansion_size: DEFAULT_MAX_EXPANSION_SIZE,
+            max_instance_depth: DEFAULT_MAX_INSTANCE_DEPTH,
+            timeout: None,
+        }
+    }
+}
+
+/// A resource limit the compilation exceeded
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LimitExceeded {
+    #[error(
+        "{location}: {what} expands to {size} elements, more than the limit of {limit}; raise the limit with --max-expansion-size"
+    )]
+    ExpansionTooLarge {
+        location: String,
+        what: String,
+        size: usize,
+        limit: usize,
+    },
+
+    #[error("compilation aborted after the timeout of {} s", .timeout.as_secs_f64())]
+    Timeout { timeout: Duration },
+}
+
+impl Limits {
+    /// Fail if `size` elements are more than [`Limits::max_expansion_size`]
+    ///
+    /// `what` describes the expansion, e.g. `for-equation index 'i'`.
+    pub fn check_expansion(
+        &self,
+        size: usize,
+        location: &Location,
+        what: impl FnOnce() -> String,
+    ) -> Result<(), LimitExceeded> {
+        let limit = self.max_expansion_size;
+        if size <= limit {
+            return Ok(());
+        }
+        Err(LimitExceeded::ExpansionTooLarge {
+            location: location.file_position(),
+            what: what(),
+            size,
+            limit,
+        })
+    }
+}
+
+thread_local! {
+    /// Deadline of the compilation running on this thread, with its timeout
+    static DEADLINE: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+}
+
+/// Restores the deadline of the enclosing compilation when dropped, also
+/// when the compilation panics
+struct DeadlineGuard {
+    previous: Option<(Instant, Duration)>,
+}
+
+impl Drop for DeadlineGuard {
+    fn drop(&mut self) {
+        DEADLINE.with(|cell| cell.set(self.previous));
+    }
+}
+
+/// Run `f` with a deadline `timeout` from now, checked by [`check_deadline`]
+/// on this thread
+pub fn with_timeout<T>(timeout: Option<Duration>, f: impl FnOnce() -> T) -> T {
+    let deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+    let _guard = DeadlineGuard {
+        previous: DEADLINE.with(|cell| cell.replace(deadline)),
+    };
+    f()
+}
+
+/// Fail if the deadline of the compilation running on this thread passed
+pub fn check_deadline() -> Result<(), LimitExceeded> {
+    match DEADLINE.with(Cell::get) {
+        Some((deadline, timeout)) if Instant::now() >= deadline => {
+            Err(LimitExceeded::Timeout { timeout })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        assert!(check_deadline().is_ok());
+        let expired = with_timeout(Some(Duration::ZERO), check_deadline);
+        assert_eq!(
+            expired,
+            Err(LimitExceeded::Timeout {
+                timeout: Duration::ZERO
+            })
+        );
+        assert!(with_timeout(Some(Duration::from_secs(60)), check_deadline).is_ok());
+        // The deadline only applies within with_timeout
+        assert!(check_deadline().is_ok());
+        // and is restored when the compilation panics
+        let panicked = std::panic::catch_unwind(|| {
+            with_timeout(Some(Duration::ZERO), || panic!("compilation failed"))
+        });
+        assert!(panicked.is_err());
+        assert!(check_deadline().is_ok());
+    }
+
+    #[test]
+    fn test_check_expansion() {
+        let limits = Limits {
+            max_expansion_size: 10,
+            ..Limits::default()
+        };
+        let location = Location::default();
+        assert!(
+            limits
+                .check_expansion(10, &location, || unreachable!())
+                .is_ok()
+        );
+        let err = limits
+            .check_expansion(11, &location, || "array 'x'".to_string())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LimitExceeded::ExpansionTooLarge {
+                size: 11,
+                limit: 10,
+                ..
+            }
+        ));
+    }
+}
@@ -14,7 +14,7 @@
 //! let result = Compiler::new()
 //!     .model("MyModel")
 //!     .compile_file("model.mo")?;
-//! # Ok::<(), anyhow::Error>(())
+//! # Ok::<(), rumoca::Error>(())
 //! ```
 //!
 //! With verbose output and template rendering:
@@ -27,7 +27,7 @@
 //!     .verbose(true)
 //!     .compile_file("model.mo")?
 //!     .render_template("template.j2")?;
-//! # Ok::<(), anyhow::Error>(())
+//! # Ok::<(), rumoca::Error>(())
 //! ```
 //!
 //! Compiling from a string:
@@ -46,28 +46,52 @@
 //! let result = Compiler::new()
 //!     .model("Integrator")
 //!     .compile_str(modelica_code, "Integrator.mo")?;
-//! # Ok::<(), anyhow::Error>(())
+//! # Ok::<(), rumoca::Error>(())
 //! ```
 
+pub mod builtin;
 pub mod cache;
-mod error_handling;
+pub mod class_info;
+pub mod conformance;
+pub mod context;
+pub mod diagnostics;
+pub(crate) mod error_handling;
+pub mod explain;
 mod function_collector;
+pub mod limits;
+pub mod outline;
+pub mod passes;
+pub mod paths;
 pub mod pipeline;
+pub mod provenance;
+pub mod repl;
+pub mod resources;
 mod result;
+pub mod source;
+pub mod topology;
 
+pub use crate::dae::ast::SortMode;
+pub use diagnostics::{CompileWarning, DiagnosticCode};
 pub use error_handling::extract_parse_error;
+pub use passes::Passes;
+pub use provenance::Provenance;
+pub use resources::ResourceResolver;
 pub use result::CompilationResult;
+pub use source::{normalize_source, read_source, read_source_with_warning};
+pub use topology::Topology;
 
-use crate::ir::ast::StoredDefinition;
+use crate::dae::ast::Dae;
+use crate::error::{Error, Result, describe};
+use crate::ir::ast::{ClassDefinition, StoredDefinition};
 use crate::modelica_grammar::ModelicaGrammar;
 use crate::modelica_parser::parse;
-use anyhow::{Context, Result};
-use error_handling::create_syntax_error;
 use indexmap::IndexSet;
+use limits::Limits;
+use provenance::SourceFile;
 #[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
-use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // Use web_time on WASM for Instant::now() polyfill
 #[cfg(not(target_arch = "wasm32"))]
@@ -82,7 +106,8 @@ use web_time::Instant;
 /// Parse Modelica source code and return the AST.
 ///
 /// This is a low-level parsing function that returns `None` on parse errors.
-/// For error details, use [`parse_source`] instead.
+/// For error details, use [`parse_source`] instead. The source is normalized
+/// with [`normalize_source`] first.
 ///
 /// # Arguments
 /// * `source` - The Modelica source code
@@ -91,8 +116,9 @@ use web_time::Instant;
 /// # Returns
 /// `Some(StoredDefinition)` if parsing succeeded, `None` otherwise.
 pub fn parse_source_simple(source: &str, file_name: &str) -> Option<StoredDefinition> {
+    let source = normalize_source(source);
     let mut grammar = ModelicaGrammar::new();
-    if parse(source, file_name, &mut grammar).is_ok() {
+    if parse(&source, file_name, &mut grammar).is_ok() {
         grammar.modelica
     } else {
         None
@@ -101,6 +127,8 @@ pub fn parse_source_simple(source: &str, file_name: &str) -> Option<StoredDefini
 
 /// Parse Modelica source code and return the AST with detailed errors.
 ///
+/// The source is normalized with [`normalize_source`] first.
+///
 /// # Arguments
 /// * `source` - The Modelica source code
 /// * `file_name` - The file name (used for error messages and location tracking)
@@ -108,16 +136,44 @@ pub fn parse_source_simple(source: &str, file_name: &str) -> Option<StoredDefini
 /// # Returns
 /// `Ok(StoredDefinition)` if parsing succeeded, `Err` with detailed error otherwise.
 pub fn parse_source(source: &str, file_name: &str) -> Result<StoredDefinition> {
+    let source = normalize_source(source);
     let mut grammar = ModelicaGrammar::new();
-    if let Err(e) = parse(source, file_name, &mut grammar) {
-        let diagnostic = create_syntax_error(&e, source);
-        let report = miette::Report::new(diagnostic);
-        return Err(anyhow::anyhow!("{:?}", report));
+    if let Err(e) = parse(&source, file_name, &mut grammar) {
+        return Err(Error::parse(&e, &source, file_name));
     }
 
-    grammar
-        .modelica
-        .ok_or_else(|| anyhow::anyhow!("Parser succeeded but produced no AST for {}", file_name))
+    grammar.modelica.ok_or_else(|| {
+        Error::Other(format!(
+            "Parser succeeded but produced no AST for {}",
+            file_name
+        ))
+    })
+}
+
+/// Parse Modelica source code and return the AST together with its lossless syntax tree.
+///
+/// The [`SyntaxTree`](crate::modelica_grammar::cst::SyntaxTree) keeps every token,
+/// comment and whitespace run with exact byte spans, so tools can copy untouched
+/// regions of the source byte-exactly while rewriting others from the AST.
+/// Both are built from the normalized source (see [`normalize_source`]), so
+/// byte spans refer to that text rather than to `source` itself.
+///
+/// # Arguments
+/// * `source` - The Modelica source code
+/// * `file_name` - The file name (used for error messages and location tracking)
+///
+/// # Returns
+/// `Some((StoredDefinition, SyntaxTree))` if parsing succeeded, `None` otherwise.
+pub fn parse_source_lossless(
+    source: &str,
+    file_name: &str,
+) -> Option<(StoredDefinition, crate::modelica_grammar::cst::SyntaxTree)> {
+    let ast = parse_source_simple(source, file_name)?;
+    let source = normalize_source(source);
+    Some((
+        ast,
+        crate::modelica_grammar::cst::SyntaxTree::parse(&source),
+    ))
 }
 
 /// Parse a Modelica file from disk, using disk cache if available.
@@ -141,7 +197,7 @@ pub fn parse_file_cached(path: &Path) -> Option<StoredDefinition> {
     }
 
     // Cache miss - read and parse the file
-    let text = fs::read_to_string(path).ok()?;
+    let text = read_source(path).ok()?;
     let path_str = path.to_string_lossy().to_string();
 
     let ast = parse_source_simple(&text, &path_str)?;
@@ -163,7 +219,8 @@ pub fn parse_file_cached(path: &Path) -> Option<StoredDefinition> {
 /// `Ok(StoredDefinition)` if parsing succeeded, `Err` with detailed error otherwise.
 pub fn parse_file_cached_result(path: &Path) -> Result<StoredDefinition> {
     // Compute file hash for cache lookup
-    let file_hash = cache::compute_file_hash(path)?;
+    let file_hash =
+        cache::compute_file_hash(path).map_err(|e| Error::io(path, std::io::Error::other(e)))?;
 
     // Try cache first
     if let Some(ast) = cache::load_cached_ast(path, &file_hash) {
@@ -171,8 +228,7 @@ pub fn parse_file_cached_result(path: &Path) -> Result<StoredDefinition> {
     }
 
     // Cache miss - read and parse the file
-    let text = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let text = read_source(path)?;
     let path_str = path.to_string_lossy().to_string();
 
     let ast = parse_source(&text, &path_str)?;
@@ -197,7 +253,7 @@ pub fn parse_file_cached_result(path: &Path) -> Result<StoredDefinition> {
 ///     .model("MyModel")
 ///     .verbose(true)
 ///     .compile_file("model.mo")?;
-/// # Ok::<(), anyhow::Error>(())
+/// # Ok::<(), rumoca::Error>(())
 /// ```
 #[derive(Debug, Clone)]
 pub struct Compiler {
@@ -212,6 +268,26 @@ pub struct Compiler {
     threads: Option<usize>,
     /// Enable AST caching for faster library loading (default: true)
     use_cache: bool,
+    /// Add runtime assertions for nonzero denominators (default: false)
+    guard_divisions: bool,
+    /// Compile structurally singular models instead of failing (default: false)
+    permissive: bool,
+    /// Fail on relaxations of the Modelica specification (default: false)
+    strict: bool,
+    /// Keep regular for-equations as loops in the DAE (default: false)
+    symbolic_loops: bool,
+    /// Order of the continuous-time equations (default: BLT)
+    sort: SortMode,
+    /// Expansion size, instance depth and timeout of the compilation of a model
+    limits: Limits,
+    /// Variables to export as outputs (default: all)
+    outputs: Vec<String>,
+    /// Warnings that fail the compilation
+    deny: Vec<DiagnosticCode>,
+    /// Custom text for the header of generated code, e.g. a license notice
+    license_header: String,
+    /// Custom passes to run during compilation
+    passes: Passes,
 }
 
 impl Default for Compiler {
@@ -223,6 +299,16 @@ impl Default for Compiler {
             modelica_paths: Vec::new(),
             threads: None,   // Will use 50% of cores
             use_cache: true, // Enable caching by default
+            guard_divisions: false,
+            permissive: false,
+            strict: false,
+            symbolic_loops: false,
+            sort: SortMode::Blt,
+            limits: Limits::default(),
+            outputs: Vec::new(),
+            deny: Vec::new(),
+            license_header: String::new(),
+            passes: Passes::default(),
         }
     }
 }
@@ -290,8 +376,11 @@ impl Compiler {
     ///     .modelica_path(&["/path/to/MSL", "/path/to/other/libs"])
     ///     .include_from_modelica_path("Modelica")?
     ///     .compile_file("model.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
+    ///
+    /// Paths of the form `builtin:<name>` refer to libraries shipped with
+    /// rumoca (see [`builtin`]), e.g. `builtin:msl-mini`.
     pub fn modelica_path(mut self, paths: &[&str]) -> Self {
         self.modelica_paths = paths.iter().map(std::path::PathBuf::from).collect();
         self
@@ -360,6 +449,361 @@ impl Compiler {
         self
     }
 
+    /// Enables or disables division guards in the generated DAE.
+    ///
+    /// When enabled, an `assert(denominator <> 0, ...)` is added to
+    /// [`Dae::asserts`](crate::dae::ast::Dae::asserts) for every division whose
+    /// denominator is not a nonzero constant, for backends that emit assertions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().guard_divisions(true);
+    /// ```
+    pub fn guard_divisions(mut self, enable: bool) -> Self {
+        self.guard_divisions = enable;
+        self
+    }
+
+    /// Enables or disables permissive compilation of singular models.
+    ///
+    /// By default, compilation fails with an [`Error::Balance`] listing the
+    /// offending equations when the equations of the model could only be
+    /// solved for a parameter or a top-level input. When permissive, the model
+    /// is compiled anyway and the equations are reported in
+    /// [`Dae::singular`](crate::dae::ast::Dae::singular).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().permissive(true);
+    /// ```
+    pub fn permissive(mut self, enable: bool) -> Self {
+        self.permissive = enable;
+        self
+    }
+
+    /// Enables or disables strict conformance to the Modelica specification.
+    ///
+    /// By default, a few constructs outside of the specification are
+    /// accepted and reported in [`CompilationResult::relaxations`]. When
+    /// strict, compilation fails with an [`Error::Strict`] listing them. See
+    /// [`conformance`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().strict(true);
+    /// ```
+    pub fn strict(mut self, enable: bool) -> Self {
+        self.strict = enable;
+        self
+    }
+
+    /// Enables or disables symbolic loops in the DAE.
+    ///
+    /// By default, for-equations are expanded to one scalar equation per
+    /// iteration. With symbolic loops, for-equations whose equations stay in
+    /// iteration order after sorting are kept in [`Dae::fx`] as
+    /// [`Equation::For`](crate::ir::ast::Equation::For) with literal ranges,
+    /// for backends that generate loops, e.g. for discretized PDEs with
+    /// thousands of identical equations. See [`crate::dae::loops`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().symbolic_loops(true);
+    /// ```
+    pub fn symbolic_loops(mut self, enable: bool) -> Self {
+        self.symbolic_loops = enable;
+        self
+    }
+
+    /// Sets the order of the continuous-time equations of the DAE.
+    ///
+    /// By default ([`SortMode::Blt`]), the equations are sorted into blocks
+    /// and solved for their unknowns where possible. With [`SortMode::None`],
+    /// [`Dae::fx`] keeps the flattened equations as declared, e.g. for
+    /// symbolic analysis or acausal export. The balance and the structural
+    /// checks are the same in both modes, and templates see the mode as
+    /// `dae.sort`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::{Compiler, SortMode};
+    ///
+    /// let compiler = Compiler::new().sort(SortMode::None);
+    /// ```
+    pub fn sort(mut self, mode: SortMode) -> Self {
+        self.sort = mode;
+        self
+    }
+
+    /// Fails the compilation of a model with an [`Error::Limit`] when it takes
+    /// longer than `timeout`.
+    ///
+    /// The deadline is checked while flattening and expanding equations and
+    /// between the stages of the pipeline, so the compilation stops soon after
+    /// it. Parsing is not limited. See [`limits`] for the other limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().timeout(Duration::from_secs(10));
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.limits.timeout = Some(timeout);
+        self
+    }
+
+    /// Fails the compilation of a model with an [`Error::Limit`] when an
+    /// array, a for-equation or an array equation expands to more than `size`
+    /// elements (default: [`limits::DEFAULT_MAX_EXPANSION_SIZE`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().max_expansion_size(10_000);
+    /// ```
+    pub fn max_expansion_size(mut self, size: usize) -> Self {
+        self.limits.max_expansion_size = size;
+        self
+    }
+
+    /// Fails the compilation of a model when its components are nested more
+    /// than `depth` levels deep (default: [`limits::DEFAULT_MAX_INSTANCE_DEPTH`]).
+    ///
+    /// Recursive instantiation is reported as soon as a class contains itself,
+    /// the limit is a safety net for unreasonably deep hierarchies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().max_instance_depth(20);
+    /// ```
+    pub fn max_instance_depth(mut self, depth: usize) -> Self {
+        self.limits.max_instance_depth = depth;
+        self
+    }
+
+    /// Selects the variables to export as outputs.
+    ///
+    /// The selection is listed in [`Dae::outputs`], together with the
+    /// variables annotated with `__rumoca_output = true`, and the algebraic
+    /// variables no output depends on are removed from the DAE. A name
+    /// selects all elements of an array and all variables of a component.
+    /// Compilation fails with an [`Error::Type`] for names that aren't
+    /// variables. See [`crate::dae::outputs`]. Can be called multiple times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().outputs(&["y1", "y2"]);
+    /// ```
+    pub fn outputs(mut self, names: &[&str]) -> Self {
+        self.outputs
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Fails the compilation with an [`Error::Denied`] when it produces
+    /// warnings with any of the given codes.
+    ///
+    /// Other warnings are only reported in
+    /// [`CompilationResult::warnings`]. Can be called multiple times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::{Compiler, DiagnosticCode};
+    ///
+    /// let compiler = Compiler::new().deny(&[
+    ///     DiagnosticCode::UnusedVariable,
+    ///     DiagnosticCode::Unbalanced,
+    /// ]);
+    /// ```
+    pub fn deny(mut self, codes: &[DiagnosticCode]) -> Self {
+        for code in codes {
+            if !self.deny.contains(code) {
+                self.deny.push(*code);
+            }
+        }
+        self
+    }
+
+    /// Sets a custom text for the header of generated code, e.g. a license
+    /// notice.
+    ///
+    /// The text comes before the provenance lines in
+    /// [`Provenance::header`], which templates get as `provenance.header`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().license_header("Copyright (c) Example Corp.");
+    /// ```
+    pub fn license_header(mut self, text: &str) -> Self {
+        self.license_header = text.to_string();
+        self
+    }
+
+    /// Registers a custom pass over the flattened class.
+    ///
+    /// The pass runs after imports are resolved and before variable references
+    /// are validated, so components and equations it adds are checked and
+    /// expanded like the model's own. Passes run in the order they were
+    /// registered, see [`passes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().flattened_pass("no-asserts", |class| {
+    ///     class.equations.retain(|eq| !matches!(
+    ///         eq,
+    ///         rumoca::ir::ast::Equation::FunctionCall { comp, .. } if comp.to_string() == "assert"
+    ///     ));
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn flattened_pass<F>(mut self, name: &str, pass: F) -> Self
+    where
+        F: Fn(&mut ClassDefinition) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.passes.flattened(name, pass);
+        self
+    }
+
+    /// Registers a custom pass over the flattened class after its equations
+    /// were expanded to scalar form, right before the DAE is created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().expanded_pass("count", |class| {
+    ///     eprintln!("{} scalar equations", class.equations.len());
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn expanded_pass<F>(mut self, name: &str, pass: F) -> Self
+    where
+        F: Fn(&mut ClassDefinition) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.passes.expanded(name, pass);
+        self
+    }
+
+    /// Registers a custom pass over the DAE, which runs before its balance is
+    /// checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rumoca::Compiler;
+    ///
+    /// let compiler = Compiler::new().dae_pass("require-states", |dae| {
+    ///     anyhow::ensure!(!dae.x.is_empty(), "the model has no states");
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn dae_pass<F>(mut self, name: &str, pass: F) -> Self
+    where
+        F: Fn(&mut Dae) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.passes.dae(name, pass);
+        self
+    }
+
+    /// Apply post-compilation options to a compilation result, and record the
+    /// sources it was compiled from
+    fn finish(
+        &self,
+        result: Result<CompilationResult>,
+        source: SourceFile,
+        libraries: Vec<SourceFile>,
+        source_warnings: Vec<CompileWarning>,
+    ) -> Result<CompilationResult> {
+        let mut result = result?;
+        let resolver = self.resource_resolver(&source, &libraries);
+        let (resources, unresolved) = resolver.resolve_dae(&mut result.dae);
+        result.resources = resources;
+        result.provenance = Provenance::new(&result.dae, source, libraries, &self.license_header);
+        if self.strict && !result.relaxations.is_empty() {
+            return Err(Error::Strict(result.relaxations));
+        }
+        if !self.permissive && !result.dae.singular.is_empty() {
+            let message: Vec<String> = result.dae.singular.iter().map(|s| s.to_string()).collect();
+            return Err(Error::Balance(message.join("\n")));
+        }
+        result.warnings = source_warnings;
+        result
+            .warnings
+            .extend(diagnostics::collect_warnings(&result));
+        result
+            .warnings
+            .extend(unresolved.into_iter().map(|(error, loc)| CompileWarning {
+                code: DiagnosticCode::MissingResource,
+                message: format!("{}: {}", loc.file_position(), error),
+            }));
+        let denied: Vec<CompileWarning> = result
+            .warnings
+            .iter()
+            .filter(|warning| self.deny.contains(&warning.code))
+            .cloned()
+            .collect();
+        if !denied.is_empty() {
+            return Err(Error::Denied(denied));
+        }
+        result.dae.select_outputs(&self.outputs)?;
+        if self.guard_divisions {
+            result.dae.add_division_guards();
+        }
+        Ok(result)
+    }
+
+    /// Resolver of the `modelica://` URIs of a model, looking up libraries in
+    /// the library paths and the packages the model was compiled from
+    fn resource_resolver(&self, source: &SourceFile, libraries: &[SourceFile]) -> ResourceResolver {
+        use crate::ir::transform::multi_file::get_modelica_path;
+
+        let search_paths = if self.modelica_paths.is_empty() {
+            get_modelica_path()
+        } else {
+            self.modelica_paths
+                .iter()
+                .filter_map(|path| builtin::resolve_library_path(&path.to_string_lossy()).ok())
+                .collect()
+        };
+        let mut resolver = ResourceResolver::new(search_paths);
+        for file in std::iter::once(source).chain(libraries) {
+            resolver.add_file(Path::new(&file.file));
+        }
+        resolver
+    }
+
     /// Adds an additional source file to include in compilation.
     ///
     /// Use this to include library files, package definitions, or other
@@ -376,7 +820,7 @@ impl Compiler {
     ///     .include("library/utils.mo")
     ///     .include("library/types.mo")
     ///     .compile_file("model.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn include(mut self, path: &str) -> Self {
         let path_buf = PathBuf::from(path);
@@ -397,7 +841,7 @@ impl Compiler {
     ///     .model("MyModel")
     ///     .include_all(&["lib1.mo", "lib2.mo"])
     ///     .compile_file("model.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn include_all(mut self, paths: &[&str]) -> Self {
         for path in paths {
@@ -423,13 +867,14 @@ impl Compiler {
     ///     .model("MyPackage.MyModel")
     ///     .include_package("path/to/MyPackage")?
     ///     .compile_file("model.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn include_package(mut self, path: &str) -> Result<Self> {
         use crate::ir::transform::multi_file::discover_modelica_files;
 
         let package_path = std::path::Path::new(path);
-        let files = discover_modelica_files(package_path)?;
+        let files =
+            discover_modelica_files(package_path).map_err(|e| Error::Package(describe(e)))?;
 
         for file in files {
             // Canonicalize to detect duplicates
@@ -466,7 +911,7 @@ impl Compiler {
     ///     .model("Modelica.Mechanics.Rotational.Examples.First")
     ///     .include_from_modelica_path("Modelica")?
     ///     .compile_file("model.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn include_from_modelica_path(self, package_name: &str) -> Result<Self> {
         use crate::ir::transform::multi_file::{find_package_in_paths, get_modelica_path};
@@ -475,15 +920,17 @@ impl Compiler {
         let search_paths = if self.modelica_paths.is_empty() {
             get_modelica_path()
         } else {
-            self.modelica_paths.clone()
+            self.modelica_paths
+                .iter()
+                .map(|path| builtin::resolve_library_path(&path.to_string_lossy()))
+                .collect::<Result<_>>()?
         };
 
         let package_path = find_package_in_paths(package_name, &search_paths).ok_or_else(|| {
-            anyhow::anyhow!(
+            Error::Package(format!(
                 "Package '{}' not found in library paths: {:?}",
-                package_name,
-                search_paths
-            )
+                package_name, search_paths
+            ))
         })?;
 
         self.include_package(&package_path.to_string_lossy())
@@ -502,17 +949,21 @@ impl Compiler {
     /// let result = Compiler::new()
     ///     .model("MyPackage.MyModel")
     ///     .compile_package("path/to/MyPackage")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     #[cfg(not(target_arch = "wasm32"))]
     pub fn compile_package(&self, path: &str) -> Result<CompilationResult> {
         use crate::ir::transform::multi_file::discover_modelica_files;
 
         let package_path = std::path::Path::new(path);
-        let files = discover_modelica_files(package_path)?;
+        let files =
+            discover_modelica_files(package_path).map_err(|e| Error::Package(describe(e)))?;
 
         if files.is_empty() {
-            anyhow::bail!("No Modelica files found in package: {}", path);
+            return Err(Error::Package(format!(
+                "No Modelica files found in package: {}",
+                path
+            )));
         }
 
         let file_strs: Vec<&str> = files.iter().map(|p| p.to_str().unwrap()).collect();
@@ -551,7 +1002,7 @@ impl Compiler {
     ///     .model("MyModel")
     ///     .compile_file("model.mo")?;
     /// println!("Model has {} states", result.dae.x.len());
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     #[cfg(not(target_arch = "wasm32"))]
     pub fn compile_file(&self, path: &str) -> Result<CompilationResult> {
@@ -562,13 +1013,13 @@ impl Compiler {
         let pool = rayon::ThreadPoolBuilder::new()
             .num_threads(thread_count)
             .build()
-            .with_context(|| "Failed to create thread pool")?;
+            .map_err(thread_pool_error)?;
 
         let cache_hits = AtomicUsize::new(0);
         let cache_misses = AtomicUsize::new(0);
 
         if self.verbose {
-            println!(
+            eprintln!(
                 "Parsing {} files using {} threads (cache: {})...",
                 self.additional_files.len() + 1,
                 thread_count,
@@ -600,13 +1051,12 @@ impl Compiler {
                             cache::load_cached_ast(additional_path, &file_hash)
                     {
                         cache_hits.fetch_add(1, Ordering::Relaxed);
-                        return Ok((path_str, cached_def, file_hash));
+                        return Ok((path_str, cached_def, file_hash, None));
                     }
 
                     // Cache miss - parse the file
                     cache_misses.fetch_add(1, Ordering::Relaxed);
-                    let additional_source = fs::read_to_string(additional_path)
-                        .with_context(|| format!("Failed to read file: {}", path_str))?;
+                    let (additional_source, warning) = read_source_with_warning(additional_path)?;
                     let def = self.parse_source(&additional_source, &path_str)?;
 
                     // Store in cache
@@ -614,7 +1064,14 @@ impl Compiler {
                         let _ = cache::store_cached_ast(additional_path, &file_hash, &def);
                     }
 
-                    Ok((path_str, def, file_hash))
+                    // Without the cache feature the file isn't hashed
+                    let file_hash = if file_hash.is_empty() || file_hash == "unknown" {
+                        source_md5(&additional_source)
+                    } else {
+                        file_hash
+                    };
+
+                    Ok((path_str, def, file_hash, warning))
                 })
                 .collect()
         });
@@ -622,7 +1079,7 @@ impl Compiler {
         let additional_results = parsed_additional?;
 
         if self.verbose {
-            println!(
+            eprintln!(
                 "Cache: {} hits, {} misses",
                 cache_hits.load(Ordering::Relaxed),
                 cache_misses.load(Ordering::Relaxed)
@@ -630,25 +1087,35 @@ impl Compiler {
         }
 
         // Parse main file (not cached - it's the user's code that changes frequently)
-        let input =
-            fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path))?;
-        let main_hash = format!("{:x}", chksum_md5::hash(&input));
+        let (input, main_warning) = read_source_with_warning(Path::new(path))?;
+        let main_hash = source_md5(&input);
 
         let main_def = self.parse_source(&input, path)?;
 
-        // Collect all definitions and hashes
+        // Collect all definitions, hashes and encoding warnings
         let mut all_definitions: Vec<(String, StoredDefinition)> = additional_results
             .iter()
-            .map(|(p, d, _)| (p.clone(), d.clone()))
+            .map(|(p, d, _, _)| (p.clone(), d.clone()))
             .collect();
         all_definitions.push((path.to_string(), main_def));
 
-        let mut all_hashes: Vec<String> =
-            additional_results.into_iter().map(|(_, _, h)| h).collect();
+        let mut all_hashes = Vec::with_capacity(all_definitions.len());
+        let mut warnings = Vec::new();
+        for (_, _, hash, warning) in additional_results {
+            all_hashes.push(hash);
+            warnings.extend(warning);
+        }
         all_hashes.push(main_hash);
+        warnings.extend(main_warning);
 
         // Compile with all definitions and hashes for flat class caching
-        self.compile_definitions_with_hashes(all_definitions, &input, path, Some(all_hashes))
+        self.compile_definitions_with_hashes(
+            all_definitions,
+            &input,
+            path,
+            Some(all_hashes),
+            warnings,
+        )
     }
 
     /// Compiles multiple Modelica files together.
@@ -672,12 +1139,14 @@ impl Compiler {
     /// let result = Compiler::new()
     ///     .model("MyPackage.MyModel")
     ///     .compile_files(&["library.mo", "model.mo"])?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     #[cfg(not(target_arch = "wasm32"))]
     pub fn compile_files(&self, paths: &[&str]) -> Result<CompilationResult> {
         if paths.is_empty() {
-            anyhow::bail!("At least one file must be provided");
+            return Err(Error::Other(
+                "At least one file must be provided".to_string(),
+            ));
         }
 
         // Configure thread pool
@@ -685,10 +1154,10 @@ impl Compiler {
         let pool = rayon::ThreadPoolBuilder::new()
             .num_threads(thread_count)
             .build()
-            .with_context(|| "Failed to create thread pool")?;
+            .map_err(thread_pool_error)?;
 
         if self.verbose {
-            println!(
+            eprintln!(
                 "Parsing {} files using {} threads...",
                 paths.len(),
                 thread_count
@@ -700,10 +1169,9 @@ impl Compiler {
             paths
                 .par_iter()
                 .map(|path| {
-                    let source = fs::read_to_string(path)
-                        .with_context(|| format!("Failed to read file: {}", path))?;
+                    let (source, warning) = read_source_with_warning(Path::new(path))?;
                     let def = self.parse_source(&source, path)?;
-                    Ok((path.to_string(), def, source))
+                    Ok((path.to_string(), def, source, warning))
                 })
                 .collect()
         });
@@ -713,78 +1181,182 @@ impl Compiler {
         // Separate definitions and sources
         let all_definitions: Vec<_> = results
             .iter()
-            .map(|(path, def, _)| (path.clone(), def.clone()))
+            .map(|(path, def, _, _)| (path.clone(), def.clone()))
+            .collect();
+        let hashes = results
+            .iter()
+            .map(|(_, _, source, _)| source_md5(source))
+            .collect();
+        let warnings = results
+            .iter()
+            .filter_map(|(_, _, _, warning)| warning.clone())
             .collect();
         let main_source = &results.last().unwrap().2;
         let main_path = &results.last().unwrap().0;
 
-        self.compile_definitions(all_definitions, main_source, main_path)
+        self.compile_definitions_with_hashes(
+            all_definitions,
+            main_source,
+            main_path,
+            Some(hashes),
+            warnings,
+        )
     }
 
     /// Parse a source file and return the StoredDefinition
     fn parse_source(&self, source: &str, file_name: &str) -> Result<StoredDefinition> {
         let mut grammar = ModelicaGrammar::new();
         if let Err(e) = parse(source, file_name, &mut grammar) {
-            let diagnostic = create_syntax_error(&e, source);
-            let report = miette::Report::new(diagnostic);
-            return Err(anyhow::anyhow!("{:?}", report));
+            return Err(Error::parse(&e, source, file_name));
         }
 
         grammar.modelica.ok_or_else(|| {
-            anyhow::anyhow!("Parser succeeded but produced no AST for {}", file_name)
+            Error::Other(format!(
+                "Parser succeeded but produced no AST for {}",
+                file_name
+            ))
         })
     }
 
     /// Compile from pre-parsed definitions (public for WASM caching)
+    ///
+    /// The main source is the last definition. The hashes of the other
+    /// definitions aren't known, so they are left out of the provenance.
     pub fn compile_definitions(
         &self,
         definitions: Vec<(String, StoredDefinition)>,
         main_source: &str,
-        _main_file_name: &str,
+        main_file_name: &str,
     ) -> Result<CompilationResult> {
-        self.compile_definitions_with_hashes(definitions, main_source, _main_file_name, None)
+        self.compile_definitions_with_hashes(
+            definitions,
+            main_source,
+            main_file_name,
+            None,
+            Vec::new(),
+        )
     }
 
-    /// Compile from pre-parsed definitions with optional source hashes for flat class caching
+    /// Compile from pre-parsed definitions with optional source hashes (one
+    /// per definition) for the provenance, and the warnings reading the
+    /// sources
     fn compile_definitions_with_hashes(
         &self,
         definitions: Vec<(String, StoredDefinition)>,
         main_source: &str,
-        _main_file_name: &str,
-        _source_hashes: Option<Vec<String>>,
+        main_file_name: &str,
+        source_hashes: Option<Vec<String>>,
+        source_warnings: Vec<CompileWarning>,
     ) -> Result<CompilationResult> {
-        use crate::ir::transform::multi_file::merge_stored_definitions;
-
         let start = Instant::now();
+        let hashes = source_hashes.unwrap_or_default();
+        let libraries: Vec<SourceFile> = definitions
+            .iter()
+            .take(definitions.len().saturating_sub(1))
+            .enumerate()
+            .map(|(i, (file, _))| {
+                SourceFile::new(file, hashes.get(i).map(String::as_str).unwrap_or_default())
+            })
+            .collect();
+        let def = self.merge_definitions(definitions)?;
 
-        // Merge all definitions
-        let def = if definitions.len() == 1 {
-            definitions.into_iter().next().unwrap().1
-        } else {
-            if self.verbose {
-                println!("Merging {} files...", definitions.len());
-            }
-            merge_stored_definitions(definitions)?
-        };
-
-        let model_hash = format!("{:x}", chksum_md5::hash(main_source));
+        let model_hash = source_md5(main_source);
+        let source = SourceFile::new(main_file_name, &model_hash);
         let parse_time = start.elapsed();
 
         if self.verbose {
-            println!("Parsing took {} ms", parse_time.as_millis());
-            println!("AST:\n{:#?}\n", def);
+            eprintln!("Parsing took {} ms", parse_time.as_millis());
+            eprintln!("AST:\n{:#?}\n", def);
         }
 
         // Run the compilation pipeline
-        pipeline::compile_from_ast_ref(
-            &def,
-            self.model_name.as_deref(),
-            model_hash,
-            parse_time,
-            self.verbose,
+        self.finish(
+            limits::with_timeout(self.limits.timeout, || {
+                pipeline::compile_from_ast_ref(
+                    &def,
+                    self.model_name.as_deref(),
+                    model_hash,
+                    parse_time,
+                    &self.passes,
+                    self.dae_options(),
+                    self.verbose,
+                )
+            }),
+            source,
+            libraries,
+            source_warnings,
         )
     }
 
+    /// Options of the DAE for the compilation pipeline
+    fn dae_options(&self) -> pipeline::DaeOptions {
+        pipeline::DaeOptions {
+            symbolic_loops: self.symbolic_loops,
+            sort: self.sort,
+            limits: self.limits,
+        }
+    }
+
+    /// Merge parsed definitions, packages after the packages they depend on
+    fn merge_definitions(
+        &self,
+        definitions: Vec<(String, StoredDefinition)>,
+    ) -> Result<StoredDefinition> {
+        use crate::ir::analysis::dependency_graph::DependencyGraph;
+        use crate::ir::transform::multi_file::merge_stored_definitions;
+
+        if definitions.len() == 1 {
+            return Ok(definitions.into_iter().next().unwrap().1);
+        }
+        if self.verbose {
+            eprintln!("Merging {} files...", definitions.len());
+        }
+        let graph = DependencyGraph::from_definitions(&definitions);
+        if self.verbose {
+            for cycle in graph.cycles() {
+                eprintln!("Cyclic package dependency: {}", cycle.join(" -> "));
+            }
+        }
+        merge_stored_definitions(graph.order_definitions(definitions))
+            .map_err(|e| Error::Flatten(describe(e)))
+    }
+
+    /// Builds the inter-package dependency graph of the main source and all
+    /// included files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rumoca::Compiler;
+    ///
+    /// let source = std::fs::read_to_string("model.mo")?;
+    /// let graph = Compiler::new()
+    ///     .include_package("path/to/MyLibrary")?
+    ///     .dependency_graph(&source, "model.mo")?;
+    /// println!("{}", graph.to_dot());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn dependency_graph(
+        &self,
+        source: &str,
+        file_name: &str,
+    ) -> Result<crate::ir::analysis::dependency_graph::DependencyGraph> {
+        let mut definitions = Vec::with_capacity(self.additional_files.len() + 1);
+        for path in &self.additional_files {
+            let def = if self.use_cache {
+                parse_file_cached_result(path)?
+            } else {
+                let text = read_source(path)?;
+                self.parse_source(&text, &path.to_string_lossy())?
+            };
+            definitions.push((path.to_string_lossy().to_string(), def));
+        }
+        definitions.push((file_name.to_string(), self.parse_source(source, file_name)?));
+
+        Ok(crate::ir::analysis::dependency_graph::DependencyGraph::from_definitions(&definitions))
+    }
+
     /// Compiles Modelica source code from a string to a DAE representation.
     ///
     /// This method performs the full compilation pipeline on the provided source code.
@@ -813,15 +1385,103 @@ impl Compiler {
     /// let result = Compiler::new()
     ///     .model("Test")
     ///     .compile_str(code, "test.mo")?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn compile_str(&self, source: &str, file_name: &str) -> Result<CompilationResult> {
+        let (all_definitions, hashes, warnings) = self.parse_with_includes(source, file_name)?;
+        self.compile_definitions_with_hashes(
+            all_definitions,
+            source,
+            file_name,
+            Some(hashes),
+            warnings,
+        )
+    }
+
+    /// Compiles several models of the same source and returns the balance of
+    /// each, or the error compiling it.
+    ///
+    /// The source and included files are parsed and merged once, and classes
+    /// used by several models are resolved once, which is much faster than
+    /// calling [`compile_str`](Self::compile_str) for each model. The model set
+    /// with [`model`](Self::model) is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source or an included file fails to parse.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rumoca::Compiler;
+    ///
+    /// let code = "package P\n  model A\n    Real x;\n  equation\n    der(x) = 1;\n  end A;\nend P;";
+    /// let balances = Compiler::new().compile_balances(code, "p.mo", &["P.A"])?;
+    /// assert!(balances[0].as_ref().unwrap().is_balanced);
+    /// # Ok::<(), rumoca::Error>(())
+    /// ```
+    pub fn compile_balances(
+        &self,
+        source: &str,
+        file_name: &str,
+        models: &[&str],
+    ) -> Result<Vec<Result<crate::dae::balance::BalanceResult>>> {
+        Ok(self
+            .compile_checks(source, file_name, models)?
+            .into_iter()
+            .map(|check| check.map(|check| check.balance))
+            .collect())
+    }
+
+    /// Compiles several models of the same source like
+    /// [`compile_balances`](Self::compile_balances), and returns the balance and
+    /// the instance checks (unused parameters, unconnected connectors) of each.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source or an included file fails to parse.
+    pub fn compile_checks(
+        &self,
+        source: &str,
+        file_name: &str,
+        models: &[&str],
+    ) -> Result<Vec<Result<pipeline::ModelCheck>>> {
+        let (all_definitions, _, _) = self.parse_with_includes(source, file_name)?;
+        let def = self.merge_definitions(all_definitions)?;
+        let model_hash = source_md5(source);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.get_thread_count())
+                .build()
+                .map_err(thread_pool_error)?;
+            Ok(pool.install(|| {
+                pipeline::compile_checks(&def, models, &model_hash, &self.passes, &self.limits)
+            }))
+        }
+        #[cfg(target_arch = "wasm32")]
+        Ok(pipeline::compile_checks(
+            &def,
+            models,
+            &model_hash,
+            &self.passes,
+            &self.limits,
+        ))
+    }
+
+    /// Parse the main source and all included files, returning the
+    /// definitions with the MD5 hash of each file
+    fn parse_with_includes(&self, source: &str, file_name: &str) -> Result<ParsedSources> {
         // WASM: No filesystem access, so no additional files - just parse main source
         #[cfg(target_arch = "wasm32")]
         {
             let def = self.parse_source(source, file_name)?;
-            let all_definitions = vec![(file_name.to_string(), def)];
-            return self.compile_definitions(all_definitions, source, file_name);
+            return Ok((
+                vec![(file_name.to_string(), def)],
+                vec![source_md5(source)],
+                Vec::new(),
+            ));
         }
 
         // Native: Full parallel processing with thread pool
@@ -834,13 +1494,13 @@ impl Compiler {
             let pool = rayon::ThreadPoolBuilder::new()
                 .num_threads(thread_count)
                 .build()
-                .with_context(|| "Failed to create thread pool")?;
+                .map_err(thread_pool_error)?;
 
             let cache_hits = AtomicUsize::new(0);
             let cache_misses = AtomicUsize::new(0);
 
             if self.verbose && !self.additional_files.is_empty() {
-                println!(
+                eprintln!(
                     "Parsing {} additional files using {} threads (cache: {})...",
                     self.additional_files.len(),
                     thread_count,
@@ -860,36 +1520,49 @@ impl Compiler {
                     .par_iter()
                     .map(|additional_path| {
                         let path_str = additional_path.to_string_lossy().to_string();
+                        let file_hash = if use_cache {
+                            cache::compute_file_hash(additional_path).unwrap_or_default()
+                        } else {
+                            String::new()
+                        };
 
                         // Try cache first
-                        if use_cache
-                            && let Ok(hash) = cache::compute_file_hash(additional_path)
-                            && let Some(cached_def) = cache::load_cached_ast(additional_path, &hash)
+                        if !file_hash.is_empty()
+                            && let Some(cached_def) =
+                                cache::load_cached_ast(additional_path, &file_hash)
                         {
                             cache_hits.fetch_add(1, Ordering::Relaxed);
-                            return Ok((path_str, cached_def));
+                            return Ok((path_str, cached_def, file_hash, None));
                         }
 
                         // Cache miss - parse the file
                         cache_misses.fetch_add(1, Ordering::Relaxed);
-                        let additional_source = fs::read_to_string(additional_path)
-                            .with_context(|| format!("Failed to read file: {}", path_str))?;
+                        let (additional_source, warning) =
+                            read_source_with_warning(additional_path)?;
                         let def = self.parse_source(&additional_source, &path_str)?;
 
                         // Store in cache
-                        if use_cache && let Ok(hash) = cache::compute_file_hash(additional_path) {
-                            let _ = cache::store_cached_ast(additional_path, &hash, &def);
+                        if !file_hash.is_empty() {
+                            let _ = cache::store_cached_ast(additional_path, &file_hash, &def);
+                            return Ok((path_str, def, file_hash, warning));
                         }
 
-                        Ok((path_str, def))
+                        Ok((path_str, def, source_md5(&additional_source), warning))
                     })
                     .collect()
             });
 
-            let mut all_definitions = parsed_additional?;
+            let mut all_definitions = Vec::with_capacity(additional_paths.len() + 1);
+            let mut hashes = Vec::with_capacity(additional_paths.len() + 1);
+            let mut warnings = Vec::new();
+            for (path, def, hash, warning) in parsed_additional? {
+                all_definitions.push((path, def));
+                hashes.push(hash);
+                warnings.extend(warning);
+            }
 
             if self.verbose && !self.additional_files.is_empty() {
-                println!(
+                eprintln!(
                     "Cache: {} hits, {} misses",
                     cache_hits.load(Ordering::Relaxed),
                     cache_misses.load(Ordering::Relaxed)
@@ -899,9 +1572,9 @@ impl Compiler {
             // Parse main source
             let def = self.parse_source(source, file_name)?;
             all_definitions.push((file_name.to_string(), def));
+            hashes.push(source_md5(source));
 
-            // Compile with all definitions
-            self.compile_definitions(all_definitions, source, file_name)
+            Ok((all_definitions, hashes, warnings))
         }
     }
 
@@ -937,16 +1610,26 @@ impl Compiler {
     /// let result = Compiler::new()
     ///     .model("Test")
     ///     .compile_parsed(def.clone(), source)?;
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn compile_parsed(&self, def: StoredDefinition, source: &str) -> Result<CompilationResult> {
-        let model_hash = format!("{:x}", chksum_md5::hash(source));
-        pipeline::compile_from_ast(
-            def,
-            self.model_name.as_deref(),
-            model_hash,
-            std::time::Duration::ZERO, // No parse time for pre-parsed
-            self.verbose,
+        let model_hash = source_md5(source);
+        let source = SourceFile::new("", &model_hash);
+        self.finish(
+            limits::with_timeout(self.limits.timeout, || {
+                pipeline::compile_from_ast(
+                    def,
+                    self.model_name.as_deref(),
+                    model_hash,
+                    std::time::Duration::ZERO, // No parse time for pre-parsed
+                    &self.passes,
+                    self.dae_options(),
+                    self.verbose,
+                )
+            }),
+            source,
+            Vec::new(),
+            Vec::new(),
         )
     }
 
@@ -960,13 +1643,23 @@ impl Compiler {
         def: &StoredDefinition,
         source: &str,
     ) -> Result<CompilationResult> {
-        let model_hash = format!("{:x}", chksum_md5::hash(source));
-        pipeline::compile_from_ast_ref(
-            def,
-            self.model_name.as_deref(),
-            model_hash,
-            std::time::Duration::ZERO,
-            self.verbose,
+        let model_hash = source_md5(source);
+        let source = SourceFile::new("", &model_hash);
+        self.finish(
+            limits::with_timeout(self.limits.timeout, || {
+                pipeline::compile_from_ast_ref(
+                    def,
+                    self.model_name.as_deref(),
+                    model_hash,
+                    std::time::Duration::ZERO,
+                    &self.passes,
+                    self.dae_options(),
+                    self.verbose,
+                )
+            }),
+            source,
+            Vec::new(),
+            Vec::new(),
         )
     }
 
@@ -974,11 +1667,12 @@ impl Compiler {
     ///
     /// This is much faster than full compilation when you only need to check
     /// if a model is balanced. It skips the StoredDefinition cloning entirely.
+    /// Custom passes are not run.
     pub fn check_balance(
         &self,
         def: &StoredDefinition,
     ) -> Result<crate::dae::balance::BalanceResult> {
-        pipeline::check_balance_only(def, self.model_name.as_deref())
+        pipeline::check_balance_only(def, self.model_name.as_deref(), &self.limits)
     }
 
     /// Compile with additional library sources provided as strings.
@@ -1006,19 +1700,46 @@ impl Compiler {
 
         // Parse all library sources
         let mut all_definitions = Vec::with_capacity(libraries.len() + 1);
+        let mut hashes = Vec::with_capacity(libraries.len() + 1);
 
         for (lib_name, lib_source) in libraries {
             let lib_def = self.parse_source(lib_source, lib_name)?;
             all_definitions.push((lib_name.to_string(), lib_def));
+            hashes.push(source_md5(lib_source));
         }
 
         // Add main source last (so its classes take precedence)
         all_definitions.push((file_name.to_string(), main_def));
-
-        self.compile_definitions(all_definitions, source, file_name)
+        hashes.push(source_md5(source));
+
+        self.compile_definitions_with_hashes(
+            all_definitions,
+            source,
+            file_name,
+            Some(hashes),
+            Vec::new(),
+        )
     }
 }
 
+/// Parsed definitions (file name and AST) with the MD5 hash of each file
+type ParsedSources = (
+    Vec<(String, StoredDefinition)>,
+    Vec<String>,
+    Vec<CompileWarning>,
+);
+
+/// MD5 hash of a source, as recorded in [`Dae::model_hash`](crate::dae::ast::Dae::model_hash)
+fn source_md5(source: &str) -> String {
+    format!("{:x}", chksum_md5::hash(source))
+}
+
+/// Create an [`Error::Other`] for a thread pool that could not be created
+#[cfg(not(target_arch = "wasm32"))]
+fn thread_pool_error(e: rayon::ThreadPoolBuildError) -> Error {
+    Error::Other(format!("Failed to create thread pool: {}", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1096,4 +1817,59 @@ end Test;
             result.parse_time + result.flatten_time + result.dae_time
         );
     }
+
+    #[test]
+    fn test_provenance() {
+        use std::io::Write;
+
+        let library = "package Lib\n  constant Real k = 2;\nend Lib;\n";
+        let source = "model Test\n  Real x;\nequation\n  der(x) = Lib.k;\nend Test;\n";
+        let mut result = Compiler::new()
+            .model("Test")
+            .license_header("Copyright (c) Example\nAll rights reserved.\n")
+            .compile_str_with_sources(source, "test.mo", vec![("lib.mo", library)])
+            .unwrap();
+
+        let provenance = &result.provenance;
+        assert_eq!(provenance.model, "Test");
+        assert_eq!(provenance.source.file, "test.mo");
+        assert_eq!(provenance.source.md5, result.model_hash);
+        assert_eq!(provenance.libraries.len(), 1);
+        assert_eq!(provenance.libraries[0].md5, source_md5(library));
+        assert!(!provenance.libraries_md5.is_empty());
+        assert_eq!(provenance.compiled_at.len(), "2024-01-31T12:00:00Z".len());
+
+        let lines: Vec<&str> = provenance.header.lines().collect();
+        assert_eq!(
+            lines[..3],
+            ["Copyright (c) Example", "All rights reserved.", ""]
+        );
+        assert_eq!(lines[3], "Model: Test");
+        assert!(lines[4].starts_with("Generated by: rumoca "));
+        assert_eq!(
+            lines[6],
+            format!("Source: test.mo (md5 {})", result.model_hash)
+        );
+        assert_eq!(
+            lines[7],
+            format!("Libraries: 1 file (md5 {})", provenance.libraries_md5)
+        );
+        assert!(
+            provenance
+                .stamp("#")
+                .starts_with("# Copyright (c) Example\n# All rights reserved.\n#\n# Model")
+        );
+
+        let mut template = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            template,
+            "{{{{ provenance.header | comment(\"//\") }}}}\n{{{{ provenance.model }}}}"
+        )
+        .unwrap();
+        let output = result
+            .render_template_to_string(template.path().to_str().unwrap())
+            .unwrap();
+        assert!(output.starts_with("// Copyright (c) Example\n"));
+        assert!(output.ends_with("\nTest"));
+    }
 }
@@ -0,0 +1,983 @@
+//! Outline parsing: class and component signatures without class bodies.
+//!
+//! Building the full AST of a very large library file takes long and a lot of
+//! memory, while indexing it only needs the names, kinds and locations of its
+//! classes and components. [`OutlineParser`] scans the tokens of a source text
+//! and reports its outline as a stream of [`OutlineEvent`]s, skipping over
+//! equations, algorithms, modifications and annotations.
+//!
+//! [`parse_outline`] collects the events into a [`StoredDefinition`] whose
+//! classes have no equations or algorithms, and [`parse_class`] fully parses a
+//! single class of it when its body is needed.
+//!
+//! The scan is lenient: it never fails, and source that doesn't parse gives a
+//! best-effort outline. Locations are the same as those of the full parser.
+//!
+//! # Example
+//!
+//! ```
+//! use rumoca::compiler::outline::{parse_class, parse_outline};
+//!
+//! let source = "package P\n  model M\n    Real x;\n  equation\n    der(x) = 1;\n  end M;\nend P;\n";
+//! let outline = parse_outline(source, "p.mo");
+//! let m = &outline.class_list["P"].classes["M"];
+//! assert!(m.components.contains_key("x"));
+//! assert!(m.equations.is_empty());
+//!
+//! let m = parse_class(source, "p.mo", m)?;
+//! assert_eq!(m.equations.len(), 1);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use anyhow::Result;
+
+use super::paths::base_name;
+use super::source::normalize_source;
+use crate::ir::ast::{
+    Causality, ClassDefinition, ClassType, Component, Connection, Extend, Location, Name,
+    ShortClassSpecifier, StoredDefinition, Token, Variability,
+};
+use crate::modelica_grammar::cst::{SyntaxKind, Tokens, lex_tokens};
+
+/// Keywords that can start a class definition (prefixes and class kinds)
+const CLASS_KEYWORDS: &[&str] = &[
+    "encapsulated",
+    "partial",
+    "expandable",
+    "pure",
+    "impure",
+    "operator",
+    "class",
+    "model",
+    "record",
+    "block",
+    "connector",
+    "type",
+    "package",
+    "function",
+];
+
+/// Class prefixes that can precede the class keyword at the top level of a file
+const TOP_LEVEL_PREFIXES: &[&str] = &[
+    "final",
+    "encapsulated",
+    "partial",
+    "expandable",
+    "pure",
+    "impure",
+    "operator",
+];
+
+/// An item of the outline of a source text, in source order
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutlineEvent {
+    /// The `within` clause of the file
+    Within(Name),
+    /// Start of a class definition.
+    ///
+    /// The class has its header (name, kind, prefixes and description) but no
+    /// contents: its components, extends clauses and nested classes follow as
+    /// separate events, up to the matching [`OutlineEvent::ClassEnd`].
+    ClassStart(Box<ClassDefinition>),
+    /// A component declared in the current class (without modifications)
+    Component(Box<Component>),
+    /// An extends clause of the current class (without modifications)
+    Extends(Extend),
+    /// End of the current class, with the location of the whole definition
+    ClassEnd(Location),
+}
+
+/// A significant token: its kind and byte span
+type RawToken = (SyntaxKind, Range<usize>);
+
+/// Streaming scanner producing the [`OutlineEvent`]s of a source text.
+///
+/// The source is expected to be normalized (see [`normalize_source`]). Tokens
+/// are lexed on demand, so memory use doesn't grow with the size of the file.
+pub struct OutlineParser<'a> {
+    source: &'a str,
+    file_name: String,
+    tokens: Tokens<'a>,
+    /// Tokens read ahead
+    lookahead: VecDeque<RawToken>,
+    positions: Positions<'a>,
+    /// Name tokens of the open classes, and whether their current section
+    /// holds equations or statements
+    classes: Vec<(Token, bool)>,
+    events: VecDeque<OutlineEvent>,
+    done: bool,
+}
+
+impl<'a> OutlineParser<'a> {
+    pub fn new(source: &'a str, file_name: &str) -> Self {
+        Self {
+            source,
+            // Like the parser, locations hold the base name of the file
+            file_name: base_name(file_name).to_string(),
+            tokens: lex_tokens(source),
+            lookahead: VecDeque::new(),
+            positions: Positions::new(source),
+            classes: Vec::new(),
+            events: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn next_token(&mut self) -> Option<RawToken> {
+        self.lookahead.pop_front().or_else(|| self.tokens.next())
+    }
+
+    fn peek(&mut self, n: usize) -> Option<&RawToken> {
+        while self.lookahead.len() <= n {
+            let token = self.tokens.next()?;
+            self.lookahead.push_back(token);
+        }
+        self.lookahead.get(n)
+    }
+
+    fn text(&self, token: &RawToken) -> &'a str {
+        &self.source[token.1.clone()]
+    }
+
+    /// The text of a keyword token, or "" for other tokens
+    fn keyword(&self, token: &RawToken) -> &'a str {
+        if token.0 == SyntaxKind::Keyword {
+            self.text(token)
+        } else {
+            ""
+        }
+    }
+
+    fn is_symbol(&self, token: &RawToken, symbol: &str) -> bool {
+        token.0 == SyntaxKind::Symbol && self.text(token) == symbol
+    }
+
+    fn peek_is_symbol(&mut self, n: usize, symbol: &str) -> bool {
+        self.peek(n)
+            .cloned()
+            .is_some_and(|t| self.is_symbol(&t, symbol))
+    }
+
+    fn peek_is_kind(&mut self, n: usize, kind: SyntaxKind) -> bool {
+        self.peek(n).is_some_and(|t| t.0 == kind)
+    }
+
+    /// An AST token with its location
+    fn token(&mut self, token: &RawToken) -> Token {
+        let (start_line, start_column) = self.positions.at(token.1.start);
+        let (end_line, end_column) = self.positions.at(token.1.end);
+        Token {
+            text: self.text(token).to_string(),
+            location: Location {
+                start_line,
+                start_column,
+                end_line,
+                end_column,
+                start: token.1.start as u32,
+                end: token.1.end as u32,
+                file_name: self.file_name.clone(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Handle the next element or statement, returning false at the end of the source
+    fn step(&mut self) -> bool {
+        let Some(token) = self.next_token() else {
+            // Close the classes left open at the end of the file
+            while let Some((name, _)) = self.classes.pop() {
+                self.events.push_back(OutlineEvent::ClassEnd(name.location));
+            }
+            return false;
+        };
+
+        let in_body = self.classes.last().is_some_and(|(_, body)| *body);
+        match self.keyword(&token) {
+            "end" => self.class_end(),
+            "equation" | "algorithm" => self.set_body(true),
+            "initial"
+                if self
+                    .peek(0)
+                    .cloned()
+                    .is_some_and(|t| matches!(self.keyword(&t), "equation" | "algorithm")) =>
+            {
+                self.next_token();
+                self.set_body(true);
+            }
+            "public" | "protected" => self.set_body(false),
+            _ if in_body => {}
+            "within" if self.classes.is_empty() => {
+                let name = self.name();
+                self.skip_statement();
+                self.events.push_back(OutlineEvent::Within(name));
+            }
+            "import" | "annotation" | "external" => self.skip_statement(),
+            "extends" if !self.classes.is_empty() => self.extends(&token),
+            _ => self.element(token),
+        }
+        true
+    }
+
+    fn set_body(&mut self, body: bool) {
+        if let Some(class) = self.classes.last_mut() {
+            class.1 = body;
+        }
+    }
+
+    /// `end Name;` closes the current class (other uses of `end` are skipped)
+    fn class_end(&mut self) {
+        if !self.peek_is_kind(0, SyntaxKind::Ident) {
+            return;
+        }
+        let ident = self.next_token().unwrap();
+        let ident = self.token(&ident);
+        if self.peek_is_symbol(0, ";") {
+            self.next_token();
+        }
+        if let Some((name, _)) = self.classes.pop() {
+            self.events.push_back(OutlineEvent::ClassEnd(span(
+                &name.location,
+                &ident.location,
+            )));
+        }
+    }
+
+    /// A dotted name (`A.B.C`, optionally with a leading dot)
+    fn name(&mut self) -> Name {
+        let mut name = Name::default();
+        if self.peek_is_symbol(0, ".") {
+            self.next_token();
+        }
+        while self.peek_is_kind(0, SyntaxKind::Ident) {
+            let token = self.next_token().unwrap();
+            name.name.push(self.token(&token));
+            if !(self.peek_is_symbol(0, ".") && self.peek_is_kind(1, SyntaxKind::Ident)) {
+                break;
+            }
+            self.next_token();
+        }
+        name
+    }
+
+    fn extends(&mut self, keyword: &RawToken) {
+        let keyword = self.token(keyword);
+        let comp = self.name();
+        self.skip_statement();
+        let location = comp.name.last().map_or(keyword.location.clone(), |last| {
+            span(&keyword.location, &last.location)
+        });
+        self.events.push_back(OutlineEvent::Extends(Extend {
+            comp,
+            location,
+            ..Default::default()
+        }));
+    }
+
+    /// A class definition or component clause, after element prefixes
+    fn element(&mut self, mut token: RawToken) {
+        let (mut inner, mut outer, mut is_final) = (false, false, false);
+        loop {
+            match self.keyword(&token) {
+                "redeclare" | "replaceable" => {}
+                "final" => is_final = true,
+                "inner" => inner = true,
+                "outer" => outer = true,
+                _ => break,
+            }
+            match self.next_token() {
+                Some(next) => token = next,
+                None => return,
+            }
+        }
+
+        if CLASS_KEYWORDS.contains(&self.keyword(&token)) {
+            self.class_definition(token);
+        } else if !self.classes.is_empty()
+            && (token.0 == SyntaxKind::Ident
+                || self.is_symbol(&token, ".")
+                || matches!(
+                    self.keyword(&token),
+                    "flow" | "stream" | "discrete" | "parameter" | "constant" | "input" | "output"
+                ))
+        {
+            let mut component = Component {
+                inner,
+                outer,
+                r#final: is_final,
+                ..Default::default()
+            };
+            self.component_clause(token, &mut component);
+        }
+    }
+
+    fn class_definition(&mut self, mut token: RawToken) {
+        let mut class = ClassDefinition::default();
+        let mut has_type = false;
+        loop {
+            let class_type = match self.keyword(&token) {
+                "encapsulated" => {
+                    class.encapsulated = true;
+                    None
+                }
+                "partial" => {
+                    class.partial = true;
+                    None
+                }
+                "class" => Some(ClassType::Class),
+                "model" => Some(ClassType::Model),
+                "record" => Some(ClassType::Record),
+                "block" => Some(ClassType::Block),
+                "connector" => Some(ClassType::Connector),
+                "type" => Some(ClassType::Type),
+                "package" => Some(ClassType::Package),
+                "function" => Some(ClassType::Function),
+                "operator" => Some(ClassType::Operator),
+                _ => None,
+            };
+            if let Some(class_type) = class_type {
+                class.class_type = class_type;
+                class.class_type_token = self.token(&token);
+                has_type = true;
+            }
+            let next_is_keyword = self
+                .peek(0)
+                .cloned()
+                .is_some_and(|t| CLASS_KEYWORDS.contains(&self.keyword(&t)));
+            if !next_is_keyword {
+                break;
+            }
+            token = self.next_token().unwrap();
+        }
+        if !has_type {
+            return;
+        }
+
+        // `model extends M ... end M;` redefines an inherited class
+        let class_extends = self
+            .peek(0)
+            .cloned()
+            .is_some_and(|t| self.keyword(&t) == "extends");
+        if class_extends {
+            self.next_token();
+        }
+        if !self.peek_is_kind(0, SyntaxKind::Ident) {
+            return;
+        }
+        let name = self.next_token().unwrap();
+        class.name = self.token(&name);
+        class.location = class.name.location.clone();
+
+        // Short class definition: `type T = Real(unit = "m") "description";`
+        if !class_extends && self.peek_is_symbol(0, "=") {
+            self.next_token();
+            if let Some(prefix) = self.peek(0).cloned() {
+                match self.keyword(&prefix) {
+                    "input" => class.causality = Causality::Input(self.token(&prefix)),
+                    "output" => class.causality = Causality::Output(self.token(&prefix)),
+                    _ => {}
+                }
+                if class.causality != Causality::Empty {
+                    self.next_token();
+                }
+            }
+
+            let location = class.location.clone();
+            let is_enumeration = self
+                .peek(0)
+                .cloned()
+                .is_some_and(|t| self.keyword(&t) == "enumeration");
+            let mut extend = None;
+            if is_enumeration {
+                self.next_token();
+                class.enum_literals = self.enum_literals();
+            } else {
+                // The class extends its base type, e.g. `Real`
+                let base_type = self.name();
+                class.short_class = Some(ShortClassSpecifier {
+                    base_type: base_type.clone(),
+                    ..Default::default()
+                });
+                extend = Some(Extend {
+                    comp: base_type,
+                    location: location.clone(),
+                    ..Default::default()
+                });
+            }
+            self.skip_statement();
+            self.events
+                .push_back(OutlineEvent::ClassStart(Box::new(class)));
+            self.events.extend(extend.map(OutlineEvent::Extends));
+            self.events.push_back(OutlineEvent::ClassEnd(location));
+            return;
+        }
+
+        if class_extends && self.peek_is_symbol(0, "(") {
+            self.skip_brackets();
+        }
+        while self.peek_is_kind(0, SyntaxKind::String) {
+            let string = self.next_token().unwrap();
+            class.description.push(self.description_token(&string));
+            if self.peek_is_symbol(0, "+") && self.peek_is_kind(1, SyntaxKind::String) {
+                self.next_token();
+            }
+        }
+
+        let name = class.name.clone();
+        self.events
+            .push_back(OutlineEvent::ClassStart(Box::new(class)));
+        if class_extends {
+            self.events.push_back(OutlineEvent::Extends(Extend {
+                comp: Name {
+                    name: vec![name.clone()],
+                },
+                location: name.location.clone(),
+                ..Default::default()
+            }));
+        }
+        self.classes.push((name, false));
+    }
+
+    /// A component clause: type prefixes, type and the declared components
+    fn component_clause(&mut self, mut token: RawToken, component: &mut Component) {
+        loop {
+            match self.keyword(&token) {
+                "flow" => component.connection = Connection::Flow(self.token(&token)),
+                "stream" => component.connection = Connection::Stream(self.token(&token)),
+                "discrete" => component.variability = Variability::Discrete(self.token(&token)),
+                "parameter" => component.variability = Variability::Parameter(self.token(&token)),
+                "constant" => component.variability = Variability::Constant(self.token(&token)),
+                "input" => component.causality = Causality::Input(self.token(&token)),
+                "output" => component.causality = Causality::Output(self.token(&token)),
+                _ => break,
+            }
+            match self.next_token() {
+                Some(next) => token = next,
+                None => return,
+            }
+        }
+
+        // The type, e.g. `Modelica.Units.SI.Voltage`
+        if self.is_symbol(&token, ".") {
+            match self.next_token() {
+                Some(next) => token = next,
+                None => return,
+            }
+        }
+        if token.0 != SyntaxKind::Ident {
+            self.skip_statement();
+            return;
+        }
+        let mut type_name = vec![self.token(&token)];
+        while self.peek_is_symbol(0, ".") && self.peek_is_kind(1, SyntaxKind::Ident) {
+            self.next_token();
+            let ident = self.next_token().unwrap();
+            type_name.push(self.token(&ident));
+        }
+        component.type_name = Name { name: type_name };
+        if self.peek_is_symbol(0, "[") {
+            self.skip_brackets();
+        }
+
+        // The declarations, e.g. `x(start = 1) "State", y[2]`
+        loop {
+            if !self.peek_is_kind(0, SyntaxKind::Ident) {
+                self.skip_statement();
+                return;
+            }
+            let ident = self.next_token().unwrap();
+            let name = self.token(&ident);
+            let (description, more) = self.declaration_rest(true);
+            self.events
+                .push_back(OutlineEvent::Component(Box::new(Component {
+                    name: name.text.clone(),
+                    location: span(&component.type_name.name[0].location, &name.location),
+                    name_token: name,
+                    description,
+                    ..component.clone()
+                })));
+            if !more {
+                return;
+            }
+        }
+    }
+
+    /// Skip the rest of a declaration after its name up to `;` (or `,` if
+    /// `at_comma`), returning its description strings and whether it ended
+    /// at a `,`.
+    fn declaration_rest(&mut self, at_comma: bool) -> (Vec<Token>, bool) {
+        let mut depth = 0usize;
+        let mut description = Vec::new();
+        // Whether the strings collected so far can still be the description
+        let mut in_description = false;
+        let mut in_annotation = false;
+        let mut prev: Option<RawToken> = None;
+
+        while let Some(token) = self.next_token() {
+            let text = self.text(&token);
+            if token.0 == SyntaxKind::Symbol {
+                match text {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    ";" if depth == 0 => return (description, false),
+                    "," if depth == 0 && at_comma => return (description, true),
+                    _ => {}
+                }
+            }
+
+            if depth == 0 && !in_annotation {
+                if token.0 == SyntaxKind::String {
+                    // A string after an operator is part of an expression
+                    let continues = prev
+                        .as_ref()
+                        .is_some_and(|p| p.0 == SyntaxKind::String || self.is_symbol(p, "+"));
+                    let follows_value = prev.as_ref().is_none_or(|p| match p.0 {
+                        SyntaxKind::String
+                        | SyntaxKind::Ident
+                        | SyntaxKind::Integer
+                        | SyntaxKind::Real => true,
+                        SyntaxKind::Keyword => matches!(self.text(p), "true" | "false"),
+                        SyntaxKind::Symbol => matches!(self.text(p), ")" | "]" | "}"),
+                        _ => false,
+                    });
+                    if in_description && continues {
+                        description.push(self.description_token(&token));
+                    } else if follows_value {
+                        description = vec![self.description_token(&token)];
+                        in_description = true;
+                    } else {
+                        description.clear();
+                        in_description = false;
+                    }
+                } else if self.keyword(&token) == "annotation" {
+                    in_annotation = true;
+                } else if !(in_description && self.is_symbol(&token, "+")) {
+                    description.clear();
+                    in_description = false;
+                }
+            }
+            prev = Some(token);
+        }
+        (description, false)
+    }
+
+    /// The literals of `enumeration(a "A", b)`, stopping before the closing `)`
+    fn enum_literals(&mut self) -> Vec<Token> {
+        let mut literals = Vec::new();
+        if !self.peek_is_symbol(0, "(") {
+            return literals;
+        }
+        let mut depth = 0usize;
+        let mut expect_literal = true;
+        while let Some(token) = self.peek(0).cloned() {
+            if token.0 == SyntaxKind::Symbol {
+                match self.text(&token) {
+                    "(" => depth += 1,
+                    ")" if depth == 1 => return literals,
+                    ")" => depth -= 1,
+                    "," if depth == 1 => expect_literal = true,
+                    ";" => return literals,
+                    _ => {}
+                }
+            } else if depth == 1 && expect_literal && token.0 == SyntaxKind::Ident {
+                literals.push(self.token(&token));
+                expect_literal = false;
+            }
+            self.next_token();
+        }
+        literals
+    }
+
+    /// A description string token, with the quotes removed from its text
+    fn description_token(&mut self, token: &RawToken) -> Token {
+        let mut token = self.token(token);
+        let text = &token.text;
+        if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+            token.text = text[1..text.len() - 1].to_string();
+        }
+        token
+    }
+
+    /// Skip up to and including the next `;` outside of brackets
+    fn skip_statement(&mut self) {
+        self.declaration_rest(false);
+    }
+
+    /// Skip a bracketed group starting at the next token
+    fn skip_brackets(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.next_token() {
+            match self.text(&token) {
+                "(" | "[" | "{" if token.0 == SyntaxKind::Symbol => depth += 1,
+                ")" | "]" | "}" if token.0 == SyntaxKind::Symbol => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Iterator for OutlineParser<'_> {
+    type Item = OutlineEvent;
+
+    fn next(&mut self) -> Option<OutlineEvent> {
+        while self.events.is_empty() && !self.done {
+            self.done = !self.step();
+        }
+        self.events.pop_front()
+    }
+}
+
+/// Parse the outline of a source text: its classes and their components and
+/// extends clauses, without equations, algorithms, modifications or annotations.
+///
+/// The source is normalized with [`normalize_source`] first.
+pub fn parse_outline(source: &str, file_name: &str) -> StoredDefinition {
+    let source = normalize_source(source);
+    let mut def = StoredDefinition::default();
+    let mut open: Vec<ClassDefinition> = Vec::new();
+
+    for event in OutlineParser::new(&source, file_name) {
+        match event {
+            OutlineEvent::Within(name) => def.within = Some(name),
+            OutlineEvent::ClassStart(class) => open.push(*class),
+            OutlineEvent::Component(component) => {
+                if let Some(class) = open.last_mut() {
+                    class.components.insert(component.name.clone(), *component);
+                }
+            }
+            OutlineEvent::Extends(extend) => {
+                if let Some(class) = open.last_mut() {
+                    class.extends.push(extend);
+                }
+            }
+            OutlineEvent::ClassEnd(location) => {
+                let Some(mut class) = open.pop() else {
+                    continue;
+                };
+                class.location = location;
+                let classes = match open.last_mut() {
+                    Some(parent) => &mut parent.classes,
+                    None => &mut def.class_list,
+                };
+                classes.insert(class.name.text.clone(), class);
+            }
+        }
+    }
+    def
+}
+
+/// Fully parse one class of a source text, e.g. a class of its outline.
+///
+/// Only the text of the class is parsed, which is much faster than parsing the
+/// whole file when a few classes of a large file are needed. Locations in the
+/// result are those in the whole file.
+///
+/// # Errors
+///
+/// Returns an error if the class has a syntax error.
+pub fn parse_class(
+    source: &str,
+    file_name: &str,
+    class: &ClassDefinition,
+) -> Result<ClassDefinition> {
+    let source = normalize_source(source);
+    let start = class_start(&source, class.class_type_token.location.start as usize);
+    let name_end = (class.location.end as usize).min(source.len());
+    let end = source[name_end..]
+        .find(';')
+        .map_or(source.len(), |i| name_end + i + 1);
+
+    let mut text = padding(&source[..start]);
+    text.push_str(&source[start..end]);
+    let def = super::parse_source(&text, file_name)?;
+    def.class_list
+        .into_values()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No class '{}' found", class.name.text))
+}
+
+/// Start of a class definition, including the prefixes before its class keyword
+fn class_start(source: &str, keyword_start: usize) -> usize {
+    let mut start = keyword_start.min(source.len());
+    loop {
+        let before = source[..start].trim_end();
+        let word_start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        if TOP_LEVEL_PREFIXES.contains(&&before[word_start..]) {
+            start = word_start;
+        } else {
+            return start;
+        }
+    }
+}
+
+/// Whitespace with the same length in bytes, number of lines and number of
+/// characters on its last line as `text`, so text appended to it gets the
+/// same locations as after `text`
+fn padding(text: &str) -> String {
+    let last_line = text.rfind('\n').map_or(0, |i| i + 1);
+    let lines = text[..last_line].matches('\n').count();
+    let columns = text[last_line..].chars().count();
+
+    let mut padding = String::with_capacity(text.len());
+    if lines > 0 {
+        // Multi-byte characters take extra bytes, added on the first line
+        padding.push_str(&" ".repeat(text.len() - lines - columns));
+    }
+    padding.push_str(&"\n".repeat(lines));
+    padding.push_str(&" ".repeat(columns));
+    padding
+}
+
+/// Location spanning from the start of one location to the end of another
+fn span(start: &Location, end: &Location) -> Location {
+    Location {
+        end_line: end.end_line,
+        end_column: end.end_column,
+        end: end.end,
+        ..start.clone()
+    }
+}
+
+/// Converts increasing byte offsets to 1-based lines and character columns
+struct Positions<'a> {
+    source: &'a str,
+    offset: usize,
+    line: u32,
+    column: u32,
+}
+
+impl<'a> Positions<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn at(&mut self, offset: usize) -> (u32, u32) {
+        if offset < self.offset {
+            *self = Self::new(self.source);
+        }
+        for c in self.source[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+        (self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source;
+
+    const SOURCE: &str = r#"within Lib.Sub;
+partial model Base "Base class" + " with é"
+  import Modelica.Constants.pi;
+  extends Lib.Interfaces.Icon(size = {1, 2});
+  parameter Real k[2] = {1, 2} "Gains";
+  Modelica.Units.SI.Voltage v(start = 0, fixed = true), i "Current"
+    annotation(Dialog(group = "Init"));
+  final input Real u if k[1] > 0;
+  parameter String s = "a" + "b" "Label";
+  type Gain = Real(unit = "1") "A gain";
+  connector Out = output Real;
+  type Mode = enumeration(off "Off", on) "Modes";
+  replaceable model Load = Base constrainedby Base;
+  encapsulated function f
+    input Real x;
+    output Real y;
+  protected
+    Real t;
+  algorithm
+    t := x[end];
+    y := t;
+  end f;
+equation
+  v = i * k[end];
+  if k[1] > 0 then
+    u = 1;
+  end if;
+initial equation
+  v = 0;
+public
+  Real w;
+  annotation(Icon(graphics = {Text(textString = "end")}));
+end Base;
+"#;
+
+    /// Token texts and locations (token numbers differ from those of the parser)
+    fn tokens<'a>(tokens: impl IntoIterator<Item = &'a Token>) -> Vec<(String, Location)> {
+        tokens
+            .into_iter()
+            .map(|t| (t.text.clone(), t.location.clone()))
+            .collect()
+    }
+
+    fn compare_classes(outline: &ClassDefinition, full: &ClassDefinition) {
+        assert_eq!(tokens([&outline.name]), tokens([&full.name]));
+        assert_eq!(outline.class_type, full.class_type, "{}", full.name.text);
+        assert_eq!(
+            tokens([&outline.class_type_token]),
+            tokens([&full.class_type_token])
+        );
+        assert_eq!(outline.location, full.location, "{}", full.name.text);
+        assert_eq!(
+            (outline.partial, outline.encapsulated),
+            (full.partial, full.encapsulated)
+        );
+        assert_eq!(tokens(&outline.description), tokens(&full.description));
+        assert_eq!(tokens(&outline.enum_literals), tokens(&full.enum_literals));
+        assert_eq!(
+            format!("{:?}", outline.causality),
+            format!("{:?}", full.causality)
+        );
+        let extends: Vec<_> = full.extends.iter().map(|e| e.comp.to_string()).collect();
+        let outline_extends: Vec<_> = outline.extends.iter().map(|e| e.comp.to_string()).collect();
+        assert_eq!(outline_extends, extends);
+
+        let names: Vec<_> = full.components.keys().collect();
+        assert_eq!(outline.components.keys().collect::<Vec<_>>(), names);
+        for (name, comp) in &full.components {
+            let o = &outline.components[name];
+            assert_eq!(tokens([&o.name_token]), tokens([&comp.name_token]));
+            assert_eq!(tokens(&o.type_name.name), tokens(&comp.type_name.name));
+            assert_eq!(o.location, comp.location, "{}", name);
+            assert_eq!(
+                format!("{:?}", o.variability),
+                format!("{:?}", comp.variability)
+            );
+            assert_eq!(
+                format!("{:?}", o.causality),
+                format!("{:?}", comp.causality)
+            );
+            assert_eq!(
+                (o.inner, o.outer, o.r#final),
+                (comp.inner, comp.outer, comp.r#final)
+            );
+            let texts = |c: &Component| {
+                c.description
+                    .iter()
+                    .map(|t| t.text.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(texts(o), texts(comp), "{}", name);
+        }
+
+        assert_eq!(
+            outline.classes.keys().collect::<Vec<_>>(),
+            full.classes.keys().collect::<Vec<_>>()
+        );
+        for (name, class) in &full.classes {
+            compare_classes(&outline.classes[name], class);
+        }
+    }
+
+    #[test]
+    fn test_outline_matches_full_parse() {
+        let full = parse_source(SOURCE, "lib.mo").unwrap();
+        let outline = parse_outline(SOURCE, "lib.mo");
+
+        assert_eq!(
+            tokens(&outline.within.unwrap().name),
+            tokens(&full.within.unwrap().name)
+        );
+        assert_eq!(outline.class_list.len(), 1);
+        compare_classes(&outline.class_list["Base"], &full.class_list["Base"]);
+
+        let base = &outline.class_list["Base"];
+        assert!(base.equations.is_empty() && base.initial_equations.is_empty());
+        assert!(base.classes["f"].algorithms.is_empty());
+    }
+
+    #[test]
+    fn test_outline_events() {
+        let source =
+            "package P\n  model M\n    Real x;\n  end M;\n  constant Real c = 1;\nend P;\n";
+        let events: Vec<_> = OutlineParser::new(source, "p.mo")
+            .map(|event| match event {
+                OutlineEvent::Within(name) => format!("within {}", name),
+                OutlineEvent::ClassStart(class) => format!("start {}", class.name.text),
+                OutlineEvent::Component(comp) => format!("component {}", comp.name),
+                OutlineEvent::Extends(extend) => format!("extends {}", extend.comp),
+                OutlineEvent::ClassEnd(location) => format!("end {}", location.end_line),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "start P",
+                "start M",
+                "component x",
+                "end 4",
+                "component c",
+                "end 6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outline_of_invalid_source() {
+        // Unterminated classes are closed at the end of the file
+        let outline = parse_outline("model M\n  Real x\n  Real y;\n", "m.mo");
+        let m = &outline.class_list["M"];
+        assert!(m.components.contains_key("x"));
+    }
+
+    #[test]
+    fn test_parse_class() {
+        let full = parse_source(SOURCE, "lib.mo").unwrap();
+        let outline = parse_outline(SOURCE, "lib.mo");
+
+        let f = parse_class(SOURCE, "lib.mo", &outline.class_list["Base"].classes["f"]).unwrap();
+        let full_f = &full.class_list["Base"].classes["f"];
+        assert!(f.encapsulated);
+        assert_eq!(f.location, full_f.location);
+        assert_eq!(f.components["t"].location, full_f.components["t"].location);
+        assert_eq!(
+            format!("{:?}", f.algorithms),
+            format!("{:?}", full_f.algorithms)
+        );
+
+        let base = parse_class(SOURCE, "lib.mo", &outline.class_list["Base"]).unwrap();
+        assert!(base.partial);
+        assert_eq!(
+            format!("{:?}", base.equations),
+            format!("{:?}", full.class_list["Base"].equations)
+        );
+
+        let gain = parse_class(
+            SOURCE,
+            "lib.mo",
+            &outline.class_list["Base"].classes["Gain"],
+        );
+        assert_eq!(gain.unwrap().class_type, ClassType::Type);
+    }
+
+    #[test]
+    fn test_padding() {
+        let text = "é\nab\ncd é";
+        let padding = padding(text);
+        assert_eq!(padding.len(), text.len());
+        assert_eq!(padding.lines().count(), 3);
+        assert_eq!(padding.lines().last().unwrap().chars().count(), 4);
+    }
+}
@@ -0,0 +1,202 @@
+//! Custom compiler passes.
+//!
+//! Downstream crates can transform models during compilation, e.g. to insert
+//! sensor noise, by registering passes with the [`Compiler`](super::Compiler)
+//! instead of forking rumoca. Passes run in the order they were registered,
+//! at three points of the pipeline:
+//!
+//! - **flattened**: on the flattened class, after imports are resolved and
+//!   before variable references are validated, so components and equations
+//!   a pass adds are checked and expanded like the model's own
+//! - **expanded**: on the flattened class after equations were expanded to
+//!   scalar form (for-loops unrolled, bindings turned into equations), right
+//!   before the DAE is created
+//! - **dae**: on the DAE, before its balance is checked
+//!
+//! A pass that returns an error stops the compilation with an
+//! [`Error::Pass`].
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::dae::ast::Dae;
+use crate::error::{Error, Result, describe};
+use crate::ir::ast::ClassDefinition;
+
+// Use web_time on WASM for Instant::now() polyfill
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Function of a pass over a `T`
+type PassFn<T> = dyn Fn(&mut T) -> anyhow::Result<()> + Send + Sync;
+
+/// A named pass over a `T`
+struct Pass<T> {
+    name: String,
+    run: Arc<PassFn<T>>,
+}
+
+impl<T> Clone for Pass<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            run: Arc::clone(&self.run),
+        }
+    }
+}
+
+/// Custom passes to run at the extension points of the pipeline, see the
+/// [module docs](self)
+#[derive(Clone, Default)]
+pub struct Passes {
+    flattened: Vec<Pass<ClassDefinition>>,
+    expanded: Vec<Pass<ClassDefinition>>,
+    dae: Vec<Pass<Dae>>,
+}
+
+impl fmt::Debug for Passes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn names<T>(passes: &[Pass<T>]) -> Vec<&str> {
+            passes.iter().map(|pass| pass.name.as_str()).collect()
+        }
+        f.debug_struct("Passes")
+            .field("flattened", &names(&self.flattened))
+            .field("expanded", &names(&self.expanded))
+            .field("dae", &names(&self.dae))
+            .finish()
+    }
+}
+
+impl Passes {
+    /// Register a pass over the flattened class
+    pub fn flattened<F>(&mut self, name: &str, pass: F)
+    where
+        F: Fn(&mut ClassDefinition) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.flattened.push(Pass {
+            name: name.to_string(),
+            run: Arc::new(pass),
+        });
+    }
+
+    /// Register a pass over the flattened class with scalar equations
+    pub fn expanded<F>(&mut self, name: &str, pass: F)
+    where
+        F: Fn(&mut ClassDefinition) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.expanded.push(Pass {
+            name: name.to_string(),
+            run: Arc::new(pass),
+        });
+    }
+
+    /// Register a pass over the DAE
+    pub fn dae<F>(&mut self, name: &str, pass: F)
+    where
+        F: Fn(&mut Dae) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.dae.push(Pass {
+            name: name.to_string(),
+            run: Arc::new(pass),
+        });
+    }
+
+    /// Run the passes over the flattened class
+    pub(crate) fn run_flattened(&self, class: &mut ClassDefinition, verbose: bool) -> Result<()> {
+        run_all(&self.flattened, class, verbose)
+    }
+
+    /// Run the passes over the flattened class with scalar equations
+    pub(crate) fn run_expanded(&self, class: &mut ClassDefinition, verbose: bool) -> Result<()> {
+        run_all(&self.expanded, class, verbose)
+    }
+
+    /// Run the passes over the DAE
+    pub(crate) fn run_dae(&self, dae: &mut Dae, verbose: bool) -> Result<()> {
+        run_all(&self.dae, dae, verbose)
+    }
+}
+
+fn run_all<T>(passes: &[Pass<T>], target: &mut T, verbose: bool) -> Result<()> {
+    for pass in passes {
+        let start = Instant::now();
+        (pass.run)(target).map_err(|e| Error::Pass {
+            name: pass.name.clone(),
+            message: describe(e),
+        })?;
+        if verbose {
+            eprintln!(
+                "Pass '{}' took {} ms",
+                pass.name,
+                start.elapsed().as_millis()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::parse_source_simple;
+    use crate::{Compiler, Error};
+    use std::sync::{Arc, Mutex};
+
+    const SOURCE: &str = "model M\n  Real x;\nequation\n  der(x) = -x;\nend M;\n";
+
+    #[test]
+    fn test_passes() {
+        // Insert a noisy measurement of x, taken from another class
+        let sensor = parse_source_simple(
+            "model Sensor\n  Real y;\nequation\n  y = x + 0.01 * sin(100 * time);\nend Sensor;",
+            "sensor.mo",
+        )
+        .unwrap()
+        .class_list["Sensor"]
+            .clone();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let log = |stage: &'static str| {
+            let order = Arc::clone(&order);
+            move || order.lock().unwrap().push(stage)
+        };
+        let (flattened, expanded, dae) = (log("flattened"), log("expanded"), log("dae"));
+        let result = Compiler::new()
+            .model("M")
+            .dae_pass("log", move |_| {
+                dae();
+                Ok(())
+            })
+            .expanded_pass("log", move |_| {
+                expanded();
+                Ok(())
+            })
+            .flattened_pass("sensor", move |class| {
+                flattened();
+                class.components.extend(sensor.components.clone());
+                class.equations.extend(sensor.equations.clone());
+                Ok(())
+            })
+            .compile_str(SOURCE, "m.mo")
+            .unwrap();
+
+        assert!(result.dae.y.contains_key("y"));
+        assert!(result.is_balanced());
+        assert_eq!(*order.lock().unwrap(), ["flattened", "expanded", "dae"]);
+
+        let err = Compiler::new()
+            .model("M")
+            .dae_pass("require-inputs", |dae| {
+                anyhow::ensure!(!dae.u.is_empty(), "the model has no inputs");
+                Ok(())
+            })
+            .compile_str(SOURCE, "m.mo")
+            .unwrap_err();
+        assert!(matches!(&err, Error::Pass { name, .. } if name == "require-inputs"));
+        assert_eq!(
+            err.to_string(),
+            "Pass 'require-inputs' failed: the model has no inputs"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! File paths and `file://` URIs across platforms.
+//!
+//! The same file is named in several forms on Windows: `C:\models\a.mo` from
+//! the file system, `C:/models/a.mo` from tools and configuration, and
+//! `file:///c%3A/models/a.mo` or `file:///C:/models/a.mo` from editors. Paths
+//! there are also case-insensitive. The helpers here convert between paths and
+//! URIs and compare paths so that these forms name the same file, on any host:
+//!
+//! ```
+//! use rumoca::compiler::paths::{base_name, file_uri_to_path, path_key, path_to_file_uri};
+//!
+//! assert_eq!(base_name(r"C:\models\a.mo"), "a.mo");
+//! assert_eq!(file_uri_to_path("file:///c%3A/my%20models/a.mo").unwrap(), "C:/my models/a.mo");
+//! assert_eq!(path_to_file_uri(r"C:\my models\a.mo"), "file:///c:/my%20models/a.mo");
+//! assert_eq!(path_key(r"C:\Models\A.mo"), path_key("c:/models/a.mo"));
+//! ```
+//!
+//! Paths are handled as strings, as a Windows path has no meaning to
+//! [`std::path::Path`] on other hosts. Paths converted from URIs use `/`
+//! separators, which Windows accepts as well.
+
+/// Characters left as they are in the path of a URI (RFC 3986 unreserved
+/// characters, sub-delimiters, `:`, `@` and `/`)
+fn is_uri_path_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte)
+}
+
+/// Whether a path names a file on Windows: it starts with a drive letter, as
+/// in `C:\` or `c:/`, or is a UNC path, as in `\\server\share`
+pub fn is_windows_path(path: &str) -> bool {
+    has_drive_letter(path) || path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Whether a path starts with a drive letter followed by the end of the path
+/// or a separator
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes.get(2).is_none_or(|&b| b == b'/' || b == b'\\')
+}
+
+/// The file name of a path, without its directories, for `/` and `\`
+/// separators alike: `a.mo` for `/models/a.mo` and `C:\models\a.mo`
+///
+/// Locations keep this name only, so that they compare equal whatever form
+/// the path of the file had.
+pub fn base_name(path: &str) -> &str {
+    let path = path.trim_end_matches(['/', '\\']);
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// A key comparing equal for paths naming the same file: separators are `/`,
+/// trailing separators are dropped, and Windows paths are lower case
+pub fn path_key(path: &str) -> String {
+    if !is_windows_path(path) {
+        return match path.trim_end_matches('/') {
+            "" if path.starts_with('/') => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+    }
+    let key = path.replace('\\', "/").to_lowercase();
+    match key.trim_end_matches('/') {
+        // A drive root keeps its separator
+        trimmed if trimmed.len() == 2 && has_drive_letter(trimmed) => format!("{}/", trimmed),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Whether a path is `root` or inside it, comparing components as
+/// [`path_key`] does
+pub fn starts_with_path(path: &str, root: &str) -> bool {
+    let (path, root) = (path_key(path), path_key(root));
+    match path.strip_prefix(root.as_str()) {
+        Some(rest) => rest.is_empty() || root.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The path of a `file://` URI, or None for other schemes
+///
+/// Percent-encoded characters are decoded. Drive letters, encoded or not
+/// (`file:///c%3A/...`, `file:///C:/...`), give paths like `C:/...`, and a host
+/// other than `localhost` gives a UNC path (`file://server/share` gives
+/// `//server/share`).
+pub fn file_uri_to_path(uri: &str) -> Option<String> {
+    let scheme = uri.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+    let rest = &uri[7..];
+    // Queries and fragments aren't part of the path
+    let rest = &rest[..rest.find(['?', '#']).unwrap_or(rest.len())];
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, ""),
+    };
+    let path = percent_decode(path);
+    if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        return Some(format!("//{}{}", percent_decode(host), path));
+    }
+    match path.strip_prefix('/') {
+        Some(drive_path) if has_drive_letter(drive_path) => {
+            let mut drive_path = drive_path.replace('\\', "/");
+            drive_path[..1].make_ascii_uppercase();
+            if drive_path.len() == 2 {
+                drive_path.push('/');
+            }
+            Some(drive_path)
+        }
+        _ => Some(path),
+    }
+}
+
+/// The `file://` URI of an absolute path
+///
+/// Backslashes become `/`, drive letters are lower case (`file:///c:/...`)
+/// and UNC paths name their server as the host. Characters not allowed in a
+/// URI path, like spaces, are percent-encoded.
+pub fn path_to_file_uri(path: &str) -> String {
+    let windows = is_windows_path(path);
+    let mut path = if windows {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+    if has_drive_letter(&path) {
+        path[..1].make_ascii_lowercase();
+        path.insert(0, '/');
+    }
+    let (host, path) = match path.strip_prefix("//") {
+        Some(unc) if windows => match unc.find('/') {
+            Some(slash) => unc.split_at(slash),
+            None => (unc, ""),
+        },
+        _ => ("", path.as_str()),
+    };
+    format!("file://{}{}", percent_encode(host), percent_encode(path))
+}
+
+/// Normalize a `file://` URI to the form [`path_to_file_uri`] gives, so that
+/// URIs naming the same file in different forms are equal, or None for other
+/// schemes
+pub fn normalize_file_uri(uri: &str) -> Option<String> {
+    file_uri_to_path(uri).map(|path| path_to_file_uri(&path))
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for &byte in text.as_bytes() {
+        if is_uri_path_char(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Decode the `%XX` escapes of a URI component, keeping malformed escapes
+/// as they are
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_name() {
+        assert_eq!(base_name("/home/user/models/a.mo"), "a.mo");
+        assert_eq!(base_name(r"C:\Users\me\models\a.mo"), "a.mo");
+        assert_eq!(base_name(r"C:\Users\me/models\a.mo"), "a.mo");
+        assert_eq!(base_name(r"\\server\share\a.mo"), "a.mo");
+        assert_eq!(base_name("models/Pkg/"), "Pkg");
+        assert_eq!(base_name("a.mo"), "a.mo");
+    }
+
+    #[test]
+    fn test_windows_file_uris() {
+        // Drive letters, with the colon encoded as VS Code does or not
+        for uri in [
+            "file:///c%3A/Users/me/a.mo",
+            "file:///C:/Users/me/a.mo",
+            "file:///c:/Users/me/a.mo",
+            "FILE://localhost/C:/Users/me/a.mo",
+        ] {
+            assert_eq!(
+                file_uri_to_path(uri).unwrap(),
+                "C:/Users/me/a.mo",
+                "{}",
+                uri
+            );
+            assert_eq!(
+                normalize_file_uri(uri).unwrap(),
+                "file:///c:/Users/me/a.mo",
+                "{}",
+                uri
+            );
+        }
+        assert_eq!(file_uri_to_path("file:///d%3A").unwrap(), "D:/");
+        // Encoded backslashes
+        assert_eq!(
+            file_uri_to_path("file:///C:%5CUsers%5Cme%5Ca.mo").unwrap(),
+            "C:/Users/me/a.mo"
+        );
+        // Spaces and non-ASCII characters
+        assert_eq!(
+            file_uri_to_path("file:///c%3A/My%20Models/W%C3%A4rme.mo").unwrap(),
+            "C:/My Models/Wärme.mo"
+        );
+        assert_eq!(
+            path_to_file_uri(r"C:\My Models\Wärme.mo"),
+            "file:///c:/My%20Models/W%C3%A4rme.mo"
+        );
+        // UNC paths
+        assert_eq!(
+            file_uri_to_path("file://server/share/a.mo").unwrap(),
+            "//server/share/a.mo"
+        );
+        assert_eq!(
+            path_to_file_uri(r"\\server\share\a.mo"),
+            "file://server/share/a.mo"
+        );
+    }
+
+    #[test]
+    fn test_unix_file_uris() {
+        assert_eq!(
+            file_uri_to_path("file:///tmp/a%20b.mo").unwrap(),
+            "/tmp/a b.mo"
+        );
+        assert_eq!(
+            path_to_file_uri("/tmp/a b#1.mo"),
+            "file:///tmp/a%20b%231.mo"
+        );
+        assert_eq!(
+            file_uri_to_path(&path_to_file_uri("/tmp/a b#1.mo")).unwrap(),
+            "/tmp/a b#1.mo"
+        );
+        assert_eq!(
+            file_uri_to_path("file:///tmp/a.mo?x#y").unwrap(),
+            "/tmp/a.mo"
+        );
+        assert_eq!(file_uri_to_path("untitled:Untitled-1"), None);
+        assert_eq!(file_uri_to_path("https://example.com/a.mo"), None);
+    }
+
+    #[test]
+    fn test_path_comparison() {
+        // Windows paths are case-insensitive, whatever their separators
+        assert_eq!(path_key(r"C:\Models\A.mo"), path_key("c:/models/a.mo"));
+        assert_eq!(path_key(r"C:\Models\"), path_key("c:/models"));
+        assert_eq!(path_key(r"C:\"), "c:/");
+        assert_eq!(path_key(r"\\Server\Share"), path_key("//server/share"));
+        // Other paths are case-sensitive
+        assert_ne!(path_key("/tmp/A.mo"), path_key("/tmp/a.mo"));
+        assert_eq!(path_key("/"), "/");
+
+        assert!(starts_with_path(r"C:\Models\Lib\a.mo", "c:/models/lib"));
+        assert!(starts_with_path(r"C:\Models\Lib\a.mo", r"C:\"));
+        assert!(starts_with_path("c:/models/lib", r"C:\Models\Lib\"));
+        assert!(!starts_with_path(
+            r"C:\Models\Library\a.mo",
+            "c:/models/lib"
+        ));
+        assert!(starts_with_path("/tmp/lib/a.mo", "/tmp/lib"));
+        assert!(!starts_with_path("/tmp/Lib/a.mo", "/tmp/lib"));
+    }
+
+    #[test]
+    fn test_windows_locations() {
+        // Locations hold the base name of Windows paths, on any host
+        let source = "model M\n  Real x;\nequation\n  der(x) = -x;\nend M;";
+        let result = crate::Compiler::new()
+            .model("M")
+            .compile_str(source, r"C:\Users\me\models\M.mo")
+            .unwrap();
+        let x = &result.dae.x["x"];
+        assert_eq!(x.location.file_position(), "M.mo:2:3");
+    }
+}
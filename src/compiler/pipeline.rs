@@ -3,24 +3,40 @@
 //! This module contains the main compilation pipeline that transforms
 //! a Modelica AST into a DAE representation.
 
+use super::conformance::{RelaxedConstruct, find_relaxations};
 use super::function_collector::collect_all_functions;
+use super::limits::{LimitExceeded, Limits, check_deadline, with_timeout};
+use super::passes::Passes;
 use super::result::CompilationResult;
+use super::topology::Topology;
+use crate::dae::ast::{Dae, SortMode};
 use crate::dae::balance::BalanceResult;
+use crate::error::{Error, Result, describe};
+use crate::ir::analysis::instance_check::{InstanceCheck, check_instances};
+use crate::ir::analysis::purity::check_purity;
+use crate::ir::analysis::structural_parameters::structural_parameters;
+use crate::ir::analysis::type_checker::check_vectorized_calls;
 use crate::ir::analysis::var_validator::VarValidator;
+use crate::ir::analysis::when_check::check_when_equations;
+use crate::ir::ast::{ClassDefinition, Expression};
 use crate::ir::ast::{ClassType, StoredDefinition};
-use crate::ir::structural::create_dae::create_dae;
+use crate::ir::error::IrError;
+use crate::ir::structural::BltResult;
+use crate::ir::structural::create_dae::{create_dae, create_dae_with_blt};
 use crate::ir::transform::array_comprehension::expand_array_comprehensions;
 use crate::ir::transform::constant_substitutor::ConstantSubstitutor;
 use crate::ir::transform::enum_substitutor::EnumSubstitutor;
-use crate::ir::transform::equation_expander::expand_equations;
-use crate::ir::transform::flatten::{
-    FileDependencies, flatten, flatten_with_deps, is_cache_enabled,
-};
-use crate::ir::transform::function_inliner::FunctionInliner;
+use crate::ir::transform::equation_expander::{expand_equations, expand_equations_with_loops};
+use crate::ir::transform::flatten::{FileDependencies, FlattenContext, is_cache_enabled};
+use crate::ir::transform::function_inliner::{FunctionInliner, check_function_calls};
 use crate::ir::transform::import_resolver::ImportResolver;
-use crate::ir::transform::tuple_expander::expand_tuple_equations;
+use crate::ir::transform::random_streams::number_random_streams;
+use crate::ir::transform::table_lookup::lower_table_lookups;
+use crate::ir::transform::tuple_expander::{check_tuple_equations, expand_tuple_equations};
 use crate::ir::visitor::MutVisitable;
-use anyhow::Result;
+use indexmap::IndexMap;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::sync::{LazyLock, RwLock};
@@ -180,14 +196,22 @@ pub fn clear_dae_cache() {
 /// 6. Tuple expansion - expand tuple equations
 /// 7. DAE creation - create the final DAE representation
 /// 8. Balance checking - verify equations match unknowns
+///
+/// Custom [`Passes`] run after import resolution, right before DAE creation
+/// and right after it. Models exceeding the expansion size or depth limits
+/// of the options fail with an [`Error::Limit`].
 pub fn compile_from_ast(
     def: StoredDefinition,
     model_name: Option<&str>,
     model_hash: String,
     parse_time: std::time::Duration,
+    passes: &Passes,
+    options: DaeOptions,
     verbose: bool,
 ) -> Result<CompilationResult> {
-    compile_from_ast_ref(&def, model_name, model_hash, parse_time, verbose)
+    compile_from_ast_ref(
+        &def, model_name, model_hash, parse_time, passes, options, verbose,
+    )
 }
 
 /// Run the compilation pipeline on a reference to a parsed AST.
@@ -200,24 +224,189 @@ pub fn compile_from_ast_ref(
     model_name: Option<&str>,
     model_hash: String,
     parse_time: std::time::Duration,
+    passes: &Passes,
+    options: DaeOptions,
     verbose: bool,
 ) -> Result<CompilationResult> {
+    let model = compile_model(
+        &FlattenContext::new(def).with_limits(options.limits),
+        model_name,
+        &model_hash,
+        passes,
+        options,
+        verbose,
+    )?;
+    // Causality errors are reported as diagnostics by the checks, but are
+    // errors when compiling the model
+    if !model.instances.causality.is_empty() {
+        let errors: Vec<String> = model
+            .instances
+            .causality
+            .iter()
+            .map(|error| {
+                let loc = &error.location;
+                format!(
+                    "{}:{}:{}: {}",
+                    loc.file_name, loc.start_line, loc.start_column, error.message
+                )
+            })
+            .collect();
+        return Err(Error::Flatten(errors.join("\n")));
+    }
+    Ok(CompilationResult {
+        dae: model.dae,
+        def: def.clone(), // Clone only at the end for result storage
+        expanded_class: model.expanded_class,
+        parse_time,
+        flatten_time: model.flatten_time,
+        dae_time: model.dae_time,
+        model_hash,
+        balance: model.balance,
+        provenance: Default::default(),
+        topology: model.topology,
+        warnings: Vec::new(),
+        relaxations: model.relaxations,
+        blt: model.blt,
+        resources: Vec::new(),
+    })
+}
+
+/// Run the compilation pipeline on several models of one parsed AST and
+/// return the balance of each, or the error compiling it.
+///
+/// Classes shared by the models are resolved only once, and the models are
+/// compiled in parallel (native only).
+pub fn compile_balances(
+    def: &StoredDefinition,
+    model_names: &[&str],
+    model_hash: &str,
+    passes: &Passes,
+) -> Vec<Result<BalanceResult>> {
+    compile_checks(def, model_names, model_hash, passes, &Limits::default())
+        .into_iter()
+        .map(|check| check.map(|check| check.balance))
+        .collect()
+}
+
+/// Checks of a compiled model
+#[derive(Debug, Clone)]
+pub struct ModelCheck {
+    /// Balance of the model
+    pub balance: BalanceResult,
+    /// Parameter and connector uses of the flattened model
+    pub instances: InstanceCheck,
+    /// BLT result of the equations of the model
+    pub blt: BltResult,
+    /// Time spent flattening the model
+    pub flatten_time: std::time::Duration,
+    /// Time spent creating the DAE
+    pub dae_time: std::time::Duration,
+}
+
+/// Run the compilation pipeline on several models of one parsed AST and
+/// return the checks of each, or the error compiling it.
+///
+/// Like [`compile_balances`], with the instance checks of the flattened
+/// models (see [`check_instances`]). The compilation of each model fails with
+/// an [`Error::Limit`] when it exceeds `limits`, including its timeout.
+pub fn compile_checks(
+    def: &StoredDefinition,
+    model_names: &[&str],
+    model_hash: &str,
+    passes: &Passes,
+    limits: &Limits,
+) -> Vec<Result<ModelCheck>> {
+    let ctx = FlattenContext::new(def).with_limits(*limits);
+    let compile = |name: &&str| {
+        with_timeout(limits.timeout, || {
+            compile_model(
+                &ctx,
+                Some(name),
+                model_hash,
+                passes,
+                DaeOptions {
+                    limits: *limits,
+                    ..DaeOptions::default()
+                },
+                false,
+            )
+        })
+        .map(|model| ModelCheck {
+            balance: model.balance,
+            instances: model.instances,
+            blt: model.blt,
+            flatten_time: model.flatten_time,
+            dae_time: model.dae_time,
+        })
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    return model_names.par_iter().map(compile).collect();
+    #[cfg(target_arch = "wasm32")]
+    model_names.iter().map(compile).collect()
+}
+
+/// Options of the DAE created by the compilation pipeline
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DaeOptions {
+    /// Keep regular for-equations as loops, see [`Dae::roll_loops`]
+    pub symbolic_loops: bool,
+    /// Order of the continuous-time equations
+    pub sort: SortMode,
+    /// Resource limits of the compilation. The pipeline checks the expansion
+    /// size and depth, its caller runs it [`with_timeout`].
+    pub limits: Limits,
+}
+
+/// Output of the compilation pipeline for one model
+struct CompiledModel {
+    dae: Dae,
+    expanded_class: ClassDefinition,
+    flatten_time: std::time::Duration,
+    dae_time: std::time::Duration,
+    balance: BalanceResult,
+    instances: InstanceCheck,
+    blt: BltResult,
+    topology: Topology,
+    relaxations: Vec<RelaxedConstruct>,
+}
+
+/// Compile one model of the definition of a flatten context
+fn compile_model(
+    ctx: &FlattenContext,
+    model_name: Option<&str>,
+    model_hash: &str,
+    passes: &Passes,
+    options: DaeOptions,
+    verbose: bool,
+) -> Result<CompiledModel> {
+    let def = ctx.def();
+
     // Flatten
     let flatten_start = Instant::now();
-    let fclass_result = flatten(def, model_name);
+    let fclass_result = ctx.flatten(model_name);
 
     // Handle flatten errors - return raw error message (miette formatting at CLI only)
-    let mut fclass = match fclass_result {
-        Ok(fc) => fc,
+    let (mut fclass, instances, conditions, topology) = match fclass_result {
+        Ok(result) => {
+            let instances = check_instances(&result);
+            let conditions: IndexMap<String, Expression> = result
+                .instances
+                .iter()
+                .filter_map(|(name, instance)| Some((name.clone(), instance.condition.clone()?)))
+                .collect();
+            let topology = Topology::new(&result.instances, &result.connects);
+            (result.class, instances, conditions, topology)
+        }
         Err(e) => {
-            return Err(e);
+            return Err(stage_error(e, Error::Flatten));
         }
     };
     let flatten_time = flatten_start.elapsed();
+    let relaxations = find_relaxations(&fclass);
 
     if verbose {
-        println!("Flattening took {} ms", flatten_time.as_millis());
-        println!("Flattened class:\n{:#?}\n", fclass);
+        eprintln!("Flattening took {} ms", flatten_time.as_millis());
+        eprintln!("Flattened class:\n{:#?}\n", fclass);
     }
 
     // Resolve imports - rewrite short function names to fully qualified names
@@ -225,6 +414,9 @@ pub fn compile_from_ast_ref(
     let mut import_resolver = ImportResolver::new(&fclass, def);
     fclass.accept_mut(&mut import_resolver);
 
+    // Custom passes over the flattened class
+    passes.run_flattened(&mut fclass, verbose)?;
+
     // Clone the expanded class after import resolution for semantic analysis
     // This is the ideal state for checking undefined/unused variables
     let expanded_class = fclass.clone();
@@ -257,18 +449,49 @@ pub fn compile_from_ast_ref(
         if !validator.undefined_vars.is_empty() {
             // Return raw error (miette formatting at CLI only)
             let (var_name, context) = &validator.undefined_vars[0];
-            return Err(anyhow::anyhow!(
+            return Err(Error::Type(format!(
                 "Undefined variable '{}' in {}",
-                var_name,
-                context
-            ));
+                var_name, context
+            )));
         }
     }
 
-    // Inline user-defined function calls
+    // Scalar functions applied element-wise need arguments of the same shape
+    let vectorized = check_vectorized_calls(&fclass);
+    if vectorized.has_errors() {
+        let errors: Vec<String> = vectorized
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.location.file_position(), e.message))
+            .collect();
+        return Err(Error::Type(errors.join("\n")));
+    }
+
+    // When-equations must not be nested, and their branches must assign
+    // the same variables
+    let when_errors = check_when_equations(&fclass);
+    if !when_errors.is_empty() {
+        let errors: Vec<String> = when_errors.iter().map(|e| e.to_string()).collect();
+        return Err(Error::Type(errors.join("\n")));
+    }
+
+    // Pure functions must not call impure ones
     let mut inliner = FunctionInliner::from_class_list(&def.class_list);
+    let impure_calls = check_purity(&fclass, inliner.functions());
+    if !impure_calls.is_empty() {
+        let errors: Vec<String> = impure_calls.iter().map(|e| e.to_string()).collect();
+        return Err(Error::Type(errors.join("\n")));
+    }
+    let mut arity_errors = check_function_calls(&fclass, inliner.functions());
+    arity_errors.extend(check_tuple_equations(&fclass, inliner.functions()));
+    if !arity_errors.is_empty() {
+        return Err(Error::Type(arity_errors.join("\n")));
+    }
+
+    // Inline user-defined function calls
     fclass.accept_mut(&mut inliner);
     drop(inliner); // Drop before cloning def
+    check_deadline().map_err(|e| Error::Limit(e.to_string()))?;
 
     // Expand tuple equations like (a, b) = (expr1, expr2) into separate equations
     expand_tuple_equations(&mut fclass);
@@ -276,49 +499,93 @@ pub fn compile_from_ast_ref(
     // Expand array comprehensions like {expr for i in 1:n} into explicit arrays
     expand_array_comprehensions(&mut fclass);
 
+    // Find the parameters the structure of the model depends on, before
+    // for-equations are unrolled
+    let structural =
+        structural_parameters(&fclass, &conditions).map_err(|e| stage_error(e, Error::Type))?;
+
     // Expand structured equations to scalar form:
     // - For-loops expanded to individual equations
     // - Array equations expanded to element equations
     // - Binding equations converted to regular equations
-    expand_equations(&mut fclass);
+    let loops = expand_equations_with_loops(&mut fclass, &options.limits)
+        .map_err(|e| stage_error(e, Error::Type))?;
+
+    // Give each random() call its own reproducible stream
+    number_random_streams(&mut fclass).map_err(|e| Error::Type(describe(e)))?;
+
+    // Lower lookups in tables known at compile time to piecewise expressions
+    lower_table_lookups(&mut fclass).map_err(|e| Error::Type(describe(e)))?;
 
     if verbose {
-        println!(
+        eprintln!(
             "After function inlining, tuple expansion, array comprehension, and equation expansion:\n{:#?}\n",
             fclass
         );
     }
 
+    // Custom passes over the scalar equations
+    passes.run_expanded(&mut fclass, verbose)?;
+
     // Create DAE
+    check_deadline().map_err(|e| Error::Limit(e.to_string()))?;
     let dae_start = Instant::now();
-    let mut dae = create_dae(&mut fclass)?;
-    dae.model_hash = model_hash.clone();
+    let (mut dae, blt) = create_dae_with_blt(&mut fclass, options.sort)
+        .map_err(|e| stage_error(e, Error::Balance))?;
+    dae.model_hash = model_hash.to_string();
+    dae.structural = structural
+        .into_iter()
+        .filter(|name| dae.p.contains_key(name))
+        .collect();
+    if options.symbolic_loops {
+        dae.roll_loops(&loops);
+    }
     let dae_time = dae_start.elapsed();
 
+    // Custom passes over the DAE
+    passes.run_dae(&mut dae, verbose)?;
+    dae.parameter_uses = dae.find_parameter_uses();
+
     if verbose {
-        println!("DAE creation took {} ms", dae_time.as_millis());
-        println!("DAE:\n{:#?}\n", dae);
+        eprintln!("DAE creation took {} ms", dae_time.as_millis());
+        eprintln!("DAE:\n{:#?}\n", dae);
     }
 
     // Check model balance
     let balance = dae.check_balance();
 
     if verbose {
-        println!("{}", balance.status_message());
+        eprintln!("{}", balance.status_message());
     }
 
-    Ok(CompilationResult {
+    Ok(CompiledModel {
         dae,
-        def: def.clone(), // Clone only at the end for result storage
         expanded_class,
-        parse_time,
         flatten_time,
         dae_time,
-        model_hash,
         balance,
+        instances,
+        blt,
+        topology,
+        relaxations,
     })
 }
 
+/// Sort an error of a stage of the pipeline into the variant of the stage, or
+/// into [`Error::Limit`] if it exceeded a resource limit
+fn stage_error(error: anyhow::Error, stage: fn(String) -> Error) -> Error {
+    let limit = error.downcast_ref::<LimitExceeded>().is_some()
+        || matches!(
+            error.downcast_ref::<IrError>(),
+            Some(IrError::InstantiationTooDeep { .. })
+        );
+    if limit {
+        Error::Limit(describe(error))
+    } else {
+        stage(describe(error))
+    }
+}
+
 /// Run a lightweight compilation that only returns the balance check result.
 ///
 /// This is much faster than full compilation when you only need to check
@@ -327,6 +594,7 @@ pub fn compile_from_ast_ref(
 pub fn check_balance_only(
     def: &StoredDefinition,
     model_name: Option<&str>,
+    limits: &Limits,
 ) -> Result<crate::dae::balance::BalanceResult> {
     let model_name_str = model_name.unwrap_or("");
     let cache_key = compute_dae_cache_key(model_name_str, def);
@@ -354,12 +622,14 @@ pub fn check_balance_only(
     }
 
     // Cache miss - compute balance with dependency tracking
-    let flatten_result = flatten_with_deps(def, model_name);
+    let flatten_result = FlattenContext::new(def)
+        .with_limits(*limits)
+        .flatten(model_name);
 
     let mut fclass = match flatten_result {
         Ok(fr) => fr,
         Err(e) => {
-            return Err(anyhow::anyhow!("Flatten error: {}", e));
+            return Err(Error::Flatten(format!("Flatten error: {:#}", e)));
         }
     };
 
@@ -385,10 +655,10 @@ pub fn check_balance_only(
     expand_array_comprehensions(&mut fclass.class);
 
     // Expand structured equations to scalar form
-    expand_equations(&mut fclass.class);
+    expand_equations(&mut fclass.class, limits).map_err(|e| Error::Type(describe(e)))?;
 
     // Create DAE
-    let dae = create_dae(&mut fclass.class)?;
+    let dae = create_dae(&mut fclass.class).map_err(|e| Error::Balance(describe(e)))?;
 
     // Check model balance
     let result = dae.check_balance();
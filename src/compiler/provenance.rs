@@ -0,0 +1,216 @@
+//! Provenance of a compiled model, for traceable generated code.
+//!
+//! A [`Provenance`] records what a model was compiled from: the model name,
+//! the rumoca version, the compile date and the MD5 hashes of the sources.
+//! Templates get it as the `provenance` context object, and the formatted
+//! [`header`](Provenance::header) can be put in a comment at the top of the
+//! generated code, e.g. with the `comment` template filter:
+//!
+//! ```jinja
+//! {{ provenance.header | comment("#") }}
+//! ```
+//!
+//! Libraries (like the Modelica Standard Library, which has thousands of
+//! files) are summarized in the header by a single digest of their hashes.
+//! The compile date honors `SOURCE_DATE_EPOCH`, for reproducible builds.
+
+use crate::dae::ast::Dae;
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// A source file and the MD5 hash of its contents
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceFile {
+    /// Path of the file, as given to the compiler
+    pub file: String,
+    /// MD5 hash of the contents, empty if unknown
+    pub md5: String,
+}
+
+impl SourceFile {
+    pub fn new(file: &str, md5: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            md5: md5.to_string(),
+        }
+    }
+}
+
+/// What a model was compiled from, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Name of the compiled model
+    pub model: String,
+    /// Version of rumoca
+    pub rumoca_version: String,
+    /// Git version of rumoca
+    pub git_version: String,
+    /// Compile date, RFC 3339 in UTC (e.g. `2024-01-31T12:00:00Z`)
+    pub compiled_at: String,
+    /// The main source file
+    pub source: SourceFile,
+    /// Library files compiled with the main source
+    pub libraries: Vec<SourceFile>,
+    /// MD5 digest of the hashes of all libraries (empty if there are none,
+    /// or a hash is unknown)
+    pub libraries_md5: String,
+    /// Custom header text, e.g. a license notice
+    pub license: String,
+    /// The license text and provenance lines, for a comment at the top of
+    /// generated code
+    pub header: String,
+}
+
+impl Provenance {
+    /// Provenance of a DAE compiled from `source` and `libraries`, with an
+    /// optional custom header text
+    pub fn new(dae: &Dae, source: SourceFile, libraries: Vec<SourceFile>, license: &str) -> Self {
+        let libraries_md5 = if libraries.is_empty() || libraries.iter().any(|l| l.md5.is_empty()) {
+            String::new()
+        } else {
+            // Sorted, so the digest doesn't depend on the file order or paths
+            let mut hashes: Vec<&str> = libraries.iter().map(|l| l.md5.as_str()).collect();
+            hashes.sort_unstable();
+            format!("{:x}", chksum_md5::hash(hashes.join("\n")))
+        };
+        let mut provenance = Self {
+            model: dae.model_name.clone(),
+            rumoca_version: dae.rumoca_version.clone(),
+            git_version: dae.git_version.clone(),
+            compiled_at: format_utc(compile_timestamp()),
+            source,
+            libraries,
+            libraries_md5,
+            license: license.trim_end().to_string(),
+            header: String::new(),
+        };
+        provenance.header = provenance.lines().join("\n");
+        provenance
+    }
+
+    /// Change the name of the model, e.g. of a model compiled through a
+    /// wrapper extending it
+    pub(crate) fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+        self.header = self.lines().join("\n");
+    }
+
+    /// Lines of the header: the license text, then one line per field
+    fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.license.lines().map(str::to_string).collect();
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("Model: {}", self.model));
+        lines.push(format!(
+            "Generated by: rumoca {} ({})",
+            self.rumoca_version, self.git_version
+        ));
+        lines.push(format!("Compiled at: {}", self.compiled_at));
+        lines.push(match self.source.file.as_str() {
+            "" => format!("Source: md5 {}", self.source.md5),
+            file => format!("Source: {} (md5 {})", file, self.source.md5),
+        });
+        if !self.libraries.is_empty() {
+            let files = match self.libraries.len() {
+                1 => "1 file".to_string(),
+                n => format!("{} files", n),
+            };
+            lines.push(match self.libraries_md5.as_str() {
+                "" => format!("Libraries: {}", files),
+                md5 => format!("Libraries: {} (md5 {})", files, md5),
+            });
+        }
+        lines
+    }
+
+    /// The header as a comment, each line starting with `prefix` (e.g. `#`
+    /// or `//`), with a trailing newline
+    pub fn stamp(&self, prefix: &str) -> String {
+        let mut stamp = comment(&self.header, prefix);
+        stamp.push('\n');
+        stamp
+    }
+}
+
+/// Prefix each line of `text` with a comment `prefix` and a space
+pub fn comment(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                prefix.to_string()
+            } else {
+                format!("{} {}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Seconds since the Unix epoch to stamp: `SOURCE_DATE_EPOCH` if set, else now
+fn compile_timestamp() -> i64 {
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH")
+        && let Ok(seconds) = epoch.trim().parse()
+    {
+        return seconds;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 date in UTC
+fn format_utc(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar),
+    // counting in 400 year eras that start on March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc(1_706_702_400), "2024-01-31T12:00:00Z");
+        assert_eq!(format_utc(1_709_251_199), "2024-02-29T23:59:59Z");
+        assert_eq!(format_utc(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_comment() {
+        assert_eq!(
+            comment("Copyright\n\nModel: M", "//"),
+            "// Copyright\n//\n// Model: M"
+        );
+    }
+}
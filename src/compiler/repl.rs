@@ -0,0 +1,516 @@
+//! Interactive shell for exploring a compiled model, `rumoca repl`.
+//!
+//! A [`Repl`] holds a compiled model and executes one line at a time:
+//! commands starting with `:` query or change the model, and any other line
+//! is a constant expression evaluated with the parameter values, e.g.
+//! `2 * pi * f0`:
+//!
+//! ```text
+//! :load FILE [MODEL]   compile MODEL of FILE (by default the current model)
+//! :reload              compile the current file again, e.g. after editing it
+//! :vars [FILTER]       states, algebraic, input and discrete variables
+//! :params [FILTER]     parameters and constants with their values
+//! :flat [FILTER]       equations before BLT, as flattened
+//! :eqs [FILTER]        equations after BLT, sorted, with their ids
+//! :set NAME = VALUE    change a parameter and compile again
+//! :reset               undo all :set
+//! :balance             equations and unknowns of each component
+//! :render TEMPLATE     render a template with the current model
+//! :help                list the commands
+//! :quit                leave the shell
+//! ```
+//!
+//! Filters keep the lines containing the filter text, e.g. a variable name
+//! or an equation id. `:set` applies to structural parameters too (e.g. the
+//! size of an array): the model is compiled again as a model extending it
+//! with the values as modifications, `extends M(n = 3)`, so the balance and
+//! the equations reflect the new value.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use indexmap::IndexMap;
+
+use super::{CompilationResult, Compiler, parse_source_simple, read_source};
+use crate::dae::balance::format_component_balance;
+use crate::ir::analysis::division_check::evaluate;
+use crate::ir::ast::{Component, Expression};
+use crate::ir::transform::constant_substitutor::ConstantSubstitutor;
+use crate::ir::visitor::MutVisitable;
+
+/// Commands of the shell and their descriptions
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        ":load FILE [MODEL]",
+        "compile MODEL of FILE (by default the current model)",
+    ),
+    (":reload", "compile the current file again"),
+    (
+        ":vars [FILTER]",
+        "states, algebraic, input and discrete variables",
+    ),
+    (
+        ":params [FILTER]",
+        "parameters and constants with their values",
+    ),
+    (":flat [FILTER]", "equations before BLT, as flattened"),
+    (
+        ":eqs [FILTER]",
+        "equations after BLT, sorted, with their ids",
+    ),
+    (":set NAME = VALUE", "change a parameter and compile again"),
+    (":reset", "undo all :set"),
+    (":balance", "equations and unknowns of each component"),
+    (
+        ":render TEMPLATE",
+        "render a template with the current model",
+    ),
+    (":help", "list the commands"),
+    (":quit", "leave the shell"),
+    (
+        "EXPRESSION",
+        "evaluate a constant expression, e.g. 2 * pi * f0",
+    ),
+];
+
+/// Interactive model shell, see the [module docs](self)
+pub struct Repl {
+    compiler: Compiler,
+    session: Option<Session>,
+}
+
+/// The loaded model
+struct Session {
+    file_name: String,
+    source: String,
+    model: String,
+    /// Parameter values set with `:set`, as Modelica expressions
+    overrides: IndexMap<String, String>,
+    result: CompilationResult,
+}
+
+/// What to do after executing a line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// Print the text (nothing if it's empty) and read the next line
+    Output(String),
+    /// Leave the shell
+    Quit,
+}
+
+impl Repl {
+    /// A shell compiling models with `compiler`, e.g. with library paths
+    pub fn new(compiler: Compiler) -> Self {
+        Self {
+            compiler,
+            session: None,
+        }
+    }
+
+    /// Compile a model of a file
+    pub fn load_file(&mut self, path: &str, model: &str) -> Result<String> {
+        let source = read_source(std::path::Path::new(path))?;
+        self.load_source(&source, path, model)
+    }
+
+    /// Compile a model of a source, `file_name` being the name used in
+    /// diagnostics
+    pub fn load_source(&mut self, source: &str, file_name: &str, model: &str) -> Result<String> {
+        let result = self.compile(source, file_name, model, &IndexMap::new())?;
+        let summary = summary(&result);
+        self.session = Some(Session {
+            file_name: file_name.to_string(),
+            source: source.to_string(),
+            model: model.to_string(),
+            overrides: IndexMap::new(),
+            result,
+        });
+        Ok(summary)
+    }
+
+    /// The compiled model, if one is loaded
+    pub fn result(&self) -> Option<&CompilationResult> {
+        self.session.as_ref().map(|session| &session.result)
+    }
+
+    /// Execute a line, see the [module docs](self)
+    pub fn execute(&mut self, line: &str) -> Result<Reply> {
+        let line = line.trim();
+        let Some(command) = line.strip_prefix(':') else {
+            if line.is_empty() {
+                return Ok(Reply::Output(String::new()));
+            }
+            return self.evaluate(line).map(Reply::Output);
+        };
+        let (command, arg) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(command, arg)| (command, arg.trim()));
+        let output = match command {
+            "q" | "quit" | "exit" => return Ok(Reply::Quit),
+            "h" | "help" => help(),
+            "load" => {
+                let mut args = arg.split_whitespace();
+                let Some(file) = args.next() else {
+                    bail!("usage: :load FILE [MODEL]");
+                };
+                let model = match (args.next(), &self.session) {
+                    (Some(model), _) => model.to_string(),
+                    (None, Some(session)) => session.model.clone(),
+                    (None, None) => bail!("no model loaded, use :load FILE MODEL"),
+                };
+                self.load_file(file, &model)?
+            }
+            "reload" => {
+                let session = self.session()?;
+                let (file, model) = (session.file_name.clone(), session.model.clone());
+                let overrides = session.overrides.clone();
+                let source = read_source(std::path::Path::new(&file))?;
+                let result = self.compile(&source, &file, &model, &overrides)?;
+                let summary = summary(&result);
+                let session = self.session_mut()?;
+                session.source = source;
+                session.result = result;
+                summary
+            }
+            "vars" => self.vars(arg)?,
+            "params" => self.params(arg)?,
+            "flat" => {
+                let class = &self.session()?.result.expanded_class;
+                let equations = class.equations.iter().map(|eq| eq.to_string());
+                let initial = class
+                    .initial_equations
+                    .iter()
+                    .map(|eq| format!("initial: {}", eq));
+                filtered(equations.chain(initial), arg)
+            }
+            "eqs" => {
+                let dae = &self.session()?.result.dae;
+                let partitions = [
+                    (&dae.fx, &dae.eq_ids.fx),
+                    (&dae.fx_init, &dae.eq_ids.fx_init),
+                    (&dae.fz, &dae.eq_ids.fz),
+                    (&dae.fm, &dae.eq_ids.fm),
+                ];
+                let equations = partitions
+                    .into_iter()
+                    .flat_map(|(equations, ids)| equations.iter().zip(ids))
+                    .map(|(eq, id)| format!("{}: {}", id, eq));
+                filtered(equations, arg)
+            }
+            "set" => {
+                let Some((name, value)) = arg.split_once('=') else {
+                    bail!("usage: :set NAME = VALUE");
+                };
+                let (name, value) = (name.trim(), value.trim());
+                if name.is_empty() || value.is_empty() {
+                    bail!("usage: :set NAME = VALUE");
+                }
+                let mut overrides = self.session()?.overrides.clone();
+                overrides.insert(name.to_string(), value.to_string());
+                self.recompile(overrides)?
+            }
+            "reset" => self.recompile(IndexMap::new())?,
+            "balance" => {
+                let dae = &self.session()?.result.dae;
+                format!(
+                    "{}{}",
+                    format_component_balance(&dae.component_balance()),
+                    dae.check_balance().status_message()
+                )
+            }
+            "render" => {
+                if arg.is_empty() {
+                    bail!("usage: :render TEMPLATE");
+                }
+                let session = self.session_mut()?;
+                let output = session.result.render_template_to_string(arg)?;
+                output.trim_end().to_string()
+            }
+            _ => bail!("unknown command ':{}', see :help", command),
+        };
+        Ok(Reply::Output(output))
+    }
+
+    fn session(&self) -> Result<&Session> {
+        self.session
+            .as_ref()
+            .context("no model loaded, use :load FILE MODEL")
+    }
+
+    fn session_mut(&mut self) -> Result<&mut Session> {
+        self.session
+            .as_mut()
+            .context("no model loaded, use :load FILE MODEL")
+    }
+
+    /// Compile a model, with parameter values as modifications of a model
+    /// extending it
+    fn compile(
+        &self,
+        source: &str,
+        file_name: &str,
+        model: &str,
+        overrides: &IndexMap<String, String>,
+    ) -> Result<CompilationResult> {
+        let compiler = with_libraries(self.compiler.clone(), model);
+        if overrides.is_empty() {
+            return Ok(compiler.model(model).compile_str(source, file_name)?);
+        }
+        let wrapper = format!("Repl_{}", model.replace('.', "_"));
+        let modifications: Vec<String> = overrides
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        let source = format!(
+            "{}\n\nmodel {}\n  extends {}({});\nend {};\n",
+            source,
+            wrapper,
+            model,
+            modifications.join(", "),
+            wrapper
+        );
+        let mut result = compiler.model(&wrapper).compile_str(&source, file_name)?;
+        result.dae.model_name = model.to_string();
+        result.provenance.set_model(model);
+        Ok(result)
+    }
+
+    /// Compile the current model again with other parameter values, keeping
+    /// the previous ones if that fails
+    fn recompile(&mut self, overrides: IndexMap<String, String>) -> Result<String> {
+        let session = self.session()?;
+        let result = self.compile(
+            &session.source,
+            &session.file_name,
+            &session.model,
+            &overrides,
+        )?;
+        let summary = summary(&result);
+        let session = self.session_mut()?;
+        session.overrides = overrides;
+        session.result = result;
+        Ok(summary)
+    }
+
+    /// The unknowns of the model with their kinds
+    fn vars(&self, filter: &str) -> Result<String> {
+        let dae = &self.session()?.result.dae;
+        let kinds = [
+            (&dae.x, "state"),
+            (&dae.y, "algebraic"),
+            (&dae.u, "input"),
+            (&dae.z, "discrete"),
+            (&dae.m, "discrete"),
+        ];
+        let rows = kinds.into_iter().flat_map(|(components, kind)| {
+            components
+                .iter()
+                .map(move |(name, comp)| (name.clone(), format!("{} {}", kind, comp.type_name)))
+        });
+        Ok(filtered(table(rows), filter))
+    }
+
+    /// The parameters and constants with their values, and the value they
+    /// evaluate to if it's an expression
+    fn params(&self, filter: &str) -> Result<String> {
+        let dae = &self.session()?.result.dae;
+        let values = parameter_values(&self.session()?.result);
+        let value_of = |name: &str, comp: &Component| {
+            let mut text = comp.start.to_string();
+            if let Some(value) = values.get(name)
+                && !matches!(comp.start, Expression::Terminal { .. })
+            {
+                text += &format!(" (= {})", value);
+            }
+            if dae.structural.iter().any(|s| s == name) {
+                text += " [structural]";
+            }
+            text
+        };
+        let rows = dae
+            .p
+            .iter()
+            .map(|(name, comp)| (name.clone(), format!("= {}", value_of(name, comp))))
+            .chain(dae.cp.iter().map(|(name, comp)| {
+                (
+                    name.clone(),
+                    format!("= {} [constant]", value_of(name, comp)),
+                )
+            }));
+        Ok(filtered(table(rows), filter))
+    }
+
+    /// Evaluate a constant expression with the parameter values
+    fn evaluate(&self, expression: &str) -> Result<String> {
+        let result = &self.session()?.result;
+        let source = format!(
+            "model Evaluate\n  constant Real value = {};\nend Evaluate;\n",
+            expression
+        );
+        let mut expr = parse_source_simple(&source, "repl.mo")
+            .and_then(|def| {
+                def.class_list
+                    .get("Evaluate")?
+                    .components
+                    .get("value")
+                    .cloned()
+            })
+            .map(|value| value.start)
+            .with_context(|| format!("invalid expression '{}'", expression))?;
+        expr.accept_mut(&mut ConstantSubstitutor::new());
+        match evaluate(&expr, &parameter_values(result)) {
+            Some(value) => Ok(value.to_string()),
+            None => bail!(
+                "'{}' is not a constant expression of the parameters",
+                expression
+            ),
+        }
+    }
+}
+
+/// Also compile the package of a model and the Modelica Standard Library
+/// from the library paths, if they are there
+fn with_libraries(mut compiler: Compiler, model: &str) -> Compiler {
+    let root_package = model.split('.').next().unwrap_or_default();
+    for package in [root_package, "Modelica"] {
+        if let Ok(with_package) = compiler.clone().include_from_modelica_path(package) {
+            compiler = with_package;
+        }
+    }
+    compiler
+}
+
+/// One line about a compiled model
+fn summary(result: &CompilationResult) -> String {
+    let dae = &result.dae;
+    format!(
+        "{}: {} states, {} algebraic, {} parameters, {} equations, {}",
+        dae.model_name,
+        dae.x.len(),
+        dae.y.len(),
+        dae.p.len(),
+        dae.fx.len(),
+        result.balance.status_message()
+    )
+}
+
+/// Values of the parameters and constants, which may be bound to each other
+fn parameter_values(result: &CompilationResult) -> HashMap<String, f64> {
+    let dae = &result.dae;
+    let mut values = HashMap::new();
+    loop {
+        let mut changed = false;
+        for (name, comp) in dae.p.iter().chain(&dae.cp) {
+            if !values.contains_key(name)
+                && let Some(value) = evaluate(&comp.start, &values)
+            {
+                values.insert(name.clone(), value);
+                changed = true;
+            }
+        }
+        if !changed {
+            return values;
+        }
+    }
+}
+
+/// Rows of names and descriptions, with the names aligned
+fn table(rows: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let rows: Vec<_> = rows.collect();
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    rows.into_iter()
+        .map(|(name, description)| format!("{:<width$}  {}", name, description))
+        .collect()
+}
+
+/// The lines containing a filter text, all lines for an empty filter
+fn filtered(lines: impl IntoIterator<Item = String>, filter: &str) -> String {
+    lines
+        .into_iter()
+        .filter(|line| line.contains(filter))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn help() -> String {
+    let width = COMMANDS
+        .iter()
+        .map(|(usage, _)| usage.len())
+        .max()
+        .unwrap_or(0);
+    COMMANDS
+        .iter()
+        .map(|(usage, description)| format!("{:<width$}  {}", usage, description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repl() {
+        let source = r#"
+model M
+  parameter Integer n = 2;
+  parameter Real k = 1;
+  parameter Real tau = 2 * k;
+  Real x[n](each start = 1);
+  Real y;
+equation
+  for i in 1:n loop
+    der(x[i]) = -x[i] / tau;
+  end for;
+  y = x[1] + x[n];
+end M;
+"#;
+        let mut repl = Repl::new(Compiler::new());
+        let output = |repl: &mut Repl, line: &str| match repl.execute(line).unwrap() {
+            Reply::Output(output) => output,
+            Reply::Quit => panic!("unexpected quit"),
+        };
+
+        let err = repl.execute(":vars").unwrap_err().to_string();
+        assert!(err.contains("no model loaded"));
+        let summary = repl.load_source(source, "m.mo", "M").unwrap();
+        assert_eq!(
+            summary,
+            "M: 2 states, 1 algebraic, 3 parameters, 3 equations, balanced"
+        );
+
+        assert_eq!(
+            output(&mut repl, ":vars"),
+            "x[1]  state Real\nx[2]  state Real\ny     algebraic Real"
+        );
+        assert_eq!(output(&mut repl, ":params tau"), "tau  = 2 * k (= 2)");
+        assert_eq!(output(&mut repl, "tau * 3 + n"), "8");
+        assert!(repl.execute("x[1] + 1").is_err());
+        assert!(repl.execute(":frobnicate").is_err());
+
+        // Equations before BLT as declared, after BLT sorted, with ids
+        assert!(output(&mut repl, ":flat").starts_with("for i in 1:n loop"));
+        assert_eq!(output(&mut repl, ":flat y ="), "y = x[1] + x[n]");
+        let after = output(&mut repl, ":eqs y");
+        assert_eq!(after.lines().count(), 1);
+        assert!(after.starts_with("fx_"));
+
+        // Structural parameters can be set too, with the balance of the
+        // recompiled model
+        let summary = output(&mut repl, ":set n = 3");
+        assert!(summary.starts_with("M: 3 states"));
+        assert!(output(&mut repl, ":balance").ends_with("balanced"));
+        assert_eq!(output(&mut repl, "tau"), "2");
+        output(&mut repl, ":set k = 4");
+        assert_eq!(output(&mut repl, "tau"), "8");
+        assert_eq!(repl.result().unwrap().dae.model_name, "M");
+
+        // A failing change keeps the previous model
+        assert!(repl.execute(":set nope = 1").is_err());
+        assert!(repl.execute(":set n = 2").is_ok());
+        assert!(output(&mut repl, ":reset").starts_with("M: 2 states"));
+        assert_eq!(output(&mut repl, "tau"), "2");
+
+        assert_eq!(repl.execute(":quit").unwrap(), Reply::Quit);
+    }
+}
@@ -0,0 +1,292 @@
+//! Resolution of `modelica://` URIs to files (Modelica spec §13.5).
+//!
+//! Models reference files shipped with a library, like the tables of
+//! `table1D` and `table2D`, by URIs naming a class and a path relative to the
+//! directory the class is stored in:
+//!
+//! ```modelica
+//! y = table1D("modelica://Lib.Engines/Resources/efficiency.csv", speed);
+//! ```
+//!
+//! `Lib` is looked up like a library (in the explicit library paths or
+//! `MODELICAPATH`, or among the packages the model was loaded from), and
+//! `Lib.Engines` in the subdirectories of `Lib`. A class stored in a file,
+//! rather than a directory of its own, has the directory of the file.
+//!
+//! The [`Compiler`](crate::Compiler) replaces the URIs of the DAE by the paths
+//! of their files, lists them in
+//! [`CompilationResult::resources`](crate::CompilationResult::resources) (e.g.
+//! to copy them along with generated code), and warns about the ones that
+//! can't be resolved with [`DiagnosticCode::MissingResource`].
+//!
+//! [`DiagnosticCode::MissingResource`]: crate::DiagnosticCode::MissingResource
+
+use std::path::{Path, PathBuf};
+
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::compiler::paths::percent_decode;
+use crate::dae::ast::Dae;
+use crate::ir::ast::{Expression, Location, TerminalType};
+use crate::ir::transform::multi_file::{find_package_in_paths, is_modelica_package};
+use crate::ir::visitor::{MutVisitable, MutVisitor};
+
+/// Scheme of the URIs of resources
+pub const MODELICA_URI_SCHEME: &str = "modelica://";
+
+/// Error resolving a `modelica://` URI
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ResourceError {
+    #[error("'{0}' is not a modelica:// URI naming a class")]
+    InvalidUri(String),
+    #[error("package '{package}' of '{uri}' is not in the library paths")]
+    UnknownPackage { uri: String, package: String },
+    #[error("resource '{uri}' not found at '{}'", path.display())]
+    Missing { uri: String, path: PathBuf },
+}
+
+/// A resource referenced by a compiled model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource {
+    /// URI as written in the model
+    pub uri: String,
+    /// Path of the file
+    pub path: PathBuf,
+}
+
+/// Resolves `modelica://` URIs, see the [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct ResourceResolver {
+    /// Directories containing libraries, or the directories of packages
+    search_paths: IndexSet<PathBuf>,
+}
+
+impl ResourceResolver {
+    /// A resolver looking up libraries in the given directories, which may
+    /// also be the directories of packages themselves
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        Self {
+            search_paths: search_paths.into_iter().collect(),
+        }
+    }
+
+    /// Add the directories of the packages a file belongs to, and the
+    /// directory of the file, to the search paths
+    pub fn add_file(&mut self, file: &Path) {
+        let mut dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+        while let Some(parent) = dir {
+            if !self.search_paths.insert(parent.to_path_buf()) || !is_modelica_package(parent) {
+                break;
+            }
+            dir = parent.parent().filter(|dir| !dir.as_os_str().is_empty());
+        }
+    }
+
+    /// Directory of a class, e.g. `Lib.Engines`
+    pub fn class_directory(&self, class: &str) -> Option<PathBuf> {
+        let mut parts = class.split('.');
+        let top = parts.next().filter(|top| !top.is_empty())?;
+        let package = self
+            .search_paths
+            .iter()
+            .find(|path| {
+                path.file_name().is_some_and(|name| name == top) && is_modelica_package(path)
+            })
+            .cloned()
+            .or_else(|| {
+                let search_paths: Vec<PathBuf> = self.search_paths.iter().cloned().collect();
+                find_package_in_paths(top, &search_paths)
+            })?;
+        // A single-file package has the directory of its file
+        let mut dir = if package.is_dir() {
+            package
+        } else {
+            package.parent()?.to_path_buf()
+        };
+        for part in parts {
+            let sub = dir.join(part);
+            if !sub.is_dir() {
+                break;
+            }
+            dir = sub;
+        }
+        Some(dir)
+    }
+
+    /// Path of the file of a `modelica://` URI
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI is malformed, its package isn't found, or
+    /// the file doesn't exist.
+    pub fn resolve(&self, uri: &str) -> Result<PathBuf, ResourceError> {
+        let invalid = || ResourceError::InvalidUri(uri.to_string());
+        let rest = uri.strip_prefix(MODELICA_URI_SCHEME).ok_or_else(invalid)?;
+        let (class, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if class.is_empty() {
+            return Err(invalid());
+        }
+        let dir = self
+            .class_directory(class)
+            .ok_or_else(|| ResourceError::UnknownPackage {
+                uri: uri.to_string(),
+                package: class.split('.').next().unwrap_or(class).to_string(),
+            })?;
+        let path = dir.join(percent_decode(path));
+        if !path.exists() {
+            return Err(ResourceError::Missing {
+                uri: uri.to_string(),
+                path,
+            });
+        }
+        Ok(path)
+    }
+
+    /// Replace the URIs in the string literals of a DAE by the paths of their
+    /// files, returning the resolved resources and the URIs that could not be
+    /// resolved, with their locations
+    pub fn resolve_dae(&self, dae: &mut Dae) -> (Vec<Resource>, Vec<(ResourceError, Location)>) {
+        let mut resolver = UriRewriter {
+            resolver: self,
+            resources: IndexMap::new(),
+            errors: Vec::new(),
+        };
+        for comp in [&mut dae.p, &mut dae.cp, &mut dae.x, &mut dae.y, &mut dae.u]
+            .into_iter()
+            .chain([&mut dae.z, &mut dae.m])
+            .flat_map(|components| components.values_mut())
+        {
+            comp.accept_mut(&mut resolver);
+        }
+        for eq in dae
+            .fx
+            .iter_mut()
+            .chain(&mut dae.fx_init)
+            .chain(&mut dae.fz)
+            .chain(&mut dae.fm)
+            .chain(&mut dae.asserts)
+        {
+            eq.accept_mut(&mut resolver);
+        }
+        for stmt in dae.fr.values_mut() {
+            stmt.accept_mut(&mut resolver);
+        }
+        for expr in dae.fc.values_mut() {
+            expr.accept_mut(&mut resolver);
+        }
+        let resources = resolver
+            .resources
+            .into_iter()
+            .map(|(uri, path)| Resource { uri, path })
+            .collect();
+        (resources, resolver.errors)
+    }
+}
+
+/// Whether a string is a `modelica://` URI
+pub fn is_modelica_uri(text: &str) -> bool {
+    text.starts_with(MODELICA_URI_SCHEME)
+}
+
+struct UriRewriter<'a> {
+    resolver: &'a ResourceResolver,
+    resources: IndexMap<String, PathBuf>,
+    errors: Vec<(ResourceError, Location)>,
+}
+
+impl MutVisitor for UriRewriter<'_> {
+    fn exit_expression(&mut self, node: &mut Expression) {
+        let Expression::Terminal {
+            terminal_type: TerminalType::String,
+            token,
+        } = node
+        else {
+            return;
+        };
+        if !is_modelica_uri(&token.text) {
+            return;
+        }
+        let uri = token.text.clone();
+        let path = match self.resources.get(&uri) {
+            Some(path) => path.clone(),
+            None => match self.resolver.resolve(&uri) {
+                Ok(path) => {
+                    self.resources.insert(uri.clone(), path.clone());
+                    path
+                }
+                Err(error) => {
+                    // Report each URI once
+                    if !self.errors.iter().any(|(e, _)| *e == error) {
+                        self.errors.push((error, token.location.clone()));
+                    }
+                    return;
+                }
+            },
+        };
+        token.text = path.to_string_lossy().into_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let root = tempfile::tempdir().unwrap();
+        let lib = root.path().join("Lib");
+        std::fs::create_dir_all(lib.join("Engines/Resources")).unwrap();
+        std::fs::write(lib.join("package.mo"), "package Lib\nend Lib;\n").unwrap();
+        std::fs::write(
+            lib.join("Engines/package.mo"),
+            "within Lib;\npackage Engines\nend Engines;\n",
+        )
+        .unwrap();
+        std::fs::write(lib.join("Engines/Resources/eta table.csv"), "0,1\n").unwrap();
+        std::fs::write(
+            lib.join("Pumps.mo"),
+            "within Lib;\npackage Pumps\nend Pumps;\n",
+        )
+        .unwrap();
+
+        let resolver = ResourceResolver::new(vec![root.path().to_path_buf()]);
+        assert_eq!(
+            resolver.resolve("modelica://Lib.Engines/Resources/eta%20table.csv"),
+            Ok(lib.join("Engines/Resources/eta table.csv"))
+        );
+        // Classes stored in files have the directory of the file
+        assert_eq!(resolver.resolve("modelica://Lib.Pumps/"), Ok(lib.clone()));
+        // Packages are also found by their own directories
+        let resolver = ResourceResolver::new(vec![lib.clone()]);
+        assert_eq!(
+            resolver.class_directory("Lib.Engines"),
+            Some(lib.join("Engines"))
+        );
+
+        assert_eq!(
+            resolver.resolve("modelica://Lib/Resources/missing.csv"),
+            Err(ResourceError::Missing {
+                uri: "modelica://Lib/Resources/missing.csv".to_string(),
+                path: lib.join("Resources/missing.csv"),
+            })
+        );
+        // Malformed escapes are kept as they are, like in file:// URIs
+        assert_eq!(
+            resolver.resolve("modelica://Lib/Resources/100%.csv"),
+            Err(ResourceError::Missing {
+                uri: "modelica://Lib/Resources/100%.csv".to_string(),
+                path: lib.join("Resources/100%.csv"),
+            })
+        );
+        assert!(matches!(
+            resolver.resolve("modelica://Other/file.csv"),
+            Err(ResourceError::UnknownPackage { package, .. }) if package == "Other"
+        ));
+        assert!(matches!(
+            resolver.resolve("file:///tmp/file.csv"),
+            Err(ResourceError::InvalidUri(_))
+        ));
+    }
+}
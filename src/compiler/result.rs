@@ -4,17 +4,24 @@
 //! the output of a successful compilation, including the DAE representation
 //! and timing information.
 
+use super::conformance::RelaxedConstruct;
+use super::resources::Resource;
+use super::{CompileWarning, Provenance, Topology};
 use crate::dae::ast::Dae;
 use crate::dae::balance::BalanceResult;
+use crate::dae::jinja::render_error;
+use crate::error::{Error, Result};
 use crate::ir::ast::{ClassDefinition, StoredDefinition};
-use anyhow::{Context, Result};
+use crate::ir::structural::BltResult;
+use serde::{Deserialize, Serialize};
 use std::fs;
 
 /// The result of a successful compilation.
 ///
 /// Contains the compiled DAE representation along with timing information
-/// and intermediate representations.
-#[derive(Debug)]
+/// and intermediate representations. It can be serialized with serde to
+/// snapshot a compilation, e.g. as JSON for tooling outside of Rust.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CompilationResult {
     /// The compiled DAE representation
     pub dae: Dae,
@@ -40,6 +47,34 @@ pub struct CompilationResult {
 
     /// Balance check result
     pub balance: BalanceResult,
+
+    /// What the model was compiled from, for the header of generated code
+    #[serde(default)]
+    pub provenance: Provenance,
+
+    /// Component hierarchy and connect equations of the model, from before
+    /// flattening
+    #[serde(default)]
+    pub topology: Topology,
+
+    /// Warnings of the compilation, e.g. an unbalanced model
+    #[serde(default)]
+    pub warnings: Vec<CompileWarning>,
+
+    /// Constructs accepted outside of the Modelica specification, see
+    /// [`conformance`](crate::compiler::conformance)
+    #[serde(default)]
+    pub relaxations: Vec<RelaxedConstruct>,
+
+    /// BLT result of the continuous equations: their order, and the unknown
+    /// each is solved for
+    #[serde(default)]
+    pub blt: BltResult,
+
+    /// Files referenced by `modelica://` URIs, which the DAE refers to by
+    /// their paths, see [`resources`](crate::compiler::resources)
+    #[serde(default)]
+    pub resources: Vec<Resource>,
 }
 
 impl CompilationResult {
@@ -54,7 +89,7 @@ impl CompilationResult {
     ///     .model("MyModel")
     ///     .compile_file("model.mo")?;
     /// println!("Compiled in {} ms", result.total_time().as_millis());
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn total_time(&self) -> std::time::Duration {
         self.parse_time + self.flatten_time + self.dae_time
@@ -86,20 +121,20 @@ impl CompilationResult {
     ///     .model("MyModel")
     ///     .compile_file("model.mo")?;
     /// result.render_template("template.j2")?; // Prints to stdout
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn render_template(&mut self, template_path: &str) -> Result<()> {
-        let template_content = fs::read_to_string(template_path)
-            .with_context(|| format!("Failed to read template file: {}", template_path))?;
-
-        let template_hash = format!("{:x}", chksum_md5::hash(&template_content));
-        self.dae.template_hash = template_hash;
-
-        crate::dae::jinja::render_template(&self.dae, template_path)
+        println!("{}", self.render_template_to_string(template_path)?);
+        Ok(())
     }
 
     /// Renders the DAE using a Jinja2 template file and returns the result as a string.
     ///
+    /// Templates get the DAE as `dae`, the [`Provenance`] of the model as
+    /// `provenance` and its component hierarchy and connections as
+    /// `topology` (see [`Topology`]). Templates requiring an incompatible
+    /// context version fail before rendering, see [`crate::compiler::context`].
+    ///
     /// # Arguments
     ///
     /// * `template_path` - Path to the Jinja2 template file
@@ -125,26 +160,76 @@ impl CompilationResult {
     ///     .compile_file("model.mo")?;
     /// let code = result.render_template_to_string("template.j2")?;
     /// println!("Generated code:\n{}", code);
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn render_template_to_string(&mut self, template_path: &str) -> Result<String> {
-        use minijinja::{Environment, context};
+        let template_content = self.read_template(template_path)?;
+
+        // Use minijinja to render the template
+        crate::dae::jinja::check_template(&template_content)?;
+        let mut env = crate::dae::jinja::environment();
+        env.add_template("template", &template_content)
+            .map_err(render_error)?;
+        let tmpl = env.get_template("template").map_err(render_error)?;
+        tmpl.render(self.template_context()).map_err(render_error)
+    }
+
+    /// Renders the DAE using a Jinja2 template file like
+    /// [`render_template_to_string`](Self::render_template_to_string), with
+    /// errors giving the line and column of the template they occur at and a
+    /// frame of the template source around it:
+    ///
+    /// ```text
+    /// Template rendering failed: model.jinja:4:11: undefined value
+    ///    3 | {% for name, v in dae.x | items %}
+    ///    4 > {{ v.start.missing.more }}
+    ///      |           ^^^^^^^^^^^^^
+    ///    5 | {% endfor %}
+    /// ```
+    pub fn render_template_debug(&mut self, template_path: &str) -> Result<String> {
+        let template_content = self.read_template(template_path)?;
+        crate::dae::jinja::render_debug(
+            crate::compiler::paths::base_name(template_path),
+            &template_content,
+            &self.template_context(),
+        )
+    }
 
-        let template_content = fs::read_to_string(template_path)
-            .with_context(|| format!("Failed to read template file: {}", template_path))?;
+    /// The context templates are rendered with, as pretty-printed JSON, to
+    /// see what a template can use
+    pub fn template_context_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.template_context())
+            .map_err(|e| Error::Render(format!("Failed to serialize template context: {}", e)))
+    }
 
-        let template_hash = format!("{:x}", chksum_md5::hash(&template_content));
-        self.dae.template_hash = template_hash.clone();
+    /// Warnings for the variables a template file looks up that aren't in
+    /// the rendering context, e.g. `template.j2:12: undefined variable
+    /// 'dae.statse'`, as these render as empty text instead of failing
+    pub fn check_template_lookups(&self, template_path: &str) -> Result<Vec<String>> {
+        let template_content =
+            fs::read_to_string(template_path).map_err(|e| Error::io(template_path, e))?;
+        crate::dae::jinja::undefined_lookups(
+            crate::compiler::paths::base_name(template_path),
+            &template_content,
+            &self.template_context(),
+        )
+    }
 
-        // Use minijinja to render the template
-        let mut env = Environment::new();
-        env.add_function("panic", crate::dae::jinja::panic);
-        env.add_function("warn", crate::dae::jinja::warn);
-        env.add_template("template", &template_content)?;
-        let tmpl = env.get_template("template")?;
-        let output = tmpl.render(context!(dae => &self.dae))?;
-
-        Ok(output)
+    /// Read a template file, recording its hash in the DAE
+    fn read_template(&mut self, template_path: &str) -> Result<String> {
+        let template_content =
+            fs::read_to_string(template_path).map_err(|e| Error::io(template_path, e))?;
+        self.dae.template_hash = format!("{:x}", chksum_md5::hash(&template_content));
+        Ok(template_content)
+    }
+
+    /// The context templates are rendered with
+    fn template_context(&self) -> minijinja::Value {
+        minijinja::context!(
+            dae => &self.dae,
+            provenance => &self.provenance,
+            topology => &self.topology
+        )
     }
 
     /// Returns a reference to the compiled DAE.
@@ -159,7 +244,7 @@ impl CompilationResult {
     ///     .compile_file("model.mo")?;
     /// let dae = result.dae();
     /// println!("States: {:?}", dae.x.keys());
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn dae(&self) -> &Dae {
         &self.dae
@@ -209,11 +294,11 @@ impl CompilationResult {
     ///     .compile_file("model.mo")?;
     /// let json = result.to_dae_ir_json()?;
     /// println!("{}", json);
-    /// # Ok::<(), anyhow::Error>(())
+    /// # Ok::<(), rumoca::Error>(())
     /// ```
     pub fn to_dae_ir_json(&self) -> Result<String> {
         self.dae
             .to_dae_ir_json()
-            .context("Failed to serialize DAE to DAE IR JSON")
+            .map_err(|e| Error::Render(format!("Failed to serialize DAE to DAE IR JSON: {}", e)))
     }
 }
@@ -0,0 +1,99 @@
+//! Source text normalization for the parser front-end.
+//!
+//! Modelica files in the wild come with a UTF-8 byte order mark, Windows (`\r\n`)
+//! or classic Mac (`\r`) line endings, or in ISO-8859-1 (older libraries often
+//! use it for `°` or `µ` in comments and descriptions). The parser only handles
+//! BOM-less UTF-8 with `\n` line endings, so all source text is normalized first.
+//!
+//! Locations produced by the parser count lines after normalization and columns
+//! in Unicode characters (not bytes), which is also the unit the language server
+//! uses for positions.
+
+use super::diagnostics::{CompileWarning, DiagnosticCode};
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::path::Path;
+
+const BOM: char = '\u{feff}';
+
+/// Strip a leading byte order mark and convert `\r\n` and lone `\r` line endings to `\n`.
+///
+/// Returns the input unchanged (borrowed) if it is already normalized.
+pub fn normalize_source(source: &str) -> Cow<'_, str> {
+    let source = source.strip_prefix(BOM).unwrap_or(source);
+    if !source.contains('\r') {
+        return Cow::Borrowed(source);
+    }
+    Cow::Owned(source.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// Decode raw source bytes.
+///
+/// Valid UTF-8 is used as is. Anything else is decoded as ISO-8859-1, which maps
+/// every byte to a character and so never fails (a UTF-8 byte order mark is
+/// dropped first). Returns the text and whether the fallback was used.
+pub fn decode_source(bytes: Vec<u8>) -> (String, bool) {
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, false),
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&bytes);
+            (bytes.iter().map(|&b| b as char).collect(), true)
+        }
+    }
+}
+
+/// Read a Modelica file, decoding and normalizing it for the parser.
+///
+/// Files that are not valid UTF-8 are silently decoded as ISO-8859-1, see
+/// [`read_source_with_warning`] to report it.
+pub fn read_source(path: &Path) -> Result<String> {
+    read_source_with_warning(path).map(|(text, _)| text)
+}
+
+/// Read a Modelica file like [`read_source`], also returning a
+/// [`DiagnosticCode::Encoding`] warning if it had to be decoded as ISO-8859-1.
+pub fn read_source_with_warning(path: &Path) -> Result<(String, Option<CompileWarning>)> {
+    let bytes = std::fs::read(path).map_err(|e| Error::io(path, e))?;
+    let (text, fallback) = decode_source(bytes);
+    let warning = fallback.then(|| encoding_warning(&path.display().to_string()));
+    Ok((normalize_source(&text).into_owned(), warning))
+}
+
+/// Warning for a source that is not valid UTF-8 and was decoded as ISO-8859-1
+pub fn encoding_warning(file_name: &str) -> CompileWarning {
+    CompileWarning {
+        code: DiagnosticCode::Encoding,
+        message: format!("{file_name} is not valid UTF-8, decoded it as ISO-8859-1"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_source() {
+        assert!(matches!(
+            normalize_source("a\nb\n"),
+            Cow::Borrowed("a\nb\n")
+        ));
+        assert_eq!(normalize_source("\u{feff}a\r\nb\rc\n"), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_decode_source() {
+        assert_eq!(
+            decode_source("20°C".as_bytes().to_vec()),
+            ("20°C".to_string(), false)
+        );
+        assert_eq!(
+            decode_source(b"20\xb0C".to_vec()),
+            ("20°C".to_string(), true)
+        );
+        assert_eq!(
+            decode_source(b"\xef\xbb\xbf20\xb0C".to_vec()),
+            ("20°C".to_string(), true)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Component hierarchy and connections of a compiled model.
+//!
+//! Flattening turns a model into a flat list of variables and equations, and
+//! its connect equations into plain equations. Some generators, e.g. for
+//! block diagram tools, need the structure from before: a [`Topology`] keeps
+//! the components that were instantiated, with their classes and nesting,
+//! and the connect equations as edges between connectors. Templates get it
+//! as the `topology` context object:
+//!
+//! ```jinja
+//! {% for name, comp in topology.components | items if comp.parent is none %}
+//! block {{ name }}: {{ comp.type_name }}
+//! {% endfor %}
+//! {% for c in topology.connections %}
+//! edge {{ c.from }} -> {{ c.to }}
+//! {% endfor %}
+//! ```
+
+use crate::ir::ast::{ClassType, ComponentReference};
+use crate::ir::transform::flatten::ComponentInstance;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// A component of the model whose type is a class, e.g. a sub-model, a
+/// connector or a type like `Voltage` (components of the predefined types,
+/// like `Real x`, are only variables of the DAE)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyComponent {
+    /// Resolved class name of the component
+    pub type_name: String,
+    /// Class type of the component's class
+    pub class_type: ClassType,
+    /// Flattened name of the enclosing component, none at the top level
+    pub parent: Option<String>,
+    /// Flattened names of the components it contains
+    pub children: Vec<String>,
+    /// True if the component or one of its enclosing components is conditional
+    pub conditional: bool,
+}
+
+/// A connect equation, as an edge between two connectors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Connection {
+    pub from: String,
+    pub to: String,
+}
+
+/// Component hierarchy and connections of a model, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Topology {
+    /// Components by flattened name, parents before their children
+    pub components: IndexMap<String, TopologyComponent>,
+    /// Connect equations of the model and its components, in order
+    pub connections: Vec<Connection>,
+}
+
+impl Topology {
+    /// Build the topology from the instances and connect equations of a
+    /// flattened model
+    pub fn new(
+        instances: &IndexMap<String, ComponentInstance>,
+        connects: &[(ComponentReference, ComponentReference)],
+    ) -> Self {
+        let mut components: IndexMap<String, TopologyComponent> = instances
+            .iter()
+            .map(|(name, instance)| {
+                let parent = name
+                    .rsplit_once('.')
+                    .map(|(parent, _)| parent)
+                    .filter(|parent| instances.contains_key(*parent));
+                let component = TopologyComponent {
+                    type_name: instance.type_name.clone(),
+                    class_type: instance.class_type.clone(),
+                    parent: parent.map(str::to_string),
+                    children: Vec::new(),
+                    conditional: instance.conditional,
+                };
+                (name.clone(), component)
+            })
+            .collect();
+        for i in 0..components.len() {
+            let (name, component) = components.get_index(i).unwrap();
+            if let Some(parent) = component.parent.clone() {
+                let name = name.clone();
+                components[&parent].children.push(name);
+            }
+        }
+        let connections = connects
+            .iter()
+            .map(|(lhs, rhs)| Connection {
+                from: lhs.to_string(),
+                to: rhs.to_string(),
+            })
+            .collect();
+        Self {
+            components,
+            connections,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    #[test]
+    fn test_topology() {
+        let source = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+model R
+  Pin p, n;
+equation
+  p.v - n.v = p.i;
+  p.i + n.i = 0;
+end R;
+model G
+  Pin p;
+equation
+  p.v = 0;
+end G;
+model Circuit
+  R r[2];
+  G g;
+equation
+  for i in 1:1 loop
+    connect(r[i].n, r[i + 1].p);
+  end for;
+  connect(r[2].n, g.p);
+  connect(r[1].p, g.p);
+end Circuit;
+"#;
+        let result = Compiler::new()
+            .model("Circuit")
+            .compile_str(source, "circuit.mo")
+            .unwrap();
+        let topology = &result.topology;
+
+        let r1 = &topology.components["r[1]"];
+        assert_eq!(r1.type_name, "R");
+        assert_eq!(r1.parent, None);
+        assert_eq!(r1.children, ["r[1].p", "r[1].n"]);
+        assert_eq!(topology.components["g.p"].parent.as_deref(), Some("g"));
+
+        let edges: Vec<(&str, &str)> = topology
+            .connections
+            .iter()
+            .map(|c| (c.from.as_str(), c.to.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            [("r[1].n", "r[2].p"), ("r[2].n", "g.p"), ("r[1].p", "g.p")]
+        );
+    }
+}
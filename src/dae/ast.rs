@@ -44,9 +44,12 @@
 //!
 //! v = fr (v, c)    : happens at event time
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use std::fmt;
+use std::str::FromStr;
 
+use crate::dae::ids::EquationIds;
+use crate::dae::uses::ParameterUses;
 use crate::ir::ast::{Component, Equation, Expression, Statement};
 use serde::{Deserialize, Serialize};
 
@@ -77,6 +80,108 @@ pub struct Dae {
     pub fm: Vec<Equation>,                  // discrete update equations
     pub fr: IndexMap<String, Statement>,    // reset expressions, condition -> assignment statements
     pub fc: IndexMap<String, Expression>,   // condition updates, condition -> expression
+    #[serde(default)]
+    pub eq_ids: EquationIds, // stable ids of the equations in fx, fx_init, fz, fm
+    #[serde(default)]
+    pub asserts: Vec<Equation>, // runtime guards, e.g. nonzero denominators
+    #[serde(default)]
+    pub singular: Vec<SingularEquations>, // equations only solvable for a parameter or input
+    #[serde(default)]
+    pub structural: IndexSet<String>, // parameters whose values must be known at compile time
+    #[serde(default)]
+    pub parameter_uses: IndexMap<String, ParameterUses>, // equations and components depending on each parameter
+    #[serde(default)]
+    pub outputs: Vec<String>, // selected output variables, empty if all variables are outputs
+    #[serde(default)]
+    pub when_clauses: Vec<Vec<String>>, // conditions of the branches of each when-equation, by priority
+    #[serde(default)]
+    pub sort: SortMode, // order of the continuous-time equations fx
+}
+
+/// Order of the continuous-time equations [`Dae::fx`]
+///
+/// Backends doing their own symbolic analysis, or exporting the model
+/// acausally, may prefer the equations as written. The matching is computed
+/// either way, for the balance and the structural checks.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// Sorted into blocks of the BLT form, and solved for their unknowns
+    /// where possible
+    #[default]
+    Blt,
+    /// As declared in the flattened model, after the expansion of arrays and
+    /// for-equations
+    None,
+}
+
+impl SortMode {
+    /// Name of the mode, as in the template context and on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortMode::Blt => "blt",
+            SortMode::None => "none",
+        }
+    }
+}
+
+impl FromStr for SortMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blt" => Ok(SortMode::Blt),
+            "none" => Ok(SortMode::None),
+            _ => Err(format!(
+                "unknown sort mode '{}' (expected one of: blt, none)",
+                s
+            )),
+        }
+    }
+}
+
+/// Equations that could only be solved for a parameter or an input
+///
+/// Found by the BLT matching when an equation is left without an unknown of
+/// its own, e.g. `y = p` next to `y = 1`: the model is structurally singular
+/// and its equations can't be evaluated in any order. The
+/// [`Compiler`](crate::Compiler) fails on them unless it is permissive.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SingularEquations {
+    /// Parameters the equations would have to be solved for
+    pub parameters: Vec<String>,
+    /// Inputs the equations would have to be solved for
+    pub inputs: Vec<String>,
+    /// The equation left without an unknown, followed by the equations that
+    /// determine its unknowns, as declared
+    pub equations: Vec<Equation>,
+}
+
+impl fmt::Display for SingularEquations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let knowns: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|name| format!("parameter '{}'", name))
+            .chain(self.inputs.iter().map(|name| format!("input '{}'", name)))
+            .collect();
+        write!(
+            f,
+            "Structurally singular equations: solving them requires solving for {}",
+            knowns.join(", ")
+        )?;
+        for eq in &self.equations {
+            match eq.get_location() {
+                Some(loc) if !loc.file_name.is_empty() => write!(
+                    f,
+                    "\n  {}:{}:{}: {}",
+                    loc.file_name, loc.start_line, loc.start_column, eq
+                )?,
+                _ => write!(f, "\n  {}", eq)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Dae {
@@ -334,10 +439,12 @@ fn format_start(f: &mut fmt::Formatter<'_>, start: &Expression) -> fmt::Result {
 /// Helper to format a Statement
 fn format_statement(stmt: &Statement) -> String {
     match stmt {
-        Statement::Assignment { comp, value } => format!("{} := {}", comp, value),
+        Statement::Assignment { comp, value, .. } => format!("{} := {}", comp, value),
         Statement::Return { .. } => "return".to_string(),
         Statement::Break { .. } => "break".to_string(),
-        Statement::For { indices, equations } => {
+        Statement::For {
+            indices, equations, ..
+        } => {
             let idx_str = indices
                 .iter()
                 .map(|i| format!("{} in {}", i.ident.text, i.range))
@@ -365,7 +472,7 @@ fn format_statement(stmt: &Statement) -> String {
             s.push_str(" end when");
             s
         }
-        Statement::FunctionCall { comp, args } => {
+        Statement::FunctionCall { comp, args, .. } => {
             let args_str = args
                 .iter()
                 .map(|a| a.to_string())
@@ -376,6 +483,7 @@ fn format_statement(stmt: &Statement) -> String {
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             let mut s = String::new();
             for (i, block) in cond_blocks.iter().enumerate() {
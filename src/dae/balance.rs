@@ -2,14 +2,34 @@
 //!
 //! Simple balance check: count equations vs unknowns from the DAE structure.
 //!
+//! The per-component report ([`Dae::component_balance`]) attributes each scalar
+//! unknown to the component instance that declares it (inputs to the enclosing
+//! instance, which gives their values) and each equation to the innermost
+//! instance containing all unknowns it references, so the submodel
+//! that makes a composed model unbalanced can be located.
+//!
+//! If-equations whose conditions could not be evaluated at compile time count
+//! the equations of one branch. Unless the conditions are parameter expressions,
+//! every branch must contribute the same number (Modelica spec 8.3.4, a missing
+//! `else` being an empty branch), otherwise the model is reported as unbalanced.
+//!
+//! Balanced models also get their structural DAE index ([`DaeIndex`]) from the
+//! Pantelides algorithm, with the equations index reduction has to
+//! differentiate.
+//!
 //! Note: This assumes equations have been expanded to scalar form by the
 //! equation_expander pass before DAE creation.
 
 use super::ast::Dae;
-use crate::ir::ast::{Component, Connection, Statement};
+use crate::ir::ast::{
+    Causality, Component, ComponentReference, Connection, Equation, Expression, Location, Statement,
+};
+use crate::ir::structural::pantelides_index_reduction;
+use crate::ir::visitor::{Visitable, Visitor};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::fmt::Write;
 
 /// Balance status categories
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -41,10 +61,53 @@ pub struct BalanceResult {
     pub num_inputs: usize,
     /// Number of external connector variables (flow variables that need connection equations)
     pub num_external_connectors: usize,
+    /// Number of if-equations whose branches contribute different numbers of equations
+    #[serde(default)]
+    pub num_unequal_if_equations: usize,
     /// Balance status category
     pub status: BalanceStatus,
     /// Whether the model is balanced (for backwards compatibility)
     pub is_balanced: bool,
+    /// Structural DAE index (balanced models only, if it could be determined)
+    #[serde(default)]
+    pub dae_index: Option<DaeIndex>,
+}
+
+/// Structural DAE index of a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaeIndex {
+    /// 0 for an ODE, 1 for a DAE whose algebraic equations can be solved for
+    /// the algebraic variables, 2 or more for a high-index DAE
+    pub index: usize,
+    /// Equations index reduction has to differentiate (high index only),
+    /// most differentiated first
+    pub constraints: Vec<IndexConstraint>,
+}
+
+/// An equation index reduction has to differentiate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexConstraint {
+    /// The equation, as in the DAE
+    pub equation: String,
+    /// Number of times it is differentiated
+    pub differentiations: usize,
+    /// Location of the equation in the source
+    pub location: Option<Location>,
+}
+
+impl DaeIndex {
+    /// Short description, e.g. "ODE" or "index-3 DAE"
+    pub fn description(&self) -> String {
+        match self.index {
+            0 => "ODE".to_string(),
+            index => format!("index-{} DAE", index),
+        }
+    }
+
+    /// Whether the model needs index reduction
+    pub fn is_high_index(&self) -> bool {
+        self.index > 1
+    }
 }
 
 impl BalanceResult {
@@ -58,8 +121,10 @@ impl BalanceResult {
             num_parameters: 0,
             num_inputs: 0,
             num_external_connectors: 0,
+            num_unequal_if_equations: 0,
             status: BalanceStatus::CompileError(message),
             is_balanced: false,
+            dae_index: None,
         }
     }
 
@@ -81,7 +146,12 @@ impl BalanceResult {
             }
             BalanceStatus::Unbalanced => {
                 let diff = self.difference();
-                if diff > 0 {
+                if self.num_unequal_if_equations > 0 {
+                    format!(
+                        "unbalanced: {} if-equation(s) with different equation counts per branch",
+                        self.num_unequal_if_equations
+                    )
+                } else if diff > 0 {
                     format!("unbalanced: over-determined by {}", diff)
                 } else {
                     format!("unbalanced: under-determined by {}", -diff)
@@ -92,7 +162,174 @@ impl BalanceResult {
     }
 }
 
+/// Equations and unknowns contributed by one component instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentBalance {
+    /// Instance path (e.g. `motor.inertia`); empty for the top-level model
+    pub name: String,
+    /// Equations attributed to this instance itself
+    pub num_equations: usize,
+    /// Scalar unknowns declared directly in this instance
+    pub num_unknowns: usize,
+    /// Equations of this instance and all nested instances
+    pub total_equations: usize,
+    /// Unknowns of this instance and all nested instances
+    pub total_unknowns: usize,
+}
+
+impl ComponentBalance {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            num_equations: 0,
+            num_unknowns: 0,
+            total_equations: 0,
+            total_unknowns: 0,
+        }
+    }
+
+    /// Difference between the instance's own equations and unknowns
+    pub fn difference(&self) -> i64 {
+        self.num_equations as i64 - self.num_unknowns as i64
+    }
+
+    /// Difference between equations and unknowns including nested instances
+    pub fn total_difference(&self) -> i64 {
+        self.total_equations as i64 - self.total_unknowns as i64
+    }
+}
+
+/// Render a per-component balance report as an indented text table.
+///
+/// Instances whose own equations and unknowns differ are marked with `<<`.
+pub fn format_component_balance(components: &[ComponentBalance]) -> String {
+    let rows: Vec<(String, &ComponentBalance)> = components
+        .iter()
+        .map(|c| {
+            let depth = instance_depth(&c.name);
+            let label = if c.name.is_empty() {
+                "(top level)".to_string()
+            } else {
+                format!("{}{}", "  ".repeat(depth), c.name)
+            };
+            (label, c)
+        })
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(label, _)| label.len())
+        .chain(std::iter::once("component".len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = format!(
+        "{:<width$}  {:>9}  {:>8}  {:>5}  {:>10}\n",
+        "component", "equations", "unknowns", "diff", "total diff"
+    );
+    for (label, c) in rows {
+        let marker = if c.difference() != 0 { "  <<" } else { "" };
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>9}  {:>8}  {:>5}  {:>10}{}",
+            label,
+            c.num_equations,
+            c.num_unknowns,
+            signed(c.difference()),
+            signed(c.total_difference()),
+            marker
+        );
+    }
+    out
+}
+
 impl Dae {
+    /// Per-component equation and unknown counts, in instance tree order.
+    ///
+    /// Each scalar unknown is attributed to the instance that declares it (an
+    /// input to the instance enclosing that one, which gives its value), and
+    /// each equation to the innermost instance containing every unknown the
+    /// equation references (connection equations between siblings therefore
+    /// land on their common parent, and equations of only the inputs of an
+    /// instance on its parent). The first entry is the top-level model.
+    pub fn component_balance(&self) -> Vec<ComponentBalance> {
+        let mut unknowns: IndexMap<&str, usize> = IndexMap::new();
+        for (name, comp) in self.x.iter().chain(&self.y).chain(&self.z).chain(&self.m) {
+            unknowns.insert(name, comp.shape.iter().product());
+        }
+        let inputs: HashSet<&str> = self
+            .y
+            .iter()
+            .filter(|(_, comp)| matches!(comp.causality, Causality::Input(..)))
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        let mut report: IndexMap<String, ComponentBalance> = IndexMap::new();
+        report.insert(String::new(), ComponentBalance::new(""));
+        for (name, count) in &unknowns {
+            // Inputs of an instance are given by the enclosing instance, with
+            // the equations connecting them
+            let instance = if inputs.contains(name) {
+                instance_of(instance_of(name))
+            } else {
+                instance_of(name)
+            };
+            instance_entry(&mut report, instance).num_unknowns += count;
+        }
+
+        for eq in self.fx.iter().chain(&self.fz).chain(&self.fm) {
+            let mut collector = UnknownCollector {
+                unknowns: &unknowns,
+                found: Vec::new(),
+            };
+            eq.accept(&mut collector);
+            let mut instance = common_instance(&collector.found);
+            // Equations of only the inputs of an instance, like `motor.u = 1`, give
+            // them in the enclosing instance
+            if !collector.found.is_empty()
+                && collector
+                    .found
+                    .iter()
+                    .all(|name| inputs.contains(name.as_str()))
+                && collector
+                    .found
+                    .iter()
+                    .all(|name| instance_of(name) == instance)
+            {
+                instance = instance_of(&instance).to_string();
+            }
+            instance_entry(&mut report, &instance).num_equations += equation_count(eq);
+        }
+
+        // Event equations count once per assigned (non-state) variable, as in check_balance
+        let mut event_vars: HashSet<String> = HashSet::new();
+        for stmt in self.fr.values() {
+            if let Statement::Assignment { comp, .. } = stmt {
+                let var_name = comp.unsubscripted_name();
+                if !self.x.contains_key(&var_name) && event_vars.insert(var_name.clone()) {
+                    instance_entry(&mut report, instance_of(&var_name)).num_equations += 1;
+                }
+            }
+        }
+
+        // Accumulate totals from every instance into all of its ancestors
+        let own: Vec<(String, usize, usize)> = report
+            .values()
+            .map(|c| (c.name.clone(), c.num_equations, c.num_unknowns))
+            .collect();
+        for (name, equations, unknowns) in own {
+            for (ancestor, balance) in report.iter_mut() {
+                if is_instance_ancestor(ancestor, &name) {
+                    balance.total_equations += equations;
+                    balance.total_unknowns += unknowns;
+                }
+            }
+        }
+
+        let mut components: Vec<ComponentBalance> = report.into_values().collect();
+        components.sort_by(|a, b| a.name.split('.').cmp(b.name.split('.')));
+        components
+    }
+
     /// Check the balance of the DAE system
     ///
     /// Counts equations and unknowns directly from the DAE structure.
@@ -102,7 +339,7 @@ impl Dae {
     /// - Unbalanced: over-determined OR under-determined without external connectors
     ///
     /// Note: This assumes equations have been expanded to scalar form by the
-    /// equation_expander pass. Each equation in fx/fz represents one scalar equation.
+    /// equation_expander pass. Each equation in fx/fz/fm represents one scalar equation.
     /// Event equations in fr (from when blocks) are also counted by unique variable.
     pub fn check_balance(&self) -> BalanceResult {
         // Count scalar elements in each variable category
@@ -115,8 +352,23 @@ impl Dae {
         // For event equations (fr), count unique variables assigned, not total assignments.
         // A when/elsewhen chain assigns to the same variable multiple times but is 1 equation.
         // Exclude state variables - reinit(state, expr) is an event action, not an equation.
+        // If-equations count the equations of one branch, not 1.
         let num_event_equations = count_unique_event_variables(&self.fr, &self.x);
-        let num_equations = self.fx.len() + self.fz.len() + num_event_equations;
+        let num_equations = self
+            .fx
+            .iter()
+            .chain(&self.fz)
+            .chain(&self.fm)
+            .map(equation_count)
+            .sum::<usize>()
+            + num_event_equations;
+        let num_unequal_if_equations = self
+            .fx
+            .iter()
+            .chain(&self.fz)
+            .chain(&self.fm)
+            .map(|eq| self.count_unequal_if_equations(eq))
+            .sum();
 
         // Count parameters and inputs for reporting
         let num_parameters = count_scalars(&self.p) + count_scalars(&self.cp);
@@ -129,11 +381,14 @@ impl Dae {
             + count_external_connectors(&self.m);
 
         // Determine balance status
-        let is_balanced = num_equations == num_unknowns;
+        let is_balanced = num_equations == num_unknowns && num_unequal_if_equations == 0;
         let diff = num_equations as i64 - num_unknowns as i64;
 
         let status = if is_balanced {
             BalanceStatus::Balanced
+        } else if num_unequal_if_equations > 0 {
+            // Branch-dependent equation counts make the model invalid (spec 8.3.4)
+            BalanceStatus::Unbalanced
         } else if diff > 0 {
             // Over-determined is always unbalanced (a bug)
             BalanceStatus::Unbalanced
@@ -155,12 +410,237 @@ impl Dae {
             num_parameters,
             num_inputs,
             num_external_connectors,
+            num_unequal_if_equations,
             status,
             is_balanced,
+            dae_index: if is_balanced {
+                self.structural_index()
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Structural DAE index of the continuous-time equations, if they are
+    /// simple equations for the states and algebraic variables
+    pub fn structural_index(&self) -> Option<DaeIndex> {
+        if self.fx.len() != self.x.len() + self.y.len()
+            || !self
+                .fx
+                .iter()
+                .all(|eq| matches!(eq, Equation::Simple { .. }))
+        {
+            return None;
         }
+        let states: HashSet<String> = self.x.keys().cloned().collect();
+        let algebraic: HashSet<String> = self.y.keys().cloned().collect();
+        let analysis = pantelides_index_reduction(&self.fx, &states, Some(&algebraic));
+        if analysis.is_singular {
+            return None;
+        }
+
+        let mut differentiated: Vec<(usize, usize)> =
+            analysis.equations_to_differentiate.into_iter().collect();
+        differentiated.sort_by_key(|&(idx, count)| (std::cmp::Reverse(count), idx));
+        let constraints = differentiated
+            .into_iter()
+            .map(|(idx, count)| IndexConstraint {
+                equation: self.fx[idx].to_string(),
+                differentiations: count,
+                location: self.fx[idx].get_location().cloned(),
+            })
+            .collect();
+        Some(DaeIndex {
+            index: analysis.dae_index,
+            constraints,
+        })
+    }
+}
+
+impl Dae {
+    /// Count if-equations (including nested ones) whose branches contribute
+    /// different numbers of equations although their condition is not a
+    /// parameter expression
+    fn count_unequal_if_equations(&self, eq: &Equation) -> usize {
+        let Equation::If {
+            cond_blocks,
+            else_block,
+            ..
+        } = eq
+        else {
+            return 0;
+        };
+        let counts = if_branch_counts(eq);
+        let unequal = counts.windows(2).any(|pair| pair[0] != pair[1])
+            && !cond_blocks
+                .iter()
+                .all(|block| self.is_parameter_expression(&block.cond));
+        let nested: usize = cond_blocks
+            .iter()
+            .flat_map(|block| &block.eqs)
+            .chain(else_block.iter().flatten())
+            .map(|inner| self.count_unequal_if_equations(inner))
+            .sum();
+        usize::from(unequal) + nested
+    }
+
+    /// Whether an expression does not depend on time, unknowns or inputs, looking
+    /// through the condition variables introduced for if-equations. Other names
+    /// are parameters, constants or function names.
+    fn is_parameter_expression(&self, expr: &Expression) -> bool {
+        let mut collector = ReferenceCollector::default();
+        expr.accept(&mut collector);
+        collector.names.iter().all(|name| match self.fc.get(name) {
+            Some(cond) => self.is_parameter_expression(cond),
+            None => {
+                let element_prefix = format!("{}[", name);
+                name != "time"
+                    && ![&self.x, &self.y, &self.z, &self.m, &self.u]
+                        .iter()
+                        .any(|vars| {
+                            vars.contains_key(name)
+                                || vars.keys().any(|key| key.starts_with(&element_prefix))
+                        })
+            }
+        })
     }
 }
 
+/// Collects the names of all component references in an expression
+#[derive(Default)]
+struct ReferenceCollector {
+    names: Vec<String>,
+}
+
+impl Visitor for ReferenceCollector {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        self.names.push(node.unsubscripted_name());
+    }
+}
+
+/// Report entry for an instance, creating it and all enclosing instances as needed
+fn instance_entry<'a>(
+    report: &'a mut IndexMap<String, ComponentBalance>,
+    instance: &str,
+) -> &'a mut ComponentBalance {
+    let mut prefix = String::new();
+    for segment in instance.split('.').filter(|s| !s.is_empty()) {
+        if !prefix.is_empty() {
+            prefix.push('.');
+        }
+        prefix.push_str(segment);
+        report
+            .entry(prefix.clone())
+            .or_insert_with(|| ComponentBalance::new(&prefix));
+    }
+    report.get_mut(instance).unwrap()
+}
+
+/// Collects the names of unknowns referenced by an equation
+struct UnknownCollector<'a> {
+    unknowns: &'a IndexMap<&'a str, usize>,
+    found: Vec<String>,
+}
+
+impl Visitor for UnknownCollector<'_> {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        let name = node.unsubscripted_name();
+        if self.unknowns.contains_key(name.as_str()) {
+            self.found.push(name);
+        }
+    }
+}
+
+/// Instance path declaring a variable (`motor.inertia.w` -> `motor.inertia`)
+fn instance_of(var_name: &str) -> &str {
+    var_name.rsplit_once('.').map_or("", |(prefix, _)| prefix)
+}
+
+/// Format a difference with an explicit sign for non-zero values
+fn signed(diff: i64) -> String {
+    if diff > 0 {
+        format!("+{}", diff)
+    } else {
+        diff.to_string()
+    }
+}
+
+/// Number of nested instance levels in an instance path (0 for the top level)
+fn instance_depth(instance: &str) -> usize {
+    if instance.is_empty() {
+        0
+    } else {
+        instance.matches('.').count() + 1
+    }
+}
+
+/// Whether `ancestor` is `instance` or one of its enclosing instances
+fn is_instance_ancestor(ancestor: &str, instance: &str) -> bool {
+    ancestor.is_empty()
+        || instance == ancestor
+        || instance
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Innermost instance containing all of the given variables
+fn common_instance(var_names: &[String]) -> String {
+    let mut names = var_names.iter().map(|n| instance_of(n));
+    let Some(first) = names.next() else {
+        return String::new();
+    };
+    let mut common: Vec<&str> = first.split('.').filter(|s| !s.is_empty()).collect();
+    for name in names {
+        let segments: Vec<&str> = name.split('.').filter(|s| !s.is_empty()).collect();
+        let shared = common
+            .iter()
+            .zip(&segments)
+            .take_while(|(a, b)| a == b)
+            .count();
+        common.truncate(shared);
+    }
+    common.join(".")
+}
+
+/// Number of equations contributed by an equation in fx/fz.
+///
+/// An if-equation contributes the equations of its largest branch (all branches
+/// agree unless the condition is a parameter expression). Function-call
+/// equations such as `assert(...)` define no unknowns and contribute none.
+pub(crate) fn equation_count(eq: &Equation) -> usize {
+    match eq {
+        Equation::Empty | Equation::FunctionCall { .. } => 0,
+        Equation::If { .. } => if_branch_counts(eq).into_iter().max().unwrap_or(0),
+        // Symbolic loops (see `Dae::roll_loops`) count their scalar equations
+        Equation::For { .. } => crate::dae::loops::loop_size(eq).unwrap_or(1),
+        // A call with several outputs defines the outputs it doesn't skip
+        Equation::Simple {
+            lhs: Expression::Tuple { elements },
+            ..
+        } => elements.iter().filter(|e| **e != Expression::Empty).count(),
+        _ => 1,
+    }
+}
+
+/// Equation count of each branch of an if-equation, including the (possibly
+/// missing, hence empty) else branch. Empty for other equations.
+fn if_branch_counts(eq: &Equation) -> Vec<usize> {
+    let Equation::If {
+        cond_blocks,
+        else_block,
+        ..
+    } = eq
+    else {
+        return Vec::new();
+    };
+    let count = |eqs: &[Equation]| eqs.iter().map(equation_count).sum::<usize>();
+    cond_blocks
+        .iter()
+        .map(|block| count(&block.eqs))
+        .chain(std::iter::once(else_block.as_deref().map_or(0, count)))
+        .collect()
+}
+
 /// Count scalar elements in a component map (accounting for array dimensions)
 fn count_scalars(components: &IndexMap<String, Component>) -> usize {
     components
@@ -234,8 +714,10 @@ mod tests {
             num_parameters: 0,
             num_inputs: 0,
             num_external_connectors: 0,
+            num_unequal_if_equations: 0,
             status: BalanceStatus::Balanced,
             is_balanced: true,
+            dae_index: None,
         };
         assert!(balanced.status_message().contains("balanced"));
         assert_eq!(balanced.difference(), 0);
@@ -250,8 +732,10 @@ mod tests {
             num_parameters: 0,
             num_inputs: 0,
             num_external_connectors: 0,
+            num_unequal_if_equations: 0,
             status: BalanceStatus::Unbalanced,
             is_balanced: false,
+            dae_index: None,
         };
         assert!(over.status_message().contains("over-determined"));
         assert_eq!(over.difference(), 2);
@@ -266,8 +750,10 @@ mod tests {
             num_parameters: 0,
             num_inputs: 0,
             num_external_connectors: 0,
+            num_unequal_if_equations: 0,
             status: BalanceStatus::Unbalanced,
             is_balanced: false,
+            dae_index: None,
         };
         assert!(under_bug.status_message().contains("under-determined"));
         assert_eq!(under_bug.difference(), -2);
@@ -282,8 +768,10 @@ mod tests {
             num_parameters: 0,
             num_inputs: 0,
             num_external_connectors: 2,
+            num_unequal_if_equations: 0,
             status: BalanceStatus::Partial,
             is_balanced: false,
+            dae_index: None,
         };
         assert!(partial.status_message().contains("partial"));
         assert_eq!(partial.difference(), -2);
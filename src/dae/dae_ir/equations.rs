@@ -4,6 +4,7 @@
 
 use crate::dae::ast::Dae;
 use crate::ir::ast::{Equation, EquationBlock, Expression, Statement};
+use indexmap::IndexMap;
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 use serde_json::json;
 
@@ -23,23 +24,30 @@ impl<'a> Serialize for ClassifiedEquations<'a> {
         let mut map = serializer.serialize_map(Some(5))?;
 
         // Continuous equations (fx)
-        map.serialize_entry("continuous", &EquationList { eqs: &self.dae.fx })?;
+        map.serialize_entry(
+            "continuous",
+            &EquationList::new(&self.dae.fx, &self.dae.eq_ids.fx, self.dae),
+        )?;
 
         // Event equations
         map.serialize_entry("event", &EmptyArray)?;
 
         // Discrete real equations (fz)
-        map.serialize_entry("discrete_real", &EquationList { eqs: &self.dae.fz })?;
+        map.serialize_entry(
+            "discrete_real",
+            &EquationList::new(&self.dae.fz, &self.dae.eq_ids.fz, self.dae),
+        )?;
 
         // Discrete valued equations (fm)
-        map.serialize_entry("discrete_valued", &EquationList { eqs: &self.dae.fm })?;
+        map.serialize_entry(
+            "discrete_valued",
+            &EquationList::new(&self.dae.fm, &self.dae.eq_ids.fm, self.dae),
+        )?;
 
         // Initial equations
         map.serialize_entry(
             "initial",
-            &EquationList {
-                eqs: &self.dae.fx_init,
-            },
+            &EquationList::new(&self.dae.fx_init, &self.dae.eq_ids.fx_init, self.dae),
         )?;
 
         map.end()
@@ -49,6 +57,20 @@ impl<'a> Serialize for ClassifiedEquations<'a> {
 /// List of equations
 pub struct EquationList<'a> {
     pub eqs: &'a Vec<Equation>,
+    /// Stable ids of the equations (may be empty for hand-built DAEs)
+    pub ids: &'a [String],
+    /// Source locations by id
+    pub sources: &'a IndexMap<String, String>,
+}
+
+impl<'a> EquationList<'a> {
+    pub fn new(eqs: &'a Vec<Equation>, ids: &'a [String], dae: &'a Dae) -> Self {
+        Self {
+            eqs,
+            ids,
+            sources: &dae.eq_ids.sources,
+        }
+    }
 }
 
 impl<'a> Serialize for EquationList<'a> {
@@ -58,7 +80,13 @@ impl<'a> Serialize for EquationList<'a> {
     {
         let mut seq = serializer.serialize_seq(Some(self.eqs.len()))?;
         for (idx, eq) in self.eqs.iter().enumerate() {
-            seq.serialize_element(&EquationWrapper { eq, index: idx + 1 })?;
+            let id = self.ids.get(idx).map(String::as_str);
+            seq.serialize_element(&EquationWrapper {
+                eq,
+                index: idx + 1,
+                id,
+                source: id.and_then(|id| self.sources.get(id)).map(String::as_str),
+            })?;
         }
         seq.end()
     }
@@ -68,6 +96,36 @@ impl<'a> Serialize for EquationList<'a> {
 pub struct EquationWrapper<'a> {
     pub eq: &'a Equation,
     pub index: usize,
+    /// Stable id (top-level equations only)
+    pub id: Option<&'a str>,
+    /// Source location of the declared equation
+    pub source: Option<&'a str>,
+}
+
+impl<'a> EquationWrapper<'a> {
+    /// Nested equation (inside a for, if or when equation), which has no id of its own
+    fn nested(eq: &'a Equation, index: usize) -> Self {
+        Self {
+            eq,
+            index,
+            id: None,
+            source: None,
+        }
+    }
+
+    fn serialize_id<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error> {
+        if let Some(id) = self.id {
+            map.serialize_entry("id", id)?;
+        }
+        if let Some(source) = self.source {
+            map.serialize_entry("source", source)?;
+        }
+        // The description string of the equation
+        if let Some(comment) = self.eq.description() {
+            map.serialize_entry("comment", &comment)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Serialize for EquationWrapper<'a> {
@@ -82,24 +140,31 @@ impl<'a> Serialize for EquationWrapper<'a> {
                 map.serialize_entry("lhs", &json!({"op": "literal", "value": 0}))?;
                 map.serialize_entry("rhs", &json!({"op": "literal", "value": 0}))?;
                 map.serialize_entry("source_ref", &format!("empty_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
-            Equation::Simple { lhs, rhs } => {
+            Equation::Simple { lhs, rhs, .. } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("eq_type", "simple")?;
                 map.serialize_entry("lhs", &ExpressionWrapper(lhs))?;
                 map.serialize_entry("rhs", &ExpressionWrapper(rhs))?;
                 map.serialize_entry("source_ref", &format!("eq_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
-            Equation::When(branches) => {
+            Equation::When {
+                blocks: branches, ..
+            } => {
                 let mut map = serializer.serialize_map(Some(3))?;
                 map.serialize_entry("eq_type", "when")?;
                 map.serialize_entry("branches", &WhenBranches { branches })?;
                 map.serialize_entry("source_ref", &format!("when_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
-            Equation::For { indices, equations } => {
+            Equation::For {
+                indices, equations, ..
+            } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("eq_type", "for")?;
                 let indices_json: Vec<serde_json::Value> = indices
@@ -116,16 +181,18 @@ impl<'a> Serialize for EquationWrapper<'a> {
                     .iter()
                     .enumerate()
                     .map(|(i, eq)| {
-                        serde_json::to_value(EquationWrapper { eq, index: i + 1 }).unwrap()
+                        serde_json::to_value(EquationWrapper::nested(eq, i + 1)).unwrap()
                     })
                     .collect();
                 map.serialize_entry("equations", &eqs_json)?;
                 map.serialize_entry("source_ref", &format!("for_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
             Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("eq_type", "if")?;
@@ -137,7 +204,7 @@ impl<'a> Serialize for EquationWrapper<'a> {
                             .iter()
                             .enumerate()
                             .map(|(i, eq)| {
-                                serde_json::to_value(EquationWrapper { eq, index: i + 1 }).unwrap()
+                                serde_json::to_value(EquationWrapper::nested(eq, i + 1)).unwrap()
                             })
                             .collect();
                         json!({
@@ -152,23 +219,25 @@ impl<'a> Serialize for EquationWrapper<'a> {
                         .iter()
                         .enumerate()
                         .map(|(i, eq)| {
-                            serde_json::to_value(EquationWrapper { eq, index: i + 1 }).unwrap()
+                            serde_json::to_value(EquationWrapper::nested(eq, i + 1)).unwrap()
                         })
                         .collect();
                     map.serialize_entry("else_equations", &else_json)?;
                 }
                 map.serialize_entry("source_ref", &format!("if_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
-            Equation::Connect { lhs, rhs } => {
+            Equation::Connect { lhs, rhs, .. } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("eq_type", "connect")?;
                 map.serialize_entry("lhs", &ComponentRefParts(lhs))?;
                 map.serialize_entry("rhs", &ComponentRefParts(rhs))?;
                 map.serialize_entry("source_ref", &format!("connect_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
-            Equation::FunctionCall { comp, args } => {
+            Equation::FunctionCall { comp, args, .. } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("eq_type", "call")?;
                 map.serialize_entry("func", &comp.to_string())?;
@@ -178,6 +247,7 @@ impl<'a> Serialize for EquationWrapper<'a> {
                     .collect();
                 map.serialize_entry("args", &args_json)?;
                 map.serialize_entry("source_ref", &format!("call_{}", self.index))?;
+                self.serialize_id(&mut map)?;
                 map.end()
             }
         }
@@ -206,7 +276,7 @@ impl<'a> Serialize for WhenBranches<'a> {
                 .iter()
                 .enumerate()
                 .map(|(idx, eq)| {
-                    serde_json::to_value(EquationWrapper { eq, index: idx + 1 }).unwrap()
+                    serde_json::to_value(EquationWrapper::nested(eq, idx + 1)).unwrap()
                 })
                 .collect();
             map.insert("equations".to_string(), json!(equations));
@@ -216,6 +286,31 @@ impl<'a> Serialize for WhenBranches<'a> {
     }
 }
 
+/// Runtime assertions (e.g., division guards)
+pub struct Assertions<'a> {
+    pub dae: &'a Dae,
+}
+
+impl<'a> Serialize for Assertions<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.dae.asserts.len()))?;
+        for eq in &self.dae.asserts {
+            if let Equation::FunctionCall { args, .. } = eq
+                && let [condition, Expression::Terminal { token, .. }, ..] = args.as_slice()
+            {
+                seq.serialize_element(&json!({
+                    "condition": serde_json::to_value(ExpressionWrapper(condition)).unwrap(),
+                    "message": token.text,
+                }))?;
+            }
+        }
+        seq.end()
+    }
+}
+
 /// Event indicators from conditions
 pub struct EventIndicators<'a> {
     pub dae: &'a Dae,
@@ -253,6 +348,26 @@ impl<'a> Serialize for EventIndicator<'a> {
     }
 }
 
+/// When-equations, by the conditions of their branches in priority order:
+/// at an event, only the first branch becoming true fires its resets
+/// (the algorithm statements with that condition as `source_ref`)
+pub struct WhenClauses<'a> {
+    pub dae: &'a Dae,
+}
+
+impl<'a> Serialize for WhenClauses<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.dae.when_clauses.len()))?;
+        for branches in &self.dae.when_clauses {
+            seq.serialize_element(&json!({ "branches": branches }))?;
+        }
+        seq.end()
+    }
+}
+
 /// Algorithms from reset expressions
 pub struct Algorithms<'a> {
     pub dae: &'a Dae,
@@ -324,7 +439,7 @@ impl<'a> Serialize for StatementWrapper<'a> {
         S: Serializer,
     {
         match self.stmt {
-            Statement::Assignment { comp, value } => {
+            Statement::Assignment { comp, value, .. } => {
                 let mut map = serializer.serialize_map(Some(4))?;
                 map.serialize_entry("stmt", "reinit")?;
                 map.serialize_entry("target", &ComponentRefParts(comp))?;
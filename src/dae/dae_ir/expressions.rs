@@ -3,6 +3,7 @@
 //! This module provides serialization for Modelica expressions to JSON format.
 
 use crate::ir::ast::{ComponentReference, Expression, OpBinary, OpUnary, Subscript, TerminalType};
+use crate::ir::literal::parse_real;
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 use serde_json::json;
 
@@ -25,7 +26,7 @@ impl<'a> Serialize for ExpressionWrapper<'a> {
                 terminal_type,
                 token,
             } => {
-                let mut map = serializer.serialize_map(Some(2))?;
+                let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("op", "literal")?;
 
                 match terminal_type {
@@ -36,13 +37,18 @@ impl<'a> Serialize for ExpressionWrapper<'a> {
                             map.serialize_entry("value", &token.text)?;
                         }
                     }
-                    TerminalType::UnsignedReal => {
-                        if let Ok(val) = token.text.parse::<f64>() {
+                    TerminalType::UnsignedReal => match parse_real(&token.text) {
+                        Some(val) => {
                             map.serialize_entry("value", &val)?;
-                        } else {
-                            map.serialize_entry("value", &token.text)?;
+                            // Keep the literal as written when JSON spells the
+                            // value differently, e.g. `1e-3` for 0.001
+                            let json = serde_json::Number::from_f64(val).map(|n| n.to_string());
+                            if json.as_deref() != Some(token.text.as_str()) {
+                                map.serialize_entry("text", &token.text)?;
+                            }
                         }
-                    }
+                        None => map.serialize_entry("value", &token.text)?,
+                    },
                     TerminalType::Bool => {
                         let val = token.text.to_lowercase() == "true";
                         map.serialize_entry("value", &val)?;
@@ -163,6 +169,13 @@ impl<'a> Serialize for ExpressionWrapper<'a> {
                 map.serialize_entry("indices", &indices)?;
                 map.end()
             }
+            Expression::NamedArgument { name, value } => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("op", "named_argument")?;
+                map.serialize_entry("name", &name.text)?;
+                map.serialize_entry("value", &ExpressionWrapper(value))?;
+                map.end()
+            }
         }
     }
 }
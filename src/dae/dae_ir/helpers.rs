@@ -43,14 +43,20 @@ impl<'a> Serialize for Structure<'a> {
     {
         let n_states = self.dae.x.len();
         let n_algebraic = self.dae.y.len();
-        let n_equations = self.dae.fx.len();
+        let n_equations: usize = self
+            .dae
+            .fx
+            .iter()
+            .map(crate::dae::balance::equation_count)
+            .sum();
         let is_ode = n_algebraic == 0;
 
         let mut map = serializer.serialize_map(Some(5))?;
         map.serialize_entry("n_states", &n_states)?;
         map.serialize_entry("n_algebraic", &n_algebraic)?;
         map.serialize_entry("n_equations", &n_equations)?;
-        map.serialize_entry("dae_index", &0)?; // TODO: compute actual index
+        let dae_index = self.dae.structural_index().map_or(0, |index| index.index);
+        map.serialize_entry("dae_index", &dae_index)?;
         map.serialize_entry("is_ode", &is_ode)?;
         map.end()
     }
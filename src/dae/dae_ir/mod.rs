@@ -8,6 +8,7 @@
 //! - Explicit state/derivative/algebraic classification (no der() scanning needed)
 //! - Direct state/derivative linkage (like FMI's derivative attribute)
 //! - Event indicators for zero-crossing detection
+//! - When-equations with the priority of their branches
 //! - Structural metadata (n_states, n_algebraic, dae_index)
 
 mod equations;
@@ -16,7 +17,7 @@ pub mod helpers;
 mod variables;
 
 use crate::dae::ast::Dae;
-use equations::{Algorithms, ClassifiedEquations, EventIndicators};
+use equations::{Algorithms, Assertions, ClassifiedEquations, EventIndicators, WhenClauses};
 use helpers::{EmptyArray, EmptyObject, Metadata, Structure};
 use serde::ser::{Serialize, SerializeMap, Serializer};
 use variables::ClassifiedVariables;
@@ -38,7 +39,7 @@ impl<'a> Serialize for DaeIR<'a> {
     where
         S: Serializer,
     {
-        let mut map = serializer.serialize_map(Some(12))?;
+        let mut map = serializer.serialize_map(Some(14))?;
 
         map.serialize_entry("ir_version", "dae-0.1.0")?;
         map.serialize_entry("base_modelica_version", "0.1")?;
@@ -56,7 +57,13 @@ impl<'a> Serialize for DaeIR<'a> {
         // Algorithms - reconstruct from fr (reset expressions)
         map.serialize_entry("algorithms", &Algorithms { dae: self.dae })?;
 
+        // Branches of the when-equations, in priority order
+        map.serialize_entry("when_clauses", &WhenClauses { dae: self.dae })?;
+
         map.serialize_entry("initial_algorithms", &EmptyArray)?;
+
+        // Runtime assertions (only present when division guards are enabled)
+        map.serialize_entry("assertions", &Assertions { dae: self.dae })?;
         map.serialize_entry("functions", &EmptyArray)?;
 
         // Structure metadata
@@ -3,12 +3,12 @@
 //! This module provides serialization for classified variables according to DAE formalism.
 
 use crate::dae::ast::Dae;
+use crate::dae::uses::ParameterUses;
 use crate::ir::ast::{Component, Expression, Name, OpUnary, TerminalType};
-use indexmap::IndexMap;
+use crate::ir::literal::parse_real;
+use indexmap::{IndexMap, IndexSet};
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 
-use super::helpers::EmptyArray;
-
 /// Classified variables according to DAE formalism
 pub struct ClassifiedVariables<'a> {
     pub dae: &'a Dae,
@@ -30,6 +30,8 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "algebraic",
             &VariableArray {
                 components: &self.dae.y,
+                structural: None,
+                uses: None,
             },
         )?;
 
@@ -38,6 +40,8 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "discrete_real",
             &VariableArray {
                 components: &self.dae.z,
+                structural: None,
+                uses: None,
             },
         )?;
 
@@ -46,6 +50,8 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "discrete_valued",
             &VariableArray {
                 components: &self.dae.m,
+                structural: None,
+                uses: None,
             },
         )?;
 
@@ -54,6 +60,8 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "parameters",
             &VariableArray {
                 components: &self.dae.p,
+                structural: Some(&self.dae.structural),
+                uses: Some(&self.dae.parameter_uses),
             },
         )?;
 
@@ -62,6 +70,8 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "constants",
             &VariableArray {
                 components: &self.dae.cp,
+                structural: None,
+                uses: None,
             },
         )?;
 
@@ -70,11 +80,38 @@ impl<'a> Serialize for ClassifiedVariables<'a> {
             "inputs",
             &VariableArray {
                 components: &self.dae.u,
+                structural: None,
+                uses: None,
             },
         )?;
 
-        // Outputs - empty for now
-        map.serialize_entry("outputs", &EmptyArray)?;
+        // Outputs selected with `--outputs` or `__rumoca_output`, empty if
+        // all variables are results
+        let outputs: IndexMap<String, Component> = self
+            .dae
+            .outputs
+            .iter()
+            .filter_map(|name| {
+                [
+                    &self.dae.x,
+                    &self.dae.y,
+                    &self.dae.z,
+                    &self.dae.m,
+                    &self.dae.u,
+                ]
+                .into_iter()
+                .find_map(|components| components.get(name))
+                .map(|comp| (name.clone(), comp.clone()))
+            })
+            .collect();
+        map.serialize_entry(
+            "outputs",
+            &VariableArray {
+                components: &outputs,
+                structural: None,
+                uses: None,
+            },
+        )?;
 
         map.end()
     }
@@ -153,6 +190,10 @@ impl<'a> Serialize for StateVariableWrapper<'a> {
 /// Array of basic variables
 pub struct VariableArray<'a> {
     pub components: &'a IndexMap<String, Component>,
+    /// Structural parameters, to give parameters their variability
+    pub structural: Option<&'a IndexSet<String>>,
+    /// Where the parameters are used
+    pub uses: Option<&'a IndexMap<String, ParameterUses>>,
 }
 
 impl<'a> Serialize for VariableArray<'a> {
@@ -162,7 +203,14 @@ impl<'a> Serialize for VariableArray<'a> {
     {
         let mut seq = serializer.serialize_seq(Some(self.components.len()))?;
         for (name, comp) in self.components {
-            seq.serialize_element(&BasicVariableWrapper { name, comp })?;
+            let structural = self.structural.map(|structural| structural.contains(name));
+            let uses = self.uses.and_then(|uses| uses.get(name));
+            seq.serialize_element(&BasicVariableWrapper {
+                name,
+                comp,
+                structural,
+                uses,
+            })?;
         }
         seq.end()
     }
@@ -172,6 +220,10 @@ impl<'a> Serialize for VariableArray<'a> {
 struct BasicVariableWrapper<'a> {
     name: &'a str,
     comp: &'a Component,
+    /// Whether a parameter is structural, `None` for other variables
+    structural: Option<bool>,
+    /// Where a parameter is used, `None` for other variables
+    uses: Option<&'a ParameterUses>,
 }
 
 impl<'a> Serialize for BasicVariableWrapper<'a> {
@@ -182,6 +234,12 @@ impl<'a> Serialize for BasicVariableWrapper<'a> {
         let has_comment = !self.comp.description.is_empty();
         let has_annotation = !self.comp.annotation.is_empty();
         let mut map_size = 3;
+        if self.structural.is_some() {
+            map_size += 1;
+        }
+        if self.uses.is_some() {
+            map_size += 1;
+        }
         if has_comment {
             map_size += 1;
         }
@@ -195,6 +253,17 @@ impl<'a> Serialize for BasicVariableWrapper<'a> {
         map.serialize_entry("vartype", &NameWrapper(&self.comp.type_name))?;
         map.serialize_entry("start", &StartValueWrapper(&self.comp.start))?;
 
+        // Structural parameters can't change without recompiling
+        if let Some(structural) = self.structural {
+            let variability = if structural { "structural" } else { "tunable" };
+            map.serialize_entry("variability", variability)?;
+        }
+
+        // Equations and components to update when a parameter is tuned
+        if let Some(uses) = self.uses {
+            map.serialize_entry("uses", uses)?;
+        }
+
         if has_comment {
             let comment: String = self
                 .comp
@@ -276,7 +345,7 @@ where
                 }
             }
             TerminalType::UnsignedReal => {
-                if let Ok(val) = token.text.parse::<f64>() {
+                if let Some(val) = parse_real(&token.text) {
                     serializer.serialize_f64(val)
                 } else {
                     serializer.serialize_str(&token.text)
@@ -314,7 +383,7 @@ fn extract_numeric_value(expr: &Expression) -> Option<f64> {
         Expression::Terminal {
             terminal_type: TerminalType::UnsignedInteger | TerminalType::UnsignedReal,
             token,
-        } => token.text.parse::<f64>().ok(),
+        } => parse_real(&token.text),
         Expression::Terminal { .. } => None,
         Expression::Unary { op, rhs } => {
             let val = extract_numeric_value(rhs)?;
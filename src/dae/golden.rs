@@ -0,0 +1,862 @@
+//! Comparison of a DAE to a golden DAE IR JSON export.
+//!
+//! A DAE exported with `--json` pins down what the compiler produced for a
+//! model. [`Dae::compare_to_golden`] compares the DAE of a later compile run to
+//! it, so that a compiler upgrade changing the model shows up as drift:
+//!
+//! ```text
+//! M drifted from the golden DAE (2 differences)
+//!   variables: 'k' start is 3 now, 2 in the golden DAE
+//!   equations: continuous equation '2 * u + x - y = 0' is missing (golden m.mo:8:3)
+//! ```
+//!
+//! The comparison is structural, not textual:
+//!
+//! - variables are compared by name, with their classification (state,
+//!   algebraic, parameter, ...) and attributes
+//! - simple equations are compared as residuals `lhs - rhs = 0`, modulo the
+//!   order of the terms of sums and of the factors of products, and the side
+//!   of the equation terms are on, so `y = x + 2 * u` and `u * 2 + x = y` are
+//!   the same equation
+//! - other equations, event indicators, algorithms, when-clauses and
+//!   assertions are compared as a whole, with the same normalization of their
+//!   expressions
+//! - the structure (numbers of states, algebraic variables and equations, DAE
+//!   index) is compared as is
+//!
+//! Equation identifiers, source locations, descriptions and the metadata of
+//! the export (compiler version, model hash) are ignored.
+//!
+//! `rumoca verify model.mo -m M --against golden.json` prints the comparison
+//! and fails if the DAE drifted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::dae::ast::Dae;
+use crate::error::{Error, Result};
+
+/// Keys of the export that don't affect the behavior of the model
+const IGNORED_KEYS: [&str; 7] = [
+    "id",
+    "source",
+    "source_ref",
+    "comment",
+    "annotation",
+    "uses",
+    "state_index",
+];
+
+/// Variable classifications of the export, in order
+const VARIABLE_KINDS: [&str; 8] = [
+    "states",
+    "algebraic",
+    "discrete_real",
+    "discrete_valued",
+    "parameters",
+    "constants",
+    "inputs",
+    "outputs",
+];
+
+/// Sections of the export compared as multisets of their entries
+const LISTS: [&str; 5] = [
+    "event_indicators",
+    "algorithms",
+    "when_clauses",
+    "initial_algorithms",
+    "assertions",
+];
+
+/// A difference between a DAE and the golden one.
+///
+/// Values, equations and other entries are given in their canonical form,
+/// see the [module docs](self); attributes are `None` where unset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Drift {
+    /// The `ir_version` or `model_name` of the export differs
+    Metadata {
+        key: String,
+        golden: String,
+        current: String,
+    },
+    /// A variable of the golden DAE is missing, with its classification
+    /// (`states`, `algebraic`, `parameters`, ...)
+    MissingVariable { name: String, kind: String },
+    /// A variable is not in the golden DAE
+    NewVariable { name: String, kind: String },
+    /// A variable is classified differently than in the golden DAE
+    Reclassified {
+        name: String,
+        golden: String,
+        current: String,
+    },
+    /// An attribute of a variable differs, e.g. its start value
+    Attribute {
+        name: String,
+        attribute: String,
+        golden: Option<String>,
+        current: Option<String>,
+    },
+    /// An entry of the golden DAE is missing, with its source location
+    MissingEntry {
+        /// Section of the export: `equations`, `event_indicators`, ...
+        section: String,
+        /// Kind of the entry, e.g. `continuous equation`
+        label: String,
+        entry: String,
+        source: Option<String>,
+    },
+    /// An entry is not in the golden DAE, with its source location
+    NewEntry {
+        section: String,
+        label: String,
+        entry: String,
+        source: Option<String>,
+    },
+    /// A count of the structure differs, e.g. `n_equations`
+    Structure {
+        key: String,
+        golden: Option<String>,
+        current: Option<String>,
+    },
+}
+
+impl Drift {
+    /// Section of the export: `variables`, `equations`, `structure`, ...
+    pub fn section(&self) -> &str {
+        match self {
+            Drift::Metadata { key, .. } => key,
+            Drift::MissingVariable { .. }
+            | Drift::NewVariable { .. }
+            | Drift::Reclassified { .. }
+            | Drift::Attribute { .. } => "variables",
+            Drift::MissingEntry { section, .. } | Drift::NewEntry { section, .. } => section,
+            Drift::Structure { .. } => "structure",
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "unset".to_string());
+        let source = |source: &Option<String>, prefix: &str| match source {
+            Some(source) => format!(" ({}{})", prefix, source),
+            None => String::new(),
+        };
+        write!(f, "{}: ", self.section())?;
+        match self {
+            Drift::Metadata {
+                golden, current, ..
+            } => write!(f, "{} now, {} in the golden DAE", current, golden),
+            Drift::MissingVariable { name, kind } => write!(f, "'{}' ({}) is missing", name, kind),
+            Drift::NewVariable { name, kind } => write!(f, "'{}' ({}) is new", name, kind),
+            Drift::Reclassified {
+                name,
+                golden,
+                current,
+            } => write!(
+                f,
+                "'{}' is {} now, {} in the golden DAE",
+                name, current, golden
+            ),
+            Drift::Attribute {
+                name,
+                attribute,
+                golden,
+                current,
+            } => write!(
+                f,
+                "'{}' {} is {} now, {} in the golden DAE",
+                name,
+                attribute,
+                show(current),
+                show(golden)
+            ),
+            Drift::MissingEntry {
+                label,
+                entry,
+                source: entry_source,
+                ..
+            } => write!(
+                f,
+                "{} '{}' is missing{}",
+                label,
+                entry,
+                source(entry_source, "golden ")
+            ),
+            Drift::NewEntry {
+                label,
+                entry,
+                source: entry_source,
+                ..
+            } => write!(
+                f,
+                "{} '{}' is new{}",
+                label,
+                entry,
+                source(entry_source, "")
+            ),
+            Drift::Structure {
+                key,
+                golden,
+                current,
+            } => write!(
+                f,
+                "{} is {} now, {} in the golden DAE",
+                key,
+                show(current),
+                show(golden)
+            ),
+        }
+    }
+}
+
+/// Comparison of a DAE to a golden one, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenComparison {
+    pub model_name: String,
+    pub drifts: Vec<Drift>,
+}
+
+impl GoldenComparison {
+    /// Whether the DAE is equivalent to the golden one
+    pub fn is_equivalent(&self) -> bool {
+        self.drifts.is_empty()
+    }
+}
+
+impl fmt::Display for GoldenComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_equivalent() {
+            return write!(f, "{} matches the golden DAE", self.model_name);
+        }
+        let count = self.drifts.len();
+        write!(
+            f,
+            "{} drifted from the golden DAE ({} difference{})",
+            self.model_name,
+            count,
+            if count == 1 { "" } else { "s" }
+        )?;
+        for drift in &self.drifts {
+            write!(f, "\n  {}", drift)?;
+        }
+        Ok(())
+    }
+}
+
+impl Dae {
+    /// Compare the DAE to a golden DAE IR JSON export, see the
+    /// [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Golden`] if the golden JSON isn't a DAE IR export.
+    pub fn compare_to_golden(&self, golden_json: &str) -> Result<GoldenComparison> {
+        let golden: Value = serde_json::from_str(golden_json)
+            .map_err(|e| Error::Golden(format!("The golden DAE is not valid JSON: {}", e)))?;
+        if !golden.get("variables").is_some_and(Value::is_object) {
+            return Err(Error::Golden(
+                "The golden DAE is not a DAE IR JSON export (no variables)".to_string(),
+            ));
+        }
+        let current = serde_json::to_value(crate::dae::dae_ir::DaeIR::from_dae(self))
+            .map_err(|e| Error::Render(format!("Failed to serialize the DAE: {}", e)))?;
+        Ok(GoldenComparison {
+            model_name: self.model_name.clone(),
+            drifts: compare_dae_ir(&golden, &current),
+        })
+    }
+}
+
+/// The differences between two DAE IR JSON exports, see the
+/// [module docs](self)
+pub fn compare_dae_ir(golden: &Value, current: &Value) -> Vec<Drift> {
+    let mut drifts = Vec::new();
+    for key in ["ir_version", "model_name"] {
+        let (old, new) = (&golden[key], &current[key]);
+        if old != new {
+            drifts.push(Drift::Metadata {
+                key: key.to_string(),
+                golden: old.to_string(),
+                current: new.to_string(),
+            });
+        }
+    }
+    compare_variables(golden, current, &mut drifts);
+    let sections: BTreeSet<&String> = object(&golden["equations"])
+        .keys()
+        .chain(object(&current["equations"]).keys())
+        .collect();
+    for section in sections {
+        compare_lists(
+            "equations",
+            &format!("{} equation", section),
+            &golden["equations"][section],
+            &current["equations"][section],
+            &mut drifts,
+        );
+    }
+    for key in LISTS {
+        let label = key.trim_end_matches('s').replace('_', " ");
+        compare_lists(key, &label, &golden[key], &current[key], &mut drifts);
+    }
+    for (key, golden, current) in
+        changed_attributes(object(&golden["structure"]), object(&current["structure"]))
+    {
+        drifts.push(Drift::Structure {
+            key,
+            golden,
+            current,
+        });
+    }
+    drifts
+}
+
+fn compare_variables(golden: &Value, current: &Value, drifts: &mut Vec<Drift>) {
+    let golden = variables(golden);
+    let current = variables(current);
+    for (name, (kind, attributes)) in &golden {
+        match current.get(name) {
+            None => drifts.push(Drift::MissingVariable {
+                name: name.clone(),
+                kind: kind.to_string(),
+            }),
+            Some((new_kind, _)) if new_kind != kind => drifts.push(Drift::Reclassified {
+                name: name.clone(),
+                golden: kind.to_string(),
+                current: new_kind.to_string(),
+            }),
+            Some((_, new_attributes)) => {
+                for (attribute, golden, current) in changed_attributes(attributes, new_attributes) {
+                    drifts.push(Drift::Attribute {
+                        name: name.clone(),
+                        attribute,
+                        golden,
+                        current,
+                    });
+                }
+            }
+        }
+    }
+    for (name, (kind, _)) in &current {
+        if !golden.contains_key(name) {
+            drifts.push(Drift::NewVariable {
+                name: name.clone(),
+                kind: kind.to_string(),
+            });
+        }
+    }
+}
+
+/// The variables of an export by name, with their classification and
+/// attributes
+fn variables(ir: &Value) -> BTreeMap<String, (&'static str, &Map<String, Value>)> {
+    let mut variables = BTreeMap::new();
+    for kind in VARIABLE_KINDS {
+        for variable in array(&ir["variables"][kind]) {
+            let attributes = object(variable);
+            if let Some(name) = attributes.get("name").and_then(Value::as_str) {
+                variables.insert(name.to_string(), (kind, attributes));
+            }
+        }
+    }
+    variables
+}
+
+/// The attributes of two objects that differ, except the ignored ones, with
+/// their canonical golden and current values
+fn changed_attributes(
+    golden: &Map<String, Value>,
+    current: &Map<String, Value>,
+) -> Vec<(String, Option<String>, Option<String>)> {
+    let keys: BTreeSet<&String> = golden.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .filter(|key| !IGNORED_KEYS.contains(&key.as_str()))
+        .filter_map(|key| {
+            let old = golden.get(key).map(canonical_value);
+            let new = current.get(key).map(canonical_value);
+            (old != new).then(|| (key.clone(), old, new))
+        })
+        .collect()
+}
+
+/// Compare two lists of entries as multisets of their canonical forms
+fn compare_lists(
+    section: &str,
+    label: &str,
+    golden: &Value,
+    current: &Value,
+    drifts: &mut Vec<Drift>,
+) {
+    let mut remaining: BTreeMap<String, Vec<&Value>> = BTreeMap::new();
+    for entry in array(golden) {
+        remaining
+            .entry(canonical_entry(entry))
+            .or_default()
+            .push(entry);
+    }
+    let mut added = Vec::new();
+    for entry in array(current) {
+        let key = canonical_entry(entry);
+        match remaining.get_mut(&key).and_then(Vec::pop) {
+            Some(_) => {}
+            None => added.push((key, entry)),
+        }
+    }
+    for (key, entries) in remaining {
+        for entry in entries {
+            drifts.push(Drift::MissingEntry {
+                section: section.to_string(),
+                label: label.to_string(),
+                entry: key.clone(),
+                source: source_of(entry),
+            });
+        }
+    }
+    for (key, entry) in added {
+        drifts.push(Drift::NewEntry {
+            section: section.to_string(),
+            label: label.to_string(),
+            entry: key,
+            source: source_of(entry),
+        });
+    }
+}
+
+/// Source location of an entry of the export
+fn source_of(entry: &Value) -> Option<String> {
+    entry
+        .get("source")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// The canonical form of an equation or other entry: the residual of simple
+/// equations, the canonical value of others
+fn canonical_entry(entry: &Value) -> String {
+    if entry.get("eq_type").and_then(Value::as_str) == Some("simple") {
+        let mut terms = Vec::new();
+        collect_terms(&entry["lhs"], false, &mut terms);
+        collect_terms(&entry["rhs"], true, &mut terms);
+        terms.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+        // The equation is the same on either side, so the first term is positive
+        let flip = terms.first().is_some_and(|(negative, _)| *negative);
+        let terms: Vec<(bool, String)> = terms
+            .into_iter()
+            .map(|(negative, term)| (negative != flip, term))
+            .collect();
+        format!("{} = 0", join_terms(&terms))
+    } else {
+        canonical_value(entry)
+    }
+}
+
+/// Binding strength of operators, for parentheses
+const PREC_OR: u8 = 1;
+const PREC_AND: u8 = 2;
+const PREC_NOT: u8 = 3;
+const PREC_RELATION: u8 = 4;
+const PREC_SUM: u8 = 5;
+const PREC_PRODUCT: u8 = 6;
+const PREC_POWER: u8 = 8;
+const PREC_ATOM: u8 = 9;
+
+/// The canonical text of a value: expressions are written out, see
+/// [`canonical_expression`], and objects list their keys in order, except the
+/// ignored ones
+fn canonical_value(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.contains_key("op") => canonical_expression(value).0,
+        Value::Object(map) => {
+            let entries: BTreeMap<&String, String> = map
+                .iter()
+                .filter(|(key, _)| !IGNORED_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key, canonical_value(value)))
+                .collect();
+            let entries: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(canonical_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Number(number) => match number.as_f64() {
+            // 2 and 2.0 are the same value
+            Some(number) => number.to_string(),
+            None => number.to_string(),
+        },
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The canonical text of an expression and the binding strength of its
+/// operator: the terms of sums and the operands of products, `and`, `or`,
+/// `==` and `!=` are sorted, and `a > b` is written `b < a`
+fn canonical_expression(expr: &Value) -> (String, u8) {
+    let op = expr["op"].as_str().unwrap_or_default();
+    let args = array(&expr["args"]);
+    let text = |index: usize, prec: u8| wrap(args.get(index).unwrap_or(&Value::Null), prec);
+    match (op, args.len()) {
+        ("literal", _) => (canonical_value(&expr["value"]), PREC_ATOM),
+        ("component_ref", _) => {
+            let parts: Vec<String> = array(&expr["parts"])
+                .iter()
+                .map(|part| {
+                    let name = part["name"].as_str().unwrap_or_default();
+                    let subscripts: Vec<String> = array(&part["subscripts"])
+                        .iter()
+                        .map(|sub| match sub["op"].as_str() {
+                            Some("colon") => ":".to_string(),
+                            _ => canonical_expression(sub).0,
+                        })
+                        .collect();
+                    if subscripts.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}[{}]", name, subscripts.join(", "))
+                    }
+                })
+                .collect();
+            (parts.join("."), PREC_ATOM)
+        }
+        ("+" | "-" | "neg", _) => {
+            let mut terms = Vec::new();
+            collect_terms(expr, false, &mut terms);
+            terms.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+            (join_terms(&terms), PREC_SUM)
+        }
+        ("*", _) => {
+            let (negative, product) = product_term(expr);
+            if negative {
+                (format!("-{}", product), PREC_SUM)
+            } else {
+                (product, PREC_PRODUCT)
+            }
+        }
+        ("pos", 1) => canonical_expression(&args[0]),
+        ("/", 2) => (
+            format!("{} / {}", text(0, PREC_PRODUCT), text(1, PREC_PRODUCT + 1)),
+            PREC_PRODUCT,
+        ),
+        ("^", 2) => (
+            format!("{} ^ {}", text(0, PREC_POWER + 1), text(1, PREC_POWER + 1)),
+            PREC_POWER,
+        ),
+        ("and" | "or", _) => {
+            let prec = if op == "and" { PREC_AND } else { PREC_OR };
+            let mut operands = Vec::new();
+            collect_operands(expr, op, &mut operands);
+            let mut operands: Vec<String> = operands.into_iter().map(|o| wrap(o, prec)).collect();
+            operands.sort();
+            (operands.join(&format!(" {} ", op)), prec)
+        }
+        ("not", 1) => (format!("not {}", text(0, PREC_NOT)), PREC_NOT),
+        ("==" | "!=", 2) => {
+            let mut operands = [text(0, PREC_SUM), text(1, PREC_SUM)];
+            operands.sort();
+            (
+                format!("{} {} {}", operands[0], op, operands[1]),
+                PREC_RELATION,
+            )
+        }
+        ("<" | "<=" | ">" | ">=", 2) => {
+            let (lhs, rhs) = (text(0, PREC_SUM), text(1, PREC_SUM));
+            let text = match op {
+                ">" => format!("{} < {}", rhs, lhs),
+                ">=" => format!("{} <= {}", rhs, lhs),
+                _ => format!("{} {} {}", lhs, op, rhs),
+            };
+            (text, PREC_RELATION)
+        }
+        ("if", _) => {
+            let mut text = String::new();
+            for (i, branch) in array(&expr["branches"]).iter().enumerate() {
+                let keyword = if i == 0 { "if" } else { " elseif" };
+                text.push_str(&format!(
+                    "{} {} then {}",
+                    keyword,
+                    canonical_expression(&branch[0]).0,
+                    canonical_expression(&branch[1]).0
+                ));
+            }
+            text.push_str(&format!(" else {}", canonical_expression(&expr["else"]).0));
+            (text, PREC_OR - 1)
+        }
+        ("array", _) => (format!("{{{}}}", list(&expr["values"])), PREC_ATOM),
+        ("tuple", _) => (format!("({})", list(&expr["elements"])), PREC_ATOM),
+        ("range", _) => {
+            let mut parts = vec![wrap(&expr["start"], PREC_SUM)];
+            if let Some(step) = expr.get("step") {
+                parts.push(wrap(step, PREC_SUM));
+            }
+            parts.push(wrap(&expr["end"], PREC_SUM));
+            (parts.join(":"), PREC_RELATION)
+        }
+        // Function calls, der included
+        (_, _) if expr.get("args").is_some() => {
+            (format!("{}({})", op, list(&expr["args"])), PREC_ATOM)
+        }
+        _ => (canonical_value(expr), PREC_ATOM),
+    }
+}
+
+/// The canonical text of an expression, in parentheses if its operator binds
+/// less than `prec`
+fn wrap(expr: &Value, prec: u8) -> String {
+    let (text, expr_prec) = match expr {
+        Value::Object(map) if map.contains_key("op") => canonical_expression(expr),
+        other => (canonical_value(other), PREC_ATOM),
+    };
+    if expr_prec < prec {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn list(values: &Value) -> String {
+    let values: Vec<String> = array(values)
+        .iter()
+        .map(|v| canonical_expression(v).0)
+        .collect();
+    values.join(", ")
+}
+
+/// The additive terms of an expression, with whether they're negated
+fn collect_terms(expr: &Value, negative: bool, terms: &mut Vec<(bool, String)>) {
+    let args = array(&expr["args"]);
+    match (expr["op"].as_str(), args) {
+        (Some("+"), args) => {
+            for arg in args {
+                collect_terms(arg, negative, terms);
+            }
+        }
+        (Some("-"), [lhs, rhs]) => {
+            collect_terms(lhs, negative, terms);
+            collect_terms(rhs, !negative, terms);
+        }
+        (Some("neg"), [arg]) => collect_terms(arg, !negative, terms),
+        (Some("pos"), [arg]) => collect_terms(arg, negative, terms),
+        (Some("*"), _) => {
+            let (product_negative, product) = product_term(expr);
+            terms.push((negative != product_negative, product));
+        }
+        _ => terms.push((negative, wrap(expr, PREC_SUM + 1))),
+    }
+}
+
+/// The sorted factors of a product, with whether an odd number of them are
+/// negated
+fn product_term(expr: &Value) -> (bool, String) {
+    let mut factors = Vec::new();
+    collect_operands(expr, "*", &mut factors);
+    let mut negative = false;
+    let mut texts: Vec<String> = factors
+        .into_iter()
+        .map(|mut factor| {
+            while let (Some("neg"), [arg]) = (factor["op"].as_str(), array(&factor["args"])) {
+                negative = !negative;
+                factor = arg;
+            }
+            wrap(factor, PREC_PRODUCT)
+        })
+        .collect();
+    texts.sort();
+    (negative, texts.join(" * "))
+}
+
+/// The operands of nested applications of an associative operator
+fn collect_operands<'a>(expr: &'a Value, op: &str, operands: &mut Vec<&'a Value>) {
+    if expr["op"].as_str() == Some(op) {
+        for arg in array(&expr["args"]) {
+            collect_operands(arg, op, operands);
+        }
+    } else {
+        operands.push(expr);
+    }
+}
+
+fn join_terms(terms: &[(bool, String)]) -> String {
+    let mut text = String::new();
+    for (i, (negative, term)) in terms.iter().enumerate() {
+        match (i, negative) {
+            (0, false) => {}
+            (0, true) => text.push('-'),
+            (_, false) => text.push_str(" + "),
+            (_, true) => text.push_str(" - "),
+        }
+        text.push_str(term);
+    }
+    if text.is_empty() {
+        text.push('0');
+    }
+    text
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
+
+fn object(value: &Value) -> &Map<String, Value> {
+    static EMPTY: std::sync::LazyLock<Map<String, Value>> = std::sync::LazyLock::new(Map::new);
+    value.as_object().unwrap_or(&EMPTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Drift;
+    use crate::{Compiler, Error};
+
+    fn dae_json(source: &str) -> String {
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        result.dae.to_dae_ir_json().unwrap()
+    }
+
+    #[test]
+    fn test_compare_to_golden() {
+        let golden = dae_json(
+            r#"model M
+  parameter Real k = 2;
+  Real x(start = 1);
+  Real y;
+  input Real u;
+equation
+  der(x) = -k * x + u;
+  y = x + 2 * u;
+end M;
+"#,
+        );
+
+        // Reordered terms, factors, equations and sides are the same DAE
+        let source = r#"model M
+  parameter Real k = 2 "Gain";
+  input Real u;
+  Real y;
+  Real x(start = 1.0);
+equation
+  u * 2 + x = y;
+  der(x) = u - x * k;
+end M;
+"#;
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        let comparison = result.dae.compare_to_golden(&golden).unwrap();
+        assert!(comparison.is_equivalent(), "{}", comparison);
+        assert_eq!(comparison.to_string(), "M matches the golden DAE");
+
+        let source = r#"model M
+  parameter Real k = 3;
+  Real x(start = 1);
+  Real y;
+  Real z;
+  input Real u;
+equation
+  der(x) = -k * x + u;
+  y = x - 2 * u;
+  z = y;
+end M;
+"#;
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        let comparison = result.dae.compare_to_golden(&golden).unwrap();
+        assert_eq!(
+            comparison.drifts[0],
+            Drift::Attribute {
+                name: "k".to_string(),
+                attribute: "start".to_string(),
+                golden: Some("2".to_string()),
+                current: Some("3".to_string()),
+            }
+        );
+        assert_eq!(
+            comparison.drifts[2],
+            Drift::MissingEntry {
+                section: "equations".to_string(),
+                label: "continuous equation".to_string(),
+                entry: "2 * u + x - y = 0".to_string(),
+                source: Some("m.mo:8:3".to_string()),
+            }
+        );
+        let drifts: Vec<String> = comparison.drifts.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            drifts,
+            [
+                "variables: 'k' start is 3 now, 2 in the golden DAE",
+                "variables: 'z' (algebraic) is new",
+                "equations: continuous equation '2 * u + x - y = 0' is missing (golden m.mo:8:3)",
+                "equations: continuous equation '2 * u - x + y = 0' is new (m.mo:9:3)",
+                "equations: continuous equation 'y - z = 0' is new (m.mo:10:3)",
+                "structure: n_algebraic is 2 now, 1 in the golden DAE",
+                "structure: n_equations is 3 now, 2 in the golden DAE",
+            ]
+        );
+        assert!(
+            comparison
+                .to_string()
+                .starts_with("M drifted from the golden DAE (7 differences)\n  variables: "),
+            "{}",
+            comparison
+        );
+
+        let err = result.dae.compare_to_golden("{}").unwrap_err();
+        assert!(matches!(err, Error::Golden(_)), "{err:?}");
+        assert_eq!(
+            err.to_string(),
+            "The golden DAE is not a DAE IR JSON export (no variables)"
+        );
+    }
+
+    #[test]
+    fn test_when_priority_drift() {
+        let source = r#"model M
+  Real h(start = 1);
+  Real v(start = 0);
+equation
+  der(h) = v;
+  der(v) = -9.81;
+  when h < 0 then
+    reinit(v, -0.8 * pre(v));
+  elsewhen h < 0.5 then
+    reinit(v, 0);
+  end when;
+end M;
+"#;
+        let golden = dae_json(source);
+
+        // Two when-equations fire both resets, the elsewhen-branch doesn't
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(&source.replace("  elsewhen", "  end when;\n  when"), "m.mo")
+            .unwrap();
+        let comparison = result.dae.compare_to_golden(&golden).unwrap();
+        let drifts: Vec<String> = comparison
+            .drifts
+            .iter()
+            .filter(|d| d.section() == "when_clauses")
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            drifts,
+            [
+                "when_clauses: when clause '{branches: [c0, c1]}' is missing",
+                "when_clauses: when clause '{branches: [c1]}' is new",
+                "when_clauses: when clause '{branches: [c0]}' is new",
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Runtime guards for generated code.
+//!
+//! Backends that support assertions can be asked to check, at runtime, that
+//! the denominators of divisions in the DAE equations are nonzero (see
+//! [`Compiler::guard_divisions`](crate::Compiler::guard_divisions)). Each guard
+//! is an `assert(denominator <> 0, message)` call stored in [`Dae::asserts`],
+//! with a message pointing back to the division in the model source.
+
+use std::collections::HashSet;
+
+use crate::dae::ast::Dae;
+use crate::ir::analysis::division_check::{evaluate, unguarded_divisions};
+use crate::ir::ast::{
+    ComponentRefPart, ComponentReference, Equation, Expression, OpBinary, TerminalType, Token,
+};
+
+impl Dae {
+    /// Add an assertion for every division whose denominator is not a nonzero constant.
+    ///
+    /// Divisions inside if-expressions and if-equations are skipped, since their
+    /// condition usually guards the denominator, as are divisions in symbolic
+    /// loops. Each denominator is guarded once.
+    pub fn add_division_guards(&mut self) {
+        let mut guarded: HashSet<String> = self
+            .asserts
+            .iter()
+            .filter_map(guarded_denominator)
+            .collect();
+        let mut asserts = Vec::new();
+        for eq in self.fx.iter().chain(&self.fz).chain(&self.fx_init) {
+            // Divisions of symbolic loops depend on the loop indices
+            if matches!(eq, Equation::For { .. }) {
+                continue;
+            }
+            for division in unguarded_divisions(eq) {
+                let denominator = division.denominator.to_string();
+                let is_nonzero_constant = evaluate(&division.denominator, &Default::default())
+                    .is_some_and(|value| value != 0.0);
+                if is_nonzero_constant || !guarded.insert(denominator.clone()) {
+                    continue;
+                }
+                let loc = &division.location;
+                let message = format!(
+                    "division by zero: '{}' is 0 at {}:{}:{}",
+                    denominator, loc.file_name, loc.start_line, loc.start_column
+                );
+                asserts.push(assert_nonzero(division.denominator, message));
+            }
+        }
+        self.asserts.extend(asserts);
+    }
+}
+
+/// `assert(denominator <> 0, message)`
+fn assert_nonzero(denominator: Expression, message: String) -> Equation {
+    Equation::FunctionCall {
+        comp: ComponentReference {
+            local: false,
+            parts: vec![ComponentRefPart {
+                ident: Token {
+                    text: "assert".to_string(),
+                    ..Default::default()
+                },
+                subs: None,
+            }],
+        },
+        args: vec![
+            Expression::Binary {
+                op: OpBinary::Neq(Token::default()),
+                lhs: Box::new(denominator),
+                rhs: Box::new(Expression::Terminal {
+                    terminal_type: TerminalType::UnsignedInteger,
+                    token: Token {
+                        text: "0".to_string(),
+                        ..Default::default()
+                    },
+                }),
+            },
+            Expression::Terminal {
+                terminal_type: TerminalType::String,
+                token: Token {
+                    text: message,
+                    ..Default::default()
+                },
+            },
+        ],
+        description: vec![],
+    }
+}
+
+/// The denominator checked by an assertion created by [`assert_nonzero`]
+fn guarded_denominator(eq: &Equation) -> Option<String> {
+    match eq {
+        Equation::FunctionCall { args, .. } => match args.first()? {
+            Expression::Binary {
+                op: OpBinary::Neq(_),
+                lhs,
+                ..
+            } => Some(lhs.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,100 @@
+//! Stable equation identifiers.
+//!
+//! Each DAE equation gets an identifier derived from its provenance — the
+//! partition it belongs to, the file it was declared in and its flattened,
+//! instance-qualified form — rather than from its position in the equation
+//! list. Reordering equations, adding unrelated ones or moving code around
+//! within a file therefore leaves the identifiers unchanged, so generated code
+//! and solver diagnostics can be mapped back to the model across recompilation.
+//!
+//! Identifiers have the form `<partition>_<hash>`, e.g. `fx_1b2c3d4e`. Exact
+//! duplicates within a model get a `_2`, `_3`, ... suffix.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::compiler::paths::base_name;
+use crate::ir::ast::Equation;
+
+/// Stable identifiers of the DAE equations, parallel to the equation lists
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquationIds {
+    pub fx: Vec<String>,      // ids of continuous time equations
+    pub fx_init: Vec<String>, // ids of initial equations
+    pub fz: Vec<String>,      // ids of event update equations
+    pub fm: Vec<String>,      // ids of discrete update equations
+    /// Source location (`file:line:column`) of each equation, by id
+    pub sources: IndexMap<String, String>,
+}
+
+/// Equation partitions of the DAE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    Fx,
+    FxInit,
+    Fz,
+    Fm,
+}
+
+impl Partition {
+    /// Name of the partition, as used in identifiers
+    pub fn name(self) -> &'static str {
+        match self {
+            Partition::Fx => "fx",
+            Partition::FxInit => "fx_init",
+            Partition::Fz => "fz",
+            Partition::Fm => "fm",
+        }
+    }
+}
+
+/// Allocates equation identifiers, disambiguating duplicates
+#[derive(Default, Debug)]
+pub struct EquationIdAllocator {
+    seen: HashMap<String, usize>,
+}
+
+impl EquationIdAllocator {
+    /// Identifier of an equation in the given partition
+    pub fn allocate(&mut self, partition: Partition, eq: &Equation) -> String {
+        let file = eq
+            .get_location()
+            .map(|l| base_name(&l.file_name).to_string())
+            .unwrap_or_default();
+        let provenance = format!("{}\n{}\n{}", partition.name(), file, eq);
+        let hash = format!("{:x}", chksum_md5::hash(provenance.as_bytes()));
+        let id = format!("{}_{}", partition.name(), &hash[..8]);
+
+        let count = self.seen.entry(id.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            id
+        } else {
+            format!("{}_{}", id, count)
+        }
+    }
+}
+
+impl EquationIds {
+    /// Record the id of an equation added to a partition, along with its source location.
+    ///
+    /// `eq` is the equation as declared; the DAE may hold a rearranged form of it.
+    pub fn push(
+        &mut self,
+        allocator: &mut EquationIdAllocator,
+        partition: Partition,
+        eq: &Equation,
+    ) {
+        let id = allocator.allocate(partition, eq);
+        if let Some(loc) = eq.get_location() {
+            self.sources.insert(id.clone(), loc.file_position());
+        }
+        match partition {
+            Partition::Fx => self.fx.push(id),
+            Partition::FxInit => self.fx_init.push(id),
+            Partition::Fz => self.fz.push(id),
+            Partition::Fm => self.fm.push(id),
+        }
+    }
+}
@@ -2,8 +2,12 @@
 //! which is part of the Abstract Syntax Tree (AST) representation in the
 //! Differential-Algebraic Equation (DAE) system. The `Dae` structure is used
 //! to model and manipulate DAE-related data within the application.
+use crate::compiler::{context as template_context, provenance};
 use crate::dae::ast::Dae;
-use anyhow::{Context, Result};
+use crate::error::{Error, Result};
+use crate::ir::ast::Equation;
+use crate::ir::{ident, literal};
+use minijinja::value::ViaDeserialize;
 use minijinja::{Environment, context};
 use std::fs;
 
@@ -15,16 +19,60 @@ pub fn warn(msg: &str) {
     eprintln!("{:?}", msg);
 }
 
-pub fn render_template(dae: &Dae, template_file: &str) -> Result<()> {
-    let template_txt = fs::read_to_string(template_file)
-        .with_context(|| format!("Can't read file {}", template_file))?;
-
+/// Template environment with the functions and filters available to templates
+///
+/// Besides the minijinja builtins, templates can use the `py_ident` and
+/// `c_ident` filters, which turn a variable name (possibly qualified, quoted
+/// or subscripted) into a Python or C identifier, see [`crate::ir::ident`],
+/// the `py_literal` and `c_literal` filters, which spell a real literal such
+/// as `Modelica.Constants.inf` in Python or C, see [`crate::ir::literal`],
+/// the `comment` filter, which prefixes each line of a text with a
+/// comment marker, e.g. `{{ provenance.header | comment("#") }}`, and the
+/// `description` filter, which gives the text of the description string of
+/// an equation (empty without one), e.g. `{{ eq | description }}`. The
+/// `require_context_version` function and the `context_version` global
+/// version the context, see [`crate::compiler::context`].
+pub(crate) fn environment() -> Environment<'static> {
     let mut env = Environment::new();
     env.add_function("panic", panic);
     env.add_function("warn", warn);
-    env.add_template("template", &template_txt)?;
-    let tmpl = env.get_template("template")?;
-    let txt = tmpl.render(context!(dae => dae)).unwrap();
+    env.add_filter("py_ident", |name: &str| ident::to_python(name));
+    env.add_filter("c_ident", |name: &str| ident::to_c(name));
+    env.add_filter("py_literal", |text: &str| {
+        literal::to_python(text).into_owned()
+    });
+    env.add_filter("c_literal", |text: &str| literal::to_c(text).into_owned());
+    env.add_filter("comment", |text: &str, prefix: &str| {
+        provenance::comment(text, prefix)
+    });
+    env.add_filter("description", |eq: ViaDeserialize<Equation>| {
+        eq.description().unwrap_or_default()
+    });
+    env.add_function("require_context_version", |version: &str| {
+        template_context::check_context_version(version)
+            .map(|_| String::new())
+            .map_err(|e| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, e))
+    });
+    env.add_global("context_version", template_context::CONTEXT_VERSION);
+    env
+}
+
+/// Check the context version a template requires before rendering it
+pub(crate) fn check_template(source: &str) -> Result<()> {
+    template_context::check_template_source(source)
+        .map_err(|e| Error::Render(format!("Template rendering failed: {}", e)))
+}
+
+pub fn render_template(dae: &Dae, template_file: &str) -> Result<()> {
+    let template_txt =
+        fs::read_to_string(template_file).map_err(|e| Error::io(template_file, e))?;
+
+    check_template(&template_txt)?;
+    let mut env = environment();
+    env.add_template("template", &template_txt)
+        .map_err(render_error)?;
+    let tmpl = env.get_template("template").map_err(render_error)?;
+    let txt = tmpl.render(context!(dae => dae)).map_err(render_error)?;
     println!("{}", txt);
     Ok(())
 }
@@ -32,13 +80,135 @@ pub fn render_template(dae: &Dae, template_file: &str) -> Result<()> {
 /// Render a template from a string directly (for WASM/editor use).
 /// Returns the rendered output as a string.
 pub fn render_template_str(dae: &Dae, template_str: &str) -> Result<String> {
-    let mut env = Environment::new();
-    env.add_function("panic", panic);
-    env.add_function("warn", warn);
-    env.add_template("template", template_str)?;
-    let tmpl = env.get_template("template")?;
-    let txt = tmpl
-        .render(context!(dae => dae))
-        .with_context(|| "Template rendering failed")?;
-    Ok(txt)
+    check_template(template_str)?;
+    let mut env = environment();
+    env.add_template("template", template_str)
+        .map_err(render_error)?;
+    let tmpl = env.get_template("template").map_err(render_error)?;
+    tmpl.render(context!(dae => dae)).map_err(render_error)
+}
+
+/// Create an [`Error::Render`] from a template error, with its details
+pub(crate) fn render_error(e: minijinja::Error) -> Error {
+    Error::Render(format!("Template rendering failed: {:#}", e))
+}
+
+/// Render a template for debugging: errors give the line and column of the
+/// template as `name:line:column` with a frame of the template source around
+/// it, and the template can use debugging aids of minijinja (e.g. `debug()`)
+pub(crate) fn render_debug(name: &str, source: &str, ctx: &minijinja::Value) -> Result<String> {
+    check_template(source)?;
+    let mut env = environment();
+    env.set_debug(true);
+    let tmpl = env
+        .template_from_named_str(name, source)
+        .map_err(|e| debug_error(&e, name, source))?;
+    tmpl.render(ctx).map_err(|e| debug_error(&e, name, source))
+}
+
+/// Create an [`Error::Render`] locating a template error in its source
+fn debug_error(e: &minijinja::Error, name: &str, source: &str) -> Error {
+    let mut message = format!("Template rendering failed: {}", name);
+    let line = e.line().unwrap_or(1);
+    let range = e.range();
+    let column = range.as_ref().map_or(1, |range| {
+        let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        source[line_start..range.start].chars().count() + 1
+    });
+    if e.line().is_some() {
+        message.push_str(&format!(":{}:{}", line, column));
+    }
+    message.push_str(&format!(": {}", e.kind()));
+    if let Some(detail) = e.detail() {
+        message.push_str(&format!(": {}", detail));
+    }
+    if e.line().is_some() {
+        message.push('\n');
+        let width = range.map(|range| source[range].chars().count());
+        message.push_str(&code_frame(source, line, column, width));
+    }
+    // Errors of called templates and filters
+    let mut cause = std::error::Error::source(e);
+    while let Some(err) = cause {
+        message.push_str(&format!("\ncaused by: {}", err));
+        cause = err.source();
+    }
+    Error::Render(message)
+}
+
+/// The lines of a template around `line` (starting at 1), marking the error
+/// from `column` on, as in
+///
+/// ```text
+///    3 |   {% for name, v in dae.x | items %}
+///    4 >   {{ v.strat }}
+///      |      ^^^^^^^
+/// ```
+fn code_frame(source: &str, line: usize, column: usize, len: Option<usize>) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let first = line.saturating_sub(3).max(1);
+    let last = (line + 2).min(lines.len());
+    let mut frame = String::new();
+    for number in first..=last {
+        let marker = if number == line { '>' } else { '|' };
+        frame.push_str(&format!("{:>4} {} {}\n", number, marker, lines[number - 1]));
+        if number == line {
+            let width = len.unwrap_or(1).max(1);
+            frame.push_str(&format!(
+                "     | {}{}\n",
+                " ".repeat(column - 1),
+                "^".repeat(width)
+            ));
+        }
+    }
+    frame.trim_end().to_string()
+}
+
+/// Warnings for the variables a template looks up that aren't in its context,
+/// like `dae.statse`, as `name:line: message`
+///
+/// Undefined variables render as empty text, so that a misspelled name fails
+/// silently. Lookups are found in the template source, for the context
+/// variables and their attributes (not for loop variables, whose values
+/// depend on rendering).
+pub(crate) fn undefined_lookups(
+    name: &str,
+    source: &str,
+    ctx: &minijinja::Value,
+) -> Result<Vec<String>> {
+    let env = environment();
+    let tmpl = env
+        .template_from_named_str(name, source)
+        .map_err(|e| debug_error(&e, name, source))?;
+    let globals: Vec<&str> = env.globals().map(|(global, _)| global).collect();
+    let mut lookups: Vec<String> = tmpl.undeclared_variables(true).into_iter().collect();
+    lookups.sort();
+    let mut warnings = Vec::new();
+    for lookup in lookups {
+        let mut parts = lookup.split('.');
+        let root = parts.next().unwrap_or_default();
+        if globals.contains(&root) {
+            continue;
+        }
+        let mut value = ctx.get_attr(root).unwrap_or_default();
+        let mut path = root.to_string();
+        let mut parent_is_map = true;
+        for part in parts {
+            if value.is_undefined() {
+                break;
+            }
+            parent_is_map = value.kind() == minijinja::value::ValueKind::Map;
+            value = value.get_attr(part).unwrap_or_default();
+            path = format!("{}.{}", path, part);
+        }
+        // Methods, as in `dae.x.items()`, aren't attributes
+        let is_method = source.contains(&format!("{}(", path));
+        if value.is_undefined() && parent_is_map && !is_method {
+            let line = source
+                .find(path.as_str())
+                .map_or(1, |at| source[..at].matches('\n').count() + 1);
+            warnings.push(format!("{}:{}: undefined variable '{}'", name, line, path));
+        }
+    }
+    Ok(warnings)
 }
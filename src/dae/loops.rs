@@ -0,0 +1,250 @@
+//! Symbolic loops of the DAE equations.
+//!
+//! For discretized PDEs and other models with many identical equations, the
+//! scalar equations of for-equations make generated code large. When compiling
+//! with [`Compiler::symbolic_loops`](crate::Compiler::symbolic_loops), the
+//! for-equations are still expanded to scalar equations for the analysis of
+//! the model, and afterwards rolled back into an [`Equation::For`] with
+//! literal ranges, e.g. `for i in 2:99 loop der(T[i]) = ...; end for`, which
+//! backends can generate loops from.
+//!
+//! A for-equation is rolled back only if its scalar equations are consecutive
+//! continuous-time equations after sorting, unchanged by causalization, and
+//! either in iteration order or independent of each other, i.e. no scalar
+//! equation uses the variable another one defines. Evaluating the loop is then
+//! equivalent to evaluating its scalar equations in the sorted order.
+//! Otherwise its scalar equations are kept.
+
+use std::collections::HashSet;
+
+use crate::dae::ast::Dae;
+use crate::ir::ast::{ComponentReference, Equation, Expression};
+use crate::ir::transform::equation_expander::SymbolicLoop;
+use crate::ir::visitor::{Visitable, Visitor};
+
+impl Dae {
+    /// Replace the scalar equations of the symbolic loops in the
+    /// continuous-time equations with the loops, returning the number of
+    /// loops rolled back
+    pub fn roll_loops(&mut self, loops: &[SymbolicLoop]) -> usize {
+        let mut rolled = 0;
+        for symbolic in loops {
+            let n = symbolic.instances.len();
+            let Some(start) = self.loop_position(symbolic) else {
+                continue;
+            };
+            self.fx
+                .splice(start..start + n, [symbolic.equation.clone()]);
+            // The loop keeps the id of its first equation
+            if self.eq_ids.fx.len() >= start + n {
+                self.eq_ids.fx.drain(start + 1..start + n);
+            }
+            rolled += 1;
+        }
+        rolled
+    }
+
+    /// Position of the consecutive scalar equations of a symbolic loop in the
+    /// continuous-time equations
+    fn loop_position(&self, symbolic: &SymbolicLoop) -> Option<usize> {
+        let instances = symbolic.instances.as_slice();
+        let n = instances.len();
+        if n == 0 {
+            return None;
+        }
+        if let Some(start) = self.fx.windows(n).position(|window| window == instances) {
+            return Some(start);
+        }
+        // Sorting may reorder independent scalar equations, e.g. in reverse
+        if !independent(instances) {
+            return None;
+        }
+        let positions = instances
+            .iter()
+            .map(|instance| self.fx.iter().position(|eq| eq == instance))
+            .collect::<Option<Vec<_>>>()?;
+        let start = *positions.iter().min()?;
+        let mut sorted = positions;
+        sorted.sort_unstable();
+        sorted.dedup();
+        (sorted.len() == n && sorted[n - 1] == start + n - 1).then_some(start)
+    }
+}
+
+/// Variable defined by an explicit scalar equation, as its name without
+/// subscripts and whether its derivative is defined
+fn defined_variable(eq: &Equation) -> Option<(String, bool)> {
+    let Equation::Simple { lhs, .. } = eq else {
+        return None;
+    };
+    match lhs {
+        Expression::ComponentReference(cref) => Some((base_name(cref), false)),
+        Expression::FunctionCall { comp, args } if comp.to_string() == "der" => {
+            match args.as_slice() {
+                [Expression::ComponentReference(cref)] => Some((base_name(cref), true)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Name of a component reference without subscripts
+fn base_name(cref: &ComponentReference) -> String {
+    cref.parts
+        .iter()
+        .map(|part| part.ident.text.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Whether no scalar equation uses a variable defined by one of them. Since
+/// subscripts may not be simplified, any element of a defined array counts.
+fn independent(instances: &[Equation]) -> bool {
+    struct Uses<'a> {
+        defined: &'a HashSet<(String, bool)>,
+        found: bool,
+    }
+    impl Visitor for Uses<'_> {
+        fn enter_expression(&mut self, node: &Expression) {
+            match node {
+                Expression::ComponentReference(cref) => {
+                    self.found |= self.defined.contains(&(base_name(cref), false));
+                }
+                Expression::FunctionCall { comp, args } => {
+                    self.found |= self.defined.contains(&(comp.to_string(), false));
+                    if comp.to_string() == "der"
+                        && let [Expression::ComponentReference(cref)] = args.as_slice()
+                    {
+                        self.found |= self.defined.contains(&(base_name(cref), true));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let Some(defined) = instances
+        .iter()
+        .map(defined_variable)
+        .collect::<Option<HashSet<_>>>()
+    else {
+        return false;
+    };
+    instances.iter().all(|eq| {
+        let Equation::Simple { rhs, .. } = eq else {
+            return false;
+        };
+        let mut uses = Uses {
+            defined: &defined,
+            found: false,
+        };
+        rhs.accept(&mut uses);
+        !uses.found
+    })
+}
+
+/// Number of scalar equations of a for-equation with literal ranges, or None
+/// if its ranges aren't literals
+pub(crate) fn loop_size(eq: &Equation) -> Option<usize> {
+    let Equation::For {
+        indices, equations, ..
+    } = eq
+    else {
+        return None;
+    };
+    let components = Default::default();
+    let iterations = indices.iter().try_fold(1, |iterations, index| {
+        crate::ir::transform::equation_expander::iteration_values(&index.range, &components)
+            .map(|values| iterations * values.len())
+    })?;
+    Some(iterations * equations.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+    use crate::compiler::limits::Limits;
+    use crate::ir::ast::Equation;
+    use crate::ir::transform::equation_expander::expand_equations_with_loops;
+
+    const SOURCE: &str = r#"
+model Rod
+  parameter Integer n = 50;
+  parameter Real k = 0.1;
+  Real T[n](each start = 300);
+  Real q[n - 1];
+equation
+  for i in 1:n - 1 loop
+    q[i] = k * (T[i] - T[i + 1]);
+  end for;
+  der(T[1]) = -q[1];
+  for i in 2:n - 1 loop
+    der(T[i]) = q[i - 1] - q[i];
+  end for;
+  der(T[n]) = q[n - 1];
+end Rod;
+"#;
+
+    #[test]
+    fn test_roll_loops() {
+        let unrolled = Compiler::new()
+            .model("Rod")
+            .compile_str(SOURCE, "rod.mo")
+            .unwrap();
+        let rolled = Compiler::new()
+            .model("Rod")
+            .symbolic_loops(true)
+            .compile_str(SOURCE, "rod.mo")
+            .unwrap();
+
+        assert_eq!(unrolled.dae.fx.len(), 99);
+        assert_eq!(rolled.dae.fx.len(), 4);
+        assert_eq!(rolled.dae.eq_ids.fx.len(), rolled.dae.fx.len());
+        let loops: Vec<String> = rolled
+            .dae
+            .fx
+            .iter()
+            .filter(|eq| matches!(eq, Equation::For { .. }))
+            .map(|eq| crate::fmt::format_equation(eq).trim_end().to_string())
+            .collect();
+        assert_eq!(
+            loops,
+            [
+                "for i in 2:49 loop\n  der(T[i]) = q[i - 1] - q[i];\nend for;",
+                "for i in 1:49 loop\n  q[i] = k * (T[i] - T[i + 1]);\nend for;",
+            ]
+        );
+
+        // The balance counts the scalar equations of the loops
+        assert!(rolled.balance.is_balanced, "{}", rolled.balance_status());
+        assert_eq!(rolled.balance.num_equations, unrolled.balance.num_equations);
+    }
+
+    #[test]
+    fn test_independent() {
+        let source = r#"
+model Chain
+  Real x[4];
+  Real y[3];
+equation
+  x[1] = time;
+  for i in 2:4 loop
+    x[i] = x[i - 1] + 1;
+  end for;
+  for i in 1:3 loop
+    der(y[i]) = -y[i] + x[i];
+  end for;
+end Chain;
+"#;
+        let result = Compiler::new()
+            .model("Chain")
+            .compile_str(source, "chain.mo")
+            .unwrap();
+        let mut class = result.expanded_class.clone();
+        let loops = expand_equations_with_loops(&mut class, &Limits::default()).unwrap();
+        assert_eq!(loops.len(), 2);
+        assert!(!super::independent(&loops[0].instances));
+        assert!(super::independent(&loops[1].instances));
+    }
+}
@@ -2,4 +2,13 @@ pub mod ast;
 pub mod balance;
 pub mod dae_ir;
 pub mod error;
+pub mod golden;
+pub mod guards;
+pub mod ids;
 pub mod jinja;
+pub mod loops;
+pub mod outputs;
+pub mod params;
+pub mod scaling;
+pub mod steady_state;
+pub mod uses;
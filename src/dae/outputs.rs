@@ -0,0 +1,309 @@
+//! Selection of the output variables of the DAE.
+//!
+//! Backends often need only a few variables of a model as results, e.g. the
+//! measured signals of a plant. Outputs are selected with
+//! [`Compiler::outputs`](crate::Compiler::outputs) (`--outputs y1,y2` on the
+//! command line), or in the model with the vendor annotation
+//! `__rumoca_output`:
+//!
+//! ```modelica
+//! Real v "Speed" annotation(__rumoca_output = true);
+//! ```
+//!
+//! A name selects the variable, or all elements of an array (`a` for `a[1]`,
+//! `a[2]`) and all variables of a component (`motor` for `motor.w`). The
+//! selected variables are listed in [`Dae::outputs`], and the algebraic
+//! variables that no output, state derivative, event or initial equation
+//! depends on are removed from the DAE with the equations computing them.
+//! Without a selection the DAE is unchanged, and all variables are results.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+
+use crate::dae::ast::Dae;
+use crate::error::{Error, Result};
+use crate::ir::ast::{Component, Expression, OpBinary, TerminalType};
+use crate::ir::structural::blt_transform_with_info;
+use crate::ir::visitor::Visitable;
+
+use super::uses::ReferenceCollector;
+
+/// Name of the annotation selecting a variable as output
+pub const OUTPUT_ANNOTATION: &str = "__rumoca_output";
+
+impl Dae {
+    /// Select the outputs named `names` and those annotated in the model, and
+    /// remove the algebraic variables they don't depend on, see the
+    /// [module docs](self). Returns the number of removed equations.
+    ///
+    /// Fails with an [`Error::UnknownOutput`] if a name is not a variable
+    /// (state, algebraic, discrete or input) of the model.
+    pub fn select_outputs(&mut self, names: &[String]) -> Result<usize> {
+        let mut outputs: IndexSet<String> = IndexSet::new();
+        for name in names {
+            let selected: Vec<&String> = self
+                .variables()
+                .map(|(var, _)| var)
+                .filter(|var| is_selected(var, name))
+                .collect();
+            if selected.is_empty() {
+                return Err(Error::UnknownOutput {
+                    name: name.clone(),
+                    model: self.model_name.clone(),
+                });
+            }
+            outputs.extend(selected.into_iter().cloned());
+        }
+        outputs.extend(
+            self.variables()
+                .filter(|(_, comp)| is_annotated_output(comp))
+                .map(|(var, _)| var.clone()),
+        );
+        if outputs.is_empty() {
+            return Ok(0);
+        }
+        let removed = self.remove_unused_algebraics(&outputs);
+        if removed > 0 {
+            self.parameter_uses = self.find_parameter_uses();
+        }
+        self.outputs = outputs.into_iter().collect();
+        Ok(removed)
+    }
+
+    /// States, algebraic, discrete and input variables
+    fn variables(&self) -> impl Iterator<Item = (&String, &Component)> {
+        self.x
+            .iter()
+            .chain(&self.y)
+            .chain(&self.z)
+            .chain(&self.m)
+            .chain(&self.u)
+    }
+
+    /// Remove the algebraic variables the outputs, the state derivatives and
+    /// the other equation partitions don't depend on, with their equations
+    fn remove_unused_algebraics(&mut self, outputs: &IndexSet<String>) -> usize {
+        // Match the continuous-time equations to the variables they compute,
+        // as when the DAE was created
+        let mut excluded: HashSet<String> = self
+            .p
+            .keys()
+            .chain(self.cp.keys())
+            .chain(self.u.keys())
+            .chain(self.x.keys())
+            .chain(self.c.keys())
+            .cloned()
+            .collect();
+        excluded.insert("time".to_string());
+        let blt = blt_transform_with_info(self.fx.clone(), &excluded);
+        let computed_by: HashMap<&str, usize> = blt
+            .matching
+            .iter()
+            .filter(|(_, var)| self.y.contains_key(*var))
+            .map(|(&eq, var)| (var.as_str(), eq))
+            .collect();
+
+        // Equations computing anything but an algebraic variable are kept,
+        // with the algebraic variables they use, transitively
+        let mut needed: IndexSet<&str> = outputs
+            .iter()
+            .map(String::as_str)
+            .filter(|name| self.y.contains_key(*name))
+            .collect();
+        let mut keep = vec![false; self.fx.len()];
+        for (i, eq) in self.fx.iter().enumerate() {
+            if !blt
+                .matching
+                .get(&i)
+                .is_some_and(|var| self.y.contains_key(var))
+            {
+                keep[i] = true;
+                needed.extend(self.referenced_algebraics(eq));
+            }
+        }
+        for eq in self
+            .fx_init
+            .iter()
+            .chain(&self.fz)
+            .chain(&self.fm)
+            .chain(&self.asserts)
+        {
+            needed.extend(self.referenced_algebraics(eq));
+        }
+        for stmt in self.fr.values() {
+            needed.extend(self.referenced_algebraics(stmt));
+        }
+        for expr in self.fc.values() {
+            needed.extend(self.referenced_algebraics(expr));
+        }
+        let mut i = 0;
+        while let Some(&name) = needed.get_index(i) {
+            if let Some(&eq) = computed_by.get(name)
+                && !keep[eq]
+            {
+                keep[eq] = true;
+                needed.extend(self.referenced_algebraics(&self.fx[eq]));
+            }
+            i += 1;
+        }
+
+        let unused: HashSet<String> = computed_by
+            .keys()
+            .filter(|name| !needed.contains(*name))
+            .map(|name| name.to_string())
+            .collect();
+        self.y.retain(|name, _| !unused.contains(name));
+        let mut keep = keep.into_iter();
+        let mut removed_ids = Vec::new();
+        let ids = std::mem::take(&mut self.eq_ids.fx);
+        let mut fx = Vec::new();
+        for (eq, id) in std::mem::take(&mut self.fx).into_iter().zip(ids) {
+            if keep.next().unwrap_or(true) {
+                fx.push(eq);
+                self.eq_ids.fx.push(id);
+            } else {
+                removed_ids.push(id);
+            }
+        }
+        self.fx = fx;
+        for id in &removed_ids {
+            self.eq_ids.sources.shift_remove(id);
+        }
+        removed_ids.len()
+    }
+
+    /// Algebraic variables referenced by an expression, equation or
+    /// statement, all elements of an array for a reference to the array or to
+    /// an element with subscripts that aren't constant
+    fn referenced_algebraics<'a>(&'a self, node: &impl Visitable) -> Vec<&'a str> {
+        let mut collector = ReferenceCollector::default();
+        node.accept(&mut collector);
+        let mut names = Vec::new();
+        for (name, base) in &collector.references {
+            if let Some((var, _)) = self.y.get_key_value(name) {
+                names.push(var.as_str());
+            } else {
+                let prefix = format!("{}[", base);
+                names.extend(
+                    self.y
+                        .keys()
+                        .filter(|var| var.starts_with(&prefix))
+                        .map(String::as_str),
+                );
+            }
+        }
+        names
+    }
+}
+
+/// Whether the output name `name` selects the variable `var`
+fn is_selected(var: &str, name: &str) -> bool {
+    var.strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('[') || rest.starts_with('.'))
+}
+
+/// Whether a variable is annotated with `__rumoca_output = true`
+fn is_annotated_output(comp: &Component) -> bool {
+    comp.annotation.iter().any(|annotation| {
+        matches!(
+            annotation,
+            Expression::Binary { op: OpBinary::Assign(_), lhs, rhs }
+                if matches!(lhs.as_ref(), Expression::ComponentReference(name) if name.to_string() == OUTPUT_ANNOTATION)
+                    && matches!(rhs.as_ref(), Expression::Terminal { terminal_type: TerminalType::Bool, token } if token.text == "true")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, Error};
+
+    #[test]
+    fn test_select_outputs() {
+        let source = r#"
+model M
+  Real x(start = 1);
+  Real a[2];
+  Real b annotation(__rumoca_output = true);
+  Real c;
+  Real d;
+equation
+  der(x) = -x + a[1];
+  a = {x, 2 * x};
+  b = x ^ 2;
+  c = b + 1;
+  d = c * 2;
+end M;
+"#;
+        let compile = |outputs: &[&str]| {
+            Compiler::new()
+                .model("M")
+                .outputs(outputs)
+                .compile_str(source, "m.mo")
+        };
+        let dae = compile(&["c"]).unwrap().dae;
+        assert_eq!(dae.outputs, ["c", "b"]);
+        // a[1] is used by der(x), a[2] and d by nothing
+        let y: Vec<&String> = dae.y.keys().collect();
+        assert_eq!(y, ["a[1]", "b", "c"]);
+        assert_eq!(dae.fx.len(), 4);
+        assert_eq!(dae.eq_ids.fx.len(), 4);
+        assert_eq!(dae.eq_ids.sources.len(), 4);
+        assert!(dae.check_balance().is_balanced);
+
+        // Arrays are selected by their name
+        let dae = compile(&["a"]).unwrap().dae;
+        assert_eq!(dae.outputs, ["a[1]", "a[2]", "b"]);
+        assert_eq!(dae.y.len(), 3);
+
+        let err = compile(&["e"]).unwrap_err();
+        assert!(
+            matches!(&err, Error::UnknownOutput { name, model } if name == "e" && model == "M"),
+            "{err:?}"
+        );
+        assert_eq!(err.to_string(), "Output 'e' is not a variable of model 'M'");
+    }
+
+    #[test]
+    fn test_dae_ir_outputs() {
+        let source = r#"
+model M
+  Real x(start = 1);
+  Real y;
+  Real z;
+equation
+  der(x) = -x;
+  y = 2 * x;
+  z = y + 1;
+end M;
+"#;
+        let json = |outputs: &[&str]| -> serde_json::Value {
+            let result = Compiler::new()
+                .model("M")
+                .outputs(outputs)
+                .compile_str(source, "m.mo")
+                .unwrap();
+            serde_json::from_str(&result.to_dae_ir_json().unwrap()).unwrap()
+        };
+        let names = |value: &serde_json::Value| -> Vec<String> {
+            value
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|var| var["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // Only the selected outputs are exported as such, and z is dropped
+        let value = json(&["y"]);
+        assert_eq!(names(&value["variables"]["outputs"]), ["y"]);
+        assert_eq!(names(&value["variables"]["algebraic"]), ["y"]);
+        assert_eq!(value["variables"]["outputs"][0]["vartype"], "Real");
+
+        // Without a selection all variables are results
+        let value = json(&[]);
+        assert!(names(&value["variables"]["outputs"]).is_empty());
+        assert_eq!(names(&value["variables"]["algebraic"]), ["y", "z"]);
+    }
+}
@@ -0,0 +1,414 @@
+//! Parameter files for parameter overrides and experiments.
+//!
+//! A parameter file sets the values of parameters of a compiled model, in
+//! TOML or, for files ending in `.json`, JSON. Names are the flattened names
+//! of the parameters, and nested tables are dotted names. An array value sets
+//! the elements of an array parameter, and the `experiments` table holds
+//! named sets of values that override the top-level ones:
+//!
+//! ```toml
+//! k = 2.5
+//! motor.J = 0.1          # or [motor] J = 0.1
+//! gains = [1.0, 2.0]     # gains[1], gains[2]
+//! "r[1]".R = 10
+//!
+//! [experiments.stiff]
+//! k = 250.0
+//! ```
+//!
+//! [`Dae::set_parameters`] validates the values against the model: every
+//! name must be a parameter (constants and structural parameters, whose
+//! values were used during compilation, can't be set) and the values must
+//! have its type.
+//!
+//! Files that can't be parsed fail with an [`Error::ParameterFile`], values
+//! the model rejects with an [`Error::InvalidParameters`] listing each
+//! [`InvalidParameter`].
+
+use std::fmt;
+use std::path::Path;
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::dae::ast::Dae;
+use crate::error::{Error, Result};
+use crate::ir::ast::{Expression, OpUnary, TerminalType, Token};
+use crate::ir::literal::format_real;
+
+/// Name of the table of experiments
+const EXPERIMENTS: &str = "experiments";
+
+/// Parameter values of a parameter file, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterFile {
+    /// Values outside of experiments, by dotted name
+    pub values: IndexMap<String, Value>,
+    /// Values of each experiment, by dotted name
+    pub experiments: IndexMap<String, IndexMap<String, Value>>,
+}
+
+impl ParameterFile {
+    /// Read a parameter file, as JSON if its name ends in `.json`, otherwise
+    /// as TOML
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let file = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        };
+        file.map_err(|e| match e {
+            Error::ParameterFile(message) => Error::ParameterFile(format!(
+                "Invalid parameter file '{}': {}",
+                path.display(),
+                message
+            )),
+            e => e,
+        })
+    }
+
+    /// Parse a parameter file in TOML
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text).map_err(invalid)?;
+        Self::from_value(serde_json::to_value(table).map_err(invalid)?)
+    }
+
+    /// Parse a parameter file in JSON
+    pub fn from_json(text: &str) -> Result<Self> {
+        Self::from_value(serde_json::from_str(text).map_err(invalid)?)
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        let Value::Object(mut table) = value else {
+            return Err(invalid("expected a table of parameter values"));
+        };
+        let mut file = Self::default();
+        match table.remove(EXPERIMENTS) {
+            Some(Value::Object(experiments)) => {
+                for (name, values) in experiments {
+                    let Value::Object(values) = values else {
+                        return Err(invalid(format!("experiment '{}' is not a table", name)));
+                    };
+                    let mut flat = IndexMap::new();
+                    flatten("", values, &mut flat);
+                    file.experiments.insert(name, flat);
+                }
+            }
+            Some(_) => {
+                return Err(invalid(format!(
+                    "'{}' is not a table of experiments",
+                    EXPERIMENTS
+                )));
+            }
+            None => {}
+        }
+        flatten("", table, &mut file.values);
+        Ok(file)
+    }
+
+    /// The values of an experiment, i.e. the top-level values overridden by
+    /// those of the experiment, or only the top-level values
+    pub fn values(&self, experiment: Option<&str>) -> Result<IndexMap<String, Value>> {
+        let mut values = self.values.clone();
+        if let Some(name) = experiment {
+            let Some(overrides) = self.experiments.get(name) else {
+                return Err(Error::UnknownExperiment {
+                    name: name.to_string(),
+                    experiments: self.experiments.keys().cloned().collect(),
+                });
+            };
+            values.extend(overrides.clone());
+        }
+        Ok(values)
+    }
+}
+
+/// An [`Error::ParameterFile`] for a file that can't be parsed
+fn invalid(error: impl fmt::Display) -> Error {
+    Error::ParameterFile(error.to_string())
+}
+
+/// A parameter value rejected by [`Dae::set_parameters`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidParameter {
+    /// The name is not a parameter of the model
+    Unknown { name: String },
+    /// The name is a constant
+    Constant { name: String },
+    /// An array parameter is set to a scalar
+    NotAnArray { name: String },
+    /// The parameter is structural, its value was used during compilation
+    Structural { name: String },
+    /// The value is not of the type of the parameter
+    Value {
+        name: String,
+        type_name: String,
+        value: Value,
+    },
+}
+
+impl fmt::Display for InvalidParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidParameter::Unknown { name } => write!(f, "unknown parameter '{}'", name),
+            InvalidParameter::Constant { name } => write!(f, "'{}' is a constant", name),
+            InvalidParameter::NotAnArray { name } => {
+                write!(f, "'{}' is an array parameter, set it to an array", name)
+            }
+            InvalidParameter::Structural { name } => write!(
+                f,
+                "'{}' is a structural parameter, its value can only be changed in the model",
+                name
+            ),
+            InvalidParameter::Value {
+                name,
+                type_name,
+                value,
+            } => write!(
+                f,
+                "invalid value {} for {} parameter '{}'",
+                value, type_name, name
+            ),
+        }
+    }
+}
+
+/// Flatten nested tables to dotted names
+fn flatten(prefix: &str, table: serde_json::Map<String, Value>, out: &mut IndexMap<String, Value>) {
+    for (key, value) in table {
+        let name = format!("{}{}", prefix, key);
+        match value {
+            Value::Object(table) => flatten(&format!("{}.", name), table, out),
+            value => {
+                out.insert(name, value);
+            }
+        }
+    }
+}
+
+impl Dae {
+    /// Set the values of parameters, see the [module docs](self), failing
+    /// with an [`Error::InvalidParameters`] listing all invalid names and
+    /// values
+    pub fn set_parameters(&mut self, values: &IndexMap<String, Value>) -> Result<()> {
+        let mut scalars = Vec::new();
+        let mut errors = Vec::new();
+        for (name, value) in values {
+            element_values(name, value, &mut Vec::new(), &mut scalars);
+        }
+        for (name, value) in scalars {
+            let Some(comp) = self.p.get_mut(&name) else {
+                errors.push(if self.cp.contains_key(&name) {
+                    InvalidParameter::Constant { name }
+                } else if self.p.keys().any(|p| p.starts_with(&format!("{}[", name))) {
+                    InvalidParameter::NotAnArray { name }
+                } else {
+                    InvalidParameter::Unknown { name }
+                });
+                continue;
+            };
+            if self.structural.contains(&name) {
+                errors.push(InvalidParameter::Structural { name });
+                continue;
+            }
+            let type_name = comp.type_name.to_string();
+            match literal(&type_name, value) {
+                Some(expr) => comp.start = expr,
+                None => errors.push(InvalidParameter::Value {
+                    name,
+                    type_name,
+                    value: value.clone(),
+                }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::InvalidParameters(errors));
+        }
+        // Parameters set to values are no longer bound to other parameters
+        self.parameter_uses = self.find_parameter_uses();
+        Ok(())
+    }
+}
+
+/// Scalar values of a value, an array setting its elements `name[i]`,
+/// `name[i,j]`, ...
+fn element_values<'a>(
+    name: &str,
+    value: &'a Value,
+    index: &mut Vec<usize>,
+    out: &mut Vec<(String, &'a Value)>,
+) {
+    match value {
+        Value::Array(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                index.push(i + 1);
+                element_values(name, element, index, out);
+                index.pop();
+            }
+        }
+        value if index.is_empty() => out.push((name.to_string(), value)),
+        value => {
+            let index: Vec<String> = index.iter().map(usize::to_string).collect();
+            out.push((format!("{}[{}]", name, index.join(",")), value));
+        }
+    }
+}
+
+/// A value as a literal of a parameter type
+fn literal(type_name: &str, value: &Value) -> Option<Expression> {
+    let terminal = |terminal_type, text: String| Expression::Terminal {
+        terminal_type,
+        token: Token {
+            text,
+            ..Default::default()
+        },
+    };
+    let number = |terminal_type, value: f64, text: String| {
+        let text = text.trim_start_matches('-').to_string();
+        if value < 0.0 {
+            Expression::Unary {
+                op: OpUnary::Minus(Token::default()),
+                rhs: Box::new(terminal(terminal_type, text)),
+            }
+        } else {
+            terminal(terminal_type, text)
+        }
+    };
+    match (type_name, value) {
+        ("Boolean", Value::Bool(value)) => Some(terminal(TerminalType::Bool, value.to_string())),
+        ("String", Value::String(value)) => Some(terminal(TerminalType::String, value.clone())),
+        ("Integer", Value::Number(value)) => {
+            let value = value.as_i64()?;
+            Some(number(
+                TerminalType::UnsignedInteger,
+                value as f64,
+                value.to_string(),
+            ))
+        }
+        ("Boolean" | "String" | "Integer", _) => None,
+        (_, Value::Number(value)) => {
+            let value = value.as_f64()?;
+            Some(number(
+                TerminalType::UnsignedReal,
+                value,
+                format_real(value),
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    #[test]
+    fn test_parameter_file() {
+        let file = ParameterFile::from_toml(
+            r#"
+k = 2
+motor.J = 0.5
+gains = [[1, 2], [3, 4]]
+
+[experiments.fast]
+k = -10.5
+"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = file.values.keys().map(String::as_str).collect();
+        assert_eq!(names, ["gains", "k", "motor.J"]);
+        assert_eq!(file.values(None).unwrap()["k"], 2);
+        assert_eq!(file.values(Some("fast")).unwrap()["k"], -10.5);
+        let err = file.values(Some("slow")).unwrap_err();
+        assert!(matches!(err, Error::UnknownExperiment { .. }), "{err:?}");
+        assert_eq!(
+            err.to_string(),
+            "unknown experiment 'slow' (experiments: fast)"
+        );
+
+        let json = ParameterFile::from_json(r#"{"motor": {"J": 0.5}, "k": 2}"#).unwrap();
+        assert_eq!(json.values["motor.J"], file.values["motor.J"]);
+
+        for err in [
+            ParameterFile::from_toml("k = ").unwrap_err(),
+            ParameterFile::from_json("[1, 2]").unwrap_err(),
+            ParameterFile::from_toml("experiments = 1").unwrap_err(),
+        ] {
+            assert!(matches!(err, Error::ParameterFile(_)), "{err:?}");
+        }
+    }
+
+    #[test]
+    fn test_set_parameters() {
+        let source = r#"
+model Motor
+  parameter Real J = 1;
+end Motor;
+model M
+  parameter Integer n = 2;
+  parameter Real k = 1;
+  parameter Real gains[2, 2] = {{1, 1}, {1, 1}};
+  parameter Boolean on = true;
+  constant Real c = 3;
+  Motor motor;
+  Real x[n](each start = 0);
+equation
+  for i in 1:n loop
+    der(x[i]) = if on then -k * x[i] + gains[1, i] * motor.J else 0;
+  end for;
+end M;
+"#;
+        let mut dae = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap()
+            .dae;
+        let file = ParameterFile::from_toml(
+            r#"
+k = -2
+motor.J = 0.5
+on = false
+gains = [[1, 2], [3, 4.5]]
+"#,
+        )
+        .unwrap();
+        dae.set_parameters(&file.values(None).unwrap()).unwrap();
+        assert_eq!(dae.p["k"].start.to_string(), "-2.0");
+        assert_eq!(dae.p["motor.J"].start.to_string(), "0.5");
+        assert_eq!(dae.p["on"].start.to_string(), "false");
+        assert_eq!(dae.p["gains[2,2]"].start.to_string(), "4.5");
+
+        let file = ParameterFile::from_toml(
+            r#"
+n = 3
+c = 1
+kk = 1
+gains = 1
+on = 1
+"#,
+        )
+        .unwrap();
+        let err = dae.set_parameters(&file.values(None).unwrap()).unwrap_err();
+        let Error::InvalidParameters(invalid) = &err else {
+            panic!("expected invalid parameters, got {err:?}");
+        };
+        assert_eq!(
+            invalid[2],
+            InvalidParameter::Unknown {
+                name: "kk".to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter values:
+  'c' is a constant
+  'gains' is an array parameter, set it to an array
+  unknown parameter 'kk'
+  'n' is a structural parameter, its value can only be changed in the model
+  invalid value 1 for Boolean parameter 'on'"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Scaling report of the continuous-time equations of the DAE.
+//!
+//! [`Dae::scaling`] estimates the range of the terms of each equation from
+//! the parameter values and the nominal attributes of the variables (see
+//! [`crate::ir::analysis::scaling`]) and reports the badly scaled equations,
+//! whose largest term is more than a threshold times their smallest:
+//!
+//! ```text
+//! Scaling of M: 1 of 3 equations badly scaled (ratio above 1e6)
+//! warning: m.mo:8:3: badly scaled equation 'p = k * x + y': its terms range from 1e-3 ('y') to 2e5 ('k * x')
+//!   help: Set the nominal attribute of 'x' to the typical magnitude of the variable
+//! ```
+//!
+//! `rumoca model.mo -m M --analyze scaling` prints the report, or all the
+//! analyzed equations as JSON with `--json`.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::dae::ast::Dae;
+use crate::ir::analysis::scaling::{EquationScaling, ScalingPoint, equation_scaling};
+
+/// Scaling of the equations of a model, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScalingReport {
+    pub model_name: String,
+    /// Ratio of the largest to the smallest term beyond which an equation is
+    /// badly scaled
+    pub threshold: f64,
+    /// Equations with at least two terms that can be evaluated
+    pub equations: Vec<EquationScaling>,
+}
+
+impl ScalingReport {
+    /// The equations whose terms range beyond the threshold
+    pub fn badly_scaled(&self) -> impl Iterator<Item = &EquationScaling> {
+        self.equations
+            .iter()
+            .filter(|scaling| scaling.ratio() > self.threshold)
+    }
+}
+
+impl fmt::Display for ScalingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Scaling of {}: {} of {} equations badly scaled (ratio above {:e})",
+            self.model_name,
+            self.badly_scaled().count(),
+            self.equations.len(),
+            self.threshold
+        )?;
+        for scaling in self.badly_scaled() {
+            write!(f, "warning: ")?;
+            if let Some(location) = &scaling.location {
+                write!(f, "{}: ", location.file_position())?;
+            }
+            writeln!(f, "{}", scaling.message())?;
+            writeln!(f, "  help: {}", scaling.suggestion())?;
+        }
+        Ok(())
+    }
+}
+
+impl Dae {
+    /// Report the scaling of the continuous-time equations, see the
+    /// [module docs](self)
+    pub fn scaling(&self, threshold: f64) -> ScalingReport {
+        let variables = self.x.iter().chain(&self.y).chain(&self.u);
+        let point = ScalingPoint::new(self.known_values(), variables);
+        ScalingReport {
+            model_name: self.model_name.clone(),
+            threshold,
+            equations: self
+                .fx
+                .iter()
+                .filter_map(|eq| equation_scaling(eq, &point))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+    use crate::ir::analysis::scaling::DEFAULT_THRESHOLD;
+
+    #[test]
+    fn test_scaling_report() {
+        let source = r#"model Sub
+  parameter Real k = 1e5;
+  Real p;
+  Real x(start = 2);
+  Real y(nominal = 1e-3);
+equation
+  p = k * x + y;
+  der(x) = -x;
+  y = 1e-3 * sin(time);
+end Sub;
+
+model M
+  Sub s(k = 10);
+  Sub t;
+end M;
+"#;
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap();
+        let report = result.dae.scaling(DEFAULT_THRESHOLD);
+        // The modified parameter keeps s well scaled
+        let badly_scaled: Vec<&str> = report
+            .badly_scaled()
+            .map(|scaling| scaling.equation.as_str())
+            .collect();
+        assert_eq!(badly_scaled, ["t.p = t.k * t.x + t.y"]);
+        let text = report.to_string();
+        assert!(
+            text.starts_with("Scaling of M: 1 of 4 equations badly scaled (ratio above 1e6)\nwarning: m.mo:7:3: badly scaled equation 't.p = t.k * t.x + t.y'"),
+            "{}",
+            text
+        );
+        assert!(
+            text.ends_with("  help: Set the nominal attribute of 't.x' to the typical magnitude of the variable\n"),
+            "{}",
+            text
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Steady-state (equilibrium) solving of the DAE.
+//!
+//! At an equilibrium the states don't change, so setting `der(x) = 0` turns
+//! the continuous-time equations into a nonlinear algebraic system in the
+//! states and algebraic variables. [`Dae::steady_state`] solves it with
+//! Newton's method, starting from the start values:
+//!
+//! - parameters, inputs and discrete variables keep their (start) values, and
+//!   time is 0
+//! - the Jacobian is symbolic, see
+//!   [`crate::ir::structural::differentiate::partial_derivative`]
+//! - conditions of if-expressions and if-equations are re-evaluated at every
+//!   iteration, with the Boolean variables defined by them (`b = x > 0`), so
+//!   a piecewise model settles on the branch of its equilibrium
+//! - steps that don't reduce the residual are halved
+//!
+//! Equations are evaluated numerically, so only the built-in math functions
+//! are supported; models calling user functions fail with an error.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{Result, bail};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::dae::ast::Dae;
+use crate::error::{Error, describe};
+use crate::ir::analysis::division_check::{evaluate, evaluate_condition};
+use crate::ir::ast::{Equation, Expression, OpBinary, TerminalType, Token};
+use crate::ir::structural::VariableFinder;
+use crate::ir::structural::differentiate::partial_derivative;
+use crate::ir::visitor::{MutVisitable, MutVisitor, Visitable};
+
+/// Newton iterations before giving up
+const MAX_ITERATIONS: usize = 100;
+
+/// Largest absolute residual of a solution
+const TOLERANCE: f64 = 1e-10;
+
+/// Step halvings of a Newton step before taking it anyway
+const MAX_HALVINGS: usize = 20;
+
+/// Equilibrium of a model, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SteadyState {
+    pub model_name: String,
+    /// Values of the states
+    pub states: IndexMap<String, f64>,
+    /// Values of the algebraic variables
+    pub algebraics: IndexMap<String, f64>,
+    /// Newton iterations taken
+    pub iterations: usize,
+    /// Largest absolute residual of the equations at the solution
+    pub residual: f64,
+}
+
+impl fmt::Display for SteadyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Steady state of {} ({} iterations, residual {:e})",
+            self.model_name, self.iterations, self.residual
+        )?;
+        for (title, values) in [
+            ("states", &self.states),
+            ("algebraic variables", &self.algebraics),
+        ] {
+            if values.is_empty() {
+                continue;
+            }
+            writeln!(f, "{}:", title)?;
+            let width = values.keys().map(String::len).max().unwrap_or(0);
+            for (name, value) in values {
+                writeln!(f, "  {:<width$} = {}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dae {
+    /// Solve for the equilibrium of the model, see the [module docs](self)
+    ///
+    /// Fails with an [`Error::SteadyState`] if there is no equilibrium or it
+    /// can't be found.
+    pub fn steady_state(&self) -> crate::Result<SteadyState> {
+        self.solve_steady_state()
+            .map_err(|e| Error::SteadyState(describe(e)))
+    }
+
+    fn solve_steady_state(&self) -> Result<SteadyState> {
+        let equations = SteadyStateEquations::new(self)?;
+        let residuals = &equations.residuals;
+        let unknowns: Vec<&String> = self.x.keys().chain(self.y.keys()).collect();
+        if residuals.len() != unknowns.len() {
+            bail!(
+                "cannot solve for the steady state: {} equations for {} unknowns",
+                residuals.len(),
+                unknowns.len()
+            );
+        }
+
+        let mut values = self.known_values();
+        for (name, comp) in self.x.iter().chain(&self.y) {
+            let guess = evaluate(&comp.start, &values).unwrap_or(0.0);
+            values.insert(name.clone(), guess);
+        }
+
+        // Symbolic Jacobian, entries of the unknowns each residual uses
+        let mut jacobian = Vec::with_capacity(residuals.len());
+        for residual in residuals {
+            let mut finder = VariableFinder::new();
+            residual.accept(&mut finder);
+            let mut row = Vec::new();
+            for (j, name) in unknowns.iter().enumerate() {
+                if !finder.variables.contains(*name) {
+                    continue;
+                }
+                let Some(derivative) = partial_derivative(residual, name) else {
+                    bail!(
+                        "cannot differentiate '{}' with respect to '{}'",
+                        residual,
+                        name
+                    );
+                };
+                row.push((j, derivative));
+            }
+            jacobian.push(row);
+        }
+
+        let n = unknowns.len();
+        let (mut r, mut norm) = equations.evaluate(&mut values)?;
+        let mut iterations = 0;
+        while norm > TOLERANCE {
+            if iterations == MAX_ITERATIONS {
+                bail!(
+                    "steady state did not converge in {} iterations (residual {:e})",
+                    MAX_ITERATIONS,
+                    norm
+                );
+            }
+            iterations += 1;
+
+            let mut matrix = vec![vec![0.0; n]; n];
+            for (i, row) in jacobian.iter().enumerate() {
+                for (j, derivative) in row {
+                    matrix[i][*j] = evaluate_at(derivative, &values)?;
+                }
+            }
+            let rhs: Vec<f64> = r.iter().map(|r| -r).collect();
+            let step = match solve_linear(matrix, rhs) {
+                Ok(step) => step,
+                Err(i) => bail!(
+                    "singular Jacobian at iteration {}: equation '{}' (residual {} = {:e}) can't be zeroed independently of the other equations",
+                    iterations,
+                    equations.sources[i],
+                    residuals[i],
+                    r[i]
+                ),
+            };
+
+            // Halve the step until the residual decreases
+            let start: Vec<f64> = unknowns.iter().map(|name| values[*name]).collect();
+            let mut scale = 1.0;
+            for halving in 0..=MAX_HALVINGS {
+                for (j, name) in unknowns.iter().enumerate() {
+                    values.insert((*name).clone(), start[j] + scale * step[j]);
+                }
+                let trial = equations.evaluate(&mut values);
+                let trial_norm = trial.as_ref().map_or(f64::INFINITY, |(_, norm)| *norm);
+                if trial_norm < norm || halving == MAX_HALVINGS {
+                    (r, norm) = trial?;
+                    break;
+                }
+                scale /= 2.0;
+            }
+            if !norm.is_finite() {
+                bail!(
+                    "steady state iteration diverged at iteration {}",
+                    iterations
+                );
+            }
+        }
+
+        let value_of = |names: &IndexMap<String, _>| -> IndexMap<String, f64> {
+            names
+                .keys()
+                .map(|name| (name.clone(), values[name]))
+                .collect()
+        };
+        Ok(SteadyState {
+            model_name: self.model_name.clone(),
+            states: value_of(&self.x),
+            algebraics: value_of(&self.y),
+            iterations,
+            residual: norm,
+        })
+    }
+
+    /// Values of time, parameters, inputs and discrete variables
+    pub(crate) fn known_values(&self) -> HashMap<String, f64> {
+        let mut values = HashMap::from([("time".to_string(), 0.0)]);
+        // Parameters may be bound to other parameters, in any order
+        let parameters: Vec<_> = self.p.iter().chain(&self.cp).collect();
+        loop {
+            let mut changed = false;
+            for (name, comp) in &parameters {
+                if !values.contains_key(*name)
+                    && let Some(value) = evaluate_known(&comp.start, &values)
+                {
+                    values.insert((*name).clone(), value);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        for (name, comp) in self.u.iter().chain(&self.z).chain(&self.m) {
+            let value = evaluate_known(&comp.start, &values).unwrap_or(0.0);
+            values.insert(name.clone(), value);
+        }
+        values
+    }
+}
+
+/// The steady-state equations, with array elements referred to by their
+/// names, e.g. `x[1]`
+struct SteadyStateEquations {
+    /// Residuals `lhs - rhs` of the continuous-time equations with `der(x) = 0`
+    residuals: Vec<Expression>,
+    /// Equation of each residual, as declared
+    sources: Vec<String>,
+    /// Conditions and their expressions
+    conditions: Vec<(String, Expression)>,
+    /// Boolean variables defined by conditions and their definitions
+    discrete: Vec<(String, Expression)>,
+}
+
+impl SteadyStateEquations {
+    fn new(dae: &Dae) -> Result<Self> {
+        let mut residuals = Vec::new();
+        let mut sources = Vec::new();
+        for eq in &dae.fx {
+            let eq_residuals = equation_residuals(eq)?;
+            sources.extend(std::iter::repeat_n(eq.to_string(), eq_residuals.len()));
+            residuals.extend(eq_residuals);
+        }
+        for residual in &mut residuals {
+            residual.accept_mut(&mut DerivativeZeroer);
+        }
+        let conditions = dae
+            .fc
+            .iter()
+            .map(|(name, cond)| (name.clone(), cond.clone()))
+            .collect();
+        let discrete = dae
+            .fm
+            .iter()
+            .filter_map(|eq| match eq {
+                Equation::Simple {
+                    lhs: Expression::ComponentReference(var),
+                    rhs,
+                    ..
+                } => Some((var.to_string(), rhs.clone())),
+                _ => None,
+            })
+            .collect();
+        Ok(Self {
+            residuals,
+            sources,
+            conditions,
+            discrete,
+        })
+    }
+
+    /// Update the conditions and the variables defined by them, then evaluate the residuals and their largest
+    /// absolute value
+    fn evaluate(&self, values: &mut HashMap<String, f64>) -> Result<(Vec<f64>, f64)> {
+        for (name, cond) in self.conditions.iter().chain(&self.discrete) {
+            match evaluate_condition(cond, values) {
+                Some(value) => values.insert(name.clone(), f64::from(u8::from(value))),
+                None => values.remove(name),
+            };
+        }
+        let r = self
+            .residuals
+            .iter()
+            .map(|residual| evaluate_at(residual, values))
+            .collect::<Result<Vec<f64>>>()?;
+        let norm = r.iter().fold(0.0, |norm: f64, r| norm.max(r.abs()));
+        Ok((r, norm))
+    }
+}
+
+/// Residuals of an equation, an if-equation giving one if-expression for
+/// each of the equations of its branches
+fn equation_residuals(eq: &Equation) -> Result<Vec<Expression>> {
+    match eq {
+        Equation::Simple { lhs, rhs, .. } => Ok(vec![Expression::Binary {
+            op: OpBinary::Sub(Token::default()),
+            lhs: Box::new(lhs.clone()),
+            rhs: Box::new(Expression::Parenthesized {
+                inner: Box::new(rhs.clone()),
+            }),
+        }]),
+        Equation::If {
+            cond_blocks,
+            else_block: Some(else_block),
+            ..
+        } => {
+            let residuals_of = |eqs: &[Equation]| -> Result<Vec<Expression>> {
+                let mut residuals = Vec::new();
+                for eq in eqs {
+                    residuals.extend(equation_residuals(eq)?);
+                }
+                Ok(residuals)
+            };
+            let else_residuals = residuals_of(else_block)?;
+            let branches = cond_blocks
+                .iter()
+                .map(|block| Ok((block.cond.clone(), residuals_of(&block.eqs)?)))
+                .collect::<Result<Vec<_>>>()?;
+            if branches
+                .iter()
+                .any(|(_, residuals)| residuals.len() != else_residuals.len())
+            {
+                bail!(
+                    "the branches of if-equation '{}' have different numbers of equations",
+                    eq
+                );
+            }
+            Ok(else_residuals
+                .into_iter()
+                .enumerate()
+                .map(|(i, else_residual)| Expression::If {
+                    branches: branches
+                        .iter()
+                        .map(|(cond, residuals)| (cond.clone(), residuals[i].clone()))
+                        .collect(),
+                    else_branch: Box::new(else_residual),
+                })
+                .collect())
+        }
+        _ => bail!("steady state of equation '{}' is not supported", eq),
+    }
+}
+
+/// Evaluate a residual or Jacobian entry
+fn evaluate_at(expr: &Expression, values: &HashMap<String, f64>) -> Result<f64> {
+    match evaluate(expr, values) {
+        Some(value) => Ok(value),
+        None => bail!("cannot evaluate '{}' numerically", expr),
+    }
+}
+
+/// Value of a parameter or discrete variable, Booleans as 1 or 0
+fn evaluate_known(expr: &Expression, values: &HashMap<String, f64>) -> Option<f64> {
+    evaluate(expr, values).or_else(|| {
+        matches!(
+            expr,
+            Expression::Terminal {
+                terminal_type: TerminalType::Bool,
+                ..
+            }
+        )
+        .then(|| evaluate_condition(expr, values))
+        .flatten()
+        .map(|value| f64::from(u8::from(value)))
+    })
+}
+
+/// Solve `a * x = b` by Gaussian elimination of the rows in order, each
+/// pivoting on its largest entry, failing with the first row that the
+/// previous ones reduce to zeros if `a` is singular
+fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> std::result::Result<Vec<f64>, usize> {
+    let n = b.len();
+    let scale = a
+        .iter()
+        .flatten()
+        .fold(0.0, |max: f64, v| max.max(v.abs()))
+        .max(1.0);
+    // Column of the pivot of each row
+    let mut pivots: Vec<usize> = Vec::with_capacity(n);
+    for row in 0..n {
+        let pivot = (0..n)
+            .filter(|col| !pivots.contains(col))
+            .max_by(|&i, &j| a[row][i].abs().total_cmp(&a[row][j].abs()))
+            .filter(|&col| a[row][col].abs() > 1e-14 * scale)
+            .ok_or(row)?;
+        for other in row + 1..n {
+            let factor = a[other][pivot] / a[row][pivot];
+            if factor == 0.0 {
+                continue;
+            }
+            for k in 0..n {
+                a[other][k] -= factor * a[row][k];
+            }
+            a[other][pivot] = 0.0;
+            b[other] -= factor * b[row];
+        }
+        pivots.push(pivot);
+    }
+    let mut x = vec![0.0; n];
+    for (row, &pivot) in pivots.iter().enumerate().rev() {
+        let sum: f64 = (0..n)
+            .filter(|&k| k != pivot)
+            .map(|k| a[row][k] * x[k])
+            .sum();
+        x[pivot] = (b[row] - sum) / a[row][pivot];
+    }
+    Ok(x)
+}
+
+/// Replaces `der(x)` with 0
+struct DerivativeZeroer;
+
+impl MutVisitor for DerivativeZeroer {
+    fn exit_expression(&mut self, node: &mut Expression) {
+        if let Expression::FunctionCall { comp, .. } = node
+            && comp.to_string() == "der"
+        {
+            *node = Expression::Terminal {
+                terminal_type: TerminalType::UnsignedInteger,
+                token: Token {
+                    text: "0".to_string(),
+                    ..Default::default()
+                },
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_linear() {
+        let a = vec![vec![0.0, 2.0], vec![1.0, 1.0]];
+        assert_eq!(solve_linear(a, vec![4.0, 3.0]), Ok(vec![1.0, 2.0]));
+        let a = vec![
+            vec![0.0, 1.0, 0.0],
+            vec![2.0, 0.0, 1.0],
+            vec![1.0, 1.0, 1.0],
+        ];
+        let x = solve_linear(a, vec![2.0, 3.0, 4.0]).unwrap();
+        assert!(
+            x.iter()
+                .zip([1.0, 2.0, 1.0])
+                .all(|(x, e)| (x - e).abs() < 1e-12)
+        );
+
+        // Fails with the row that can't be zeroed
+        let singular = vec![vec![1.0, 2.0], vec![2.0, 4.0]];
+        assert_eq!(solve_linear(singular, vec![1.0, 2.0]), Err(1));
+        let singular = vec![vec![0.0, 0.0], vec![1.0, 1.0]];
+        assert_eq!(solve_linear(singular, vec![1.0, 2.0]), Err(0));
+    }
+}
@@ -0,0 +1,208 @@
+//! Where-used index of the parameters.
+//!
+//! Tuning a parameter changes the equations that reference it, and the
+//! components whose values are bound to it: parameters with bindings like
+//! `k2 = 2 * k1`, start values and conditions. Bindings of parameters and
+//! conditions are followed transitively, so the equations using `k2` (or a
+//! condition `c0` for `x > k1`) depend on `k1` too. Embedded
+//! users can tell which parameters require re-linearization or new code when
+//! tuned. The index is `dae.parameter_uses` in templates, and `uses` of each
+//! parameter in the DAE IR JSON.
+
+use std::collections::HashSet;
+
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::dae::ast::Dae;
+use crate::ir::ast::ComponentReference;
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// What depends on a parameter, see the [module docs](self)
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterUses {
+    /// Ids of the equations using the parameter (see
+    /// [`EquationIds`](crate::dae::ids::EquationIds))
+    pub equations: Vec<String>,
+    /// Parameters, variables and conditions whose values depend on it
+    pub components: Vec<String>,
+}
+
+impl Dae {
+    /// Where each parameter is used, see the [module docs](self)
+    pub fn find_parameter_uses(&self) -> IndexMap<String, ParameterUses> {
+        // Components bound to each parameter, directly
+        let mut dependents: IndexMap<&str, IndexSet<&str>> = IndexMap::new();
+        let components = self
+            .p
+            .iter()
+            .chain(&self.x)
+            .chain(&self.y)
+            .chain(&self.z)
+            .chain(&self.m)
+            .map(|(name, comp)| (name, &comp.start))
+            .chain(&self.fc);
+        for (name, expr) in components {
+            for known in self.referenced_knowns(expr) {
+                if known != name {
+                    dependents.entry(known).or_default().insert(name);
+                }
+            }
+        }
+
+        // Parameters and conditions used by each equation
+        let partitions = [
+            (&self.fx, &self.eq_ids.fx),
+            (&self.fx_init, &self.eq_ids.fx_init),
+            (&self.fz, &self.eq_ids.fz),
+            (&self.fm, &self.eq_ids.fm),
+        ];
+        let equations: Vec<(&String, HashSet<&str>)> = partitions
+            .into_iter()
+            .flat_map(|(equations, ids)| equations.iter().zip(ids))
+            .map(|(eq, id)| (id, self.referenced_knowns(eq)))
+            .collect();
+
+        self.p
+            .keys()
+            .map(|name| {
+                // The parameter and the components bound to it, transitively
+                // through parameters and conditions
+                let mut bound: IndexSet<&str> = IndexSet::from([name.as_str()]);
+                let mut i = 0;
+                while let Some(&known) = bound.get_index(i) {
+                    if (self.p.contains_key(known) || self.c.contains_key(known))
+                        && let Some(names) = dependents.get(known)
+                    {
+                        bound.extend(names);
+                    }
+                    i += 1;
+                }
+                let uses = ParameterUses {
+                    equations: equations
+                        .iter()
+                        .filter(|(_, knowns)| knowns.iter().any(|known| bound.contains(known)))
+                        .map(|(id, _)| id.to_string())
+                        .collect(),
+                    components: bound.iter().skip(1).map(|name| name.to_string()).collect(),
+                };
+                (name.clone(), uses)
+            })
+            .collect()
+    }
+
+    /// Parameters and conditions referenced by an expression or equation, all
+    /// elements of an array parameter for a reference to the array or to an
+    /// element with subscripts that aren't constant
+    fn referenced_knowns<'a>(&'a self, node: &impl Visitable) -> HashSet<&'a str> {
+        let mut collector = ReferenceCollector::default();
+        node.accept(&mut collector);
+        let mut knowns = HashSet::new();
+        for (name, base) in &collector.references {
+            if let Some((known, _)) = self
+                .p
+                .get_key_value(name)
+                .or_else(|| self.c.get_key_value(name))
+            {
+                knowns.insert(known.as_str());
+            } else {
+                let prefix = format!("{}[", base);
+                knowns.extend(
+                    self.p
+                        .keys()
+                        .filter(|param| param.starts_with(&prefix))
+                        .map(String::as_str),
+                );
+            }
+        }
+        knowns
+    }
+}
+
+/// Collects the names of the references of an expression, with and without
+/// their subscripts
+#[derive(Default)]
+pub(crate) struct ReferenceCollector {
+    pub(crate) references: HashSet<(String, String)>,
+}
+
+impl Visitor for ReferenceCollector {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        self.references
+            .insert((node.to_string(), node.unsubscripted_name()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    #[test]
+    fn test_parameter_uses() {
+        let source = r#"
+model M
+  parameter Real k1 = 1;
+  parameter Real k2 = 2 * k1;
+  parameter Real k3 = k2 + 1;
+  parameter Real x0 = 3;
+  parameter Real gains[2] = {1, 2};
+  parameter Real unused = 0;
+  Real x(start = x0);
+  Real y;
+equation
+  der(x) = -k3 * x;
+  y = if x > k1 then gains[1] * x else 0;
+end M;
+"#;
+        let mut dae = Compiler::new()
+            .model("M")
+            .compile_str(source, "m.mo")
+            .unwrap()
+            .dae;
+        let id = |start: &str| {
+            let i = dae
+                .fx
+                .iter()
+                .position(|eq| eq.to_string().starts_with(start));
+            dae.eq_ids.fx[i.unwrap()].clone()
+        };
+        let (der_x, y) = (id("der(x)"), id("y ="));
+        let uses = &dae.parameter_uses;
+
+        // k1 is used through the condition of y, and through k2 and k3 by der(x)
+        assert_eq!(uses["k1"].components, ["k2", "c0", "k3"]);
+        let mut equations = uses["k1"].equations.clone();
+        equations.sort();
+        let mut expected = vec![der_x.clone(), y.clone()];
+        expected.sort();
+        assert_eq!(equations, expected);
+
+        assert_eq!(uses["k3"].equations, std::slice::from_ref(&der_x));
+        assert!(uses["k3"].components.is_empty());
+        assert_eq!(uses["x0"].components, ["x"]);
+        assert_eq!(uses["gains[1]"].equations, std::slice::from_ref(&y));
+        assert!(uses["gains[2]"].equations.is_empty());
+        assert_eq!(uses["unused"], Default::default());
+
+        let json: serde_json::Value = serde_json::from_str(&dae.to_dae_ir_json().unwrap()).unwrap();
+        let x0 = json["variables"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "x0")
+            .unwrap();
+        assert_eq!(x0["uses"]["components"], serde_json::json!(["x"]));
+        let txt = crate::dae::jinja::render_template_str(
+            &dae,
+            "{{ dae.parameter_uses.k3.equations | join(',') }}",
+        )
+        .unwrap();
+        assert_eq!(txt, der_x);
+
+        // Setting k2 to a value unbinds it from k1
+        let values = [("k2".to_string(), serde_json::json!(5.0))].into();
+        dae.set_parameters(&values).unwrap();
+        assert_eq!(dae.parameter_uses["k1"].components, ["c0"]);
+        assert_eq!(dae.parameter_uses["k1"].equations, [y]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Error type of the library API.
+//!
+//! Every fallible entry point of the library ([`Compiler`](crate::Compiler),
+//! [`CompilationResult`](crate::CompilationResult), the pipeline and the DAE
+//! template renderer) returns an [`enum@Error`], so callers can tell a syntax
+//! error from a missing class or a template that failed to render without
+//! matching on message text. Internal passes report `anyhow` errors, which are
+//! sorted into a variant by the compilation stage that produced them.
+//!
+//! The linter and formatter never fail: lint problems are reported as
+//! [`LintMessage`](crate::LintMessage)s, and the formatter only re-indents
+//! source that does not parse.
+
+use std::path::PathBuf;
+
+use parol_runtime::ParolError;
+use thiserror::Error;
+
+use crate::compiler::CompileWarning;
+use crate::compiler::conformance::RelaxedConstruct;
+use crate::compiler::error_handling::{create_syntax_error, extract_parse_error};
+use crate::dae::params::InvalidParameter;
+
+/// Result type of the library API
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the library API
+#[derive(Error, Debug)]
+pub enum Error {
+    /// A source, package or template file could not be read
+    #[error("Failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The source text is not valid Modelica
+    ///
+    /// Displays as the rendered diagnostic, with the offending line and a
+    /// label at the error location. Line and column are 1-indexed.
+    #[error("{report}")]
+    Parse {
+        file: String,
+        line: u32,
+        column: u32,
+        message: String,
+        report: String,
+    },
+
+    /// A package or library could not be found, or contains no Modelica files
+    #[error("{0}")]
+    Package(String),
+
+    /// The model could not be flattened, e.g. the model name is missing or a
+    /// class it extends or instantiates was not found
+    #[error("{0}")]
+    Flatten(String),
+
+    /// The flattened model is invalid, e.g. it references an undefined
+    /// variable or calls a built-in function with invalid arguments
+    #[error("{0}")]
+    Type(String),
+
+    /// The DAE could not be created from the flattened model
+    #[error("{0}")]
+    Balance(String),
+
+    /// An output selected with [`Compiler::outputs`](crate::Compiler::outputs)
+    /// is not a variable of the model
+    #[error("Output '{name}' is not a variable of model '{model}'")]
+    UnknownOutput { name: String, model: String },
+
+    /// A custom pass registered with the [`Compiler`](crate::Compiler) failed
+    #[error("Pass '{name}' failed: {message}")]
+    Pass { name: String, message: String },
+
+    /// A template could not be rendered, or the DAE could not be serialized
+    #[error("{0}")]
+    Render(String),
+
+    /// The compilation produced warnings denied with
+    /// [`Compiler::deny`](crate::Compiler::deny)
+    #[error("{}", describe_denied(.0))]
+    Denied(Vec<CompileWarning>),
+
+    /// The model uses relaxations of the Modelica specification, which fail
+    /// with [`Compiler::strict`](crate::Compiler::strict)
+    #[error("{}", describe_strict(.0))]
+    Strict(Vec<RelaxedConstruct>),
+
+    /// The compilation exceeded a resource limit, e.g. an array expanding to
+    /// too many elements, too deeply nested components or the
+    /// [`Compiler::timeout`](crate::Compiler::timeout)
+    #[error("{0}")]
+    Limit(String),
+
+    /// A parameter file could not be parsed, see
+    /// [`ParameterFile`](crate::dae::params::ParameterFile)
+    #[error("{0}")]
+    ParameterFile(String),
+
+    /// The experiment selected from a parameter file is not in the file
+    #[error("unknown experiment '{name}' (experiments: {})", describe_experiments(.experiments))]
+    UnknownExperiment {
+        name: String,
+        experiments: Vec<String>,
+    },
+
+    /// Parameter values are not valid for the model, see
+    /// [`Dae::set_parameters`](crate::dae::ast::Dae::set_parameters)
+    #[error("invalid parameter values:\n  {}", describe_invalid_parameters(.0))]
+    InvalidParameters(Vec<InvalidParameter>),
+
+    /// A golden DAE to compare to is not a DAE IR JSON export, see
+    /// [`Dae::compare_to_golden`](crate::dae::ast::Dae::compare_to_golden)
+    #[error("{0}")]
+    Golden(String),
+
+    /// The steady state of the DAE could not be solved, e.g. Newton's method
+    /// did not converge or the model calls functions that can't be evaluated
+    /// numerically, see [`Dae::steady_state`](crate::dae::ast::Dae::steady_state)
+    #[error("{0}")]
+    SteadyState(String),
+
+    /// Any other failure, e.g. the thread pool could not be created
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Create an [`Error::Parse`] from a parser error
+    pub(crate) fn parse(error: &ParolError, source: &str, file: &str) -> Self {
+        let (line, column, message) = extract_parse_error(error, source);
+        let report = format!(
+            "{:?}",
+            miette::Report::new(create_syntax_error(error, source))
+        );
+        Error::Parse {
+            file: file.to_string(),
+            line,
+            column,
+            message,
+            report,
+        }
+    }
+
+    /// Create an [`Error::Io`] for a file that could not be read
+    pub(crate) fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+/// List denied warnings, one per line
+fn describe_denied(warnings: &[CompileWarning]) -> String {
+    let lines: Vec<String> = warnings.iter().map(|w| format!("denied: {w}")).collect();
+    lines.join("\n")
+}
+
+/// List relaxed constructs, one per line
+fn describe_strict(relaxations: &[RelaxedConstruct]) -> String {
+    let lines: Vec<String> = relaxations.iter().map(|r| format!("strict: {r}")).collect();
+    lines.join("\n")
+}
+
+/// List the experiments of a parameter file
+fn describe_experiments(experiments: &[String]) -> String {
+    if experiments.is_empty() {
+        "none".to_string()
+    } else {
+        experiments.join(", ")
+    }
+}
+
+/// List invalid parameter values, one per line
+fn describe_invalid_parameters(invalid: &[InvalidParameter]) -> String {
+    let lines: Vec<String> = invalid.iter().map(ToString::to_string).collect();
+    lines.join("\n  ")
+}
+
+/// Format an internal error with its chain of causes
+pub(crate) fn describe(error: impl Into<anyhow::Error>) -> String {
+    format!("{:#}", error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+
+    #[test]
+    fn test_parse_error_location() {
+        let err = Compiler::new()
+            .model("M")
+            .compile_str("model M\n  Real x = ;\nend M;", "m.mo")
+            .unwrap_err();
+        match err {
+            Error::Parse {
+                file, line, report, ..
+            } => {
+                assert_eq!(file, "m.mo");
+                assert_eq!(line, 2);
+                assert!(report.contains("Syntax error"), "{report}");
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_errors_by_stage() {
+        let compile = |model: Option<&str>, source: &str| {
+            let mut compiler = Compiler::new();
+            if let Some(model) = model {
+                compiler = compiler.model(model);
+            }
+            compiler.compile_str(source, "m.mo").unwrap_err()
+        };
+        let source = "model M\n  Real x;\nequation\n  der(x) = y;\nend M;";
+        assert!(matches!(compile(None, source), Error::Flatten(_)));
+        assert!(matches!(compile(Some("N"), source), Error::Flatten(_)));
+        assert!(matches!(compile(Some("M"), source), Error::Type(msg) if msg.contains("'y'")));
+        assert!(matches!(
+            Compiler::new().model("M").compile_file("missing.mo"),
+            Err(Error::Io { .. })
+        ));
+    }
+}
@@ -1,7 +1,10 @@
 //! Class formatting with comment preservation.
 
 use super::visitor::FormatVisitor;
-use crate::ir::ast::{Causality, ClassDefinition, ClassType, Component, Equation, Statement};
+use crate::ir::ast::{
+    Causality, ClassDefinition, ClassType, Component, Equation, Expression, OpBinary, Purity,
+    Statement,
+};
 
 /// Get the source line number of an equation
 pub fn get_equation_location(eq: &Equation) -> Option<u32> {
@@ -10,7 +13,7 @@ pub fn get_equation_location(eq: &Equation) -> Option<u32> {
         Equation::Simple { lhs, .. } => lhs.get_location().map(|l| l.start_line),
         Equation::Connect { lhs, .. } => Some(lhs.parts.first()?.ident.location.start_line),
         Equation::For { indices, .. } => Some(indices.first()?.ident.location.start_line),
-        Equation::When(blocks) => blocks
+        Equation::When { blocks, .. } => blocks
             .first()
             .and_then(|b| b.cond.get_location())
             .map(|l| l.start_line),
@@ -22,6 +25,37 @@ pub fn get_equation_location(eq: &Equation) -> Option<u32> {
     }
 }
 
+/// `redeclare` and `replaceable` prefixes of a nested class
+fn replaceable_prefix(class: &ClassDefinition) -> String {
+    format!(
+        "{}{}",
+        if class.redeclare { "redeclare " } else { "" },
+        if class.replaceable {
+            "replaceable "
+        } else {
+            ""
+        }
+    )
+}
+
+/// `pure ` or `impure ` prefix of a function
+fn purity_prefix(class: &ClassDefinition) -> &'static str {
+    match class.purity {
+        Purity::Unspecified => "",
+        Purity::Pure => "pure ",
+        Purity::Impure => "impure ",
+    }
+}
+
+/// ` constrainedby Interface` suffix of a replaceable class
+fn constraining_clause(class: &ClassDefinition) -> String {
+    class
+        .constrainedby
+        .as_ref()
+        .map(|name| format!(" constrainedby {}", name))
+        .unwrap_or_default()
+}
+
 /// Get the source line number of a statement
 pub fn get_statement_location(stmt: &Statement) -> Option<u32> {
     match stmt {
@@ -38,8 +72,25 @@ pub fn get_statement_location(stmt: &Statement) -> Option<u32> {
             .first()
             .and_then(|b| b.cond.get_location())
             .map(|l| l.start_line),
-        Statement::Return { token } => Some(token.location.start_line),
-        Statement::Break { token } => Some(token.location.start_line),
+        Statement::Return { token, .. } => Some(token.location.start_line),
+        Statement::Break { token, .. } => Some(token.location.start_line),
+    }
+}
+
+/// Name of the element modified by a modification argument (e.g. `unit` in `unit = "m"`)
+fn modification_key(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Binary {
+            op: OpBinary::Assign(_),
+            lhs,
+            ..
+        } => match &**lhs {
+            Expression::ComponentReference(comp) => Some(comp.to_string()),
+            Expression::FunctionCall { comp, .. } => Some(comp.to_string()),
+            _ => None,
+        },
+        Expression::FunctionCall { comp, .. } => Some(comp.to_string()),
+        _ => None,
     }
 }
 
@@ -100,15 +151,46 @@ pub fn format_class_with_comments(
             let mod_strs: Vec<String> = ext
                 .modifications
                 .iter()
-                .map(|e| visitor.format_expression(e))
+                .map(|e| {
+                    let prefix = modification_key(e)
+                        .and_then(|key| ext.modification_prefixes.get(&key))
+                        .copied()
+                        .unwrap_or_default();
+                    format!(
+                        "{}{}{}",
+                        if prefix.each { "each " } else { "" },
+                        if prefix.r#final { "final " } else { "" },
+                        visitor.format_expression(e)
+                    )
+                })
                 .collect();
             format!("({})", mod_strs.join(", "))
         } else {
             String::new()
         };
+        // Include array dimensions if present (e.g., Real[3])
+        let dims_str = match &class.short_class {
+            Some(short_class) if !short_class.shape_expr.is_empty() => {
+                let dims: Vec<String> = short_class
+                    .shape_expr
+                    .iter()
+                    .map(|s| visitor.format_subscript(s))
+                    .collect();
+                format!("[{}]", dims.join(", "))
+            }
+            _ => String::new(),
+        };
         visitor.writeln(&format!(
-            "{} {} = {}{}{};",
-            class_keyword, class.name.text, causality_prefix, base_type, mods_str
+            "{}{}{} {} = {}{}{}{}{};",
+            replaceable_prefix(class),
+            purity_prefix(class),
+            class_keyword,
+            class.name.text,
+            causality_prefix,
+            base_type,
+            dims_str,
+            mods_str,
+            constraining_clause(class)
         ));
 
         // Add blank lines after this class if requested
@@ -150,8 +232,13 @@ pub fn format_class_with_comments(
         String::new()
     };
     visitor.writeln(&format!(
-        "{}{} {}{}",
-        encapsulated, class_keyword, class.name.text, description
+        "{}{}{}{} {}{}",
+        replaceable_prefix(class),
+        encapsulated,
+        purity_prefix(class),
+        class_keyword,
+        class.name.text,
+        description
     ));
     visitor.indent_level += 1;
 
@@ -294,9 +381,31 @@ pub fn format_class_with_comments(
         }
     }
 
+    // Class annotation - copied verbatim from source when preserving unformatted content
+    if !class.annotation.is_empty() {
+        let preserved = visitor.source_class_annotation(
+            class.location.start as usize,
+            class.location.end as usize,
+            &class.name.text,
+        );
+        let text = preserved.unwrap_or_else(|| {
+            let args: Vec<String> = class
+                .annotation
+                .iter()
+                .map(|e| visitor.format_expression(e))
+                .collect();
+            format!("annotation({})", args.join(", "))
+        });
+        visitor.writeln(&format!("{};", text));
+    }
+
     // End class - emit any remaining comments for this class before end
     visitor.indent_level -= 1;
-    visitor.writeln(&format!("end {};", class.name.text));
+    visitor.writeln(&format!(
+        "end {}{};",
+        class.name.text,
+        constraining_clause(class)
+    ));
 
     // Add blank lines after this class if requested (for spacing between classes)
     if add_trailing_blanks {
@@ -25,7 +25,7 @@ mod operators;
 mod options;
 mod visitor;
 
-pub use options::{CONFIG_FILE_NAMES, FormatOptions};
+pub use options::{CONFIG_FILE_NAMES, ExponentStyle, FormatOptions};
 
 use crate::ir::ast::{Expression, StoredDefinition};
 use class_formatter::format_class_with_comments;
@@ -58,6 +58,9 @@ pub fn format_modelica(text: &str, options: &FormatOptions) -> String {
     use crate::modelica_grammar::ModelicaGrammar;
     use crate::modelica_parser::parse;
 
+    // Strip any byte order mark and normalize line endings before parsing
+    let text = crate::compiler::normalize_source(text);
+    let text = text.as_ref();
     let mut grammar = ModelicaGrammar::new();
     match parse(text, "<format>", &mut grammar) {
         Ok(_) => {
@@ -353,4 +356,171 @@ end Test;"#;
             result
         );
     }
+
+    #[test]
+    fn test_format_preserves_annotations_verbatim() {
+        let input = r#"model Test
+  Real x annotation(Dialog(group = "A",  tab="B"));
+equation
+  der(x) = -x;
+  annotation(Icon(graphics={Line(points={{0,0},{1,1}})}));
+end Test;"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert!(
+            result.contains(r#"Real x annotation(Dialog(group = "A",  tab="B"));"#),
+            "Component annotation should be copied verbatim: {}",
+            result
+        );
+        assert!(
+            result
+                .contains("  annotation(Icon(graphics={Line(points={{0,0},{1,1}})}));\nend Test;"),
+            "Class annotation should be copied verbatim: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_format_class_annotation_without_preserve() {
+        let input = r#"model Test
+  Real x;
+  annotation(experiment(StopTime=1));
+end Test;"#;
+        let options = FormatOptions {
+            preserve_unformatted: false,
+            ..Default::default()
+        };
+        let result = format_modelica(input, &options);
+        assert!(
+            result.contains("annotation(experiment("),
+            "Class annotation should not be dropped: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_each_and_final_modifications() {
+        let input = r#"model Test
+  Real x[3](each final start=1, final fixed=true);
+  final parameter Real k = 1;
+end Test;"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert!(
+            result.contains("Real x[3](each final start = 1, final fixed = true);"),
+            "Modification prefixes should be kept: {}",
+            result
+        );
+        assert!(
+            result.contains("final parameter Real k = 1;"),
+            "Final prefix should be kept: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_format_short_class_with_dimensions() {
+        let input = r#"package P
+  type Vec = Real[3](each unit="m");
+  connector RealOutput = output Real[2];
+end P;"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert!(
+            result.contains("type Vec = Real[3](each unit = \"m\");"),
+            "Short class dimensions should be kept: {}",
+            result
+        );
+        assert!(
+            result.contains("connector RealOutput = output Real[2];"),
+            "Short class prefix and dimensions should be kept: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_format_round_trips_quoted_identifiers() {
+        let input = r#"model 'Quoted Model'
+  parameter Real 'k\'s' = 2;
+  Real 'x'[2](each start = 1);
+  Real 'my var+1';
+equation
+  for 'i' in 1:2 loop
+    der('x'['i']) = -'k\'s' * 'x'['i'];
+  end for;
+  'my var+1' = 'x'[1] + 'x'[2];
+end 'Quoted Model';
+"#;
+        // Parsed, so this isn't the fallback formatter's output
+        assert!(crate::compiler::parse_source_simple(input, "q.mo").is_some());
+        let result = format_modelica(input, &FormatOptions::default());
+        assert_eq!(result, input);
+        assert_eq!(format_modelica(&result, &FormatOptions::default()), result);
+    }
+
+    #[test]
+    fn test_format_round_trips_replaceable_elements() {
+        let input = r#"model Base
+  replaceable Resistor r(R = 2) "Load" constrainedby OnePort;
+  replaceable model Load = Resistor constrainedby OnePort;
+end Base;
+
+model M
+  extends Base;
+  redeclare final Capacitor r;
+  redeclare model Load = Capacitor;
+end M;
+"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_format_round_trips_function_purity() {
+        let input = r#"impure function log
+  input Real x;
+algorithm
+  print(String(x));
+end log;
+
+pure function f = g;
+"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_format_round_trips_global_references() {
+        let input = r#"model M
+  Real x;
+equation
+  der(x) = .P.f(x) + P.c;
+algorithm
+  x := .P.c;
+  .P.log(x);
+end M;
+"#;
+        let result = format_modelica(input, &FormatOptions::default());
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_format_exponent_style() {
+        let input =
+            "model M\n  parameter Real a = 1.5E+05;\n  parameter Real b = 2e-03 * 1.25;\nend M;\n";
+        assert_eq!(format_modelica(input, &FormatOptions::default()), input);
+
+        let options = FormatOptions {
+            exponent_style: ExponentStyle::Lower,
+            ..Default::default()
+        };
+        let result = format_modelica(input, &options);
+        assert!(result.contains("a = 1.5e5;"), "{}", result);
+        assert!(result.contains("b = 2e-3 * 1.25;"), "{}", result);
+
+        let options = FormatOptions {
+            exponent_style: ExponentStyle::Upper,
+            ..Default::default()
+        };
+        let result = format_modelica(input, &options);
+        assert!(result.contains("a = 1.5E5;"), "{}", result);
+        assert!(result.contains("b = 2E-3 * 1.25;"), "{}", result);
+    }
 }
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+pub use crate::ir::literal::ExponentStyle;
+
 /// Formatting options for Modelica code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -19,6 +21,10 @@ pub struct FormatOptions {
     /// Number of blank lines to insert between top-level class definitions (models, functions, etc.)
     #[serde(default = "default_blank_lines_between_classes")]
     pub blank_lines_between_classes: usize,
+    /// Exponent style of real literals: `preserve` (as written), `lower`
+    /// (`1.5e5`) or `upper` (`1.5E5`)
+    #[serde(default)]
+    pub exponent_style: ExponentStyle,
 }
 
 fn default_blank_lines_between_classes() -> usize {
@@ -33,6 +39,7 @@ impl Default for FormatOptions {
             max_line_length: 100,
             preserve_unformatted: true,
             blank_lines_between_classes: 1,
+            exponent_style: ExponentStyle::Preserve,
         }
     }
 }
@@ -49,6 +56,7 @@ impl FormatOptions {
             max_line_length: 100,
             preserve_unformatted: true,
             blank_lines_between_classes: 1,
+            exponent_style: ExponentStyle::Preserve,
         }
     }
 
@@ -60,6 +68,7 @@ impl FormatOptions {
             max_line_length: 100,
             preserve_unformatted: true,
             blank_lines_between_classes: 1,
+            exponent_style: ExponentStyle::Preserve,
         }
     }
 
@@ -17,6 +17,10 @@ impl FormatVisitor {
         if !comp.description.is_empty() {
             return true;
         }
+        // Has final, redeclare or replaceable prefix
+        if comp.r#final || comp.redeclare || comp.replaceable {
+            return true;
+        }
         // Has annotation
         if !comp.annotation.is_empty() {
             return true;
@@ -104,6 +108,16 @@ impl FormatVisitor {
     pub fn format_component(&self, comp: &Component) -> String {
         let mut result = String::new();
 
+        if comp.redeclare {
+            result.push_str("redeclare ");
+        }
+        if comp.r#final {
+            result.push_str("final ");
+        }
+        if comp.replaceable {
+            result.push_str("replaceable ");
+        }
+
         // Variability prefix
         match &comp.variability {
             Variability::Constant(_) => result.push_str("constant "),
@@ -153,15 +167,31 @@ impl FormatVisitor {
             // Add start= modifier if it's a modification
             if has_start_mod {
                 let each_prefix = if comp.start_has_each { "each " } else { "" };
+                let final_prefix = match comp.modification_prefixes.get("start") {
+                    Some(prefix) if prefix.r#final => "final ",
+                    _ => "",
+                };
                 mods.push(format!(
-                    "{}start = {}",
+                    "{}{}start = {}",
                     each_prefix,
+                    final_prefix,
                     self.format_expression(&comp.start)
                 ));
             }
             // Add other modifications
             for (k, v) in &comp.modifications {
-                mods.push(format!("{} = {}", k, self.format_expression(v)));
+                let prefix = comp
+                    .modification_prefixes
+                    .get(k)
+                    .copied()
+                    .unwrap_or_default();
+                mods.push(format!(
+                    "{}{}{} = {}",
+                    if prefix.each { "each " } else { "" },
+                    if prefix.r#final { "final " } else { "" },
+                    k,
+                    self.format_expression(v)
+                ));
             }
             result.push_str(&format!("({})", mods.join(", ")));
         }
@@ -191,14 +221,23 @@ impl FormatVisitor {
             result.push_str(&format!(" {}", desc.join(" ")));
         }
 
-        // Annotation
+        // Annotation - copied verbatim from source when preserving unformatted content
         if !comp.annotation.is_empty() {
-            let args: Vec<String> = comp
-                .annotation
-                .iter()
-                .map(|e| self.format_expression(e))
-                .collect();
-            result.push_str(&format!(" annotation({})", args.join(", ")));
+            if let Some(text) = self.source_declaration_annotation(comp.location.start as usize) {
+                result.push_str(&format!(" {}", text));
+            } else {
+                let args: Vec<String> = comp
+                    .annotation
+                    .iter()
+                    .map(|e| self.format_expression(e))
+                    .collect();
+                result.push_str(&format!(" annotation({})", args.join(", ")));
+            }
+        }
+
+        // Constraining clause of a replaceable component
+        if let Some(constrainedby) = &comp.constrainedby {
+            result.push_str(&format!(" constrainedby {}", constrainedby));
         }
 
         result.push(';');
@@ -12,7 +12,7 @@ impl FormatVisitor {
 
         match eq {
             Equation::Empty => String::new(),
-            Equation::Simple { lhs, rhs } => {
+            Equation::Simple { lhs, rhs, .. } => {
                 let lhs_str = self.format_expression(lhs);
 
                 // Check if RHS is a multi-line array
@@ -30,7 +30,7 @@ impl FormatVisitor {
                 let rhs_str = self.format_expression(rhs);
                 format!("{}{} = {};\n", indent, lhs_str, rhs_str)
             }
-            Equation::Connect { lhs, rhs } => {
+            Equation::Connect { lhs, rhs, .. } => {
                 format!(
                     "{}connect({}, {});\n",
                     indent,
@@ -38,7 +38,9 @@ impl FormatVisitor {
                     self.format_comp_ref(rhs)
                 )
             }
-            Equation::For { indices, equations } => {
+            Equation::For {
+                indices, equations, ..
+            } => {
                 let idx_str = self.format_for_indices(indices);
                 let mut result = format!("{}for {} loop\n", indent, idx_str);
                 for sub_eq in equations {
@@ -47,7 +49,7 @@ impl FormatVisitor {
                 result.push_str(&format!("{}end for;\n", indent));
                 result
             }
-            Equation::When(blocks) => {
+            Equation::When { blocks, .. } => {
                 let mut result = String::new();
                 for (i, block) in blocks.iter().enumerate() {
                     if i == 0 {
@@ -73,6 +75,7 @@ impl FormatVisitor {
             Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 let mut result = String::new();
                 for (i, block) in cond_blocks.iter().enumerate() {
@@ -102,7 +105,7 @@ impl FormatVisitor {
                 result.push_str(&format!("{}end if;\n", indent));
                 result
             }
-            Equation::FunctionCall { comp, args } => {
+            Equation::FunctionCall { comp, args, .. } => {
                 let args_str: Vec<String> =
                     args.iter().map(|a| self.format_expression(a)).collect();
                 format!(
@@ -120,7 +123,7 @@ impl FormatVisitor {
 
         match stmt {
             Statement::Empty => String::new(),
-            Statement::Assignment { comp, value } => {
+            Statement::Assignment { comp, value, .. } => {
                 format!(
                     "{}{} := {};\n",
                     indent,
@@ -128,7 +131,7 @@ impl FormatVisitor {
                     self.format_expression(value)
                 )
             }
-            Statement::FunctionCall { comp, args } => {
+            Statement::FunctionCall { comp, args, .. } => {
                 let args_str: Vec<String> =
                     args.iter().map(|a| self.format_expression(a)).collect();
                 format!(
@@ -138,7 +141,9 @@ impl FormatVisitor {
                     args_str.join(", ")
                 )
             }
-            Statement::For { indices, equations } => {
+            Statement::For {
+                indices, equations, ..
+            } => {
                 let idx_str = self.format_for_indices(indices);
                 let mut result = format!("{}for {} loop\n", indent, idx_str);
                 for sub_stmt in equations {
@@ -162,6 +167,7 @@ impl FormatVisitor {
             Statement::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 let mut result = String::new();
                 for (i, block) in cond_blocks.iter().enumerate() {
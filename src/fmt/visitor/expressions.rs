@@ -6,6 +6,8 @@ use crate::ir::ast::{
     ComponentRefPart, ComponentReference, Expression, OpBinary, Subscript, TerminalType,
 };
 
+use crate::ir::literal::normalize_exponent;
+
 use super::super::operators::{
     binary_op_is_right_assoc, binary_op_precedence, format_binary_op, format_unary_op,
 };
@@ -34,14 +36,22 @@ impl FormatVisitor {
                 TerminalType::String => format!("\"{}\"", token.text),
                 _ => {
                     // Use original source text if available to preserve exact user input
+                    let mut text = &token.text;
+                    let source_text;
                     if let Some(src) = &self.source {
                         let start = token.location.start as usize;
                         let end = token.location.end as usize;
                         if start < end && end <= src.len() {
-                            return src[start..end].to_string();
+                            source_text = src[start..end].to_string();
+                            text = &source_text;
+                        }
+                    }
+                    match terminal_type {
+                        TerminalType::UnsignedReal => {
+                            normalize_exponent(text, self.options.exponent_style).into_owned()
                         }
+                        _ => text.clone(),
                     }
-                    token.text.clone()
                 }
             },
             Expression::ComponentReference(comp_ref) => self.format_comp_ref(comp_ref),
@@ -172,6 +182,9 @@ impl FormatVisitor {
                     indices_str.join(", ")
                 )
             }
+            Expression::NamedArgument { name, value } => {
+                format!("{} = {}", name.text, self.format_expression(value))
+            }
         }
     }
 
@@ -181,7 +194,9 @@ impl FormatVisitor {
             .iter()
             .map(|p| self.format_comp_ref_part(p))
             .collect();
-        parts.join(".")
+        // Global references, as in `.P.c`, keep their leading dot
+        let dot = if comp_ref.local { "." } else { "" };
+        format!("{}{}", dot, parts.join("."))
     }
 
     fn format_comp_ref_part(&self, part: &ComponentRefPart) -> String {
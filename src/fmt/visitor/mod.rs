@@ -7,6 +7,7 @@ mod visitor_impl;
 
 use super::FormatOptions;
 use crate::ir::ast::Import;
+use crate::modelica_grammar::cst::SyntaxTree;
 
 /// A comment with its location for reinsertion during formatting
 #[derive(Debug, Clone)]
@@ -29,6 +30,9 @@ pub struct FormatVisitor {
     current_line: u32,
     /// Original source text for extracting exact token text
     pub source: Option<String>,
+    /// Lossless syntax tree of the source, used to copy regions the formatter
+    /// doesn't model byte-exactly (only set when `preserve_unformatted` is enabled)
+    pub syntax: Option<SyntaxTree>,
 }
 
 impl FormatVisitor {
@@ -47,6 +51,7 @@ impl FormatVisitor {
             next_comment_idx: 0,
             current_line: 1,
             source: None,
+            syntax: None,
         }
     }
 
@@ -69,9 +74,61 @@ impl FormatVisitor {
             next_comment_idx: 0,
             current_line: 1,
             source: Some(source.to_string()),
+            syntax: options
+                .preserve_unformatted
+                .then(|| SyntaxTree::parse(source)),
         }
     }
 
+    /// Source text of the `annotation(...)` clause of the declaration starting at
+    /// `offset`, copied byte-exactly from the syntax tree.
+    ///
+    /// Scans up to the `;` terminating the declaration, ignoring nested parentheses.
+    pub fn source_declaration_annotation(&self, offset: usize) -> Option<String> {
+        let tree = self.syntax.as_ref()?;
+        let tokens = tree.tokens();
+        let mut depth = 0usize;
+        let first = tree.token_index_from_offset(offset);
+        for (idx, token) in tokens.iter().enumerate().skip(first) {
+            if token.is_symbol("(") || token.is_symbol("[") || token.is_symbol("{") {
+                depth += 1;
+            } else if token.is_symbol(")") || token.is_symbol("]") || token.is_symbol("}") {
+                depth = depth.saturating_sub(1);
+            } else if depth == 0 && token.is_symbol(";") {
+                return None;
+            } else if depth == 0 && token.is_keyword("annotation") {
+                let span = tree.annotation_span_at(idx)?;
+                return tree.text(span).map(str::to_string);
+            }
+        }
+        None
+    }
+
+    /// Source text of a class-level `annotation(...)` clause, copied byte-exactly
+    /// from the syntax tree.
+    ///
+    /// The class annotation is the clause directly followed by `; end <name>`
+    /// within the class's byte range.
+    pub fn source_class_annotation(&self, start: usize, end: usize, name: &str) -> Option<String> {
+        let tree = self.syntax.as_ref()?;
+        let tokens = tree.tokens();
+        let first = tree.token_index_from_offset(start);
+        let last = tree.token_index_from_offset(end);
+        (first..last)
+            .rev()
+            .filter(|&idx| tokens[idx].is_keyword("annotation"))
+            .find_map(|idx| {
+                let close = tree.matching_close(idx + 1)?;
+                let is_class_annotation = tokens.get(close + 1)?.is_symbol(";")
+                    && tokens.get(close + 2)?.is_keyword("end")
+                    && tokens.get(close + 3)?.text == name;
+                is_class_annotation
+                    .then(|| tree.annotation_span_at(idx))
+                    .flatten()
+                    .and_then(|span| tree.text(span).map(str::to_string))
+            })
+    }
+
     /// Emit any comments that should appear before the given source line
     pub fn emit_comments_before_line(&mut self, target_line: u32) {
         while self.next_comment_idx < self.comments.len() {
@@ -1,26 +1,102 @@
 //! Finds conditions, and replaces them with variables
+//!
+//! Besides the conditions of when-clauses and if-equations, relations that
+//! make continuous-time expressions nondifferentiable are turned into
+//! conditions, so that a solver detects their zero crossings as events:
+//!
+//! - conditions of if-expressions, e.g. saturations like
+//!   `if u > uMax then uMax else u`
+//! - `min(a, b)` and `max(a, b)`, which are rewritten to
+//!   `if c then a else b` with the condition `a < b` or `a > b`
+//! - definitions of Boolean variables, e.g. `b = x > 0`, which become
+//!   `b = c`: the variable only changes at the events of its condition, so it
+//!   is a discrete-valued variable (see [`ConditionFinder::discrete`])
+//!
+//! A when-clause with an array of conditions, `when {a, b} then`, fires when
+//! any of them becomes true: it is split into a branch for each condition,
+//! as in `when a then ... elsewhen b then ...`, which fire at most once per
+//! event.
+//!
+//! Relations inside `noEvent()` or `smooth()`, inside when-clauses (which are
+//! only evaluated at events), or that depend only on parameters and constants
+//! do not generate events.
+//!
+//! Each condition variable is located at its condition in the source, and
+//! described by the construct it comes from (`when-clause`, `if-equation`,
+//! `if-expression`, `min()`, `max()` or `Boolean equation`), so that backends
+//! can report on them,
+//! e.g. which conditions a simulation never made true.
+use std::collections::HashSet;
+
 use indexmap::IndexMap;
 
 use crate::ir;
 use crate::ir::ast::{
-    Component, ComponentRefPart, ComponentReference, Equation, EquationBlock, Expression, Name,
-    Token,
+    ClassDefinition, Component, ComponentRefPart, ComponentReference, Equation, EquationBlock,
+    Expression, Name, OpBinary, Statement, Token, Variability,
+};
+use crate::ir::transform::constants::{
+    BUILTIN_MAX, BUILTIN_MIN, BUILTIN_NO_EVENT, BUILTIN_SMOOTH, BUILTIN_TIME, CONDITION_PREFIX,
+    TYPE_BOOL,
 };
-use crate::ir::transform::constants::{TYPE_BOOL, condition_name};
-use crate::ir::visitor::MutVisitor;
+use crate::ir::transform::fresh_names::FreshNames;
+use crate::ir::visitor::{MutVisitor, Visitable, Visitor};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct ConditionFinder {
     pub conditions: IndexMap<String, Component>,
     pub expressions: IndexMap<String, Expression>,
+    /// Boolean variables defined by a condition, e.g. `b = c0` for `b = x > 0`
+    pub discrete: HashSet<String>,
+    /// Continuous-time variables, whose relations generate events
+    continuous: HashSet<String>,
+    /// Boolean variables that aren't discrete, parameters or constants
+    booleans: HashSet<String>,
+    /// Depth of the `noEvent()` and `smooth()` calls being visited
+    no_event_depth: usize,
+    /// Depth of the when-equations and when-statements being visited
+    when_depth: usize,
+    /// Names of the condition variables, which avoid the names of the class
+    names: FreshNames,
 }
 
 impl ConditionFinder {
-    fn process_condition_block(&mut self, block: &mut EquationBlock) {
-        let i = self.conditions.len();
-        let name = condition_name(i);
+    /// Create a condition finder for a flattened class
+    pub fn new(class: &ClassDefinition) -> Self {
+        let continuous = class
+            .components
+            .iter()
+            .filter(|(_, comp)| matches!(comp.variability, Variability::Empty))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let booleans = class
+            .components
+            .iter()
+            .filter(|(_, comp)| {
+                matches!(comp.variability, Variability::Empty)
+                    && matches!(comp.type_name.to_string().as_str(), "Boolean" | TYPE_BOOL)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        Self {
+            continuous,
+            booleans,
+            names: FreshNames::new(class),
+            ..Default::default()
+        }
+    }
+
+    /// Add a condition variable for an expression of a construct (e.g.
+    /// `when-clause`), returning a reference to it
+    fn add_condition(&mut self, cond: Expression, kind: &str) -> Expression {
+        let name = self.names.fresh(CONDITION_PREFIX);
         let comp = Component {
             name: name.clone(),
+            description: vec![Token {
+                text: kind.to_string(),
+                ..Default::default()
+            }],
+            location: cond.get_location().cloned().unwrap_or_default(),
             type_name: Name {
                 name: vec![Token {
                     text: TYPE_BOOL.to_string(),
@@ -37,8 +113,8 @@ impl ConditionFinder {
             ..Default::default()
         };
         self.conditions.insert(name.clone(), comp.clone());
-        self.expressions.insert(name.clone(), block.cond.clone());
-        block.cond = Expression::ComponentReference(ComponentReference {
+        self.expressions.insert(name.clone(), cond);
+        Expression::ComponentReference(ComponentReference {
             local: false,
             parts: vec![ComponentRefPart {
                 ident: Token {
@@ -47,27 +123,192 @@ impl ConditionFinder {
                 },
                 subs: None,
             }],
-        });
+        })
+    }
+
+    fn process_condition_block(&mut self, block: &mut EquationBlock, kind: &str) {
+        block.cond = self.add_condition(block.cond.clone(), kind);
+    }
+
+    /// Whether a condition has a relation whose zero crossing is an event
+    fn generates_event(&self, cond: &Expression) -> bool {
+        let mut scanner = RelationScanner {
+            continuous: &self.continuous,
+            no_event_depth: 0,
+            found: false,
+        };
+        cond.accept(&mut scanner);
+        scanner.found
     }
 }
 
 impl MutVisitor for ConditionFinder {
+    fn enter_equation(&mut self, node: &mut Equation) {
+        if matches!(node, Equation::When { .. }) {
+            self.when_depth += 1;
+        }
+    }
+
     fn exit_equation(&mut self, node: &mut Equation) {
         match node {
-            Equation::When(blocks) => {
+            Equation::When { blocks, .. } => {
+                self.when_depth -= 1;
+                *blocks = std::mem::take(blocks)
+                    .into_iter()
+                    .flat_map(split_array_condition)
+                    .collect();
                 for block in blocks.iter_mut() {
-                    self.process_condition_block(block);
+                    self.process_condition_block(block, "when-clause");
                 }
             }
-            ir::ast::Equation::If {
-                cond_blocks,
-                else_block: _,
-            } => {
+            ir::ast::Equation::If { cond_blocks, .. } => {
                 for block in cond_blocks.iter_mut() {
-                    self.process_condition_block(block);
+                    self.process_condition_block(block, "if-equation");
+                }
+            }
+            Equation::Simple {
+                lhs: Expression::ComponentReference(var),
+                rhs,
+                ..
+            } if self.when_depth == 0 => {
+                let name = var.to_string();
+                let base = name.split('[').next().unwrap_or_default();
+                if (self.booleans.contains(&name) || self.booleans.contains(base))
+                    && self.generates_event(rhs)
+                {
+                    *rhs = self.add_condition(rhs.clone(), "Boolean equation");
+                    self.discrete.insert(name);
                 }
             }
             _ => {}
         }
     }
+
+    fn enter_statement(&mut self, node: &mut Statement) {
+        if matches!(node, Statement::When(_)) {
+            self.when_depth += 1;
+        }
+    }
+
+    fn exit_statement(&mut self, node: &mut Statement) {
+        if matches!(node, Statement::When(_)) {
+            self.when_depth -= 1;
+        }
+    }
+
+    fn enter_expression(&mut self, node: &mut Expression) {
+        if is_event_free_call(node) {
+            self.no_event_depth += 1;
+        }
+    }
+
+    fn exit_expression(&mut self, node: &mut Expression) {
+        if is_event_free_call(node) {
+            self.no_event_depth -= 1;
+            return;
+        }
+        if self.no_event_depth > 0 || self.when_depth > 0 {
+            return;
+        }
+        match node {
+            Expression::If { branches, .. } => {
+                for (cond, _) in branches.iter_mut() {
+                    if self.generates_event(cond) {
+                        *cond = self.add_condition(cond.clone(), "if-expression");
+                    }
+                }
+            }
+            Expression::FunctionCall { comp, args } if args.len() == 2 => {
+                let (op, kind) = match comp.to_string().as_str() {
+                    BUILTIN_MIN => (OpBinary::Lt(Token::default()), "min()"),
+                    BUILTIN_MAX => (OpBinary::Gt(Token::default()), "max()"),
+                    _ => return,
+                };
+                let relation = Expression::Binary {
+                    op,
+                    lhs: Box::new(args[0].clone()),
+                    rhs: Box::new(args[1].clone()),
+                };
+                if self.generates_event(&relation) {
+                    *node = Expression::If {
+                        branches: vec![(self.add_condition(relation, kind), args[0].clone())],
+                        else_branch: Box::new(args[1].clone()),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Split a when-branch with an array of conditions into a branch for each
+/// condition, with the same equations
+fn split_array_condition(block: EquationBlock) -> Vec<EquationBlock> {
+    match block.cond {
+        Expression::Array { elements } if !elements.is_empty() => elements
+            .into_iter()
+            .map(|cond| EquationBlock {
+                cond,
+                eqs: block.eqs.clone(),
+            })
+            .collect(),
+        _ => vec![block],
+    }
+}
+
+/// Whether an expression is a `noEvent()` or `smooth()` call, which suppress events
+fn is_event_free_call(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::FunctionCall { comp, .. }
+            if matches!(comp.to_string().as_str(), BUILTIN_NO_EVENT | BUILTIN_SMOOTH)
+    )
+}
+
+/// Looks for a relation on continuous-time variables outside of `noEvent()`
+struct RelationScanner<'a> {
+    continuous: &'a HashSet<String>,
+    no_event_depth: usize,
+    found: bool,
+}
+
+impl RelationScanner<'_> {
+    fn is_continuous(&self, expr: &Expression) -> bool {
+        struct ContinuousFinder<'a>(&'a HashSet<String>, bool);
+        impl Visitor for ContinuousFinder<'_> {
+            fn enter_expression(&mut self, node: &Expression) {
+                if let Expression::ComponentReference(cref) = node {
+                    let name = cref.to_string();
+                    let base = name.split('[').next().unwrap_or_default();
+                    self.1 |=
+                        name == BUILTIN_TIME || self.0.contains(&name) || self.0.contains(base);
+                }
+            }
+        }
+        let mut finder = ContinuousFinder(self.continuous, false);
+        expr.accept(&mut finder);
+        finder.1
+    }
+}
+
+impl Visitor for RelationScanner<'_> {
+    fn enter_expression(&mut self, node: &Expression) {
+        if is_event_free_call(node) {
+            self.no_event_depth += 1;
+        } else if self.no_event_depth == 0
+            && let Expression::Binary {
+                op: OpBinary::Lt(_) | OpBinary::Le(_) | OpBinary::Gt(_) | OpBinary::Ge(_),
+                ..
+            } = node
+            && self.is_continuous(node)
+        {
+            self.found = true;
+        }
+    }
+
+    fn exit_expression(&mut self, node: &Expression) {
+        if is_event_free_call(node) {
+            self.no_event_depth -= 1;
+        }
+    }
 }
@@ -0,0 +1,309 @@
+//! Inter-package dependency graph
+//!
+//! This module extracts the dependency graph between top-level packages from
+//! the `import` and `extends` clauses of a set of parsed files. It is used to:
+//!
+//! - Order multi-file compilation so that packages are merged after the
+//!   packages they depend on
+//! - Detect dependency cycles between packages
+//! - Export the graph for inspection (DOT or JSON, see `rumoca --emit depgraph`)
+//!
+//! A file belongs to the package named by the first segment of its `within`
+//! clause, or to its top-level class when it has none. Only references whose
+//! first segment names another known package produce an edge, so references to
+//! nested classes and to packages outside the workspace are ignored.
+
+use crate::ir::ast::{ClassDefinition, StoredDefinition};
+use anyhow::Result;
+use indexmap::{IndexMap, IndexSet};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Dependency graph between top-level packages
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DependencyGraph {
+    /// Package names in the order they were first seen
+    pub packages: IndexSet<String>,
+    /// Map from package to the packages it depends on
+    pub dependencies: IndexMap<String, IndexSet<String>>,
+}
+
+impl DependencyGraph {
+    /// Build the dependency graph from a list of (file_path, StoredDefinition) tuples
+    pub fn from_definitions(definitions: &[(String, StoredDefinition)]) -> Self {
+        let mut graph = DependencyGraph::default();
+        for (_, def) in definitions {
+            for package in definition_packages(def) {
+                graph.add_package(&package);
+            }
+        }
+
+        for (_, def) in definitions {
+            let packages = definition_packages(def);
+            let mut references = Vec::new();
+            for class in def.class_list.values() {
+                collect_references(class, &mut references);
+            }
+            for package in &packages {
+                for reference in &references {
+                    if reference != package && graph.packages.contains(reference) {
+                        graph.add_dependency(package, reference);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Add a package node (no-op if it already exists)
+    pub fn add_package(&mut self, package: &str) {
+        self.packages.insert(package.to_string());
+        self.dependencies.entry(package.to_string()).or_default();
+    }
+
+    /// Record that `package` depends on `dependency`
+    pub fn add_dependency(&mut self, package: &str, dependency: &str) {
+        self.add_package(package);
+        self.add_package(dependency);
+        self.dependencies
+            .get_mut(package)
+            .unwrap()
+            .insert(dependency.to_string());
+    }
+
+    /// Strongly connected components, dependencies first.
+    ///
+    /// Uses Tarjan's algorithm, which emits a component only after every
+    /// component reachable from it, i.e. after all of its dependencies.
+    pub fn components(&self) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: IndexMap::new(),
+            low_link: IndexMap::new(),
+            stack: Vec::new(),
+            on_stack: IndexSet::new(),
+            components: Vec::new(),
+        };
+        for package in &self.packages {
+            if !tarjan.index.contains_key(package) {
+                tarjan.visit(package);
+            }
+        }
+        tarjan.components
+    }
+
+    /// Groups of packages that depend on each other cyclically
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        self.components()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect()
+    }
+
+    /// Packages in build order (dependencies before dependents).
+    ///
+    /// Returns an error naming the packages involved if the graph has a cycle.
+    pub fn topological_order(&self) -> Result<Vec<String>> {
+        if let Some(cycle) = self.cycles().first() {
+            anyhow::bail!("Cyclic dependency between packages: {}", cycle.join(" -> "));
+        }
+        Ok(self.components().into_iter().flatten().collect())
+    }
+
+    /// Reorder definitions so that files of a package come after the files of
+    /// the packages it depends on.
+    ///
+    /// The sort is stable: files of the same package, and of packages that are
+    /// part of a cycle, keep their relative input order.
+    pub fn order_definitions(
+        &self,
+        definitions: Vec<(String, StoredDefinition)>,
+    ) -> Vec<(String, StoredDefinition)> {
+        let rank: IndexMap<String, usize> = self
+            .components()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, component)| component.into_iter().map(move |p| (p, i)))
+            .collect();
+
+        let mut ranked: Vec<_> = definitions
+            .into_iter()
+            .map(|(path, def)| {
+                let file_rank = definition_packages(&def)
+                    .iter()
+                    .filter_map(|p| rank.get(p).copied())
+                    .max()
+                    .unwrap_or(0);
+                (file_rank, path, def)
+            })
+            .collect();
+        ranked.sort_by_key(|(file_rank, _, _)| *file_rank);
+        ranked
+            .into_iter()
+            .map(|(_, path, def)| (path, def))
+            .collect()
+    }
+
+    /// Render the graph in Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+        for package in &self.packages {
+            let _ = writeln!(dot, "    \"{}\";", package);
+        }
+        for (package, dependencies) in &self.dependencies {
+            for dependency in dependencies {
+                let _ = writeln!(dot, "    \"{}\" -> \"{}\";", package, dependency);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as JSON, including detected cycles and the build order
+    pub fn to_json(&self) -> Result<String> {
+        let order: Vec<String> = self.components().into_iter().flatten().collect();
+        let value = serde_json::json!({
+            "packages": self.packages,
+            "dependencies": self.dependencies,
+            "cycles": self.cycles(),
+            "order": order,
+        });
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+}
+
+/// Top-level packages a file contributes to
+fn definition_packages(def: &StoredDefinition) -> Vec<String> {
+    match def.within.as_ref().and_then(|w| w.name.first()) {
+        Some(root) => vec![root.text.clone()],
+        None => def.class_list.keys().cloned().collect(),
+    }
+}
+
+/// Collect the root segment of every import and extends clause in a class tree
+fn collect_references(class: &ClassDefinition, references: &mut Vec<String>) {
+    let roots = class
+        .imports
+        .iter()
+        .filter_map(|import| import.base_path().name.first())
+        .chain(class.extends.iter().filter_map(|ext| ext.comp.name.first()));
+    for root in roots {
+        if !references.contains(&root.text) {
+            references.push(root.text.clone());
+        }
+    }
+    for nested in class.classes.values() {
+        collect_references(nested, references);
+    }
+}
+
+/// State for Tarjan's strongly connected components algorithm
+struct Tarjan<'a> {
+    graph: &'a DependencyGraph,
+    index: IndexMap<String, usize>,
+    low_link: IndexMap<String, usize>,
+    stack: Vec<String>,
+    on_stack: IndexSet<String>,
+    components: Vec<Vec<String>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, package: &str) {
+        let index = self.index.len();
+        self.index.insert(package.to_string(), index);
+        self.low_link.insert(package.to_string(), index);
+        self.stack.push(package.to_string());
+        self.on_stack.insert(package.to_string());
+
+        let graph = self.graph;
+        for dependency in graph.dependencies.get(package).into_iter().flatten() {
+            if !self.index.contains_key(dependency) {
+                self.visit(dependency);
+                let low = self.low_link[package].min(self.low_link[dependency]);
+                self.low_link.insert(package.to_string(), low);
+            } else if self.on_stack.contains(dependency) {
+                let low = self.low_link[package].min(self.index[dependency]);
+                self.low_link.insert(package.to_string(), low);
+            }
+        }
+
+        if self.low_link[package] == self.index[package] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.swap_remove(&member);
+                let done = member == package;
+                component.push(member);
+                if done {
+                    break;
+                }
+            }
+            // Keep the packages of a cycle in the order they were first seen
+            component.sort_by_key(|p| self.graph.packages.get_index_of(p));
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source;
+
+    fn definitions(sources: &[(&str, &str)]) -> Vec<(String, StoredDefinition)> {
+        sources
+            .iter()
+            .map(|(name, source)| (name.to_string(), parse_source(source, name).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let defs = definitions(&[
+            (
+                "App.mo",
+                "package App\n  model M\n    extends Lib.Base;\n  end M;\nend App;",
+            ),
+            (
+                "Lib.mo",
+                "package Lib\n  import Units.*;\n  model Base\n    Real x;\n  end Base;\nend Lib;",
+            ),
+            ("Units.mo", "package Units\n  type Time = Real;\nend Units;"),
+        ]);
+        let graph = DependencyGraph::from_definitions(&defs);
+
+        assert!(graph.dependencies["App"].contains("Lib"));
+        assert!(graph.dependencies["Lib"].contains("Units"));
+        assert!(graph.cycles().is_empty());
+        assert_eq!(graph.topological_order().unwrap(), ["Units", "Lib", "App"]);
+
+        let ordered: Vec<String> = graph
+            .order_definitions(defs)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(ordered, ["Units.mo", "Lib.mo", "App.mo"]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"App\" -> \"Lib\";"), "{}", dot);
+    }
+
+    #[test]
+    fn test_dependency_cycle() {
+        let defs = definitions(&[
+            (
+                "A.mo",
+                "package A\n  model M\n    extends B.N;\n  end M;\nend A;",
+            ),
+            (
+                "B.mo",
+                "package B\n  import A.M;\n  model N\n    Real y;\n  end N;\nend B;",
+            ),
+            ("C.mo", "within A;\nmodel P\n  extends A.M;\nend P;"),
+        ]);
+        let graph = DependencyGraph::from_definitions(&defs);
+
+        assert_eq!(graph.cycles(), vec![vec!["A".to_string(), "B".to_string()]]);
+        let err = graph.topological_order().unwrap_err();
+        assert!(err.to_string().contains("A -> B"), "{}", err);
+    }
+}
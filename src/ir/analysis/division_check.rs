@@ -0,0 +1,479 @@
+//! Division-by-zero analysis.
+//!
+//! Flags divisions whose denominator is zero at the initial point:
+//!
+//! - a parameter or constant whose default value is 0 (`parameter Real R = 0;`)
+//! - an expression that evaluates to 0 from parameter defaults and state start
+//!   values, or that is structurally zero (`0 * x`, `x - x`)
+//!
+//! Divisions inside if-expressions, if-equations and if-statements are assumed
+//! to be guarded by their condition and are not flagged.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::ast::{
+    ClassDefinition, Equation, Expression, Location, OpBinary, OpUnary, Statement, TerminalType,
+    Variability,
+};
+use crate::ir::literal::parse_real;
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// A division whose denominator is zero at the initial point
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZeroDivision {
+    /// The denominator, as written
+    pub denominator: String,
+    /// Why the denominator is zero
+    pub reason: String,
+    /// Location of the division operator
+    pub location: Location,
+}
+
+impl ZeroDivision {
+    /// Diagnostic message for this division
+    pub fn message(&self) -> String {
+        format!("possible division by zero: {}", self.reason)
+    }
+}
+
+/// Find divisions by zero in the equations and algorithms of a class.
+///
+/// Nested classes are not analyzed (callers recurse as needed).
+pub fn find_zero_divisions(class: &ClassDefinition) -> Vec<ZeroDivision> {
+    let values = initial_values(class);
+    unguarded_divisions(class)
+        .into_iter()
+        .filter_map(|division| {
+            let reason = zero_reason(&division.denominator, class, &values)?;
+            Some(ZeroDivision {
+                denominator: division.denominator.to_string(),
+                reason,
+                location: division.location,
+            })
+        })
+        .collect()
+}
+
+/// Values at the initial point: parameter and constant defaults, and state start values
+///
+/// Parameters bound by an initial equation of a flattened class (e.g. from
+/// the modification `R1(R = 2 * R0)`) take the value of the binding.
+pub fn initial_values(class: &ClassDefinition) -> HashMap<String, f64> {
+    let mut states = HashSet::new();
+    collect_states(&class.equations, &mut states);
+
+    let bindings: HashMap<String, &Expression> = class
+        .initial_equations
+        .iter()
+        .filter_map(|eq| match eq {
+            Equation::Simple {
+                lhs: Expression::ComponentReference(lhs),
+                rhs,
+                ..
+            } => Some((lhs.to_string(), rhs)),
+            _ => None,
+        })
+        .collect();
+
+    let mut values = HashMap::new();
+    // Defaults may refer to other parameters, so evaluate until nothing changes
+    loop {
+        let mut changed = false;
+        for (name, comp) in &class.components {
+            if values.contains_key(name) {
+                continue;
+            }
+            let value = match comp.variability {
+                Variability::Parameter(_) | Variability::Constant(_) => match comp.start {
+                    Expression::Empty => bindings
+                        .get(name)
+                        .and_then(|binding| evaluate(binding, &values)),
+                    _ => evaluate(&comp.start, &values),
+                },
+                // Real states start at 0 unless a start value is given
+                _ if states.contains(name) => match comp.start {
+                    Expression::Empty => Some(0.0),
+                    _ => evaluate(&comp.start, &values),
+                },
+                _ => None,
+            };
+            if let Some(value) = value {
+                values.insert(name.clone(), value);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    values
+}
+
+/// Collect the names of variables that appear differentiated
+fn collect_states(equations: &[Equation], states: &mut HashSet<String>) {
+    struct DerFinder<'a>(&'a mut HashSet<String>);
+    impl Visitor for DerFinder<'_> {
+        fn enter_expression(&mut self, node: &Expression) {
+            if let Expression::FunctionCall { comp, args } = node
+                && comp.to_string() == "der"
+                && let Some(Expression::ComponentReference(cref)) = args.first()
+            {
+                self.0.insert(cref.to_string());
+            }
+        }
+    }
+    let mut finder = DerFinder(states);
+    for eq in equations {
+        eq.accept(&mut finder);
+    }
+}
+
+/// Evaluate an expression numerically, if all its variables have known values
+pub fn evaluate(expr: &Expression, values: &HashMap<String, f64>) -> Option<f64> {
+    match expr {
+        Expression::Terminal {
+            terminal_type: TerminalType::UnsignedInteger | TerminalType::UnsignedReal,
+            token,
+        } => parse_real(&token.text),
+        Expression::ComponentReference(cref) => values.get(&cref.to_string()).copied(),
+        Expression::Parenthesized { inner } => evaluate(inner, values),
+        Expression::Unary { op, rhs } => {
+            let value = evaluate(rhs, values)?;
+            match op {
+                OpUnary::Minus(_) | OpUnary::DotMinus(_) => Some(-value),
+                OpUnary::Plus(_) | OpUnary::DotPlus(_) => Some(value),
+                _ => None,
+            }
+        }
+        Expression::Binary { op, lhs, rhs } => {
+            let (lhs, rhs) = (evaluate(lhs, values)?, evaluate(rhs, values)?);
+            match op {
+                OpBinary::Add(_) | OpBinary::AddElem(_) => Some(lhs + rhs),
+                OpBinary::Sub(_) | OpBinary::SubElem(_) => Some(lhs - rhs),
+                OpBinary::Mul(_) | OpBinary::MulElem(_) => Some(lhs * rhs),
+                OpBinary::Div(_) | OpBinary::DivElem(_) if rhs != 0.0 => Some(lhs / rhs),
+                OpBinary::Exp(_) => Some(lhs.powf(rhs)),
+                _ => None,
+            }
+        }
+        Expression::FunctionCall { comp, args } => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(arg, values))
+                .collect::<Option<Vec<_>>>()?;
+            let name = comp.to_string();
+            let name = name.strip_prefix("Modelica.Math.").unwrap_or(&name);
+            evaluate_function(name, &args)
+        }
+        Expression::If {
+            branches,
+            else_branch,
+        } => {
+            for (cond, value) in branches {
+                if evaluate_condition(cond, values)? {
+                    return evaluate(value, values);
+                }
+            }
+            evaluate(else_branch, values)
+        }
+        _ => None,
+    }
+}
+
+/// Evaluate a Boolean expression, where variables are true if nonzero
+pub fn evaluate_condition(expr: &Expression, values: &HashMap<String, f64>) -> Option<bool> {
+    match expr {
+        Expression::Terminal {
+            terminal_type: TerminalType::Bool,
+            token,
+        } => token.text.parse().ok(),
+        Expression::Parenthesized { inner } => evaluate_condition(inner, values),
+        Expression::Unary {
+            op: OpUnary::Not(_),
+            rhs,
+        } => Some(!evaluate_condition(rhs, values)?),
+        Expression::Binary {
+            op: op @ (OpBinary::And(_) | OpBinary::Or(_)),
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = (
+                evaluate_condition(lhs, values)?,
+                evaluate_condition(rhs, values)?,
+            );
+            Some(match op {
+                OpBinary::And(_) => lhs && rhs,
+                _ => lhs || rhs,
+            })
+        }
+        Expression::Binary { op, lhs, rhs } => {
+            let (lhs, rhs) = (evaluate(lhs, values)?, evaluate(rhs, values)?);
+            match op {
+                OpBinary::Lt(_) => Some(lhs < rhs),
+                OpBinary::Le(_) => Some(lhs <= rhs),
+                OpBinary::Gt(_) => Some(lhs > rhs),
+                OpBinary::Ge(_) => Some(lhs >= rhs),
+                OpBinary::Eq(_) => Some(lhs == rhs),
+                OpBinary::Neq(_) => Some(lhs != rhs),
+                _ => None,
+            }
+        }
+        Expression::FunctionCall { comp, args } if comp.to_string() == "noEvent" => {
+            evaluate_condition(args.first()?, values)
+        }
+        _ => Some(evaluate(expr, values)? != 0.0),
+    }
+}
+
+/// Evaluate a call of a built-in math function
+fn evaluate_function(name: &str, args: &[f64]) -> Option<f64> {
+    let value = match (name, args) {
+        ("abs", [x]) => x.abs(),
+        ("sign", [x]) => match x.partial_cmp(&0.0)? {
+            std::cmp::Ordering::Greater => 1.0,
+            std::cmp::Ordering::Less => -1.0,
+            std::cmp::Ordering::Equal => 0.0,
+        },
+        ("sqrt", [x]) if *x >= 0.0 => x.sqrt(),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("atan2", [y, x]) => y.atan2(*x),
+        ("sinh", [x]) => x.sinh(),
+        ("cosh", [x]) => x.cosh(),
+        ("tanh", [x]) => x.tanh(),
+        ("exp", [x]) => x.exp(),
+        ("log", [x]) if *x > 0.0 => x.ln(),
+        ("log10", [x]) if *x > 0.0 => x.log10(),
+        ("floor" | "integer", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        ("min", [x, y]) => x.min(*y),
+        ("noEvent", [x]) | ("smooth", [_, x]) => *x,
+        ("max", [x, y]) => x.max(*y),
+        _ => return None,
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Why an expression is zero at the initial point, if it is
+fn zero_reason(
+    expr: &Expression,
+    class: &ClassDefinition,
+    values: &HashMap<String, f64>,
+) -> Option<String> {
+    if !is_structurally_zero(expr, values) {
+        return None;
+    }
+    if let Expression::ComponentReference(cref) = expr
+        && let Some(comp) = class.components.get(&cref.to_string())
+    {
+        return Some(match comp.variability {
+            Variability::Parameter(_) => format!("parameter '{}' defaults to 0", cref),
+            Variability::Constant(_) => format!("constant '{}' is 0", cref),
+            _ => format!("'{}' starts at 0", cref),
+        });
+    }
+    Some(format!("'{}' is 0 at the initial point", expr))
+}
+
+fn is_structurally_zero(expr: &Expression, values: &HashMap<String, f64>) -> bool {
+    if evaluate(expr, values) == Some(0.0) {
+        return true;
+    }
+    match expr {
+        Expression::Parenthesized { inner } => is_structurally_zero(inner, values),
+        Expression::Unary { rhs, .. } => is_structurally_zero(rhs, values),
+        Expression::Binary {
+            op: OpBinary::Mul(_) | OpBinary::MulElem(_),
+            lhs,
+            rhs,
+        } => is_structurally_zero(lhs, values) || is_structurally_zero(rhs, values),
+        Expression::Binary {
+            op: OpBinary::Sub(_) | OpBinary::SubElem(_),
+            lhs,
+            rhs,
+        } => lhs.to_string() == rhs.to_string(),
+        _ => false,
+    }
+}
+
+/// A division outside of any if-expression, if-equation or if-statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Division {
+    pub denominator: Expression,
+    /// Location of the division operator
+    pub location: Location,
+}
+
+/// Collect the divisions of a node that aren't guarded by an if.
+///
+/// For a class, only its own equations, algorithms and bindings are searched,
+/// not those of nested classes.
+pub fn unguarded_divisions<T: Visitable>(node: &T) -> Vec<Division> {
+    let mut finder = DivisionFinder::default();
+    node.accept(&mut finder);
+    finder.divisions
+}
+
+#[derive(Default)]
+struct DivisionFinder {
+    /// Class nesting depth (only the outermost class is searched)
+    depth: usize,
+    /// Number of enclosing if-expressions/equations/statements
+    guarded: usize,
+    divisions: Vec<Division>,
+}
+
+impl Visitor for DivisionFinder {
+    fn enter_class_definition(&mut self, _node: &ClassDefinition) {
+        self.depth += 1;
+    }
+
+    fn exit_class_definition(&mut self, _node: &ClassDefinition) {
+        self.depth -= 1;
+    }
+
+    fn enter_equation(&mut self, node: &Equation) {
+        if matches!(node, Equation::If { .. }) {
+            self.guarded += 1;
+        }
+    }
+
+    fn exit_equation(&mut self, node: &Equation) {
+        if matches!(node, Equation::If { .. }) {
+            self.guarded -= 1;
+        }
+    }
+
+    fn enter_statement(&mut self, node: &Statement) {
+        if matches!(node, Statement::If { .. }) {
+            self.guarded += 1;
+        }
+    }
+
+    fn exit_statement(&mut self, node: &Statement) {
+        if matches!(node, Statement::If { .. }) {
+            self.guarded -= 1;
+        }
+    }
+
+    fn enter_expression(&mut self, node: &Expression) {
+        if matches!(node, Expression::If { .. }) {
+            self.guarded += 1;
+        }
+        if self.depth > 1 || self.guarded > 0 {
+            return;
+        }
+        if let Expression::Binary {
+            op: OpBinary::Div(token) | OpBinary::DivElem(token),
+            rhs,
+            ..
+        } = node
+        {
+            self.divisions.push(Division {
+                denominator: (**rhs).clone(),
+                location: token.location.clone(),
+            });
+        }
+    }
+
+    fn exit_expression(&mut self, node: &Expression) {
+        if matches!(node, Expression::If { .. }) {
+            self.guarded -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    fn divisions(source: &str) -> Vec<ZeroDivision> {
+        let def = parse_source_simple(source, "test.mo").unwrap();
+        find_zero_divisions(def.class_list.values().next().unwrap())
+    }
+
+    #[test]
+    fn test_parameter_default_zero() {
+        let found = divisions(
+            r#"
+            model M
+              parameter Real R = 0;
+              parameter Real C = 2 * R;
+              parameter Real L = 1;
+              Real i, v;
+            equation
+              i = v / R;
+              v = i / C;
+              der(i) = v / L;
+            end M;
+            "#,
+        );
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].denominator, "R");
+        assert_eq!(found[0].reason, "parameter 'R' defaults to 0");
+        assert_eq!(found[0].location.start_line, 8);
+        assert_eq!(found[1].denominator, "C");
+    }
+
+    #[test]
+    fn test_state_start_and_structural_zero() {
+        let found = divisions(
+            r#"
+            model M
+              Real x;
+              Real y(start = 1);
+              Real z;
+              Real w;
+            equation
+              der(x) = 1;
+              der(y) = 1 / y;
+              z = 1 / x;
+              w = 1 / (z - z);
+            end M;
+            "#,
+        );
+        let denominators: Vec<_> = found.iter().map(|d| d.denominator.as_str()).collect();
+        assert_eq!(denominators, vec!["x", "(z - z)"]);
+    }
+
+    #[test]
+    fn test_guarded_division_not_flagged() {
+        let found = divisions(
+            r#"
+            model M
+              parameter Real R = 0;
+              Real i;
+            equation
+              i = if R > 0 then 1 / R else 0;
+            end M;
+            "#,
+        );
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_initial_values_functions_and_bindings() {
+        let source = r#"
+            package P
+              model Sub
+                parameter Real R = 1;
+              end Sub;
+              model M
+                parameter Real R0 = 4;
+                parameter Real a = sqrt(R0) + max(1, abs(-3));
+                Sub sub(R = 2 * R0);
+              end M;
+            end P;
+            "#;
+        let def = parse_source_simple(source, "test.mo").unwrap();
+        let class = crate::ir::transform::flatten::flatten(&def, Some("P.M")).unwrap();
+        let values = initial_values(&class);
+        assert_eq!(values["a"], 5.0);
+        assert_eq!(values["sub.R"], 8.0);
+    }
+}
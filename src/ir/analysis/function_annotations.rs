@@ -0,0 +1,313 @@
+//! Code generation hints from function annotations.
+//!
+//! Recognizes the standard function annotations (Modelica spec §12.7, §18.3):
+//!
+//! - `Inline` / `LateInline`: whether calls to the function should be inlined
+//! - `smoothOrder`: the function is continuously differentiable, so relations in
+//!   its inlined body do not generate events
+//! - `derivative`: a user-supplied derivative function, used by the symbolic
+//!   differentiator instead of wrapping the call in `der()`
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+
+use crate::ir::ast::{Causality, ClassDefinition, ClassType, Expression, OpBinary, TerminalType};
+use crate::ir::transform::function_inliner::bind_arguments;
+
+/// A `derivative` annotation, e.g. `derivative(noDerivative=n, order=1)=f_der`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DerivativeAnnotation {
+    /// Name of the derivative function, as written
+    pub function: String,
+    /// Order of the derivative (defaults to 1)
+    pub order: usize,
+    /// Inputs whose derivative is not passed to the derivative function
+    pub no_derivative: Vec<String>,
+    /// Inputs that must have a zero derivative for this rule to apply
+    pub zero_derivative: Vec<String>,
+}
+
+/// Code generation hints of a function
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionAnnotations {
+    /// `Inline` or `LateInline`, if given
+    pub inline: Option<bool>,
+    /// `smoothOrder`, if given
+    pub smooth_order: Option<usize>,
+    /// All `derivative` annotations
+    pub derivatives: Vec<DerivativeAnnotation>,
+}
+
+impl FunctionAnnotations {
+    /// Extract the annotations of a function definition
+    pub fn from_class(class: &ClassDefinition) -> Self {
+        let mut annotations = Self::default();
+        for expr in &class.annotation {
+            let Expression::Binary {
+                op: OpBinary::Assign(_),
+                lhs,
+                rhs,
+            } = expr
+            else {
+                continue;
+            };
+            match &**lhs {
+                Expression::ComponentReference(comp) => match comp.to_string().as_str() {
+                    "Inline" | "LateInline" => {
+                        if let Some(value) = bool_value(rhs) {
+                            annotations.inline = Some(value);
+                        }
+                    }
+                    "smoothOrder" => annotations.smooth_order = integer_value(rhs),
+                    "derivative" => {
+                        if let Expression::ComponentReference(function) = &**rhs {
+                            annotations.derivatives.push(DerivativeAnnotation {
+                                function: function.to_string(),
+                                order: 1,
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    _ => {}
+                },
+                Expression::FunctionCall { comp, args } => match comp.to_string().as_str() {
+                    // smoothOrder(normallyConstant=...)=n
+                    "smoothOrder" => annotations.smooth_order = integer_value(rhs),
+                    "derivative" => {
+                        if let Expression::ComponentReference(function) = &**rhs {
+                            annotations
+                                .derivatives
+                                .push(derivative_annotation(function.to_string(), args));
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        annotations
+    }
+
+    /// The first-order derivative annotation, if any
+    pub fn first_derivative(&self) -> Option<&DerivativeAnnotation> {
+        self.derivatives.iter().find(|d| d.order == 1)
+    }
+}
+
+fn derivative_annotation(function: String, args: &[Expression]) -> DerivativeAnnotation {
+    let mut annotation = DerivativeAnnotation {
+        function,
+        order: 1,
+        ..Default::default()
+    };
+    for (name, value) in args.iter().flat_map(Expression::modification_pairs) {
+        match name.to_string().as_str() {
+            "order" => annotation.order = integer_value(&value).unwrap_or(1),
+            "noDerivative" => annotation.no_derivative.push(value.to_string()),
+            "zeroDerivative" => annotation.zero_derivative.push(value.to_string()),
+            _ => {}
+        }
+    }
+    annotation
+}
+
+fn bool_value(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Terminal {
+            terminal_type: TerminalType::Bool,
+            token,
+        } => Some(token.text == "true"),
+        _ => None,
+    }
+}
+
+fn integer_value(expr: &Expression) -> Option<usize> {
+    match expr {
+        Expression::Terminal {
+            terminal_type: TerminalType::UnsignedInteger,
+            token,
+        } => token.text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// How to differentiate calls to a function with a `derivative` annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivativeRule {
+    /// Name of the derivative function, as written in the annotation
+    pub function: String,
+    /// For each input (in declaration order), whether its derivative is passed
+    pub differentiated_inputs: Vec<bool>,
+    /// For each input (in declaration order), whether it must have a zero
+    /// derivative (`zeroDerivative`) for the rule to apply
+    pub zero_derivative_inputs: Vec<bool>,
+    /// The function with only its inputs, to match the arguments of calls
+    pub signature: ClassDefinition,
+}
+
+impl DerivativeRule {
+    /// Name to call the derivative function by, given the name the function was called by.
+    ///
+    /// An unqualified derivative name is resolved in the scope of the called function.
+    pub fn call_name(&self, called: &str) -> String {
+        match called.rsplit_once('.') {
+            Some((scope, _)) if !self.function.contains('.') => {
+                format!("{}.{}", scope, self.function)
+            }
+            _ => self.function.clone(),
+        }
+    }
+
+    /// The arguments of a call for all inputs in declaration order, matching
+    /// named arguments by name and filling in defaults, or None if the call
+    /// doesn't match the inputs
+    pub fn bind(&self, args: &[Expression]) -> Option<Vec<Expression>> {
+        bind_arguments(&self.signature, args).ok()
+    }
+}
+
+/// User-supplied first derivatives of the functions in a class tree
+#[derive(Debug, Clone, Default)]
+pub struct FunctionDerivatives {
+    rules: HashMap<String, DerivativeRule>,
+    /// Variables with a zero derivative, like parameters and constants
+    time_invariant: HashSet<String>,
+}
+
+impl FunctionDerivatives {
+    /// Collect the `derivative` annotations of all functions (including nested ones).
+    ///
+    /// Functions are registered under their full name and their short name, like the
+    /// function inliner does.
+    pub fn from_class_list(class_list: &IndexMap<String, ClassDefinition>) -> Self {
+        let mut derivatives = Self::default();
+        for class in class_list.values() {
+            derivatives.collect(class, "");
+        }
+        derivatives
+    }
+
+    fn collect(&mut self, class: &ClassDefinition, prefix: &str) {
+        let full_name = if prefix.is_empty() {
+            class.name.text.clone()
+        } else {
+            format!("{}.{}", prefix, class.name.text)
+        };
+
+        if class.class_type == ClassType::Function
+            && let Some(annotation) = FunctionAnnotations::from_class(class).first_derivative()
+        {
+            let inputs: IndexMap<_, _> = class
+                .components
+                .iter()
+                .filter(|(_, comp)| matches!(comp.causality, Causality::Input(_)))
+                .map(|(name, comp)| (name.clone(), comp.clone()))
+                .collect();
+            let differentiated_inputs = inputs
+                .values()
+                .map(|comp| {
+                    let type_name = comp.type_name.to_string();
+                    !matches!(type_name.as_str(), "Integer" | "Boolean" | "String")
+                        && !annotation.no_derivative.contains(&comp.name)
+                        && !annotation.zero_derivative.contains(&comp.name)
+                })
+                .collect();
+            let zero_derivative_inputs = inputs
+                .keys()
+                .map(|name| annotation.zero_derivative.contains(name))
+                .collect();
+            let rule = DerivativeRule {
+                function: annotation.function.clone(),
+                differentiated_inputs,
+                zero_derivative_inputs,
+                signature: ClassDefinition {
+                    name: class.name.clone(),
+                    class_type: ClassType::Function,
+                    components: inputs,
+                    ..Default::default()
+                },
+            };
+            self.rules.insert(class.name.text.clone(), rule.clone());
+            self.rules.insert(full_name.clone(), rule);
+        }
+
+        for nested in class.classes.values() {
+            self.collect(nested, &full_name);
+        }
+    }
+
+    /// The derivative rule for a function, by the name it is called with
+    pub fn get(&self, function: &str) -> Option<&DerivativeRule> {
+        self.rules.get(function)
+    }
+
+    /// Whether no derivative rules are known
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Declare variables with a zero derivative, like parameters and
+    /// constants, so that rules whose `zeroDerivative` inputs are given
+    /// those apply
+    pub fn with_time_invariant(mut self, variables: impl IntoIterator<Item = String>) -> Self {
+        self.time_invariant.extend(variables);
+        self
+    }
+
+    /// Whether a variable has a zero derivative, see [`Self::with_time_invariant`]
+    pub fn is_time_invariant(&self, variable: &str) -> bool {
+        self.time_invariant.contains(variable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    const SOURCE: &str = r#"
+        package P
+          function f
+            input Real x;
+            input Integer n;
+            input Real k;
+            output Real y;
+          algorithm
+            y := k * x^n;
+            annotation(Inline=false, smoothOrder=2,
+              derivative(noDerivative=k, order=1)=f_der,
+              derivative(order=2)=f_der2);
+          end f;
+        end P;
+    "#;
+
+    #[test]
+    fn test_function_annotations() {
+        let def = parse_source_simple(SOURCE, "test.mo").unwrap();
+        let f = &def.class_list["P"].classes["f"];
+        let annotations = FunctionAnnotations::from_class(f);
+
+        assert_eq!(annotations.inline, Some(false));
+        assert_eq!(annotations.smooth_order, Some(2));
+        assert_eq!(annotations.derivatives.len(), 2);
+        let first = annotations.first_derivative().unwrap();
+        assert_eq!(first.function, "f_der");
+        assert_eq!(first.no_derivative, vec!["k".to_string()]);
+        assert_eq!(annotations.derivatives[1].order, 2);
+    }
+
+    #[test]
+    fn test_function_derivative_rules() {
+        let def = parse_source_simple(SOURCE, "test.mo").unwrap();
+        let derivatives = FunctionDerivatives::from_class_list(&def.class_list);
+
+        let rule = derivatives.get("P.f").unwrap();
+        // Integer inputs and noDerivative inputs are not differentiated
+        assert_eq!(rule.differentiated_inputs, vec![true, false, false]);
+        assert_eq!(rule.zero_derivative_inputs, vec![false, false, false]);
+        assert_eq!(rule.call_name("P.f"), "P.f_der");
+        assert_eq!(rule.call_name("f"), "f_der");
+        assert!(derivatives.get("g").is_none());
+    }
+}
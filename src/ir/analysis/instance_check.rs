@@ -0,0 +1,537 @@
+//! Instance-level checks of a flattened class.
+//!
+//! - Parameters that nothing in the flattened system refers to: no equation,
+//!   algorithm, binding, modification, array dimension, condition or
+//!   annotation of a component
+//! - Connectors of sub-components that no connect equation refers to
+//! - Causality of signal connectors (`input`/`output` connectors like
+//!   `RealInput`): each connection set has at most one source (an output of a
+//!   sub-component, an input of the flattened class or a bound input), the
+//!   inputs of sub-components are connected to a source, and the inputs of
+//!   blocks are connected, bound or given by an equation. Outputs may fan out.
+//!
+//! Fields of record instances are not checked, since records are often only
+//! partly used. Connectors of the flattened class itself are its interface
+//! and are not checked either.
+
+use std::collections::HashSet;
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::ir::ast::{
+    Causality, ClassDefinition, ClassType, ComponentReference, Equation, Expression, Location,
+    Variability,
+};
+use crate::ir::transform::flatten::{ComponentInstance, FlattenResult};
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// A parameter of the flattened class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterUse {
+    /// Flattened name, e.g. `R1.R`
+    pub name: String,
+    /// Location of the parameter name in its declaration
+    pub location: Location,
+    /// Whether the flattened system refers to the parameter
+    pub used: bool,
+}
+
+/// A connector of a sub-component of the flattened class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectorUse {
+    /// Flattened name, e.g. `R1.p`
+    pub name: String,
+    /// The sub-component, e.g. `R1`
+    pub component: String,
+    /// Location of the sub-component name in its declaration
+    pub location: Location,
+    /// Whether a connect equation refers to the connector
+    pub connected: bool,
+}
+
+/// A signal connector violating the causality rules of connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct CausalityError {
+    /// Flattened name of the connector, e.g. `gain.u`
+    pub name: String,
+    /// The sub-component of the flattened class containing the connector,
+    /// e.g. `gain`, or the connector itself if it belongs to the flattened class
+    pub component: String,
+    /// Location of the component name in its declaration
+    pub location: Location,
+    pub message: String,
+}
+
+/// Parameters and sub-component connectors of a flattened class, whether
+/// they are used, and the causality errors of its signal connectors
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstanceCheck {
+    pub parameters: Vec<ParameterUse>,
+    pub connectors: Vec<ConnectorUse>,
+    pub causality: Vec<CausalityError>,
+}
+
+impl InstanceCheck {
+    /// Parameters nothing refers to
+    pub fn unused_parameters(&self) -> impl Iterator<Item = &ParameterUse> {
+        self.parameters.iter().filter(|p| !p.used)
+    }
+
+    /// Connectors no connect equation refers to
+    pub fn unconnected_connectors(&self) -> impl Iterator<Item = &ConnectorUse> {
+        self.connectors.iter().filter(|c| !c.connected)
+    }
+}
+
+/// Check the parameters and connectors of a flattened class
+pub fn check_instances(result: &FlattenResult) -> InstanceCheck {
+    let (class, instances) = (&result.class, &result.instances);
+    let is_parameter = |name: &str| {
+        class
+            .components
+            .get(name)
+            .is_some_and(|comp| matches!(comp.variability, Variability::Parameter(_)))
+    };
+
+    let mut references = References::default();
+    for (name, comp) in &class.components {
+        // Sub-component dimensions and conditions are not scope-renamed
+        references.scope = name.rsplit_once('.').map(|(scope, _)| scope.to_string());
+        comp.accept(&mut references);
+        for annotation in &comp.annotation {
+            annotation.accept(&mut references);
+        }
+    }
+    references.scope = None;
+    for eq in &class.equations {
+        eq.accept(&mut references);
+    }
+    for eq in &class.initial_equations {
+        // The left-hand side of a parameter binding is not a use
+        match eq {
+            Equation::Simple {
+                lhs: Expression::ComponentReference(lhs),
+                rhs,
+                ..
+            } if is_parameter(&lhs.unsubscripted_name()) => rhs.accept(&mut references),
+            _ => eq.accept(&mut references),
+        }
+    }
+    for stmt in class.algorithms.iter().chain(&class.initial_algorithms) {
+        for stmt in stmt {
+            stmt.accept(&mut references);
+        }
+    }
+
+    let in_record = |name: &str| {
+        ancestors(name).any(|parent| {
+            instances
+                .get(parent)
+                .is_some_and(|instance| matches!(instance.class_type, ClassType::Record))
+        })
+    };
+    let parameters = class
+        .components
+        .iter()
+        .filter(|(name, _)| is_parameter(name) && !in_record(name))
+        .map(|(name, comp)| ParameterUse {
+            name: name.clone(),
+            location: comp.name_token.location.clone(),
+            used: references.refers_to(name),
+        })
+        .collect();
+
+    let is_connector =
+        |instance: &ComponentInstance| matches!(instance.class_type, ClassType::Connector);
+    let connectors = instances
+        .iter()
+        .filter(|(_, instance)| is_connector(instance) && !instance.conditional)
+        .filter_map(|(name, instance)| {
+            let (component, _) = name.split_once('.')?;
+            let owner = instances.get(component)?;
+            (!name[component.len() + 1..].contains('.') && !is_connector(owner)).then(|| {
+                ConnectorUse {
+                    name: name.clone(),
+                    component: component.to_string(),
+                    location: owner.location.clone(),
+                    connected: instance.connected,
+                }
+            })
+        })
+        .collect();
+
+    InstanceCheck {
+        parameters,
+        connectors,
+        causality: check_causality(class, instances, &result.connects),
+    }
+}
+
+/// Role of a signal connector in a connection set
+#[derive(PartialEq)]
+enum Role {
+    /// Provides the value: an output of a sub-component, an input of the
+    /// flattened class, or a bound input of a sub-component
+    Source,
+    /// An unbound input of a sub-component
+    Sink,
+    /// An output of the flattened class, which may be given by an equation,
+    /// or a connector without causality
+    Other,
+}
+
+/// Check the causality of the signal connectors of a flattened class
+fn check_causality(
+    class: &ClassDefinition,
+    instances: &IndexMap<String, ComponentInstance>,
+    connects: &[(ComponentReference, ComponentReference)],
+) -> Vec<CausalityError> {
+    let is_bound = |name: &str| {
+        class.components.get(name).is_some_and(|comp| {
+            !comp.start_is_modification && !matches!(comp.start, Expression::Empty)
+        })
+    };
+    let error = |name: &str, message: String| {
+        let component = name
+            .split_once('.')
+            .map_or(name, |(component, _)| component);
+        CausalityError {
+            name: name.to_string(),
+            component: component.to_string(),
+            location: instances
+                .get(component)
+                .map(|instance| instance.location.clone())
+                .unwrap_or_default(),
+            message,
+        }
+    };
+
+    // Connection sets of the connectors connected as a whole
+    let mut sets: Vec<IndexSet<String>> = Vec::new();
+    for (lhs, rhs) in connects {
+        if [lhs, rhs]
+            .iter()
+            .any(|cref| cref.parts.iter().any(|part| part.subs.is_some()))
+        {
+            continue;
+        }
+        let (lhs, rhs) = (lhs.unsubscripted_name(), rhs.unsubscripted_name());
+        let position =
+            |name: &str, sets: &[IndexSet<String>]| sets.iter().position(|set| set.contains(name));
+        match (position(&lhs, &sets), position(&rhs, &sets)) {
+            (Some(a), Some(b)) if a != b => {
+                let merged = sets.remove(a.max(b));
+                sets[a.min(b)].extend(merged);
+            }
+            (Some(_), Some(_)) => {}
+            (Some(a), None) => {
+                sets[a].insert(rhs);
+            }
+            (None, Some(b)) => {
+                sets[b].insert(lhs);
+            }
+            (None, None) => sets.push(IndexSet::from([lhs, rhs])),
+        }
+    }
+
+    // Variables given by an equation, other than the equations of the
+    // expanded connections, which relate two connectors of a set
+    let same_set = |a: &str, b: &str| sets.iter().any(|set| set.contains(a) && set.contains(b));
+    let defined: HashSet<String> = class
+        .equations
+        .iter()
+        .filter_map(|eq| match eq {
+            Equation::Simple {
+                lhs: Expression::ComponentReference(lhs),
+                rhs,
+                ..
+            } => {
+                let lhs = lhs.unsubscripted_name();
+                match rhs {
+                    Expression::ComponentReference(rhs)
+                        if same_set(&lhs, &rhs.unsubscripted_name()) =>
+                    {
+                        None
+                    }
+                    _ => Some(lhs),
+                }
+            }
+            _ => None,
+        })
+        .collect();
+    let role = |name: &str| {
+        let inside = name.contains('.');
+        let given = is_bound(name) || defined.contains(name);
+        match class.components.get(name).map(|comp| &comp.causality) {
+            Some(Causality::Input(_)) if inside && !given => Role::Sink,
+            Some(Causality::Input(_) | Causality::Output(_)) if inside => Role::Source,
+            Some(Causality::Input(_)) => Role::Source,
+            _ => Role::Other,
+        }
+    };
+
+    let mut errors = Vec::new();
+    let quoted = |names: &[&String]| {
+        names
+            .iter()
+            .map(|name| format!("'{}'", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    for set in &sets {
+        let with_role = |wanted: Role| {
+            set.iter()
+                .filter(|name| role(name) == wanted)
+                .collect::<Vec<_>>()
+        };
+        let (sources, sinks) = (with_role(Role::Source), with_role(Role::Sink));
+        if sources.len() > 1 {
+            // Reported once per set, at an input receiving the values
+            errors.push(match sinks.first() {
+                Some(sink) => error(
+                    sink,
+                    format!(
+                        "Input '{}' is connected to more than one output: {}",
+                        sink,
+                        quoted(&sources)
+                    ),
+                ),
+                None => error(
+                    sources[1],
+                    format!(
+                        "'{}' is connected to another output: {}",
+                        sources[1],
+                        quoted(&sources[..1])
+                    ),
+                ),
+            });
+        } else if sources.is_empty() && with_role(Role::Other).is_empty() {
+            for sink in sinks {
+                errors.push(error(
+                    sink,
+                    format!("Input '{}' is not connected to an output", sink),
+                ));
+            }
+        }
+    }
+
+    // Inputs of blocks that are neither connected, bound nor given by an equation
+    let connected: HashSet<String> = connects
+        .iter()
+        .flat_map(|(lhs, rhs)| [lhs.unsubscripted_name(), rhs.unsubscripted_name()])
+        .collect();
+    for (name, comp) in &class.components {
+        let Some((owner, _)) = name.rsplit_once('.') else {
+            continue;
+        };
+        let in_block = instances.get(owner).is_some_and(|instance| {
+            matches!(instance.class_type, ClassType::Block) && !instance.conditional
+        });
+        if in_block
+            && matches!(comp.causality, Causality::Input(_))
+            && !connected.contains(name)
+            && !is_bound(name)
+            && !defined.contains(name)
+        {
+            errors.push(error(
+                name,
+                format!("Input '{}' is not connected and has no binding", name),
+            ));
+        }
+    }
+    errors
+}
+
+/// Enclosing components of a flattened name, innermost first
+fn ancestors(name: &str) -> impl Iterator<Item = &str> {
+    name.match_indices('.').rev().map(|(i, _)| &name[..i])
+}
+
+/// Names referred to, and their enclosing names
+#[derive(Default)]
+struct References {
+    names: HashSet<String>,
+    prefixes: HashSet<String>,
+    /// Scope of unrenamed references, also recorded within it
+    scope: Option<String>,
+}
+
+impl References {
+    fn insert(&mut self, name: String) {
+        for prefix in ancestors(&name) {
+            self.prefixes.insert(prefix.to_string());
+        }
+        self.names.insert(name);
+    }
+
+    /// Whether a component, a part of it or a component containing it is referred to
+    fn refers_to(&self, name: &str) -> bool {
+        self.names.contains(name)
+            || self.prefixes.contains(name)
+            || ancestors(name).any(|parent| self.names.contains(parent))
+    }
+}
+
+impl Visitor for References {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        let name = node.unsubscripted_name();
+        if let Some(scope) = &self.scope {
+            self.insert(format!("{}.{}", scope, name));
+        }
+        self.insert(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+    use crate::ir::transform::flatten::flatten_with_deps;
+
+    const SOURCE: &str = r#"
+package P
+  connector Pin
+    Real v;
+    flow Real i;
+  end Pin;
+  record Data
+    parameter Real a = 1;
+    parameter Real b = 2;
+  end Data;
+  model R
+    parameter Real r = 1;
+    parameter Real n_dim = 2;
+    parameter Real spare = 3;
+    parameter Data data;
+    Real w[n_dim];
+    Pin p, n;
+  equation
+    p.v - n.v = r * p.i;
+    p.i + n.i = 0;
+    w = fill(data.a, 2);
+  end R;
+  model C
+    parameter Real k = 2;
+    parameter Real gain = 1;
+    R r1(r = k), r2, r3;
+    Pin interface;
+  equation
+    connect(r1.p, r2.p);
+    connect(r2.n, interface);
+  end C;
+end P;
+"#;
+
+    fn check() -> InstanceCheck {
+        let def = parse_source_simple(SOURCE, "test.mo").unwrap();
+        let result = flatten_with_deps(&def, Some("P.C")).unwrap();
+        check_instances(&result)
+    }
+
+    #[test]
+    fn test_unused_parameters() {
+        let check = check();
+        let unused: Vec<&str> = check.unused_parameters().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            unused,
+            ["gain", "r1.spare", "r2.spare", "r3.spare"],
+            "{:#?}",
+            check.parameters
+        );
+        // Record fields are not checked
+        assert!(check.parameters.iter().all(|p| !p.name.contains("data")));
+        let gain = check.unused_parameters().next().unwrap();
+        assert_eq!(gain.location.start_line, 25);
+    }
+
+    #[test]
+    fn test_unconnected_connectors() {
+        let check = check();
+        let unconnected: Vec<&str> = check
+            .unconnected_connectors()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(unconnected, ["r1.n", "r3.p", "r3.n"]);
+        assert_eq!(check.connectors.len(), 6);
+        let r3 = check.unconnected_connectors().last().unwrap();
+        assert_eq!(r3.component, "r3");
+        assert_eq!(r3.location.start_line, 26);
+    }
+
+    #[test]
+    fn test_causality() {
+        let source = r#"
+connector RealInput = input Real;
+connector RealOutput = output Real;
+block Gain
+  parameter Real k = 1;
+  RealInput u;
+  RealOutput y;
+equation
+  y = k * u;
+end Gain;
+model Plant
+  RealInput u;
+  Gain g;
+equation
+  connect(u, g.u);
+end Plant;
+model Good
+  RealInput x;
+  RealOutput z;
+  Gain bound(u = 3), connected, given, fanout;
+  Plant plant;
+equation
+  connect(bound.y, connected.u);
+  connect(bound.y, fanout.u);
+  connect(bound.y, plant.u);
+  given.u = x;
+  connect(given.y, z);
+end Good;
+model Bad
+  Gain a, b, c, unconnected;
+  Plant plant;
+equation
+  connect(a.y, c.u);
+  connect(b.y, c.u);
+  connect(plant.u, a.u);
+  b.u = 1;
+end Bad;
+"#;
+        let def = parse_source_simple(source, "test.mo").unwrap();
+        let check = |model: &str| check_instances(&flatten_with_deps(&def, Some(model)).unwrap());
+
+        assert_eq!(check("Good").causality, []);
+
+        let errors: Vec<(String, String)> = check("Bad")
+            .causality
+            .into_iter()
+            .map(|error| (error.component, error.message))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    "c".to_string(),
+                    "Input 'c.u' is connected to more than one output: 'a.y', 'b.y'".to_string()
+                ),
+                (
+                    "plant".to_string(),
+                    "Input 'plant.u' is not connected to an output".to_string()
+                ),
+                (
+                    "a".to_string(),
+                    "Input 'a.u' is not connected to an output".to_string()
+                ),
+                (
+                    "plant".to_string(),
+                    "Input 'plant.g.u' is not connected to an output".to_string()
+                ),
+                (
+                    "unconnected".to_string(),
+                    "Input 'unconnected.u' is not connected and has no binding".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -4,9 +4,18 @@
 //! without modifying it, as well as supporting data structures.
 
 pub mod condition_finder;
+pub mod dependency_graph;
+pub mod division_check;
+pub mod function_annotations;
+pub mod instance_check;
+pub mod plug_compatibility;
+pub mod purity;
+pub mod scaling;
 pub mod state_finder;
+pub mod structural_parameters;
 pub mod symbol_table;
 pub mod symbols;
 pub mod type_checker;
 pub mod type_inference;
 pub mod var_validator;
+pub mod when_check;
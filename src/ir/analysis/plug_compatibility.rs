@@ -0,0 +1,365 @@
+//! Plug compatibility of redeclarations.
+//!
+//! A replaceable element can only be redeclared with a class that is
+//! plug-compatible with its constraining type: the `constrainedby` clause,
+//! or the declared type if there is none. The replacement has to provide
+//! every component of the constraining type with
+//!
+//! - the same causality (`input`/`output`) and connection prefix (`flow`/`stream`)
+//! - the same or a lower variability (a `constant` may replace a `parameter`)
+//! - the same array dimensions
+//! - a compatible type: the same built-in type (following short class
+//!   definitions like `type Voltage = Real(unit = "V")`), or a class that is in
+//!   turn plug-compatible with the constraining component's class
+//!
+//! Components are compared by name, and the replacement may have components
+//! the constraining type doesn't have. Protected components are compared as
+//! well, since the IR doesn't record visibility.
+//!
+//! Type names are looked up with a resolver, which gets the names as they are
+//! stored in the classes it returned, so it decides how they are qualified.
+
+use std::fmt;
+
+use crate::ir::ast::{
+    Causality, ClassDefinition, Component, Connection, Location, Subscript, Variability,
+};
+use crate::ir::transform::constants::is_primitive_type;
+
+/// Maximum depth of nested component types compared (guards against cycles)
+const MAX_DEPTH: usize = 16;
+
+/// A component of the constraining type that the replacement doesn't match
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    /// Dotted path of the component, e.g. `p.v`
+    pub element: String,
+    /// What doesn't match, e.g. "is missing"
+    pub reason: String,
+    /// Declaration of the component in the replacement, if it has one
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(loc) = &self.location {
+            write!(
+                f,
+                "{}:{}:{}: ",
+                loc.file_name, loc.start_line, loc.start_column
+            )?;
+        }
+        write!(f, "'{}' {}", self.element, self.reason)
+    }
+}
+
+/// Check that `class` is plug-compatible with the constraining class `interface`
+///
+/// `resolve` looks up the class of a (non built-in) component type, with its
+/// inherited components. Types it can't find are only compatible with types
+/// of the same name.
+pub fn check_plug_compatibility(
+    class: &ClassDefinition,
+    interface: &ClassDefinition,
+    resolve: &dyn Fn(&str) -> Option<ClassDefinition>,
+) -> Vec<Incompatibility> {
+    let mut checker = Checker {
+        resolve,
+        incompatibilities: Vec::new(),
+    };
+    checker.compare_classes("", class, interface, 0);
+    checker.incompatibilities
+}
+
+/// Check that a redeclared component is plug-compatible with the component it
+/// replaces, whose type is the constraining type
+pub fn check_component_compatibility(
+    component: &Component,
+    constraint: &Component,
+    resolve: &dyn Fn(&str) -> Option<ClassDefinition>,
+) -> Vec<Incompatibility> {
+    let mut checker = Checker {
+        resolve,
+        incompatibilities: Vec::new(),
+    };
+    checker.compare_components(&component.name, component, constraint, 0);
+    checker.incompatibilities
+}
+
+/// Type of a component after following short class definitions
+enum ComponentType {
+    /// A built-in type like `Real`
+    Builtin(String),
+    /// A class with components
+    Class(String, Box<ClassDefinition>),
+    /// A type the resolver couldn't find
+    Unknown(String),
+}
+
+impl ComponentType {
+    fn name(&self) -> &str {
+        match self {
+            ComponentType::Builtin(name)
+            | ComponentType::Class(name, _)
+            | ComponentType::Unknown(name) => name,
+        }
+    }
+}
+
+struct Checker<'a> {
+    resolve: &'a dyn Fn(&str) -> Option<ClassDefinition>,
+    incompatibilities: Vec<Incompatibility>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, element: &str, reason: String, location: Option<&Location>) {
+        self.incompatibilities.push(Incompatibility {
+            element: element.to_string(),
+            reason,
+            location: location.cloned(),
+        });
+    }
+
+    fn compare_classes(
+        &mut self,
+        prefix: &str,
+        class: &ClassDefinition,
+        interface: &ClassDefinition,
+        depth: usize,
+    ) {
+        for (name, required) in &interface.components {
+            let path = format!("{}{}", prefix, name);
+            match class.components.get(name) {
+                Some(component) => self.compare_components(&path, component, required, depth),
+                None => self.report(&path, "is missing".to_string(), None),
+            }
+        }
+    }
+
+    fn compare_components(
+        &mut self,
+        path: &str,
+        component: &Component,
+        required: &Component,
+        depth: usize,
+    ) {
+        let location = Some(&component.location);
+        if std::mem::discriminant(&component.causality)
+            != std::mem::discriminant(&required.causality)
+        {
+            self.report(
+                path,
+                format!(
+                    "is {}, but the constraining type declares it {}",
+                    causality_name(&component.causality),
+                    causality_name(&required.causality)
+                ),
+                location,
+            );
+        }
+        if std::mem::discriminant(&component.connection)
+            != std::mem::discriminant(&required.connection)
+        {
+            self.report(
+                path,
+                format!(
+                    "is {}, but the constraining type declares it {}",
+                    connection_name(&component.connection),
+                    connection_name(&required.connection)
+                ),
+                location,
+            );
+        }
+        if variability_rank(&component.variability) > variability_rank(&required.variability) {
+            self.report(
+                path,
+                format!(
+                    "is {}, but the constraining type declares it {}",
+                    variability_name(&component.variability),
+                    variability_name(&required.variability)
+                ),
+                location,
+            );
+        }
+        let (dims, required_dims) = (dimensions(component), dimensions(required));
+        if dims != required_dims {
+            self.report(
+                path,
+                format!(
+                    "has dimensions {}, but the constraining type declares {}",
+                    format_dimensions(&dims),
+                    format_dimensions(&required_dims)
+                ),
+                location,
+            );
+        }
+
+        let component_type = self.component_type(&component.type_name.to_string());
+        let required_type = self.component_type(&required.type_name.to_string());
+        match (&component_type, &required_type) {
+            (a, b) if a.name() == b.name() => {}
+            (ComponentType::Class(_, class), ComponentType::Class(_, interface))
+                if depth < MAX_DEPTH =>
+            {
+                self.compare_classes(&format!("{}.", path), class, interface, depth + 1);
+            }
+            (a, b) => self.report(
+                path,
+                format!(
+                    "has type '{}', but the constraining type declares '{}'",
+                    a.name(),
+                    b.name()
+                ),
+                location,
+            ),
+        }
+    }
+
+    /// Follow short class definitions of a type to a built-in type or a class
+    fn component_type(&self, name: &str) -> ComponentType {
+        let mut name = name.to_string();
+        for _ in 0..MAX_DEPTH {
+            if is_primitive_type(&name) {
+                return ComponentType::Builtin(name);
+            }
+            let Some(class) = (self.resolve)(&name) else {
+                return ComponentType::Unknown(name);
+            };
+            match &class.short_class {
+                Some(short) if short.shape_expr.is_empty() => {
+                    name = short.base_type.to_string();
+                }
+                _ => return ComponentType::Class(name, Box::new(class)),
+            }
+        }
+        ComponentType::Unknown(name)
+    }
+}
+
+/// Array dimensions as written, or the literal dimensions if none were recorded
+pub(crate) fn dimensions(component: &Component) -> Vec<String> {
+    if component.shape_expr.is_empty() {
+        component.shape.iter().map(|dim| dim.to_string()).collect()
+    } else {
+        component
+            .shape_expr
+            .iter()
+            .map(|sub| match sub {
+                Subscript::Expression(expr) => expr.to_string(),
+                Subscript::Range { .. } => ":".to_string(),
+                Subscript::Empty => String::new(),
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn format_dimensions(dims: &[String]) -> String {
+    if dims.is_empty() {
+        "none (a scalar)".to_string()
+    } else {
+        format!("[{}]", dims.join(", "))
+    }
+}
+
+fn causality_name(causality: &Causality) -> &'static str {
+    match causality {
+        Causality::Input(_) => "an input",
+        Causality::Output(_) => "an output",
+        Causality::Empty => "neither input nor output",
+    }
+}
+
+pub(crate) fn connection_name(connection: &Connection) -> &'static str {
+    match connection {
+        Connection::Flow(_) => "a flow variable",
+        Connection::Stream(_) => "a stream variable",
+        Connection::Empty => "a potential variable",
+    }
+}
+
+/// Variabilities from lowest (constant) to highest (continuous-time)
+fn variability_rank(variability: &Variability) -> usize {
+    match variability {
+        Variability::Constant(_) => 0,
+        Variability::Parameter(_) => 1,
+        Variability::Discrete(_) => 2,
+        Variability::Empty => 3,
+    }
+}
+
+fn variability_name(variability: &Variability) -> &'static str {
+    match variability {
+        Variability::Constant(_) => "a constant",
+        Variability::Parameter(_) => "a parameter",
+        Variability::Discrete(_) => "discrete-time",
+        Variability::Empty => "continuous-time",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    #[test]
+    fn test_plug_compatibility() {
+        let def = parse_source_simple(
+            r#"
+type Voltage = Real(unit = "V");
+connector Pin
+  Voltage v;
+  flow Real i;
+end Pin;
+connector BadPin
+  Real v;
+  Real i;
+end BadPin;
+partial model OnePort
+  parameter Real R;
+  input Real u;
+  Pin p;
+  Real x[2];
+end OnePort;
+model Good
+  constant Real R = 1;
+  input Real u;
+  Pin p;
+  Real x[2];
+  Real extra;
+end Good;
+model Bad
+  Real R;
+  output Real u;
+  BadPin p;
+  Integer x[3];
+end Bad;
+"#,
+            "test.mo",
+        )
+        .unwrap();
+        let resolve = |name: &str| def.class_list.get(name).cloned();
+        let class = |name: &str| resolve(name).unwrap();
+
+        assert!(check_plug_compatibility(&class("Good"), &class("OnePort"), &resolve).is_empty());
+
+        let reasons: Vec<String> =
+            check_plug_compatibility(&class("Bad"), &class("OnePort"), &resolve)
+                .iter()
+                .map(|i| format!("'{}' {}", i.element, i.reason))
+                .collect();
+        assert_eq!(
+            reasons,
+            [
+                "'R' is continuous-time, but the constraining type declares it a parameter",
+                "'u' is an output, but the constraining type declares it an input",
+                "'p.i' is a potential variable, but the constraining type declares it a flow variable",
+                "'x' has dimensions [3], but the constraining type declares [2]",
+                "'x' has type 'Integer', but the constraining type declares 'Real'",
+            ]
+        );
+
+        let missing = check_plug_compatibility(&class("Pin"), &class("OnePort"), &resolve);
+        assert_eq!(missing.len(), 4);
+        assert_eq!(missing[0].to_string(), "'R' is missing");
+    }
+}
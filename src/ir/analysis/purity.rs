@@ -0,0 +1,256 @@
+//! Purity of functions (Modelica spec §12.3).
+//!
+//! A function is pure if it is declared `pure`, or has no prefix and no
+//! `external` clause; it is impure if it is declared `impure` or is external
+//! without the `pure` prefix, as external code may have side effects (see
+//! [`ClassDefinition::is_pure`]). Pure functions must not call impure ones,
+//! except inside the `pure(...)` operator, which asserts that the call is
+//! pure where it is used:
+//!
+//! ```modelica
+//! impure function log "writes to a file" ... end log;
+//! function f
+//!   input Real x;
+//!   output Real y;
+//! algorithm
+//!   log(x);           // error: f is pure, log is impure
+//!   y := pure(g(x));  // allowed
+//! end f;
+//! ```
+//!
+//! The compiler checks the functions the model calls, directly or through
+//! other functions. Only pure functions are inlined, so calls of impure
+//! functions are kept as calls.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use indexmap::IndexMap;
+
+use crate::ir::ast::{
+    ClassDefinition, ComponentReference, Equation, Expression, Location, Statement,
+};
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// Name of the operator marking a call as pure
+const PURE_OPERATOR: &str = "pure";
+
+/// A pure function calling an impure one
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurityError {
+    /// Name of the pure function
+    pub function: String,
+    /// Name of the impure function, as called
+    pub callee: String,
+    /// Location of the call
+    pub location: Location,
+}
+
+impl fmt::Display for PurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: pure function '{}' calls impure function '{}' (declare '{}' impure, or wrap the call in pure(...))",
+            self.location.file_position(),
+            self.function,
+            self.callee,
+            self.function
+        )
+    }
+}
+
+/// Check the purity of the functions a class calls, directly or through
+/// other functions, see the [module docs](self)
+pub fn check_purity(
+    class: &ClassDefinition,
+    functions: &IndexMap<String, &ClassDefinition>,
+) -> Vec<PurityError> {
+    let mut errors = Vec::new();
+    let mut queue: Vec<String> = calls(class).into_iter().map(|call| call.name).collect();
+    let mut checked = HashSet::new();
+    while let Some(name) = queue.pop() {
+        let Some(function) = functions.get(&name) else {
+            continue;
+        };
+        // Functions are known by several names, e.g. short and full
+        if !checked.insert(*function as *const ClassDefinition) {
+            continue;
+        }
+        for call in calls(function) {
+            if function.is_pure()
+                && !call.in_pure_operator
+                && functions.get(&call.name).is_some_and(|f| !f.is_pure())
+            {
+                errors.push(PurityError {
+                    function: name.clone(),
+                    callee: call.name.clone(),
+                    location: call.location.clone(),
+                });
+            }
+            queue.push(call.name);
+        }
+    }
+    errors
+}
+
+/// A function call
+struct Call {
+    name: String,
+    location: Location,
+    /// Whether the call is an argument of `pure(...)`
+    in_pure_operator: bool,
+}
+
+/// The function calls of a class: expressions, statements and equations
+fn calls(class: &ClassDefinition) -> Vec<Call> {
+    let mut collector = CallCollector::default();
+    class.accept(&mut collector);
+    collector.calls
+}
+
+#[derive(Default)]
+struct CallCollector {
+    calls: Vec<Call>,
+    /// Depth of nested `pure(...)` operators
+    pure_depth: usize,
+}
+
+impl CallCollector {
+    fn call(&mut self, comp: &ComponentReference) {
+        let name = comp.to_string();
+        if name != PURE_OPERATOR {
+            self.calls.push(Call {
+                name,
+                location: comp.get_location().cloned().unwrap_or_default(),
+                in_pure_operator: self.pure_depth > 0,
+            });
+        }
+    }
+}
+
+impl Visitor for CallCollector {
+    fn enter_expression(&mut self, node: &Expression) {
+        if let Expression::FunctionCall { comp, .. } = node {
+            self.call(comp);
+            if comp.to_string() == PURE_OPERATOR {
+                self.pure_depth += 1;
+            }
+        }
+    }
+
+    fn exit_expression(&mut self, node: &Expression) {
+        if let Expression::FunctionCall { comp, .. } = node
+            && comp.to_string() == PURE_OPERATOR
+        {
+            self.pure_depth -= 1;
+        }
+    }
+
+    fn enter_statement(&mut self, node: &Statement) {
+        if let Statement::FunctionCall { comp, .. } = node {
+            self.call(comp);
+        }
+    }
+
+    fn enter_equation(&mut self, node: &Equation) {
+        if let Equation::FunctionCall { comp, .. } = node {
+            self.call(comp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+    use crate::ir::ast::Purity;
+
+    const FUNCTIONS: &str = r#"
+impure function counter
+  input Real x;
+  output Real y;
+algorithm
+  y := x + 1;
+end counter;
+
+function ext
+  input Real x;
+  output Real y;
+external "C" y = ext_c(x);
+end ext;
+
+pure function pureExt
+  input Real x;
+  output Real y;
+external "C" y = pure_ext_c(x);
+end pureExt;
+
+function scaled
+  input Real x;
+  output Real y;
+algorithm
+  y := 2 * pure(counter(x)) + pureExt(x);
+end scaled;
+"#;
+
+    fn compile(model: &str) -> Result<crate::CompilationResult, crate::Error> {
+        Compiler::new()
+            .model("M")
+            .compile_str(&format!("{}{}", FUNCTIONS, model), "m.mo")
+    }
+
+    #[test]
+    fn test_purity() {
+        let result = compile(
+            "model M\n  Real x;\n  Real y;\nequation\n  x = scaled(time);\n  y = counter(time);\nend M;",
+        )
+        .unwrap();
+        let purity = |name: &str| {
+            let class = &result.def.class_list[name];
+            (class.purity, class.is_pure())
+        };
+        assert_eq!(purity("counter"), (Purity::Impure, false));
+        assert_eq!(purity("ext"), (Purity::Unspecified, false));
+        assert_eq!(purity("pureExt"), (Purity::Pure, true));
+        assert_eq!(purity("scaled"), (Purity::Unspecified, true));
+
+        // The pure function is inlined, the impure one is kept as a call
+        let equations: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+        assert!(
+            equations.iter().any(|eq| eq.contains("pureExt(time)")
+                && eq.contains("counter(time)")
+                && !eq.contains("scaled")),
+            "{:?}",
+            equations
+        );
+        assert!(equations.iter().any(|eq| eq.ends_with("= counter(time)")));
+
+        // Pure functions must not call impure ones, directly or through
+        // another function
+        let source = r#"
+function bad
+  input Real x;
+  output Real y;
+algorithm
+  y := ext(x);
+end bad;
+
+function wrapper
+  input Real x;
+  output Real y;
+algorithm
+  y := bad(x);
+end wrapper;
+
+model M
+  Real x;
+equation
+  x = wrapper(time);
+end M;"#;
+        let err = compile(source).unwrap_err().to_string();
+        assert!(
+            err.contains("pure function 'bad' calls impure function 'ext'"),
+            "{}",
+            err
+        );
+    }
+}
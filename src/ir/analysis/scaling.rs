@@ -0,0 +1,308 @@
+//! Scaling analysis of equations.
+//!
+//! Solvers lose precision on equations whose terms differ by many orders of
+//! magnitude, e.g. a pressure in pascal next to a displacement in meters:
+//!
+//! ```modelica
+//! p = 1e5 * x + y;  // terms of magnitude 1e5, 1e5 and 1
+//! ```
+//!
+//! [`equation_scaling`] splits an equation into its additive terms and
+//! evaluates each at a typical point (see [`ScalingPoint`]): parameters and
+//! constants take their values, variables the magnitude of their `nominal`
+//! attribute, else of their start value, else 1, and derivatives `der(x)` the
+//! magnitude of `x`. The ratio of the largest to the smallest nonzero term
+//! estimates how badly scaled the equation is. Equations beyond a threshold
+//! ([`DEFAULT_THRESHOLD`] by default) are reported with the variables of their
+//! extreme terms that have no nominal value, which should be given one.
+//!
+//! Terms that can't be evaluated, e.g. calls of user functions, are ignored.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::ir::analysis::division_check::{evaluate, initial_values};
+use crate::ir::ast::{
+    ClassDefinition, Component, ComponentReference, Equation, Expression, Location, OpBinary,
+    OpUnary, Variability,
+};
+use crate::ir::visitor::{MutVisitable, MutVisitor, Visitable, Visitor};
+
+/// Ratio of the largest to the smallest term beyond which an equation is
+/// badly scaled
+pub const DEFAULT_THRESHOLD: f64 = 1e6;
+
+/// An additive term of an equation and its magnitude at the typical point
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Term {
+    pub term: String,
+    pub magnitude: f64,
+}
+
+/// The range of the terms of an equation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EquationScaling {
+    pub equation: String,
+    pub location: Option<Location>,
+    pub largest: Term,
+    pub smallest: Term,
+    /// Variables of the largest and smallest terms without a nominal value
+    pub without_nominal: Vec<String>,
+}
+
+impl EquationScaling {
+    /// Ratio of the largest to the smallest term
+    pub fn ratio(&self) -> f64 {
+        self.largest.magnitude / self.smallest.magnitude
+    }
+
+    /// Diagnostic message for a badly scaled equation
+    pub fn message(&self) -> String {
+        format!(
+            "badly scaled equation '{}': its terms range from {:e} ('{}') to {:e} ('{}')",
+            self.equation,
+            self.smallest.magnitude,
+            self.smallest.term,
+            self.largest.magnitude,
+            self.largest.term
+        )
+    }
+
+    /// How to improve the scaling of the equation
+    pub fn suggestion(&self) -> String {
+        if self.without_nominal.is_empty() {
+            "Check the units of the coefficients, or rescale the variables of the equation"
+                .to_string()
+        } else {
+            let names: Vec<String> = self
+                .without_nominal
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect();
+            format!(
+                "Set the nominal attribute of {} to the typical magnitude of the variable",
+                names.join(", ")
+            )
+        }
+    }
+}
+
+/// Magnitudes at which the terms of equations are evaluated, see the
+/// [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct ScalingPoint {
+    values: HashMap<String, f64>,
+    /// Variables with a nominal value
+    nominal: HashSet<String>,
+    variables: HashSet<String>,
+}
+
+impl ScalingPoint {
+    /// The point of variables, given the values of the known parameters and
+    /// constants
+    pub fn new<'a>(
+        known: HashMap<String, f64>,
+        variables: impl IntoIterator<Item = (&'a String, &'a Component)>,
+    ) -> Self {
+        let mut point = ScalingPoint {
+            values: known,
+            ..Default::default()
+        };
+        for (name, comp) in variables {
+            let nominal = comp
+                .modifications
+                .get("nominal")
+                .and_then(|nominal| evaluate(nominal, &point.values))
+                .filter(|nominal| *nominal != 0.0);
+            if nominal.is_some() {
+                point.nominal.insert(name.clone());
+            }
+            let magnitude = nominal
+                .or_else(|| evaluate(&comp.start, &point.values).filter(|start| *start != 0.0))
+                .map_or(1.0, f64::abs);
+            point.values.insert(name.clone(), magnitude);
+            point.variables.insert(name.clone());
+        }
+        point
+    }
+
+    /// The point of the components of a class: parameters and constants take
+    /// their default values, the other components are variables
+    pub fn from_class(class: &ClassDefinition) -> Self {
+        let variables = class.components.iter().filter(|(_, comp)| {
+            !matches!(
+                comp.variability,
+                Variability::Parameter(_) | Variability::Constant(_)
+            )
+        });
+        ScalingPoint::new(initial_values(class), variables)
+    }
+}
+
+/// The range of the terms of an equation, if it has at least two nonzero
+/// terms that can be evaluated
+pub fn equation_scaling(eq: &Equation, point: &ScalingPoint) -> Option<EquationScaling> {
+    let Equation::Simple { lhs, rhs, .. } = eq else {
+        return None;
+    };
+    let mut terms = Vec::new();
+    collect_terms(lhs, &mut terms);
+    collect_terms(rhs, &mut terms);
+
+    let mut magnitudes: Vec<(&Expression, f64)> = terms
+        .into_iter()
+        .filter_map(|term| {
+            let mut magnitude = term.clone();
+            magnitude.accept_mut(&mut DerivativeMagnitudes);
+            let value = evaluate(&magnitude, &point.values)?.abs();
+            (value > 0.0 && value.is_finite()).then_some((term, value))
+        })
+        .collect();
+    if magnitudes.len() < 2 {
+        return None;
+    }
+    magnitudes.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (smallest, largest) = (magnitudes[0], magnitudes[magnitudes.len() - 1]);
+
+    let mut without_nominal = Vec::new();
+    for (term, _) in [largest, smallest] {
+        let mut finder = VariableFinder::default();
+        term.accept(&mut finder);
+        for name in finder.names {
+            if point.variables.contains(&name)
+                && !point.nominal.contains(&name)
+                && !without_nominal.contains(&name)
+            {
+                without_nominal.push(name);
+            }
+        }
+    }
+    let term = |(term, magnitude): (&Expression, f64)| Term {
+        term: term.to_string(),
+        magnitude,
+    };
+    Some(EquationScaling {
+        equation: eq.to_string(),
+        location: eq.get_location().cloned(),
+        largest: term(largest),
+        smallest: term(smallest),
+        without_nominal,
+    })
+}
+
+/// The equations of a class whose terms range beyond a ratio
+///
+/// Nested classes are not analyzed (callers recurse as needed).
+pub fn find_badly_scaled(class: &ClassDefinition, threshold: f64) -> Vec<EquationScaling> {
+    let point = ScalingPoint::from_class(class);
+    class
+        .equations
+        .iter()
+        .filter_map(|eq| equation_scaling(eq, &point))
+        .filter(|scaling| scaling.ratio() > threshold)
+        .collect()
+}
+
+/// The additive terms of an expression
+fn collect_terms<'a>(expr: &'a Expression, terms: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::Binary {
+            op: OpBinary::Add(_) | OpBinary::Sub(_) | OpBinary::AddElem(_) | OpBinary::SubElem(_),
+            lhs,
+            rhs,
+        } => {
+            collect_terms(lhs, terms);
+            collect_terms(rhs, terms);
+        }
+        Expression::Unary {
+            op: OpUnary::Minus(_) | OpUnary::Plus(_) | OpUnary::DotMinus(_) | OpUnary::DotPlus(_),
+            rhs,
+        } => collect_terms(rhs, terms),
+        Expression::Parenthesized { inner } => collect_terms(inner, terms),
+        _ => terms.push(expr),
+    }
+}
+
+/// Replaces `der(x)` by `x`, whose magnitude it takes
+struct DerivativeMagnitudes;
+
+impl MutVisitor for DerivativeMagnitudes {
+    fn exit_expression(&mut self, node: &mut Expression) {
+        if let Expression::FunctionCall { comp, args } = node
+            && comp.to_string() == "der"
+            && let [arg] = args.as_slice()
+        {
+            *node = arg.clone();
+        }
+    }
+}
+
+/// Names of the component references of an expression
+#[derive(Default)]
+struct VariableFinder {
+    names: Vec<String>,
+}
+
+impl Visitor for VariableFinder {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        self.names.push(node.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    #[test]
+    fn test_badly_scaled_equations() {
+        let source = r#"model M
+  parameter Real k = 1e5;
+  Real p;
+  Real x(start = 2);
+  Real y(nominal = 1e-3);
+  Real z;
+equation
+  p = k * x + y;
+  der(x) = -x + 2 * z;
+  z = 1e-4 * p;
+end M;
+"#;
+        let def = parse_source_simple(source, "m.mo").unwrap();
+        let class = &def.class_list["M"];
+        let point = ScalingPoint::from_class(class);
+        let scalings: Vec<EquationScaling> = class
+            .equations
+            .iter()
+            .map(|eq| equation_scaling(eq, &point).unwrap())
+            .collect();
+
+        // k * x is 2e5 and y is 1e-3
+        let first = &scalings[0];
+        assert_eq!(first.largest.term, "k * x");
+        assert_eq!(first.smallest.term, "y");
+        assert_eq!(first.ratio(), 2e8);
+        assert_eq!(first.without_nominal, ["x"]);
+        assert_eq!(first.location.as_ref().unwrap().file_position(), "m.mo:8:3");
+        assert_eq!(
+            first.suggestion(),
+            "Set the nominal attribute of 'x' to the typical magnitude of the variable"
+        );
+
+        // der(x) takes the magnitude of x, as do x and 2 * z
+        assert_eq!(scalings[1].largest.magnitude, 2.0);
+        assert_eq!(scalings[1].ratio(), 1.0);
+        assert_eq!(scalings[2].ratio(), 1e4);
+
+        let badly_scaled = find_badly_scaled(class, DEFAULT_THRESHOLD);
+        assert_eq!(badly_scaled, std::slice::from_ref(first));
+        assert!(
+            badly_scaled[0]
+                .message()
+                .starts_with("badly scaled equation 'p = k * x + y': its terms range from 1e-3 ('y') to 2e5 ('k * x')"),
+            "{}",
+            badly_scaled[0].message()
+        );
+    }
+}
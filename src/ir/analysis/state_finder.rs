@@ -7,28 +7,77 @@
 //! # Fields
 //! - `states`: A `HashSet` containing the names of the state variables found
 //!   during the traversal.
+//! - `shapes`: The declared shapes of the array variables, to tell elements
+//!   from slices of multi-dimensional arrays.
 //!
 //! # Visitor Implementation
 //! - The `exit_expression` method is invoked when exiting an expression node
 //!   during the AST traversal. It performs the following actions:
 //!   - Checks if the expression is a function call with the identifier `der`.
 //!   - If the first argument of the `der` function is a component reference,
-//!     the state variable name is extracted and added to the `states` set.
+//!     the state variable name is extracted and added to the `states` set:
+//!     `x[1,2]` for an array element with constant subscripts, every element
+//!     of the row `x[1]` of a matrix `x`, otherwise `x`.
 //!   - **Note:** The AST is NOT modified - `der()` calls remain as function calls
 //!     to maintain Base Modelica compliance.
 //!
 //! This visitor is useful for identifying which variables are states (appear in
 //! der() calls) without transforming the AST representation.
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use crate::ir;
+use crate::ir::transform::component_arrays::{element_indices, element_name};
 use crate::ir::transform::constants::BUILTIN_DER;
 use crate::ir::visitor::MutVisitor;
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct StateFinder {
     pub states: IndexSet<String>,
+    shapes: IndexMap<String, Vec<usize>>,
+}
+
+impl StateFinder {
+    /// Create a state finder for the variables of a flattened class
+    pub fn new(components: &IndexMap<String, ir::ast::Component>) -> Self {
+        Self {
+            states: IndexSet::new(),
+            shapes: components
+                .iter()
+                .filter(|(_, comp)| !comp.shape.is_empty())
+                .map(|(name, comp)| (name.clone(), comp.shape.clone()))
+                .collect(),
+        }
+    }
+
+    /// Add the scalarized elements a reference with constant subscripts
+    /// refers to: the element itself when all dimensions are subscripted,
+    /// otherwise every element of the slice (`x[1]` of a matrix is its row)
+    fn insert_elements(&mut self, comp: &ir::ast::ComponentReference) {
+        if let [part] = comp.parts.as_slice()
+            && let Some(shape) = self.shapes.get(&part.ident.text)
+        {
+            let prefix: Vec<usize> = part
+                .subs
+                .iter()
+                .flatten()
+                .filter_map(|sub| match sub {
+                    ir::ast::Subscript::Expression(ir::ast::Expression::Terminal {
+                        token, ..
+                    }) => token.text.parse().ok(),
+                    _ => None,
+                })
+                .collect();
+            if !prefix.is_empty() && prefix.len() < shape.len() {
+                for rest in element_indices(&shape[prefix.len()..]) {
+                    let index: Vec<usize> = prefix.iter().chain(&rest).copied().collect();
+                    self.states.insert(element_name(&part.ident.text, &index));
+                }
+                return;
+            }
+        }
+        self.states.insert(comp.to_string());
+    }
 }
 
 impl MutVisitor for StateFinder {
@@ -39,11 +88,30 @@ impl MutVisitor for StateFinder {
             // SAFETY: Modelica der() function always has exactly 1 argument
             let arg = args.first().unwrap();
             if let ir::ast::Expression::ComponentReference(comp) = &arg {
-                // Collect the state variable name
-                self.states.insert(comp.parts[0].ident.text.clone());
+                // Collect the state variable names: the scalarized elements for
+                // constant subscripts (`der(x[1])` makes `x[1]` a state, not all
+                // of `x`), otherwise the whole variable
+                if comp.parts.iter().all(has_constant_subscripts) {
+                    self.insert_elements(comp);
+                } else {
+                    self.states.insert(comp.parts[0].ident.text.clone());
+                }
                 // DO NOT transform the AST - keep der() as a function call
                 // for Base Modelica compliance
             }
         }
     }
 }
+
+/// Whether a part of a reference has no subscripts or only integer literals
+fn has_constant_subscripts(part: &ir::ast::ComponentRefPart) -> bool {
+    part.subs.iter().flatten().all(|sub| {
+        matches!(
+            sub,
+            ir::ast::Subscript::Expression(ir::ast::Expression::Terminal {
+                terminal_type: ir::ast::TerminalType::UnsignedInteger,
+                ..
+            })
+        )
+    })
+}
@@ -0,0 +1,232 @@
+//! Structural and tunable parameters.
+//!
+//! A parameter is structural if the structure of the model depends on its
+//! value, so it has to be known at compile time and can't change between
+//! simulations without recompiling:
+//!
+//! - array dimensions, e.g. `n` in `Real x[n]`
+//! - conditions of conditional components, e.g. `use_heat` in
+//!   `HeatPort port if use_heat`
+//! - ranges of for-equations, which are unrolled, e.g. `n` in `for i in 1:n loop`
+//! - the bindings of structural parameters, e.g. `m` in `parameter Integer n = 2 * m`
+//!
+//! All other parameters are tunable. Backends can expose the classification,
+//! e.g. as the FMI variability of parameters (`fixed` or structural
+//! parameters for structural ones, `tunable` for the others).
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::ir::ast::{
+    ClassDefinition, Component, ComponentReference, Equation, Expression, Subscript, Variability,
+};
+use crate::ir::transform::equation_expander::{
+    eval_boolean, eval_integer_with_params, iteration_values,
+};
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// Find the structural parameters of a flattened class, given the conditions
+/// of its conditional components by component name.
+///
+/// Fails for a structural parameter whose value isn't known at compile time,
+/// naming what it determines.
+pub fn structural_parameters(
+    class: &ClassDefinition,
+    conditions: &IndexMap<String, Expression>,
+) -> anyhow::Result<IndexSet<String>> {
+    // Parameters with what they determine, in order of discovery
+    let mut uses: IndexMap<String, String> = IndexMap::new();
+    for (name, comp) in &class.components {
+        for sub in &comp.shape_expr {
+            if let Subscript::Expression(expr) = sub {
+                record(&mut uses, class, expr, || format!("the size of '{}'", name));
+            }
+        }
+    }
+    for (name, condition) in conditions {
+        record(&mut uses, class, condition, || {
+            format!("whether '{}' exists", name)
+        });
+    }
+    let mut ranges = ForRanges::default();
+    for eq in class.equations.iter().chain(&class.initial_equations) {
+        eq.accept(&mut ranges);
+    }
+    for range in &ranges.0 {
+        record(&mut uses, class, range, || {
+            "the range of a for-equation".to_string()
+        });
+    }
+
+    // Parameters the bindings of structural parameters depend on
+    let mut i = 0;
+    while let Some((name, _)) = uses.get_index(i) {
+        let name = name.clone();
+        let binding = &class.components[&name].start;
+        record(&mut uses, class, binding, || {
+            format!("the value of '{}'", name)
+        });
+        i += 1;
+    }
+
+    for (name, usage) in &uses {
+        if !has_compile_time_value(name, &class.components) {
+            let loc = &class.components[name].location;
+            anyhow::bail!(
+                "{}:{}:{}: parameter '{}' determines {}, so its value must be known at compile time",
+                loc.file_name,
+                loc.start_line,
+                loc.start_column,
+                name,
+                usage
+            );
+        }
+    }
+    Ok(uses.into_keys().collect())
+}
+
+/// Record the parameters an expression refers to, unless they are recorded
+/// already
+fn record(
+    uses: &mut IndexMap<String, String>,
+    class: &ClassDefinition,
+    expr: &Expression,
+    usage: impl Fn() -> String,
+) {
+    for name in references(expr) {
+        let is_parameter = class
+            .components
+            .get(&name)
+            .is_some_and(|comp| matches!(comp.variability, Variability::Parameter(_)));
+        if is_parameter && !uses.contains_key(&name) {
+            uses.insert(name, usage());
+        }
+    }
+}
+
+/// Whether the value of a parameter can be evaluated at compile time, as an
+/// integer, a Boolean or a vector of integers
+fn has_compile_time_value(name: &str, components: &IndexMap<String, Component>) -> bool {
+    // Declarations without a binding get a default start value, which has
+    // no location in the source
+    if let Expression::Terminal { token, .. } = &components[name].start
+        && token.location.start_line == 0
+    {
+        return false;
+    }
+    let expr = Expression::ComponentReference(ComponentReference {
+        local: false,
+        parts: vec![crate::ir::ast::ComponentRefPart {
+            ident: crate::ir::ast::Token {
+                text: name.to_string(),
+                ..Default::default()
+            },
+            subs: None,
+        }],
+    });
+    eval_integer_with_params(&expr, components).is_some()
+        || eval_boolean(&expr, components).is_some()
+        || matches!(components[name].start, Expression::Array { .. })
+            && iteration_values(&expr, components).is_some()
+}
+
+/// Names of the component references in an expression, including subscripts
+fn references(expr: &Expression) -> Vec<String> {
+    #[derive(Default)]
+    struct Names(Vec<String>);
+    impl Visitor for Names {
+        fn enter_component_reference(&mut self, node: &ComponentReference) {
+            let name = node
+                .parts
+                .iter()
+                .map(|part| part.ident.text.as_str())
+                .collect::<Vec<_>>()
+                .join(".");
+            self.0.push(name);
+        }
+    }
+    let mut names = Names::default();
+    expr.accept(&mut names);
+    names.0
+}
+
+/// Ranges of the for-equations, including nested ones
+#[derive(Default)]
+struct ForRanges(Vec<Expression>);
+
+impl Visitor for ForRanges {
+    fn enter_equation(&mut self, node: &Equation) {
+        if let Equation::For { indices, .. } = node {
+            self.0
+                .extend(indices.iter().map(|index| index.range.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    const SOURCE: &str = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+model Sub
+  Pin p;
+equation
+  p.v = 1;
+end Sub;
+model M
+  parameter Boolean use_sub = true;
+  parameter Integer m = 2;
+  parameter Integer n = 2 * m;
+  parameter Real k = 1;
+  Real x[n];
+  Sub s if use_sub;
+equation
+  for i in 1:n loop
+    x[i] = k * i;
+  end for;
+end M;
+"#;
+
+    #[test]
+    fn test_structural_parameters() {
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(SOURCE, "m.mo")
+            .unwrap();
+        let structural: Vec<&str> = result.dae.structural.iter().map(String::as_str).collect();
+        assert_eq!(structural, ["n", "use_sub", "m"]);
+        assert!(!result.dae.structural.contains("k"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&result.dae.to_dae_ir_json().unwrap()).unwrap();
+        let variability = |name: &str| {
+            json["variables"]["parameters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|p| p["name"] == name)
+                .map(|p| p["variability"].clone())
+                .unwrap()
+        };
+        assert_eq!(variability("n"), "structural");
+        assert_eq!(variability("k"), "tunable");
+    }
+
+    #[test]
+    fn test_structural_parameter_without_value() {
+        let source = SOURCE.replace("parameter Integer m = 2;", "parameter Integer m;");
+        let err = Compiler::new()
+            .model("M")
+            .compile_str(&source, "m.mo")
+            .unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                "parameter 'm' determines the value of 'n', so its value must be known at compile time"
+            ),
+            "{err}"
+        );
+    }
+}
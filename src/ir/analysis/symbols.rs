@@ -35,6 +35,8 @@ pub struct DefinedSymbol {
     /// For functions: the return type (output variable type and shape)
     /// None for non-functions
     pub function_return: Option<(String, Vec<usize>)>,
+    /// For records: the fields, see [`add_record_fields`]
+    pub fields: HashMap<String, DefinedSymbol>,
 }
 
 impl DefinedSymbol {
@@ -70,6 +72,7 @@ impl DefinedSymbol {
                 type_name,
                 shape: comp.shape.clone(),
                 function_return: None,
+                fields: HashMap::new(),
             },
         )
     }
@@ -99,6 +102,7 @@ impl DefinedSymbol {
                 type_name: name.to_string(),
                 shape: vec![],
                 function_return,
+                fields: HashMap::new(),
             },
         )
     }
@@ -115,10 +119,45 @@ impl DefinedSymbol {
             type_name: "Integer".to_string(),
             shape: vec![],
             function_return: None,
+            fields: HashMap::new(),
         }
     }
 }
 
+/// Add the fields of the record components in `defined`, recursively, with
+/// `lookup` resolving type names to classes
+///
+/// Type inference uses them to find the type of `p.origin.x`.
+pub fn add_record_fields<'a>(
+    defined: &mut HashMap<String, DefinedSymbol>,
+    lookup: &impl Fn(&str) -> Option<&'a ClassDefinition>,
+) {
+    for symbol in defined.values_mut().filter(|sym| !sym.is_class) {
+        add_fields(symbol, lookup, 0);
+    }
+}
+
+fn add_fields<'a>(
+    symbol: &mut DefinedSymbol,
+    lookup: &impl Fn(&str) -> Option<&'a ClassDefinition>,
+    depth: usize,
+) {
+    // Records can't contain themselves, but invalid code may
+    const MAX_DEPTH: usize = 16;
+    if depth > MAX_DEPTH || !is_class_instance_type(&symbol.type_name) {
+        return;
+    }
+    let Some(record) = lookup(&symbol.type_name).filter(|c| c.class_type == ClassType::Record)
+    else {
+        return;
+    };
+    for (name, comp) in &record.components {
+        let (name, mut field) = DefinedSymbol::from_component(name, comp);
+        add_fields(&mut field, lookup, depth + 1);
+        symbol.fields.insert(name, field);
+    }
+}
+
 /// Check if a type name represents a class instance (not a primitive type).
 ///
 /// Returns `false` for built-in types like Real, Integer, Boolean, String,
@@ -150,6 +189,9 @@ pub fn collect_defined_symbols(class: &ClassDefinition) -> HashMap<String, Defin
         defined.insert(sym_name, symbol);
     }
 
+    // Fields of components of the nested records
+    add_record_fields(&mut defined, &|name: &str| class.classes.get(name));
+
     defined
 }
 
@@ -240,6 +282,9 @@ pub fn collect_expr_symbols(expr: &Expression, used: &mut HashSet<String>) {
         Expression::Parenthesized { inner } => {
             collect_expr_symbols(inner, used);
         }
+        Expression::NamedArgument { value, .. } => {
+            collect_expr_symbols(value, used);
+        }
         Expression::ArrayComprehension { expr, indices } => {
             collect_expr_symbols(expr, used);
             for idx in indices {
@@ -253,15 +298,17 @@ pub fn collect_expr_symbols(expr: &Expression, used: &mut HashSet<String>) {
 pub fn collect_equation_symbols(eq: &Equation, used: &mut HashSet<String>) {
     match eq {
         Equation::Empty => {}
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             collect_expr_symbols(lhs, used);
             collect_expr_symbols(rhs, used);
         }
-        Equation::Connect { lhs, rhs } => {
+        Equation::Connect { lhs, rhs, .. } => {
             collect_comp_ref_symbols(lhs, used);
             collect_comp_ref_symbols(rhs, used);
         }
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices, equations, ..
+        } => {
             for index in indices {
                 collect_expr_symbols(&index.range, used);
             }
@@ -269,7 +316,7 @@ pub fn collect_equation_symbols(eq: &Equation, used: &mut HashSet<String>) {
                 collect_equation_symbols(sub_eq, used);
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
                 collect_expr_symbols(&block.cond, used);
                 for sub_eq in &block.eqs {
@@ -280,6 +327,7 @@ pub fn collect_equation_symbols(eq: &Equation, used: &mut HashSet<String>) {
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_expr_symbols(&block.cond, used);
@@ -293,7 +341,7 @@ pub fn collect_equation_symbols(eq: &Equation, used: &mut HashSet<String>) {
                 }
             }
         }
-        Equation::FunctionCall { comp, args } => {
+        Equation::FunctionCall { comp, args, .. } => {
             collect_comp_ref_symbols(comp, used);
             for arg in args {
                 collect_expr_symbols(arg, used);
@@ -306,17 +354,19 @@ pub fn collect_equation_symbols(eq: &Equation, used: &mut HashSet<String>) {
 pub fn collect_statement_symbols(stmt: &Statement, used: &mut HashSet<String>) {
     match stmt {
         Statement::Empty => {}
-        Statement::Assignment { comp, value } => {
+        Statement::Assignment { comp, value, .. } => {
             collect_comp_ref_symbols(comp, used);
             collect_expr_symbols(value, used);
         }
-        Statement::FunctionCall { comp, args } => {
+        Statement::FunctionCall { comp, args, .. } => {
             collect_comp_ref_symbols(comp, used);
             for arg in args {
                 collect_expr_symbols(arg, used);
             }
         }
-        Statement::For { indices, equations } => {
+        Statement::For {
+            indices, equations, ..
+        } => {
             for index in indices {
                 collect_expr_symbols(&index.range, used);
             }
@@ -333,6 +383,7 @@ pub fn collect_statement_symbols(stmt: &Statement, used: &mut HashSet<String>) {
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_expr_symbols(&block.cond, used);
@@ -8,10 +8,14 @@
 
 use std::collections::HashMap;
 
-use crate::ir::ast::{Equation, Expression, Location, Statement};
+use crate::ir::ast::{
+    ClassDefinition, ComponentReference, Equation, Expression, Location, OpBinary, Statement,
+};
+use crate::ir::transform::constants::is_elementwise_function;
+use crate::ir::visitor::{Visitable, Visitor};
 
-use super::symbols::DefinedSymbol;
-use super::type_inference::{InferredType, infer_expression_type};
+use super::symbols::{DefinedSymbol, collect_defined_symbols};
+use super::type_inference::{InferredType, component_type, infer_expression_type};
 
 /// Severity of a type error
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -161,6 +165,17 @@ pub fn check_statements(
     result
 }
 
+/// Check types within an expression: the conditions of if-expressions, the
+/// operands of relational operators and the array arguments of vectorized calls
+pub fn check_expression(
+    expr: &Expression,
+    defined: &HashMap<String, DefinedSymbol>,
+) -> TypeCheckResult {
+    let mut result = TypeCheckResult::new();
+    check_expression_impl(expr, defined, &mut result);
+    result
+}
+
 /// Internal implementation for checking an equation
 fn check_equation_impl(
     eq: &Equation,
@@ -169,16 +184,19 @@ fn check_equation_impl(
 ) {
     match eq {
         Equation::Empty => {}
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             check_expression_pair(lhs, rhs, defined, result);
         }
         Equation::Connect { .. } => {
             // Connect equations have special typing rules not yet implemented
         }
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices, equations, ..
+        } => {
             // For loop indices are locally defined
             let mut local_defined = defined.clone();
             for index in indices {
+                check_expression_impl(&index.range, defined, result);
                 local_defined.insert(
                     index.ident.text.clone(),
                     DefinedSymbol::loop_index(
@@ -191,23 +209,9 @@ fn check_equation_impl(
                 check_equation_impl(sub_eq, &local_defined, result);
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
-                // Check the condition is Boolean
-                let cond_type = infer_expression_type(&block.cond, defined);
-                if !matches!(
-                    cond_type.base_type(),
-                    InferredType::Boolean | InferredType::Unknown
-                ) && let Some(loc) = block.cond.get_location()
-                {
-                    result.add_error(TypeError::new(
-                        loc.clone(),
-                        InferredType::Boolean,
-                        cond_type,
-                        "When condition must be Boolean".to_string(),
-                        TypeErrorSeverity::Error,
-                    ));
-                }
+                check_condition(&block.cond, "When", defined, result);
                 for sub_eq in &block.eqs {
                     check_equation_impl(sub_eq, defined, result);
                 }
@@ -216,23 +220,10 @@ fn check_equation_impl(
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
-                // Check the condition is Boolean
-                let cond_type = infer_expression_type(&block.cond, defined);
-                if !matches!(
-                    cond_type.base_type(),
-                    InferredType::Boolean | InferredType::Unknown
-                ) && let Some(loc) = block.cond.get_location()
-                {
-                    result.add_error(TypeError::new(
-                        loc.clone(),
-                        InferredType::Boolean,
-                        cond_type,
-                        "If condition must be Boolean".to_string(),
-                        TypeErrorSeverity::Error,
-                    ));
-                }
+                check_condition(&block.cond, "If", defined, result);
                 for sub_eq in &block.eqs {
                     check_equation_impl(sub_eq, defined, result);
                 }
@@ -243,8 +234,11 @@ fn check_equation_impl(
                 }
             }
         }
-        Equation::FunctionCall { .. } => {
-            // Function call equations - argument type checking could be added
+        Equation::FunctionCall { args, .. } => {
+            // Argument types against the function signature are not checked yet
+            for arg in args {
+                check_expression_impl(arg, defined, result);
+            }
         }
     }
 }
@@ -257,12 +251,18 @@ fn check_statement_impl(
 ) {
     match stmt {
         Statement::Empty => {}
-        Statement::Assignment { comp, value } => {
+        Statement::Assignment { comp, value, .. } => {
+            check_expression_impl(value, defined, result);
+            check_record_fields(comp, defined, result);
+
             // Infer the type of the target component
             if let Some(first) = comp.parts.first()
                 && let Some(sym) = defined.get(&first.ident.text)
             {
-                let target_type = super::type_inference::type_from_name(&sym.type_name);
+                let target_type = match comp.parts.len() {
+                    1 => super::type_inference::type_from_name(&sym.type_name),
+                    _ => component_type(comp, defined).base_type().clone(),
+                };
                 let value_type = infer_expression_type(value, defined);
 
                 if !target_type.is_compatible_with(&value_type)
@@ -272,12 +272,18 @@ fn check_statement_impl(
                 }
             }
         }
-        Statement::FunctionCall { .. } => {
-            // Function call statements - argument type checking could be added
+        Statement::FunctionCall { args, .. } => {
+            // Argument types against the function signature are not checked yet
+            for arg in args {
+                check_expression_impl(arg, defined, result);
+            }
         }
-        Statement::For { indices, equations } => {
+        Statement::For {
+            indices, equations, ..
+        } => {
             let mut local_defined = defined.clone();
             for index in indices {
+                check_expression_impl(&index.range, defined, result);
                 local_defined.insert(
                     index.ident.text.clone(),
                     DefinedSymbol::loop_index(
@@ -291,21 +297,7 @@ fn check_statement_impl(
             }
         }
         Statement::While(block) => {
-            // Check the condition is Boolean
-            let cond_type = infer_expression_type(&block.cond, defined);
-            if !matches!(
-                cond_type.base_type(),
-                InferredType::Boolean | InferredType::Unknown
-            ) && let Some(loc) = block.cond.get_location()
-            {
-                result.add_error(TypeError::new(
-                    loc.clone(),
-                    InferredType::Boolean,
-                    cond_type,
-                    "While condition must be Boolean".to_string(),
-                    TypeErrorSeverity::Error,
-                ));
-            }
+            check_condition(&block.cond, "While", defined, result);
             for sub_stmt in &block.stmts {
                 check_statement_impl(sub_stmt, defined, result);
             }
@@ -313,22 +305,10 @@ fn check_statement_impl(
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
-                let cond_type = infer_expression_type(&block.cond, defined);
-                if !matches!(
-                    cond_type.base_type(),
-                    InferredType::Boolean | InferredType::Unknown
-                ) && let Some(loc) = block.cond.get_location()
-                {
-                    result.add_error(TypeError::new(
-                        loc.clone(),
-                        InferredType::Boolean,
-                        cond_type,
-                        "If condition must be Boolean".to_string(),
-                        TypeErrorSeverity::Error,
-                    ));
-                }
+                check_condition(&block.cond, "If", defined, result);
                 for sub_stmt in &block.stmts {
                     check_statement_impl(sub_stmt, defined, result);
                 }
@@ -341,20 +321,7 @@ fn check_statement_impl(
         }
         Statement::When(blocks) => {
             for block in blocks {
-                let cond_type = infer_expression_type(&block.cond, defined);
-                if !matches!(
-                    cond_type.base_type(),
-                    InferredType::Boolean | InferredType::Unknown
-                ) && let Some(loc) = block.cond.get_location()
-                {
-                    result.add_error(TypeError::new(
-                        loc.clone(),
-                        InferredType::Boolean,
-                        cond_type,
-                        "When condition must be Boolean".to_string(),
-                        TypeErrorSeverity::Error,
-                    ));
-                }
+                check_condition(&block.cond, "When", defined, result);
                 for sub_stmt in &block.stmts {
                     check_statement_impl(sub_stmt, defined, result);
                 }
@@ -371,6 +338,9 @@ fn check_expression_pair(
     defined: &HashMap<String, DefinedSymbol>,
     result: &mut TypeCheckResult,
 ) {
+    check_expression_impl(lhs, defined, result);
+    check_expression_impl(rhs, defined, result);
+
     let lhs_type = infer_expression_type(lhs, defined);
     let rhs_type = infer_expression_type(rhs, defined);
 
@@ -394,6 +364,236 @@ fn check_expression_pair(
     }
 }
 
+/// Check the condition of an if, when or while equation or statement, which
+/// must be Boolean
+fn check_condition(
+    cond: &Expression,
+    kind: &str,
+    defined: &HashMap<String, DefinedSymbol>,
+    result: &mut TypeCheckResult,
+) {
+    check_expression_impl(cond, defined, result);
+    check_condition_type(cond, kind, defined, result);
+}
+
+/// Report a condition that is not Boolean
+fn check_condition_type(
+    cond: &Expression,
+    kind: &str,
+    defined: &HashMap<String, DefinedSymbol>,
+    result: &mut TypeCheckResult,
+) {
+    let cond_type = infer_expression_type(cond, defined);
+    if !matches!(
+        cond_type.base_type(),
+        InferredType::Boolean | InferredType::Unknown
+    ) && let Some(loc) = cond.get_location()
+    {
+        result.add_error(TypeError::new(
+            loc.clone(),
+            InferredType::Boolean,
+            cond_type,
+            format!("{} condition must be Boolean", kind),
+            TypeErrorSeverity::Error,
+        ));
+    }
+}
+
+/// Internal implementation for checking an expression and its subexpressions
+fn check_expression_impl(
+    expr: &Expression,
+    defined: &HashMap<String, DefinedSymbol>,
+    result: &mut TypeCheckResult,
+) {
+    let mut checker = ExpressionChecker { defined, result };
+    expr.accept(&mut checker);
+}
+
+/// Visitor reporting type errors within expressions
+struct ExpressionChecker<'a> {
+    defined: &'a HashMap<String, DefinedSymbol>,
+    result: &'a mut TypeCheckResult,
+}
+
+impl Visitor for ExpressionChecker<'_> {
+    fn enter_expression(&mut self, node: &Expression) {
+        match node {
+            Expression::If { branches, .. } => {
+                for (cond, _) in branches {
+                    check_condition_type(cond, "If", self.defined, self.result);
+                }
+            }
+            Expression::Binary { lhs, op, rhs } if is_relational(op) => {
+                self.check_comparison(node, lhs, rhs);
+            }
+            Expression::FunctionCall { comp, args } => {
+                check_vectorized_call(node, comp, args, self.defined, self.result);
+            }
+            Expression::ComponentReference(comp_ref) => {
+                check_record_fields(comp_ref, self.defined, self.result);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Report a reference to a field that its record doesn't have, e.g.
+/// `p.origin.z` for a `Point origin` without `z`
+fn check_record_fields(
+    comp_ref: &ComponentReference,
+    defined: &HashMap<String, DefinedSymbol>,
+    result: &mut TypeCheckResult,
+) {
+    let Some((first, rest)) = comp_ref.parts.split_first() else {
+        return;
+    };
+    let Some(mut symbol) = defined.get(&first.ident.text) else {
+        return;
+    };
+    for part in rest {
+        if symbol.fields.is_empty() {
+            // Not a record, or one whose class wasn't found
+            return;
+        }
+        let Some(field) = symbol.fields.get(&part.ident.text) else {
+            result.add_error(TypeError::new(
+                part.ident.location.clone(),
+                InferredType::Unknown,
+                InferredType::Unknown,
+                format!(
+                    "Record '{}' has no field '{}'",
+                    symbol.type_name, part.ident.text
+                ),
+                TypeErrorSeverity::Error,
+            ));
+            return;
+        };
+        symbol = field;
+    }
+}
+
+impl ExpressionChecker<'_> {
+    /// Check that the operands of a relational operator can be compared
+    ///
+    /// e.g., `(a < b) < c` compares the Boolean `a < b` with `c`.
+    fn check_comparison(&mut self, node: &Expression, lhs: &Expression, rhs: &Expression) {
+        let lhs_type = infer_expression_type(lhs, self.defined);
+        let rhs_type = infer_expression_type(rhs, self.defined);
+        let (a, b) = (lhs_type.base_type(), rhs_type.base_type());
+        let comparable = matches!(a, InferredType::Unknown)
+            || matches!(b, InferredType::Unknown)
+            || (lhs_type.is_numeric() && rhs_type.is_numeric())
+            || a == b;
+        if !comparable && let Some(loc) = node.get_location() {
+            self.result.add_error(TypeError::new(
+                loc.clone(),
+                lhs_type.clone(),
+                rhs_type.clone(),
+                format!("Cannot compare {} with {}", lhs_type, rhs_type),
+                TypeErrorSeverity::Error,
+            ));
+        }
+    }
+}
+
+/// Check that a vectorized call of a scalar function has array arguments of
+/// matching shape
+///
+/// e.g., `atan2(a, b)` with `Real a[3], b[2]` cannot be applied element-wise.
+fn check_vectorized_call(
+    node: &Expression,
+    comp: &ComponentReference,
+    args: &[Expression],
+    defined: &HashMap<String, DefinedSymbol>,
+    result: &mut TypeCheckResult,
+) {
+    let name = comp.to_string();
+    if !is_elementwise_function(&name) {
+        return;
+    }
+
+    let array_args: Vec<InferredType> = args
+        .iter()
+        .map(|arg| infer_expression_type(arg, defined))
+        .filter(|ty| matches!(ty, InferredType::Array(_, _)))
+        .collect();
+    let Some(first) = array_args.first() else {
+        return;
+    };
+    if let Some(other) = array_args
+        .iter()
+        .find(|ty| !same_known_shape(first, ty).unwrap_or(true))
+        && let Some(loc) = node.get_location()
+    {
+        result.add_error(TypeError::new(
+            loc.clone(),
+            first.clone(),
+            other.clone(),
+            format!(
+                "Vectorized call to '{}' has array arguments of different shapes: {} and {}",
+                name, first, other
+            ),
+            TypeErrorSeverity::Error,
+        ));
+    }
+}
+
+/// Check the vectorized calls of a flattened class, in its equations and the
+/// bindings of its components, see [`check_expression`]
+pub fn check_vectorized_calls(class: &ClassDefinition) -> TypeCheckResult {
+    struct Calls<'a> {
+        defined: &'a HashMap<String, DefinedSymbol>,
+        result: TypeCheckResult,
+    }
+    impl Visitor for Calls<'_> {
+        fn enter_expression(&mut self, node: &Expression) {
+            if let Expression::FunctionCall { comp, args } = node {
+                check_vectorized_call(node, comp, args, self.defined, &mut self.result);
+            }
+        }
+    }
+    let defined = collect_defined_symbols(class);
+    let mut calls = Calls {
+        defined: &defined,
+        result: TypeCheckResult::new(),
+    };
+    class.accept(&mut calls);
+    calls.result
+}
+
+/// Whether an operator is a relational operator
+fn is_relational(op: &OpBinary) -> bool {
+    matches!(
+        op,
+        OpBinary::Lt(_)
+            | OpBinary::Le(_)
+            | OpBinary::Gt(_)
+            | OpBinary::Ge(_)
+            | OpBinary::Eq(_)
+            | OpBinary::Neq(_)
+    )
+}
+
+/// Compare the dimensions of two array types.
+/// Returns `None` if either shape is not statically known.
+fn same_known_shape(a: &InferredType, b: &InferredType) -> Option<bool> {
+    match (a, b) {
+        (InferredType::Array(inner_a, size_a), InferredType::Array(inner_b, size_b)) => {
+            if size_a.zip(*size_b).is_some_and(|(x, y)| x != y) {
+                return Some(false);
+            }
+            let inner = same_known_shape(inner_a, inner_b);
+            if size_a.is_none() || size_b.is_none() {
+                // Unknown outer size - only a known inner mismatch is conclusive
+                return inner.filter(|same| !same);
+            }
+            inner
+        }
+        (InferredType::Array(_, _), _) | (_, InferredType::Array(_, _)) => Some(false),
+        _ => Some(true),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -423,4 +623,152 @@ mod tests {
         ));
         assert!(result.has_errors());
     }
+
+    fn check_model_equations(source: &str) -> TypeCheckResult {
+        let def = crate::parse_source_simple(source, "test.mo").unwrap();
+        let class = def.class_list.values().next().unwrap();
+        let defined = super::super::symbols::collect_defined_symbols(class);
+        check_equations(&class.equations, &defined)
+    }
+
+    #[test]
+    fn test_vectorized_call_shapes() {
+        let result = check_model_equations(
+            r#"
+model M
+  Real x[3];
+  Real y[3];
+  Real z[3];
+equation
+  y = sin(x);
+  z = atan2(x, y);
+end M;
+"#,
+        );
+        assert!(!result.has_issues(), "{:?}", result.errors);
+
+        let result = check_model_equations(
+            r#"
+model M
+  Real x[3];
+  Real w[2];
+  Real z[3];
+equation
+  z = atan2(x, w);
+end M;
+"#,
+        );
+        assert!(result.has_errors());
+        assert!(
+            result.errors[0].message.contains("Real[3] and Real[2]"),
+            "{}",
+            result.errors[0].message
+        );
+    }
+
+    #[test]
+    fn test_condition_types() {
+        let result = check_model_equations(
+            r#"
+model M
+  Real x;
+  Real y;
+  Boolean b;
+equation
+  y = if x > 0 and b then 1 else 2;
+  b = (x > 1) == true;
+  x = time;
+end M;
+"#,
+        );
+        assert!(!result.has_issues(), "{:?}", result.errors);
+
+        let result = check_model_equations(
+            r#"
+model M
+  Real x;
+  Real y;
+  Real z;
+equation
+  y = if x then 1 else 2;
+  z = if (0 < x) < 1 then 1 else 0;
+  x = time;
+end M;
+"#,
+        );
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "If condition must be Boolean",
+                "Cannot compare Boolean with Integer"
+            ]
+        );
+        assert_eq!(result.errors[0].location.start_line, 7);
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let result = check_model_equations(
+            r#"
+model M
+  record Point
+    Real x;
+    Boolean visible;
+  end Point;
+  record Frame
+    Point origin;
+    Point corners[2];
+  end Frame;
+  Frame p;
+  Real y;
+  Boolean b;
+equation
+  y = p.origin.x + p.corners[2].x;
+  b = p.origin.visible;
+  y = p.origin.visible;
+  y = p.origin.z;
+end M;
+"#,
+        );
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Cannot mix Boolean and numeric types in equation",
+                "Record 'Point' has no field 'z'"
+            ]
+        );
+        assert_eq!(result.errors[1].location.start_line, 18);
+    }
+
+    #[test]
+    fn test_statement_condition_types() {
+        let def = crate::parse_source_simple(
+            r#"
+model M
+  Real x;
+  Real y;
+algorithm
+  y := if x then 1 else 0;
+  while x loop
+    y := 1;
+  end while;
+end M;
+"#,
+            "test.mo",
+        )
+        .unwrap();
+        let class = &def.class_list["M"];
+        let defined = super::super::symbols::collect_defined_symbols(class);
+        let result = check_statements(&class.algorithms[0], &defined);
+        let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "If condition must be Boolean",
+                "While condition must be Boolean"
+            ]
+        );
+    }
 }
@@ -5,9 +5,10 @@
 
 use std::collections::HashMap;
 
-use crate::ir::ast::{Expression, OpBinary, TerminalType};
+use crate::ir::ast::{ComponentReference, Expression, OpBinary, TerminalType};
 
 use super::symbols::DefinedSymbol;
+use crate::ir::transform::constants::is_elementwise_function;
 
 /// Inferred type for an expression.
 ///
@@ -32,6 +33,16 @@ impl InferredType {
         }
     }
 
+    /// Replace the base (scalar) type, keeping array dimensions
+    pub fn with_base_type(&self, base: InferredType) -> InferredType {
+        match self {
+            InferredType::Array(inner, size) => {
+                InferredType::Array(Box::new(inner.with_base_type(base)), *size)
+            }
+            _ => base,
+        }
+    }
+
     /// Check if this is a numeric type (Real or Integer)
     pub fn is_numeric(&self) -> bool {
         matches!(self.base_type(), InferredType::Real | InferredType::Integer)
@@ -93,43 +104,7 @@ pub fn infer_expression_type(
 ) -> InferredType {
     match expr {
         Expression::Empty => InferredType::Unknown,
-        Expression::ComponentReference(comp_ref) => {
-            if let Some(first) = comp_ref.parts.first() {
-                if let Some(sym) = defined.get(&first.ident.text) {
-                    let base = type_from_name(&sym.type_name);
-                    if sym.shape.is_empty() {
-                        base
-                    } else {
-                        // Build array type from innermost to outermost
-                        let mut result = base;
-                        for &dim in sym.shape.iter().rev() {
-                            result = InferredType::Array(Box::new(result), Some(dim));
-                        }
-                        // Account for subscripts - each index reduces one dimension
-                        // e.g., q[3] where q is Real[4] becomes Real (scalar)
-                        // e.g., R[1,2] where R is Real[3,3] becomes Real (scalar)
-                        if let Some(subs) = &first.subs {
-                            for _sub in subs {
-                                // Each subscript strips one array dimension
-                                if let InferredType::Array(inner, _) = result {
-                                    result = *inner;
-                                }
-                            }
-                        }
-                        result
-                    }
-                } else {
-                    // Check if it's 'time' (global Real)
-                    if first.ident.text == "time" {
-                        InferredType::Real
-                    } else {
-                        InferredType::Unknown
-                    }
-                }
-            } else {
-                InferredType::Unknown
-            }
-        }
+        Expression::ComponentReference(comp_ref) => component_type(comp_ref, defined),
         Expression::Terminal {
             terminal_type,
             token: _,
@@ -172,6 +147,7 @@ pub fn infer_expression_type(
             InferredType::Array(Box::new(InferredType::Integer), None)
         }
         Expression::Parenthesized { inner } => infer_expression_type(inner, defined),
+        Expression::NamedArgument { value, .. } => infer_expression_type(value, defined),
         Expression::ArrayComprehension { expr, .. } => {
             // Array comprehension produces an array of the expression type
             let elem_type = infer_expression_type(expr, defined);
@@ -180,6 +156,44 @@ pub fn infer_expression_type(
     }
 }
 
+/// Infer the type of a component reference, following record fields as in
+/// `p.origin.x`
+///
+/// Each subscript strips one array dimension of its part, e.g. `q[3]` is a
+/// Real for `Real q[4]`, and `pts[1].x` is a Real for `Point pts[2]`.
+pub fn component_type(
+    comp_ref: &ComponentReference,
+    defined: &HashMap<String, DefinedSymbol>,
+) -> InferredType {
+    let Some(first) = comp_ref.parts.first() else {
+        return InferredType::Unknown;
+    };
+    if comp_ref.parts.len() == 1 && first.ident.text == "time" && !defined.contains_key("time") {
+        return InferredType::Real;
+    }
+
+    let Some(mut symbol) = defined.get(&first.ident.text) else {
+        return InferredType::Unknown;
+    };
+    // Dimensions left after the subscripts of each part, outermost first
+    let mut dims = Vec::new();
+    for (i, part) in comp_ref.parts.iter().enumerate() {
+        if i > 0 {
+            let Some(field) = symbol.fields.get(&part.ident.text) else {
+                return InferredType::Unknown;
+            };
+            symbol = field;
+        }
+        let subscripts = part.subs.as_ref().map_or(0, |subs| subs.len());
+        dims.extend(symbol.shape.iter().skip(subscripts).copied());
+    }
+    dims.iter()
+        .rev()
+        .fold(type_from_name(&symbol.type_name), |inner, &dim| {
+            InferredType::Array(Box::new(inner), Some(dim))
+        })
+}
+
 /// Infer the return type of a function call
 fn infer_function_call_type(
     comp: &crate::ir::ast::ComponentReference,
@@ -188,13 +202,11 @@ fn infer_function_call_type(
 ) -> InferredType {
     if let Some(first) = comp.parts.first() {
         match first.ident.text.as_str() {
-            // Trigonometric and math functions return Real
-            "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "sinh" | "cosh"
-            | "tanh" | "exp" | "log" | "log10" | "sqrt" | "abs" | "sign" | "floor" | "ceil"
-            | "mod" | "rem" | "max" | "min" | "sum" | "product" => InferredType::Real,
+            // Reductions return a Real scalar
+            "max" | "min" | "sum" | "product" => InferredType::Real,
 
             // der returns the same type as its argument (preserves array dimensions)
-            "der" | "pre" | "delay" => {
+            "der" | "pre" | "delay" | "noEvent" => {
                 if let Some(arg) = args.first() {
                     infer_expression_type(arg, defined)
                 } else {
@@ -202,6 +214,12 @@ fn infer_function_call_type(
                 }
             }
 
+            // Scalar math functions are vectorized: called with array arguments
+            // they are applied element-wise and return an array of the same shape
+            name if is_elementwise_function(name) => {
+                infer_vectorized_call_type(args, defined).unwrap_or(InferredType::Real)
+            }
+
             // cross(a, b) returns a 3-vector
             "cross" => InferredType::Array(Box::new(InferredType::Real), Some(3)),
 
@@ -230,6 +248,8 @@ fn infer_function_call_type(
             // Boolean functions
             "initial" | "terminal" | "edge" | "change" | "sample" => InferredType::Boolean,
 
+            "random" | "table1D" | "table2D" => InferredType::Real,
+
             // Size returns Integer
             "size" | "ndims" => InferredType::Integer,
 
@@ -260,6 +280,20 @@ fn infer_function_call_type(
     }
 }
 
+/// Infer the shape of a vectorized (element-wise) call of a scalar function.
+///
+/// Returns the type of the first array argument with its element type replaced
+/// by Real, or `None` if all arguments are scalars.
+pub fn infer_vectorized_call_type(
+    args: &[Expression],
+    defined: &HashMap<String, DefinedSymbol>,
+) -> Option<InferredType> {
+    args.iter()
+        .map(|arg| infer_expression_type(arg, defined))
+        .find(|ty| matches!(ty, InferredType::Array(_, _)))
+        .map(|ty| ty.with_base_type(InferredType::Real))
+}
+
 /// Infer the result type of a binary operation
 fn infer_binary_op_type(
     op: &OpBinary,
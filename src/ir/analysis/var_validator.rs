@@ -4,7 +4,10 @@
 //! correspond to declared components using a SymbolTable.
 
 use crate::ir::analysis::symbol_table::SymbolTable;
-use crate::ir::ast::{ClassDefinition, ComponentReference, Expression, Import, Variability};
+use crate::ir::ast::{
+    ClassDefinition, ComponentReference, Equation, Expression, ForIndex, Import, Statement,
+    Variability,
+};
 use crate::ir::visitor::MutVisitor;
 use std::collections::HashSet;
 
@@ -16,6 +19,8 @@ pub struct VarValidator {
     imported_packages: HashSet<String>,
     /// Undefined variables found
     pub undefined_vars: Vec<(String, String)>, // (var_name, context)
+    /// Indices of the enclosing for-loops (innermost last)
+    loop_indices: Vec<String>,
 }
 
 impl VarValidator {
@@ -45,6 +50,7 @@ impl VarValidator {
             symbol_table,
             imported_packages,
             undefined_vars: Vec::new(),
+            loop_indices: Vec::new(),
         }
     }
 
@@ -102,7 +108,9 @@ impl VarValidator {
             // 2. The full qualified name is in the symbol table (e.g., "D.x_start")
             // 3. The first part is an imported package root (e.g., "Modelica")
             // 4. There's a component that starts with this prefix (e.g., "D" when "D.x" exists)
+            // 5. It is the index of an enclosing for-loop
             if self.symbol_table.contains(first_name)
+                || self.loop_indices.contains(first_name)
                 || self.symbol_table.contains(&full_name)
                 || self.imported_packages.contains(first_name)
                 || self.symbol_table.has_prefix(first_name)
@@ -116,7 +124,43 @@ impl VarValidator {
     }
 }
 
+impl VarValidator {
+    fn enter_loop(&mut self, indices: &[ForIndex]) {
+        self.loop_indices
+            .extend(indices.iter().map(|index| index.ident.text.clone()));
+    }
+
+    fn exit_loop(&mut self, indices: &[ForIndex]) {
+        let len = self.loop_indices.len().saturating_sub(indices.len());
+        self.loop_indices.truncate(len);
+    }
+}
+
 impl MutVisitor for VarValidator {
+    fn enter_equation(&mut self, eq: &mut Equation) {
+        if let Equation::For { indices, .. } = eq {
+            self.enter_loop(indices);
+        }
+    }
+
+    fn exit_equation(&mut self, eq: &mut Equation) {
+        if let Equation::For { indices, .. } = eq {
+            self.exit_loop(indices);
+        }
+    }
+
+    fn enter_statement(&mut self, stmt: &mut Statement) {
+        if let Statement::For { indices, .. } = stmt {
+            self.enter_loop(indices);
+        }
+    }
+
+    fn exit_statement(&mut self, stmt: &mut Statement) {
+        if let Statement::For { indices, .. } = stmt {
+            self.exit_loop(indices);
+        }
+    }
+
     fn enter_expression(&mut self, expr: &mut Expression) {
         match expr {
             Expression::ComponentReference(comp_ref) => {
@@ -0,0 +1,280 @@
+//! Checks of when-equations and when-statements (Modelica spec §8.3.5).
+//!
+//! The branches of a when-equation are tried in order at an event, and only
+//! the first whose condition becomes true is activated, so the variables it
+//! defines must be the same in all branches:
+//!
+//! ```modelica
+//! when x > 1 then
+//!   y = 1;
+//! elsewhen x < 0 then
+//!   y = 2;        // ok
+//!   z = 0;        // error: z isn't assigned in the when-branch
+//! end when;
+//! ```
+//!
+//! `reinit` and the other calls assign no variable. When-equations and
+//! when-statements can't be nested, in any branch of an if-equation,
+//! for-equation or statement of the outer one.
+
+use std::fmt;
+
+use indexmap::IndexSet;
+
+use crate::ir::ast::{ClassDefinition, Equation, EquationBlock, Expression, Location, Statement};
+
+/// A when-equation or when-statement violating the restrictions of the spec
+#[derive(Debug, Clone, PartialEq)]
+pub enum WhenError {
+    /// A when-equation or when-statement inside another one
+    Nested { location: Location },
+    /// An elsewhen-branch not assigning the variables of the when-branch
+    BranchVariables {
+        location: Location,
+        /// Variables of the when-branch the elsewhen-branch doesn't assign
+        missing: Vec<String>,
+        /// Variables only the elsewhen-branch assigns
+        extra: Vec<String>,
+    },
+}
+
+impl WhenError {
+    /// Location of the nested when, or of the condition of the elsewhen-branch
+    pub fn location(&self) -> &Location {
+        match self {
+            WhenError::Nested { location } | WhenError::BranchVariables { location, .. } => {
+                location
+            }
+        }
+    }
+
+    /// Description of the error, without its location
+    pub fn message(&self) -> String {
+        match self {
+            WhenError::Nested { .. } => {
+                "when-equations and when-statements cannot be nested".to_string()
+            }
+            WhenError::BranchVariables { missing, extra, .. } => {
+                let quoted = |names: &[String]| {
+                    names
+                        .iter()
+                        .map(|name| format!("'{}'", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let mut problems = Vec::new();
+                if !missing.is_empty() {
+                    problems.push(format!("doesn't assign {}", quoted(missing)));
+                }
+                if !extra.is_empty() {
+                    problems.push(format!(
+                        "assigns {}, which the when-branch doesn't",
+                        quoted(extra)
+                    ));
+                }
+                format!(
+                    "the branches of a when-equation must assign the same variables, but this elsewhen-branch {}",
+                    problems.join(" and ")
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for WhenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location().file_position(), self.message())
+    }
+}
+
+/// Check the when-equations and when-statements of a class, see the
+/// [module docs](self)
+pub fn check_when_equations(class: &ClassDefinition) -> Vec<WhenError> {
+    let mut errors = Vec::new();
+    for eq in &class.equations {
+        check_equation(eq, false, &mut errors);
+    }
+    for stmt in class.algorithms.iter().flatten() {
+        check_statement(stmt, false, &mut errors);
+    }
+    errors
+}
+
+fn check_equation(eq: &Equation, in_when: bool, errors: &mut Vec<WhenError>) {
+    match eq {
+        Equation::When { blocks, .. } => {
+            if in_when {
+                errors.push(WhenError::Nested {
+                    location: eq.get_location().cloned().unwrap_or_default(),
+                });
+            }
+            check_branch_variables(blocks, errors);
+            for block in blocks {
+                for eq in &block.eqs {
+                    check_equation(eq, true, errors);
+                }
+            }
+        }
+        Equation::For { equations, .. } => {
+            for eq in equations {
+                check_equation(eq, in_when, errors);
+            }
+        }
+        Equation::If {
+            cond_blocks,
+            else_block,
+            ..
+        } => {
+            for eq in cond_blocks
+                .iter()
+                .flat_map(|block| &block.eqs)
+                .chain(else_block.iter().flatten())
+            {
+                check_equation(eq, in_when, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_statement(stmt: &Statement, in_when: bool, errors: &mut Vec<WhenError>) {
+    match stmt {
+        Statement::When(blocks) => {
+            if in_when {
+                errors.push(WhenError::Nested {
+                    location: stmt.get_location().cloned().unwrap_or_default(),
+                });
+            }
+            for stmt in blocks.iter().flat_map(|block| &block.stmts) {
+                check_statement(stmt, true, errors);
+            }
+        }
+        Statement::For { equations, .. } => {
+            for stmt in equations {
+                check_statement(stmt, in_when, errors);
+            }
+        }
+        Statement::While(block) => {
+            for stmt in &block.stmts {
+                check_statement(stmt, in_when, errors);
+            }
+        }
+        Statement::If {
+            cond_blocks,
+            else_block,
+            ..
+        } => {
+            for stmt in cond_blocks
+                .iter()
+                .flat_map(|block| &block.stmts)
+                .chain(else_block.iter().flatten())
+            {
+                check_statement(stmt, in_when, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compare the variables assigned by each elsewhen-branch to the when-branch
+fn check_branch_variables(blocks: &[EquationBlock], errors: &mut Vec<WhenError>) {
+    let Some((first, rest)) = blocks.split_first() else {
+        return;
+    };
+    let expected = assigned_variables(&first.eqs);
+    for block in rest {
+        let assigned = assigned_variables(&block.eqs);
+        let missing: Vec<String> = expected.difference(&assigned).cloned().collect();
+        let extra: Vec<String> = assigned.difference(&expected).cloned().collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            errors.push(WhenError::BranchVariables {
+                location: block.cond.get_location().cloned().unwrap_or_default(),
+                missing,
+                extra,
+            });
+        }
+    }
+}
+
+/// Variables assigned by the equations of a branch, as written
+fn assigned_variables(eqs: &[Equation]) -> IndexSet<String> {
+    let mut names = IndexSet::new();
+    for eq in eqs {
+        match eq {
+            Equation::Simple { lhs, .. } => match lhs {
+                Expression::ComponentReference(cref) => {
+                    names.insert(cref.to_string());
+                }
+                Expression::Tuple { elements } => {
+                    for element in elements {
+                        if let Expression::ComponentReference(cref) = element {
+                            names.insert(cref.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Equation::For { equations, .. } => names.extend(assigned_variables(equations)),
+            Equation::If {
+                cond_blocks,
+                else_block,
+                ..
+            } => {
+                for block in cond_blocks {
+                    names.extend(assigned_variables(&block.eqs));
+                }
+                if let Some(eqs) = else_block {
+                    names.extend(assigned_variables(eqs));
+                }
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    fn compile(equations: &str) -> Result<crate::CompilationResult, crate::Error> {
+        let source = format!(
+            "model M\n  Real x(start = 1);\n  discrete Real y;\n  discrete Real z;\nequation\n  der(x) = -x;\n{}end M;\n",
+            equations
+        );
+        Compiler::new().model("M").compile_str(&source, "m.mo")
+    }
+
+    #[test]
+    fn test_when_branches() {
+        assert!(
+            compile(
+                "  when x < 0.5 then\n    y = 1;\n    z = 1;\n  elsewhen x < 0.2 then\n    z = 2;\n    y = 2;\n  end when;\n"
+            )
+            .is_ok()
+        );
+
+        let err = compile(
+            "  when x < 0.5 then\n    y = 1;\n    z = 1;\n  elsewhen x < 0.2 then\n    y = 2;\n  end when;\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "m.mo:10:12: the branches of a when-equation must assign the same variables, but this elsewhen-branch doesn't assign 'z'"
+        );
+    }
+
+    #[test]
+    fn test_nested_when() {
+        let err = compile(
+            "  when x < 0.5 then\n    y = 1;\n    when x < 0.2 then\n      z = 2;\n    end when;\n  end when;\n",
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("m.mo:9:10: when-equations and when-statements cannot be nested"),
+            "{}",
+            err
+        );
+    }
+}
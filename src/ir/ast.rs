@@ -39,7 +39,7 @@
 //!
 //! This module is designed to be extensible and serves as the foundation for parsing,
 //! analyzing, and generating code for the custom language or model representation.
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, fmt::Display};
 
@@ -54,6 +54,16 @@ pub struct Location {
     pub file_name: String,
 }
 
+impl Location {
+    /// `file:line:column` of the start, e.g. `model.mo:12:5`
+    pub fn file_position(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.file_name, self.start_line, self.start_column
+        )
+    }
+}
+
 #[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct Token {
@@ -130,8 +140,12 @@ pub struct Component {
     /// Annotation arguments (e.g., from `annotation(Icon(...), Dialog(...))`)
     pub annotation: Vec<Expression>,
     /// Component modifications (e.g., R=10 in `Resistor R1(R=10)`)
-    /// Maps parameter name to its modified value expression
+    /// Maps parameter name to its modified value expression. Nested modifications
+    /// are keyed by dotted path: `motor(inertia(J=2))` stores `inertia.J`.
     pub modifications: IndexMap<String, Expression>,
+    /// `each`/`final` prefixes of entries in `modifications` (and of `start`),
+    /// keyed like `modifications`; modifications without prefixes have no entry
+    pub modification_prefixes: IndexMap<String, ModificationPrefix>,
     /// Full source location for the component declaration
     pub location: Location,
     /// Conditional component expression (e.g., `if use_reset` in `BooleanInput reset if use_reset`)
@@ -141,6 +155,32 @@ pub struct Component {
     pub inner: bool,
     /// True if declared with 'outer' prefix (references an inner instance from enclosing scope)
     pub outer: bool,
+    /// True if declared with 'final' prefix, or fixed by a `final` modification
+    /// (the element can no longer be modified)
+    pub r#final: bool,
+    /// True if declared `replaceable` (a redeclaration may change its type)
+    pub replaceable: bool,
+    /// True if declared with `redeclare`, replacing an inherited replaceable component
+    pub redeclare: bool,
+    /// Type of the `constrainedby` clause of a replaceable component. Without
+    /// one, redeclarations are constrained by the declared type.
+    pub constrainedby: Option<Name>,
+}
+
+impl Component {
+    /// The binding of the declaration, as in `input Real eps = 1e-6;`, which
+    /// for a function input is its default argument
+    ///
+    /// None for `start` modifications and for the default start value the
+    /// parser gives declarations without a binding.
+    pub fn binding(&self) -> Option<&Expression> {
+        match &self.start {
+            _ if self.start_is_modification => None,
+            Expression::Empty => None,
+            Expression::Terminal { token, .. } if token.location.start_line == 0 => None,
+            binding => Some(binding),
+        }
+    }
 }
 
 impl Debug for Component {
@@ -173,6 +213,9 @@ impl Debug for Component {
         if !self.modifications.is_empty() {
             builder.field("modifications", &self.modifications);
         }
+        if !self.modification_prefixes.is_empty() {
+            builder.field("modification_prefixes", &self.modification_prefixes);
+        }
         if self.condition.is_some() {
             builder.field("condition", &self.condition);
         }
@@ -182,10 +225,38 @@ impl Debug for Component {
         if self.outer {
             builder.field("outer", &self.outer);
         }
+        if self.r#final {
+            builder.field("final", &self.r#final);
+        }
+        if self.replaceable {
+            builder.field("replaceable", &self.replaceable);
+        }
+        if self.redeclare {
+            builder.field("redeclare", &self.redeclare);
+        }
+        if self.constrainedby.is_some() {
+            builder.field("constrainedby", &self.constrainedby);
+        }
         builder.finish()
     }
 }
 
+/// Prefixes of an element modification, e.g. `each start = 0` or `final J = 1`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModificationPrefix {
+    /// `each`: the value applies to every element of an array component
+    pub each: bool,
+    /// `final`: the value cannot be modified further by enclosing modifications
+    pub r#final: bool,
+}
+
+impl ModificationPrefix {
+    /// True if neither `each` nor `final` is set
+    pub fn is_empty(&self) -> bool {
+        !self.each && !self.r#final
+    }
+}
+
 /// Type of class (model, function, connector, etc.)
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ClassType {
@@ -201,6 +272,38 @@ pub enum ClassType {
     Operator,
 }
 
+/// Purity prefix of a function, `pure` or `impure`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Purity {
+    /// No prefix: pure, unless the function is external
+    #[default]
+    Unspecified,
+    Pure,
+    Impure,
+}
+
+/// Kind of an equation or algorithm section
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SectionKind {
+    #[default]
+    Equation,
+    InitialEquation,
+    Algorithm,
+    InitialAlgorithm,
+}
+
+/// Source ranges of an equation or algorithm section of a class
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub kind: SectionKind,
+    /// From the `initial`, `equation` or `algorithm` keyword to the semicolon
+    /// of the last equation or statement
+    pub location: Location,
+    /// Each equation or statement of the section, from its first token to
+    /// its semicolon
+    pub items: Vec<Location>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct ClassDefinition {
@@ -211,13 +314,21 @@ pub struct ClassDefinition {
     pub encapsulated: bool,
     /// True if the class is declared with the `partial` keyword
     pub partial: bool,
+    /// Purity prefix of a function, see [`ClassDefinition::is_pure`]
+    pub purity: Purity,
+    /// True if the function has an `external` clause
+    pub external: bool,
     /// Causality from type alias definition (e.g., `connector RealInput = input Real`)
     /// Components of this type inherit this causality
     pub causality: Causality,
     /// Description string for this class (e.g., "A test model")
     pub description: Vec<Token>,
-    /// Full source location spanning from class keyword to end statement
+    /// Source location spanning from the class name to the name after `end`
     pub location: Location,
+    /// Source range of the whole definition, from its first prefix (e.g.
+    /// `partial` or the class keyword) to the name after `end`, or to the
+    /// class name for short class definitions
+    pub span: Location,
     pub extends: Vec<Extend>,
     pub imports: Vec<Import>,
     /// Nested class definitions (functions, models, packages, etc.)
@@ -235,12 +346,57 @@ pub struct ClassDefinition {
     pub algorithm_keyword: Option<Token>,
     /// Token for "initial algorithm" keyword (if present)
     pub initial_algorithm_keyword: Option<Token>,
+    /// Source ranges of the equation and algorithm sections, in source order
+    pub sections: Vec<Section>,
     /// Token for the class name in "end ClassName;" (for rename support)
     pub end_name_token: Option<Token>,
     /// Enumeration literals for enum types (e.g., `type MyEnum = enumeration(A, B, C)`)
     pub enum_literals: Vec<Token>,
     /// Annotation clause for this class (e.g., Documentation, Icon, Diagram)
     pub annotation: Vec<Expression>,
+    /// Right-hand side of a short class definition (e.g., `type Vec = Real[3]`).
+    /// Short classes also get an extends clause for the base type so that
+    /// inheritance-based lookups keep working.
+    pub short_class: Option<ShortClassSpecifier>,
+    /// True if declared `replaceable` (a redeclaration may replace the class)
+    pub replaceable: bool,
+    /// True if declared with `redeclare`, replacing an inherited replaceable class
+    pub redeclare: bool,
+    /// Type of the `constrainedby` clause of a replaceable class. Without one,
+    /// redeclarations are constrained by the class itself.
+    pub constrainedby: Option<Name>,
+    /// Inputs of sub-models and blocks in a flattened class, e.g. `gain.u`.
+    /// They keep their causality, but are unknowns of the flattened model,
+    /// given by its connections and equations (only the inputs of the class
+    /// itself and of its connectors are inputs of the model).
+    pub internal_inputs: IndexSet<String>,
+}
+
+impl ClassDefinition {
+    /// Whether a function is pure: declared `pure`, or without prefix and
+    /// not external, as external functions may have side effects
+    pub fn is_pure(&self) -> bool {
+        match self.purity {
+            Purity::Pure => true,
+            Purity::Impure => false,
+            Purity::Unspecified => !self.external,
+        }
+    }
+}
+
+/// Right-hand side of a short class definition, e.g. `type Vec = input Real[3](unit="m")`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortClassSpecifier {
+    /// Base type name (e.g., `Real` or `Modelica.Units.SI.Voltage`)
+    pub base_type: Name,
+    /// Array dimensions as written (e.g., `[3]` or `[n]`)
+    pub shape_expr: Vec<Subscript>,
+    /// Array dimensions that are integer literals, in declaration order
+    pub shape: Vec<usize>,
+    /// Modifications of the base type (e.g., `unit="m"`)
+    pub modifications: Vec<Expression>,
+    /// `each`/`final` prefixes of the modifications, keyed by dotted path
+    pub modification_prefixes: IndexMap<String, ModificationPrefix>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -251,6 +407,9 @@ pub struct Extend {
     pub location: Location,
     /// Modifications applied to the extends clause (e.g., extends Foo(bar=1))
     pub modifications: Vec<Expression>,
+    /// `each`/`final` prefixes of the modifications, keyed by dotted path
+    /// (e.g. `extends Foo(final bar=1)` records `bar`)
+    pub modification_prefixes: IndexMap<String, ModificationPrefix>,
 }
 
 /// Import clause for bringing names into scope
@@ -332,11 +491,19 @@ pub struct ComponentReference {
     pub parts: Vec<ComponentRefPart>,
 }
 
+/// Renders subscripts like the names of scalarized array elements, e.g.
+/// `x[1,2]` or `a[i + 1].b`, so that references to elements match them
 impl Display for ComponentReference {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut s = Vec::new();
         for part in &self.parts {
-            s.push(format!("{:?}", part));
+            match &part.subs {
+                Some(subs) => {
+                    let subs: Vec<String> = subs.iter().map(|sub| sub.to_string()).collect();
+                    s.push(format!("{}[{}]", part.ident.text, subs.join(",")));
+                }
+                None => s.push(part.ident.text.clone()),
+            }
         }
         write!(f, "{}", s.join("."))
     }
@@ -357,6 +524,15 @@ impl ComponentReference {
     pub fn get_location(&self) -> Option<&Location> {
         self.parts.first().map(|part| &part.ident.location)
     }
+
+    /// Dotted name of the reference, without subscripts (`a[1].b` -> `a.b`)
+    pub fn unsubscripted_name(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| part.ident.text.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -388,23 +564,33 @@ pub enum Equation {
     Simple {
         lhs: Expression,
         rhs: Expression,
+        /// Description string, e.g. `x = y "position constraint";`
+        description: Vec<Token>,
     },
     Connect {
         lhs: ComponentReference,
         rhs: ComponentReference,
+        description: Vec<Token>,
     },
     For {
         indices: Vec<ForIndex>,
         equations: Vec<Equation>,
+        description: Vec<Token>,
+    },
+    /// When-equation: when cond then eqs elsewhen cond2 then eqs2
+    When {
+        blocks: Vec<EquationBlock>,
+        description: Vec<Token>,
     },
-    When(Vec<EquationBlock>),
     If {
         cond_blocks: Vec<EquationBlock>,
         else_block: Option<Vec<Equation>>,
+        description: Vec<Token>,
     },
     FunctionCall {
         comp: ComponentReference,
         args: Vec<Expression>,
+        description: Vec<Token>,
     },
 }
 
@@ -417,13 +603,76 @@ impl Equation {
             Equation::Simple { lhs, .. } => lhs.get_location(),
             Equation::Connect { lhs, .. } => lhs.get_location(),
             Equation::For { indices, .. } => indices.first().map(|i| &i.ident.location),
-            Equation::When(blocks) => blocks.first().and_then(|b| b.cond.get_location()),
+            Equation::When { blocks, .. } => blocks.first().and_then(|b| b.cond.get_location()),
             Equation::If { cond_blocks, .. } => {
                 cond_blocks.first().and_then(|b| b.cond.get_location())
             }
             Equation::FunctionCall { comp, .. } => comp.get_location(),
         }
     }
+
+    /// Text of the description string, without quotes, or None if there is
+    /// none
+    pub fn description(&self) -> Option<String> {
+        match self {
+            Equation::Simple { description, .. }
+            | Equation::Connect { description, .. }
+            | Equation::For { description, .. }
+            | Equation::If { description, .. }
+            | Equation::When { description, .. }
+            | Equation::FunctionCall { description, .. } => description_text(description),
+            Equation::Empty => None,
+        }
+    }
+}
+
+/// Text of a description string, concatenating its parts without their
+/// delimiting quotes and with their escape sequences replaced
+fn description_text(description: &[Token]) -> Option<String> {
+    if description.is_empty() {
+        return None;
+    }
+    Some(
+        description
+            .iter()
+            .map(|t| unescape_string(unquote_string(&t.text)))
+            .collect(),
+    )
+}
+
+/// A string literal without its delimiting quotes, one on each side. The
+/// parser already removes them from string tokens, whose text then can't
+/// start with an (unescaped) quote.
+fn unquote_string(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+/// The text of a string literal with its escape sequences (`\"`, `\n`, ...)
+/// replaced by the characters they stand for (Modelica spec §2.4.6)
+fn unescape_string(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('a') => unescaped.push('\u{07}'),
+            Some('b') => unescaped.push('\u{08}'),
+            Some('f') => unescaped.push('\u{0c}'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('v') => unescaped.push('\u{0b}'),
+            // \' \" \? \\ stand for the character itself
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
@@ -525,6 +774,14 @@ pub enum Expression {
         expr: Box<Expression>,
         indices: Vec<ForIndex>,
     },
+    /// Named argument of a function call: `f(x, eps = 1e-6)`
+    ///
+    /// The name is the input of the function it binds to, which isn't a
+    /// reference to a variable in the scope of the call.
+    NamedArgument {
+        name: Token,
+        value: Box<Expression>,
+    },
 }
 
 impl Debug for Expression {
@@ -586,6 +843,9 @@ impl Debug for Expression {
             Expression::ArrayComprehension { expr, indices } => {
                 write!(f, "{{{{ {:?} for {:?} }}}}", expr, indices)
             }
+            Expression::NamedArgument { name, value } => {
+                write!(f, "{} = {:?}", name.text, value)
+            }
         }
     }
 }
@@ -613,10 +873,57 @@ impl Expression {
             }
             Expression::Parenthesized { inner } => inner.get_location(),
             Expression::ArrayComprehension { expr, .. } => expr.get_location(),
+            Expression::NamedArgument { name, .. } => Some(&name.location),
+        }
+    }
+
+    /// Flatten a modification argument into `(path, value)` pairs, descending into
+    /// nested class modifications: `inertia(J = 2, w(start = 0))` gives
+    /// `inertia.J = 2` and `inertia.w.start = 0`.
+    ///
+    /// The returned references keep the source tokens of every path segment.
+    pub fn modification_pairs(&self) -> Vec<(ComponentReference, Expression)> {
+        match self {
+            Expression::Binary {
+                op: OpBinary::Assign(_),
+                lhs,
+                rhs,
+            } => match &**lhs {
+                Expression::ComponentReference(comp) => vec![(comp.clone(), (**rhs).clone())],
+                Expression::FunctionCall { comp, args } => {
+                    let mut pairs = vec![(comp.clone(), (**rhs).clone())];
+                    pairs.extend(nested_modification_pairs(comp, args));
+                    pairs
+                }
+                _ => vec![],
+            },
+            Expression::FunctionCall { comp, args } => nested_modification_pairs(comp, args),
+            _ => vec![],
         }
     }
 }
 
+/// Modification pairs of the arguments of a nested class modification, under `prefix`
+fn nested_modification_pairs(
+    prefix: &ComponentReference,
+    args: &[Expression],
+) -> Vec<(ComponentReference, Expression)> {
+    args.iter()
+        .flat_map(Expression::modification_pairs)
+        .map(|(comp, value)| {
+            let mut parts = prefix.parts.clone();
+            parts.extend(comp.parts);
+            (
+                ComponentReference {
+                    local: comp.local,
+                    parts,
+                },
+                value,
+            )
+        })
+        .collect()
+}
+
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -726,6 +1033,7 @@ impl std::fmt::Display for Expression {
                 }
                 write!(f, " }}")
             }
+            Expression::NamedArgument { name, value } => write!(f, "{} = {}", name.text, value),
         }
     }
 }
@@ -734,9 +1042,11 @@ impl std::fmt::Display for Equation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Equation::Empty => write!(f, ""),
-            Equation::Simple { lhs, rhs } => write!(f, "{} = {}", lhs, rhs),
-            Equation::Connect { lhs, rhs } => write!(f, "connect({}, {})", lhs, rhs),
-            Equation::For { indices, equations } => {
+            Equation::Simple { lhs, rhs, .. } => write!(f, "{} = {}", lhs, rhs),
+            Equation::Connect { lhs, rhs, .. } => write!(f, "connect({}, {})", lhs, rhs),
+            Equation::For {
+                indices, equations, ..
+            } => {
                 write!(f, "for ")?;
                 for (i, idx) in indices.iter().enumerate() {
                     if i > 0 {
@@ -750,7 +1060,7 @@ impl std::fmt::Display for Equation {
                 }
                 write!(f, "end for")
             }
-            Equation::When(blocks) => {
+            Equation::When { blocks, .. } => {
                 for (i, block) in blocks.iter().enumerate() {
                     if i == 0 {
                         write!(f, "when {} then", block.cond)?;
@@ -766,6 +1076,7 @@ impl std::fmt::Display for Equation {
             Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for (i, block) in cond_blocks.iter().enumerate() {
                     if i == 0 {
@@ -785,7 +1096,7 @@ impl std::fmt::Display for Equation {
                 }
                 write!(f, " end if")
             }
-            Equation::FunctionCall { comp, args } => {
+            Equation::FunctionCall { comp, args, .. } => {
                 write!(f, "{}(", comp)?;
                 for (i, arg) in args.iter().enumerate() {
                     if i > 0 {
@@ -807,31 +1118,75 @@ pub enum Statement {
     Assignment {
         comp: ComponentReference,
         value: Expression,
+        /// Description string, e.g. `y := 1 "reset";`
+        description: Vec<Token>,
     },
     Return {
         token: Token,
+        description: Vec<Token>,
     },
     Break {
         token: Token,
+        description: Vec<Token>,
     },
     For {
         indices: Vec<ForIndex>,
         equations: Vec<Statement>,
+        description: Vec<Token>,
     },
+    /// While statement; like those of when statements, its description string
+    /// isn't kept
     While(StatementBlock),
     /// If statement: if cond then stmts elseif cond2 then stmts2 else stmts3
     If {
         cond_blocks: Vec<StatementBlock>,
         else_block: Option<Vec<Statement>>,
+        description: Vec<Token>,
     },
     /// When statement: when cond then stmts elsewhen cond2 then stmts2
     When(Vec<StatementBlock>),
     FunctionCall {
         comp: ComponentReference,
         args: Vec<Expression>,
+        description: Vec<Token>,
     },
 }
 
+impl Statement {
+    /// Get the source location of the first token in this statement.
+    /// Returns None for Empty statements.
+    pub fn get_location(&self) -> Option<&Location> {
+        match self {
+            Statement::Empty => None,
+            Statement::Assignment { comp, .. } => comp.get_location(),
+            Statement::Return { token, .. } | Statement::Break { token, .. } => {
+                Some(&token.location)
+            }
+            Statement::For { indices, .. } => indices.first().map(|i| &i.ident.location),
+            Statement::While(block) => block.cond.get_location(),
+            Statement::If { cond_blocks, .. } => {
+                cond_blocks.first().and_then(|b| b.cond.get_location())
+            }
+            Statement::When(blocks) => blocks.first().and_then(|b| b.cond.get_location()),
+            Statement::FunctionCall { comp, .. } => comp.get_location(),
+        }
+    }
+
+    /// Text of the description string, without quotes, or None if there is
+    /// none
+    pub fn description(&self) -> Option<String> {
+        match self {
+            Statement::Assignment { description, .. }
+            | Statement::Return { description, .. }
+            | Statement::Break { description, .. }
+            | Statement::For { description, .. }
+            | Statement::If { description, .. }
+            | Statement::FunctionCall { description, .. } => description_text(description),
+            Statement::Empty | Statement::While(_) | Statement::When(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 
 pub enum Subscript {
@@ -843,6 +1198,16 @@ pub enum Subscript {
     },
 }
 
+impl Display for Subscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Subscript::Empty => Ok(()),
+            Subscript::Expression(expr) => write!(f, "{}", expr),
+            Subscript::Range { .. } => write!(f, ":"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 
 pub enum Variability {
@@ -24,6 +24,24 @@ pub enum IrError {
     #[error("Component class '{0}' not found")]
     ComponentClassNotFound(String),
 
+    #[error(
+        "{location}: recursive instantiation: component '{component}' is an instance of a class containing it ({cycle})"
+    )]
+    RecursiveInstantiation {
+        location: String,
+        component: String,
+        cycle: String,
+    },
+
+    #[error(
+        "{location}: component '{component}' is nested more than {depth} levels deep; raise the limit with --max-instance-depth"
+    )]
+    InstantiationTooDeep {
+        location: String,
+        component: String,
+        depth: usize,
+    },
+
     #[error("Import failed: class '{0}' not found. Did you forget to include the library?")]
     ImportClassNotFound(String),
 
@@ -0,0 +1,214 @@
+//! Identifier helpers for quoted identifiers.
+//!
+//! Modelica identifiers are either basic (`x`, `_tmp1`) or quoted
+//! (`'my var+1'`), and the quotes are part of the name: `'x'` and `x` are
+//! different identifiers. Token texts and flattened names keep the quotes, so
+//! a qualified name like `'my sub'.'a.b'` can't be split at every dot, and
+//! quoted names can't be used as is where a program identifier is expected.
+//!
+//! ```
+//! use rumoca::ir::ident;
+//!
+//! assert_eq!(ident::split_qualified("'my sub'.'a.b'"), ["'my sub'", "'a.b'"]);
+//! assert_eq!(ident::quote("my var"), "'my var'");
+//! assert_eq!(ident::to_python("'my sub'.x[2]"), "my_20sub_x_2");
+//! ```
+
+use std::borrow::Cow;
+
+use crate::modelica_grammar::cst::KEYWORDS;
+
+/// Python keywords, which can't be used as identifiers
+pub const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// C keywords (C99), which can't be used as identifiers
+pub const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Bool",
+    "_Complex",
+    "_Imaginary",
+];
+
+/// Characters that follow a backslash in an escape sequence (S-ESCAPE)
+const ESCAPED: &str = "'\"?\\abfnrtv";
+
+/// Whether a name is a basic identifier, e.g. `x` but not `'x'` or `model`
+pub fn is_basic(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Whether a name is a valid quoted identifier, e.g. `'my var'`
+pub fn is_quoted(name: &str) -> bool {
+    let Some(inner) = name
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    else {
+        return false;
+    };
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let valid = match c {
+            '\\' => chars.next().is_some_and(|e| ESCAPED.contains(e)),
+            '\'' => false,
+            c => c == ' ' || c.is_ascii_graphic(),
+        };
+        if !valid {
+            return false;
+        }
+    }
+    true
+}
+
+/// Turn a name into a valid identifier, quoting it if it isn't one already
+///
+/// Used for names entered by a user, e.g. the new name of a rename.
+pub fn quote(name: &str) -> Cow<'_, str> {
+    if is_basic(name) || is_quoted(name) {
+        return Cow::Borrowed(name);
+    }
+    let escaped = name.replace('\\', "\\\\").replace('\'', "\\'");
+    Cow::Owned(format!("'{}'", escaped))
+}
+
+/// Split a qualified name at the dots that aren't part of a quoted identifier
+pub fn split_qualified(name: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut chars = name.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_quotes => {
+                chars.next();
+            }
+            '\'' => in_quotes = !in_quotes,
+            '.' if !in_quotes => {
+                parts.push(&name[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&name[start..]);
+    parts
+}
+
+/// Python identifier for a (possibly qualified, quoted or subscripted) name
+pub fn to_python(name: &str) -> String {
+    mangle(name, PYTHON_KEYWORDS)
+}
+
+/// C identifier for a (possibly qualified, quoted or subscripted) name
+pub fn to_c(name: &str) -> String {
+    mangle(name, C_KEYWORDS)
+}
+
+/// Map a name to a program identifier
+///
+/// Letters, digits and underscores are kept, quotes are dropped, dots, `[`
+/// and commas become underscores and `]` is dropped, so `'s'.x[1,2]` becomes
+/// `s_x_1_2`. Any other character becomes `_` and its hex code (`'a b'` becomes
+/// `a_20b`). A leading digit gets an underscore prefix, and keywords of the
+/// target language get an underscore suffix. The mapping only depends on the
+/// name, but names that differ only in the characters dropped or turned into
+/// underscores map to the same identifier.
+pub fn mangle(name: &str, keywords: &[&str]) -> String {
+    let mut ident = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || c == '_' => ident.push(c),
+            '\'' | ']' => {}
+            '.' | '[' | ',' => ident.push('_'),
+            c if (c as u32) <= 0xFF => ident.push_str(&format!("_{:02X}", c as u32)),
+            c => ident.push_str(&format!("_u{:04X}", c as u32)),
+        }
+    }
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if keywords.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert!(is_basic("x1") && !is_basic("'x'") && !is_basic("model") && !is_basic("1x"));
+        assert!(is_quoted("'my var+1'") && is_quoted(r"'k\'s'") && is_quoted("''"));
+        assert!(!is_quoted("'k's'") && !is_quoted(r"'k\'") && !is_quoted("'tab\t'"));
+
+        assert_eq!(quote("x"), "x");
+        assert_eq!(quote("'x'"), "'x'");
+        assert_eq!(quote("model"), "'model'");
+        assert_eq!(quote(r"k's\"), r"'k\'s\\'");
+    }
+
+    #[test]
+    fn test_split_qualified() {
+        assert_eq!(split_qualified("a.b.c"), ["a", "b", "c"]);
+        assert_eq!(split_qualified(r"'a.b'.'c\'.d'"), ["'a.b'", r"'c\'.d'"]);
+        assert_eq!(split_qualified("a"), ["a"]);
+    }
+
+    #[test]
+    fn test_mangle() {
+        assert_eq!(to_python("x"), "x");
+        assert_eq!(to_python("'my var+1'"), "my_20var_2B1");
+        assert_eq!(to_python("s.'x'[1,2]"), "s_x_1_2");
+        assert_eq!(to_python("'1st'"), "_1st");
+        assert_eq!(to_python("lambda"), "lambda_");
+        assert_eq!(to_c("lambda"), "lambda");
+        assert_eq!(to_c("'double'"), "double_");
+        assert_eq!(to_c("''"), "_");
+        assert_eq!(to_c("'µ'"), "_B5");
+        assert_eq!(to_c("'→'"), "_u2192");
+    }
+}
@@ -0,0 +1,215 @@
+//! Literal helpers for parsing and generated code.
+//!
+//! Numbers are parsed and printed the same way on every machine, whatever
+//! the locale: the decimal separator is always `.`, never `,`. Real literals
+//! keep their source text in the IR, e.g. `1.E3` or `.5`, and [`parse_real`]
+//! gives their value; numbers the compiler creates are spelled by
+//! [`format_real`], which round-trips exactly:
+//!
+//! ```
+//! use rumoca::ir::literal::{self, ExponentStyle};
+//!
+//! assert_eq!(literal::parse_real("1.E3"), Some(1000.0));
+//! assert_eq!(literal::parse_real("1,5"), None);
+//! assert_eq!(literal::format_real(6.02214076e23), "6.02214076e23");
+//! assert_eq!(literal::normalize_exponent("1.5E+05", ExponentStyle::Lower), "1.5e5");
+//! ```
+//!
+//! `Modelica.Constants.inf` is substituted as the largest finite double,
+//! printed `1.7976931348623157e308`, so that it still const-evaluates and
+//! fits in JSON. Generated backends spell it the way their language does:
+//! `float('inf')` in Python and `DBL_MAX` in C. Other literals are kept as
+//! they are, except non-finite values, which can't be written as numbers.
+//!
+//! ```
+//! use rumoca::ir::literal;
+//!
+//! assert_eq!(literal::to_python("1.7976931348623157e308"), "float('inf')");
+//! assert_eq!(literal::to_c("1.7976931348623157e308"), "DBL_MAX");
+//! assert_eq!(literal::to_c("2.5"), "2.5");
+//! ```
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ir::transform::constants::MODELICA_INF;
+
+/// The value of an unsigned number literal, e.g. `2`, `1.5`, `1.`, `.5` or
+/// `1.5E-3`, or None if the text isn't one
+///
+/// Only the Modelica syntax is accepted: no signs, digit separators or
+/// locale-specific decimal commas. `inf` and `NaN`, which [`format_real`]
+/// gives for non-finite values, are accepted as well.
+pub fn parse_real(text: &str) -> Option<f64> {
+    if matches!(text, "inf" | "NaN") {
+        return text.parse().ok();
+    }
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(e) => (&text[..e], Some(&text[e + 1..])),
+        None => (text, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let valid_mantissa = digits(integer) && digits(fraction) && integer.len() + fraction.len() > 0;
+    let valid_exponent = exponent.is_none_or(|exponent| {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !exponent.is_empty() && digits(exponent)
+    });
+    if valid_mantissa && valid_exponent {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// The text of a real value, as the compiler writes the numbers it creates
+///
+/// The text is the shortest that parses back to exactly the same value, with
+/// a `.` or an exponent so that it reads as a Real: `2.0`, `0.001`, `1e-5`,
+/// `6.02214076e23`. Non-finite values are written `inf` and `NaN`, which
+/// [`to_python`] and [`to_c`] spell for their languages.
+pub fn format_real(value: f64) -> String {
+    // Debug formatting is the shortest round-trip text, in scientific
+    // notation for very large and small magnitudes
+    format!("{:?}", value)
+}
+
+/// How the formatter writes the exponent of real literals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExponentStyle {
+    /// Keep the exponent as written
+    #[default]
+    Preserve,
+    /// `1.5e5`, `1e-3`: lower case `e`, no `+` and no leading zeros
+    Lower,
+    /// `1.5E5`, `1E-3`: upper case `E`, no `+` and no leading zeros
+    Upper,
+}
+
+impl std::str::FromStr for ExponentStyle {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "preserve" => Ok(ExponentStyle::Preserve),
+            "lower" => Ok(ExponentStyle::Lower),
+            "upper" => Ok(ExponentStyle::Upper),
+            other => Err(format!(
+                "Invalid exponent style '{}'. Expected preserve, lower or upper",
+                other
+            )),
+        }
+    }
+}
+
+/// Rewrite the exponent of a real literal in a style, keeping its mantissa
+/// as written, so that its value doesn't change
+pub fn normalize_exponent(text: &str, style: ExponentStyle) -> Cow<'_, str> {
+    let marker = match style {
+        ExponentStyle::Preserve => return text.into(),
+        ExponentStyle::Lower => 'e',
+        ExponentStyle::Upper => 'E',
+    };
+    let Some(e) = text.find(['e', 'E']) else {
+        return text.into();
+    };
+    let (mantissa, exponent) = (&text[..e], &text[e + 1..]);
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", exponent.strip_prefix('+').unwrap_or(exponent)),
+    };
+    let digits = digits.trim_start_matches('0');
+    let digits = if digits.is_empty() { "0" } else { digits };
+    format!("{}{}{}{}", mantissa, marker, sign, digits).into()
+}
+
+/// Spelling of a real literal in Python
+pub fn to_python(text: &str) -> Cow<'_, str> {
+    match text.parse::<f64>() {
+        Ok(value) if value.is_nan() => "float('nan')".into(),
+        Ok(value) if value == MODELICA_INF || value == f64::INFINITY => "float('inf')".into(),
+        _ => text.into(),
+    }
+}
+
+/// Spelling of a real literal in C, using the `<float.h>` and `<math.h>` macros
+pub fn to_c(text: &str) -> Cow<'_, str> {
+    match text.parse::<f64>() {
+        Ok(value) if value.is_nan() => "NAN".into(),
+        Ok(value) if value == MODELICA_INF => "DBL_MAX".into(),
+        Ok(value) if value == f64::INFINITY => "INFINITY".into(),
+        _ => text.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_real_literals() {
+        for (text, value) in [
+            ("2", 2.0),
+            ("1.5", 1.5),
+            ("1.", 1.0),
+            (".5", 0.5),
+            ("1.E3", 1000.0),
+            ("1.5e-3", 0.0015),
+            ("2E+2", 200.0),
+        ] {
+            assert_eq!(parse_real(text), Some(value), "{}", text);
+        }
+        for text in [
+            "1,5", "1.5,0", "-1", "1_000", "e5", ".", "1e", "1.5e+", "infinity",
+        ] {
+            assert_eq!(parse_real(text), None, "{}", text);
+        }
+
+        // Scientific notation round-trips exactly
+        for value in [
+            0.1,
+            2.0,
+            1e-5,
+            1e16,
+            6.02214076e23,
+            2.220446049250313e-16,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+        ] {
+            let text = format_real(value);
+            assert!(!text.contains(','), "{}", text);
+            assert_eq!(parse_real(&text), Some(value), "{}", text);
+        }
+        assert_eq!(format_real(2.0), "2.0");
+        assert_eq!(format_real(1e-5), "1e-5");
+        assert_eq!(format_real(f64::INFINITY), "inf");
+        assert_eq!(format_real(f64::NAN), "NaN");
+        assert!(parse_real(&format_real(f64::NAN)).unwrap().is_nan());
+
+        assert_eq!(normalize_exponent("1.5E+05", ExponentStyle::Lower), "1.5e5");
+        assert_eq!(normalize_exponent("1e-03", ExponentStyle::Upper), "1E-3");
+        assert_eq!(normalize_exponent("1.E00", ExponentStyle::Lower), "1.e0");
+        assert_eq!(
+            normalize_exponent("1.5E+05", ExponentStyle::Preserve),
+            "1.5E+05"
+        );
+        assert_eq!(normalize_exponent("0.25", ExponentStyle::Upper), "0.25");
+        assert_eq!("lower".parse(), Ok(ExponentStyle::Lower));
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(to_python("1.7976931348623157e308"), "float('inf')");
+        assert_eq!(to_python("inf"), "float('inf')");
+        assert_eq!(to_python("NaN"), "float('nan')");
+        assert_eq!(to_python("1e60"), "1e60");
+        assert_eq!(to_python("2"), "2");
+
+        assert_eq!(to_c("1.7976931348623157e308"), "DBL_MAX");
+        assert_eq!(to_c("inf"), "INFINITY");
+        assert_eq!(to_c("NaN"), "NAN");
+        assert_eq!(to_c("2.220446049250313e-16"), "2.220446049250313e-16");
+    }
+}
@@ -5,6 +5,8 @@
 pub mod analysis;
 pub mod ast;
 pub mod error;
+pub mod ident;
+pub mod literal;
 pub mod structural;
 pub mod transform;
 pub mod visitor;
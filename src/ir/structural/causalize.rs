@@ -7,12 +7,79 @@
 //! variable each equation should solve for (via matching), we need to algebraically
 //! rearrange the equation to compute that variable.
 
+use std::sync::LazyLock;
+
 use crate::ir::ast::{
     ComponentRefPart, ComponentReference, Equation, Expression, OpBinary, OpUnary, TerminalType,
     Token,
 };
+use crate::ir::literal::format_real;
+use crate::ir::transform::rewrite::{Bindings, Pattern, Rule, apply_first, rewrite};
 use crate::ir::visitor::{Visitable, Visitor};
 
+/// Rules moving the coefficient of a derivative to the right-hand side, over
+/// the pair `(lhs, rhs)` of an equation
+static DERIVATIVE_COEFFICIENT_RULES: LazyLock<Vec<Rule>> = LazyLock::new(|| {
+    let der = || Pattern::named("der", Pattern::call("der", vec![Pattern::bind("x")]));
+    let mul = || OpBinary::Mul(Token::default());
+    let divide_rhs = |b: &Bindings| Expression::Tuple {
+        elements: vec![
+            b["der"].clone(),
+            Expression::Binary {
+                op: OpBinary::Div(Token::default()),
+                lhs: Box::new(b["rhs"].clone()),
+                rhs: Box::new(b["coeff"].clone()),
+            },
+        ],
+    };
+    vec![
+        Rule::new(
+            "coeff * der(x) = rhs => der(x) = rhs / coeff",
+            Pattern::tuple(vec![
+                Pattern::binary(mul(), Pattern::bind("coeff"), der()),
+                Pattern::bind("rhs"),
+            ]),
+            divide_rhs,
+        ),
+        Rule::new(
+            "der(x) * coeff = rhs => der(x) = rhs / coeff",
+            Pattern::tuple(vec![
+                Pattern::binary(mul(), der(), Pattern::bind("coeff")),
+                Pattern::bind("rhs"),
+            ]),
+            divide_rhs,
+        ),
+    ]
+});
+
+/// Rules simplifying a negation
+static NEGATION_RULES: LazyLock<Vec<Rule>> = LazyLock::new(|| {
+    let minus = || OpUnary::Minus(Token::default());
+    vec![
+        Rule::new(
+            "-(-x) => x",
+            Pattern::unary(minus(), Pattern::unary(minus(), Pattern::bind("x"))),
+            |b| b["x"].clone(),
+        ),
+        Rule::new(
+            "-(a - b) => b - a",
+            Pattern::unary(
+                minus(),
+                Pattern::binary(
+                    OpBinary::Sub(Token::default()),
+                    Pattern::bind("a"),
+                    Pattern::bind("b"),
+                ),
+            ),
+            |b| Expression::Binary {
+                op: OpBinary::Sub(Token::default()),
+                lhs: Box::new(b["b"].clone()),
+                rhs: Box::new(b["a"].clone()),
+            },
+        ),
+    ]
+});
+
 /// Visitor to find der() calls in an expression
 struct DerivativeFinder {
     derivatives: Vec<String>,
@@ -65,51 +132,32 @@ pub(super) fn normalize_derivative_equation(
     lhs: &Expression,
     rhs: &Expression,
 ) -> Option<Equation> {
-    // Check if LHS is coeff * der(x) or der(x) * coeff
-    if let Expression::Binary {
-        op: OpBinary::Mul(_),
-        lhs: mult_lhs,
-        rhs: mult_rhs,
-    } = lhs
-    {
-        // Case 1: coeff * der(x)
-        if let Expression::FunctionCall { comp, args } = mult_rhs.as_ref()
-            && comp.to_string() == "der"
-            && args.len() == 1
-        {
-            // Extract der(x) and coefficient
-            let der_expr = mult_rhs.as_ref().clone();
-            let coeff = mult_lhs.as_ref().clone();
-            return Some(Equation::Simple {
-                lhs: der_expr,
-                rhs: Expression::Binary {
-                    op: OpBinary::Div(Token::default()),
-                    lhs: Box::new(rhs.clone()),
-                    rhs: Box::new(coeff),
-                },
-            });
+    // The rules only match products, so other equations aren't copied into
+    // the tuple they match against
+    if !matches!(
+        lhs,
+        Expression::Binary {
+            op: OpBinary::Mul(_),
+            ..
         }
-
-        // Case 2: der(x) * coeff
-        if let Expression::FunctionCall { comp, args } = mult_lhs.as_ref()
-            && comp.to_string() == "der"
-            && args.len() == 1
-        {
-            // Extract der(x) and coefficient
-            let der_expr = mult_lhs.as_ref().clone();
-            let coeff = mult_rhs.as_ref().clone();
-            return Some(Equation::Simple {
-                lhs: der_expr,
-                rhs: Expression::Binary {
-                    op: OpBinary::Div(Token::default()),
-                    lhs: Box::new(rhs.clone()),
-                    rhs: Box::new(coeff),
-                },
-            });
+    ) {
+        return None;
+    }
+    let equation = Expression::Tuple {
+        elements: vec![lhs.clone(), rhs.clone()],
+    };
+    match apply_first(&equation, &DERIVATIVE_COEFFICIENT_RULES)? {
+        Expression::Tuple { mut elements } => {
+            let rhs = elements.pop()?;
+            let lhs = elements.pop()?;
+            Some(Equation::Simple {
+                lhs,
+                rhs,
+                description: vec![],
+            })
         }
+        _ => None,
     }
-
-    None
 }
 
 /// Causalize an equation by solving for a specific variable.
@@ -147,6 +195,34 @@ pub(super) fn causalize_equation(
     rhs: &Expression,
     solve_for: &str,
 ) -> Option<Equation> {
+    let causalized = isolate_variable(lhs, rhs, solve_for)?;
+    // The variable may appear again in the other terms, e.g. inside a
+    // nondifferentiable function in `y = x + max(x, 0)`, so it isn't isolated
+    match &causalized {
+        Equation::Simple { rhs, .. } if references(rhs, solve_for) => None,
+        _ => Some(causalized),
+    }
+}
+
+/// Check if an expression references a variable
+pub(super) fn references(expr: &Expression, var: &str) -> bool {
+    struct ReferenceFinder<'a>(&'a str, bool);
+    impl Visitor for ReferenceFinder<'_> {
+        fn enter_expression(&mut self, node: &Expression) {
+            match node {
+                Expression::ComponentReference(cref) => self.1 |= cref.to_string() == self.0,
+                Expression::FunctionCall { .. } => self.1 |= node.to_string() == self.0,
+                _ => {}
+            }
+        }
+    }
+    let mut finder = ReferenceFinder(var, false);
+    expr.accept(&mut finder);
+    finder.1
+}
+
+/// Rearrange a linear equation to isolate a variable on the LHS
+fn isolate_variable(lhs: &Expression, rhs: &Expression, solve_for: &str) -> Option<Equation> {
     // Check if LHS is already just the variable we're solving for
     if let Expression::ComponentReference(cref) = lhs
         && cref.to_string() == solve_for
@@ -162,6 +238,7 @@ pub(super) fn causalize_equation(
         return Some(Equation::Simple {
             lhs: rhs.clone(),
             rhs: lhs.clone(),
+            description: vec![],
         });
     }
 
@@ -204,6 +281,7 @@ pub(super) fn causalize_equation(
                     }],
                 }),
                 rhs: new_rhs,
+                description: vec![],
             });
         }
     }
@@ -234,6 +312,7 @@ pub(super) fn causalize_equation(
                     }],
                 }),
                 rhs: new_rhs,
+                description: vec![],
             });
         }
     }
@@ -266,6 +345,7 @@ pub(super) fn causalize_equation(
                     lhs: Box::new(rhs.clone()),
                     rhs: mult_lhs.clone(),
                 },
+                description: vec![],
             });
         }
         // Check if solve_for is on the left side of multiplication: var * coeff
@@ -288,6 +368,7 @@ pub(super) fn causalize_equation(
                     lhs: Box::new(rhs.clone()),
                     rhs: mult_rhs.clone(),
                 },
+                description: vec![],
             });
         }
     }
@@ -319,7 +400,7 @@ pub(super) fn causalize_equation(
                 rhs: Box::new(Expression::Terminal {
                     terminal_type: TerminalType::UnsignedReal,
                     token: Token {
-                        text: coeff.to_string(),
+                        text: format_real(coeff),
                         ..Default::default()
                     },
                 }),
@@ -338,6 +419,7 @@ pub(super) fn causalize_equation(
                 }],
             }),
             rhs: new_rhs,
+            description: vec![],
         });
     }
 
@@ -368,7 +450,7 @@ pub(super) fn causalize_equation(
                 rhs: Box::new(Expression::Terminal {
                     terminal_type: TerminalType::UnsignedReal,
                     token: Token {
-                        text: coeff.to_string(),
+                        text: format_real(coeff),
                         ..Default::default()
                     },
                 }),
@@ -387,6 +469,7 @@ pub(super) fn causalize_equation(
                 }],
             }),
             rhs: new_rhs,
+            description: vec![],
         });
     }
 
@@ -401,31 +484,15 @@ fn is_zero_expression(expr: &Expression) -> bool {
     }
 }
 
-/// Negate an expression: expr -> -expr or -(expr)
+/// Negate an expression: expr -> -expr, simplified with [`NEGATION_RULES`]
 fn negate_expression(expr: &Expression) -> Expression {
-    // Handle simple cases to produce cleaner output
-    match expr {
-        // -(-x) = x
-        Expression::Unary {
-            op: OpUnary::Minus(_),
-            rhs,
-        } => (**rhs).clone(),
-        // -(a - b) = b - a
-        Expression::Binary {
-            op: OpBinary::Sub(_),
-            lhs,
-            rhs,
-        } => Expression::Binary {
-            op: OpBinary::Sub(Token::default()),
-            lhs: rhs.clone(),
-            rhs: lhs.clone(),
-        },
-        // For other expressions, just negate
-        _ => Expression::Unary {
+    rewrite(
+        &Expression::Unary {
             op: OpUnary::Minus(Token::default()),
             rhs: Box::new(expr.clone()),
         },
-    }
+        &NEGATION_RULES,
+    )
 }
 
 /// Extract the coefficient and remaining terms for a variable in a linear expression.
@@ -580,6 +647,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_normalize_derivative_equation() {
+        let der = Expression::FunctionCall {
+            comp: ComponentReference {
+                local: false,
+                parts: vec![ComponentRefPart {
+                    ident: Token {
+                        text: "der".to_string(),
+                        ..Default::default()
+                    },
+                    subs: None,
+                }],
+            },
+            args: vec![make_var("v")],
+        };
+        for (a, b) in [(make_var("C"), der.clone()), (der.clone(), make_var("C"))] {
+            // C * der(v) = i and der(v) * C = i => der(v) = i / C
+            let lhs = Expression::Binary {
+                op: OpBinary::Mul(Token::default()),
+                lhs: Box::new(a),
+                rhs: Box::new(b),
+            };
+            let Some(Equation::Simple { lhs, rhs, .. }) =
+                normalize_derivative_equation(&lhs, &make_var("i"))
+            else {
+                panic!("Expected Simple equation");
+            };
+            assert_eq!(lhs, der);
+            assert_eq!(rhs.to_string(), "i / C");
+        }
+        assert!(normalize_derivative_equation(&make_var("x"), &make_var("i")).is_none());
+    }
+
+    #[test]
+    fn test_negate_expression() {
+        let a_minus_b = Expression::Binary {
+            op: OpBinary::Sub(Token::default()),
+            lhs: Box::new(make_var("a")),
+            rhs: Box::new(make_var("b")),
+        };
+        assert_eq!(negate_expression(&a_minus_b).to_string(), "b - a");
+        assert_eq!(
+            negate_expression(&negate_expression(&make_var("a"))),
+            make_var("a")
+        );
+        assert_eq!(negate_expression(&make_var("a")).to_string(), "-a");
+    }
+
     #[test]
     fn test_causalize_already_causal() {
         // x = y (already in correct form for solving for x)
@@ -603,7 +718,7 @@ mod tests {
         let result = causalize_equation(&lhs, &rhs, "a");
         assert!(result.is_some());
 
-        if let Some(Equation::Simple { lhs, rhs: _ }) = result {
+        if let Some(Equation::Simple { lhs, .. }) = result {
             // LHS should be just "a"
             assert!(
                 matches!(lhs, Expression::ComponentReference(_)),
@@ -646,4 +761,30 @@ mod tests {
         let result = causalize_equation(&lhs, &rhs, "a");
         assert!(result.is_some(), "Should handle zero on LHS");
     }
+
+    #[test]
+    fn test_causalize_through_discontinuity() {
+        // y = x + max(x, 0) can't be solved for x: x = y - max(x, 0) isn't explicit
+        let max = Expression::FunctionCall {
+            comp: ComponentReference {
+                local: false,
+                parts: vec![ComponentRefPart {
+                    ident: Token {
+                        text: "max".to_string(),
+                        ..Default::default()
+                    },
+                    subs: None,
+                }],
+            },
+            args: vec![make_var("x"), make_zero()],
+        };
+        let rhs = Expression::Binary {
+            op: OpBinary::Add(Token::default()),
+            lhs: Box::new(make_var("x")),
+            rhs: Box::new(max),
+        };
+
+        let result = causalize_equation(&make_var("y"), &rhs, "x");
+        assert!(result.is_none(), "x is not isolated: {:?}", result);
+    }
 }
@@ -2,7 +2,8 @@
 //! which is part of the Abstract Syntax Tree (AST) representation in the
 //! Differential-Algebraic Equation (DAE) domain. It is used to model and
 //! manipulate DAE-related constructs within the application.
-use crate::dae::ast::Dae;
+use crate::dae::ast::{Dae, SingularEquations, SortMode};
+use crate::dae::ids::{EquationIdAllocator, Partition};
 use crate::ir::analysis::condition_finder::ConditionFinder;
 use crate::ir::analysis::state_finder::StateFinder;
 use crate::ir::ast::{
@@ -10,6 +11,8 @@ use crate::ir::ast::{
     Variability,
 };
 use crate::ir::error::IrError;
+use crate::ir::structural::BltResult;
+use crate::ir::structural::pantelides::pantelides_index_reduction;
 use crate::ir::transform::constants::BUILTIN_REINIT;
 use crate::ir::visitor::MutVisitable;
 use git_version::git_version;
@@ -22,6 +25,41 @@ const GIT_VERSION: &str = git_version!(
     fallback = "unknown"
 );
 
+/// Whether the continuous-time equations are a high-index DAE that index
+/// reduction can make solvable, rather than structurally singular
+fn is_high_index(fclass: &ClassDefinition, dae: &Dae) -> bool {
+    let equations: Vec<Equation> = fclass
+        .equations
+        .iter()
+        .filter(|eq| matches!(eq, Equation::Simple { .. }))
+        .cloned()
+        .collect();
+    let states: HashSet<String> = dae.x.keys().cloned().collect();
+    let algebraic: HashSet<String> = dae.y.keys().cloned().collect();
+    !pantelides_index_reduction(&equations, &states, Some(&algebraic)).is_singular
+}
+
+/// Name of the condition variable of a when-branch
+///
+/// The [`ConditionFinder`] replaces every condition by a variable, so
+/// anything else is an error rather than a branch to leave out.
+fn when_condition_name(cond: &Expression) -> Result<String> {
+    match cond {
+        Expression::ComponentReference(cref) => Ok(cref.to_string()),
+        other => {
+            let loc = other
+                .get_location()
+                .map(|l| format!(" at {}:{}:{}", l.file_name, l.start_line, l.start_column))
+                .unwrap_or_default();
+            anyhow::bail!(
+                "Unsupported condition type in 'when' block{}. \
+                 Expected a component reference.",
+                loc
+            )
+        }
+    }
+}
+
 /// Collect variable names that appear on the left-hand side of simple equations.
 /// These variables have defining equations and should not be treated as external inputs
 /// even if they have Input causality (e.g., signal connector inputs with connect equations).
@@ -50,6 +88,7 @@ fn collect_defined_variables_recursive(equations: &[Equation], defined: &mut Has
             Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     collect_defined_variables_recursive(&block.eqs, defined);
@@ -58,7 +97,7 @@ fn collect_defined_variables_recursive(equations: &[Equation], defined: &mut Has
                     collect_defined_variables_recursive(else_eqs, defined);
                 }
             }
-            Equation::When(blocks) => {
+            Equation::When { blocks, .. } => {
                 for block in blocks {
                     collect_defined_variables_recursive(&block.eqs, defined);
                 }
@@ -198,6 +237,16 @@ fn extract_array_element(expr: &Expression, indices: &[usize]) -> Expression {
 /// Returns an error if connection equations are encountered (they should be expanded during
 /// flattening but this feature is not yet implemented).
 pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
+    create_dae_with_blt(fclass, SortMode::Blt).map(|(dae, _)| dae)
+}
+
+/// Like [`create_dae`], also returning the BLT result of the equations, e.g.
+/// the variable each equation is solved for. With [`SortMode::None`], the
+/// continuous-time equations keep their declared order and form.
+pub fn create_dae_with_blt(
+    fclass: &mut ClassDefinition,
+    sort: SortMode,
+) -> Result<(Dae, BltResult)> {
     // create default Dae struct
     let mut dae = Dae {
         model_name: fclass.name.text.clone(),
@@ -213,16 +262,17 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
             },
             ..Default::default()
         },
+        sort,
         ..Default::default()
     };
 
     // run statefinder to find states and replace
     // derivative references
-    let mut state_finder = StateFinder::default();
+    let mut state_finder = StateFinder::new(&fclass.components);
     fclass.accept_mut(&mut state_finder);
 
     // find conditions
-    let mut condition_finder = ConditionFinder::default();
+    let mut condition_finder = ConditionFinder::new(fclass);
     fclass.accept_mut(&mut condition_finder);
 
     // Find variables that have defining equations (appear on LHS of simple equations)
@@ -246,32 +296,28 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                     dae.m.insert(scalar_name, scalar_comp);
                 }
                 Variability::Empty => {
-                    // Check causality, but also check if the variable has a defining equation
-                    match scalar_comp.causality {
-                        Causality::Input(..) => {
-                            // Input causality variables are only true "inputs" if they have no defining equation
-                            // If they have a defining equation (e.g., from connect), they're algebraic variables
-                            if defined_variables.contains(&scalar_name) {
-                                // Has a defining equation - treat as algebraic variable
-                                dae.y.insert(scalar_name, scalar_comp);
-                            } else {
-                                // No defining equation - true external input
-                                dae.u.insert(scalar_name, scalar_comp);
-                            }
-                        }
-                        Causality::Output(..) | Causality::Empty => {
-                            // For outputs and regular variables, check if it's a state
-                            // Check both the original array name and the scalar name for states
-                            let base_name = comp.name.clone();
-                            if state_finder.states.contains(&base_name)
-                                || state_finder.states.contains(&scalar_name)
-                            {
-                                // Add state variable only - derivatives remain as der() calls in equations
-                                dae.x.insert(scalar_name, scalar_comp);
-                            } else {
-                                dae.y.insert(scalar_name, scalar_comp);
-                            }
-                        }
+                    // Inputs are only true "inputs" of the model if they belong to the
+                    // model or its connectors and have no defining equation. Inputs of
+                    // sub-models, and inputs given by an equation (e.g., from connect),
+                    // are unknowns like any other variable.
+                    let is_input = matches!(scalar_comp.causality, Causality::Input(..))
+                        && !defined_variables.contains(&scalar_name)
+                        && !fclass.internal_inputs.contains(&comp.name);
+                    // Check both the original array name and the scalar name for states
+                    let base_name = comp.name.clone();
+                    if is_input {
+                        dae.u.insert(scalar_name, scalar_comp);
+                    } else if condition_finder.discrete.contains(&scalar_name) {
+                        // Boolean variables defined by a relation only change
+                        // at the events of its condition
+                        dae.m.insert(scalar_name, scalar_comp);
+                    } else if state_finder.states.contains(&base_name)
+                        || state_finder.states.contains(&scalar_name)
+                    {
+                        // Add state variable only - derivatives remain as der() calls in equations
+                        dae.x.insert(scalar_name, scalar_comp);
+                    } else {
+                        dae.y.insert(scalar_name, scalar_comp);
                     }
                 }
             }
@@ -283,8 +329,9 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
     dae.fc = condition_finder.expressions.clone();
 
     // Build set of variables to exclude from BLT matching
-    // (parameters, constants, inputs, states, and "time" should not be solved for)
-    // States are excluded because their values come from integration, not algebraic equations
+    // (parameters, constants, inputs, states, conditions, and "time" should not be solved for)
+    // States are excluded because their values come from integration, not algebraic equations,
+    // and Boolean variables defined by a condition because they are updated at events
     let mut exclude_from_matching: HashSet<String> = HashSet::new();
     for name in dae.p.keys() {
         exclude_from_matching.insert(name.clone());
@@ -298,55 +345,121 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
     for name in dae.x.keys() {
         exclude_from_matching.insert(name.clone());
     }
+    for name in dae.c.keys() {
+        exclude_from_matching.insert(name.clone());
+    }
+    for name in &condition_finder.discrete {
+        exclude_from_matching.insert(name.clone());
+    }
     exclude_from_matching.insert("time".to_string());
 
     // Apply structural transformation to reorder and normalize equations
-    let transformed_equations =
-        crate::ir::structural::blt_transform(fclass.equations.clone(), &exclude_from_matching);
+    let blt = crate::ir::structural::blt_transform_with_info(
+        fclass.equations.clone(),
+        &exclude_from_matching,
+    );
 
-    // handle equations
-    for eq in &transformed_equations {
+    // Equations left without an unknown that reference a parameter or an
+    // input could only be solved for it. If they involve a state, they may
+    // constrain the states instead (a high-index DAE, like the position
+    // constraint of a pendulum), which index reduction handles unless the
+    // equations are singular even when differentiated (like y = p next to
+    // y = 2 * x and der(x) = -x + y).
+    let mut high_index = None;
+    for unmatched in &blt.unmatched_equations {
+        if unmatched
+            .excluded_variables
+            .iter()
+            .any(|name| dae.x.contains_key(name))
+            && *high_index.get_or_insert_with(|| is_high_index(fclass, &dae))
+        {
+            continue;
+        }
+        let parameters: Vec<String> = unmatched
+            .excluded_variables
+            .iter()
+            .filter(|name| dae.p.contains_key(*name))
+            .cloned()
+            .collect();
+        let inputs: Vec<String> = unmatched
+            .excluded_variables
+            .iter()
+            .filter(|name| dae.u.contains_key(*name))
+            .cloned()
+            .collect();
+        if parameters.is_empty() && inputs.is_empty() {
+            continue;
+        }
+        let equations = std::iter::once(unmatched.equation_index)
+            .chain(unmatched.related_indices.iter().copied())
+            .map(|idx| fclass.equations[idx].clone())
+            .collect();
+        dae.singular.push(SingularEquations {
+            parameters,
+            inputs,
+            equations,
+        });
+    }
+
+    // Equation ids are derived from the declared equations, so they don't
+    // depend on how BLT ordered or causalized them
+    let mut ids = EquationIdAllocator::default();
+
+    // handle equations, sorted or as declared
+    let equations: Vec<(&Equation, usize)> = match sort {
+        SortMode::Blt => blt
+            .equations
+            .iter()
+            .zip(blt.source_indices.iter().copied())
+            .collect(),
+        SortMode::None => fclass.equations.iter().zip(0..).collect(),
+    };
+    for (eq, source_idx) in equations {
+        let declared = &fclass.equations[source_idx];
         match &eq {
+            Equation::Simple {
+                lhs: Expression::ComponentReference(cref),
+                ..
+            } if condition_finder.discrete.contains(&cref.to_string()) => {
+                dae.fm.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::Fm, declared);
+            }
             Equation::Simple { .. } => {
                 dae.fx.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::Fx, declared);
             }
             Equation::If { .. } => {
                 dae.fx.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::Fx, declared);
             }
             Equation::For { .. } => {
                 // For equations are passed through directly - they will be
                 // either expanded by the backend or serialized as-is
                 dae.fx.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::Fx, declared);
             }
             Equation::Connect { .. } => {
                 return Err(IrError::UnexpandedConnectionEquation.into());
             }
-            Equation::When(blocks) => {
+            Equation::When { blocks, .. } => {
+                // Only the first branch becoming true at an event fires
+                dae.when_clauses.push(
+                    blocks
+                        .iter()
+                        .map(|block| when_condition_name(&block.cond))
+                        .collect::<Result<_>>()?,
+                );
                 for block in blocks {
                     for eq in &block.eqs {
                         match eq {
-                            Equation::FunctionCall { comp, args } => {
+                            Equation::FunctionCall {
+                                comp,
+                                args,
+                                description,
+                            } => {
                                 let name = comp.to_string();
                                 if name == BUILTIN_REINIT {
-                                    let cond_name = match &block.cond {
-                                        Expression::ComponentReference(cref) => cref.to_string(),
-                                        other => {
-                                            let loc = other
-                                                .get_location()
-                                                .map(|l| {
-                                                    format!(
-                                                        " at {}:{}:{}",
-                                                        l.file_name, l.start_line, l.start_column
-                                                    )
-                                                })
-                                                .unwrap_or_default();
-                                            anyhow::bail!(
-                                                "Unsupported condition type in 'when' block{}. \
-                                                 Expected a component reference.",
-                                                loc
-                                            )
-                                        }
-                                    };
+                                    let cond_name = when_condition_name(&block.cond)?;
                                     if args.len() != 2 {
                                         return Err(
                                             IrError::InvalidReinitArgCount(args.len()).into()
@@ -359,6 +472,7 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                                 Statement::Assignment {
                                                     comp: cref.clone(),
                                                     value: args[1].clone(),
+                                                    description: description.clone(),
                                                 },
                                             );
                                         }
@@ -372,28 +486,14 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                     }
                                 }
                             }
-                            Equation::Simple { lhs, rhs } => {
+                            Equation::Simple {
+                                lhs,
+                                rhs,
+                                description,
+                            } => {
                                 // Handle direct variable assignments in when blocks
                                 // e.g., when trigger then y = expr; end when;
-                                let cond_name = match &block.cond {
-                                    Expression::ComponentReference(cref) => cref.to_string(),
-                                    other => {
-                                        let loc = other
-                                            .get_location()
-                                            .map(|l| {
-                                                format!(
-                                                    " at {}:{}:{}",
-                                                    l.file_name, l.start_line, l.start_column
-                                                )
-                                            })
-                                            .unwrap_or_default();
-                                        anyhow::bail!(
-                                            "Unsupported condition type in 'when' block{}. \
-                                             Expected a component reference.",
-                                            loc
-                                        )
-                                    }
-                                };
+                                let cond_name = when_condition_name(&block.cond)?;
                                 // Convert lhs to ComponentReference for assignment
                                 match lhs {
                                     Expression::ComponentReference(cref) => {
@@ -402,6 +502,7 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                             Statement::Assignment {
                                                 comp: cref.clone(),
                                                 value: rhs.clone(),
+                                                description: description.clone(),
                                             },
                                         );
                                     }
@@ -409,6 +510,7 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                         // Handle tuple assignments like (a, b) = func()
                                         // Add as event update equation
                                         dae.fz.push(eq.clone());
+                                        dae.eq_ids.push(&mut ids, Partition::Fz, eq);
                                         // Also add individual assignments for simple tuple elements
                                         for (i, elem) in elements.iter().enumerate() {
                                             if let Expression::ComponentReference(cref) = elem {
@@ -418,6 +520,7 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                                     Statement::Assignment {
                                                         comp: cref.clone(),
                                                         value: rhs.clone(), // Will be handled by backend
+                                                        description: description.clone(),
                                                     },
                                                 );
                                             }
@@ -426,12 +529,14 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
                                     _ => {
                                         // For other complex LHS patterns, add as event equation
                                         dae.fz.push(eq.clone());
+                                        dae.eq_ids.push(&mut ids, Partition::Fz, eq);
                                     }
                                 }
                             }
                             Equation::If { .. } | Equation::For { .. } => {
                                 // Pass through if/for equations inside when blocks as event equations
                                 dae.fz.push(eq.clone());
+                                dae.eq_ids.push(&mut ids, Partition::Fz, eq);
                             }
                             other => {
                                 let loc = other
@@ -462,14 +567,16 @@ pub fn create_dae(fclass: &mut ClassDefinition) -> Result<Dae> {
         match eq {
             Equation::Simple { .. } | Equation::For { .. } | Equation::If { .. } => {
                 dae.fx_init.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::FxInit, eq);
             }
             _ => {
                 // Other equation types in initial section are less common
                 // but we'll pass them through
                 dae.fx_init.push(eq.clone());
+                dae.eq_ids.push(&mut ids, Partition::FxInit, eq);
             }
         }
     }
 
-    Ok(dae)
+    Ok((dae, blt))
 }
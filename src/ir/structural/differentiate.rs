@@ -11,15 +11,25 @@
 //! - `d/dt(a - b) = d/dt(a) - d/dt(b)` (difference rule)
 //! - `d/dt(a * b) = a' * b + a * b'` (product rule)
 //! - `d/dt(der(x)) = der(der(x))` (higher derivatives)
+//! - `d/dt(f(u)) = f_der(u, der(u))` for functions with `annotation(derivative=f_der)`
+//!
+//! [`partial_derivative`] differentiates with respect to a variable instead,
+//! for Jacobians (e.g. of the steady-state equations), with the chain rule
+//! for the built-in math functions.
 //!
 //! ## References
 //!
 //! - Pantelides, C. (1988). "The Consistent Initialization of Differential-Algebraic Systems"
 //! - Mattsson, S.E. & Söderlind, G. (1993). "Index Reduction in Differential-Algebraic Equations"
 
+use crate::ir::analysis::function_annotations::FunctionDerivatives;
 use crate::ir::ast::{
-    ComponentRefPart, ComponentReference, Equation, Expression, OpBinary, TerminalType, Token,
+    ComponentRefPart, ComponentReference, Equation, Expression, OpBinary, OpUnary, TerminalType,
+    Token,
 };
+use crate::ir::literal::parse_real;
+use crate::ir::structural::VariableFinder;
+use crate::ir::visitor::Visitable;
 
 /// Symbolically differentiate an equation with respect to time
 ///
@@ -31,13 +41,29 @@ use crate::ir::ast::{
 ///
 /// The differentiated equation, or `None` if the equation type is not supported
 pub fn differentiate_equation(equation: &Equation) -> Option<Equation> {
-    if let Equation::Simple { lhs, rhs, .. } = equation {
-        let diff_lhs = differentiate_expression(lhs);
-        let diff_rhs = differentiate_expression(rhs);
+    differentiate_equation_with(equation, &FunctionDerivatives::default())
+}
 
+/// Symbolically differentiate an equation with respect to time, using the
+/// `derivative` annotations of user functions for the chain rule
+pub fn differentiate_equation_with(
+    equation: &Equation,
+    derivatives: &FunctionDerivatives,
+) -> Option<Equation> {
+    if let Equation::Simple {
+        lhs,
+        rhs,
+        description,
+    } = equation
+    {
+        let diff_lhs = differentiate_expression_with(lhs, derivatives);
+        let diff_rhs = differentiate_expression_with(rhs, derivatives);
+
+        // The derivative keeps the description of the equation
         Some(Equation::Simple {
             lhs: diff_lhs,
             rhs: diff_rhs,
+            description: description.clone(),
         })
     } else {
         None
@@ -54,6 +80,15 @@ pub fn differentiate_equation(equation: &Equation) -> Option<Equation> {
 ///
 /// The differentiated expression
 pub fn differentiate_expression(expr: &Expression) -> Expression {
+    differentiate_expression_with(expr, &FunctionDerivatives::default())
+}
+
+/// Symbolically differentiate an expression with respect to time, using the
+/// `derivative` annotations of user functions for the chain rule
+pub fn differentiate_expression_with(
+    expr: &Expression,
+    derivatives: &FunctionDerivatives,
+) -> Expression {
     match expr {
         Expression::ComponentReference(cref) => {
             // d/dt(x) = der(x)
@@ -77,15 +112,15 @@ pub fn differentiate_expression(expr: &Expression) -> Expression {
                     // d/dt(a + b) = d/dt(a) + d/dt(b)
                     // d/dt(a - b) = d/dt(a) - d/dt(b)
                     Expression::Binary {
-                        lhs: Box::new(differentiate_expression(lhs)),
+                        lhs: Box::new(differentiate_expression_with(lhs, derivatives)),
                         op: op.clone(),
-                        rhs: Box::new(differentiate_expression(rhs)),
+                        rhs: Box::new(differentiate_expression_with(rhs, derivatives)),
                     }
                 }
                 OpBinary::Mul(_) => {
                     // Product rule: d/dt(a * b) = a' * b + a * b'
-                    let da = differentiate_expression(lhs);
-                    let db = differentiate_expression(rhs);
+                    let da = differentiate_expression_with(lhs, derivatives);
+                    let db = differentiate_expression_with(rhs, derivatives);
                     Expression::Binary {
                         lhs: Box::new(Expression::Binary {
                             lhs: Box::new(da),
@@ -102,8 +137,8 @@ pub fn differentiate_expression(expr: &Expression) -> Expression {
                 }
                 OpBinary::Div(_) => {
                     // Quotient rule: d/dt(a / b) = (a' * b - a * b') / b^2
-                    let da = differentiate_expression(lhs);
-                    let db = differentiate_expression(rhs);
+                    let da = differentiate_expression_with(lhs, derivatives);
+                    let db = differentiate_expression_with(rhs, derivatives);
                     Expression::Binary {
                         lhs: Box::new(Expression::Binary {
                             lhs: Box::new(Expression::Binary {
@@ -153,11 +188,16 @@ pub fn differentiate_expression(expr: &Expression) -> Expression {
                 // Wrap in another der call
                 Expression::FunctionCall {
                     comp: comp.clone(),
-                    args: args.iter().map(differentiate_expression).collect(),
+                    args: args
+                        .iter()
+                        .map(|e| differentiate_expression_with(e, derivatives))
+                        .collect(),
                 }
+            } else if let Some(der_call) = annotated_derivative(comp, args, derivatives) {
+                der_call
             } else {
-                // Chain rule for function calls (simplified - just wraps in der)
-                // For proper chain rule, we would need to know the function's derivative
+                // Without an applicable derivative annotation the call is
+                // wrapped in der()
                 wrap_in_der(expr)
             }
         }
@@ -165,41 +205,336 @@ pub fn differentiate_expression(expr: &Expression) -> Expression {
             // d/dt(-x) = -d/dt(x)
             Expression::Unary {
                 op: op.clone(),
-                rhs: Box::new(differentiate_expression(rhs)),
+                rhs: Box::new(differentiate_expression_with(rhs, derivatives)),
             }
         }
         Expression::Array { elements } => {
             // Differentiate each element
             Expression::Array {
-                elements: elements.iter().map(differentiate_expression).collect(),
+                elements: elements
+                    .iter()
+                    .map(|e| differentiate_expression_with(e, derivatives))
+                    .collect(),
             }
         }
         Expression::Tuple { elements } => {
             // Differentiate each element
             Expression::Tuple {
-                elements: elements.iter().map(differentiate_expression).collect(),
+                elements: elements
+                    .iter()
+                    .map(|e| differentiate_expression_with(e, derivatives))
+                    .collect(),
             }
         }
-        Expression::Range { .. } | Expression::If { .. } | Expression::Empty => {
+        Expression::Range { .. }
+        | Expression::If { .. }
+        | Expression::Empty
+        | Expression::NamedArgument { .. } => {
             // For unsupported expressions, wrap in der()
             wrap_in_der(expr)
         }
         Expression::Parenthesized { inner } => {
             // Differentiate the inner expression and preserve parentheses
             Expression::Parenthesized {
-                inner: Box::new(differentiate_expression(inner)),
+                inner: Box::new(differentiate_expression_with(inner, derivatives)),
             }
         }
         Expression::ArrayComprehension { expr, indices } => {
             // Differentiate the expression inside the comprehension
             Expression::ArrayComprehension {
-                expr: Box::new(differentiate_expression(expr)),
+                expr: Box::new(differentiate_expression_with(expr, derivatives)),
                 indices: indices.clone(),
             }
         }
     }
 }
 
+/// Partial derivative of an expression with respect to a variable
+///
+/// Other variables, and `der()` calls other than `var` itself (e.g.
+/// `der(x)`), are held constant. If-expressions are differentiated branch by
+/// branch, and `min`/`max` like the if-expressions they stand for. Returns
+/// `None` for an operator or a function of `var` without a rule (e.g. a user
+/// function).
+pub fn partial_derivative(expr: &Expression, var: &str) -> Option<Expression> {
+    let d = |e: &Expression| partial_derivative(e, var);
+    Some(match expr {
+        Expression::Terminal { .. } => number(0),
+        Expression::ComponentReference(cref) => number(i64::from(cref.to_string() == var)),
+        Expression::Parenthesized { inner } => d(inner)?,
+        Expression::Unary { op, rhs } => match op {
+            OpUnary::Minus(_) | OpUnary::DotMinus(_) => neg(d(rhs)?),
+            OpUnary::Plus(_) | OpUnary::DotPlus(_) => d(rhs)?,
+            _ => return None,
+        },
+        Expression::Binary { op, lhs, rhs } => {
+            let (a, b) = (lhs.as_ref(), rhs.as_ref());
+            let (da, db) = (d(a)?, d(b)?);
+            match op {
+                OpBinary::Add(_) | OpBinary::AddElem(_) => add(da, db),
+                OpBinary::Sub(_) | OpBinary::SubElem(_) => sub(da, db),
+                OpBinary::Mul(_) | OpBinary::MulElem(_) => {
+                    add(mul(da, b.clone()), mul(a.clone(), db))
+                }
+                OpBinary::Div(_) | OpBinary::DivElem(_) => div(
+                    sub(mul(da, b.clone()), mul(a.clone(), db)),
+                    mul(b.clone(), b.clone()),
+                ),
+                // a^b = b * a^(b - 1) * a' for a constant exponent
+                OpBinary::Exp(_) if is_zero(&db) => mul(
+                    mul(
+                        b.clone(),
+                        binary(OpBinary::Exp, a.clone(), sub(b.clone(), number(1))),
+                    ),
+                    da,
+                ),
+                // a^b = a^b * (b' * log(a) + b * a' / a)
+                OpBinary::Exp(_) => mul(
+                    expr.clone(),
+                    add(
+                        mul(db, call("log", vec![a.clone()])),
+                        div(mul(b.clone(), da), a.clone()),
+                    ),
+                ),
+                _ => return None,
+            }
+        }
+        Expression::If {
+            branches,
+            else_branch,
+        } => Expression::If {
+            branches: branches
+                .iter()
+                .map(|(cond, value)| Some((cond.clone(), d(value)?)))
+                .collect::<Option<_>>()?,
+            else_branch: Box::new(d(else_branch)?),
+        },
+        Expression::FunctionCall { comp, args } => {
+            let name = comp.to_string();
+            match (name.as_str(), args.as_slice()) {
+                ("der", _) => number(i64::from(expr.to_string() == var)),
+                ("noEvent", [a]) | ("smooth", [_, a]) => d(a)?,
+                ("min" | "max", [a, b]) => {
+                    let op = if name == "min" {
+                        OpBinary::Lt
+                    } else {
+                        OpBinary::Gt
+                    };
+                    Expression::If {
+                        branches: vec![(binary(op, a.clone(), b.clone()), d(a)?)],
+                        else_branch: Box::new(d(b)?),
+                    }
+                }
+                // atan2(y, x)' = (x * y' - y * x') / (x^2 + y^2)
+                ("atan2", [y, x]) => div(
+                    sub(mul(x.clone(), d(y)?), mul(y.clone(), d(x)?)),
+                    add(mul(x.clone(), x.clone()), mul(y.clone(), y.clone())),
+                ),
+                (_, [a]) => {
+                    let da = d(a)?;
+                    if is_zero(&da) {
+                        return Some(da);
+                    }
+                    let a = a.clone();
+                    let f = |name: &str| call(name, vec![a.clone()]);
+                    let outer = match name.as_str() {
+                        "sin" => f("cos"),
+                        "cos" => neg(f("sin")),
+                        "tan" => div(number(1), mul(f("cos"), f("cos"))),
+                        "asin" => div(
+                            number(1),
+                            call("sqrt", vec![sub(number(1), mul(a.clone(), a))]),
+                        ),
+                        "acos" => neg(div(
+                            number(1),
+                            call("sqrt", vec![sub(number(1), mul(a.clone(), a))]),
+                        )),
+                        "atan" => div(number(1), add(number(1), mul(a.clone(), a))),
+                        "sinh" => f("cosh"),
+                        "cosh" => f("sinh"),
+                        "tanh" => sub(number(1), mul(f("tanh"), f("tanh"))),
+                        "exp" => f("exp"),
+                        "log" => div(number(1), a),
+                        "log10" => div(number(1), mul(a, call("log", vec![number(10)]))),
+                        "sqrt" => div(number(1), mul(number(2), f("sqrt"))),
+                        "abs" => f("sign"),
+                        // Piecewise constant
+                        "sign" | "floor" | "ceil" | "integer" => number(0),
+                        _ => return None,
+                    };
+                    mul(outer, da)
+                }
+                // Functions of arguments that don't depend on var
+                _ => {
+                    for arg in args {
+                        if !is_zero(&d(arg)?) {
+                            return None;
+                        }
+                    }
+                    number(0)
+                }
+            }
+        }
+        _ => return None,
+    })
+}
+
+fn number(value: i64) -> Expression {
+    Expression::Terminal {
+        terminal_type: TerminalType::UnsignedInteger,
+        token: Token {
+            text: value.to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+fn is_number(expr: &Expression, value: f64) -> bool {
+    matches!(
+        expr,
+        Expression::Terminal {
+            terminal_type: TerminalType::UnsignedInteger | TerminalType::UnsignedReal,
+            token,
+        } if parse_real(&token.text) == Some(value)
+    )
+}
+
+fn is_zero(expr: &Expression) -> bool {
+    is_number(expr, 0.0)
+}
+
+fn call(name: &str, args: Vec<Expression>) -> Expression {
+    Expression::FunctionCall {
+        comp: ComponentReference {
+            local: false,
+            parts: vec![ComponentRefPart {
+                ident: Token {
+                    text: name.to_string(),
+                    ..Default::default()
+                },
+                subs: None,
+            }],
+        },
+        args,
+    }
+}
+
+fn binary(op: fn(Token) -> OpBinary, lhs: Expression, rhs: Expression) -> Expression {
+    let parenthesize = |e: Expression| match e {
+        Expression::Binary { .. } | Expression::Unary { .. } => {
+            Expression::Parenthesized { inner: Box::new(e) }
+        }
+        e => e,
+    };
+    Expression::Binary {
+        op: op(Token::default()),
+        lhs: Box::new(parenthesize(lhs)),
+        rhs: Box::new(parenthesize(rhs)),
+    }
+}
+
+fn neg(e: Expression) -> Expression {
+    if is_zero(&e) {
+        return e;
+    }
+    Expression::Unary {
+        op: OpUnary::Minus(Token::default()),
+        rhs: Box::new(Expression::Parenthesized { inner: Box::new(e) }),
+    }
+}
+
+fn add(a: Expression, b: Expression) -> Expression {
+    match (is_zero(&a), is_zero(&b)) {
+        (true, _) => b,
+        (_, true) => a,
+        _ => binary(OpBinary::Add, a, b),
+    }
+}
+
+fn sub(a: Expression, b: Expression) -> Expression {
+    match (is_zero(&a), is_zero(&b)) {
+        (_, true) => a,
+        (true, _) => neg(b),
+        _ => binary(OpBinary::Sub, a, b),
+    }
+}
+
+fn mul(a: Expression, b: Expression) -> Expression {
+    if is_zero(&a) || is_zero(&b) {
+        number(0)
+    } else if is_number(&a, 1.0) {
+        b
+    } else if is_number(&b, 1.0) {
+        a
+    } else {
+        binary(OpBinary::Mul, a, b)
+    }
+}
+
+fn div(a: Expression, b: Expression) -> Expression {
+    if is_zero(&a) || is_number(&b, 1.0) {
+        a
+    } else {
+        binary(OpBinary::Div, a, b)
+    }
+}
+
+/// The derivative of a call given by the `derivative` annotation of the
+/// function: `f_der(inputs..., derivatives of the differentiated inputs...)`.
+///
+/// The arguments are matched to the inputs by position or name, with the
+/// defaults of the inputs without one. None if the function has no
+/// annotation, or a `zeroDerivative` input is given an argument that may
+/// vary in time (see [`FunctionDerivatives::with_time_invariant`]).
+fn annotated_derivative(
+    comp: &ComponentReference,
+    args: &[Expression],
+    derivatives: &FunctionDerivatives,
+) -> Option<Expression> {
+    let rule = derivatives.get(&comp.to_string())?;
+    let args = rule.bind(args)?;
+    let time_invariant = args
+        .iter()
+        .zip(&rule.zero_derivative_inputs)
+        .filter(|(_, zero)| **zero)
+        .all(|(arg, _)| {
+            let mut finder = VariableFinder::new();
+            arg.accept(&mut finder);
+            finder
+                .variables
+                .iter()
+                .all(|var| derivatives.is_time_invariant(var))
+        });
+    if !time_invariant {
+        return None;
+    }
+
+    let mut der_args = args.clone();
+    der_args.extend(
+        args.iter()
+            .zip(&rule.differentiated_inputs)
+            .filter(|(_, differentiated)| **differentiated)
+            .map(|(arg, _)| differentiate_expression_with(arg, derivatives)),
+    );
+    Some(Expression::FunctionCall {
+        comp: ComponentReference {
+            local: false,
+            parts: rule
+                .call_name(&comp.to_string())
+                .split('.')
+                .map(|part| ComponentRefPart {
+                    ident: Token {
+                        text: part.to_string(),
+                        ..Default::default()
+                    },
+                    subs: None,
+                })
+                .collect(),
+        },
+        args: der_args,
+    })
+}
+
 /// Wrap an expression in a der() call
 fn wrap_in_der(expr: &Expression) -> Expression {
     Expression::FunctionCall {
@@ -387,15 +722,146 @@ mod tests {
         let eq = Equation::Simple {
             lhs: make_var("x"),
             rhs: make_var("y"),
+            description: vec![],
         };
 
         let diff_eq = differentiate_equation(&eq);
         assert!(diff_eq.is_some());
 
-        if let Some(Equation::Simple { lhs, rhs }) = diff_eq {
+        if let Some(Equation::Simple { lhs, rhs, .. }) = diff_eq {
             // Both sides should have der()
             assert!(matches!(lhs, Expression::FunctionCall { .. }));
             assert!(matches!(rhs, Expression::FunctionCall { .. }));
         }
     }
+
+    #[test]
+    fn test_differentiate_with_derivative_annotation() {
+        let source = r#"
+            package P
+              function f
+                input Real x;
+                input Integer n;
+                output Real y;
+              algorithm
+                y := x^n;
+                annotation(derivative=f_der);
+              end f;
+            end P;
+        "#;
+        let def = crate::compiler::parse_source_simple(source, "test.mo").unwrap();
+        let derivatives = FunctionDerivatives::from_class_list(&def.class_list);
+
+        // d/dt(P.f(x, 2)) = P.f_der(x, 2, der(x)), the Integer input is not differentiated
+        let call = Expression::FunctionCall {
+            comp: ComponentReference {
+                local: false,
+                parts: ["P", "f"]
+                    .iter()
+                    .map(|p| ComponentRefPart {
+                        ident: Token {
+                            text: p.to_string(),
+                            ..Default::default()
+                        },
+                        subs: None,
+                    })
+                    .collect(),
+            },
+            args: vec![
+                make_var("x"),
+                Expression::Terminal {
+                    terminal_type: TerminalType::UnsignedInteger,
+                    token: Token {
+                        text: "2".to_string(),
+                        ..Default::default()
+                    },
+                },
+            ],
+        };
+        let diff = differentiate_expression_with(&call, &derivatives);
+        let Expression::FunctionCall { comp, args } = diff else {
+            panic!("Expected function call");
+        };
+        assert_eq!(comp.to_string(), "P.f_der");
+        assert_eq!(args.len(), 3);
+        assert_eq!(args[2], make_der(make_var("x")));
+
+        // Without annotations the call is wrapped in der()
+        let diff = differentiate_expression(&call);
+        assert!(matches!(diff, Expression::FunctionCall { comp, .. } if comp.to_string() == "der"));
+    }
+
+    #[test]
+    fn test_differentiate_with_zero_derivative_annotation() {
+        let source = r#"
+            package P
+              function f
+                input Real x;
+                input Real k = 2;
+                output Real y;
+              algorithm
+                y := k * x;
+                annotation(derivative(zeroDerivative=k)=f_der);
+              end f;
+            end P;
+        "#;
+        let def = crate::compiler::parse_source_simple(source, "test.mo").unwrap();
+        let derivatives = FunctionDerivatives::from_class_list(&def.class_list)
+            .with_time_invariant(["p".to_string()]);
+        let diff = |call: &str| {
+            let source = format!("model M\n  Real y = {};\nend M;\n", call);
+            let def = crate::compiler::parse_source_simple(&source, "test.mo").unwrap();
+            let expr = def.class_list["M"].components["y"].start.clone();
+            differentiate_expression_with(&expr, &derivatives).to_string()
+        };
+
+        // k is a parameter, given by position or name, or has its default
+        assert_eq!(diff("P.f(x, p)"), "P.f_der(x, p, der(x))");
+        assert_eq!(diff("P.f(k = p, x = x)"), "P.f_der(x, p, der(x))");
+        assert_eq!(diff("P.f(x)"), "P.f_der(x, 2, der(x))");
+        // The derivative function only applies if k doesn't vary in time
+        assert_eq!(diff("P.f(x, z)"), "der(P.f(x, z))");
+        assert_eq!(diff("P.f(x, 2 * time)"), "der(P.f(x, 2 * time))");
+    }
+
+    #[test]
+    fn test_partial_derivative() {
+        use crate::ir::analysis::division_check::evaluate;
+        use std::collections::HashMap;
+
+        let (x, y) = (make_var("x"), make_var("y"));
+        // x * sin(x) + x^2 / y - exp(x * y) + y^x
+        let expr = sub(
+            add(
+                mul(x.clone(), call("sin", vec![x.clone()])),
+                div(binary(OpBinary::Exp, x.clone(), make_const("2")), y.clone()),
+            ),
+            sub(
+                call("exp", vec![mul(x.clone(), y.clone())]),
+                binary(OpBinary::Exp, y.clone(), x.clone()),
+            ),
+        );
+        let at = |x: f64, y: f64| HashMap::from([("x".to_string(), x), ("y".to_string(), y)]);
+        let value = |x: f64, y: f64| evaluate(&expr, &at(x, y)).unwrap();
+        let (x0, y0, h) = (0.7, 1.3, 1e-6);
+        for (var, expected) in [
+            ("x", (value(x0 + h, y0) - value(x0 - h, y0)) / (2.0 * h)),
+            ("y", (value(x0, y0 + h) - value(x0, y0 - h)) / (2.0 * h)),
+        ] {
+            let derivative = partial_derivative(&expr, var).unwrap();
+            let actual = evaluate(&derivative, &at(x0, y0)).unwrap();
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "d/d{var}: {actual} != {expected}"
+            );
+        }
+
+        // Other variables and der() calls are constants
+        assert!(is_zero(
+            &partial_derivative(&make_der(x.clone()), "x").unwrap()
+        ));
+        assert!(is_zero(&partial_derivative(&y, "x").unwrap()));
+        // User functions of the variable have no rule
+        assert_eq!(partial_derivative(&call("f", vec![x]), "x"), None);
+    }
 }
@@ -242,8 +242,13 @@ pub(super) fn find_maximum_matching(
                 candidates.push(var_idx);
             }
 
-            // Second priority: all other non-forced variables
-            for var in &info.all_variables {
+            // Second priority: other non-forced variables the equation can be
+            // solved for, and last those that only appear inside discontinuities
+            let (smooth, nonsmooth): (Vec<&String>, Vec<&String>) = info
+                .all_variables
+                .iter()
+                .partition(|var| !info.nonsmooth_variables.contains(*var));
+            for var in smooth.into_iter().chain(nonsmooth) {
                 if !exclude_from_matching.contains(var)
                     && let Some(&var_idx) = var_to_idx.get(var)
                     && !forced_var_to_eq.contains_key(&var_idx)
@@ -362,20 +367,6 @@ fn fix_unmatched_variables(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ir::ast::{ComponentRefPart, ComponentReference, Equation, Expression, Token};
-
-    fn make_var(name: &str) -> Expression {
-        Expression::ComponentReference(ComponentReference {
-            local: false,
-            parts: vec![ComponentRefPart {
-                ident: Token {
-                    text: name.to_string(),
-                    ..Default::default()
-                },
-                subs: None,
-            }],
-        })
-    }
 
     #[test]
     fn test_hopcroft_karp_simple_matching() {
@@ -474,23 +465,19 @@ mod tests {
         // Integration test with EquationInfo structures
         let eq_infos = vec![
             EquationInfo {
-                equation: Equation::Simple {
-                    lhs: make_var("x"),
-                    rhs: make_var("y"),
-                },
+                is_simple: true,
                 all_variables: ["x".to_string(), "y".to_string()].into_iter().collect(),
                 lhs_variable: Some("x".to_string()),
                 is_derivative: false,
+                nonsmooth_variables: HashSet::new(),
                 matched_variable: None,
             },
             EquationInfo {
-                equation: Equation::Simple {
-                    lhs: make_var("y"),
-                    rhs: make_var("z"),
-                },
+                is_simple: true,
                 all_variables: ["y".to_string(), "z".to_string()].into_iter().collect(),
                 lhs_variable: Some("y".to_string()),
                 is_derivative: false,
+                nonsmooth_variables: HashSet::new(),
                 matched_variable: None,
             },
         ];
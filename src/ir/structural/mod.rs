@@ -34,36 +34,43 @@
 
 mod causalize;
 pub mod create_dae;
-mod differentiate;
+pub mod differentiate;
 pub mod location;
 mod matching;
 mod pantelides;
 mod scc;
 mod tearing;
 
-use crate::ir::ast::{ComponentReference, Equation, Expression};
+use crate::ir::ast::{ComponentReference, Equation, Expression, Location, Token};
+use crate::ir::transform::constants::is_nondifferentiable_function;
 use crate::ir::visitor::{Visitable, Visitor};
-use causalize::{causalize_equation, check_if_needs_swap, normalize_derivative_equation};
+use causalize::{
+    causalize_equation, check_if_needs_swap, normalize_derivative_equation, references,
+};
 use matching::find_maximum_matching;
 use scc::tarjan_scc;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 // Re-export public APIs
 pub use causalize::has_der_call;
-pub use differentiate::{differentiate_equation, differentiate_expression};
-pub use pantelides::pantelides_index_reduction;
+pub use differentiate::{
+    differentiate_equation, differentiate_equation_with, differentiate_expression,
+    differentiate_expression_with,
+};
+pub use pantelides::{pantelides_index_reduction, pantelides_index_reduction_with_derivatives};
 pub use tearing::{analyze_algebraic_loops, tear_algebraic_loop};
 
 /// Visitor to find all variables referenced in an expression.
 /// Excludes function names (like "der", "sin", etc.) from the variable list.
-struct VariableFinder {
-    variables: HashSet<String>,
+pub(crate) struct VariableFinder {
+    pub(crate) variables: HashSet<String>,
     /// Track when entering a function call to skip the function name
     skip_next_cref: bool,
 }
 
 impl VariableFinder {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             variables: HashSet::new(),
             skip_next_cref: false,
@@ -115,16 +122,69 @@ impl Visitor for DerivativeFinder {
     }
 }
 
+/// Visitor to find the variables that appear outside of discontinuities, i.e.
+/// outside of if-expressions and nondifferentiable functions like `max`.
+/// An equation can only be solved explicitly for these variables.
+#[derive(Default)]
+struct SmoothVariableFinder {
+    variables: HashSet<String>,
+    /// Depth of the discontinuous expressions being visited
+    depth: usize,
+}
+
+impl SmoothVariableFinder {
+    fn is_discontinuous(node: &Expression) -> bool {
+        match node {
+            Expression::If { .. } => true,
+            Expression::FunctionCall { comp, .. } => {
+                is_nondifferentiable_function(&comp.to_string())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Visitor for SmoothVariableFinder {
+    fn enter_expression(&mut self, node: &Expression) {
+        if Self::is_discontinuous(node) {
+            self.depth += 1;
+        } else if self.depth == 0 {
+            match node {
+                Expression::ComponentReference(cref) => {
+                    self.variables.insert(cref.to_string());
+                }
+                Expression::FunctionCall { comp, args }
+                    if comp.to_string() == "der"
+                        && matches!(args.first(), Some(Expression::ComponentReference(_))) =>
+                {
+                    self.variables.insert(node.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn exit_expression(&mut self, node: &Expression) {
+        if Self::is_discontinuous(node) {
+            self.depth -= 1;
+        }
+    }
+}
+
 /// Information about an equation in the BLT graph
 #[derive(Debug, Clone)]
 pub(crate) struct EquationInfo {
-    pub equation: Equation,
+    /// True for simple equations (`lhs = rhs`), the ones BLT solves
+    pub is_simple: bool,
     /// All variables that appear in this equation (both LHS and RHS)
     pub all_variables: HashSet<String>,
     /// Variable on LHS (if in form: var = expr or der(var) = expr)
     pub lhs_variable: Option<String>,
     /// True if this is a derivative equation: der(x) = expr
     pub is_derivative: bool,
+    /// Variables that only appear inside if-expressions or nondifferentiable
+    /// functions, so the equation can't be solved explicitly for them
+    pub nonsmooth_variables: HashSet<String>,
     /// Matched variable (assigned by Hopcroft-Karp)
     pub matched_variable: Option<String>,
 }
@@ -134,7 +194,7 @@ pub(crate) struct EquationInfo {
 /// Algebraic loops occur when equations are mutually dependent and must be
 /// solved simultaneously. Tearing can reduce the computational cost by
 /// selecting a subset of variables to iterate on.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlgebraicLoop {
     /// Indices of equations in this loop
     pub equation_indices: Vec<usize>,
@@ -149,11 +209,29 @@ pub struct AlgebraicLoop {
     pub size: usize,
 }
 
+/// An equation left without an unknown by the matching
+///
+/// A maximum matching leaves an equation unmatched when every unknown it
+/// references is already determined by another equation, so the equations
+/// could only be solved by treating one of the variables excluded from the
+/// matching (a parameter, an input, ...) as unknown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmatchedEquation {
+    /// Index of the unmatched equation
+    pub equation_index: usize,
+    /// Indices of the equations it competes with for its unknowns (reachable
+    /// through alternating paths), in ascending order
+    pub related_indices: Vec<usize>,
+    /// Variables excluded from the matching that these equations reference,
+    /// in ascending order
+    pub excluded_variables: Vec<String>,
+}
+
 /// A dummy derivative variable introduced by index reduction
 ///
 /// When the Pantelides algorithm differentiates constraint equations,
 /// it introduces new algebraic variables representing higher derivatives.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DummyDerivative {
     /// Name of the dummy variable (e.g., "der_x" for der(x))
     pub name: String,
@@ -169,7 +247,7 @@ pub struct DummyDerivative {
 /// - Detecting high-index DAEs that need index reduction
 /// - Identifying algebraic loops that may need tearing
 /// - Diagnosing structural singularities
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StructuralAnalysis {
     /// The DAE index (0 = ODE, 1 = index-1 DAE, 2+ = high index)
     pub dae_index: usize,
@@ -193,10 +271,12 @@ pub struct StructuralAnalysis {
 }
 
 /// Result of BLT transformation including structural information
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BltResult {
     /// Transformed equations in topological order
     pub equations: Vec<Equation>,
+    /// Index of each transformed equation in the input equation list
+    pub source_indices: Vec<usize>,
     /// Strongly connected components (algebraic loops have size > 1)
     pub sccs: Vec<Vec<usize>>,
     /// Matching: equation index -> matched variable name
@@ -205,6 +285,17 @@ pub struct BltResult {
     pub is_complete_matching: bool,
     /// Algebraic loops with tearing information (SCCs with size > 1)
     pub algebraic_loops: Vec<AlgebraicLoop>,
+    /// Indices of transformed equations that could not be solved explicitly for
+    /// their matched variable, e.g. because it only appears inside an
+    /// if-expression or `max()`. They are left implicit for a numeric solver.
+    pub implicit_equations: Vec<usize>,
+    /// Simple equations that could not be matched to an unknown (indices in
+    /// the input equation list)
+    #[serde(default)]
+    pub unmatched_equations: Vec<UnmatchedEquation>,
+    /// Source location of each input equation
+    #[serde(default)]
+    pub locations: Vec<Option<Location>>,
 }
 
 /// Perform BLT transformation on a set of equations
@@ -237,6 +328,11 @@ pub fn blt_transform_with_info(
     equations: Vec<Equation>,
     exclude_from_matching: &HashSet<String>,
 ) -> BltResult {
+    let locations = equations
+        .iter()
+        .map(|eq| eq.get_location().cloned())
+        .collect();
+
     // Parse equations and extract variable information
     let mut eq_infos: Vec<EquationInfo> = Vec::new();
     let mut all_variables_set: HashSet<String> = HashSet::new();
@@ -244,10 +340,11 @@ pub fn blt_transform_with_info(
     for eq in equations.iter() {
         if let Equation::Simple { lhs, rhs, .. } = eq {
             let mut info = EquationInfo {
-                equation: eq.clone(),
+                is_simple: true,
                 all_variables: HashSet::new(),
                 lhs_variable: None,
                 is_derivative: false,
+                nonsmooth_variables: HashSet::new(),
                 matched_variable: None,
             };
 
@@ -300,14 +397,24 @@ pub fn blt_transform_with_info(
                 all_variables_set.insert(var_name);
             }
 
+            let mut smooth_finder = SmoothVariableFinder::default();
+            lhs.accept(&mut smooth_finder);
+            rhs.accept(&mut smooth_finder);
+            info.nonsmooth_variables = info
+                .all_variables
+                .difference(&smooth_finder.variables)
+                .cloned()
+                .collect();
+
             eq_infos.push(info);
         } else {
             // Non-simple equations (If, When, etc.) - keep as-is
             eq_infos.push(EquationInfo {
-                equation: eq.clone(),
+                is_simple: false,
                 all_variables: HashSet::new(),
                 lhs_variable: None,
                 is_derivative: false,
+                nonsmooth_variables: HashSet::new(),
                 matched_variable: None,
             });
         }
@@ -335,41 +442,66 @@ pub fn blt_transform_with_info(
     // Build dependency graph and find ordering using Tarjan's SCC algorithm
     let tarjan_result = tarjan_scc(&eq_infos);
 
-    // Reorder, normalize, and causalize equations
-    let mut result_equations = Vec::new();
+    // Reorder, normalize, and causalize equations. The equations are moved
+    // into the result, only those that are rewritten are built anew.
+    let mut equations = equations;
+    let mut result_equations = Vec::with_capacity(equations.len());
     for idx in &tarjan_result.ordered_indices {
         let info = &eq_infos[*idx];
+        let equation = std::mem::take(&mut equations[*idx]);
+        let Equation::Simple {
+            lhs,
+            rhs,
+            description,
+        } = equation
+        else {
+            result_equations.push(equation);
+            continue;
+        };
 
-        // Normalize derivative equations: if der(x) appears on RHS, swap sides
-        if let Equation::Simple { lhs, rhs, .. } = &info.equation {
-            let needs_swap = check_if_needs_swap(lhs, rhs);
-
-            if needs_swap {
-                // Swap LHS and RHS
-                result_equations.push(Equation::Simple {
-                    lhs: rhs.clone(),
-                    rhs: lhs.clone(),
-                });
-            } else if let Some(normalized) = normalize_derivative_equation(lhs, rhs) {
-                // Normalize derivative equations like C * der(x) = y to der(x) = y / C
-                result_equations.push(normalized);
-            } else {
-                // Check if we need to causalize (solve for matched variable)
-                if let Some(matched_var) = &info.matched_variable {
-                    if let Some(causalized) = causalize_equation(lhs, rhs, matched_var) {
-                        result_equations.push(causalized);
-                    } else {
-                        result_equations.push(info.equation.clone());
-                    }
-                } else {
-                    result_equations.push(info.equation.clone());
-                }
-            }
+        if check_if_needs_swap(&lhs, &rhs) {
+            // Normalize derivative equations: if der(x) appears on RHS, swap sides
+            result_equations.push(Equation::Simple {
+                lhs: rhs,
+                rhs: lhs,
+                description,
+            });
+        } else if let Some(normalized) = normalize_derivative_equation(&lhs, &rhs) {
+            // Normalize derivative equations like C * der(x) = y to der(x) = y / C
+            result_equations.push(keep_description(normalized, description));
+        } else if let Some(causalized) = info
+            .matched_variable
+            .as_ref()
+            .and_then(|matched_var| causalize_equation(&lhs, &rhs, matched_var))
+        {
+            // Solve for the matched variable
+            result_equations.push(keep_description(causalized, description));
         } else {
-            result_equations.push(info.equation.clone());
+            result_equations.push(Equation::Simple {
+                lhs,
+                rhs,
+                description,
+            });
         }
     }
 
+    // Equations that are not of the form `var = expr` without var in expr
+    // could not be causalized for their matched variable
+    let implicit_equations = result_equations
+        .iter()
+        .zip(&tarjan_result.ordered_indices)
+        .enumerate()
+        .filter_map(|(pos, (eq, idx))| {
+            let var = eq_infos[*idx].matched_variable.as_ref()?;
+            let Equation::Simple { lhs, rhs, .. } = eq else {
+                return None;
+            };
+            (lhs.to_string() != *var || references(rhs, var)).then_some(pos)
+        })
+        .collect();
+
+    let unmatched_equations = find_unmatched_equations(&eq_infos, &matching, exclude_from_matching);
+
     // Check if we have a complete matching
     let is_complete_matching = matching.len() == eq_infos.len();
 
@@ -378,11 +510,78 @@ pub fn blt_transform_with_info(
 
     BltResult {
         equations: result_equations,
+        source_indices: tarjan_result.ordered_indices,
         sccs: tarjan_result.sccs,
         matching,
         is_complete_matching,
         algebraic_loops,
+        implicit_equations,
+        unmatched_equations,
+        locations,
+    }
+}
+
+/// An equation rewritten from one with a description string, which it keeps
+fn keep_description(mut equation: Equation, kept: Vec<Token>) -> Equation {
+    if let Equation::Simple { description, .. } = &mut equation {
+        *description = kept;
     }
+    equation
+}
+
+/// Find the simple equations the matching left without an unknown, with the
+/// equations that determine the unknowns they reference
+fn find_unmatched_equations(
+    eq_infos: &[EquationInfo],
+    matching: &HashMap<usize, String>,
+    exclude_from_matching: &HashSet<String>,
+) -> Vec<UnmatchedEquation> {
+    let matched_equation: HashMap<&str, usize> = matching
+        .iter()
+        .map(|(eq, var)| (var.as_str(), *eq))
+        .collect();
+
+    eq_infos
+        .iter()
+        .enumerate()
+        .filter(|(_, info)| info.matched_variable.is_none() && info.is_simple)
+        .map(|(idx, _)| {
+            // Follow alternating paths: unknown -> equation matched to it
+            let mut related = HashSet::new();
+            let mut stack = vec![idx];
+            while let Some(eq) = stack.pop() {
+                for var in &eq_infos[eq].all_variables {
+                    if let Some(&other) = matched_equation.get(var.as_str())
+                        && other != idx
+                        && related.insert(other)
+                    {
+                        stack.push(other);
+                    }
+                }
+            }
+            let mut related_indices: Vec<usize> = related.into_iter().collect();
+            related_indices.sort_unstable();
+
+            let mut excluded_variables: Vec<String> = std::iter::once(idx)
+                .chain(related_indices.iter().copied())
+                .flat_map(|eq| {
+                    eq_infos[eq]
+                        .all_variables
+                        .intersection(exclude_from_matching)
+                })
+                .cloned()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            excluded_variables.sort();
+
+            UnmatchedEquation {
+                equation_index: idx,
+                related_indices,
+                excluded_variables,
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -435,6 +634,7 @@ mod tests {
         let equations = vec![Equation::Simple {
             lhs: make_var("v"),
             rhs: make_der(make_var("h")),
+            description: vec![],
         }];
 
         let result = blt_transform(equations, &HashSet::new());
@@ -458,11 +658,13 @@ mod tests {
             Equation::Simple {
                 lhs: make_var("x"),
                 rhs: make_var("y"),
+                description: vec![],
             },
             // y = z
             Equation::Simple {
                 lhs: make_var("y"),
                 rhs: make_var("z"),
+                description: vec![],
             },
             // z = 1
             Equation::Simple {
@@ -474,6 +676,7 @@ mod tests {
                         ..Default::default()
                     },
                 },
+                description: vec![],
             },
         ];
 
@@ -540,6 +743,7 @@ mod tests {
                     op: OpBinary::Add(Token::default()),
                     rhs: Box::new(one.clone()),
                 },
+                description: vec![],
             },
             // y = x + 1
             Equation::Simple {
@@ -549,6 +753,7 @@ mod tests {
                     op: OpBinary::Add(Token::default()),
                     rhs: Box::new(one),
                 },
+                description: vec![],
             },
         ];
 
@@ -558,6 +763,87 @@ mod tests {
         assert_eq!(result.len(), 2);
     }
 
+    #[test]
+    fn test_blt_reports_equation_solved_through_discontinuity() {
+        // y = time
+        // y = max(x, 0), which has to determine x but can't be solved for it
+        let max = Expression::FunctionCall {
+            comp: ComponentReference {
+                local: false,
+                parts: vec![ComponentRefPart {
+                    ident: Token {
+                        text: "max".to_string(),
+                        ..Default::default()
+                    },
+                    subs: None,
+                }],
+            },
+            args: vec![
+                make_var("x"),
+                Expression::Terminal {
+                    terminal_type: TerminalType::UnsignedInteger,
+                    token: Token {
+                        text: "0".to_string(),
+                        ..Default::default()
+                    },
+                },
+            ],
+        };
+        let equations = vec![
+            Equation::Simple {
+                lhs: make_var("y"),
+                rhs: make_var("time"),
+                description: vec![],
+            },
+            Equation::Simple {
+                lhs: make_var("y"),
+                rhs: max,
+                description: vec![],
+            },
+        ];
+        let exclude = HashSet::from(["time".to_string()]);
+
+        let result = blt_transform_with_info(equations, &exclude);
+
+        assert_eq!(result.implicit_equations.len(), 1);
+        let eq = &result.equations[result.implicit_equations[0]];
+        assert_eq!(format!("{}", eq), "y = max(x, 0)");
+    }
+
+    #[test]
+    fn test_blt_reports_unmatched_equations() {
+        // y = x, y = p and z = 1: y = p is left without an unknown
+        let equations = vec![
+            Equation::Simple {
+                lhs: make_var("y"),
+                rhs: make_var("x"),
+                description: vec![],
+            },
+            Equation::Simple {
+                lhs: make_var("y"),
+                rhs: make_var("p"),
+                description: vec![],
+            },
+            Equation::Simple {
+                lhs: make_var("z"),
+                rhs: make_zero(),
+                description: vec![],
+            },
+        ];
+        let exclude = HashSet::from(["x".to_string(), "p".to_string()]);
+
+        let result = blt_transform_with_info(equations, &exclude);
+
+        assert!(!result.is_complete_matching);
+        assert_eq!(result.unmatched_equations.len(), 1);
+        let unmatched = &result.unmatched_equations[0];
+        let mut block = vec![unmatched.equation_index];
+        block.extend(&unmatched.related_indices);
+        block.sort();
+        assert_eq!(block, [0, 1]);
+        assert_eq!(unmatched.excluded_variables, ["p", "x"]);
+    }
+
     #[test]
     fn test_causalize_already_causal() {
         // Test: a = b should return None (already in causal form for "a")
@@ -587,7 +873,7 @@ mod tests {
             "Should be able to causalize a + b = 0 for a"
         );
 
-        if let Some(Equation::Simple { lhs, rhs }) = result {
+        if let Some(Equation::Simple { lhs, rhs, .. }) = result {
             // LHS should be "a"
             if let Expression::ComponentReference(cref) = lhs {
                 assert_eq!(cref.to_string(), "a");
@@ -659,7 +945,7 @@ mod tests {
             "Should be able to causalize 0 = a + b for a"
         );
 
-        if let Some(Equation::Simple { lhs, rhs }) = result {
+        if let Some(Equation::Simple { lhs, rhs, .. }) = result {
             // LHS should be "a"
             if let Expression::ComponentReference(cref) = lhs {
                 assert_eq!(cref.to_string(), "a");
@@ -695,6 +981,7 @@ mod tests {
                 rhs: Box::new(make_var("L1_p_i")),
             },
             rhs: make_zero(),
+            description: vec![],
         }];
 
         let result = blt_transform(equations, &HashSet::new());
@@ -724,6 +1011,7 @@ mod tests {
                 lhs: Box::new(make_var("R2_n_i")),
                 rhs: Box::new(make_var("L1_p_i")),
             },
+            description: vec![],
         }];
 
         let result = blt_transform(equations, &HashSet::new());
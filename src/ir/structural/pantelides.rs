@@ -21,137 +21,84 @@
 //!
 //! ## Algorithm
 //!
-//! 1. Build a bipartite graph between equations and variables
-//! 2. Find maximum matching using Hopcroft-Karp
-//! 3. If matching is incomplete, differentiate unmatched constraint equations
-//! 4. Repeat until all equations can be matched
+//! Each equation is matched to one of the highest derivatives of the unknowns
+//! it references, along an augmenting path. If there is none, the equations
+//! and variables visited by the search form a structurally singular subset:
+//! the equations are differentiated, their variables get a derivative, and
+//! the search is repeated with the derivative of the equation. The structural
+//! index is the largest number of differentiations, plus one if algebraic
+//! variables remain.
 //!
 //! ## References
 //!
 //! - Pantelides, C. (1988). "The Consistent Initialization of Differential-Algebraic Systems"
 //! - Mattsson, S.E. & Söderlind, G. (1993). "Index Reduction in Differential-Algebraic Equations"
 
-use super::differentiate::differentiate_equation;
+use super::differentiate::differentiate_equation_with;
 use super::{DummyDerivative, StructuralAnalysis};
+use crate::ir::analysis::function_annotations::FunctionDerivatives;
 use crate::ir::ast::{ComponentReference, Equation, Expression};
 use crate::ir::visitor::{Visitable, Visitor};
-use std::collections::{HashMap, HashSet, VecDeque};
-
-/// Information about an equation for structural analysis
-#[derive(Debug, Clone)]
-struct EquationStructure {
-    /// Original equation
-    equation: Equation,
-    /// All variables in this equation
-    variables: HashSet<String>,
-    /// Derivative variables (from der() calls)
-    derivatives: HashSet<String>,
-    /// Whether this is a constraint (no derivatives)
-    is_constraint: bool,
-    /// Differentiation level (0 = original, 1 = differentiated once, etc.)
-    diff_level: usize,
-}
-
-/// Visitor to find all variables referenced in an expression.
-struct VariableFinder {
-    variables: HashSet<String>,
+use std::collections::{HashMap, HashSet};
+
+/// Visitor to collect the variables of an expression with their derivative
+/// order. Function names are skipped.
+#[derive(Default)]
+struct OrderCollector {
+    orders: HashSet<(String, usize)>,
+    /// Number of der() calls being visited
+    depth: usize,
     skip_next_cref: bool,
 }
 
-impl VariableFinder {
-    fn new() -> Self {
-        Self {
-            variables: HashSet::new(),
-            skip_next_cref: false,
-        }
-    }
+fn is_der_call(node: &Expression) -> bool {
+    matches!(node, Expression::FunctionCall { comp, .. } if comp.to_string() == "der")
 }
 
-impl Visitor for VariableFinder {
+impl Visitor for OrderCollector {
     fn enter_expression(&mut self, node: &Expression) {
         if matches!(node, Expression::FunctionCall { .. }) {
             self.skip_next_cref = true;
         }
-    }
-
-    fn enter_component_reference(&mut self, node: &ComponentReference) {
-        if self.skip_next_cref {
-            self.skip_next_cref = false;
-        } else {
-            self.variables.insert(node.to_string());
+        if is_der_call(node) {
+            self.depth += 1;
         }
     }
-}
 
-/// Visitor to collect derivative expressions
-struct DerivativeCollector {
-    derivatives: HashSet<String>,
-}
-
-impl DerivativeCollector {
-    fn new() -> Self {
-        Self {
-            derivatives: HashSet::new(),
+    fn exit_expression(&mut self, node: &Expression) {
+        if is_der_call(node) {
+            self.depth -= 1;
         }
     }
-}
 
-impl Visitor for DerivativeCollector {
-    fn enter_expression(&mut self, node: &Expression) {
-        if let Expression::FunctionCall { comp, args } = node
-            && comp.to_string() == "der"
-            && !args.is_empty()
-            && let Expression::ComponentReference(cref) = &args[0]
-        {
-            self.derivatives.insert(cref.to_string());
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        if self.skip_next_cref {
+            self.skip_next_cref = false;
+        } else {
+            self.orders.insert((node.to_string(), self.depth));
         }
     }
 }
 
-/// Analyze the structure of a single equation
-fn analyze_equation_structure(equation: &Equation) -> EquationStructure {
-    let mut variables = HashSet::new();
-    let mut derivatives = HashSet::new();
-
+/// Variables of an equation with the number of der() calls around them, e.g.
+/// `("x", 1)` and `("v", 0)` for `der(x) = v`
+fn derivative_orders(equation: &Equation) -> HashSet<(String, usize)> {
+    let mut collector = OrderCollector::default();
     if let Equation::Simple { lhs, rhs, .. } = equation {
-        // Find all variables
-        let mut var_finder = VariableFinder::new();
-        lhs.accept(&mut var_finder);
-        rhs.accept(&mut var_finder);
-        variables = var_finder.variables;
-
-        // Find derivatives (der() calls)
-        let mut der_finder = DerivativeCollector::new();
-        lhs.accept(&mut der_finder);
-        rhs.accept(&mut der_finder);
-        derivatives = der_finder.derivatives;
-
-        // IMPORTANT: Also add der(x) as a variable for matching purposes
-        // When der(x) appears in an equation, it should be considered as
-        // a variable that the equation can be matched to.
-        for der_var in &derivatives {
-            variables.insert(format!("der({})", der_var));
-        }
-    }
-
-    let is_constraint = derivatives.is_empty();
-
-    EquationStructure {
-        equation: equation.clone(),
-        variables,
-        derivatives,
-        is_constraint,
-        diff_level: 0,
+        lhs.accept(&mut collector);
+        rhs.accept(&mut collector);
     }
+    collector.orders
 }
 
 /// Pantelides algorithm for structural index reduction
 ///
 /// The algorithm works by:
-/// 1. Building a bipartite graph between equations and variables
-/// 2. Finding maximum matching
-/// 3. If matching is incomplete, differentiate unmatched constraint equations
-/// 4. Repeat until all equations can be matched
+/// 1. Matching each equation to a highest derivative of an unknown along an
+///    augmenting path
+/// 2. If no path exists, differentiating the equations visited by the search
+///    and adding a derivative to the variables visited
+/// 3. Repeating the search with the differentiated equation
 ///
 /// # Arguments
 ///
@@ -168,364 +115,296 @@ pub fn pantelides_index_reduction(
     state_variables: &HashSet<String>,
     algebraic_variables: Option<&HashSet<String>>,
 ) -> StructuralAnalysis {
-    let mut analysis = StructuralAnalysis::default();
+    pantelides_index_reduction_with_derivatives(
+        equations,
+        state_variables,
+        algebraic_variables,
+        &FunctionDerivatives::default(),
+    )
+}
 
-    // Parse equations to extract structure
-    let mut eq_structures: Vec<EquationStructure> =
-        equations.iter().map(analyze_equation_structure).collect();
+/// Pantelides algorithm using the `derivative` annotations of user functions
+/// when differentiating constraint equations
+pub fn pantelides_index_reduction_with_derivatives(
+    equations: &[Equation],
+    state_variables: &HashSet<String>,
+    algebraic_variables: Option<&HashSet<String>>,
+    derivatives: &FunctionDerivatives,
+) -> StructuralAnalysis {
+    let mut analysis = StructuralAnalysis::default();
 
-    // Collect all variables that appear in equations
-    let mut all_equation_vars: HashSet<String> = HashSet::new();
-    for eq_struct in &eq_structures {
-        all_equation_vars.extend(eq_struct.variables.clone());
+    let orders: Vec<HashSet<(String, usize)>> = equations.iter().map(derivative_orders).collect();
+
+    // The unknowns are the states (whose derivatives the equations determine)
+    // and the algebraic variables, not parameters or constants
+    let mut unknowns: HashSet<String> = state_variables.clone();
+    match algebraic_variables {
+        Some(alg_vars) => unknowns.extend(alg_vars.iter().cloned()),
+        None => {
+            // Without explicit algebraic variables, every other variable is unknown
+            unknowns.extend(
+                orders
+                    .iter()
+                    .flatten()
+                    .filter(|(name, _)| !state_variables.contains(name))
+                    .map(|(name, _)| name.clone()),
+            );
+        }
     }
 
-    // Build the set of UNKNOWN variables (what we solve for)
-    // This should NOT include:
-    // - Parameters/constants (like L, g)
-    // - State variables (like x, y) - states are determined by INTEGRATION, not by equations
-    // It SHOULD include:
-    // - Derivative variables (der(x), der(y), etc.) - equations determine these
-    // - Algebraic unknowns (lambda, etc.)
-    let mut unknown_variables: HashSet<String> = HashSet::new();
-
-    // Add derivative variables for states (NOT the states themselves!)
-    for state in state_variables {
-        unknown_variables.insert(format!("der({})", state));
+    let mut graph = PantelidesGraph::new(equations.len(), &unknowns, derivatives);
+    let mut states: Vec<&String> = state_variables.iter().collect();
+    states.sort();
+    for state in states {
+        graph.variable(state, 1);
     }
-
-    // Add algebraic unknowns
-    if let Some(alg_vars) = algebraic_variables {
-        unknown_variables.extend(alg_vars.clone());
-    } else {
-        // If no explicit algebraic vars provided, assume all non-state, non-derivative vars are unknowns
-        for var in &all_equation_vars {
-            if !state_variables.contains(var) && !var.starts_with("der(") {
-                unknown_variables.insert(var.clone());
-            }
-        }
+    let n_variables = graph.variables.len();
+    for (eq, orders) in equations.iter().zip(&orders) {
+        graph.add_equation(eq.clone(), orders);
     }
 
-    // Iteratively apply Pantelides until we get a complete matching
-    let mut iteration = 0;
-    let max_iterations = 10; // Prevent infinite loops
-
-    while iteration < max_iterations {
-        iteration += 1;
-
-        // Build bipartite graph and find matching
-        let (matching, unmatched_eqs) =
-            find_structural_matching(&eq_structures, &unknown_variables, state_variables);
-
-        // Find the maximum differentiation level
-        let max_level = eq_structures
-            .iter()
-            .map(|e| e.diff_level)
-            .max()
-            .unwrap_or(0);
-
-        // Count equations at the highest level
-        let highest_level_eqs: Vec<usize> = eq_structures
-            .iter()
-            .enumerate()
-            .filter(|(_, e)| e.diff_level == max_level)
-            .map(|(i, _)| i)
-            .collect();
-
-        // Check for convergence:
-        // Success when all equations at the highest differentiation level are matched
-        let unmatched_at_highest: Vec<usize> = unmatched_eqs
-            .iter()
-            .filter(|&&idx| idx < eq_structures.len() && eq_structures[idx].diff_level == max_level)
-            .copied()
-            .collect();
-
-        if unmatched_at_highest.is_empty() && !highest_level_eqs.is_empty() {
-            // All equations at highest level are matched - success!
-            analysis.dae_index = max_level;
-            break;
-        }
-
-        // Alternative convergence: we matched all unknowns
-        if matching.len() >= unknown_variables.len() {
-            analysis.dae_index = max_level;
-            break;
-        }
-
-        // Find equations to differentiate using augmenting path approach
-        // First, find unmatched equations at highest level
-        let mut eqs_to_diff: Vec<usize> = if !unmatched_at_highest.is_empty() {
-            unmatched_at_highest.clone()
-        } else {
-            find_equations_to_differentiate(&eq_structures, &unmatched_eqs)
-        };
-
-        if eqs_to_diff.is_empty() {
-            // Structurally singular - cannot reduce index
+    for k in 0..equations.len() {
+        if let Err(message) = graph.assign(k) {
             analysis.is_singular = true;
-            analysis.diagnostics.push(
-                "Structurally singular system: cannot find equations to differentiate".to_string(),
-            );
+            analysis.diagnostics.push(message);
             break;
         }
+    }
 
-        // Augmenting path: also differentiate equations that are matched to variables
-        // used by the unmatched equations. This breaks the "conflict" and allows
-        // the differentiated unmatched equation to take that variable.
-        let mut additional_eqs: Vec<usize> = Vec::new();
-        for &unmatched_idx in &eqs_to_diff {
-            if unmatched_idx < eq_structures.len() {
-                let unmatched_eq = &eq_structures[unmatched_idx];
-                // Find which unknowns this equation could potentially match to
-                for var in &unmatched_eq.variables {
-                    if unknown_variables.contains(var) {
-                        // This variable is an unknown. Find which equation is currently matched to it.
-                        for (&eq_idx, matched_var) in &matching {
-                            if matched_var == var
-                                && !eqs_to_diff.contains(&eq_idx)
-                                && !additional_eqs.contains(&eq_idx)
-                            {
-                                // This equation is matched to a variable we want
-                                // Differentiate it too to free up the variable
-                                additional_eqs.push(eq_idx);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        eqs_to_diff.extend(additional_eqs);
-
-        // Differentiate the identified equations
-        for eq_idx in eqs_to_diff {
-            if eq_idx < eq_structures.len() {
-                let eq_struct = &eq_structures[eq_idx];
-
-                // Record that this equation needs differentiation
-                *analysis
-                    .equations_to_differentiate
-                    .entry(eq_idx)
-                    .or_insert(0) += 1;
-
-                // Create differentiated version of the equation
-                if let Some(diff_eq) = differentiate_equation(&eq_struct.equation) {
-                    let mut diff_struct = analyze_equation_structure(&diff_eq);
-                    diff_struct.diff_level = eq_struct.diff_level + 1;
-
-                    // Add new derivative variables ONLY for STATE variables
-                    for var in &diff_struct.derivatives {
-                        if state_variables.contains(var) {
-                            let der_var = format!("der({})", var);
-                            if unknown_variables.insert(der_var.clone()) {
-                                // Record dummy derivative
-                                analysis.dummy_derivatives.push(DummyDerivative {
-                                    name: der_var,
-                                    base_variable: var.clone(),
-                                    order: diff_struct.diff_level,
-                                });
-                            }
-                        }
-                    }
-
-                    eq_structures.push(diff_struct);
-                }
-            }
+    // Number of times each original equation is differentiated
+    let counts: Vec<usize> = (0..equations.len())
+        .map(|k| graph.differentiation_count(k))
+        .collect();
+    for (k, &count) in counts.iter().enumerate() {
+        if count > 0 {
+            analysis.equations_to_differentiate.insert(k, count);
         }
-
-        analysis.dae_index = iteration;
     }
 
-    if iteration >= max_iterations {
-        analysis.is_singular = true;
-        analysis
-            .diagnostics
-            .push("Index reduction did not converge".to_string());
+    for (base, order) in &graph.variables[n_variables..] {
+        analysis.dummy_derivatives.push(DummyDerivative {
+            name: derivative_name(base, *order),
+            base_variable: base.clone(),
+            order: *order,
+        });
     }
 
+    // Algebraic variables remain if a variable that never appears
+    // differentiated determines an equation
+    let has_algebraic = (0..graph.variables.len()).any(|j| {
+        graph.derivative[j].is_none() && graph.variables[j].1 == 0 && graph.assignment[j].is_some()
+    });
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    analysis.dae_index = max_count + usize::from(has_algebraic);
+
     analysis
 }
 
-/// Find structural matching between equations and variables
-fn find_structural_matching(
-    eq_structures: &[EquationStructure],
-    all_variables: &HashSet<String>,
-    state_variables: &HashSet<String>,
-) -> (HashMap<usize, String>, Vec<usize>) {
-    let n_equations = eq_structures.len();
-    let vars: Vec<String> = all_variables.iter().cloned().collect();
-    let n_variables = vars.len();
-
-    let var_to_idx: HashMap<&String, usize> =
-        vars.iter().enumerate().map(|(i, v)| (v, i)).collect();
-
-    // Build adjacency list with proper causality constraints
-    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n_equations];
-    for (eq_idx, eq_struct) in eq_structures.iter().enumerate() {
-        for var in &eq_struct.variables {
-            // Never allow matching to state variables
-            if state_variables.contains(var) {
-                continue;
-            }
-            if let Some(&var_idx) = var_to_idx.get(var) {
-                adj[eq_idx].push(var_idx);
-            }
-        }
-        // Add derivative variables
-        for der_var in &eq_struct.derivatives {
-            if state_variables.contains(der_var) {
-                let der_var_name = format!("der({})", der_var);
-                if let Some(&var_idx) = var_to_idx.get(&der_var_name) {
-                    adj[eq_idx].push(var_idx);
-                }
-            }
-        }
+/// Name of a derivative, e.g. `der(der(x))` for the second derivative of x
+fn derivative_name(base: &str, order: usize) -> String {
+    let mut name = base.to_string();
+    for _ in 0..order {
+        name = format!("der({})", name);
     }
+    name
+}
 
-    // Run Hopcroft-Karp
-    let mut hk = HopcroftKarp::new(n_equations, n_variables, adj);
-    hk.max_matching();
-
-    // Extract matching
-    let mut matching = HashMap::new();
-    let mut unmatched = Vec::new();
+/// Bipartite graph of equations and variables (unknowns and their
+/// derivatives), which grows as equations are differentiated
+struct PantelidesGraph<'a> {
+    /// Number of equations differentiated at most before the system is
+    /// considered structurally singular
+    max_differentiations: usize,
+    unknowns: &'a HashSet<String>,
+    function_derivatives: &'a FunctionDerivatives,
+    /// Base name and derivative order of each variable
+    variables: Vec<(String, usize)>,
+    variable_index: HashMap<(String, usize), usize>,
+    /// Index of the derivative of each variable, once it appears
+    derivative: Vec<Option<usize>>,
+    /// Equation each variable is matched to (only used for highest derivatives)
+    assignment: Vec<Option<usize>>,
+    equations: Vec<Option<Equation>>,
+    /// Variables referenced by each equation
+    incidence: Vec<Vec<usize>>,
+    /// Index of the derivative of each equation, once it is differentiated
+    differentiated: Vec<Option<usize>>,
+    /// Index of the equation each equation is the derivative of
+    original: Vec<Option<usize>>,
+    colored_equations: Vec<bool>,
+    colored_variables: Vec<bool>,
+}
 
-    for (eq_idx, var_idx) in hk.pair_eq.iter().enumerate() {
-        if *var_idx != NIL && *var_idx < vars.len() {
-            matching.insert(eq_idx, vars[*var_idx].clone());
-        } else {
-            unmatched.push(eq_idx);
+impl<'a> PantelidesGraph<'a> {
+    fn new(
+        max_differentiations: usize,
+        unknowns: &'a HashSet<String>,
+        function_derivatives: &'a FunctionDerivatives,
+    ) -> Self {
+        Self {
+            max_differentiations,
+            unknowns,
+            function_derivatives,
+            variables: Vec::new(),
+            variable_index: HashMap::new(),
+            derivative: Vec::new(),
+            assignment: Vec::new(),
+            equations: Vec::new(),
+            incidence: Vec::new(),
+            differentiated: Vec::new(),
+            original: Vec::new(),
+            colored_equations: Vec::new(),
+            colored_variables: Vec::new(),
         }
     }
 
-    (matching, unmatched)
-}
-
-/// Find which equations need to be differentiated
-fn find_equations_to_differentiate(
-    eq_structures: &[EquationStructure],
-    unmatched_eqs: &[usize],
-) -> Vec<usize> {
-    // Find unmatched equations at the highest differentiation level
-    let mut max_diff_level = 0;
-    let mut to_diff = Vec::new();
-
-    for &eq_idx in unmatched_eqs {
-        if eq_idx < eq_structures.len() {
-            let level = eq_structures[eq_idx].diff_level;
-            if level > max_diff_level {
-                max_diff_level = level;
-                to_diff.clear();
-                to_diff.push(eq_idx);
-            } else if level == max_diff_level {
-                to_diff.push(eq_idx);
-            }
+    /// Index of a derivative of an unknown, adding it and its lower
+    /// derivatives as needed
+    fn variable(&mut self, base: &str, order: usize) -> usize {
+        if let Some(&j) = self.variable_index.get(&(base.to_string(), order)) {
+            return j;
+        }
+        let lower = (order > 0).then(|| self.variable(base, order - 1));
+        let j = self.variables.len();
+        self.variables.push((base.to_string(), order));
+        self.variable_index.insert((base.to_string(), order), j);
+        self.derivative.push(None);
+        self.assignment.push(None);
+        if let Some(lower) = lower {
+            self.derivative[lower] = Some(j);
         }
+        j
     }
 
-    // If all unmatched are at level 0, prefer constraints
-    if max_diff_level == 0 && !to_diff.is_empty() {
-        let constraints: Vec<usize> = to_diff
+    fn add_equation(&mut self, equation: Equation, orders: &HashSet<(String, usize)>) -> usize {
+        let mut orders: Vec<&(String, usize)> = orders
             .iter()
-            .filter(|&&idx| eq_structures[idx].is_constraint)
-            .copied()
+            .filter(|(name, _)| self.unknowns.contains(name))
             .collect();
-        if !constraints.is_empty() {
-            return constraints;
-        }
+        orders.sort();
+        let incidence = orders
+            .into_iter()
+            .map(|(name, order)| self.variable(name, *order))
+            .collect();
+        self.push_equation(Some(equation), incidence)
+    }
+
+    fn push_equation(&mut self, equation: Option<Equation>, incidence: Vec<usize>) -> usize {
+        self.equations.push(equation);
+        self.incidence.push(incidence);
+        self.differentiated.push(None);
+        self.original.push(None);
+        self.equations.len() - 1
+    }
+
+    /// Add the derivative of an equation
+    fn differentiate(&mut self, l: usize) -> usize {
+        let derivative = self.equations[l]
+            .as_ref()
+            .and_then(|eq| differentiate_equation_with(eq, self.function_derivatives));
+        let new = match derivative {
+            Some(eq) => {
+                let orders = derivative_orders(&eq);
+                self.add_equation(eq, &orders)
+            }
+            None => {
+                // Structurally, the derivative references the variables of the
+                // equation and their derivatives
+                let mut incidence = self.incidence[l].clone();
+                for j in self.incidence[l].clone() {
+                    let (base, order) = self.variables[j].clone();
+                    incidence.push(self.variable(&base, order + 1));
+                }
+                incidence.sort_unstable();
+                incidence.dedup();
+                self.push_equation(None, incidence)
+            }
+        };
+        self.differentiated[l] = Some(new);
+        self.original[new] = Some(l);
+        new
     }
 
-    to_diff
-}
-
-// ============================================================================
-// Hopcroft-Karp Algorithm (local copy for Pantelides)
-// ============================================================================
-
-const NIL: usize = usize::MAX;
-
-struct HopcroftKarp {
-    n_equations: usize,
-    adj: Vec<Vec<usize>>,
-    pair_eq: Vec<usize>,
-    pair_var: Vec<usize>,
-    dist: Vec<usize>,
-}
-
-impl HopcroftKarp {
-    fn new(n_equations: usize, n_variables: usize, adj: Vec<Vec<usize>>) -> Self {
-        Self {
-            n_equations,
-            adj,
-            pair_eq: vec![NIL; n_equations],
-            pair_var: vec![NIL; n_variables],
-            dist: vec![0; n_equations + 1],
+    /// Number of derivatives of an equation that were added
+    fn differentiation_count(&self, mut i: usize) -> usize {
+        let mut count = 0;
+        while let Some(next) = self.differentiated[i] {
+            count += 1;
+            i = next;
         }
-    }
+        count
+    }
+
+    /// Match an original equation, differentiating it and the equations it
+    /// competes with until an augmenting path exists
+    fn assign(&mut self, k: usize) -> Result<(), String> {
+        let mut i = k;
+        loop {
+            self.colored_equations = vec![false; self.equations.len()];
+            self.colored_variables = vec![false; self.variables.len()];
+            if self.augment(i) {
+                return Ok(());
+            }
 
-    fn max_matching(&mut self) -> usize {
-        let mut matching = 0;
-        while self.bfs() {
-            for eq in 0..self.n_equations {
-                if self.pair_eq[eq] == NIL && self.dfs(eq) {
-                    matching += 1;
-                }
+            // Differentiating an equation without unknowns doesn't help, and a
+            // nonsingular system never needs more differentiations than it
+            // has equations
+            if self.incidence[i].is_empty()
+                || self.differentiation_count(k) >= self.max_differentiations
+            {
+                return Err(format!(
+                    "Structurally singular system: equation {} can't be matched to an unknown",
+                    k
+                ));
             }
-        }
-        matching
-    }
 
-    fn bfs(&mut self) -> bool {
-        let mut queue = VecDeque::new();
-        for eq in 0..self.n_equations {
-            if self.pair_eq[eq] == NIL {
-                self.dist[eq] = 0;
-                queue.push_back(eq);
-            } else {
-                self.dist[eq] = usize::MAX;
+            let colored_variables: Vec<usize> = (0..self.variables.len())
+                .filter(|&j| self.colored_variables[j])
+                .collect();
+            let colored_equations: Vec<usize> = (0..self.equations.len())
+                .filter(|&l| self.colored_equations[l])
+                .collect();
+            for &j in &colored_variables {
+                let (base, order) = self.variables[j].clone();
+                self.variable(&base, order + 1);
             }
-        }
-        self.dist[self.n_equations] = usize::MAX;
-
-        while let Some(eq) = queue.pop_front() {
-            if self.dist[eq] < self.dist[self.n_equations] {
-                for &var in &self.adj[eq] {
-                    let next_eq = self.pair_var[var];
-                    let next_idx = if next_eq == NIL {
-                        self.n_equations
-                    } else {
-                        next_eq
-                    };
-                    if self.dist[next_idx] == usize::MAX {
-                        self.dist[next_idx] = self.dist[eq] + 1;
-                        if next_eq != NIL {
-                            queue.push_back(next_eq);
-                        }
-                    }
+            for &l in &colored_equations {
+                self.differentiate(l);
+            }
+            for &j in &colored_variables {
+                if let (Some(der_j), Some(l)) = (self.derivative[j], self.assignment[j]) {
+                    self.assignment[der_j] = self.differentiated[l];
                 }
             }
+            i = self.differentiated[i].expect("colored equations are differentiated");
         }
-        self.dist[self.n_equations] != usize::MAX
     }
 
-    fn dfs(&mut self, eq: usize) -> bool {
-        if eq == NIL {
+    /// Search an augmenting path from an equation over the highest
+    /// derivatives, coloring the equations and variables visited
+    fn augment(&mut self, i: usize) -> bool {
+        self.colored_equations[i] = true;
+        let candidates: Vec<usize> = self.incidence[i]
+            .iter()
+            .copied()
+            .filter(|&j| self.derivative[j].is_none())
+            .collect();
+        if let Some(&j) = candidates.iter().find(|&&j| self.assignment[j].is_none()) {
+            self.assignment[j] = Some(i);
             return true;
         }
-        for i in 0..self.adj[eq].len() {
-            let var = self.adj[eq][i];
-            let next_eq = self.pair_var[var];
-            let next_idx = if next_eq == NIL {
-                self.n_equations
-            } else {
-                next_eq
-            };
-            if self.dist[next_idx] == self.dist[eq] + 1 && self.dfs(next_eq) {
-                self.pair_var[var] = eq;
-                self.pair_eq[eq] = var;
+        for j in candidates {
+            if self.colored_variables[j] {
+                continue;
+            }
+            self.colored_variables[j] = true;
+            if let Some(l) = self.assignment[j]
+                && self.augment(l)
+            {
+                self.assignment[j] = Some(i);
                 return true;
             }
         }
-        self.dist[eq] = usize::MAX;
         false
     }
 }
@@ -604,14 +483,15 @@ mod tests {
         let eq = Equation::Simple {
             lhs: make_der(make_var("x")),
             rhs: make_var("v"),
+            description: vec![],
         };
 
-        let structure = analyze_equation_structure(&eq);
+        let orders = derivative_orders(&eq);
 
-        assert!(structure.variables.contains("x"));
-        assert!(structure.variables.contains("v"));
-        assert!(structure.derivatives.contains("x"));
-        assert!(!structure.is_constraint);
+        assert_eq!(
+            orders,
+            HashSet::from([("x".to_string(), 1), ("v".to_string(), 0)])
+        );
     }
 
     #[test]
@@ -620,10 +500,11 @@ mod tests {
         let eq = Equation::Simple {
             lhs: make_var("x"),
             rhs: make_var("y"),
+            description: vec![],
         };
 
-        let structure = analyze_equation_structure(&eq);
-        assert!(structure.is_constraint);
+        let orders = derivative_orders(&eq);
+        assert!(orders.iter().all(|(_, order)| *order == 0));
     }
 
     #[test]
@@ -635,6 +516,7 @@ mod tests {
                 op: OpUnary::Minus(Token::default()),
                 rhs: Box::new(make_var("x")),
             },
+            description: vec![],
         }];
 
         let states: HashSet<String> = ["x".to_string()].into_iter().collect();
@@ -654,11 +536,13 @@ mod tests {
             Equation::Simple {
                 lhs: make_der(make_var("x")),
                 rhs: make_var("vx"),
+                description: vec![],
             },
             // der(y) = vy
             Equation::Simple {
                 lhs: make_der(make_var("y")),
                 rhs: make_var("vy"),
+                description: vec![],
             },
             // der(vx) = -lambda * x
             Equation::Simple {
@@ -667,6 +551,7 @@ mod tests {
                     op: OpUnary::Minus(Token::default()),
                     rhs: Box::new(make_mul(make_var("lambda"), make_var("x"))),
                 },
+                description: vec![],
             },
             // der(vy) = -lambda * y - g
             Equation::Simple {
@@ -678,6 +563,7 @@ mod tests {
                     },
                     make_var("g"),
                 ),
+                description: vec![],
             },
             // x^2 + y^2 = L^2 (constraint)
             Equation::Simple {
@@ -686,6 +572,7 @@ mod tests {
                     make_mul(make_var("y"), make_var("y")),
                 ),
                 rhs: make_mul(make_var("L"), make_var("L")),
+                description: vec![],
             },
         ];
 
@@ -699,11 +586,12 @@ mod tests {
         let analysis = pantelides_index_reduction(&equations, &states, Some(&algebraic));
 
         // The pendulum should be detected as a high-index DAE
-        assert!(
-            analysis.dae_index > 0,
-            "Pendulum should be detected as high-index DAE (got index {})",
-            analysis.dae_index
+        assert_eq!(
+            analysis.dae_index, 3,
+            "Pendulum should be detected as an index-3 DAE"
         );
+        // The position constraint is differentiated twice
+        assert_eq!(analysis.equations_to_differentiate.get(&4), Some(&2));
 
         // The constraint equation should need differentiation
         assert!(
@@ -727,22 +615,20 @@ mod tests {
                 make_mul(make_var("y"), make_var("y")),
             ),
             rhs: make_mul(make_var("L"), make_var("L")),
+            description: vec![],
         };
 
-        let structure = analyze_equation_structure(&constraint);
+        let orders = derivative_orders(&constraint);
 
-        assert!(
-            structure.is_constraint,
-            "x^2 + y^2 = L^2 should be detected as constraint"
-        );
-        assert!(
-            structure.derivatives.is_empty(),
-            "Constraint should have no derivatives"
+        assert_eq!(
+            orders,
+            HashSet::from([
+                ("x".to_string(), 0),
+                ("y".to_string(), 0),
+                ("L".to_string(), 0)
+            ]),
+            "x^2 + y^2 = L^2 should be detected as a constraint without derivatives"
         );
-
-        assert!(structure.variables.contains("x"));
-        assert!(structure.variables.contains("y"));
-        assert!(structure.variables.contains("L"));
     }
 
     #[test]
@@ -757,10 +643,12 @@ mod tests {
                     op: OpUnary::Minus(Token::default()),
                     rhs: Box::new(make_var("y")),
                 },
+                description: vec![],
             },
             Equation::Simple {
                 lhs: make_add(make_var("x"), make_var("y")),
                 rhs: make_const("1"),
+                description: vec![],
             },
         ];
 
@@ -769,10 +657,7 @@ mod tests {
 
         let analysis = pantelides_index_reduction(&equations, &states, Some(&algebraic));
 
-        // Index-1 DAE should have low index
-        assert!(
-            analysis.dae_index <= 1,
-            "Simple index-1 DAE should have index <= 1"
-        );
+        assert_eq!(analysis.dae_index, 1, "Simple DAE should have index 1");
+        assert!(analysis.equations_to_differentiate.is_empty());
     }
 }
@@ -294,10 +294,12 @@ mod tests {
             Equation::Simple {
                 lhs: make_var("x"),
                 rhs: make_add(make_var("y"), make_const("1")),
+                description: vec![],
             },
             Equation::Simple {
                 lhs: make_var("y"),
                 rhs: make_add(make_var("x"), make_const("1")),
+                description: vec![],
             },
         ];
 
@@ -316,6 +318,7 @@ mod tests {
         let equations = vec![Equation::Simple {
             lhs: make_var("x"),
             rhs: make_const("1"),
+            description: vec![],
         }];
 
         let variables: HashSet<String> = ["x".to_string()].into_iter().collect();
@@ -344,6 +347,7 @@ mod tests {
                     op: crate::ir::ast::OpUnary::Minus(Token::default()),
                     rhs: Box::new(make_mul(make_var("lambda"), make_var("x"))),
                 },
+                description: vec![],
             },
             // ay = -lambda * y - g
             Equation::Simple {
@@ -355,6 +359,7 @@ mod tests {
                     },
                     make_var("g"),
                 ),
+                description: vec![],
             },
             // ax*x + ay*y + vx*vx + vy*vy = 0 (second derivative of constraint)
             Equation::Simple {
@@ -369,6 +374,7 @@ mod tests {
                     ),
                 ),
                 rhs: make_const("0"),
+                description: vec![],
             },
         ];
 
@@ -392,10 +398,12 @@ mod tests {
             Equation::Simple {
                 lhs: make_var("x"),
                 rhs: make_add(make_var("y"), make_const("1")),
+                description: vec![],
             },
             Equation::Simple {
                 lhs: make_var("y"),
                 rhs: make_add(make_var("x"), make_const("1")),
+                description: vec![],
             },
         ];
 
@@ -415,10 +423,12 @@ mod tests {
             Equation::Simple {
                 lhs: make_var("x"),
                 rhs: make_const("1"),
+                description: vec![],
             },
             Equation::Simple {
                 lhs: make_var("y"),
                 rhs: make_var("x"),
+                description: vec![],
             },
         ];
 
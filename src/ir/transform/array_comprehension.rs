@@ -50,13 +50,14 @@ fn expand_in_equation(eq: &mut crate::ir::ast::Equation, params: &IndexMap<Strin
     use crate::ir::ast::Equation;
 
     match eq {
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             expand_in_expression(lhs, params);
             expand_in_expression(rhs, params);
         }
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 expand_in_expression(&mut block.cond, params);
@@ -73,12 +74,13 @@ fn expand_in_equation(eq: &mut crate::ir::ast::Equation, params: &IndexMap<Strin
         Equation::For {
             indices: _,
             equations,
+            ..
         } => {
             for eq in equations {
                 expand_in_equation(eq, params);
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
                 expand_in_expression(&mut block.cond, params);
                 for eq in &mut block.eqs {
@@ -136,6 +138,9 @@ fn expand_in_expression(expr: &mut Expression, params: &IndexMap<String, Compone
         Expression::Parenthesized { inner } => {
             expand_in_expression(inner, params);
         }
+        Expression::NamedArgument { value, .. } => {
+            expand_in_expression(value, params);
+        }
         Expression::ArrayComprehension { expr: inner, .. } => {
             expand_in_expression(inner, params);
         }
@@ -155,7 +160,7 @@ fn expand_in_expression(expr: &mut Expression, params: &IndexMap<String, Compone
 }
 
 /// Try to expand an array comprehension into an explicit array
-fn try_expand_comprehension(
+pub(crate) fn try_expand_comprehension(
     expr: &Expression,
     indices: &[ForIndex],
     params: &IndexMap<String, Component>,
@@ -409,6 +414,10 @@ fn substitute_variable(expr: &Expression, var_name: &str, value: i64) -> Express
         Expression::Parenthesized { inner } => Expression::Parenthesized {
             inner: Box::new(substitute_variable(inner, var_name, value)),
         },
+        Expression::NamedArgument { name, value: arg } => Expression::NamedArgument {
+            name: name.clone(),
+            value: Box::new(substitute_variable(arg, var_name, value)),
+        },
         Expression::ArrayComprehension {
             expr: inner,
             indices,
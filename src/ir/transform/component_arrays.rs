@@ -0,0 +1,550 @@
+//! Arrays of components, e.g. `Motor m[3]` or `Pin p[2]`.
+//!
+//! Arrays of instances of classes with components are flattened into one
+//! instance per element, named like scalarized array elements: `m[1]`, `m[2]`
+//! and `m[3]`, with components `m[1].J`, .... Array-valued modifications of
+//! the array are split among the elements (`Motor m[3](J = {1, 2, 3})`),
+//! unless they have the `each` prefix (`Motor m[3](each J = 0.1)`).
+//!
+//! Once all components are expanded, references to elements, i.e. a reference
+//! part `m` subscripted with `[2]` followed by `J`, are renamed to the single
+//! name `m[2].J`, like references to the components of scalar instances.
+//! Before that,
+//! for-equations referring to elements with their loop indices are unrolled,
+//! and connect equations of array slices like `connect(m[1:2].n, m[2:3].p)` or
+//! `connect(m.p, ground.p)` are expanded to connections of the elements.
+//! In expressions, components of the whole array like `m.J` are expanded to
+//! the array `{m[1].J, m[2].J, m[3].J}`, and array comprehensions and
+//! reductions over the elements like `sum(m[i].J for i in 1:3)` to the arrays
+//! of their elements.
+
+use anyhow::Result;
+use indexmap::{IndexMap, IndexSet};
+
+use crate::ir::ast::{
+    ClassDefinition, Component, ComponentRefPart, ComponentReference, Equation, Expression,
+    Subscript, TerminalType, Token, Variability,
+};
+use crate::ir::transform::array_comprehension::try_expand_comprehension;
+use crate::ir::transform::equation_expander::{
+    eval_integer_with_params, get_iteration_range, substitute_index,
+};
+use crate::ir::visitor::{MutVisitable, MutVisitor, Visitable, Visitor};
+
+/// Indices of the elements of an array of a shape, in row-major order
+pub(crate) fn element_indices(shape: &[usize]) -> Vec<Vec<usize>> {
+    shape.iter().fold(vec![vec![]], |indices, &dim| {
+        indices
+            .iter()
+            .flat_map(|index| {
+                (1..=dim).map(move |i| {
+                    let mut index = index.clone();
+                    index.push(i);
+                    index
+                })
+            })
+            .collect()
+    })
+}
+
+/// Index of the element at a 1-based flat position of an array of a shape,
+/// in row-major order: position 3 of a `[2, 2]` array is `[2, 1]`
+pub(crate) fn element_index(flat_index: usize, shape: &[usize]) -> Vec<usize> {
+    let mut rest = flat_index.saturating_sub(1);
+    let mut index = vec![0; shape.len()];
+    for (i, &dim) in shape.iter().enumerate().rev() {
+        let dim = dim.max(1);
+        index[i] = rest % dim + 1;
+        rest /= dim;
+    }
+    index
+}
+
+/// Flattened name of an element of a component array, e.g. `m[1]` or `m[1,2]`
+pub(crate) fn element_name(name: &str, index: &[usize]) -> String {
+    let index: Vec<String> = index.iter().map(|i| i.to_string()).collect();
+    format!("{}[{}]", name, index.join(","))
+}
+
+/// The value of an array modification for one element of a component array:
+/// the element of an array literal, or the subscripted array it refers to.
+/// Other values apply to every element, like modifications with `each`.
+///
+/// `shape` is the shape of the component array and `is_array` tells whether
+/// a (flattened) name refers to an array.
+pub(crate) fn element_modification(
+    expr: &Expression,
+    index: &[usize],
+    shape: &[usize],
+    is_array: &dyn Fn(&str) -> bool,
+) -> Result<Expression> {
+    let (Some((&first, rest)), Some((&dim, rest_shape))) =
+        (index.split_first(), shape.split_first())
+    else {
+        return Ok(expr.clone());
+    };
+    match expr {
+        Expression::Array { elements } => {
+            if elements.len() != dim {
+                anyhow::bail!(
+                    "Modification {} has {} elements, but the array has {}",
+                    crate::fmt::format_expression(expr),
+                    elements.len(),
+                    dim
+                );
+            }
+            element_modification(&elements[first - 1], rest, rest_shape, is_array)
+        }
+        Expression::Parenthesized { inner } => element_modification(inner, index, shape, is_array),
+        Expression::ComponentReference(cref)
+            if cref.parts.last().is_some_and(|part| part.subs.is_none())
+                && is_array(&cref.to_string()) =>
+        {
+            let mut cref = cref.clone();
+            if let Some(part) = cref.parts.last_mut() {
+                part.subs = Some(index.iter().map(|&i| integer_subscript(i)).collect());
+            }
+            Ok(Expression::ComponentReference(cref))
+        }
+        _ => Ok(expr.clone()),
+    }
+}
+
+/// Rename the references to elements of component arrays in a flattened class
+///
+/// `arrays` has the shapes of the component arrays by flattened name, and
+/// `instances` the flattened names of all expanded components.
+pub(crate) fn expand_array_references(
+    class: &mut ClassDefinition,
+    arrays: &IndexMap<String, Vec<usize>>,
+    instances: &IndexSet<String>,
+) -> Result<()> {
+    if arrays.is_empty() {
+        return Ok(());
+    }
+    let components = class.components.clone();
+
+    for equations in [&mut class.equations, &mut class.initial_equations] {
+        let mut unrolled = Vec::new();
+        for eq in equations.iter() {
+            unroll(eq, arrays, &components, &mut unrolled);
+        }
+        let mut expanded = Vec::new();
+        for eq in unrolled {
+            match eq {
+                Equation::Connect { lhs, rhs, .. } => {
+                    expand_connect(&lhs, &rhs, arrays, &components, &mut expanded)?
+                }
+                eq => expanded.push(eq),
+            }
+        }
+        *equations = expanded;
+    }
+
+    let parameters = components
+        .iter()
+        .filter(|(_, comp)| matches!(comp.variability, Variability::Parameter(..)))
+        .map(|(name, comp)| (name.clone(), comp.clone()))
+        .collect();
+    class.accept_mut(&mut ElementExpander { arrays, parameters });
+    class.accept_mut(&mut ElementNamer {
+        arrays,
+        instances,
+        components: &components,
+    });
+    Ok(())
+}
+
+/// Unroll for-equations referring to elements of component arrays, when
+/// their ranges can be evaluated
+fn unroll(
+    eq: &Equation,
+    arrays: &IndexMap<String, Vec<usize>>,
+    components: &IndexMap<String, Component>,
+    out: &mut Vec<Equation>,
+) {
+    let Equation::For {
+        indices, equations, ..
+    } = eq
+    else {
+        out.push(eq.clone());
+        return;
+    };
+    let mut refers = RefersToArrays {
+        arrays,
+        found: false,
+    };
+    eq.accept(&mut refers);
+    let Some((index, rest)) = indices.split_first() else {
+        out.push(eq.clone());
+        return;
+    };
+    let Some((start, end, step)) = refers
+        .found
+        .then(|| get_iteration_range(&index.range, components))
+        .flatten()
+        .filter(|(_, _, step)| *step != 0)
+    else {
+        out.push(eq.clone());
+        return;
+    };
+
+    let mut i = start;
+    while (step > 0 && i <= end) || (step < 0 && i >= end) {
+        for inner in equations {
+            let inner = substitute_index(inner, &index.ident.text, i);
+            if rest.is_empty() {
+                unroll(&inner, arrays, components, out);
+            } else {
+                let nested = Equation::For {
+                    indices: rest.to_vec(),
+                    equations: vec![inner],
+                    description: vec![],
+                };
+                unroll(&nested, arrays, components, out);
+            }
+        }
+        i += step;
+    }
+}
+
+/// Expand a connect equation of array slices to connections of the elements
+fn expand_connect(
+    lhs: &ComponentReference,
+    rhs: &ComponentReference,
+    arrays: &IndexMap<String, Vec<usize>>,
+    components: &IndexMap<String, Component>,
+    out: &mut Vec<Equation>,
+) -> Result<()> {
+    let (Some(mut lhs_elements), Some(mut rhs_elements)) = (
+        slice_elements(lhs, arrays, components),
+        slice_elements(rhs, arrays, components),
+    ) else {
+        out.push(Equation::Connect {
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+            description: vec![],
+        });
+        return Ok(());
+    };
+
+    // A whole array of signal connectors, e.g. `RealInput u[3]`, connects
+    // element-wise to a slice of the same size
+    let split = |cref: &ComponentReference, size: usize| {
+        let comp = components.get(&cref.to_string())?;
+        (comp.shape == [size] && cref.parts.last()?.subs.is_none()).then(|| {
+            (1..=size)
+                .map(|i| {
+                    let mut cref = cref.clone();
+                    if let Some(part) = cref.parts.last_mut() {
+                        part.subs = Some(vec![integer_subscript(i)]);
+                    }
+                    cref
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    match (lhs_elements.len(), rhs_elements.len()) {
+        (1, n) if n > 1 => {
+            if let Some(elements) = split(&lhs_elements[0], n) {
+                lhs_elements = elements;
+            }
+        }
+        (n, 1) if n > 1 => {
+            if let Some(elements) = split(&rhs_elements[0], n) {
+                rhs_elements = elements;
+            }
+        }
+        _ => {}
+    }
+
+    if lhs_elements.len() != rhs_elements.len() {
+        let loc = lhs.get_location().cloned().unwrap_or_default();
+        let format = |cref: &ComponentReference| {
+            crate::fmt::format_expression(&Expression::ComponentReference(cref.clone()))
+        };
+        anyhow::bail!(
+            "{}:{}:{}: connect({}, {}) connects {} connectors to {}",
+            loc.file_name,
+            loc.start_line,
+            loc.start_column,
+            format(lhs),
+            format(rhs),
+            lhs_elements.len(),
+            rhs_elements.len()
+        );
+    }
+    out.extend(
+        lhs_elements
+            .into_iter()
+            .zip(rhs_elements)
+            .map(|(lhs, rhs)| Equation::Connect {
+                lhs,
+                rhs,
+                description: vec![],
+            }),
+    );
+    Ok(())
+}
+
+/// The elements a connector reference stands for, with the component arrays
+/// it refers to subscripted by literal indices: one reference per element of
+/// the arrays it refers to without subscripts, or with ranges or `:`. None if
+/// a subscript can't be evaluated.
+fn slice_elements(
+    cref: &ComponentReference,
+    arrays: &IndexMap<String, Vec<usize>>,
+    components: &IndexMap<String, Component>,
+) -> Option<Vec<ComponentReference>> {
+    let mut elements = vec![(String::new(), Vec::new())];
+    for part in &cref.parts {
+        let mut next = Vec::new();
+        for (prefix, parts) in elements {
+            let name = if prefix.is_empty() {
+                part.ident.text.clone()
+            } else {
+                format!("{}.{}", prefix, part.ident.text)
+            };
+            let Some(shape) = arrays.get(&name) else {
+                let mut parts = parts;
+                parts.push(part.clone());
+                next.push((name, parts));
+                continue;
+            };
+            let subs = part.subs.as_deref().unwrap_or_default();
+            if subs.len() > shape.len() {
+                return None;
+            }
+            // Indices selected in each dimension
+            let mut selected = Vec::new();
+            for (dim, &size) in shape.iter().enumerate() {
+                selected.push(match subs.get(dim) {
+                    None | Some(Subscript::Range { .. }) => (1..=size).collect(),
+                    Some(Subscript::Expression(Expression::Range { start, step, end })) => {
+                        let start = eval_integer_with_params(start, components)?;
+                        let end = eval_integer_with_params(end, components)?;
+                        let step = match step {
+                            Some(step) => eval_integer_with_params(step, components)?,
+                            None => 1,
+                        };
+                        if step <= 0 || start < 1 {
+                            return None;
+                        }
+                        (start..=end)
+                            .step_by(step as usize)
+                            .map(|i| i as usize)
+                            .collect()
+                    }
+                    Some(Subscript::Expression(expr)) => {
+                        vec![usize::try_from(eval_integer_with_params(expr, components)?).ok()?]
+                    }
+                    Some(Subscript::Empty) => return None,
+                });
+            }
+            for index in selected.iter().fold(vec![vec![]], |indices, dim| {
+                indices
+                    .iter()
+                    .flat_map(|index: &Vec<usize>| {
+                        dim.iter().map(move |&i| {
+                            let mut index = index.clone();
+                            index.push(i);
+                            index
+                        })
+                    })
+                    .collect()
+            }) {
+                let mut parts = parts.clone();
+                parts.push(ComponentRefPart {
+                    ident: part.ident.clone(),
+                    subs: Some(index.iter().map(|&i| integer_subscript(i)).collect()),
+                });
+                next.push((element_name(&name, &index), parts));
+            }
+        }
+        elements = next;
+    }
+    Some(
+        elements
+            .into_iter()
+            .map(|(_, parts)| ComponentReference {
+                local: cref.local,
+                parts,
+            })
+            .collect(),
+    )
+}
+
+fn integer_subscript(i: usize) -> Subscript {
+    Subscript::Expression(Expression::Terminal {
+        terminal_type: TerminalType::UnsignedInteger,
+        token: Token {
+            text: i.to_string(),
+            ..Default::default()
+        },
+    })
+}
+
+/// Finds references to component arrays with subscripts
+struct RefersToArrays<'a> {
+    arrays: &'a IndexMap<String, Vec<usize>>,
+    found: bool,
+}
+
+impl Visitor for RefersToArrays<'_> {
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        let mut name = String::new();
+        for part in &node.parts {
+            if !name.is_empty() {
+                name.push('.');
+            }
+            name.push_str(&part.ident.text);
+            if part.subs.is_some() && self.arrays.contains_key(&name) {
+                self.found = true;
+            }
+        }
+    }
+}
+
+/// The array of the element references a reference to a component of whole
+/// component arrays stands for, e.g. `{m[1].J, m[2].J}` for `m.J`, nested
+/// for arrays of several dimensions. None if it doesn't refer to one.
+fn whole_array_elements(
+    cref: &ComponentReference,
+    arrays: &IndexMap<String, Vec<usize>>,
+) -> Option<Expression> {
+    let mut name = String::new();
+    for (k, part) in cref.parts.iter().enumerate() {
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&part.ident.text);
+        if k + 1 < cref.parts.len()
+            && part.subs.is_none()
+            && let Some(shape) = arrays.get(&name)
+        {
+            return Some(element_array(cref, k, shape, &[], arrays));
+        }
+    }
+    None
+}
+
+/// The (nested) array of the elements of the component array of part `k`
+/// of a reference, under the indices selected so far
+fn element_array(
+    cref: &ComponentReference,
+    k: usize,
+    shape: &[usize],
+    index: &[usize],
+    arrays: &IndexMap<String, Vec<usize>>,
+) -> Expression {
+    let Some(&size) = shape.get(index.len()) else {
+        let mut cref = cref.clone();
+        cref.parts[k].subs = Some(index.iter().map(|&i| integer_subscript(i)).collect());
+        // Components further down may be arrays too
+        return whole_array_elements(&cref, arrays).unwrap_or(Expression::ComponentReference(cref));
+    };
+    Expression::Array {
+        elements: (1..=size)
+            .map(|i| {
+                let mut index = index.to_vec();
+                index.push(i);
+                element_array(cref, k, shape, &index, arrays)
+            })
+            .collect(),
+    }
+}
+
+/// Expands references to components of whole component arrays and array
+/// comprehensions over their elements to arrays of elements, see
+/// [`whole_array_elements`]
+struct ElementExpander<'a> {
+    arrays: &'a IndexMap<String, Vec<usize>>,
+    /// The parameters, to evaluate the ranges of comprehensions
+    parameters: IndexMap<String, Component>,
+}
+
+impl MutVisitor for ElementExpander<'_> {
+    fn exit_expression(&mut self, node: &mut Expression) {
+        match node {
+            Expression::ComponentReference(cref) => {
+                if let Some(elements) = whole_array_elements(cref, self.arrays) {
+                    *node = elements;
+                }
+            }
+            Expression::ArrayComprehension { expr, indices } => {
+                let mut refers = RefersToArrays {
+                    arrays: self.arrays,
+                    found: false,
+                };
+                expr.accept(&mut refers);
+                if refers.found
+                    && let Some(expanded) =
+                        try_expand_comprehension(expr, indices, &self.parameters)
+                {
+                    *node = expanded;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Renames references to elements of component arrays and their components
+/// to flattened names, merging the subscripted parts into a single part
+struct ElementNamer<'a> {
+    arrays: &'a IndexMap<String, Vec<usize>>,
+    instances: &'a IndexSet<String>,
+    components: &'a IndexMap<String, Component>,
+}
+
+impl MutVisitor for ElementNamer<'_> {
+    fn exit_component_reference(&mut self, node: &mut ComponentReference) {
+        loop {
+            let first = &node.parts[0];
+            match &first.subs {
+                Some(subs) if self.arrays.contains_key(&first.ident.text) => {
+                    let Some(index) = subs
+                        .iter()
+                        .map(|sub| match sub {
+                            Subscript::Expression(expr) => {
+                                eval_integer_with_params(expr, self.components)
+                                    .and_then(|i| usize::try_from(i).ok())
+                            }
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<usize>>>()
+                    else {
+                        return;
+                    };
+                    let name = element_name(&first.ident.text, &index);
+                    node.parts[0].ident.text = name;
+                    node.parts[0].subs = None;
+                }
+                None if node.parts.len() >= 2 && self.instances.contains(&first.ident.text) => {
+                    let first = node.parts.remove(0);
+                    node.parts[0].ident.text =
+                        format!("{}.{}", first.ident.text, node.parts[0].ident.text);
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_indices() {
+        assert_eq!(element_indices(&[2]), [vec![1], vec![2]]);
+        assert_eq!(
+            element_indices(&[2, 2]),
+            [vec![1, 1], vec![1, 2], vec![2, 1], vec![2, 2]]
+        );
+        assert_eq!(element_name("m", &[1, 2]), "m[1,2]");
+        for (i, index) in element_indices(&[2, 3]).iter().enumerate() {
+            assert_eq!(&element_index(i + 1, &[2, 3]), index);
+        }
+    }
+}
@@ -7,6 +7,7 @@
 //! so they are only substituted when using the fully qualified Modelica.Constants.x form.
 
 use crate::ir::ast::{Expression, TerminalType, Token};
+use crate::ir::literal::format_real;
 use crate::ir::transform::constants::get_modelica_constant;
 use crate::ir::visitor::MutVisitor;
 
@@ -71,11 +72,13 @@ impl MutVisitor for ConstantSubstitutor {
             };
 
             if should_substitute && let Some(value) = get_modelica_constant(&name) {
-                // Replace with a literal value
+                // Replace with a literal value, at the location of the
+                // reference so that it still reads as a binding from source
                 *expr = Expression::Terminal {
                     terminal_type: TerminalType::UnsignedReal,
                     token: Token {
-                        text: format_float(value),
+                        text: format_real(value),
+                        location: comp_ref.parts[0].ident.location.clone(),
                         ..Default::default()
                     },
                 };
@@ -85,29 +88,6 @@ impl MutVisitor for ConstantSubstitutor {
     }
 }
 
-/// Format a float value for Modelica syntax
-/// Uses scientific notation for very large or small numbers
-fn format_float(value: f64) -> String {
-    if value == f64::MAX {
-        // Modelica.Constants.inf
-        "1.7976931348623157e308".to_string()
-    } else if value == f64::MIN_POSITIVE {
-        // Modelica.Constants.small
-        "2.2250738585072014e-308".to_string()
-    } else if value.abs() >= 1e10 || (value != 0.0 && value.abs() < 1e-4) {
-        // Use scientific notation for very large or small numbers
-        format!("{:e}", value)
-    } else {
-        // Use standard notation, ensuring it has a decimal point
-        let s = format!("{}", value);
-        if s.contains('.') || s.contains('e') {
-            s
-        } else {
-            format!("{}.0", s)
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
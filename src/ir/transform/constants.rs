@@ -9,7 +9,8 @@ pub const DERIVATIVE_PREFIX: &str = "der_";
 /// Prefix for previous-value variables (e.g., pre_x for previous value of x)
 pub const PREVIOUS_VALUE_PREFIX: &str = "pre_";
 
-/// Prefix for condition variables (e.g., c0, c1, c2)
+/// Prefix for condition variables (e.g., c0, c1, c2), numbered by
+/// [`FreshNames`](crate::ir::transform::fresh_names::FreshNames)
 pub const CONDITION_PREFIX: &str = "c";
 
 /// Built-in function: derivative operator
@@ -52,6 +53,18 @@ pub const BUILTIN_INITIAL: &str = "initial";
 /// terminal() returns true during the terminal equation evaluation
 pub const BUILTIN_TERMINAL: &str = "terminal";
 
+/// Built-in function: random - pseudo-random number for stochastic models
+/// random(seed) returns a uniform number in [0, 1), drawn when its when-clause fires
+pub const BUILTIN_RANDOM: &str = "random";
+
+/// Built-in function: table1D - interpolation in a 1D lookup table
+/// table1D(table, u) interpolates column 2 of table over its first column
+pub const BUILTIN_TABLE_1D: &str = "table1D";
+
+/// Built-in function: table2D - interpolation in a 2D lookup table
+/// table2D(table, u1, u2) interpolates over the first column and first row of table
+pub const BUILTIN_TABLE_2D: &str = "table2D";
+
 /// Built-in math functions - Trigonometric
 pub const BUILTIN_SIN: &str = "sin";
 pub const BUILTIN_COS: &str = "cos";
@@ -144,7 +157,8 @@ pub const TYPE_STRING: &str = "String";
 
 // =============================================================================
 // Modelica.Constants - Standard mathematical and physical constants
-// Values from MSL 4.1.0 (CODATA 2018 / SI 2019)
+// Values from MSL 4.1.0 (CODATA 2018 / SI 2019), the machine-dependent ones
+// those of f64. The `Modelica.Constants` of builtin:msl-mini declare the same.
 // =============================================================================
 
 /// Mathematical constant pi (π)
@@ -160,7 +174,7 @@ pub const MODELICA_D2R: f64 = std::f64::consts::PI / 180.0;
 pub const MODELICA_R2D: f64 = 180.0 / std::f64::consts::PI;
 
 /// Euler-Mascheroni constant (γ)
-pub const MODELICA_GAMMA: f64 = 0.577_215_664_901_532_9;
+pub const MODELICA_GAMMA: f64 = std::f64::consts::EULER_GAMMA;
 
 /// Machine epsilon - difference between 1 and next representable float
 pub const MODELICA_EPS: f64 = f64::EPSILON;
@@ -390,6 +404,47 @@ pub static BUILTIN_FUNCTIONS: &[BuiltinFunction] = &[
         documentation: "Returns true at the end of a successful simulation.",
         parameters: &[],
     },
+    // Stochastic models
+    BuiltinFunction {
+        name: "random",
+        signature: "random(seed: Integer) -> Real",
+        documentation: "Uniformly distributed pseudo-random number in [0, 1), drawn each time the enclosing when-clause fires. Each call has its own stream, reproducible for a given seed.",
+        parameters: &[("seed", "Seed of the random stream")],
+    },
+    // Lookup tables
+    BuiltinFunction {
+        name: "table1D",
+        signature: "table1D(table: Real[:, 2], u: Real, smoothness: Smoothness = Smoothness.LinearSegments) -> Real",
+        documentation: "Interpolates the second column of table over its first column, which must be strictly increasing. Outside the table the first and last segments are extrapolated. table may also be the path of a CSV file loaded by the backend.",
+        parameters: &[
+            (
+                "table",
+                "Matrix whose rows are the points (u, y), or a CSV file path",
+            ),
+            ("u", "Value to look up"),
+            (
+                "smoothness",
+                "Smoothness.LinearSegments or Smoothness.ConstantSegments",
+            ),
+        ],
+    },
+    BuiltinFunction {
+        name: "table2D",
+        signature: "table2D(table: Real[:, :], u1: Real, u2: Real, smoothness: Smoothness = Smoothness.LinearSegments) -> Real",
+        documentation: "Bilinear interpolation in a 2D table: table[2:, 1] are the u1 values, table[1, 2:] the u2 values and table[2:, 2:] the table values. Both axes must be strictly increasing. table may also be the path of a CSV file loaded by the backend.",
+        parameters: &[
+            (
+                "table",
+                "Matrix with the u1 values in its first column and the u2 values in its first row, or a CSV file path",
+            ),
+            ("u1", "Value to look up along the first column"),
+            ("u2", "Value to look up along the first row"),
+            (
+                "smoothness",
+                "Smoothness.LinearSegments or Smoothness.ConstantSegments",
+            ),
+        ],
+    },
     // Trigonometric functions
     BuiltinFunction {
         name: "sin",
@@ -766,11 +821,6 @@ pub fn previous_value_name(var: &str) -> String {
     format!("{}{}", PREVIOUS_VALUE_PREFIX, var)
 }
 
-/// Helper to create condition variable name
-pub fn condition_name(index: usize) -> String {
-    format!("{}{}", CONDITION_PREFIX, index)
-}
-
 /// List of global built-in symbols that should not be scoped
 pub fn global_builtins() -> Vec<String> {
     let mut builtins = vec![
@@ -786,6 +836,10 @@ pub fn global_builtins() -> Vec<String> {
         BUILTIN_CHANGE.to_string(),
         BUILTIN_INITIAL.to_string(),
         BUILTIN_TERMINAL.to_string(),
+        BUILTIN_RANDOM.to_string(),
+        // Lookup tables
+        BUILTIN_TABLE_1D.to_string(),
+        BUILTIN_TABLE_2D.to_string(),
         // Trigonometric
         BUILTIN_SIN.to_string(),
         BUILTIN_COS.to_string(),
@@ -874,6 +928,64 @@ pub fn is_builtin_function(name: &str) -> bool {
     global_builtins().contains(&name.to_string())
 }
 
+/// Built-in functions with scalar formal parameters that are applied element-wise
+/// when called with array arguments (Modelica spec 12.4.6, "vectorized calls").
+/// e.g., `sin(x)` for `Real x[3]` is `{sin(x[1]), sin(x[2]), sin(x[3])}`.
+pub const ELEMENTWISE_FUNCTIONS: &[&str] = &[
+    BUILTIN_DER,
+    BUILTIN_PRE,
+    BUILTIN_SIN,
+    BUILTIN_COS,
+    BUILTIN_TAN,
+    BUILTIN_ASIN,
+    BUILTIN_ACOS,
+    BUILTIN_ATAN,
+    BUILTIN_ATAN2,
+    BUILTIN_SINH,
+    BUILTIN_COSH,
+    BUILTIN_TANH,
+    BUILTIN_EXP,
+    BUILTIN_LOG,
+    BUILTIN_LOG10,
+    BUILTIN_SQRT,
+    BUILTIN_ABS,
+    BUILTIN_SIGN,
+    BUILTIN_FLOOR,
+    BUILTIN_CEIL,
+    BUILTIN_MOD,
+    BUILTIN_REM,
+    BUILTIN_DIV,
+    BUILTIN_INTEGER,
+    BUILTIN_NO_EVENT,
+    BUILTIN_SEMI_LINEAR,
+];
+
+/// Check if a built-in function is applied element-wise to array arguments
+pub fn is_elementwise_function(name: &str) -> bool {
+    ELEMENTWISE_FUNCTIONS.contains(&name)
+}
+
+/// Built-in functions that are not differentiable everywhere in their arguments,
+/// so an equation can't be solved for a variable that only appears inside them.
+pub const NONDIFFERENTIABLE_FUNCTIONS: &[&str] = &[
+    BUILTIN_ABS,
+    BUILTIN_SIGN,
+    BUILTIN_MIN,
+    BUILTIN_MAX,
+    BUILTIN_FLOOR,
+    BUILTIN_CEIL,
+    BUILTIN_INTEGER,
+    BUILTIN_DIV,
+    BUILTIN_MOD,
+    BUILTIN_REM,
+    BUILTIN_SEMI_LINEAR,
+];
+
+/// Check if a built-in function is nondifferentiable in its arguments
+pub fn is_nondifferentiable_function(name: &str) -> bool {
+    NONDIFFERENTIABLE_FUNCTIONS.contains(&name)
+}
+
 /// Check if a type name is a primitive/built-in type
 pub fn is_primitive_type(name: &str) -> bool {
     matches!(
@@ -956,6 +1068,14 @@ pub mod simple_controller {
     pub const PID: i64 = 4;
 }
 
+/// Smoothness enumeration - interpolation in lookup tables
+/// enumeration(LinearSegments, ContinuousDerivative, ConstantSegments, ...)
+pub mod smoothness {
+    pub const LINEAR_SEGMENTS: i64 = 1;
+    pub const CONTINUOUS_DERIVATIVE: i64 = 2;
+    pub const CONSTANT_SEGMENTS: i64 = 3;
+}
+
 /// Look up a built-in enumeration value by qualified name
 /// Returns the integer value for enumeration literals like "StateSelect.prefer"
 pub fn get_enumeration_value(name: &str) -> Option<i64> {
@@ -1068,6 +1188,19 @@ pub fn get_enumeration_value(name: &str) -> Option<i64> {
         return Some(simple_controller::PID);
     }
 
+    // Smoothness enumeration (Modelica.Blocks.Types.Smoothness)
+    if name == "Smoothness.LinearSegments" || name.ends_with(".Smoothness.LinearSegments") {
+        return Some(smoothness::LINEAR_SEGMENTS);
+    }
+    if name == "Smoothness.ContinuousDerivative"
+        || name.ends_with(".Smoothness.ContinuousDerivative")
+    {
+        return Some(smoothness::CONTINUOUS_DERIVATIVE);
+    }
+    if name == "Smoothness.ConstantSegments" || name.ends_with(".Smoothness.ConstantSegments") {
+        return Some(smoothness::CONSTANT_SEGMENTS);
+    }
+
     None
 }
 
@@ -1120,5 +1253,10 @@ pub fn builtin_enumeration_names() -> Vec<String> {
         "SimpleController.PI".to_string(),
         "SimpleController.PD".to_string(),
         "SimpleController.PID".to_string(),
+        // Smoothness
+        "Smoothness".to_string(),
+        "Smoothness.LinearSegments".to_string(),
+        "Smoothness.ContinuousDerivative".to_string(),
+        "Smoothness.ConstantSegments".to_string(),
     ]
 }
@@ -3,16 +3,27 @@
 //! This module expands structured equations into scalar form according to
 //! the Modelica specification. After flattening, equations should be expanded:
 //!
-//! - For-equations are expanded to individual scalar equations
+//! - For-equations are expanded to individual scalar equations; their ranges
+//!   must be evaluable at compile time from literals, parameters and constants
 //! - Array equations are expanded to individual element equations
 //! - Binding equations in declarations are converted to regular equations
+//! - Vectorized calls of scalar functions (e.g., `sin(x)` for an array `x`)
+//!   are applied element-wise to the scalarized equations
+//! - Algorithm sections are executed symbolically into an equation per
+//!   assigned variable, unrolling their for-loops
 //!
 //! This makes balance checking trivial: just count the number of equations.
 
+use crate::compiler::limits::{Limits, check_deadline};
+use crate::ir::analysis::division_check::evaluate;
 use crate::ir::ast::{
     ClassDefinition, Component, ComponentRefPart, ComponentReference, Equation, Expression,
-    ForIndex, Statement, Subscript, TerminalType, Token,
+    ForIndex, OpBinary, Statement, StatementBlock, Subscript, TerminalType, Token,
 };
+use crate::ir::literal::parse_real;
+use crate::ir::transform::component_arrays::{element_index, element_indices};
+use crate::ir::transform::constants::is_elementwise_function;
+use anyhow::Result;
 use indexmap::IndexMap;
 use std::collections::HashSet;
 
@@ -24,19 +35,58 @@ use std::collections::HashSet;
 /// - Expanding array equations to scalar equations
 /// - Converting binding equations to regular equations
 /// - Converting algorithm sections to equations
-pub fn expand_equations(class: &mut ClassDefinition) {
+///
+/// Fails for for-equations whose range can't be evaluated at compile time.
+pub fn expand_equations(class: &mut ClassDefinition, limits: &Limits) -> Result<()> {
+    expand_equations_with_loops(class, limits).map(|_| ())
+}
+
+/// A for-equation of the class that can be kept as a loop, see
+/// [`expand_equations_with_loops`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolicLoop {
+    /// The for-equation, with its ranges evaluated to literals like `1:100`
+    pub equation: Equation,
+    /// The scalar equations the for-equation was expanded to, in iteration order
+    pub instances: Vec<Equation>,
+}
+
+/// Expand all equations in a class definition to scalar form, like
+/// [`expand_equations`], and return the for-equations that can be kept as
+/// loops by backends that generate loops.
+///
+/// These are the for-equations of the equation section whose equations are
+/// scalar equations for every value of the indices. Their scalar equations
+/// are still added to the class, so the DAE is analyzed as usual.
+pub fn expand_equations_with_loops(
+    class: &mut ClassDefinition,
+    limits: &Limits,
+) -> Result<Vec<SymbolicLoop>> {
     // First, evaluate any parameter-dependent array shapes
     evaluate_array_shapes(&mut class.components);
+    for (name, comp) in &class.components {
+        limits.check_expansion(
+            comp.shape.iter().product(),
+            &comp.name_token.location,
+            || format!("array '{}'", name),
+        )?;
+    }
 
     // Expand structured equations first
     let mut expanded = Vec::new();
+    let mut loops = Vec::new();
     for eq in &class.equations {
-        expand_equation(eq, &class.components, &mut expanded);
+        let first = expanded.len();
+        expand_equation(eq, &class.components, &mut expanded, limits)?;
+        if let Some(symbolic) = symbolic_loop(eq, &expanded[first..], &class.components) {
+            loops.push(symbolic);
+        }
     }
 
     // Convert algorithm sections to equations
     // Each algorithm section contributes one equation per unique variable assigned
-    let algorithm_equations = convert_algorithms_to_equations(&class.algorithms, &class.components);
+    let algorithm_equations =
+        convert_algorithms_to_equations(&class.algorithms, &class.components, limits)?;
     expanded.extend(algorithm_equations);
 
     // Collect binding equations from components (needs expanded equations to find states)
@@ -50,9 +100,122 @@ pub fn expand_equations(class: &mut ClassDefinition) {
     // Also expand initial equations
     let mut expanded_init = Vec::new();
     for eq in &class.initial_equations {
-        expand_equation(eq, &class.components, &mut expanded_init);
+        expand_equation(eq, &class.components, &mut expanded_init, limits)?;
     }
     class.initial_equations = expanded_init;
+    Ok(loops)
+}
+
+/// The loop of a for-equation expanded to scalar equations, if each of its
+/// equations is a scalar equation for every value of the indices
+fn symbolic_loop(
+    eq: &Equation,
+    instances: &[Equation],
+    components: &IndexMap<String, Component>,
+) -> Option<SymbolicLoop> {
+    let Equation::For {
+        indices,
+        equations,
+        description,
+    } = eq
+    else {
+        return None;
+    };
+    if !equations
+        .iter()
+        .all(|eq| matches!(eq, Equation::Simple { .. }))
+    {
+        return None;
+    }
+
+    // Ranges depending on other indices (`j in 1:i`) make the iterations
+    // irregular, so the loop isn't kept
+    let values: Vec<Vec<i64>> = indices
+        .iter()
+        .map(|index| iteration_values(&index.range, components))
+        .collect::<Option<_>>()?;
+    let ranges: Vec<Expression> = values
+        .iter()
+        .map(|values| literal_range(values))
+        .collect::<Option<_>>()?;
+
+    // Each iteration must have expanded to the equations of the loop
+    let mut expected = Vec::new();
+    for tuple in values.iter().fold(vec![vec![]], |tuples, values| {
+        tuples
+            .iter()
+            .flat_map(|tuple: &Vec<i64>| {
+                values.iter().map(move |&value| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(value);
+                    tuple
+                })
+            })
+            .collect()
+    }) {
+        for eq in equations {
+            let instance = indices
+                .iter()
+                .zip(&tuple)
+                .fold(eq.clone(), |eq, (index, &value)| {
+                    substitute_index(&eq, &index.ident.text, value)
+                });
+            expected.push(instance);
+        }
+    }
+    if expected != instances {
+        return None;
+    }
+
+    Some(SymbolicLoop {
+        equation: Equation::For {
+            indices: indices
+                .iter()
+                .zip(ranges)
+                .map(|(index, range)| ForIndex {
+                    ident: index.ident.clone(),
+                    range,
+                })
+                .collect(),
+            equations: equations.clone(),
+            description: description.clone(),
+        },
+        instances: instances.to_vec(),
+    })
+}
+
+/// A literal range `start:end` or `start:step:end` of evenly spaced values,
+/// or a vector of the values otherwise
+fn literal_range(values: &[i64]) -> Option<Expression> {
+    let integer = |value: i64| {
+        let literal = Expression::Terminal {
+            terminal_type: TerminalType::UnsignedInteger,
+            token: Token {
+                text: value.unsigned_abs().to_string(),
+                ..Default::default()
+            },
+        };
+        if value < 0 {
+            Expression::Unary {
+                op: crate::ir::ast::OpUnary::Minus(Token::default()),
+                rhs: Box::new(literal),
+            }
+        } else {
+            literal
+        }
+    };
+    let (&first, &last) = (values.first()?, values.last()?);
+    let step = values.get(1).map_or(1, |second| second - first);
+    if values.windows(2).any(|pair| pair[1] - pair[0] != step) || step == 0 {
+        return Some(Expression::Array {
+            elements: values.iter().copied().map(integer).collect(),
+        });
+    }
+    Some(Expression::Range {
+        start: Box::new(integer(first)),
+        step: (step != 1).then(|| Box::new(integer(step))),
+        end: Box::new(integer(last)),
+    })
 }
 
 /// Evaluate parameter-dependent array shapes.
@@ -174,10 +337,17 @@ fn extract_binding_equations(
             binding_equations.push(Equation::Simple {
                 lhs,
                 rhs: comp.start.clone(),
+                description: vec![],
             });
         } else {
             // Array binding equation - expand to scalars
-            expand_array_binding(name, &comp.shape, &comp.start, &mut binding_equations);
+            expand_array_binding(
+                name,
+                &comp.shape,
+                &comp.start,
+                components,
+                &mut binding_equations,
+            );
         }
     }
 
@@ -187,54 +357,399 @@ fn extract_binding_equations(
 /// Convert algorithm sections to equations.
 ///
 /// In Modelica, each algorithm section contributes one equation per unique
-/// variable that is assigned. This function finds all assigned variables
-/// and creates Simple equations for them.
+/// variable that is assigned. Sections of assignments, if-statements and
+/// for-loops with ranges known at compile time are executed symbolically:
+/// for-loops are unrolled, and each assigned variable gets an equation
+/// `var = expr` with the value the section computes for it. Variables read
+/// before they are assigned have their start value (Modelica spec §11.1.2).
 ///
-/// For balance checking purposes, we create a simple placeholder equation
-/// for each unique assigned variable. The actual algorithm semantics would
-/// need more sophisticated handling for simulation.
+/// Other sections (e.g. with when-statements) get a placeholder equation
+/// `var = var` per assigned variable, for balance checking. Fails for
+/// while-loops, break statements and for-loops whose range can't be
+/// evaluated at compile time, which can't be executed symbolically.
 fn convert_algorithms_to_equations(
     algorithms: &[Vec<Statement>],
     components: &IndexMap<String, Component>,
-) -> Vec<Equation> {
+    limits: &Limits,
+) -> Result<Vec<Equation>> {
     let mut equations = Vec::new();
 
     for algorithm_section in algorithms {
-        // Find all unique variables assigned in this algorithm section
-        let assigned_vars = find_assigned_variables(algorithm_section);
-
-        // Create one equation per assigned variable
-        for var_name in assigned_vars {
-            // Skip if it's an input (inputs don't need equations)
-            if let Some(comp) = components.get(&var_name)
-                && matches!(comp.causality, crate::ir::ast::Causality::Input(..))
-            {
+        match execute_algorithm(algorithm_section, components, limits)? {
+            Some(executed) => equations.extend(executed),
+            None => equations.extend(placeholder_equations(algorithm_section, components)),
+        }
+    }
+
+    Ok(equations)
+}
+
+/// The equations of an algorithm section executed symbolically, see
+/// [`convert_algorithms_to_equations`], or None if it can't be executed
+fn execute_algorithm(
+    statements: &[Statement],
+    components: &IndexMap<String, Component>,
+    limits: &Limits,
+) -> Result<Option<Vec<Equation>>> {
+    check_executable(statements)?;
+    let statements = unroll_for_statements(statements, components)?;
+    Ok(execute_unrolled(&statements, components, limits))
+}
+
+/// Fail for the first while-loop or break statement of algorithm statements,
+/// including nested ones
+fn check_executable(statements: &[Statement]) -> Result<()> {
+    for stmt in statements {
+        let (keyword, loc) = match stmt {
+            Statement::While(_) => ("while-loops", stmt.get_location()),
+            Statement::Break { token, .. } => ("break statements", Some(&token.location)),
+            Statement::For { equations, .. } => {
+                check_executable(equations)?;
+                continue;
+            }
+            Statement::If {
+                cond_blocks,
+                else_block,
+                ..
+            } => {
+                for block in cond_blocks {
+                    check_executable(&block.stmts)?;
+                }
+                check_executable(else_block.as_deref().unwrap_or_default())?;
                 continue;
             }
+            Statement::When(blocks) => {
+                for block in blocks {
+                    check_executable(&block.stmts)?;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let loc = loc.cloned().unwrap_or_default();
+        anyhow::bail!(
+            "{}:{}:{}: {} are not supported in algorithm sections of models",
+            loc.file_name,
+            loc.start_line,
+            loc.start_column,
+            keyword
+        );
+    }
+    Ok(())
+}
 
-            // Create a placeholder equation: var = var (self-assignment)
-            // This is a simplification - the actual algorithm semantics are procedural
-            let comp_ref = ComponentReference {
-                local: false,
-                parts: vec![ComponentRefPart {
-                    ident: Token {
-                        text: var_name.clone(),
-                        ..Default::default()
-                    },
-                    subs: None,
-                }],
-            };
+/// The equations of algorithm statements whose for-loops are unrolled, or
+/// None if they can't be executed
+fn execute_unrolled(
+    statements: &[Statement],
+    components: &IndexMap<String, Component>,
+    limits: &Limits,
+) -> Option<Vec<Equation>> {
+    // The assigned variables, in the order of their first assignment
+    let mut assigned: IndexMap<String, ComponentReference> = IndexMap::new();
+    collect_assignments(statements, &mut assigned);
+    let base_name = |cref: &ComponentReference| {
+        let mut base = cref.clone();
+        if let Some(last) = base.parts.last_mut() {
+            last.subs = None;
+        }
+        base.to_string()
+    };
+    // A variable assigned as a whole and by element would get overlapping
+    // equations
+    if assigned.values().any(|cref| {
+        cref.parts.last().is_some_and(|part| part.subs.is_some())
+            && assigned.contains_key(&base_name(cref))
+    }) {
+        return None;
+    }
 
-            let lhs = Expression::ComponentReference(comp_ref.clone());
-            let rhs = Expression::ComponentReference(comp_ref);
+    let mut values: IndexMap<String, Expression> = IndexMap::new();
+    for (name, cref) in &assigned {
+        if let Some(start) = components
+            .get(&base_name(cref))
+            .map(|comp| &comp.start)
+            .filter(|start| !matches!(start, Expression::Empty | Expression::Array { .. }))
+        {
+            values.insert(name.clone(), start.clone());
+        }
+    }
+    crate::ir::transform::function_inliner::execute_statements(statements, &mut values, true)?;
+
+    let mut equations = Vec::new();
+    for (name, cref) in assigned {
+        // Inputs don't need equations
+        if components
+            .get(&base_name(&cref))
+            .is_some_and(|comp| matches!(comp.causality, crate::ir::ast::Causality::Input(..)))
+        {
+            continue;
+        }
+        let eq = Equation::Simple {
+            lhs: Expression::ComponentReference(cref),
+            rhs: values.get(&name)?.clone(),
+            description: vec![],
+        };
+        expand_equation(&eq, components, &mut equations, limits).ok()?;
+    }
+    Some(equations)
+}
 
-            equations.push(Equation::Simple { lhs, rhs });
+/// Collect the assigned component references of statements by name
+fn collect_assignments(
+    statements: &[Statement],
+    assigned: &mut IndexMap<String, ComponentReference>,
+) {
+    for stmt in statements {
+        match stmt {
+            Statement::Assignment { comp, .. } => {
+                assigned
+                    .entry(comp.to_string())
+                    .or_insert_with(|| comp.clone());
+            }
+            Statement::If {
+                cond_blocks,
+                else_block,
+                ..
+            } => {
+                for block in cond_blocks {
+                    collect_assignments(&block.stmts, assigned);
+                }
+                if let Some(else_stmts) = else_block {
+                    collect_assignments(else_stmts, assigned);
+                }
+            }
+            Statement::For { equations, .. } => collect_assignments(equations, assigned),
+            Statement::When(blocks) => {
+                for block in blocks {
+                    collect_assignments(&block.stmts, assigned);
+                }
+            }
+            Statement::While(block) => collect_assignments(&block.stmts, assigned),
+            _ => {}
         }
     }
+}
+
+/// Placeholder equations `var = var` for the variables an algorithm section
+/// assigns
+fn placeholder_equations(
+    algorithm_section: &[Statement],
+    components: &IndexMap<String, Component>,
+) -> Vec<Equation> {
+    let mut equations = Vec::new();
+
+    // Find all unique variables assigned in this algorithm section
+    let assigned_vars = find_assigned_variables(algorithm_section);
+
+    // Create one equation per assigned variable
+    for var_name in assigned_vars {
+        // Skip if it's an input (inputs don't need equations)
+        if let Some(comp) = components.get(&var_name)
+            && matches!(comp.causality, crate::ir::ast::Causality::Input(..))
+        {
+            continue;
+        }
+
+        // Create a placeholder equation: var = var (self-assignment)
+        // This is a simplification - the actual algorithm semantics are procedural
+        let comp_ref = ComponentReference {
+            local: false,
+            parts: vec![ComponentRefPart {
+                ident: Token {
+                    text: var_name.clone(),
+                    ..Default::default()
+                },
+                subs: None,
+            }],
+        };
+
+        let lhs = Expression::ComponentReference(comp_ref.clone());
+        let rhs = Expression::ComponentReference(comp_ref);
+
+        equations.push(Equation::Simple {
+            lhs,
+            rhs,
+            description: vec![],
+        });
+    }
 
     equations
 }
 
+/// Unroll the for-loops of algorithm statements, including nested ones,
+/// substituting the values of their indices into their bodies. Fails if the
+/// range of a loop can't be evaluated at compile time.
+pub(crate) fn unroll_for_statements(
+    statements: &[Statement],
+    components: &IndexMap<String, Component>,
+) -> Result<Vec<Statement>> {
+    let mut unrolled = Vec::new();
+    for stmt in statements {
+        match stmt {
+            Statement::For {
+                indices, equations, ..
+            } => unroll_for_statement(indices, equations, components, &mut unrolled)?,
+            Statement::If {
+                cond_blocks,
+                else_block,
+                description,
+            } => unrolled.push(Statement::If {
+                cond_blocks: cond_blocks
+                    .iter()
+                    .map(|block| unroll_block(block, components))
+                    .collect::<Result<_>>()?,
+                else_block: match else_block {
+                    Some(stmts) => Some(unroll_for_statements(stmts, components)?),
+                    None => None,
+                },
+                description: description.clone(),
+            }),
+            Statement::When(blocks) => unrolled.push(Statement::When(
+                blocks
+                    .iter()
+                    .map(|block| unroll_block(block, components))
+                    .collect::<Result<_>>()?,
+            )),
+            _ => unrolled.push(stmt.clone()),
+        }
+    }
+    Ok(unrolled)
+}
+
+fn unroll_block(
+    block: &StatementBlock,
+    components: &IndexMap<String, Component>,
+) -> Result<StatementBlock> {
+    Ok(StatementBlock {
+        cond: block.cond.clone(),
+        stmts: unroll_for_statements(&block.stmts, components)?,
+    })
+}
+
+fn unroll_for_statement(
+    indices: &[ForIndex],
+    body: &[Statement],
+    components: &IndexMap<String, Component>,
+    out: &mut Vec<Statement>,
+) -> Result<()> {
+    let Some((index, rest)) = indices.split_first() else {
+        out.extend(unroll_for_statements(body, components)?);
+        return Ok(());
+    };
+    let Some(values) = iteration_values(&index.range, components) else {
+        let loc = &index.ident.location;
+        anyhow::bail!(
+            "{}:{}:{}: The range '{}' of for-loop index '{}' can't be evaluated at compile time \
+             (for-loops with non-constant ranges are not supported in algorithm sections of models)",
+            loc.file_name,
+            loc.start_line,
+            loc.start_column,
+            crate::fmt::format_expression(&index.range),
+            index.ident.text
+        );
+    };
+    let index_name = &index.ident.text;
+    for i in values {
+        check_deadline()?;
+        let rest: Vec<ForIndex> = rest
+            .iter()
+            .map(|index| ForIndex {
+                ident: index.ident.clone(),
+                range: substitute_in_expr(&index.range, index_name, i),
+            })
+            .collect();
+        let substituted: Vec<Statement> = body
+            .iter()
+            .map(|stmt| substitute_index_in_statement(stmt, index_name, i))
+            .collect();
+        unroll_for_statement(&rest, &substituted, components, out)?;
+    }
+    Ok(())
+}
+
+/// Substitute an index variable with a concrete value in a statement.
+pub(crate) fn substitute_index_in_statement(
+    stmt: &Statement,
+    index_name: &str,
+    value: i64,
+) -> Statement {
+    let substitute_cref = |cref: &ComponentReference| match substitute_in_expr(
+        &Expression::ComponentReference(cref.clone()),
+        index_name,
+        value,
+    ) {
+        Expression::ComponentReference(cref) => cref,
+        _ => cref.clone(),
+    };
+    let substitute_all = |stmts: &[Statement]| -> Vec<Statement> {
+        stmts
+            .iter()
+            .map(|stmt| substitute_index_in_statement(stmt, index_name, value))
+            .collect()
+    };
+    let substitute_block = |block: &StatementBlock| StatementBlock {
+        cond: substitute_in_expr(&block.cond, index_name, value),
+        stmts: substitute_all(&block.stmts),
+    };
+    match stmt {
+        Statement::Assignment {
+            comp,
+            value: expr,
+            description,
+        } => Statement::Assignment {
+            comp: substitute_cref(comp),
+            value: substitute_in_expr(expr, index_name, value),
+            description: description.clone(),
+        },
+        Statement::FunctionCall {
+            comp,
+            args,
+            description,
+        } => Statement::FunctionCall {
+            comp: comp.clone(),
+            args: args
+                .iter()
+                .map(|arg| substitute_in_expr(arg, index_name, value))
+                .collect(),
+            description: description.clone(),
+        },
+        // An inner loop with the same index shadows it
+        Statement::For { indices, .. }
+            if indices.iter().any(|idx| idx.ident.text == index_name) =>
+        {
+            stmt.clone()
+        }
+        Statement::For {
+            indices,
+            equations,
+            description,
+        } => Statement::For {
+            indices: indices
+                .iter()
+                .map(|idx| ForIndex {
+                    ident: idx.ident.clone(),
+                    range: substitute_in_expr(&idx.range, index_name, value),
+                })
+                .collect(),
+            equations: substitute_all(equations),
+            description: description.clone(),
+        },
+        Statement::If {
+            cond_blocks,
+            else_block,
+            description,
+        } => Statement::If {
+            cond_blocks: cond_blocks.iter().map(substitute_block).collect(),
+            else_block: else_block.as_deref().map(substitute_all),
+            description: description.clone(),
+        },
+        Statement::When(blocks) => Statement::When(blocks.iter().map(substitute_block).collect()),
+        Statement::While(block) => Statement::While(substitute_block(block)),
+        Statement::Empty | Statement::Return { .. } | Statement::Break { .. } => stmt.clone(),
+    }
+}
+
 /// Find all unique variable names assigned in an algorithm section.
 fn find_assigned_variables(statements: &[Statement]) -> HashSet<String> {
     let mut assigned = HashSet::new();
@@ -263,6 +778,7 @@ fn collect_assigned_variables(stmt: &Statement, assigned: &mut HashSet<String>)
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 for inner in &block.stmts {
@@ -305,7 +821,7 @@ fn find_differentiated_variables(equations: &[Equation]) -> std::collections::Ha
 
 fn find_der_vars_in_equation(eq: &Equation, states: &mut std::collections::HashSet<String>) {
     match eq {
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             find_der_vars_in_expr(lhs, states);
             find_der_vars_in_expr(rhs, states);
         }
@@ -317,6 +833,7 @@ fn find_der_vars_in_equation(eq: &Equation, states: &mut std::collections::HashS
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 for inner in &block.eqs {
@@ -329,7 +846,7 @@ fn find_der_vars_in_equation(eq: &Equation, states: &mut std::collections::HashS
                 }
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
                 for inner in &block.eqs {
                     find_der_vars_in_equation(inner, states);
@@ -396,11 +913,7 @@ fn is_default_value(expr: &Expression) -> bool {
                 TerminalType::UnsignedInteger => token.text == "0",
                 TerminalType::UnsignedReal => {
                     // Check for 0, 0.0, 0.0e0, etc.
-                    if let Ok(val) = token.text.parse::<f64>() {
-                        val == 0.0
-                    } else {
-                        false
-                    }
+                    parse_real(&token.text) == Some(0.0)
                 }
                 TerminalType::Bool => token.text == "false",
                 _ => false,
@@ -415,6 +928,7 @@ fn expand_array_binding(
     name: &str,
     shape: &[usize],
     rhs: &Expression,
+    components: &IndexMap<String, Component>,
     equations: &mut Vec<Equation>,
 ) {
     // For now, handle 1D arrays
@@ -424,6 +938,7 @@ fn expand_array_binding(
             let lhs = make_subscripted_ref(name, &[i]);
 
             // If RHS is an array literal, extract the corresponding element
+            // If RHS is a vectorized call, apply it element-wise
             // Otherwise, subscript the RHS as well
             let rhs_elem = match rhs {
                 Expression::Array { elements } => {
@@ -433,10 +948,26 @@ fn expand_array_binding(
                         subscript_expr(rhs.clone(), &[i])
                     }
                 }
+                Expression::FunctionCall { .. } if is_array_expr(rhs, components) => {
+                    flatten_and_subscript(rhs, i, components)
+                }
                 _ => subscript_expr(rhs.clone(), &[i]),
             };
 
-            equations.push(Equation::Simple { lhs, rhs: rhs_elem });
+            equations.push(Equation::Simple {
+                lhs,
+                rhs: rhs_elem,
+                description: vec![],
+            });
+        }
+    } else if matches!(rhs, Expression::FunctionCall { .. }) && is_array_expr(rhs, components) {
+        // Vectorized calls are applied to the elements in row-major order
+        for (i, index) in element_indices(shape).iter().enumerate() {
+            equations.push(Equation::Simple {
+                lhs: make_subscripted_ref(name, index),
+                rhs: flatten_and_subscript(rhs, i + 1, components),
+                description: vec![],
+            });
         }
     } else {
         // Multi-dimensional arrays - create nested subscripts
@@ -457,7 +988,11 @@ fn expand_array_binding_nd(
         // Base case: all dimensions indexed
         let lhs = make_subscripted_ref(name, indices);
         let rhs_elem = subscript_expr_nd(rhs.clone(), indices);
-        equations.push(Equation::Simple { lhs, rhs: rhs_elem });
+        equations.push(Equation::Simple {
+            lhs,
+            rhs: rhs_elem,
+            description: vec![],
+        });
         return;
     }
 
@@ -473,34 +1008,42 @@ fn expand_equation(
     eq: &Equation,
     components: &IndexMap<String, Component>,
     out: &mut Vec<Equation>,
-) {
+    limits: &Limits,
+) -> Result<()> {
     match eq {
         Equation::Empty => {}
 
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple {
+            lhs,
+            rhs,
+            description,
+        } => {
             // Check if this is an array equation that needs expansion
             if let Some(size) = get_equation_array_size(lhs, components) {
                 if size == 0 {
                     // Empty array equation (e.g., y[0] = u[0]) - no scalar equations
-                    return;
+                    return Ok(());
                 }
                 if size > 1 {
-                    expand_array_equation(lhs, rhs, size, components, out);
-                    return;
+                    expand_array_equation(lhs, rhs, description, size, components, out);
+                    return Ok(());
                 }
             }
             // Scalar equation - keep as is
             out.push(eq.clone());
         }
 
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices, equations, ..
+        } => {
             // Expand for-loop to individual equations
-            expand_for_equation(indices, equations, components, out);
+            expand_for_equation(indices, equations, components, out, limits)?;
         }
 
         Equation::If {
             cond_blocks,
             else_block,
+            description,
         } => {
             // Try to evaluate conditions at compile time for parameter-based conditions
             let mut selected_branch: Option<&Vec<Equation>> = None;
@@ -518,7 +1061,7 @@ fn expand_equation(
                     for block in cond_blocks {
                         let mut expanded_eqs = Vec::new();
                         for inner_eq in &block.eqs {
-                            expand_equation(inner_eq, components, &mut expanded_eqs);
+                            expand_equation(inner_eq, components, &mut expanded_eqs, limits)?;
                         }
                         expanded_cond_blocks.push(crate::ir::ast::EquationBlock {
                             cond: block.cond.clone(),
@@ -526,19 +1069,23 @@ fn expand_equation(
                         });
                     }
 
-                    let expanded_else = else_block.as_ref().map(|eqs| {
-                        let mut expanded = Vec::new();
-                        for inner_eq in eqs {
-                            expand_equation(inner_eq, components, &mut expanded);
+                    let expanded_else = match else_block {
+                        Some(eqs) => {
+                            let mut expanded = Vec::new();
+                            for inner_eq in eqs {
+                                expand_equation(inner_eq, components, &mut expanded, limits)?;
+                            }
+                            Some(expanded)
                         }
-                        expanded
-                    });
+                        None => None,
+                    };
 
                     out.push(Equation::If {
                         cond_blocks: expanded_cond_blocks,
                         else_block: expanded_else,
+                        description: description.clone(),
                     });
-                    return;
+                    return Ok(());
                 }
             }
 
@@ -547,25 +1094,31 @@ fn expand_equation(
 
             if let Some(eqs) = eqs_to_expand {
                 for inner_eq in eqs {
-                    expand_equation(inner_eq, components, out);
+                    expand_equation(inner_eq, components, out, limits)?;
                 }
             }
         }
 
-        Equation::When(blocks) => {
+        Equation::When {
+            blocks,
+            description,
+        } => {
             // Expand equations inside when blocks
             let mut expanded_blocks = Vec::new();
             for block in blocks {
                 let mut expanded_eqs = Vec::new();
                 for inner_eq in &block.eqs {
-                    expand_equation(inner_eq, components, &mut expanded_eqs);
+                    expand_equation(inner_eq, components, &mut expanded_eqs, limits)?;
                 }
                 expanded_blocks.push(crate::ir::ast::EquationBlock {
                     cond: block.cond.clone(),
                     eqs: expanded_eqs,
                 });
             }
-            out.push(Equation::When(expanded_blocks));
+            out.push(Equation::When {
+                blocks: expanded_blocks,
+                description: description.clone(),
+            });
         }
 
         Equation::Connect { .. } | Equation::FunctionCall { .. } => {
@@ -573,57 +1126,116 @@ fn expand_equation(
             out.push(eq.clone());
         }
     }
+    Ok(())
 }
 
 /// Expand a for-equation to individual scalar equations.
+///
+/// Fails if the range of an index can't be evaluated at compile time, as
+/// for-equations aren't kept symbolically.
 fn expand_for_equation(
     indices: &[ForIndex],
     equations: &[Equation],
     components: &IndexMap<String, Component>,
     out: &mut Vec<Equation>,
-) {
-    if indices.is_empty() {
+    limits: &Limits,
+) -> Result<()> {
+    let Some((index, rest)) = indices.split_first() else {
         // No more indices to expand - expand the inner equations
         for eq in equations {
-            expand_equation(eq, components, out);
+            expand_equation(eq, components, out, limits)?;
         }
-        return;
+        return Ok(());
+    };
+
+    // Count the iterations before listing them, as a huge range would
+    // exhaust the memory
+    if let Some((start, end, step)) = get_iteration_range(&index.range, components) {
+        let count = if step == 0 {
+            0
+        } else {
+            ((end as i128 - start as i128) / step as i128 + 1).max(0)
+        };
+        limits.check_expansion(
+            usize::try_from(count).unwrap_or(usize::MAX),
+            &index.ident.location,
+            || format!("for-equation index '{}'", index.ident.text),
+        )?;
     }
 
-    // Get the range for the first index
-    let index = &indices[0];
-    let range = get_iteration_range(&index.range, components);
-
-    if let Some((start, end, step)) = range {
-        // Expand the for-loop
-        let index_name = &index.ident.text;
-        let mut i = start;
-        while (step > 0 && i <= end) || (step < 0 && i >= end) {
-            // Substitute the index variable in the inner equations
-            for eq in equations {
-                let substituted = substitute_index(eq, index_name, i);
-                // Recursively expand remaining indices
-                expand_for_equation(&indices[1..], &[substituted], components, out);
-            }
-            i += step;
+    let Some(values) = iteration_values(&index.range, components) else {
+        let loc = &index.ident.location;
+        anyhow::bail!(
+            "{}:{}:{}: The range '{}' of for-loop index '{}' can't be evaluated at compile time \
+             (for-equations with non-constant ranges are not supported)",
+            loc.file_name,
+            loc.start_line,
+            loc.start_column,
+            crate::fmt::format_expression(&index.range),
+            index.ident.text
+        );
+    };
+
+    let index_name = &index.ident.text;
+    for i in values {
+        check_deadline()?;
+        // Nested loops can expand to many equations with small ranges
+        limits.check_expansion(out.len(), &index.ident.location, || {
+            format!("for-equation index '{}'", index_name)
+        })?;
+        // Substitute the index variable in the ranges of the remaining
+        // indices, e.g. `j in 1:i`, and in the inner equations
+        let rest: Vec<ForIndex> = rest
+            .iter()
+            .map(|index| ForIndex {
+                ident: index.ident.clone(),
+                range: substitute_in_expr(&index.range, index_name, i),
+            })
+            .collect();
+        let substituted: Vec<Equation> = equations
+            .iter()
+            .map(|eq| substitute_index(eq, index_name, i))
+            .collect();
+        // Recursively expand remaining indices
+        expand_for_equation(&rest, &substituted, components, out, limits)?;
+    }
+    Ok(())
+}
+
+/// Values of a for-loop index: the elements of a range like `1:n` or `n:-1:1`,
+/// `1:n` for a single value `n`, or the elements of a vector like `{1, 3, 5}`.
+/// None if they can't be evaluated at compile time.
+pub(crate) fn iteration_values(
+    expr: &Expression,
+    components: &IndexMap<String, Component>,
+) -> Option<Vec<i64>> {
+    match expr {
+        Expression::Array { elements } => {
+            return elements
+                .iter()
+                .map(|element| eval_integer_with_params(element, components))
+                .collect();
         }
-    } else {
-        // Couldn't determine the range (e.g., parameter-dependent)
-        // Keep the for-equation as is, but expand inner equations
-        let mut expanded_inner = Vec::new();
-        for eq in equations {
-            expand_equation(eq, components, &mut expanded_inner);
+        Expression::ComponentReference(_) => {
+            if let Some(Expression::Array { elements }) = known_value(expr, components) {
+                return iteration_values(&Expression::Array { elements }, components);
+            }
         }
-        out.push(Equation::For {
-            indices: indices.to_vec(),
-            equations: expanded_inner,
-        });
+        _ => {}
     }
+    let (start, end, step) = get_iteration_range(expr, components)?;
+    let mut values = Vec::new();
+    let mut i = start;
+    while (step > 0 && i <= end) || (step < 0 && i >= end) {
+        values.push(i);
+        i += step;
+    }
+    Some(values)
 }
 
 /// Get the iteration range from a range expression.
 /// Returns (start, end, step) if determinable, None otherwise.
-fn get_iteration_range(
+pub(crate) fn get_iteration_range(
     expr: &Expression,
     components: &IndexMap<String, Component>,
 ) -> Option<(i64, i64, i64)> {
@@ -655,7 +1267,10 @@ fn get_iteration_range(
 }
 
 /// Evaluate a boolean expression if possible (for parameter conditions).
-fn eval_boolean(expr: &Expression, components: &IndexMap<String, Component>) -> Option<bool> {
+pub(crate) fn eval_boolean(
+    expr: &Expression,
+    components: &IndexMap<String, Component>,
+) -> Option<bool> {
     match expr {
         Expression::Terminal {
             terminal_type: TerminalType::Bool,
@@ -756,8 +1371,26 @@ fn eval_boolean(expr: &Expression, components: &IndexMap<String, Component>) ->
     }
 }
 
-/// Evaluate an expression to an integer, with parameter lookup support.
-fn eval_integer_with_params(
+/// Value of a reference to a parameter or constant with a known value
+fn known_value(expr: &Expression, components: &IndexMap<String, Component>) -> Option<Expression> {
+    let Expression::ComponentReference(comp_ref) = expr else {
+        return None;
+    };
+    if comp_ref.parts.len() != 1 || comp_ref.parts[0].subs.is_some() {
+        return None;
+    }
+    let comp = components.get(&comp_ref.parts[0].ident.text)?;
+    match comp.variability {
+        crate::ir::ast::Variability::Parameter(_) | crate::ir::ast::Variability::Constant(_) => {
+            Some(comp.start.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Evaluate an expression to an integer at compile time, looking up the
+/// values of parameters and constants.
+pub(crate) fn eval_integer_with_params(
     expr: &Expression,
     components: &IndexMap<String, Component>,
 ) -> Option<i64> {
@@ -770,26 +1403,17 @@ fn eval_integer_with_params(
             terminal_type: TerminalType::UnsignedReal,
             token,
         } => {
-            let f: f64 = token.text.parse().ok()?;
+            let f = parse_real(&token.text)?;
             if f.fract() == 0.0 {
                 Some(f as i64)
             } else {
                 None
             }
         }
-        Expression::ComponentReference(comp_ref) => {
-            // Look up the component to see if it's a parameter with a known value
-            if comp_ref.parts.len() == 1 && comp_ref.parts[0].subs.is_none() {
-                let name = &comp_ref.parts[0].ident.text;
-                if let Some(comp) = components.get(name) {
-                    // Only evaluate parameters with known values
-                    if matches!(comp.variability, crate::ir::ast::Variability::Parameter(_)) {
-                        return eval_integer_with_params(&comp.start, components);
-                    }
-                }
-            }
-            None
+        Expression::ComponentReference(_) => {
+            eval_integer_with_params(&known_value(expr, components)?, components)
         }
+        Expression::Parenthesized { inner } => eval_integer_with_params(inner, components),
         Expression::Unary { op, rhs } => {
             let val = eval_integer_with_params(rhs, components)?;
             match op {
@@ -816,6 +1440,42 @@ fn eval_integer_with_params(
             }
         }
         Expression::FunctionCall { comp, args } => {
+            // Integer functions of evaluable arguments
+            let name = comp.to_string();
+
+            // Rounding of a real constant expression, e.g. integer(Modelica.Constants.pi)
+            if let ("integer" | "floor" | "ceil", [arg]) = (name.as_str(), args.as_slice())
+                && eval_integer_with_params(arg, components).is_none()
+            {
+                let value = evaluate(arg, &Default::default())?;
+                let value = if name == "ceil" {
+                    value.ceil()
+                } else {
+                    value.floor()
+                };
+                return (value.abs() < i64::MAX as f64).then_some(value as i64);
+            }
+
+            if matches!(
+                name.as_str(),
+                "min" | "max" | "div" | "mod" | "rem" | "abs" | "integer"
+            ) {
+                let values: Vec<i64> = args
+                    .iter()
+                    .map(|arg| eval_integer_with_params(arg, components))
+                    .collect::<Option<_>>()?;
+                return match (name.as_str(), values.as_slice()) {
+                    ("min", &[a, b]) => Some(a.min(b)),
+                    ("max", &[a, b]) => Some(a.max(b)),
+                    ("div", &[a, b]) => a.checked_div(b),
+                    ("mod", &[a, b]) => (b != 0).then(|| (a % b + b) % b),
+                    ("rem", &[a, b]) => a.checked_rem(b),
+                    ("abs", &[a]) => Some(a.abs()),
+                    ("integer", &[a]) => Some(a),
+                    _ => None,
+                };
+            }
+
             // Handle size(array, dim) function
             if let Some(first_part) = comp.parts.first()
                 && first_part.ident.text == "size"
@@ -858,13 +1518,9 @@ fn eval_integer_with_params(
             // Evaluate if-then-else expression for conditional array sizes
             // e.g., if filterType == LowPass then 0 else na
             for (condition, result) in branches {
-                if let Some(cond_val) = eval_boolean(condition, components) {
-                    if cond_val {
-                        return eval_integer_with_params(result, components);
-                    }
-                } else {
-                    // Condition can't be evaluated at compile time
-                    return None;
+                // Condition can't be evaluated at compile time -> None
+                if eval_boolean(condition, components)? {
+                    return eval_integer_with_params(result, components);
                 }
             }
             // All conditions were false, evaluate else branch
@@ -875,30 +1531,47 @@ fn eval_integer_with_params(
 }
 
 /// Substitute an index variable with a concrete value in an equation.
-fn substitute_index(eq: &Equation, index_name: &str, value: i64) -> Equation {
+pub(crate) fn substitute_index(eq: &Equation, index_name: &str, value: i64) -> Equation {
     match eq {
-        Equation::Simple { lhs, rhs } => Equation::Simple {
+        Equation::Simple {
+            lhs,
+            rhs,
+            description,
+        } => Equation::Simple {
             lhs: substitute_in_expr(lhs, index_name, value),
             rhs: substitute_in_expr(rhs, index_name, value),
+            description: description.clone(),
         },
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices,
+            equations,
+            description,
+        } => {
             // Check if this introduces a shadowing variable
             let is_shadowed = indices.iter().any(|idx| idx.ident.text == index_name);
             if is_shadowed {
                 eq.clone()
             } else {
                 Equation::For {
-                    indices: indices.clone(),
+                    indices: indices
+                        .iter()
+                        .map(|idx| ForIndex {
+                            ident: idx.ident.clone(),
+                            range: substitute_in_expr(&idx.range, index_name, value),
+                        })
+                        .collect(),
                     equations: equations
                         .iter()
                         .map(|e| substitute_index(e, index_name, value))
                         .collect(),
+                    description: description.clone(),
                 }
             }
         }
         Equation::If {
             cond_blocks,
             else_block,
+            description,
         } => Equation::If {
             cond_blocks: cond_blocks
                 .iter()
@@ -916,9 +1589,13 @@ fn substitute_index(eq: &Equation, index_name: &str, value: i64) -> Equation {
                     .map(|e| substitute_index(e, index_name, value))
                     .collect()
             }),
+            description: description.clone(),
         },
-        Equation::When(blocks) => Equation::When(
-            blocks
+        Equation::When {
+            blocks,
+            description,
+        } => Equation::When {
+            blocks: blocks
                 .iter()
                 .map(|b| crate::ir::ast::EquationBlock {
                     cond: substitute_in_expr(&b.cond, index_name, value),
@@ -929,7 +1606,27 @@ fn substitute_index(eq: &Equation, index_name: &str, value: i64) -> Equation {
                         .collect(),
                 })
                 .collect(),
-        ),
+            description: description.clone(),
+        },
+        Equation::Connect {
+            lhs,
+            rhs,
+            description,
+        } => {
+            let substitute = |cref: &ComponentReference| match substitute_in_expr(
+                &Expression::ComponentReference(cref.clone()),
+                index_name,
+                value,
+            ) {
+                Expression::ComponentReference(cref) => cref,
+                _ => cref.clone(),
+            };
+            Equation::Connect {
+                lhs: substitute(lhs),
+                rhs: substitute(rhs),
+                description: description.clone(),
+            }
+        }
         _ => eq.clone(),
     }
 }
@@ -1083,11 +1780,7 @@ fn get_equation_array_size(
             // Sum up the sizes of all elements (handles concatenation)
             let mut total = 0;
             for elem in elements {
-                if let Some(size) = get_equation_array_size(elem, components) {
-                    total += size;
-                } else {
-                    return None;
-                }
+                total += get_equation_array_size(elem, components)?;
             }
             Some(total)
         }
@@ -1095,11 +1788,12 @@ fn get_equation_array_size(
     }
 }
 
-/// Expand an array equation to scalar equations.
+/// Expand an array equation to scalar equations, which share its description.
 /// Takes components map for determining array sizes during flattening.
 fn expand_array_equation(
     lhs: &Expression,
     rhs: &Expression,
+    description: &[Token],
     size: usize,
     components: &IndexMap<String, Component>,
     out: &mut Vec<Equation>,
@@ -1111,6 +1805,7 @@ fn expand_array_equation(
         out.push(Equation::Simple {
             lhs: lhs_elem,
             rhs: rhs_elem,
+            description: description.to_vec(),
         });
     }
 }
@@ -1236,17 +1931,98 @@ fn flatten_and_subscript(
                 if let Some(comp) = components.get(name)
                     && !comp.shape.is_empty()
                 {
-                    // It's an array - subscript it
-                    return subscript_expr(expr.clone(), &[flat_index]);
+                    // It's an array - subscript it with the index of the
+                    // element in its declared shape (x[2,1], not x[3])
+                    return subscript_expr(expr.clone(), &element_index(flat_index, &comp.shape));
                 }
             }
             // Scalar - return as-is
             expr.clone()
         }
+        Expression::FunctionCall { comp, args }
+            if is_elementwise_call(comp) && is_array_expr(expr, components) =>
+        {
+            // Vectorized call: apply the scalar function to each element,
+            // subscripting array arguments and passing scalar arguments through
+            Expression::FunctionCall {
+                comp: comp.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| flatten_and_subscript(arg, flat_index, components))
+                    .collect(),
+            }
+        }
+        Expression::Unary { op, rhs } => Expression::Unary {
+            op: op.clone(),
+            rhs: Box::new(flatten_and_subscript(rhs, flat_index, components)),
+        },
+        Expression::Parenthesized { inner } => Expression::Parenthesized {
+            inner: Box::new(flatten_and_subscript(inner, flat_index, components)),
+        },
+        Expression::Binary { op, lhs, rhs } if is_elementwise_binary(op, lhs, rhs, components) => {
+            Expression::Binary {
+                op: op.clone(),
+                lhs: Box::new(flatten_and_subscript(lhs, flat_index, components)),
+                rhs: Box::new(flatten_and_subscript(rhs, flat_index, components)),
+            }
+        }
         _ => expr.clone(),
     }
 }
 
+/// Check if a function call applies a scalar built-in function element-wise.
+fn is_elementwise_call(comp: &ComponentReference) -> bool {
+    comp.parts.len() == 1 && is_elementwise_function(&comp.parts[0].ident.text)
+}
+
+/// Check if a binary operation can be scalarized element by element.
+///
+/// Addition, subtraction and the explicit element-wise operators always are.
+/// `*` and `/` only are when one side is a scalar; array * array is a
+/// matrix/inner product and must be kept intact.
+fn is_elementwise_binary(
+    op: &OpBinary,
+    lhs: &Expression,
+    rhs: &Expression,
+    components: &IndexMap<String, Component>,
+) -> bool {
+    match op {
+        OpBinary::Add(_)
+        | OpBinary::Sub(_)
+        | OpBinary::AddElem(_)
+        | OpBinary::SubElem(_)
+        | OpBinary::MulElem(_)
+        | OpBinary::DivElem(_) => true,
+        OpBinary::Mul(_) => !(is_array_expr(lhs, components) && is_array_expr(rhs, components)),
+        OpBinary::Div(_) => !is_array_expr(rhs, components),
+        _ => false,
+    }
+}
+
+/// Check if an expression evaluates to an array (after flattening).
+///
+/// Vectorized calls are arrays if any of their arguments is an array.
+fn is_array_expr(expr: &Expression, components: &IndexMap<String, Component>) -> bool {
+    match expr {
+        Expression::ComponentReference(comp_ref) => comp_ref.parts.first().is_some_and(|part| {
+            part.subs.as_ref().is_none_or(|s| s.is_empty())
+                && components
+                    .get(&part.ident.text)
+                    .is_some_and(|comp| !comp.shape.is_empty())
+        }),
+        Expression::FunctionCall { comp, args } => {
+            is_elementwise_call(comp) && args.iter().any(|arg| is_array_expr(arg, components))
+        }
+        Expression::Unary { rhs, .. } => is_array_expr(rhs, components),
+        Expression::Parenthesized { inner } => is_array_expr(inner, components),
+        Expression::Binary { lhs, rhs, .. } => {
+            is_array_expr(lhs, components) || is_array_expr(rhs, components)
+        }
+        Expression::Array { .. } => true,
+        _ => false,
+    }
+}
+
 /// Add subscripts to an expression for multi-dimensional arrays.
 fn subscript_expr_nd(expr: Expression, indices: &[usize]) -> Expression {
     subscript_expr(expr, indices)
@@ -1307,4 +2083,217 @@ mod tests {
         let components = IndexMap::new();
         assert_eq!(get_iteration_range(&range, &components), Some((1, 3, 1)));
     }
+
+    #[test]
+    fn test_vectorized_call_expansion() {
+        let def = crate::parse_source_simple(
+            r#"
+model M
+  Real x[2];
+  Real y[2];
+  parameter Real k = 2;
+equation
+  der(x) = -x;
+  y = k * atan2(x, k);
+end M;
+"#,
+            "test.mo",
+        )
+        .unwrap();
+        let mut class = def.class_list["M"].clone();
+        for comp in class.components.values_mut() {
+            comp.shape = match comp.name.as_str() {
+                "k" => vec![],
+                _ => vec![2],
+            };
+        }
+        expand_equations(&mut class, &Limits::default()).unwrap();
+
+        let eqs: Vec<String> = class.equations.iter().map(|eq| eq.to_string()).collect();
+        assert_eq!(eqs.len(), 4, "{:?}", eqs);
+        assert!(eqs[0].starts_with("der(x[") && eqs[0].contains("= -x["));
+        assert!(
+            eqs[3].contains("k * atan2(x[") && eqs[3].ends_with("], k)"),
+            "{}",
+            eqs[3]
+        );
+    }
+
+    #[test]
+    fn test_algorithm_for_loops() {
+        let def = crate::parse_source_simple(
+            r#"
+model M
+  parameter Integer n = 2;
+  Real x[2];
+  Real s(start = 1);
+  Real y[2, 2];
+  Real k;
+equation
+  x = {1, 2};
+algorithm
+  for i in 1:n loop
+    s := s + x[i];
+    for j in 1:i loop
+      y[i, j] := i * x[j];
+    end for;
+  end for;
+  k := 0;
+  for i in {1, 2} loop
+    if x[i] > 1 then
+      k := i;
+    end if;
+  end for;
+end M;
+"#,
+            "test.mo",
+        )
+        .unwrap();
+        let mut class = def.class_list["M"].clone();
+        expand_equations(&mut class, &Limits::default()).unwrap();
+        let eqs: Vec<String> = class
+            .equations
+            .iter()
+            .map(|eq| crate::fmt::format_equation(eq).trim_end().to_string())
+            .collect();
+        assert_eq!(
+            eqs[2..],
+            [
+                // s is read before it is assigned, with its start value
+                "s = 1 + x[1] + x[2];",
+                "y[1, 1] = 1 * x[1];",
+                "y[2, 1] = 2 * x[1];",
+                "y[2, 2] = 2 * x[2];",
+                // The last index of an element greater than 1
+                "k = if x[2] > 1 then 2 else if x[1] > 1 then 1 else 0;",
+            ]
+        );
+
+        // Sections with when-statements get placeholders
+        let def = crate::parse_source_simple(
+            "model N
+  Real x;
+  Real y;
+algorithm
+  when time > 1 then
+    x := 1;
+  end when;
+  y := x;
+end N;
+",
+            "test.mo",
+        )
+        .unwrap();
+        let mut class = def.class_list["N"].clone();
+        expand_equations(&mut class, &Limits::default()).unwrap();
+        let mut eqs: Vec<String> = class
+            .equations
+            .iter()
+            .map(|eq| crate::fmt::format_equation(eq).trim_end().to_string())
+            .collect();
+        eqs.sort();
+        assert_eq!(eqs, ["x = x;", "y = y;"]);
+    }
+
+    #[test]
+    fn test_unsupported_algorithm_statements() {
+        let expand = |body: &str| {
+            let source = format!(
+                "model N\n  Integer k = 2;\n  Real x;\nalgorithm\n  x := 0;\n{}end N;\n",
+                body
+            );
+            let def = crate::parse_source_simple(&source, "test.mo").unwrap();
+            let mut class = def.class_list["N"].clone();
+            expand_equations(&mut class, &Limits::default())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            expand("  while x < 1 loop\n    x := x + 0.5;\n  end while;\n"),
+            "test.mo:6:9: while-loops are not supported in algorithm sections of models"
+        );
+        assert_eq!(
+            expand(
+                "  for i in 1:3 loop\n    x := x + i;\n    if x > 2 then\n      break;\n    end if;\n  end for;\n"
+            ),
+            "test.mo:9:7: break statements are not supported in algorithm sections of models"
+        );
+        assert_eq!(
+            expand("  for i in 1:k loop\n    x := x + i;\n  end for;\n"),
+            "test.mo:6:7: The range '1:k' of for-loop index 'i' can't be evaluated at compile time \
+             (for-loops with non-constant ranges are not supported in algorithm sections of models)"
+        );
+    }
+
+    #[test]
+    fn test_for_equation_expansion() {
+        let def = crate::parse_source_simple(
+            r#"
+model M
+  constant Integer n = 3;
+  parameter Integer m = min(n, 2);
+  Real x[3, 3];
+  Real y[5];
+  Real z;
+equation
+  for i in 1:n loop
+    for j in 1:i loop
+      x[i, j] = i + j;
+    end for;
+  end for;
+  for i in {1, 3, 5}, j in i:-2:i - 2 loop
+    y[i] = j;
+  end for;
+  for i in m loop
+    z = i;
+  end for;
+end M;
+"#,
+            "test.mo",
+        )
+        .unwrap();
+        let mut class = def.class_list["M"].clone();
+        expand_equations(&mut class, &Limits::default()).unwrap();
+        let eqs: Vec<String> = class
+            .equations
+            .iter()
+            .map(|eq| crate::fmt::format_equation(eq).trim_end().to_string())
+            .collect();
+        assert_eq!(
+            eqs,
+            [
+                "x[1, 1] = 1 + 1;",
+                "x[2, 1] = 2 + 1;",
+                "x[2, 2] = 2 + 2;",
+                "x[3, 1] = 3 + 1;",
+                "x[3, 2] = 3 + 2;",
+                "x[3, 3] = 3 + 3;",
+                "y[1] = 1;",
+                "y[1] = -1;",
+                "y[3] = 3;",
+                "y[3] = 1;",
+                "y[5] = 5;",
+                "y[5] = 3;",
+                "z = 1;",
+                "z = 2;",
+            ]
+        );
+
+        // Ranges depending on variables can't be unrolled
+        let def = crate::parse_source_simple(
+            "model N\n  Integer k;\n  Real x;\nequation\n  k = 2;\n  for i in 1:k loop\n    x = i;\n  end for;\nend N;\n",
+            "test.mo",
+        )
+        .unwrap();
+        let mut class = def.class_list["N"].clone();
+        let err = expand_equations(&mut class, &Limits::default())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "test.mo:6:7: The range '1:k' of for-loop index 'i' can't be evaluated at compile time \
+             (for-equations with non-constant ranges are not supported)"
+        );
+    }
 }
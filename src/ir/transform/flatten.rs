@@ -19,14 +19,18 @@
 //! - `indexmap::IndexMap`: To maintain the order of class definitions and components.
 //!
 
+use crate::compiler::limits::{Limits, check_deadline};
 use crate::ir;
+use crate::ir::analysis::plug_compatibility;
 use crate::ir::analysis::symbol_table::SymbolTable;
 use crate::ir::ast::{
     ComponentRefPart, ComponentReference, Connection, Equation, Expression, Import, OpBinary,
     TerminalType, Token,
 };
 use crate::ir::error::IrError;
+use crate::ir::transform::component_arrays;
 use crate::ir::transform::constants::is_primitive_type;
+use crate::ir::transform::equation_expander::eval_integer_with_params;
 use crate::ir::transform::sub_comp_namer::SubCompNamer;
 use crate::ir::visitor::{MutVisitable, MutVisitor};
 use anyhow::Result;
@@ -82,6 +86,9 @@ type ResolvedClassEntry = (Arc<ir::ast::ClassDefinition>, FileDependencies);
 /// Type alias for resolved class cache key: (def_hash, class_path)
 type ResolvedClassKey = (u64, String);
 
+/// Resolved classes of one StoredDefinition, keyed by class path
+type ResolvedClasses = RwLock<HashMap<String, ResolvedClassEntry>>;
+
 /// Global cache for resolved classes.
 /// Only populated when caching is enabled via enable_cache().
 static RESOLVED_CLASS_CACHE: LazyLock<RwLock<HashMap<ResolvedClassKey, ResolvedClassEntry>>> =
@@ -180,6 +187,28 @@ pub struct FlattenResult {
     pub class: ir::ast::ClassDefinition,
     /// File dependencies used during flattening
     pub dependencies: FileDependencies,
+    /// Components expanded during flattening, by flattened name
+    pub instances: IndexMap<String, ComponentInstance>,
+    /// Connect equations of the flattened class, before their expansion
+    pub connects: Vec<(ComponentReference, ComponentReference)>,
+}
+
+/// A component expanded during flattening, e.g. a sub-model or a connector
+#[derive(Debug, Clone)]
+pub struct ComponentInstance {
+    /// Resolved class name of the component
+    pub type_name: String,
+    /// Class type of the component's class
+    pub class_type: ir::ast::ClassType,
+    /// Location of the component name in its declaration
+    pub location: ir::ast::Location,
+    /// True if the component or one of its enclosing components is conditional
+    pub conditional: bool,
+    /// Condition of a conditional component, in the scope of the flattened class
+    pub condition: Option<Expression>,
+    /// True if a connect equation references the component, one of its
+    /// sub-components or a component containing it
+    pub connected: bool,
 }
 
 /// Compute a content-based hash for a StoredDefinition.
@@ -222,6 +251,11 @@ fn hash_class_content(
         ext.comp.to_string().hash(hasher);
     }
 
+    // Hash short class definitions (base type, dimensions and modifications)
+    if let Some(short_class) = &class.short_class {
+        format!("{:?}", short_class).hash(hasher);
+    }
+
     // Hash equation count and a representation of equations
     class.equations.len().hash(hasher);
     for eq in &class.equations {
@@ -338,6 +372,7 @@ fn compute_dependency_levels(
 pub fn prewarm_class_cache(def: &ir::ast::StoredDefinition) -> usize {
     let def_hash = compute_def_hash(def);
     let class_dict = get_or_build_class_dict(def, def_hash);
+    let resolved = ResolvedClasses::default();
 
     // Build dependency graph
     let deps = build_dependency_graph(&class_dict);
@@ -352,13 +387,13 @@ pub fn prewarm_class_cache(def: &ir::ast::StoredDefinition) -> usize {
         #[cfg(not(target_arch = "wasm32"))]
         level.par_iter().for_each(|class_name| {
             if let Some(class_arc) = class_dict.get(class_name) {
-                let _ = resolve_class(class_arc, class_name, &class_dict, def_hash);
+                let _ = resolve_class(class_arc, class_name, &class_dict, def_hash, &resolved);
             }
         });
         #[cfg(target_arch = "wasm32")]
         level.iter().for_each(|class_name| {
             if let Some(class_arc) = class_dict.get(class_name) {
-                let _ = resolve_class(class_arc, class_name, &class_dict, def_hash);
+                let _ = resolve_class(class_arc, class_name, &class_dict, def_hash, &resolved);
             }
         });
         total_prewarmed += level.len();
@@ -387,28 +422,88 @@ pub fn get_cache_stats() -> (usize, usize) {
     (class_dict_size, resolved_size)
 }
 
-/// Extract name=value pairs from extends clause modifications.
+/// Extract path=value pairs from extends clause modifications.
 ///
-/// Extends modifications are stored as a Vec<Expression> containing Binary expressions
-/// like `L = 1e-3` which are `Binary { op: Eq, lhs: ComponentReference("L"), rhs: value }`.
+/// Extends modifications are stored as a Vec<Expression> containing Binary assignments
+/// like `L = 1e-3` and nested class modifications like `sub(k = 2)`, which are
+/// flattened to dotted paths (`sub.k`) as for component modifications.
 ///
 /// This function extracts these into an IndexMap for easy lookup.
 fn extract_extends_modifications(modifications: &[Expression]) -> IndexMap<String, Expression> {
     let mut result = IndexMap::new();
 
     for expr in modifications {
-        if let Expression::Binary { op, lhs, rhs } = expr
-            && matches!(op, OpBinary::Eq(_))
-            && let Expression::ComponentReference(comp_ref) = &**lhs
-        {
-            let param_name = comp_ref.to_string();
-            result.insert(param_name, (**rhs).clone());
+        for (comp_ref, value) in expr.modification_pairs() {
+            result.insert(comp_ref.to_string(), value);
         }
     }
 
     result
 }
 
+/// Check that a component (or the element `key` inside it) may still be modified.
+///
+/// An element is final if the component itself is declared or modified as `final`,
+/// or if `key` or any enclosing element of `key` was given a `final` modification.
+/// `path` is the full name of the modified element, used in the error message.
+fn check_modifiable(comp: &ir::ast::Component, key: Option<&str>, path: &str) -> Result<()> {
+    let final_key = key.is_some_and(|key| {
+        key.match_indices('.')
+            .map(|(idx, _)| &key[..idx])
+            .chain(std::iter::once(key))
+            .any(|k| comp.modification_prefixes.get(k).is_some_and(|p| p.r#final))
+    });
+    if comp.r#final || final_key {
+        anyhow::bail!("Cannot modify final element '{}'", path);
+    }
+    Ok(())
+}
+
+/// Apply a value modification (`x = value`) to a component, replacing its binding.
+fn apply_binding_modification(
+    comp: &mut ir::ast::Component,
+    value: &Expression,
+    prefix: Option<ir::ast::ModificationPrefix>,
+    path: &str,
+) -> Result<()> {
+    check_modifiable(comp, None, path)?;
+    comp.start = value.clone();
+    comp.start_is_modification = false;
+    if prefix.is_some_and(|p| p.r#final) {
+        comp.r#final = true;
+    }
+    Ok(())
+}
+
+/// Apply a modification of `key` (`start`, another attribute, or a nested element
+/// path like `inertia.J`) to a component. Outer modifications take precedence, so the
+/// value replaces any modification of the same element already on the component.
+fn apply_modification(
+    comp: &mut ir::ast::Component,
+    key: &str,
+    value: &Expression,
+    prefix: Option<ir::ast::ModificationPrefix>,
+    path: &str,
+) -> Result<()> {
+    check_modifiable(comp, Some(key), path)?;
+    if key == "start" {
+        comp.start = value.clone();
+        comp.start_is_modification = true;
+        comp.start_has_each = prefix.is_some_and(|p| p.each);
+    } else {
+        comp.modifications.insert(key.to_string(), value.clone());
+    }
+    match prefix {
+        Some(prefix) => {
+            comp.modification_prefixes.insert(key.to_string(), prefix);
+        }
+        None => {
+            comp.modification_prefixes.swap_remove(key);
+        }
+    }
+    Ok(())
+}
+
 /// Builds a map of import aliases from a class's imports.
 ///
 /// For renamed imports like `import D = Modelica.Electrical.Digital;`,
@@ -432,8 +527,7 @@ fn build_import_aliases(imports: &[Import]) -> IndexMap<String, String> {
                 }
             }
             Import::Unqualified { .. } => {
-                // import A.B.*; - handled differently (need to check all classes in A.B)
-                // For now, skip as this requires checking the class dictionary
+                // import A.B.*; - needs the classes of A.B, see build_import_aliases_for_class
             }
             Import::Selective { path, names, .. } => {
                 // import A.B.{C, D}; => C -> A.B.C, D -> A.B.D
@@ -516,6 +610,8 @@ fn validate_imports(imports: &[Import], class_dict: &ClassDict) -> Result<()> {
 /// Builds a combined import alias map from a class and all its enclosing scopes.
 ///
 /// This collects imports from the class itself and all parent packages up to the root.
+/// Unqualified imports (`import A.B.*;`) map the names of the classes of `A.B`,
+/// unless an explicit import of the same scope maps them.
 fn build_import_aliases_for_class(
     class_path: &str,
     class_dict: &ClassDict,
@@ -527,7 +623,20 @@ fn build_import_aliases_for_class(
     for i in (1..=parts.len()).rev() {
         let path = parts[..i].join(".");
         if let Some(class) = class_dict.get(&path) {
-            let aliases = build_import_aliases(&class.imports);
+            let mut aliases = build_import_aliases(&class.imports);
+            for import in &class.imports {
+                if let Import::Unqualified { path, .. } = import {
+                    let package_path = path.to_string();
+                    let Some(package) = class_dict.get(&package_path) else {
+                        continue;
+                    };
+                    for name in package.classes.keys() {
+                        aliases
+                            .entry(name.clone())
+                            .or_insert_with(|| format!("{}.{}", package_path, name));
+                    }
+                }
+            }
             // Earlier (more specific) imports take precedence
             for (alias, target) in aliases {
                 all_aliases.entry(alias).or_insert(target);
@@ -568,11 +677,13 @@ fn apply_import_aliases(name: &str, aliases: &IndexMap<String, String>) -> Strin
 /// This function implements Modelica's name lookup rules for extends clauses:
 /// 1. First applies import aliases to resolve aliased names
 /// 2. Then tries an exact match (for fully qualified names)
-/// 3. Then tries prepending enclosing package prefixes from most specific to least
+/// 3. Then tries prepending the class itself and its enclosing package prefixes,
+///    from most specific to least
 ///
 /// For example, if `current_class_path` is `Modelica.Blocks.Continuous.Derivative`
 /// and `name` is `Interfaces.SISO`, it will try:
 /// - `Interfaces.SISO` (exact match)
+/// - `Modelica.Blocks.Continuous.Derivative.Interfaces.SISO`
 /// - `Modelica.Blocks.Continuous.Interfaces.SISO`
 /// - `Modelica.Blocks.Interfaces.SISO` (found!)
 /// - `Modelica.Interfaces.SISO`
@@ -586,14 +697,11 @@ fn resolve_class_name_with_imports(
     // 0. Apply import aliases first
     let resolved_name = apply_import_aliases(name, import_aliases);
 
-    // 1. Try exact match first (handles fully qualified names)
-    if class_dict.contains_key(&resolved_name) {
-        return Some(resolved_name);
-    }
-
-    // 2. Try prepending enclosing package prefixes
+    // 1. Try prepending the class and its enclosing package prefixes, from
+    //    the innermost scope to the top level (fully qualified names), so
+    //    that `P.f` in package `Outer` names `Outer.P.f` if it exists
     let parts: Vec<&str> = current_class_path.split('.').collect();
-    for i in (0..parts.len()).rev() {
+    for i in (0..=parts.len()).rev() {
         let prefix = parts[..i].join(".");
         let candidate = if prefix.is_empty() {
             resolved_name.clone()
@@ -605,13 +713,10 @@ fn resolve_class_name_with_imports(
         }
     }
 
-    // 3. If import alias resolution changed the name but still not found,
+    // 2. If import alias resolution changed the name but still not found,
     //    try with the original name too
     if resolved_name != name {
-        if class_dict.contains_key(name) {
-            return Some(name.to_string());
-        }
-        for i in (0..parts.len()).rev() {
+        for i in (0..=parts.len()).rev() {
             let prefix = parts[..i].join(".");
             let candidate = if prefix.is_empty() {
                 name.to_string()
@@ -637,6 +742,12 @@ struct ScopeRenamer<'a> {
     symbol_table: &'a SymbolTable,
     /// The component scope prefix to prepend
     scope_prefix: String,
+    /// Indices of the enclosing for-equations and for-statements, which are
+    /// local to the loops and keep their names
+    loop_indices: Vec<String>,
+    /// Name of the function call being visited, which names a class, not a
+    /// component (see FunctionResolver)
+    function_name: Option<*const ir::ast::ComponentReference>,
 }
 
 impl<'a> ScopeRenamer<'a> {
@@ -644,15 +755,66 @@ impl<'a> ScopeRenamer<'a> {
         Self {
             symbol_table,
             scope_prefix: scope_prefix.to_string(),
+            loop_indices: Vec::new(),
+            function_name: None,
         }
     }
 }
 
 impl MutVisitor for ScopeRenamer<'_> {
+    fn enter_expression(&mut self, node: &mut Expression) {
+        if let Expression::FunctionCall { comp, .. } = node {
+            self.function_name = Some(comp);
+        }
+    }
+
+    fn enter_equation(&mut self, node: &mut ir::ast::Equation) {
+        match node {
+            ir::ast::Equation::For { indices, .. } => {
+                self.loop_indices
+                    .extend(indices.iter().map(|index| index.ident.text.clone()));
+            }
+            ir::ast::Equation::FunctionCall { comp, .. } => self.function_name = Some(comp),
+            _ => {}
+        }
+    }
+
+    fn exit_equation(&mut self, node: &mut ir::ast::Equation) {
+        if let ir::ast::Equation::For { indices, .. } = node {
+            let len = self.loop_indices.len() - indices.len();
+            self.loop_indices.truncate(len);
+        }
+    }
+
+    fn enter_statement(&mut self, node: &mut ir::ast::Statement) {
+        match node {
+            ir::ast::Statement::For { indices, .. } => {
+                self.loop_indices
+                    .extend(indices.iter().map(|index| index.ident.text.clone()));
+            }
+            ir::ast::Statement::FunctionCall { comp, .. } => self.function_name = Some(comp),
+            _ => {}
+        }
+    }
+
+    fn exit_statement(&mut self, node: &mut ir::ast::Statement) {
+        if let ir::ast::Statement::For { indices, .. } = node {
+            let len = self.loop_indices.len() - indices.len();
+            self.loop_indices.truncate(len);
+        }
+    }
+
     fn exit_component_reference(&mut self, node: &mut ir::ast::ComponentReference) {
+        // The name of a function call is visited first, before its arguments
+        if self.function_name == Some(node as *const _) {
+            self.function_name = None;
+            return;
+        }
         let name = node.to_string();
-        // Only prepend scope if not a global symbol
-        if !self.symbol_table.is_global(&name) {
+        // Only prepend scope if not a global symbol or a loop index; global
+        // names, as in `.P.c`, never belong to the component
+        if !node.local && !self.symbol_table.is_global(&name) && !self.loop_indices.contains(&name)
+        {
             node.parts.insert(
                 0,
                 ir::ast::ComponentRefPart {
@@ -667,6 +829,95 @@ impl MutVisitor for ScopeRenamer<'_> {
     }
 }
 
+/// Visitor that fully qualifies the names of the functions a class calls.
+///
+/// Function names are looked up like class names, in the scope of the class
+/// that calls them: its imports and those of its enclosing packages (renamed,
+/// qualified, selective and unqualified), then the class and its enclosing
+/// packages. Built-in functions and names that can't be resolved are kept, as
+/// are global names like `.P.f`, which already name a top-level class. Calls
+/// keep resolving to the same functions once the equations of the class
+/// are flattened into another scope.
+struct FunctionResolver<'a> {
+    class_path: &'a str,
+    class_dict: &'a ClassDict,
+    import_aliases: &'a IndexMap<String, String>,
+    /// Depth of the visited class, as nested classes have scopes of their own
+    depth: usize,
+}
+
+impl FunctionResolver<'_> {
+    fn resolve(&self, comp: &mut ComponentReference) {
+        if self.depth > 1 {
+            return;
+        }
+        let name = comp.to_string();
+        // Global names, as in `.P.f(x)`, are looked up at the top level only
+        if comp.local {
+            return;
+        }
+        let Some(resolved) = resolve_class_name_with_imports(
+            &name,
+            self.class_path,
+            self.class_dict,
+            self.import_aliases,
+        ) else {
+            return;
+        };
+        // Functions declared in the class itself keep their short name
+        let is_local = resolved
+            .strip_prefix(self.class_path)
+            .and_then(|rest| rest.strip_prefix('.'))
+            == Some(name.as_str());
+        if resolved == name
+            || is_local
+            || crate::ir::transform::constants::is_builtin_function(&name)
+        {
+            return;
+        }
+        let location = comp.get_location().cloned().unwrap_or_default();
+        comp.parts = resolved
+            .split('.')
+            .map(|part| ComponentRefPart {
+                ident: ir::ast::Token {
+                    text: part.to_string(),
+                    location: location.clone(),
+                    ..Default::default()
+                },
+                subs: None,
+            })
+            .collect();
+    }
+}
+
+impl MutVisitor for FunctionResolver<'_> {
+    fn enter_class_definition(&mut self, _node: &mut ir::ast::ClassDefinition) {
+        self.depth += 1;
+    }
+
+    fn exit_class_definition(&mut self, _node: &mut ir::ast::ClassDefinition) {
+        self.depth -= 1;
+    }
+
+    fn exit_expression(&mut self, node: &mut Expression) {
+        if let Expression::FunctionCall { comp, .. } = node {
+            self.resolve(comp);
+        }
+    }
+
+    fn exit_equation(&mut self, node: &mut Equation) {
+        if let Equation::FunctionCall { comp, .. } = node {
+            self.resolve(comp);
+        }
+    }
+
+    fn exit_statement(&mut self, node: &mut ir::ast::Statement) {
+        if let ir::ast::Statement::FunctionCall { comp, .. } = node {
+            self.resolve(comp);
+        }
+    }
+}
+
 /// Recursively resolves a class definition by processing all extends clauses.
 ///
 /// This function takes a class and resolves all inheritance by copying components
@@ -678,12 +929,19 @@ impl MutVisitor for ScopeRenamer<'_> {
 /// * `current_class_path` - The fully qualified path of the current class (for scope lookup)
 /// * `class_dict` - Dictionary of all available classes
 /// * `def_hash` - Content hash of StoredDefinition for cache key stability
+/// * `resolved` - Classes already resolved for the same StoredDefinition
 fn resolve_class(
     class: &ir::ast::ClassDefinition,
     current_class_path: &str,
     class_dict: &ClassDict,
     def_hash: u64,
+    resolved: &ResolvedClasses,
 ) -> Result<(Arc<ir::ast::ClassDefinition>, FileDependencies)> {
+    // Classes resolved while flattening other models of the same definition
+    if let Some((class, deps)) = resolved.read().unwrap().get(current_class_path) {
+        return Ok((Arc::clone(class), deps.clone()));
+    }
+
     // Check in-memory cache first (only if caching is enabled)
     let cache_key = (def_hash, current_class_path.to_string());
     if is_cache_enabled()
@@ -697,7 +955,7 @@ fn resolve_class(
     // Dependencies are tracked recursively in resolve_class_internal
     let mut visited = IndexSet::new();
     let mut deps = FileDependencies::new();
-    let resolved = resolve_class_internal(
+    let resolved_class = resolve_class_internal(
         class,
         current_class_path,
         class_dict,
@@ -705,8 +963,12 @@ fn resolve_class(
         &mut deps,
     )?;
 
-    // Wrap in Arc and cache in memory (only if caching is enabled)
-    let resolved_arc = Arc::new(resolved);
+    // Wrap in Arc and cache in memory (globally only if caching is enabled)
+    let resolved_arc = Arc::new(resolved_class);
+    resolved.write().unwrap().insert(
+        current_class_path.to_string(),
+        (Arc::clone(&resolved_arc), deps.clone()),
+    );
     if is_cache_enabled() {
         RESOLVED_CLASS_CACHE
             .write()
@@ -740,6 +1002,15 @@ fn resolve_class_internal(
     // Build import aliases for this class
     let import_aliases = build_import_aliases_for_class(current_class_path, class_dict);
 
+    // Resolve the function calls of the class in its scope, before inheriting
+    // the equations of its parents, resolved in theirs
+    resolved.accept_mut(&mut FunctionResolver {
+        class_path: current_class_path,
+        class_dict,
+        import_aliases: &import_aliases,
+        depth: 0,
+    });
+
     // Record dependencies from imports
     // Each imported class/package contributes a file dependency
     for import in &class.imports {
@@ -763,6 +1034,11 @@ fn resolve_class_internal(
         }
     }
 
+    // Inherited components replaced by a redeclaration, with their constraining
+    // type and the class they were inherited from
+    let mut redeclared: IndexMap<String, (ir::ast::Component, String)> = IndexMap::new();
+    let mut parent_paths = Vec::new();
+
     // Process all extends clauses
     for extend in &class.extends {
         let parent_name = extend.comp.to_string();
@@ -794,6 +1070,8 @@ fn resolve_class_internal(
             None => continue, // Skip missing classes
         };
 
+        parent_paths.push(resolved_name.clone());
+
         // Recursively resolve the parent class first (using resolved name as new context)
         // This also collects dependencies from parent classes
         let resolved_parent =
@@ -814,37 +1092,40 @@ fn resolve_class_internal(
                 // This is critical when inheriting components - e.g., SISO has "RealInput u"
                 // and when SimpleIntegrator extends SISO, we need to resolve RealInput
                 // in SISO's context (Interfaces package) to get "Interfaces.RealInput"
-                let type_name = comp.type_name.to_string();
-                if !is_primitive_type(&type_name)
-                    && let Some(fq_name) = resolve_class_name_with_imports(
-                        &type_name,
-                        &resolved_name,
-                        class_dict,
-                        &parent_import_aliases,
-                    )
-                {
-                    // Update the type name to the fully qualified version
-                    modified_comp.type_name = ir::ast::Name {
-                        name: fq_name
-                            .split('.')
-                            .map(|s| ir::ast::Token {
-                                text: s.to_string(),
-                                ..Default::default()
-                            })
-                            .collect(),
-                    };
-                }
-
-                // Apply extends modifications to inherited components
-                if let Some(mod_value) = extends_mods.get(comp_name) {
-                    modified_comp.start = mod_value.clone();
-                    modified_comp.start_is_modification = true;
+                modified_comp.type_name = qualify_type_name(
+                    &comp.type_name,
+                    &resolved_name,
+                    class_dict,
+                    &parent_import_aliases,
+                );
+
+                // Apply extends modifications to inherited components. They override the
+                // modifications in the inherited declaration (and are in turn overridden by
+                // modifications of the enclosing instance when the class is instantiated).
+                let nested_prefix = format!("{}.", comp_name);
+                for (path, mod_value) in &extends_mods {
+                    let prefix = extend.modification_prefixes.get(path).copied();
+                    if path == comp_name {
+                        apply_binding_modification(&mut modified_comp, mod_value, prefix, path)?;
+                    } else if let Some(key) = path.strip_prefix(&nested_prefix) {
+                        apply_modification(&mut modified_comp, key, mod_value, prefix, path)?;
+                    }
                 }
 
                 resolved.components.insert(comp_name.clone(), modified_comp);
                 resolved
                     .components
                     .move_index(resolved.components.len() - 1, 0);
+            } else if class.components.get(comp_name).is_some_and(|c| c.redeclare) {
+                // Checked against its constraining type once all parents are resolved
+                let mut replaced = comp.clone();
+                replaced.type_name = qualify_type_name(
+                    comp.constrainedby.as_ref().unwrap_or(&comp.type_name),
+                    &resolved_name,
+                    class_dict,
+                    &parent_import_aliases,
+                );
+                redeclared.insert(comp_name.clone(), (replaced, resolved_name.clone()));
             }
         }
 
@@ -854,51 +1135,324 @@ fn resolve_class_internal(
         resolved.equations = new_equations;
     }
 
-    // Apply causality from type definitions to components
+    // Apply short class definitions (type aliases) to the components declared here
     // e.g., if a component has type RealInput which is defined as "connector RealInput = input Real"
-    // then the component should have Input causality
-    apply_type_causality(&mut resolved, current_class_path, class_dict);
+    // then the component should have Input causality. Inherited components were already
+    // processed when the parent class was resolved.
+    apply_type_aliases(
+        &mut resolved,
+        &class.components.keys().cloned().collect(),
+        current_class_path,
+        class_dict,
+    )?;
+
+    check_redeclarations(
+        class,
+        current_class_path,
+        class_dict,
+        visited,
+        &redeclared,
+        &parent_paths,
+    )?;
 
     Ok(resolved)
 }
 
-/// Apply causality from type definitions to components whose causality is Empty
-/// This handles type aliases like "connector RealInput = input Real"
-fn apply_type_causality(
+/// Fully qualify a type name in the context of a class, keeping built-in types
+/// and names that can't be resolved as they are
+fn qualify_type_name(
+    name: &ir::ast::Name,
+    class_path: &str,
+    class_dict: &ClassDict,
+    import_aliases: &IndexMap<String, String>,
+) -> ir::ast::Name {
+    let type_name = name.to_string();
+    if is_primitive_type(&type_name) {
+        return name.clone();
+    }
+    match resolve_class_name_with_imports(&type_name, class_path, class_dict, import_aliases) {
+        Some(fq_name) => ir::ast::Name {
+            name: fq_name
+                .split('.')
+                .map(|s| ir::ast::Token {
+                    text: s.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        },
+        None => name.clone(),
+    }
+}
+
+/// Check the redeclarations of a class against the replaceable elements they replace.
+///
+/// `redeclared` holds the inherited components replaced by a `redeclare`d
+/// component, with their constraining type. Redeclared classes are looked up
+/// in the parent classes (`parent_paths`) and their ancestors. Replacements
+/// must be plug-compatible with the constraining type; elements whose classes
+/// can't be found (e.g. from libraries that aren't loaded) are not checked.
+fn check_redeclarations(
+    class: &ir::ast::ClassDefinition,
+    current_class_path: &str,
+    class_dict: &ClassDict,
+    visited: &IndexSet<String>,
+    redeclared: &IndexMap<String, (ir::ast::Component, String)>,
+    parent_paths: &[String],
+) -> Result<()> {
+    // Classes being resolved are not resolved again, which guards against
+    // classes redeclared with a class that extends them
+    let resolve = |path: &str| -> Option<ir::ast::ClassDefinition> {
+        let found = class_dict.get(path)?;
+        let mut resolved = resolve_class_internal(
+            found,
+            path,
+            class_dict,
+            &mut visited.clone(),
+            &mut FileDependencies::new(),
+        )
+        .ok()?;
+        let aliases = build_import_aliases_for_class(path, class_dict);
+        for comp in resolved.components.values_mut() {
+            comp.type_name = qualify_type_name(&comp.type_name, path, class_dict, &aliases);
+        }
+        if let Some(short) = &mut resolved.short_class {
+            short.base_type = qualify_type_name(&short.base_type, path, class_dict, &aliases);
+        }
+        Some(resolved)
+    };
+    let location = |loc: &ir::ast::Location| {
+        format!("{}:{}:{}", loc.file_name, loc.start_line, loc.start_column)
+    };
+    let report = |loc: &ir::ast::Location,
+                  what: &str,
+                  replacement: &str,
+                  constraint: &str,
+                  incompatibilities: Vec<plug_compatibility::Incompatibility>|
+     -> Result<()> {
+        if incompatibilities.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = incompatibilities.iter().map(|i| i.to_string()).collect();
+        anyhow::bail!(
+            "{}: Redeclaration of {} as '{}' is not compatible with its constraining type '{}':\n  {}",
+            location(loc),
+            what,
+            replacement,
+            constraint,
+            details.join("\n  ")
+        )
+    };
+
+    let aliases = build_import_aliases_for_class(current_class_path, class_dict);
+    for (name, (constraint, parent_path)) in redeclared {
+        let comp = &class.components[name];
+        if !constraint.replaceable {
+            anyhow::bail!(
+                "{}: Component '{}' is redeclared, but it is not replaceable in '{}'",
+                location(&comp.location),
+                name,
+                parent_path
+            );
+        }
+        let mut replacement = comp.clone();
+        replacement.type_name =
+            qualify_type_name(&comp.type_name, current_class_path, class_dict, &aliases);
+        report(
+            &comp.location,
+            &format!("component '{}'", name),
+            &replacement.type_name.to_string(),
+            &constraint.type_name.to_string(),
+            plug_compatibility::check_component_compatibility(&replacement, constraint, &resolve),
+        )?;
+    }
+
+    for (name, nested) in class.classes.iter().filter(|(_, c)| c.redeclare) {
+        let Some((inherited_path, inherited)) = parent_paths
+            .iter()
+            .find_map(|parent| find_inherited_class(name, parent, class_dict, 0))
+        else {
+            continue;
+        };
+        if !inherited.replaceable {
+            anyhow::bail!(
+                "{}: Class '{}' is redeclared, but it is not replaceable in '{}'",
+                location(&nested.name.location),
+                name,
+                inherited_path
+                    .rsplit_once('.')
+                    .map_or("", |(parent, _)| parent)
+            );
+        }
+        let constraint_path = match &inherited.constrainedby {
+            Some(constraint) => {
+                let parent = inherited_path.rsplit_once('.').map_or("", |(p, _)| p);
+                let parent_aliases = build_import_aliases_for_class(parent, class_dict);
+                qualify_type_name(constraint, parent, class_dict, &parent_aliases).to_string()
+            }
+            None => inherited_path.clone(),
+        };
+        let replacement_path = format!("{}.{}", current_class_path, name);
+        let (Some(replacement), Some(interface)) =
+            (resolve(&replacement_path), resolve(&constraint_path))
+        else {
+            continue;
+        };
+        let replacement_name = nested
+            .short_class
+            .as_ref()
+            .map_or(replacement_path.clone(), |short| {
+                short.base_type.to_string()
+            });
+        report(
+            &nested.name.location,
+            &format!("class '{}'", name),
+            &replacement_name,
+            &constraint_path,
+            plug_compatibility::check_plug_compatibility(&replacement, &interface, &resolve),
+        )?;
+    }
+    Ok(())
+}
+
+/// Maximum depth of the inheritance searched for a redeclared class (guards against cycles)
+const MAX_INHERITANCE_DEPTH: usize = 32;
+
+/// Find the class `name` declared in a class or inherited by it, returning its path
+fn find_inherited_class(
+    name: &str,
+    class_path: &str,
+    class_dict: &ClassDict,
+    depth: usize,
+) -> Option<(String, Arc<ir::ast::ClassDefinition>)> {
+    let path = format!("{}.{}", class_path, name);
+    if let Some(found) = class_dict.get(&path) {
+        return Some((path, Arc::clone(found)));
+    }
+    if depth >= MAX_INHERITANCE_DEPTH {
+        return None;
+    }
+    let class = class_dict.get(class_path)?;
+    let aliases = build_import_aliases_for_class(class_path, class_dict);
+    class.extends.iter().find_map(|extend| {
+        let parent = resolve_class_name_with_imports(
+            &extend.comp.to_string(),
+            class_path,
+            class_dict,
+            &aliases,
+        )?;
+        find_inherited_class(name, &parent, class_dict, depth + 1)
+    })
+}
+
+/// Maximum length of a chain of short class definitions (guards against cycles)
+const MAX_TYPE_ALIAS_DEPTH: usize = 32;
+
+/// Apply short class definitions (type aliases) to components.
+///
+/// For a component whose type is a chain of short class definitions, e.g.
+/// `type Voltage = Real(unit="V"); type V2 = Voltage[2];`:
+/// - The alias dimensions are appended to the component's own (`V2 w[4]` is 4x2)
+/// - The first causality found along the chain applies if the component has none
+///   (e.g., `connector RealInput = input Real`)
+/// - If the chain ends in a built-in type, the alias modifications apply to
+///   attributes the component does not modify itself (outer aliases first).
+///   Modifications of other classes are handled through the alias's extends clause.
+fn apply_type_aliases(
     class: &mut ir::ast::ClassDefinition,
+    declared: &IndexSet<String>,
     current_class_path: &str,
     class_dict: &ClassDict,
-) {
+) -> Result<()> {
     use crate::ir::ast::Causality;
 
     // Build import aliases for this class
     let import_aliases = build_import_aliases_for_class(current_class_path, class_dict);
 
-    for (_comp_name, comp) in class.components.iter_mut() {
-        // Only apply if component's causality is empty (not explicitly set)
-        if !matches!(comp.causality, Causality::Empty) {
+    for (comp_name, comp) in class.components.iter_mut() {
+        if !declared.contains(comp_name) {
             continue;
         }
 
-        let type_name = comp.type_name.to_string();
-
-        // Resolve the type name using enclosing scope search with import aliases
-        let resolved_type_name = resolve_class_name_with_imports(
-            &type_name,
-            current_class_path,
-            class_dict,
-            &import_aliases,
-        );
+        // Follow the chain of type definitions, resolving each base type in the
+        // scope of the alias that names it
+        let mut chain: Vec<Arc<ir::ast::ClassDefinition>> = Vec::new();
+        let mut type_name = comp.type_name.to_string();
+        let mut context = current_class_path.to_string();
+        let mut aliases = import_aliases.clone();
+        while chain.len() < MAX_TYPE_ALIAS_DEPTH && !is_primitive_type(&type_name) {
+            let Some(resolved_name) =
+                resolve_class_name_with_imports(&type_name, &context, class_dict, &aliases)
+            else {
+                break;
+            };
+            let Some(type_class) = class_dict.get(&resolved_name) else {
+                break;
+            };
+            chain.push(type_class.clone());
+            let Some(short_class) = &type_class.short_class else {
+                break;
+            };
+            type_name = short_class.base_type.to_string();
+            aliases = build_import_aliases_for_class(&resolved_name, class_dict);
+            context = resolved_name;
+        }
 
-        if let Some(resolved_name) = resolved_type_name
-            && let Some(type_class) = class_dict.get(&resolved_name)
+        // Only apply if component's causality is empty (not explicitly set)
+        if matches!(comp.causality, Causality::Empty)
+            && let Some(type_class) = chain
+                .iter()
+                .find(|c| !matches!(c.causality, Causality::Empty))
         {
-            // If the type has causality (from base_prefix), apply it to the component
-            if !matches!(type_class.causality, Causality::Empty) {
-                comp.causality = type_class.causality.clone();
+            comp.causality = type_class.causality.clone();
+        }
+
+        let ends_in_builtin = is_primitive_type(&type_name);
+        for short_class in chain.iter().filter_map(|c| c.short_class.as_ref()) {
+            // Dimensions: only keep the evaluated shape while every dimension is a literal,
+            // otherwise leave it to shape evaluation from `shape_expr`
+            let comp_literal = comp.shape_expr.len() <= comp.shape.len();
+            let alias_literal = short_class.shape.len() == short_class.shape_expr.len();
+            if comp_literal && alias_literal {
+                comp.shape.extend(&short_class.shape);
+            } else {
+                comp.shape.clear();
+            }
+            comp.shape_expr
+                .extend(short_class.shape_expr.iter().cloned());
+
+            if !ends_in_builtin {
+                continue;
+            }
+            for expr in &short_class.modifications {
+                for (comp_ref, value) in expr.modification_pairs() {
+                    let key = comp_ref.to_string();
+                    let prefix = short_class.modification_prefixes.get(&key).copied();
+                    let modified = if key == "start" {
+                        comp.start_is_modification || !matches!(comp.start, Expression::Empty)
+                    } else {
+                        comp.modifications.contains_key(&key)
+                    };
+                    if modified {
+                        if prefix.is_some_and(|p| p.r#final) {
+                            anyhow::bail!("Cannot modify final element '{}.{}'", comp_name, key);
+                        }
+                        continue;
+                    }
+                    if key == "start" {
+                        comp.start = value;
+                        comp.start_is_modification = true;
+                        comp.start_has_each = prefix.is_some_and(|p| p.each);
+                    } else {
+                        comp.modifications.insert(key.clone(), value);
+                    }
+                    if let Some(prefix) = prefix {
+                        comp.modification_prefixes.insert(key, prefix);
+                    }
+                }
             }
         }
     }
+    Ok(())
 }
 
 /// Creates a component reference from a flattened name like "R1.p.v"
@@ -921,6 +1475,7 @@ fn make_simple_eq(lhs: &str, rhs: &str) -> Equation {
     Equation::Simple {
         lhs: Expression::ComponentReference(make_comp_ref(lhs)),
         rhs: Expression::ComponentReference(make_comp_ref(rhs)),
+        description: vec![],
     }
 }
 
@@ -929,6 +1484,7 @@ fn make_binding_eq(lhs: &str, rhs: Expression) -> Equation {
     Equation::Simple {
         lhs: Expression::ComponentReference(make_comp_ref(lhs)),
         rhs,
+        description: vec![],
     }
 }
 
@@ -970,6 +1526,7 @@ fn make_sum_eq(vars: &[String]) -> Equation {
                 },
                 terminal_type: TerminalType::UnsignedReal,
             },
+            description: vec![],
         };
     }
 
@@ -991,6 +1548,7 @@ fn make_sum_eq(vars: &[String]) -> Equation {
             },
             terminal_type: TerminalType::UnsignedReal,
         },
+        description: vec![],
     }
 }
 
@@ -1001,11 +1559,15 @@ fn extract_connect_equations_recursive(
     connect_eqs: &mut Vec<(ComponentReference, ComponentReference)>,
 ) -> Option<Equation> {
     match eq {
-        Equation::Connect { lhs, rhs } => {
+        Equation::Connect { lhs, rhs, .. } => {
             connect_eqs.push((lhs.clone(), rhs.clone()));
             None // Remove connect equation
         }
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices,
+            equations,
+            description,
+        } => {
             let mut filtered_eqs = Vec::new();
             for inner_eq in equations {
                 if let Some(filtered) = extract_connect_equations_recursive(inner_eq, connect_eqs) {
@@ -1018,12 +1580,14 @@ fn extract_connect_equations_recursive(
                 Some(Equation::For {
                     indices: indices.clone(),
                     equations: filtered_eqs,
+                    description: description.clone(),
                 })
             }
         }
         Equation::If {
             cond_blocks,
             else_block,
+            description,
         } => {
             let mut new_cond_blocks = Vec::new();
             for block in cond_blocks {
@@ -1052,9 +1616,13 @@ fn extract_connect_equations_recursive(
             Some(Equation::If {
                 cond_blocks: new_cond_blocks,
                 else_block: new_else,
+                description: description.clone(),
             })
         }
-        Equation::When(blocks) => {
+        Equation::When {
+            blocks,
+            description,
+        } => {
             let mut new_blocks = Vec::new();
             for block in blocks {
                 let mut filtered_eqs = Vec::new();
@@ -1070,12 +1638,41 @@ fn extract_connect_equations_recursive(
                     eqs: filtered_eqs,
                 });
             }
-            Some(Equation::When(new_blocks))
+            Some(Equation::When {
+                blocks: new_blocks,
+                description: description.clone(),
+            })
         }
         _ => Some(eq.clone()),
     }
 }
 
+/// Connect equations of a class, including those nested in for, if and when equations
+fn connect_equations(equations: &[Equation]) -> Vec<(ComponentReference, ComponentReference)> {
+    let mut connect_eqs = Vec::new();
+    for eq in equations {
+        extract_connect_equations_recursive(eq, &mut connect_eqs);
+    }
+    connect_eqs
+}
+
+/// Names of the components referenced by connect equations, without subscripts
+fn connected_components(
+    connect_eqs: &[(ComponentReference, ComponentReference)],
+) -> IndexSet<String> {
+    let name = |cref: &ComponentReference| {
+        cref.parts
+            .iter()
+            .map(|part| part.ident.text.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+    connect_eqs
+        .iter()
+        .flat_map(|(lhs, rhs)| [name(lhs), name(rhs)])
+        .collect()
+}
+
 /// Expands connect equations into simple equations.
 ///
 /// Connect equations in Modelica follow these rules:
@@ -1109,6 +1706,10 @@ fn expand_connect_equations(
         return Ok(());
     }
 
+    for (lhs, rhs) in &connect_eqs {
+        check_connection(lhs, rhs, fclass, class_dict, pin_types)?;
+    }
+
     // Build connection sets using a simple union-find approach
     // Each pin is represented as "component.subcomponent" (e.g., "R1.p")
     let mut parent: IndexMap<String, String> = IndexMap::new();
@@ -1258,6 +1859,225 @@ fn generate_connection_equations(
     }
 }
 
+/// Maximum depth of nested connectors compared (guards against cycles)
+const MAX_CONNECTOR_DEPTH: usize = 16;
+
+/// A connector class with its inherited components and the type names of its
+/// components qualified, following short class definitions to a class with
+/// components or a signal connector like `connector RealInput = input Real`
+fn resolve_connector(path: &str, class_dict: &ClassDict) -> Option<ir::ast::ClassDefinition> {
+    let mut path = path.to_string();
+    for _ in 0..MAX_CONNECTOR_DEPTH {
+        let found = class_dict.get(&path)?;
+        let mut resolved = resolve_class_internal(
+            found,
+            &path,
+            class_dict,
+            &mut IndexSet::new(),
+            &mut FileDependencies::new(),
+        )
+        .ok()?;
+        let aliases = build_import_aliases_for_class(&path, class_dict);
+        for comp in resolved.components.values_mut() {
+            comp.type_name = qualify_type_name(&comp.type_name, &path, class_dict, &aliases);
+        }
+        match &resolved.short_class {
+            Some(short) if short.shape_expr.is_empty() && resolved.components.is_empty() => {
+                let base = qualify_type_name(&short.base_type, &path, class_dict, &aliases);
+                if is_primitive_type(&base.to_string()) {
+                    return Some(resolved);
+                }
+                path = base.to_string();
+            }
+            _ => return Some(resolved),
+        }
+    }
+    None
+}
+
+/// Check that the connectors of a connect equation are compatible, so the
+/// generated equations relate matching variables: both have the same
+/// components, with the same flow/stream prefixes and dimensions, or both are
+/// signal connectors of the same built-in type and shape.
+///
+/// Subscripted connectors and connectors whose classes can't be found (e.g.
+/// from libraries that aren't loaded) are not checked.
+fn check_connection(
+    lhs: &ComponentReference,
+    rhs: &ComponentReference,
+    fclass: &ir::ast::ClassDefinition,
+    class_dict: &ClassDict,
+    pin_types: &IndexMap<String, String>,
+) -> Result<()> {
+    let (lhs_name, rhs_name) = (lhs.to_string(), rhs.to_string());
+    let (Some(lhs_type), Some(rhs_type)) = (pin_types.get(&lhs_name), pin_types.get(&rhs_name))
+    else {
+        return Ok(());
+    };
+    let mut mismatches = Vec::new();
+
+    // Signal connectors are flattened to variables with their shapes
+    let known_shape = |comp: &ir::ast::Component| {
+        (!comp.shape.is_empty() || comp.shape_expr.is_empty()).then(|| comp.shape.clone())
+    };
+    if let (Some(a), Some(b)) = (
+        fclass.components.get(&lhs_name).and_then(known_shape),
+        fclass.components.get(&rhs_name).and_then(known_shape),
+    ) && a != b
+    {
+        mismatches.push(format!(
+            "'{}' has dimensions {}, but '{}' has {}",
+            lhs_name,
+            format_shape(&a),
+            rhs_name,
+            format_shape(&b)
+        ));
+    }
+
+    if lhs_type != rhs_type
+        && let (Some(a), Some(b)) = (
+            resolve_connector(lhs_type, class_dict),
+            resolve_connector(rhs_type, class_dict),
+        )
+    {
+        compare_connectors(
+            "",
+            (lhs_type, &a),
+            (rhs_type, &b),
+            class_dict,
+            0,
+            &mut mismatches,
+        );
+    }
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let loc = lhs
+        .parts
+        .first()
+        .map(|part| &part.ident.location)
+        .cloned()
+        .unwrap_or_default();
+    anyhow::bail!(
+        "{}:{}:{}: connect({}, {}): connectors '{}' and '{}' are incompatible:\n  {}",
+        loc.file_name,
+        loc.start_line,
+        loc.start_column,
+        lhs_name,
+        rhs_name,
+        lhs_type,
+        rhs_type,
+        mismatches.join("\n  ")
+    )
+}
+
+/// Compare the components of two connector classes, given with their type
+/// names, recording the mismatches of components under `prefix`
+fn compare_connectors(
+    prefix: &str,
+    (a_name, a): (&str, &ir::ast::ClassDefinition),
+    (b_name, b): (&str, &ir::ast::ClassDefinition),
+    class_dict: &ClassDict,
+    depth: usize,
+    mismatches: &mut Vec<String>,
+) {
+    let signal_type = |class: &ir::ast::ClassDefinition| {
+        class.components.is_empty().then(|| {
+            class
+                .short_class
+                .as_ref()
+                .map_or(String::new(), |short| short.base_type.to_string())
+        })
+    };
+    match (signal_type(a), signal_type(b)) {
+        (Some(a_type), Some(b_type)) => {
+            if a_type != b_type {
+                mismatches.push(format!(
+                    "'{}' is a '{}' signal, but '{}' is a '{}' signal",
+                    a_name, a_type, b_name, b_type
+                ));
+            }
+            return;
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            let (signal, structured) = if a.components.is_empty() {
+                (a_name, b_name)
+            } else {
+                (b_name, a_name)
+            };
+            mismatches.push(format!(
+                "'{}' is a signal connector, but '{}' has components",
+                signal, structured
+            ));
+            return;
+        }
+        (None, None) => {}
+    }
+
+    for (name, comp) in &a.components {
+        let path = format!("{}{}", prefix, name);
+        let Some(other) = b.components.get(name) else {
+            mismatches.push(format!("'{}' is missing in '{}'", path, b_name));
+            continue;
+        };
+        if std::mem::discriminant(&comp.connection) != std::mem::discriminant(&other.connection) {
+            mismatches.push(format!(
+                "'{}' is {} in '{}', but {} in '{}'",
+                path,
+                plug_compatibility::connection_name(&comp.connection),
+                a_name,
+                plug_compatibility::connection_name(&other.connection),
+                b_name
+            ));
+        }
+        let (dims, other_dims) = (
+            plug_compatibility::dimensions(comp),
+            plug_compatibility::dimensions(other),
+        );
+        if dims != other_dims {
+            mismatches.push(format!(
+                "'{}' has dimensions {} in '{}', but {} in '{}'",
+                path,
+                plug_compatibility::format_dimensions(&dims),
+                a_name,
+                plug_compatibility::format_dimensions(&other_dims),
+                b_name
+            ));
+        }
+
+        // Nested connectors are compared component by component
+        let (comp_type, other_type) = (comp.type_name.to_string(), other.type_name.to_string());
+        if comp_type != other_type
+            && depth < MAX_CONNECTOR_DEPTH
+            && let (Some(comp_class), Some(other_class)) = (
+                resolve_connector(&comp_type, class_dict),
+                resolve_connector(&other_type, class_dict),
+            )
+            && !(comp_class.components.is_empty() && other_class.components.is_empty())
+        {
+            compare_connectors(
+                &format!("{}.", path),
+                (&comp_type, &comp_class),
+                (&other_type, &other_class),
+                class_dict,
+                depth + 1,
+                mismatches,
+            );
+        }
+    }
+    for name in b.components.keys() {
+        if !a.components.contains_key(name) {
+            mismatches.push(format!("'{}{}' is missing in '{}'", prefix, name, a_name));
+        }
+    }
+}
+
+fn format_shape(shape: &[usize]) -> String {
+    let dims: Vec<String> = shape.iter().map(|dim| dim.to_string()).collect();
+    plug_compatibility::format_dimensions(&dims)
+}
+
 /// Track inner components for inner/outer resolution.
 /// Maps (type_name, component_name) -> flattened component name
 /// For example, ("World", "world") -> "world" means there's an inner World world at the top level
@@ -1324,14 +2144,25 @@ struct ExpansionContext<'a> {
     symbol_table: &'a SymbolTable,
     /// Maps flattened pin names to their connector types
     pin_types: IndexMap<String, String>,
+    /// Expanded components by flattened name
+    instances: IndexMap<String, ComponentInstance>,
+    /// Shapes of the arrays of components expanded element by element
+    component_arrays: IndexMap<String, Vec<usize>>,
     /// Maps (type_name, component_name) -> inner component's flattened name
     inner_map: InnerMap,
     /// Tracks outer->inner mappings for equation rewriting
     outer_renamer: OuterRenamer,
     /// Content hash of StoredDefinition for cache key stability
     def_hash: u64,
+    /// Classes already resolved for the StoredDefinition
+    resolved: &'a ResolvedClasses,
     /// Collected file dependencies from all resolved classes
     deps: FileDependencies,
+    /// Classes of the components being expanded, outermost (the main class)
+    /// first, to detect recursive instantiation
+    instance_path: Vec<String>,
+    /// Resource limits of the compilation
+    limits: Limits,
 }
 
 impl<'a> ExpansionContext<'a> {
@@ -1340,16 +2171,24 @@ impl<'a> ExpansionContext<'a> {
         class_dict: &'a ClassDict,
         symbol_table: &'a SymbolTable,
         def_hash: u64,
+        resolved: &'a ResolvedClasses,
+        main_class_name: &str,
+        limits: Limits,
     ) -> Self {
         Self {
             fclass,
             class_dict,
             symbol_table,
             pin_types: IndexMap::new(),
+            instances: IndexMap::new(),
+            component_arrays: IndexMap::new(),
             inner_map: IndexMap::new(),
             outer_renamer: OuterRenamer::default(),
             def_hash,
+            resolved,
             deps: FileDependencies::new(),
+            instance_path: vec![main_class_name.to_string()],
+            limits,
         }
     }
 
@@ -1368,6 +2207,80 @@ impl<'a> ExpansionContext<'a> {
         self.fclass.accept_mut(&mut self.outer_renamer);
     }
 
+    /// Shape of an array component, evaluating parameter-dependent dimensions
+    /// (already scoped when the component was added) with the flattened
+    /// parameters. None for scalars and for dimensions that can't be evaluated.
+    fn component_array_shape(&self, comp: &ir::ast::Component) -> Option<Vec<usize>> {
+        if !comp.shape.is_empty() {
+            return Some(comp.shape.clone());
+        }
+        if comp.shape_expr.is_empty() {
+            return None;
+        }
+        comp.shape_expr
+            .iter()
+            .map(|sub| {
+                let ir::ast::Subscript::Expression(expr) = sub else {
+                    return None;
+                };
+                let dim = eval_integer_with_params(expr, &self.fclass.components)?;
+                usize::try_from(dim).ok()
+            })
+            .collect()
+    }
+
+    /// Expand an array of components into one instance per element, splitting
+    /// array modifications among the elements
+    fn expand_component_array(
+        &mut self,
+        comp_name: &str,
+        comp: &ir::ast::Component,
+        shape: Vec<usize>,
+        current_class_path: &str,
+    ) -> Result<()> {
+        self.limits
+            .check_expansion(shape.iter().product(), &comp.name_token.location, || {
+                format!("component array '{}'", comp_name)
+            })?;
+        self.fclass.components.swap_remove(comp_name);
+        let is_array = |name: &str| {
+            self.fclass
+                .components
+                .get(name)
+                .is_some_and(|c| !c.shape.is_empty() || !c.shape_expr.is_empty())
+        };
+        let mut elements = Vec::new();
+        for index in component_arrays::element_indices(&shape) {
+            let name = component_arrays::element_name(comp_name, &index);
+            let mut element = comp.clone();
+            element.name = name.clone();
+            element.shape.clear();
+            element.shape_expr.clear();
+            for (key, value) in element.modifications.iter_mut() {
+                if comp.modification_prefixes.get(key).is_some_and(|p| p.each) {
+                    continue;
+                }
+                *value = component_arrays::element_modification(value, &index, &shape, &is_array)
+                    .map_err(|e| {
+                    let loc = &comp.name_token.location;
+                    anyhow::anyhow!(
+                        "{}:{}:{}: {}",
+                        loc.file_name,
+                        loc.start_line,
+                        loc.start_column,
+                        e
+                    )
+                })?;
+            }
+            elements.push((name, element));
+        }
+        self.component_arrays.insert(comp_name.to_string(), shape);
+        for (name, element) in &elements {
+            self.expand_component(name, element, current_class_path)?;
+        }
+        Ok(())
+    }
+
     /// Expand a component recursively
     fn expand_component(
         &mut self,
@@ -1399,6 +2312,33 @@ impl<'a> ExpansionContext<'a> {
             }
         };
 
+        // A component of a class containing it would be expanded forever
+        let location = comp.name_token.location.file_position();
+        if let Some(start) = self
+            .instance_path
+            .iter()
+            .position(|class| *class == resolved_type_name)
+        {
+            let mut cycle = self.instance_path[start..].to_vec();
+            cycle.push(resolved_type_name);
+            return Err(IrError::RecursiveInstantiation {
+                location,
+                component: comp_name.to_string(),
+                cycle: cycle.join(" -> "),
+            }
+            .into());
+        }
+        let depth = self.limits.max_instance_depth;
+        if self.instance_path.len() > depth {
+            return Err(IrError::InstantiationTooDeep {
+                location,
+                component: comp_name.to_string(),
+                depth,
+            }
+            .into());
+        }
+        check_deadline()?;
+
         // Get the component class
         let comp_class_raw = match self.class_dict.get(&resolved_type_name) {
             Some(c) => c,
@@ -1411,6 +2351,7 @@ impl<'a> ExpansionContext<'a> {
             &resolved_type_name,
             self.class_dict,
             self.def_hash,
+            self.resolved,
         )?;
 
         // Collect dependencies from this resolved class
@@ -1418,11 +2359,34 @@ impl<'a> ExpansionContext<'a> {
             self.deps.record(file, hash);
         }
 
+        // Arrays of instances are expanded element by element
+        if !comp_class.components.is_empty()
+            && let Some(shape) = self.component_array_shape(comp)
+        {
+            return self.expand_component_array(comp_name, comp, shape, current_class_path);
+        }
+
         // Record the connector type for this component BEFORE checking if it has sub-components.
         // This is critical for connectors like Pin that have only primitive types (Real v, Real i).
         // These connectors have no class-type sub-components but are still used in connect equations.
         self.pin_types
             .insert(comp_name.to_string(), resolved_type_name.clone());
+        let conditional = comp.condition.is_some()
+            || comp_name
+                .rsplit_once('.')
+                .and_then(|(parent, _)| self.instances.get(parent))
+                .is_some_and(|parent| parent.conditional);
+        self.instances.insert(
+            comp_name.to_string(),
+            ComponentInstance {
+                type_name: resolved_type_name.clone(),
+                class_type: comp_class.class_type.clone(),
+                location: comp.name_token.location.clone(),
+                conditional,
+                condition: comp.condition.clone(),
+                connected: false,
+            },
+        );
 
         // If the resolved class has no components, it's effectively a type alias (like Voltage = Real)
         // or a "leaf" connector with only primitive types.
@@ -1448,6 +2412,22 @@ impl<'a> ExpansionContext<'a> {
             return Ok(());
         }
 
+        // A record bound to another record, e.g. `Frame q = p`, is bound field
+        // by field (see expand_record_equations)
+        if comp_class.class_type == ir::ast::ClassType::Record
+            && let Some(binding @ Expression::ComponentReference(_)) = comp.binding()
+        {
+            let binding_eq = make_binding_eq(comp_name, binding.clone());
+            if matches!(
+                comp.variability,
+                ir::ast::Variability::Parameter(_) | ir::ast::Variability::Constant(_)
+            ) {
+                self.fclass.initial_equations.push(binding_eq);
+            } else {
+                self.fclass.equations.push(binding_eq);
+            }
+        }
+
         // Create a scope renamer for this component
         let mut renamer = ScopeRenamer::new(self.symbol_table, comp_name);
 
@@ -1497,6 +2477,17 @@ impl<'a> ExpansionContext<'a> {
             let name = format!("{}.{}", comp_name, subcomp_name);
             scomp.name = name.clone();
 
+            // The causality of a component applies to its elements, e.g. the
+            // variables of an `input Pin p` are inputs
+            if matches!(scomp.causality, ir::ast::Causality::Empty) {
+                scomp.causality = comp.causality.clone();
+            }
+            // So do the variability prefixes, e.g. the fields of a
+            // `parameter Frame p` are parameters
+            if matches!(scomp.variability, ir::ast::Variability::Empty) {
+                scomp.variability = comp.variability.clone();
+            }
+
             // If this is an inner component, register it
             if subcomp.inner {
                 let key = (subcomp.type_name.to_string(), subcomp_name.clone());
@@ -1507,6 +2498,14 @@ impl<'a> ExpansionContext<'a> {
             // For simple literals, use as start value
             // For complex expressions, generate binding equations
             if let Some(mod_expr) = comp.modifications.get(subcomp_name) {
+                check_modifiable(subcomp, None, &name)?;
+                if comp
+                    .modification_prefixes
+                    .get(subcomp_name)
+                    .is_some_and(|p| p.r#final)
+                {
+                    scomp.r#final = true;
+                }
                 if is_simple_literal(mod_expr) {
                     scomp.start = mod_expr.clone();
                 } else {
@@ -1516,13 +2515,17 @@ impl<'a> ExpansionContext<'a> {
                     // Parameter and constant bindings go to initial equations (computed once at init)
                     // Other bindings go to regular equations
                     if matches!(
-                        subcomp.variability,
+                        scomp.variability,
                         ir::ast::Variability::Parameter(_) | ir::ast::Variability::Constant(_)
                     ) {
                         self.fclass.initial_equations.push(binding_eq);
                     } else {
                         self.fclass.equations.push(binding_eq);
                     }
+                    // The modification replaces the declaration's own binding
+                    if !scomp.start_is_modification {
+                        scomp.start = Expression::Empty;
+                    }
                 }
             }
 
@@ -1530,12 +2533,38 @@ impl<'a> ExpansionContext<'a> {
             // This prefixes internal references like `x_start` to `comp.x_start`
             scomp.start.accept_mut(&mut renamer);
 
+            // Apply scope renaming to the component's array dimensions
+            // This prefixes parameters like `n` in `x[n]` to `comp.n`
+            for sub in scomp.shape_expr.iter_mut() {
+                sub.accept_mut(&mut renamer);
+                sub.accept_mut(&mut SubCompNamer {
+                    comp: comp_name.to_string(),
+                });
+            }
+
             // Apply scope renaming to the component's modifications
             // This prefixes internal references like `unitTime/Ti` to `comp.unitTime/comp.Ti`
             for mod_expr in scomp.modifications.values_mut() {
                 mod_expr.accept_mut(&mut renamer);
             }
 
+            // Apply scope renaming to the condition of a conditional component
+            if let Some(condition) = scomp.condition.as_mut() {
+                condition.accept_mut(&mut renamer);
+            }
+
+            // Merge nested modifications from the parent (e.g. `motor(inertia.J = 2)` or
+            // `motor(inertia(J = 2))` applied to `inertia`). They override the subcomponent's
+            // own modifications and are already in the parent's scope, so no renaming is needed.
+            let nested_prefix = format!("{}.", subcomp_name);
+            for (key, mod_expr) in &comp.modifications {
+                if let Some(rest) = key.strip_prefix(&nested_prefix) {
+                    let prefix = comp.modification_prefixes.get(key).copied();
+                    let path = format!("{}.{}", name, rest);
+                    apply_modification(&mut scomp, rest, mod_expr, prefix, &path)?;
+                }
+            }
+
             // For parameters with non-simple start expressions (binding equations like
             // `zeroGain = abs(k) < eps`), generate an initial equation if no parent
             // modification was applied. The start expression has already been scope-renamed.
@@ -1569,6 +2598,7 @@ impl<'a> ExpansionContext<'a> {
         // Build import aliases for the resolved component class for subcomponent resolution
         let subcomp_import_aliases =
             build_import_aliases_for_class(&resolved_type_name, self.class_dict);
+        self.instance_path.push(resolved_type_name.clone());
         for (subcomp_name, subcomp) in &subcomponents {
             // Use resolved_type_name as context for resolving nested component types
             if resolve_class_name_with_imports(
@@ -1582,11 +2612,121 @@ impl<'a> ExpansionContext<'a> {
                 self.expand_component(subcomp_name, subcomp, &resolved_type_name)?;
             }
         }
+        self.instance_path.pop();
 
         Ok(())
     }
 }
 
+/// Replace the equations between two records, e.g. `q = p`, by one equation
+/// per field of the flattened records: `q.origin.x = p.origin.x`, ...
+///
+/// Equations whose right-hand side isn't a record with the same fields are
+/// kept, to be reported by the later stages.
+fn expand_record_equations(
+    fclass: &mut ir::ast::ClassDefinition,
+    instances: &IndexMap<String, ComponentInstance>,
+) {
+    let expand = |equations: &mut Vec<Equation>, components: &IndexMap<_, _>| {
+        *equations = std::mem::take(equations)
+            .into_iter()
+            .flat_map(
+                |eq| match record_fields_equations(&eq, components, instances) {
+                    Some(fields) => fields,
+                    None => vec![eq],
+                },
+            )
+            .collect();
+    };
+    expand(&mut fclass.equations, &fclass.components);
+    expand(&mut fclass.initial_equations, &fclass.components);
+}
+
+/// The field equations of an equation between two records, see
+/// [`expand_record_equations`]
+fn record_fields_equations(
+    eq: &Equation,
+    components: &IndexMap<String, ir::ast::Component>,
+    instances: &IndexMap<String, ComponentInstance>,
+) -> Option<Vec<Equation>> {
+    let Equation::Simple {
+        lhs: Expression::ComponentReference(lhs),
+        rhs: Expression::ComponentReference(rhs),
+        description,
+    } = eq
+    else {
+        return None;
+    };
+    let (lhs_name, rhs_name) = (lhs.to_string(), rhs.to_string());
+    if instances.get(&lhs_name)?.class_type != ir::ast::ClassType::Record {
+        return None;
+    }
+    let prefix = format!("{}.", lhs_name);
+    let fields: Vec<&str> = components
+        .keys()
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .collect();
+    if fields.is_empty()
+        || !fields
+            .iter()
+            .all(|field| components.contains_key(&format!("{}.{}", rhs_name, field)))
+    {
+        return None;
+    }
+    Some(
+        fields
+            .into_iter()
+            .map(|field| Equation::Simple {
+                lhs: Expression::ComponentReference(field_ref(lhs, field)),
+                rhs: Expression::ComponentReference(field_ref(rhs, field)),
+                description: description.clone(),
+            })
+            .collect(),
+    )
+}
+
+/// The flattened reference to a field of a record, e.g. `p.origin.x` for the
+/// field `x` of `p.origin`, keeping the location of the record reference
+fn field_ref(record: &ComponentReference, field: &str) -> ComponentReference {
+    ComponentReference {
+        local: record.local,
+        parts: vec![ComponentRefPart {
+            ident: Token {
+                text: format!("{}.{}", record, field),
+                ..record.parts[0].ident.clone()
+            },
+            subs: None,
+        }],
+    }
+}
+
+/// Inputs of a flattened class that belong to a sub-model or block rather
+/// than to the class itself or one of its connectors (or records), e.g.
+/// `gain.u` but not `u` or `bus.u`
+fn internal_inputs(
+    fclass: &ir::ast::ClassDefinition,
+    instances: &IndexMap<String, ComponentInstance>,
+) -> IndexSet<String> {
+    use crate::ir::ast::{Causality, ClassType};
+
+    fclass
+        .components
+        .iter()
+        .filter(|(_, comp)| matches!(comp.causality, Causality::Input(..)))
+        .filter(|(name, _)| {
+            name.match_indices('.').any(|(end, _)| {
+                instances.get(&name[..end]).is_some_and(|instance| {
+                    !matches!(
+                        instance.class_type,
+                        ClassType::Connector | ClassType::Record | ClassType::Type
+                    )
+                })
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
 /// Flattens a hierarchical Modelica class definition into a single flat class.
 ///
 /// This function takes a stored definition containing one or more class definitions
@@ -1737,75 +2877,154 @@ pub fn flatten_with_deps(
     def: &ir::ast::StoredDefinition,
     model_name: Option<&str>,
 ) -> Result<FlattenResult> {
-    // Compute content hash for cache key stability
-    let def_hash = compute_def_hash(def);
+    FlattenContext::new(def).flatten(model_name)
+}
 
-    // Get or build cached class dictionary
-    let class_dict = get_or_build_class_dict(def, def_hash);
+/// The class dictionary and resolved classes of a StoredDefinition.
+///
+/// Flattening several models of the same definition through one context
+/// hashes the definition, builds its class dictionary and resolves each class
+/// (extends clauses) only once, instead of once per model. The context is
+/// `Sync`, so models can be flattened in parallel.
+pub struct FlattenContext<'a> {
+    def: &'a ir::ast::StoredDefinition,
+    /// Content hash of the definition
+    def_hash: u64,
+    class_dict: Arc<ClassDict>,
+    resolved: ResolvedClasses,
+    limits: Limits,
+}
+
+impl<'a> FlattenContext<'a> {
+    pub fn new(def: &'a ir::ast::StoredDefinition) -> Self {
+        let def_hash = compute_def_hash(def);
+        Self {
+            def,
+            def_hash,
+            class_dict: get_or_build_class_dict(def, def_hash),
+            resolved: ResolvedClasses::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Flatten models within the expansion size and depth `limits`
+    /// instead of the default ones
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-    // Determine main class name - model name is required
-    let main_class_name = model_name.ok_or(IrError::ModelNameRequired)?.to_string();
+    /// The definition models are flattened from
+    pub fn def(&self) -> &'a ir::ast::StoredDefinition {
+        self.def
+    }
 
-    // Get main class (supports dotted paths like "Package.Model")
-    let main_class =
-        lookup_class(def, &class_dict, &main_class_name).ok_or(IrError::MainClassNotFound)?;
+    /// Flatten a model and return both the flattened class and its file dependencies.
+    pub fn flatten(&self, model_name: Option<&str>) -> Result<FlattenResult> {
+        let (def, def_hash, class_dict) = (self.def, self.def_hash, &self.class_dict);
 
-    // Resolve the main class (process extends clauses recursively)
-    // This also collects dependencies from all classes involved
-    let (resolved_main, mut deps) =
-        resolve_class(&main_class, &main_class_name, &class_dict, def_hash)?;
+        // Determine main class name - model name is required
+        let main_class_name = model_name.ok_or(IrError::ModelNameRequired)?.to_string();
 
-    // Validate all imports in the resolved class before proceeding
-    validate_imports(&resolved_main.imports, &class_dict)?;
+        // Get main class (supports dotted paths like "Package.Model")
+        let main_class =
+            lookup_class(def, class_dict, &main_class_name).ok_or(IrError::MainClassNotFound)?;
 
-    // Create the flat class starting from resolved main
-    // Clone the inner value from Arc since we need a mutable copy for flattening
-    let mut fclass = (*resolved_main).clone();
+        // Resolve the main class (process extends clauses recursively)
+        // This also collects dependencies from all classes involved
+        let (resolved_main, mut deps) = resolve_class(
+            &main_class,
+            &main_class_name,
+            class_dict,
+            def_hash,
+            &self.resolved,
+        )?;
 
-    // Create symbol table for tracking variable scopes
-    let symbol_table = SymbolTable::new();
+        // Validate all imports in the resolved class before proceeding
+        validate_imports(&resolved_main.imports, class_dict)?;
 
-    // Create expansion context
-    let mut ctx = ExpansionContext::new(&mut fclass, &class_dict, &symbol_table, def_hash);
+        // Create the flat class starting from resolved main
+        // Clone the inner value from Arc since we need a mutable copy for flattening
+        let mut fclass = (*resolved_main).clone();
 
-    // Register top-level inner components before expansion
-    ctx.register_inner_components(&resolved_main.components);
+        // Create symbol table for tracking variable scopes
+        let symbol_table = SymbolTable::new();
 
-    // Collect component names that need expansion (to avoid borrow issues)
-    // Include all non-primitive types - expand_component will error if type is not found
-    let components_to_expand: Vec<(String, ir::ast::Component)> = resolved_main
-        .components
-        .iter()
-        .filter(|(_, comp)| {
-            // Skip primitive types, they don't need expansion
-            !is_primitive_type(&comp.type_name.to_string())
-        })
-        .map(|(name, comp)| (name.clone(), comp.clone()))
-        .collect();
+        // Create expansion context
+        let mut ctx = ExpansionContext::new(
+            &mut fclass,
+            class_dict,
+            &symbol_table,
+            def_hash,
+            &self.resolved,
+            &main_class_name,
+            self.limits,
+        );
 
-    // Recursively expand each component that references a class (with inner/outer support)
-    // Note: component expansion may use additional classes, but those dependencies
-    // are already captured in resolve_class calls during expansion
-    for (comp_name, comp) in &components_to_expand {
-        ctx.expand_component(comp_name, comp, &main_class_name)?;
-    }
+        // Register top-level inner components before expansion
+        ctx.register_inner_components(&resolved_main.components);
+
+        // Collect component names that need expansion (to avoid borrow issues)
+        // Include all non-primitive types - expand_component will error if type is not found
+        let components_to_expand: Vec<(String, ir::ast::Component)> = resolved_main
+            .components
+            .iter()
+            .filter(|(_, comp)| {
+                // Skip primitive types, they don't need expansion
+                !is_primitive_type(&comp.type_name.to_string())
+            })
+            .map(|(name, comp)| (name.clone(), comp.clone()))
+            .collect();
+
+        // Recursively expand each component that references a class (with inner/outer support)
+        // Note: component expansion may use additional classes, but those dependencies
+        // are already captured in resolve_class calls during expansion
+        for (comp_name, comp) in &components_to_expand {
+            ctx.expand_component(comp_name, comp, &main_class_name)?;
+        }
 
-    // Rewrite equations to redirect outer references to inner components
-    ctx.apply_outer_renaming();
+        // Rewrite equations to redirect outer references to inner components
+        ctx.apply_outer_renaming();
 
-    // Extract pin_types and merge component dependencies
-    let pin_types = ctx.pin_types;
+        // Extract pin_types and instances, and merge component dependencies
+        let pin_types = ctx.pin_types;
+        let mut instances = ctx.instances;
+        let component_arrays = ctx.component_arrays;
 
-    // Merge dependencies from component expansion into main deps
-    for (file, hash) in ctx.deps.files {
-        deps.record(&file, &hash);
-    }
+        // Merge dependencies from component expansion into main deps
+        for (file, hash) in ctx.deps.files {
+            deps.record(&file, &hash);
+        }
+
+        // Refer to the elements of arrays of components by their flattened names
+        let instance_names: IndexSet<String> = instances.keys().cloned().collect();
+        component_arrays::expand_array_references(&mut fclass, &component_arrays, &instance_names)?;
+
+        // Equations between records, e.g. `q = p` or the binding `q(origin = p.origin)`
+        expand_record_equations(&mut fclass, &instances);
+
+        // Mark the instances referenced by connect equations
+        let connects = connect_equations(&fclass.equations);
+        let connected = connected_components(&connects);
+        for (name, instance) in instances.iter_mut() {
+            instance.connected = connected.iter().any(|pin| {
+                let (pin, name) = (pin.as_str(), name.as_str());
+                pin == name
+                    || pin.strip_prefix(name).is_some_and(|p| p.starts_with('.'))
+                    || name.strip_prefix(pin).is_some_and(|n| n.starts_with('.'))
+            });
+        }
 
-    // Expand connect equations into simple equations
-    expand_connect_equations(&mut fclass, &class_dict, &pin_types)?;
+        // Expand connect equations into simple equations
+        expand_connect_equations(&mut fclass, class_dict, &pin_types)?;
 
-    Ok(FlattenResult {
-        class: fclass,
-        dependencies: deps,
-    })
+        fclass.internal_inputs = internal_inputs(&fclass, &instances);
+
+        Ok(FlattenResult {
+            class: fclass,
+            dependencies: deps,
+            instances,
+            connects,
+        })
+    }
 }
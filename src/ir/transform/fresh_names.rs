@@ -0,0 +1,78 @@
+//! Fresh names for variables introduced by the compiler.
+//!
+//! Passes that introduce variables, like the condition variables `c0`, `c1`,
+//! ... of [`ConditionFinder`](crate::ir::analysis::condition_finder::ConditionFinder),
+//! name them with a prefix reserved for the pass (see
+//! [`constants`](crate::ir::transform::constants)) and a number. The names
+//! must not collide with the names of the model, and must not change between
+//! runs, so that generated code and equation ids are reproducible:
+//!
+//! - numbers count up from 0 for each prefix, in the order the pass asks for
+//!   names, which follows the order of the flattened model
+//! - names of the flattened model are skipped, e.g. a model with a variable
+//!   `c0` gets the conditions `c1`, `c2`, ...
+//!
+//! ```
+//! use rumoca::ir::transform::fresh_names::FreshNames;
+//!
+//! let mut names = FreshNames::from_names(["c0", "x"]);
+//! assert_eq!(names.fresh("c"), "c1");
+//! assert_eq!(names.fresh("c"), "c2");
+//! assert_eq!(names.fresh("tmp"), "tmp0");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::ast::ClassDefinition;
+
+/// Generator of fresh names, see the [module docs](self)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FreshNames {
+    /// Names of the model and names given out
+    taken: HashSet<String>,
+    /// Next number to try for each prefix
+    next: HashMap<String, usize>,
+}
+
+impl FreshNames {
+    /// Create a generator avoiding the component names of a flattened class
+    pub fn new(class: &ClassDefinition) -> Self {
+        Self::from_names(class.components.keys())
+    }
+
+    /// Create a generator avoiding the given names
+    pub fn from_names(names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            taken: names.into_iter().map(Into::into).collect(),
+            next: HashMap::new(),
+        }
+    }
+
+    /// The next name with a prefix that isn't taken, which is taken from now on
+    pub fn fresh(&mut self, prefix: &str) -> String {
+        let next = self.next.entry(prefix.to_string()).or_default();
+        loop {
+            let name = format!("{}{}", prefix, next);
+            *next += 1;
+            if self.taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_names() {
+        let mut names = FreshNames::from_names(["c1", "c2", "c4"]);
+        let fresh: Vec<String> = (0..3).map(|_| names.fresh("c")).collect();
+        assert_eq!(fresh, ["c0", "c3", "c5"]);
+
+        // Prefixes are numbered independently, and names are never reused
+        assert_eq!(names.fresh("c_"), "c_0");
+        assert_eq!(names.fresh("c"), "c6");
+    }
+}
@@ -2,10 +2,25 @@
 //!
 //! This visitor inlines user-defined function calls by substituting
 //! the function body with actual arguments.
+//!
+//! The function annotations `Inline` and `smoothOrder` are honored: `Inline=false`
+//! keeps the call, `Inline=true` also inlines bodies with if-statements, and
+//! relations inlined from a function with `smoothOrder` are wrapped in `noEvent()`.
+//! Impure functions (see [`purity`](crate::ir::analysis::purity)) are never
+//! inlined, so their calls are kept.
+//!
+//! Calls may omit the inputs with a default, as in `input Real eps = 1e-6;`,
+//! and name their arguments, as in `f(x, eps = 1e-3)`. [`bind_arguments`]
+//! matches them to the inputs, and every call of a user function, inlined or
+//! kept, is rewritten with all of its arguments, in declaration order.
 
-use crate::ir::ast::{Causality, ClassDefinition, ClassType, Expression, Statement};
-use crate::ir::transform::constants::is_builtin_function;
-use crate::ir::visitor::MutVisitor;
+use crate::ir::analysis::function_annotations::FunctionAnnotations;
+use crate::ir::ast::{
+    Causality, ClassDefinition, ClassType, Component, ComponentRefPart, ComponentReference,
+    Expression, OpBinary, Statement, Subscript, TerminalType, Token,
+};
+use crate::ir::transform::constants::{BUILTIN_NO_EVENT, is_builtin_function};
+use crate::ir::visitor::{MutVisitor, Visitable, Visitor};
 use indexmap::IndexMap;
 
 /// Visitor that inlines user-defined function calls
@@ -27,9 +42,41 @@ impl<'a> FunctionInliner<'a> {
         for (_name, class) in class_list {
             Self::collect_functions_recursive(class, "", &mut functions);
         }
+        // Full names win over the short and relative names of other
+        // functions, e.g. `P.f` names the top-level `P.f`, not `Outer.P.f`
+        let mut full_names = IndexMap::new();
+        for (_name, class) in class_list {
+            Self::collect_full_names(class, "", &mut full_names);
+        }
+        functions.extend(full_names);
         Self { functions }
     }
 
+    /// Recursively collect functions from a class and its nested classes by
+    /// full name only
+    fn collect_full_names(
+        class: &'a ClassDefinition,
+        prefix: &str,
+        functions: &mut IndexMap<String, &'a ClassDefinition>,
+    ) {
+        let full_name = if prefix.is_empty() {
+            class.name.text.clone()
+        } else {
+            format!("{}.{}", prefix, class.name.text)
+        };
+        for (_name, nested_class) in &class.classes {
+            Self::collect_full_names(nested_class, &full_name, functions);
+        }
+        if matches!(class.class_type, ClassType::Function) {
+            functions.insert(full_name, class);
+        }
+    }
+
+    /// The functions by full, short and package-relative name
+    pub fn functions(&self) -> &IndexMap<String, &'a ClassDefinition> {
+        &self.functions
+    }
+
     /// Recursively collect functions from a class and its nested classes
     fn collect_functions_recursive(
         class: &'a ClassDefinition,
@@ -86,28 +133,37 @@ impl<'a> FunctionInliner<'a> {
         }
     }
 
-    /// Inline a function call, returning the substituted expression
-    /// For single-output functions, returns the single expression
-    /// For multi-output functions, returns a Tuple of expressions
-    fn inline_call(&self, func_name: &str, args: &[Expression]) -> Option<Expression> {
-        // Don't inline built-in functions (like abs, sqrt, sin, cos, etc.)
-        // These should be preserved and handled by the backend
-        // Also extract the simple function name (last part) for checking
+    /// The user-defined function of a name, unless the name is a built-in one
+    fn user_function(&self, func_name: &str) -> Option<&'a ClassDefinition> {
+        // Built-in functions (like abs, sqrt, sin, cos, etc.) are preserved
+        // and handled by the backend. Also extract the simple function name
+        // (last part) for checking
         let simple_name = func_name.rsplit('.').next().unwrap_or(func_name);
         if is_builtin_function(simple_name) {
             return None;
         }
+        self.functions.get(func_name).copied()
+    }
 
-        let func = self.functions.get(func_name)?;
+    /// Inline a function call with all of its arguments, in declaration
+    /// order, returning the substituted expression
+    /// For single-output functions, returns the single expression
+    /// For multi-output functions, returns a Tuple of expressions
+    fn inline_call(&self, func_name: &str, args: &[Expression]) -> Option<Expression> {
+        let func = self.user_function(func_name)?;
+        let annotations = FunctionAnnotations::from_class(func);
+        if annotations.inline == Some(false) || !func.is_pure() {
+            return None;
+        }
 
         // Get input and output parameters from function components
-        let inputs: Vec<(&String, &crate::ir::ast::Component)> = func
+        let inputs: Vec<(&String, &Component)> = func
             .components
             .iter()
             .filter(|(_, comp)| matches!(comp.causality, Causality::Input(_)))
             .collect();
 
-        let outputs: Vec<(&String, &crate::ir::ast::Component)> = func
+        let outputs: Vec<(&String, &Component)> = func
             .components
             .iter()
             .filter(|(_, comp)| matches!(comp.causality, Causality::Output(_)))
@@ -117,7 +173,7 @@ impl<'a> FunctionInliner<'a> {
             return None;
         }
 
-        // Check argument count matches input count
+        // Calls with all their arguments only, see bind_arguments
         if args.len() != inputs.len() {
             return None;
         }
@@ -128,39 +184,191 @@ impl<'a> FunctionInliner<'a> {
             substitutions.insert((*input_name).clone(), args[i].clone());
         }
 
-        // Build a map of output_name -> expression from algorithm assignments
-        let mut output_exprs: IndexMap<String, Expression> = IndexMap::new();
-        for algo in &func.algorithms {
-            for stmt in algo {
-                if let Statement::Assignment { comp, value } = stmt {
-                    let comp_name = comp.to_string();
-                    // Check if this is an output variable assignment
-                    if outputs.iter().any(|(name, _)| **name == comp_name) {
-                        output_exprs.insert(comp_name, substitute_vars(value, &substitutions));
-                    }
-                }
+        // Bindings of outputs and protected variables, e.g. `protected Real t = x * x;`
+        for (name, comp) in &func.components {
+            if !matches!(comp.causality, Causality::Input(_))
+                && !comp.start_is_modification
+                && comp.start != Expression::Empty
+            {
+                let value = substitute_vars(&comp.start, &substitutions);
+                substitutions.insert(name.clone(), value);
             }
         }
 
-        // Return based on number of outputs
-        if outputs.len() == 1 {
+        // Execute the algorithm symbolically; each assignment updates the map so
+        // later statements see the values of intermediate variables.
+        // Inline=true also turns if-statements into if-expressions.
+        let force = annotations.inline == Some(true);
+        for algo in &func.algorithms {
+            execute_statements(algo, &mut substitutions, force)?;
+        }
+
+        let mut result = if outputs.len() == 1 {
             // Single output - return the expression directly
-            let output_name = outputs[0].0;
-            output_exprs.get(output_name).cloned()
+            substitutions.get(outputs[0].0)?.clone()
         } else {
             // Multi-output - return a Tuple in the same order as outputs are declared
             let mut elements = Vec::new();
             for (output_name, _) in &outputs {
-                if let Some(expr) = output_exprs.get(*output_name) {
-                    elements.push(expr.clone());
-                } else {
-                    // Output not assigned - can't inline this function
+                // Output not assigned - can't inline this function
+                elements.push(substitutions.get(*output_name)?.clone());
+            }
+            Expression::Tuple { elements }
+        };
+
+        // A smooth function doesn't generate events, so neither do the
+        // relations of its inlined body
+        if annotations.smooth_order.is_some() {
+            result = suppress_events(&result);
+        }
+        Some(result)
+    }
+}
+
+/// Symbolically execute algorithm statements, recording the value of each
+/// assigned variable in `values`. With `force`, if-statements are turned into
+/// if-expressions.
+///
+/// Returns `None` if a statement can't be represented as an expression.
+pub(crate) fn execute_statements(
+    stmts: &[Statement],
+    values: &mut IndexMap<String, Expression>,
+    force: bool,
+) -> Option<()> {
+    for stmt in stmts {
+        match stmt {
+            Statement::Assignment { comp, value, .. } => {
+                // Element assignments like y[i] := ... are only supported
+                // with literal subscripts, as in unrolled for-loops
+                let literal_subscripts = comp
+                    .parts
+                    .iter()
+                    .flat_map(|part| part.subs.iter().flatten())
+                    .all(|sub| {
+                        matches!(
+                            sub,
+                            Subscript::Expression(Expression::Terminal {
+                                terminal_type: TerminalType::UnsignedInteger,
+                                ..
+                            })
+                        )
+                    });
+                if !literal_subscripts {
                     return None;
                 }
+                let value = substitute_vars(value, values);
+                values.insert(comp.to_string(), value);
+            }
+            // assert() and other calls have no effect on the outputs
+            Statement::FunctionCall { .. } | Statement::Empty => {}
+            Statement::If {
+                cond_blocks,
+                else_block,
+                ..
+            } if force => {
+                let mut branches = Vec::new();
+                for block in cond_blocks {
+                    let cond = substitute_vars(&block.cond, values);
+                    let mut branch_values = values.clone();
+                    execute_statements(&block.stmts, &mut branch_values, force)?;
+                    branches.push((cond, branch_values));
+                }
+                let mut else_values = values.clone();
+                if let Some(stmts) = else_block {
+                    execute_statements(stmts, &mut else_values, force)?;
+                }
+
+                // Merge every variable assigned in some branch into an if-expression
+                let mut assigned: Vec<String> = Vec::new();
+                for branch_values in branches.iter().map(|(_, v)| v).chain([&else_values]) {
+                    for (name, value) in branch_values {
+                        if values.get(name) != Some(value) && !assigned.contains(name) {
+                            assigned.push(name.clone());
+                        }
+                    }
+                }
+                for name in assigned {
+                    let mut if_branches = Vec::new();
+                    for (cond, branch_values) in &branches {
+                        if_branches.push((cond.clone(), branch_values.get(&name)?.clone()));
+                    }
+                    let else_branch = Box::new(else_values.get(&name)?.clone());
+                    values.insert(
+                        name,
+                        Expression::If {
+                            branches: if_branches,
+                            else_branch,
+                        },
+                    );
+                }
             }
-            Some(Expression::Tuple { elements })
+            _ => return None,
         }
     }
+    Some(())
+}
+
+/// Wrap the relations of an expression in `noEvent()`
+fn suppress_events(expr: &Expression) -> Expression {
+    match expr {
+        Expression::Binary {
+            op:
+                OpBinary::Lt(_)
+                | OpBinary::Le(_)
+                | OpBinary::Gt(_)
+                | OpBinary::Ge(_)
+                | OpBinary::Eq(_)
+                | OpBinary::Neq(_),
+            ..
+        } => Expression::FunctionCall {
+            comp: ComponentReference {
+                local: false,
+                parts: vec![ComponentRefPart {
+                    ident: Token {
+                        text: BUILTIN_NO_EVENT.to_string(),
+                        ..Default::default()
+                    },
+                    subs: None,
+                }],
+            },
+            args: vec![expr.clone()],
+        },
+        Expression::Binary { op, lhs, rhs } => Expression::Binary {
+            op: op.clone(),
+            lhs: Box::new(suppress_events(lhs)),
+            rhs: Box::new(suppress_events(rhs)),
+        },
+        Expression::Unary { op, rhs } => Expression::Unary {
+            op: op.clone(),
+            rhs: Box::new(suppress_events(rhs)),
+        },
+        Expression::FunctionCall { comp, args } if comp.to_string() != BUILTIN_NO_EVENT => {
+            Expression::FunctionCall {
+                comp: comp.clone(),
+                args: args.iter().map(suppress_events).collect(),
+            }
+        }
+        Expression::If {
+            branches,
+            else_branch,
+        } => Expression::If {
+            branches: branches
+                .iter()
+                .map(|(cond, value)| (suppress_events(cond), suppress_events(value)))
+                .collect(),
+            else_branch: Box::new(suppress_events(else_branch)),
+        },
+        Expression::Array { elements } => Expression::Array {
+            elements: elements.iter().map(suppress_events).collect(),
+        },
+        Expression::Tuple { elements } => Expression::Tuple {
+            elements: elements.iter().map(suppress_events).collect(),
+        },
+        Expression::Parenthesized { inner } => Expression::Parenthesized {
+            inner: Box::new(suppress_events(inner)),
+        },
+        _ => expr.clone(),
+    }
 }
 
 /// Substitute variable references in an expression with their replacements
@@ -202,6 +410,24 @@ fn substitute_vars(expr: &Expression, substitutions: &IndexMap<String, Expressio
                 .map(|e| substitute_vars(e, substitutions))
                 .collect(),
         },
+        Expression::If {
+            branches,
+            else_branch,
+        } => Expression::If {
+            branches: branches
+                .iter()
+                .map(|(cond, value)| {
+                    (
+                        substitute_vars(cond, substitutions),
+                        substitute_vars(value, substitutions),
+                    )
+                })
+                .collect(),
+            else_branch: Box::new(substitute_vars(else_branch, substitutions)),
+        },
+        Expression::Parenthesized { inner } => Expression::Parenthesized {
+            inner: Box::new(substitute_vars(inner, substitutions)),
+        },
         // Terminal expressions and other types don't need substitution
         _ => expr.clone(),
     }
@@ -211,9 +437,126 @@ impl<'a> MutVisitor for FunctionInliner<'a> {
     fn exit_expression(&mut self, expr: &mut Expression) {
         if let Expression::FunctionCall { comp, args } = expr {
             let func_name = comp.to_string();
+            // Give the call all of its arguments, defaults included, whether
+            // it's inlined or kept (invalid calls are reported by
+            // check_function_calls)
+            if let Some(func) = self.user_function(&func_name)
+                && let Ok(bound) = bind_arguments(func, args)
+            {
+                *args = bound;
+            }
             if let Some(inlined) = self.inline_call(&func_name, args) {
                 *expr = inlined;
             }
         }
     }
 }
+
+/// Match the arguments of a call to the inputs of a function: positional
+/// arguments in declaration order, named arguments `name = value` by name,
+/// and the defaults of the inputs without an argument
+///
+/// Defaults may refer to other inputs, as in `input Real b = 2 * a;`, which
+/// are replaced by their arguments. Returns the arguments of all inputs in
+/// declaration order, or why the call is invalid.
+pub fn bind_arguments(
+    func: &ClassDefinition,
+    args: &[Expression],
+) -> Result<Vec<Expression>, String> {
+    let inputs: Vec<(&String, &Component)> = func
+        .components
+        .iter()
+        .filter(|(_, comp)| matches!(comp.causality, Causality::Input(_)))
+        .collect();
+
+    let mut bound: IndexMap<String, Expression> = IndexMap::new();
+    let mut positional = 0;
+    for arg in args {
+        let (name, value) = match named_argument(arg) {
+            Some((name, value)) => {
+                if !inputs.iter().any(|(input, _)| *input == name) {
+                    return Err(format!("no input named '{}'", name));
+                }
+                (name.to_string(), value)
+            }
+            None => {
+                let Some((input, _)) = inputs.get(positional) else {
+                    return Err(format!(
+                        "too many arguments ({} for {} inputs)",
+                        positional + 1,
+                        inputs.len()
+                    ));
+                };
+                positional += 1;
+                ((*input).clone(), arg)
+            }
+        };
+        if bound.insert(name.clone(), value.clone()).is_some() {
+            return Err(format!("argument '{}' given twice", name));
+        }
+    }
+
+    for (name, comp) in &inputs {
+        if bound.contains_key(*name) {
+            continue;
+        }
+        let Some(default) = comp.binding() else {
+            return Err(format!("missing argument '{}', which has no default", name));
+        };
+        let value = substitute_vars(default, &bound);
+        bound.insert((*name).clone(), value);
+    }
+    Ok(inputs
+        .iter()
+        .filter_map(|(name, _)| bound.shift_remove(*name))
+        .collect())
+}
+
+/// The name and value of a named argument `name = value`
+fn named_argument(arg: &Expression) -> Option<(&str, &Expression)> {
+    match arg {
+        Expression::NamedArgument { name, value } => Some((name.text.as_str(), value)),
+        _ => None,
+    }
+}
+
+/// Check that the calls of the given functions in a class match their
+/// inputs, see [`bind_arguments`]
+pub fn check_function_calls(
+    class: &ClassDefinition,
+    functions: &IndexMap<String, &ClassDefinition>,
+) -> Vec<String> {
+    struct CallChecker<'a, 'b> {
+        functions: &'b IndexMap<String, &'a ClassDefinition>,
+        errors: Vec<String>,
+    }
+    impl Visitor for CallChecker<'_, '_> {
+        fn enter_expression(&mut self, node: &Expression) {
+            let Expression::FunctionCall { comp, args } = node else {
+                return;
+            };
+            let name = comp.to_string();
+            let simple_name = name.rsplit('.').next().unwrap_or(&name);
+            if is_builtin_function(simple_name) {
+                return;
+            }
+            let Some(func) = self.functions.get(&name) else {
+                return;
+            };
+            if let Err(error) = bind_arguments(func, args) {
+                let location = comp
+                    .get_location()
+                    .map(|loc| format!("{}: ", loc.file_position()))
+                    .unwrap_or_default();
+                self.errors
+                    .push(format!("{}call of '{}': {}", location, name, error));
+            }
+        }
+    }
+    let mut checker = CallChecker {
+        functions,
+        errors: Vec::new(),
+    };
+    class.accept(&mut checker);
+    checker.errors
+}
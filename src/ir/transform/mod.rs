@@ -4,15 +4,20 @@
 //! including flattening, import resolution, and function inlining.
 
 pub mod array_comprehension;
+pub mod component_arrays;
 pub mod constant_substitutor;
 pub mod constants;
 pub mod enum_substitutor;
 pub mod equation_expander;
 pub mod flatten;
+pub mod fresh_names;
 pub mod function_inliner;
 pub mod import_resolver;
 pub mod multi_file;
 pub mod operator_expand;
+pub mod random_streams;
+pub mod rewrite;
 pub mod scope_resolver;
 pub mod sub_comp_namer;
+pub mod table_lookup;
 pub mod tuple_expander;
@@ -364,7 +364,7 @@ pub fn package_path_from_file(
 ///
 /// A vector of entity names in the order they should appear
 pub fn parse_package_order(path: &Path) -> Result<Vec<String>> {
-    let content = std::fs::read_to_string(path)
+    let content = crate::compiler::read_source(path)
         .with_context(|| format!("Failed to read package.order: {}", path.display()))?;
 
     let mut order = Vec::new();
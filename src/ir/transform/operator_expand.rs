@@ -102,6 +102,7 @@ fn expand_single_equation(
     let Equation::Simple {
         lhs: Expression::ComponentReference(lhs_ref),
         rhs,
+        description,
     } = eq
     else {
         return None;
@@ -112,8 +113,19 @@ fn expand_single_equation(
     let record_info = operator_records.get(lhs_type)?;
 
     if record_info.is_complex {
-        // This is a Complex assignment, try to expand the RHS
-        expand_complex_assignment(&lhs_name, rhs, type_map, operator_records)
+        // This is a Complex assignment, try to expand the RHS. The field
+        // equations share the description of the equation.
+        let mut expanded = expand_complex_assignment(&lhs_name, rhs, type_map, operator_records)?;
+        for eq in &mut expanded {
+            if let Equation::Simple {
+                description: field_description,
+                ..
+            } = eq
+            {
+                field_description.clone_from(description);
+            }
+        }
+        Some(expanded)
     } else {
         None
     }
@@ -164,10 +176,12 @@ fn expand_complex_assignment(
                     Equation::Simple {
                         lhs: make_field_ref(lhs_name, "re"),
                         rhs: re_expr,
+                        description: vec![],
                     },
                     Equation::Simple {
                         lhs: make_field_ref(lhs_name, "im"),
                         rhs: im_expr,
+                        description: vec![],
                     },
                 ]);
             }
@@ -271,6 +285,7 @@ fn make_field_eq(lhs_name: &str, lhs_field: &str, rhs_name: &str, rhs_field: &st
     Equation::Simple {
         lhs: make_field_ref(lhs_name, lhs_field),
         rhs: make_field_ref(rhs_name, rhs_field),
+        description: vec![],
     }
 }
 
@@ -291,6 +306,7 @@ fn make_binary_field_eq(
             lhs: Box::new(make_field_ref(lhs_name, lhs_field)),
             rhs: Box::new(make_field_ref(rhs_name, rhs_field)),
         },
+        description: vec![],
     }
 }
 
@@ -327,6 +343,7 @@ fn make_complex_mul_re_eq(result: &str, a: &str, b: &str) -> Equation {
             lhs: Box::new(term1),
             rhs: Box::new(term2),
         },
+        description: vec![],
     }
 }
 
@@ -352,6 +369,7 @@ fn make_complex_mul_im_eq(result: &str, a: &str, b: &str) -> Equation {
             lhs: Box::new(term1),
             rhs: Box::new(term2),
         },
+        description: vec![],
     }
 }
 
@@ -392,6 +410,7 @@ fn make_complex_div_re_eq(result: &str, a: &str, b: &str) -> Equation {
             lhs: Box::new(num),
             rhs: Box::new(denom),
         },
+        description: vec![],
     }
 }
 
@@ -432,6 +451,7 @@ fn make_complex_div_im_eq(result: &str, a: &str, b: &str) -> Equation {
             lhs: Box::new(num),
             rhs: Box::new(denom),
         },
+        description: vec![],
     }
 }
 
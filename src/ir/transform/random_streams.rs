@@ -0,0 +1,192 @@
+//! Random number streams for stochastic models.
+//!
+//! `random(seed)` returns a uniformly distributed number in [0, 1) that is
+//! drawn anew each time its when-clause fires, e.g. to model sensor noise:
+//!
+//! ```modelica
+//! when sample(0, 0.1) then
+//!   noise = 0.01 * (random(seed) - 0.5);
+//! end when;
+//! ```
+//!
+//! Each call draws from its own stream. This pass numbers the calls of a
+//! flattened class, rewriting `random(seed)` to `random(seed, stream)`, so a
+//! backend can give every stream its own [`RandomStream`] and produce the same
+//! numbers for a given seed on every run.
+
+use anyhow::Result;
+
+use crate::ir::ast::{ClassDefinition, Equation, Expression, Statement, TerminalType, Token};
+use crate::ir::transform::constants::BUILTIN_RANDOM;
+use crate::ir::visitor::{MutVisitable, MutVisitor};
+
+/// Number the `random(seed)` calls of a class, returning the number of streams.
+///
+/// # Errors
+///
+/// Returns an error if `random` is called outside of a when-clause, where its
+/// value would change at every solver step, or without exactly one seed argument.
+pub fn number_random_streams(class: &mut ClassDefinition) -> Result<usize> {
+    let mut numberer = StreamNumberer::default();
+    class.accept_mut(&mut numberer);
+    match numberer.error {
+        Some(error) => anyhow::bail!(error),
+        None => Ok(numberer.streams),
+    }
+}
+
+#[derive(Default)]
+struct StreamNumberer {
+    /// Depth of the when-equations and when-statements being visited
+    when_depth: usize,
+    streams: usize,
+    error: Option<String>,
+}
+
+impl MutVisitor for StreamNumberer {
+    fn enter_equation(&mut self, node: &mut Equation) {
+        if matches!(node, Equation::When { .. }) {
+            self.when_depth += 1;
+        }
+    }
+
+    fn exit_equation(&mut self, node: &mut Equation) {
+        if matches!(node, Equation::When { .. }) {
+            self.when_depth -= 1;
+        }
+    }
+
+    fn enter_statement(&mut self, node: &mut Statement) {
+        if matches!(node, Statement::When(_)) {
+            self.when_depth += 1;
+        }
+    }
+
+    fn exit_statement(&mut self, node: &mut Statement) {
+        if matches!(node, Statement::When(_)) {
+            self.when_depth -= 1;
+        }
+    }
+
+    fn enter_expression(&mut self, node: &mut Expression) {
+        let Expression::FunctionCall { comp, args } = node else {
+            return;
+        };
+        if self.error.is_some() || comp.to_string() != BUILTIN_RANDOM {
+            return;
+        }
+        let loc = comp
+            .get_location()
+            .map(|l| format!(" at {}:{}:{}", l.file_name, l.start_line, l.start_column))
+            .unwrap_or_default();
+        if self.when_depth == 0 {
+            self.error = Some(format!(
+                "random() is only allowed inside when-clauses{}, \
+                 e.g. `when sample(0, dt) then r = random(seed); end when;`",
+                loc
+            ));
+        } else if args.len() != 1 {
+            self.error = Some(format!(
+                "random() expects one seed argument, got {}{}",
+                args.len(),
+                loc
+            ));
+        } else {
+            self.streams += 1;
+            args.push(Expression::Terminal {
+                terminal_type: TerminalType::UnsignedInteger,
+                token: Token {
+                    text: self.streams.to_string(),
+                    ..Default::default()
+                },
+            });
+        }
+    }
+}
+
+/// Reference generator for `random(seed, stream)`.
+///
+/// A xorshift64* generator whose state is initialized by splitmix64 from the
+/// seed and stream number. Backends that implement the same algorithm produce
+/// the same numbers as this one.
+#[derive(Debug, Clone)]
+pub struct RandomStream {
+    state: u64,
+}
+
+impl RandomStream {
+    pub fn new(seed: i64, stream: u64) -> Self {
+        let mut z = (seed as u64) ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // xorshift requires a nonzero state
+        Self {
+            state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+        }
+    }
+
+    /// The next number of the stream, uniformly distributed in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        // The 53 high bits give a uniform double in [0, 1)
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source;
+
+    fn class(source: &str) -> ClassDefinition {
+        let def = parse_source(source, "test.mo").unwrap();
+        def.class_list.into_values().next().unwrap()
+    }
+
+    #[test]
+    fn test_streams_are_numbered() {
+        let mut m = class(
+            "model M\n  parameter Integer seed = 1;\n  discrete Real a, b;\nequation\n  \
+             when sample(0, 0.1) then\n    a = random(seed);\n    b = random(seed);\n  \
+             end when;\nend M;",
+        );
+        assert_eq!(number_random_streams(&mut m).unwrap(), 2);
+        let Equation::When { blocks, .. } = &m.equations[0] else {
+            panic!("expected a when-equation");
+        };
+        let Equation::Simple { rhs, .. } = &blocks[0].eqs[1] else {
+            panic!("expected a simple equation");
+        };
+        assert_eq!(rhs.to_string(), "random(seed, 2)");
+    }
+
+    #[test]
+    fn test_random_outside_when() {
+        let mut m = class("model M\n  Real x;\nequation\n  x = random(1);\nend M;");
+        let error = number_random_streams(&mut m).unwrap_err().to_string();
+        assert!(
+            error.contains("only allowed inside when-clauses"),
+            "{error}"
+        );
+        assert!(error.contains("test.mo:4:7"), "{error}");
+    }
+
+    #[test]
+    fn test_random_stream_is_reproducible() {
+        let draw = |seed, stream| {
+            let mut s = RandomStream::new(seed, stream);
+            (0..100).map(|_| s.next_f64()).collect::<Vec<_>>()
+        };
+        let a = draw(42, 1);
+        assert_eq!(a, draw(42, 1));
+        assert_ne!(a, draw(42, 2));
+        assert_ne!(a, draw(43, 1));
+        assert!(a.iter().all(|&r| (0.0..1.0).contains(&r)));
+        let mean = a.iter().sum::<f64>() / a.len() as f64;
+        assert!((mean - 0.5).abs() < 0.1);
+    }
+}
@@ -0,0 +1,298 @@
+//! Pattern-based expression rewriting.
+//!
+//! A [`Rule`] pairs a [`Pattern`] with an optional guard and a builder. The
+//! pattern matches the shape of an expression and binds its parts to names,
+//! the guard checks the bindings, and the builder creates the replacement:
+//!
+//! ```
+//! use rumoca::ir::ast::{OpUnary, Token};
+//! use rumoca::ir::transform::rewrite::{Pattern, Rule};
+//!
+//! let minus = || OpUnary::Minus(Token::default());
+//! // -(-x) => x
+//! let rule = Rule::new(
+//!     "double negation",
+//!     Pattern::unary(minus(), Pattern::unary(minus(), Pattern::bind("x"))),
+//!     |b| b["x"].clone(),
+//! );
+//! ```
+//!
+//! [`rewrite`] applies a set of rules bottom-up, so the operands of an
+//! expression are simplified before the expression itself, and repeats until
+//! no rule matches anymore. [`apply_first`] only rewrites the root.
+//!
+//! Operators are matched by kind, ignoring their tokens, and parentheses are
+//! matched literally.
+
+use std::collections::HashMap;
+use std::mem::discriminant;
+
+use crate::ir::ast::{Expression, OpBinary, OpUnary};
+use crate::ir::visitor::{MutVisitable, MutVisitor};
+
+/// Upper bound on the bottom-up passes of [`rewrite`], which guards against
+/// rule sets that don't terminate
+const MAX_PASSES: usize = 64;
+
+/// Expressions bound to the names of a pattern
+pub type Bindings = HashMap<&'static str, Expression>;
+
+/// Shape of an expression to match
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Any expression, bound to a name. A name used twice in a pattern only
+    /// matches equal expressions.
+    Bind(&'static str),
+    /// An expression matching the inner pattern, also bound to a name as a whole
+    Named(&'static str, Box<Pattern>),
+    /// A unary operation of the given kind
+    Unary(OpUnary, Box<Pattern>),
+    /// A binary operation of the given kind
+    Binary(OpBinary, Box<Pattern>, Box<Pattern>),
+    /// A call of the named function with exactly these arguments
+    Call(&'static str, Vec<Pattern>),
+    /// A tuple with exactly these elements
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn bind(name: &'static str) -> Self {
+        Pattern::Bind(name)
+    }
+
+    pub fn named(name: &'static str, inner: Pattern) -> Self {
+        Pattern::Named(name, Box::new(inner))
+    }
+
+    pub fn unary(op: OpUnary, rhs: Pattern) -> Self {
+        Pattern::Unary(op, Box::new(rhs))
+    }
+
+    pub fn binary(op: OpBinary, lhs: Pattern, rhs: Pattern) -> Self {
+        Pattern::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn call(name: &'static str, args: Vec<Pattern>) -> Self {
+        Pattern::Call(name, args)
+    }
+
+    pub fn tuple(elements: Vec<Pattern>) -> Self {
+        Pattern::Tuple(elements)
+    }
+
+    /// Match an expression, returning the bindings if it has this shape
+    pub fn matches(&self, expr: &Expression) -> Option<Bindings> {
+        let mut bindings = Bindings::new();
+        self.match_into(expr, &mut bindings).then_some(bindings)
+    }
+
+    fn match_into(&self, expr: &Expression, bindings: &mut Bindings) -> bool {
+        match (self, expr) {
+            (Pattern::Bind(name), _) => bind(name, expr, bindings),
+            (Pattern::Named(name, inner), _) => {
+                inner.match_into(expr, bindings) && bind(name, expr, bindings)
+            }
+            (Pattern::Unary(op, rhs_pat), Expression::Unary { op: expr_op, rhs }) => {
+                discriminant(op) == discriminant(expr_op) && rhs_pat.match_into(rhs, bindings)
+            }
+            (
+                Pattern::Binary(op, lhs_pat, rhs_pat),
+                Expression::Binary {
+                    op: expr_op,
+                    lhs,
+                    rhs,
+                },
+            ) => {
+                discriminant(op) == discriminant(expr_op)
+                    && lhs_pat.match_into(lhs, bindings)
+                    && rhs_pat.match_into(rhs, bindings)
+            }
+            (Pattern::Call(name, arg_pats), Expression::FunctionCall { comp, args }) => {
+                comp.to_string() == *name && match_all(arg_pats, args, bindings)
+            }
+            (Pattern::Tuple(element_pats), Expression::Tuple { elements }) => {
+                match_all(element_pats, elements, bindings)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Bind a name, or check that an already bound name has the same expression
+fn bind(name: &'static str, expr: &Expression, bindings: &mut Bindings) -> bool {
+    match bindings.get(name) {
+        Some(bound) => bound == expr,
+        None => {
+            bindings.insert(name, expr.clone());
+            true
+        }
+    }
+}
+
+fn match_all(patterns: &[Pattern], exprs: &[Expression], bindings: &mut Bindings) -> bool {
+    patterns.len() == exprs.len()
+        && patterns
+            .iter()
+            .zip(exprs)
+            .all(|(pattern, expr)| pattern.match_into(expr, bindings))
+}
+
+/// A rewrite rule: expressions matching the pattern (and the guard, if any)
+/// are replaced with the expression built from the bindings
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Short description, e.g. `-(-x) => x`
+    pub name: &'static str,
+    pattern: Pattern,
+    guard: Option<fn(&Bindings) -> bool>,
+    build: fn(&Bindings) -> Expression,
+}
+
+impl Rule {
+    pub fn new(name: &'static str, pattern: Pattern, build: fn(&Bindings) -> Expression) -> Self {
+        Self {
+            name,
+            pattern,
+            guard: None,
+            build,
+        }
+    }
+
+    /// Only apply the rule if the guard accepts the bindings
+    pub fn when(mut self, guard: fn(&Bindings) -> bool) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Rewrite an expression, or return None if the rule doesn't match it
+    pub fn apply(&self, expr: &Expression) -> Option<Expression> {
+        let bindings = self.pattern.matches(expr)?;
+        if let Some(guard) = self.guard
+            && !guard(&bindings)
+        {
+            return None;
+        }
+        Some((self.build)(&bindings))
+    }
+}
+
+/// Rewrite the root of an expression with the first matching rule
+pub fn apply_first(expr: &Expression, rules: &[Rule]) -> Option<Expression> {
+    rules.iter().find_map(|rule| rule.apply(expr))
+}
+
+/// Rewrite an expression bottom-up until no rule matches any subexpression
+pub fn rewrite(expr: &Expression, rules: &[Rule]) -> Expression {
+    let mut result = expr.clone();
+    let mut rewriter = Rewriter {
+        rules,
+        changed: false,
+    };
+    for _ in 0..MAX_PASSES {
+        rewriter.changed = false;
+        result.accept_mut(&mut rewriter);
+        if !rewriter.changed {
+            break;
+        }
+    }
+    result
+}
+
+/// Applies rules to each expression after its operands
+struct Rewriter<'a> {
+    rules: &'a [Rule],
+    changed: bool,
+}
+
+impl MutVisitor for Rewriter<'_> {
+    fn exit_expression(&mut self, node: &mut Expression) {
+        if let Some(rewritten) = apply_first(node, self.rules) {
+            *node = rewritten;
+            self.changed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::ast::{ComponentRefPart, ComponentReference, Token};
+
+    fn var(name: &str) -> Expression {
+        Expression::ComponentReference(ComponentReference {
+            local: false,
+            parts: vec![ComponentRefPart {
+                ident: Token {
+                    text: name.to_string(),
+                    ..Default::default()
+                },
+                subs: None,
+            }],
+        })
+    }
+
+    fn neg(expr: Expression) -> Expression {
+        Expression::Unary {
+            op: OpUnary::Minus(Token::default()),
+            rhs: Box::new(expr),
+        }
+    }
+
+    fn sub(lhs: Expression, rhs: Expression) -> Expression {
+        Expression::Binary {
+            op: OpBinary::Sub(Token::default()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+
+    fn double_negation() -> Rule {
+        let minus = || OpUnary::Minus(Token::default());
+        Rule::new(
+            "-(-x) => x",
+            Pattern::unary(minus(), Pattern::unary(minus(), Pattern::bind("x"))),
+            |b| b["x"].clone(),
+        )
+    }
+
+    #[test]
+    fn test_rewrite_bottom_up_to_fixpoint() {
+        // a - -(-(-(-b))) => a - b
+        let expr = sub(var("a"), neg(neg(neg(neg(var("b"))))));
+        assert_eq!(
+            rewrite(&expr, &[double_negation()]),
+            sub(var("a"), var("b"))
+        );
+        // Only the root is rewritten by apply_first
+        assert_eq!(apply_first(&expr, &[double_negation()]), None);
+    }
+
+    #[test]
+    fn test_repeated_bindings_and_guards() {
+        // x - x => 0, for plain variables only
+        let rule = Rule::new(
+            "x - x => 0",
+            Pattern::binary(
+                OpBinary::Sub(Token::default()),
+                Pattern::bind("x"),
+                Pattern::bind("x"),
+            ),
+            |_| Expression::Terminal {
+                terminal_type: crate::ir::ast::TerminalType::UnsignedInteger,
+                token: Token {
+                    text: "0".to_string(),
+                    ..Default::default()
+                },
+            },
+        )
+        .when(|b| matches!(b["x"], Expression::ComponentReference(_)));
+
+        assert_eq!(
+            rule.apply(&sub(var("a"), var("a"))).unwrap().to_string(),
+            "0"
+        );
+        assert_eq!(rule.apply(&sub(var("a"), var("b"))), None);
+        let negated = sub(neg(var("a")), neg(var("a")));
+        assert_eq!(rule.apply(&negated), None);
+    }
+}
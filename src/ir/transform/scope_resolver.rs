@@ -350,11 +350,7 @@ impl<'a, L: SymbolLookup + ?Sized> ScopeResolver<'a, L> {
         {
             let mut current = outer;
             for part in rest_parts {
-                if let Some(nested) = current.classes.get(*part) {
-                    current = nested;
-                } else {
-                    return None;
-                }
+                current = current.classes.get(*part)?;
             }
             return Some(ResolvedSymbol::Class(current));
         }
@@ -418,11 +414,7 @@ impl<'a, L: SymbolLookup + ?Sized> ScopeResolver<'a, L> {
             let first = parts[0];
             if let Some(mut current) = self.ast.class_list.get(first) {
                 for part in &parts[1..] {
-                    if let Some(nested) = current.classes.get(*part) {
-                        current = nested;
-                    } else {
-                        return None;
-                    }
+                    current = current.classes.get(*part)?;
                 }
                 return Some(current);
             }
@@ -0,0 +1,459 @@
+//! Lowering of lookup tables.
+//!
+//! `table1D(table, u)` and `table2D(table, u1, u2)` interpolate in tables laid
+//! out like the Modelica Standard Library's CombiTable1D and CombiTable2D:
+//!
+//! ```modelica
+//! parameter Real efficiency[4, 2] = [0, 0.2; 100, 0.6; 200, 0.8; 400, 0.85];
+//! equation
+//!   eta = table1D(efficiency, speed);
+//!   cd = table2D("drag.csv", mach, alpha, Smoothness.ConstantSegments);
+//! ```
+//!
+//! A table that is a matrix literal, or a parameter or constant whose value is
+//! known at compile time, is lowered into a piecewise expression in the DAE:
+//! linear segments (extrapolated beyond the ends of the table) or constant
+//! segments (held beyond the ends). Any other table, such as the path of a CSV
+//! file, is left as a call that the backend implements.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::ir::analysis::division_check::{evaluate, initial_values};
+use crate::ir::ast::{
+    ClassDefinition, ComponentRefPart, ComponentReference, Expression, OpBinary, OpUnary,
+    TerminalType, Token, Variability,
+};
+use crate::ir::literal::format_real;
+use crate::ir::transform::constants::{
+    BUILTIN_NO_EVENT, BUILTIN_TABLE_1D, BUILTIN_TABLE_2D, smoothness,
+};
+use crate::ir::visitor::{MutVisitable, MutVisitor};
+
+/// Lower the table lookups of a class, returning the number of lowered calls.
+///
+/// # Errors
+///
+/// Returns an error for a call with the wrong number of arguments, or for a
+/// known table that is not rectangular, too small, has axes that are not
+/// strictly increasing, or is used with an unsupported smoothness.
+pub fn lower_table_lookups(class: &mut ClassDefinition) -> Result<usize> {
+    let tables = class
+        .components
+        .iter()
+        .filter(|(_, comp)| {
+            matches!(
+                comp.variability,
+                Variability::Parameter(_) | Variability::Constant(_)
+            ) && matches!(comp.start, Expression::Array { .. })
+        })
+        .map(|(name, comp)| (name.clone(), comp.start.clone()))
+        .collect();
+    let mut lowerer = TableLowerer {
+        values: initial_values(class),
+        tables,
+        lowered: 0,
+        error: None,
+    };
+    class.accept_mut(&mut lowerer);
+    match lowerer.error {
+        Some(error) => anyhow::bail!(error),
+        None => Ok(lowerer.lowered),
+    }
+}
+
+struct TableLowerer {
+    /// Values of the scalar parameters and constants
+    values: HashMap<String, f64>,
+    /// Array-valued parameters and constants that may hold tables
+    tables: HashMap<String, Expression>,
+    lowered: usize,
+    error: Option<String>,
+}
+
+impl MutVisitor for TableLowerer {
+    // Lower on exit, so that lookups nested in the arguments are lowered first
+    fn exit_expression(&mut self, node: &mut Expression) {
+        let Expression::FunctionCall { comp, args } = node else {
+            return;
+        };
+        let name = comp.to_string();
+        let axes = match name.as_str() {
+            BUILTIN_TABLE_1D => 1,
+            BUILTIN_TABLE_2D => 2,
+            _ => return,
+        };
+        if self.error.is_some() {
+            return;
+        }
+        match self.lower(&name, args, axes) {
+            Ok(Some(expr)) => {
+                *node = expr;
+                self.lowered += 1;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                let loc = comp
+                    .get_location()
+                    .map(|l| format!(" at {}:{}:{}", l.file_name, l.start_line, l.start_column))
+                    .unwrap_or_default();
+                self.error = Some(format!("{}{}", error, loc));
+            }
+        }
+    }
+}
+
+impl TableLowerer {
+    fn lower(
+        &self,
+        name: &str,
+        args: &[Expression],
+        axes: usize,
+    ) -> Result<Option<Expression>, String> {
+        if args.len() != axes + 1 && args.len() != axes + 2 {
+            return Err(format!(
+                "{}() expects {} or {} arguments, got {}",
+                name,
+                axes + 1,
+                axes + 2,
+                args.len()
+            ));
+        }
+        let Some(table) = self.matrix(&args[0]) else {
+            return Ok(None);
+        };
+        let constant = match args.get(axes + 1) {
+            None => false,
+            Some(arg) => match evaluate(arg, &self.values).map(|v| v as i64) {
+                Some(smoothness::LINEAR_SEGMENTS) => false,
+                Some(smoothness::CONSTANT_SEGMENTS) => true,
+                _ => {
+                    return Err(format!(
+                        "{}() supports Smoothness.LinearSegments and \
+                         Smoothness.ConstantSegments, got {}",
+                        name, arg
+                    ));
+                }
+            },
+        };
+        let columns = table.first().map_or(0, Vec::len);
+        if table.iter().any(|row| row.len() != columns) {
+            return Err(format!("{}() table rows must have the same length", name));
+        }
+
+        if axes == 1 {
+            if table.is_empty() || columns < 2 {
+                return Err(format!(
+                    "{}() table needs at least one row and two columns",
+                    name
+                ));
+            }
+            let xs: Vec<f64> = table.iter().map(|row| row[0]).collect();
+            check_increasing(name, &xs)?;
+            let ys = table.iter().map(|row| number(row[1])).collect();
+            return Ok(Some(interpolate(&xs, ys, &args[1], constant)));
+        }
+
+        if table.len() < 2 || columns < 2 {
+            return Err(format!(
+                "{}() table needs at least two rows and two columns",
+                name
+            ));
+        }
+        let u1s: Vec<f64> = table[1..].iter().map(|row| row[0]).collect();
+        let u2s = &table[0][1..];
+        check_increasing(name, &u1s)?;
+        check_increasing(name, u2s)?;
+        // Bilinear interpolation is linear interpolation along u2 within each
+        // row, followed by linear interpolation of the rows along u1
+        let rows = table[1..]
+            .iter()
+            .map(|row| {
+                let ys = row[1..].iter().copied().map(number).collect();
+                interpolate(u2s, ys, &args[2], constant)
+            })
+            .collect();
+        Ok(Some(interpolate(&u1s, rows, &args[1], constant)))
+    }
+
+    /// The numeric value of a table argument, if known at compile time
+    fn matrix(&self, arg: &Expression) -> Option<Vec<Vec<f64>>> {
+        let rows = match arg {
+            Expression::Array { elements } => elements,
+            Expression::ComponentReference(cref) => match self.tables.get(&cref.to_string()) {
+                Some(Expression::Array { elements }) => elements,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        rows.iter()
+            .map(|row| match row {
+                Expression::Array { elements } => {
+                    elements.iter().map(|e| evaluate(e, &self.values)).collect()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn check_increasing(name: &str, axis: &[f64]) -> Result<(), String> {
+    match axis.windows(2).find(|w| w[0] >= w[1]) {
+        Some(w) => Err(format!(
+            "{}() table axis must be strictly increasing, got {} before {}",
+            name, w[0], w[1]
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Piecewise interpolation of the points (xs[i], ys[i]) at u
+fn interpolate(xs: &[f64], mut ys: Vec<Expression>, u: &Expression, constant: bool) -> Expression {
+    if xs.len() == 1 {
+        return ys.remove(0);
+    }
+    let u = operand(u.clone());
+    // Constant segments hold a value from each point to the next one; linear
+    // segments join the points, so there is one segment less
+    let segments = if constant { xs.len() } else { xs.len() - 1 };
+    let segment = |k: usize| {
+        if constant {
+            ys[k].clone()
+        } else {
+            line(xs[k], xs[k + 1], &ys[k], &ys[k + 1], &u)
+        }
+    };
+    let branches = (0..segments - 1)
+        .map(|k| {
+            let cond = binary(OpBinary::Lt(Token::default()), u.clone(), number(xs[k + 1]));
+            // Linear segments are continuous, so crossing a breakpoint needs no event
+            let cond = if constant {
+                cond
+            } else {
+                Expression::FunctionCall {
+                    comp: name_ref(BUILTIN_NO_EVENT),
+                    args: vec![cond],
+                }
+            };
+            (cond, segment(k))
+        })
+        .collect::<Vec<_>>();
+    let else_branch = segment(segments - 1);
+    if branches.is_empty() {
+        return else_branch;
+    }
+    Expression::If {
+        branches,
+        else_branch: Box::new(else_branch),
+    }
+}
+
+/// The line through (x0, y0) and (x1, y1) at u
+fn line(x0: f64, x1: f64, y0: &Expression, y1: &Expression, u: &Expression) -> Expression {
+    let empty = HashMap::new();
+    let offset = match x0 {
+        0.0 => u.clone(),
+        x if x < 0.0 => paren(binary(
+            OpBinary::Add(Token::default()),
+            u.clone(),
+            number(-x),
+        )),
+        x => paren(binary(
+            OpBinary::Sub(Token::default()),
+            u.clone(),
+            number(x),
+        )),
+    };
+    let rise = match (evaluate(y0, &empty), evaluate(y1, &empty)) {
+        (Some(a), Some(b)) => {
+            let slope = (b - a) / (x1 - x0);
+            if slope == 0.0 {
+                return y0.clone();
+            }
+            binary(OpBinary::Mul(Token::default()), number(slope), offset)
+        }
+        _ => binary(
+            OpBinary::Div(Token::default()),
+            binary(
+                OpBinary::Mul(Token::default()),
+                paren(binary(
+                    OpBinary::Sub(Token::default()),
+                    operand(y1.clone()),
+                    operand(y0.clone()),
+                )),
+                offset,
+            ),
+            number(x1 - x0),
+        ),
+    };
+    paren(binary(
+        OpBinary::Add(Token::default()),
+        operand(y0.clone()),
+        rise,
+    ))
+}
+
+fn number(value: f64) -> Expression {
+    let literal = |v: f64| Expression::Terminal {
+        terminal_type: TerminalType::UnsignedReal,
+        token: Token {
+            text: format_real(v),
+            ..Default::default()
+        },
+    };
+    if value < 0.0 {
+        paren(Expression::Unary {
+            op: OpUnary::Minus(Token::default()),
+            rhs: Box::new(literal(-value)),
+        })
+    } else {
+        literal(value)
+    }
+}
+
+fn binary(op: OpBinary, lhs: Expression, rhs: Expression) -> Expression {
+    Expression::Binary {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+fn paren(inner: Expression) -> Expression {
+    Expression::Parenthesized {
+        inner: Box::new(inner),
+    }
+}
+
+/// Parenthesize an expression used as an operand, unless it is atomic
+fn operand(expr: Expression) -> Expression {
+    match expr {
+        Expression::Binary { .. } | Expression::Unary { .. } | Expression::If { .. } => paren(expr),
+        _ => expr,
+    }
+}
+
+fn name_ref(name: &str) -> ComponentReference {
+    ComponentReference {
+        local: false,
+        parts: vec![ComponentRefPart {
+            ident: Token {
+                text: name.to_string(),
+                ..Default::default()
+            },
+            subs: None,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source;
+
+    fn class(source: &str) -> ClassDefinition {
+        let def = parse_source(source, "test.mo").unwrap();
+        def.class_list.into_values().next().unwrap()
+    }
+
+    fn lowered_rhs(source: &str) -> String {
+        let mut m = class(source);
+        assert_eq!(lower_table_lookups(&mut m).unwrap(), 1);
+        let crate::ir::ast::Equation::Simple { rhs, .. } = &m.equations[0] else {
+            panic!("expected a simple equation");
+        };
+        rhs.to_string()
+    }
+
+    #[test]
+    fn test_table1d_linear_segments() {
+        let rhs = lowered_rhs(
+            "model M\n  parameter Real tab[3, 2] = [0, 1; 1, 3; 2, 2];\n  Real y;\n\
+             equation\n  y = table1D(tab, time);\nend M;",
+        );
+        assert_eq!(
+            rhs,
+            "if noEvent(time < 1.0) then (1.0 + 2.0 * time) else (3.0 + (-1.0) * (time - 1.0))"
+        );
+    }
+
+    #[test]
+    fn test_table1d_constant_segments() {
+        let rhs = lowered_rhs(
+            "model M\n  Real y;\nequation\n  \
+             y = table1D({{0, 5}, {1, 6}, {2, 7}}, time, 3);\nend M;",
+        );
+        assert_eq!(
+            rhs,
+            "if time < 1.0 then 5.0 elseif time < 2.0 then 6.0 else 7.0"
+        );
+    }
+
+    #[test]
+    fn test_table2d_is_bilinear() {
+        let mut m = class(
+            "model M\n  parameter Real tab[3, 3] = [0, 0, 1; 0, 0, 1; 1, 1, 3];\n  Real y;\n\
+             equation\n  y = table2D(tab, 0.5, 0.5);\nend M;",
+        );
+        lower_table_lookups(&mut m).unwrap();
+        let crate::ir::ast::Equation::Simple { rhs, .. } = &m.equations[0] else {
+            panic!("expected a simple equation");
+        };
+        // table(u1, u2) = u1 + u2 + u1 * u2 over the unit square
+        let value = eval_if(rhs);
+        assert!((value - 1.25).abs() < 1e-12, "{rhs}: {value}");
+    }
+
+    #[test]
+    fn test_csv_table_is_kept() {
+        let mut m =
+            class("model M\n  Real y;\nequation\n  y = table1D(\"data.csv\", time);\nend M;");
+        assert_eq!(lower_table_lookups(&mut m).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unsorted_table_is_rejected() {
+        let mut m =
+            class("model M\n  Real y;\nequation\n  y = table1D({{1, 0}, {0, 1}}, time);\nend M;");
+        let error = lower_table_lookups(&mut m).unwrap_err().to_string();
+        assert!(error.contains("strictly increasing"), "{error}");
+        assert!(error.contains("test.mo:4:7"), "{error}");
+    }
+
+    /// Evaluate a lowered lookup whose inputs are constants
+    fn eval_if(expr: &Expression) -> f64 {
+        let empty = HashMap::new();
+        match expr {
+            Expression::If {
+                branches,
+                else_branch,
+            } => {
+                for (cond, value) in branches {
+                    let Expression::FunctionCall { args, .. } = cond else {
+                        panic!("expected noEvent");
+                    };
+                    let Expression::Binary { lhs, rhs, .. } = &args[0] else {
+                        panic!("expected a comparison");
+                    };
+                    if evaluate(lhs, &empty).unwrap() < evaluate(rhs, &empty).unwrap() {
+                        return eval_if(value);
+                    }
+                }
+                eval_if(else_branch)
+            }
+            Expression::Parenthesized { inner } => eval_if(inner),
+            Expression::Binary { op, lhs, rhs } => {
+                let (a, b) = (eval_if(lhs), eval_if(rhs));
+                match op {
+                    OpBinary::Add(_) => a + b,
+                    OpBinary::Sub(_) => a - b,
+                    OpBinary::Mul(_) => a * b,
+                    OpBinary::Div(_) => a / b,
+                    _ => panic!("unexpected operator"),
+                }
+            }
+            _ => evaluate(expr, &empty).unwrap(),
+        }
+    }
+}
@@ -2,8 +2,17 @@
 //!
 //! This visitor expands tuple equations like `(a, b) = (expr1, expr2)` into
 //! separate equations `a = expr1` and `b = expr2`.
+//!
+//! Tuple equations come from calls of functions with several outputs, e.g.
+//! `(q, r) = divmod(a, b)`. The left-hand side may take fewer outputs than the
+//! function has, and skip some, as in `(, r) = divmod(a, b)`; a single
+//! left-hand side takes the first output. [`check_tuple_equations`] checks the
+//! number of outputs against the function definitions before the calls are
+//! inlined. Calls that can't be inlined are kept as tuple equations.
+
+use indexmap::IndexMap;
 
-use crate::ir::ast::{ClassDefinition, Equation, Expression};
+use crate::ir::ast::{Causality, ClassDefinition, Equation, Expression};
 
 /// Expand all tuple equations in a class definition into individual equations.
 ///
@@ -12,74 +21,160 @@ use crate::ir::ast::{ClassDefinition, Equation, Expression};
 /// - `a = sin(x)`
 /// - `b = cos(x)`
 pub fn expand_tuple_equations(class: &mut ClassDefinition) {
-    let mut new_equations = Vec::new();
+    class.equations = expand_equations(&class.equations);
+    class.initial_equations = expand_equations(&class.initial_equations);
+}
 
-    for eq in &class.equations {
-        if let Equation::Simple { lhs, rhs } = eq {
-            if let (
-                Expression::Tuple {
-                    elements: lhs_elems,
-                },
-                Expression::Tuple {
-                    elements: rhs_elems,
-                },
-            ) = (lhs, rhs)
-            {
-                // Both sides are tuples - expand into individual equations
-                if lhs_elems.len() == rhs_elems.len() {
-                    for (l, r) in lhs_elems.iter().zip(rhs_elems.iter()) {
-                        new_equations.push(Equation::Simple {
-                            lhs: l.clone(),
-                            rhs: r.clone(),
-                        });
-                    }
-                } else {
-                    // Mismatched tuple sizes - keep the original equation
-                    // (will be caught as an error later)
-                    new_equations.push(eq.clone());
-                }
-            } else {
-                // Not a tuple equation - keep as is
-                new_equations.push(eq.clone());
-            }
-        } else {
-            // Not a Simple equation - keep as is
+fn expand_equations(equations: &[Equation]) -> Vec<Equation> {
+    let mut new_equations = Vec::new();
+    for eq in equations {
+        let Equation::Simple {
+            lhs,
+            rhs: Expression::Tuple {
+                elements: rhs_elems,
+            },
+            description,
+        } = eq
+        else {
+            // Not a tuple equation - keep as is
             new_equations.push(eq.clone());
+            continue;
+        };
+        let lhs_elems = match lhs {
+            Expression::Tuple { elements } => elements.as_slice(),
+            // A single left-hand side, as in `(a) = func(x)`, takes the first
+            // output
+            Expression::Parenthesized { inner } => std::slice::from_ref(inner.as_ref()),
+            _ => std::slice::from_ref(lhs),
+        };
+        if lhs_elems.len() > rhs_elems.len() {
+            // Too many outputs - keep the original equation
+            // (reported by check_tuple_equations)
+            new_equations.push(eq.clone());
+            continue;
+        }
+        for (l, r) in lhs_elems.iter().zip(rhs_elems) {
+            // Skipped outputs, as in `(, b) = func(x)`, give no equation
+            if *l != Expression::Empty {
+                new_equations.push(Equation::Simple {
+                    lhs: l.clone(),
+                    rhs: r.clone(),
+                    description: description.clone(),
+                });
+            }
         }
     }
+    new_equations
+}
 
-    class.equations = new_equations;
-
-    // Also process initial equations
-    let mut new_initial_equations = Vec::new();
-    for eq in &class.initial_equations {
-        if let Equation::Simple { lhs, rhs } = eq {
-            if let (
-                Expression::Tuple {
-                    elements: lhs_elems,
-                },
-                Expression::Tuple {
-                    elements: rhs_elems,
-                },
-            ) = (lhs, rhs)
-            {
-                if lhs_elems.len() == rhs_elems.len() {
-                    for (l, r) in lhs_elems.iter().zip(rhs_elems.iter()) {
-                        new_initial_equations.push(Equation::Simple {
-                            lhs: l.clone(),
-                            rhs: r.clone(),
-                        });
-                    }
-                } else {
-                    new_initial_equations.push(eq.clone());
-                }
-            } else {
-                new_initial_equations.push(eq.clone());
-            }
-        } else {
-            new_initial_equations.push(eq.clone());
+/// Check that the tuple equations calling the given functions don't take
+/// more outputs than the functions have
+pub fn check_tuple_equations(
+    class: &ClassDefinition,
+    functions: &IndexMap<String, &ClassDefinition>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for eq in class.equations.iter().chain(&class.initial_equations) {
+        let Equation::Simple {
+            lhs: Expression::Tuple { elements },
+            rhs: Expression::FunctionCall { comp, .. },
+            ..
+        } = eq
+        else {
+            continue;
+        };
+        let name = comp.to_string();
+        let Some(function) = functions.get(&name) else {
+            continue;
+        };
+        let outputs = function
+            .components
+            .values()
+            .filter(|c| matches!(c.causality, Causality::Output(_)))
+            .count();
+        if elements.len() > outputs {
+            let location = comp
+                .get_location()
+                .map(|loc| format!("{}: ", loc.file_position()))
+                .unwrap_or_default();
+            errors.push(format!(
+                "{}the equation takes {} outputs of '{}', which has {}",
+                location,
+                elements.len(),
+                name,
+                outputs
+            ));
         }
     }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Compiler;
+
+    const DIVMOD: &str = r#"
+function divmod
+  input Integer a;
+  input Integer b;
+  output Integer q;
+  output Integer r;
+algorithm
+  q := div(a, b);
+  r := a - q * b;
+end divmod;
+"#;
+
+    fn compile(model: &str) -> Result<Vec<String>, String> {
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(&format!("{}{}", DIVMOD, model), "m.mo")
+            .map_err(|e| e.to_string())?;
+        let mut equations: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+        equations.sort();
+        Ok(equations)
+    }
+
+    #[test]
+    fn test_tuple_equations() {
+        let equations =
+            compile("model M\n  Real q;\n  Real r;\nequation\n  (q, r) = divmod(7, 2);\nend M;")
+                .unwrap();
+        assert_eq!(equations.len(), 2);
+        assert!(equations[0].starts_with("q = div(7, 2)"), "{:?}", equations);
+        assert!(equations[1].starts_with("r = 7 - "), "{:?}", equations);
+
+        // Skipped and omitted outputs
+        let equations =
+            compile("model M\n  Real r;\nequation\n  (, r) = divmod(7, 2);\nend M;").unwrap();
+        assert_eq!(equations.len(), 1);
+        assert!(equations[0].starts_with("r = 7 - "), "{:?}", equations);
+        let equations =
+            compile("model M\n  Real q;\nequation\n  (q) = divmod(7, 2);\nend M;").unwrap();
+        assert_eq!(equations, ["q = div(7, 2)"]);
 
-    class.initial_equations = new_initial_equations;
+        // Calls that aren't inlined stay tuple equations, counting an
+        // equation per output
+        let source = format!(
+            "{}{}",
+            DIVMOD.replace("function divmod", "impure function divmod"),
+            "model M\n  Real q;\n  Real r;\nequation\n  (q, r) = divmod(7, 2);\nend M;"
+        );
+        let result = Compiler::new()
+            .model("M")
+            .compile_str(&source, "m.mo")
+            .unwrap();
+        assert_eq!(result.dae.fx[0].to_string(), "(q, r) = divmod(7, 2)");
+        assert!(result.is_balanced());
+
+        let err = compile(
+            "model M\n  Real q;\n  Real r;\n  Real s;\nequation\n  (q, r, s) = divmod(7, 2);\nend M;",
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("m.mo:16:15: the equation takes 3 outputs of 'divmod', which has 2"),
+            "{}",
+            err
+        );
+    }
 }
@@ -18,6 +18,13 @@
 //!
 //! - **`MutVisitor` Trait**: Mutable visitor with `enter_*` and `exit_*` methods for each
 //!   AST node type. Used for transformations that modify the AST.
+//!
+//! ## Coverage
+//!
+//! Both traits visit every equation, statement and expression of a class, including
+//! expressions in subscripts of component references, array dimensions, component and
+//! extends modifications, and conditions of conditional components. Annotations are not
+//! visited, since they don't contribute to the model's equations.
 use crate::ir;
 
 // =============================================================================
@@ -112,6 +119,21 @@ impl Visitable for ir::ast::ClassDefinition {
     fn accept<V: Visitor>(&self, visitor: &mut V) {
         visitor.enter_class_definition(self);
 
+        // Visit modifications of extends clauses and short class definitions
+        for extend in &self.extends {
+            for modification in &extend.modifications {
+                modification.accept(visitor);
+            }
+        }
+        if let Some(short_class) = &self.short_class {
+            for sub in &short_class.shape_expr {
+                sub.accept(visitor);
+            }
+            for modification in &short_class.modifications {
+                modification.accept(visitor);
+            }
+        }
+
         // Visit components
         for comp in self.components.values() {
             comp.accept(visitor);
@@ -150,17 +172,19 @@ impl Visitable for ir::ast::Equation {
     fn accept<V: Visitor>(&self, visitor: &mut V) {
         visitor.enter_equation(self);
         match self {
-            ir::ast::Equation::Simple { lhs, rhs } => {
+            ir::ast::Equation::Simple { lhs, rhs, .. } => {
                 lhs.accept(visitor);
                 rhs.accept(visitor);
             }
-            ir::ast::Equation::FunctionCall { comp, args } => {
+            ir::ast::Equation::FunctionCall { comp, args, .. } => {
                 comp.accept(visitor);
                 for arg in args {
                     arg.accept(visitor);
                 }
             }
-            ir::ast::Equation::For { indices, equations } => {
+            ir::ast::Equation::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     index.range.accept(visitor);
                 }
@@ -168,11 +192,11 @@ impl Visitable for ir::ast::Equation {
                     eq.accept(visitor);
                 }
             }
-            ir::ast::Equation::Connect { lhs, rhs } => {
+            ir::ast::Equation::Connect { lhs, rhs, .. } => {
                 lhs.accept(visitor);
                 rhs.accept(visitor);
             }
-            ir::ast::Equation::When(blocks) => {
+            ir::ast::Equation::When { blocks, .. } => {
                 for block in blocks {
                     block.cond.accept(visitor);
                     for eq in &block.eqs {
@@ -183,6 +207,7 @@ impl Visitable for ir::ast::Equation {
             ir::ast::Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     block.cond.accept(visitor);
@@ -207,12 +232,14 @@ impl Visitable for ir::ast::Statement {
         visitor.enter_statement(self);
         match self {
             ir::ast::Statement::Empty => {}
-            ir::ast::Statement::Assignment { comp, value } => {
+            ir::ast::Statement::Assignment { comp, value, .. } => {
                 comp.accept(visitor);
                 value.accept(visitor);
             }
             ir::ast::Statement::Return { .. } | ir::ast::Statement::Break { .. } => {}
-            ir::ast::Statement::For { indices, equations } => {
+            ir::ast::Statement::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     index.range.accept(visitor);
                 }
@@ -229,6 +256,7 @@ impl Visitable for ir::ast::Statement {
             ir::ast::Statement::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     block.cond.accept(visitor);
@@ -250,7 +278,7 @@ impl Visitable for ir::ast::Statement {
                     }
                 }
             }
-            ir::ast::Statement::FunctionCall { comp, args } => {
+            ir::ast::Statement::FunctionCall { comp, args, .. } => {
                 comp.accept(visitor);
                 for arg in args {
                     arg.accept(visitor);
@@ -313,6 +341,11 @@ impl Visitable for ir::ast::Expression {
             ir::ast::Expression::Parenthesized { inner } => {
                 inner.accept(visitor);
             }
+            // The name of a named argument is an input of the function, not a
+            // reference, so only the value is visited
+            ir::ast::Expression::NamedArgument { value, .. } => {
+                value.accept(visitor);
+            }
             ir::ast::Expression::ArrayComprehension { expr, indices } => {
                 expr.accept(visitor);
                 for idx in indices {
@@ -327,14 +360,38 @@ impl Visitable for ir::ast::Expression {
 impl Visitable for ir::ast::Component {
     fn accept<V: Visitor>(&self, visitor: &mut V) {
         visitor.enter_component(self);
+        for sub in &self.shape_expr {
+            sub.accept(visitor);
+        }
         self.start.accept(visitor);
+        for modification in self.modifications.values() {
+            modification.accept(visitor);
+        }
+        if let Some(condition) = &self.condition {
+            condition.accept(visitor);
+        }
         visitor.exit_component(self);
     }
 }
 
+impl Visitable for ir::ast::Subscript {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        if let ir::ast::Subscript::Expression(expr) = self {
+            expr.accept(visitor);
+        }
+    }
+}
+
 impl Visitable for ir::ast::ComponentReference {
     fn accept<V: Visitor>(&self, visitor: &mut V) {
         visitor.enter_component_reference(self);
+        for part in &self.parts {
+            if let Some(subs) = &part.subs {
+                for sub in subs {
+                    sub.accept(visitor);
+                }
+            }
+        }
         visitor.exit_component_reference(self);
     }
 }
@@ -357,6 +414,21 @@ impl MutVisitable for ir::ast::ClassDefinition {
     fn accept_mut<V: MutVisitor>(&mut self, visitor: &mut V) {
         visitor.enter_class_definition(self);
 
+        // Visit modifications of extends clauses and short class definitions
+        for extend in &mut self.extends {
+            for modification in &mut extend.modifications {
+                modification.accept_mut(visitor);
+            }
+        }
+        if let Some(short_class) = &mut self.short_class {
+            for sub in &mut short_class.shape_expr {
+                sub.accept_mut(visitor);
+            }
+            for modification in &mut short_class.modifications {
+                modification.accept_mut(visitor);
+            }
+        }
+
         // Visit components
         for comp in self.components.values_mut() {
             comp.accept_mut(visitor);
@@ -395,17 +467,19 @@ impl MutVisitable for ir::ast::Equation {
     fn accept_mut<V: MutVisitor>(&mut self, visitor: &mut V) {
         visitor.enter_equation(self);
         match self {
-            ir::ast::Equation::Simple { lhs, rhs } => {
+            ir::ast::Equation::Simple { lhs, rhs, .. } => {
                 lhs.accept_mut(visitor);
                 rhs.accept_mut(visitor);
             }
-            ir::ast::Equation::FunctionCall { comp, args } => {
+            ir::ast::Equation::FunctionCall { comp, args, .. } => {
                 comp.accept_mut(visitor);
                 for arg in args {
                     arg.accept_mut(visitor);
                 }
             }
-            ir::ast::Equation::For { indices, equations } => {
+            ir::ast::Equation::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     index.range.accept_mut(visitor);
                 }
@@ -413,11 +487,11 @@ impl MutVisitable for ir::ast::Equation {
                     eq.accept_mut(visitor);
                 }
             }
-            ir::ast::Equation::Connect { lhs, rhs } => {
+            ir::ast::Equation::Connect { lhs, rhs, .. } => {
                 lhs.accept_mut(visitor);
                 rhs.accept_mut(visitor);
             }
-            ir::ast::Equation::When(blocks) => {
+            ir::ast::Equation::When { blocks, .. } => {
                 for block in blocks {
                     block.cond.accept_mut(visitor);
                     for eq in &mut block.eqs {
@@ -428,6 +502,7 @@ impl MutVisitable for ir::ast::Equation {
             ir::ast::Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     block.cond.accept_mut(visitor);
@@ -452,12 +527,14 @@ impl MutVisitable for ir::ast::Statement {
         visitor.enter_statement(self);
         match self {
             ir::ast::Statement::Empty => {}
-            ir::ast::Statement::Assignment { comp, value } => {
+            ir::ast::Statement::Assignment { comp, value, .. } => {
                 comp.accept_mut(visitor);
                 value.accept_mut(visitor);
             }
             ir::ast::Statement::Return { .. } | ir::ast::Statement::Break { .. } => {}
-            ir::ast::Statement::For { indices, equations } => {
+            ir::ast::Statement::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     index.range.accept_mut(visitor);
                 }
@@ -474,6 +551,7 @@ impl MutVisitable for ir::ast::Statement {
             ir::ast::Statement::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     block.cond.accept_mut(visitor);
@@ -495,7 +573,7 @@ impl MutVisitable for ir::ast::Statement {
                     }
                 }
             }
-            ir::ast::Statement::FunctionCall { comp, args } => {
+            ir::ast::Statement::FunctionCall { comp, args, .. } => {
                 comp.accept_mut(visitor);
                 for arg in args {
                     arg.accept_mut(visitor);
@@ -558,6 +636,11 @@ impl MutVisitable for ir::ast::Expression {
             ir::ast::Expression::Parenthesized { inner } => {
                 inner.accept_mut(visitor);
             }
+            // The name of a named argument is an input of the function, not a
+            // reference, so only the value is visited
+            ir::ast::Expression::NamedArgument { value, .. } => {
+                value.accept_mut(visitor);
+            }
             ir::ast::Expression::ArrayComprehension { expr, indices } => {
                 expr.accept_mut(visitor);
                 for idx in indices {
@@ -572,14 +655,38 @@ impl MutVisitable for ir::ast::Expression {
 impl MutVisitable for ir::ast::Component {
     fn accept_mut<V: MutVisitor>(&mut self, visitor: &mut V) {
         visitor.enter_component(self);
+        for sub in &mut self.shape_expr {
+            sub.accept_mut(visitor);
+        }
         self.start.accept_mut(visitor);
+        for modification in self.modifications.values_mut() {
+            modification.accept_mut(visitor);
+        }
+        if let Some(condition) = &mut self.condition {
+            condition.accept_mut(visitor);
+        }
         visitor.exit_component(self);
     }
 }
 
+impl MutVisitable for ir::ast::Subscript {
+    fn accept_mut<V: MutVisitor>(&mut self, visitor: &mut V) {
+        if let ir::ast::Subscript::Expression(expr) = self {
+            expr.accept_mut(visitor);
+        }
+    }
+}
+
 impl MutVisitable for ir::ast::ComponentReference {
     fn accept_mut<V: MutVisitor>(&mut self, visitor: &mut V) {
         visitor.enter_component_reference(self);
+        for part in &mut self.parts {
+            if let Some(subs) = &mut part.subs {
+                for sub in subs {
+                    sub.accept_mut(visitor);
+                }
+            }
+        }
         visitor.exit_component_reference(self);
     }
 }
@@ -651,6 +758,42 @@ end Test;
         assert!(visitor.expressions >= 4, "Should have multiple expressions");
     }
 
+    /// Collects the names of all visited component references
+    struct RefCollector(Vec<String>);
+
+    impl Visitor for RefCollector {
+        fn enter_component_reference(&mut self, node: &ComponentReference) {
+            self.0.push(node.to_string());
+        }
+    }
+
+    #[test]
+    fn test_visits_subscripts_and_modifications() {
+        let code = r#"
+model Test
+  extends Base(k = a);
+  parameter Integer n = 2;
+  parameter Real b = 1;
+  Real x[n](each start = b);
+  Real y if c;
+equation
+  x[n - 1] = 1;
+end Test;
+"#;
+        let ast = parse_test_code(code);
+        let mut visitor = RefCollector(Vec::new());
+        ast.accept(&mut visitor);
+
+        for name in ["a", "n", "b", "c"] {
+            assert!(
+                visitor.0.iter().any(|r| r == name),
+                "{name}: {:?}",
+                visitor.0
+            );
+        }
+        assert_eq!(visitor.0.iter().filter(|r| *r == "n").count(), 2);
+    }
+
     #[test]
     fn test_nested_classes() {
         let code = r#"
@@ -1,5 +1,6 @@
 pub mod compiler;
 pub mod dae;
+pub mod error;
 pub mod fmt;
 pub mod ir;
 pub mod lint;
@@ -19,9 +20,11 @@ pub use modelica_grammar::generated::modelica_parser;
 
 // Re-export the main API types for convenience
 pub use compiler::{
-    CompilationResult, Compiler, extract_parse_error, parse_file_cached, parse_file_cached_result,
-    parse_source, parse_source_simple,
+    CompilationResult, CompileWarning, Compiler, DiagnosticCode, SortMode, extract_parse_error,
+    normalize_source, parse_file_cached, parse_file_cached_result, parse_source,
+    parse_source_lossless, parse_source_simple, read_source, read_source_with_warning,
 };
+pub use error::{Error, Result};
 pub use fmt::{CONFIG_FILE_NAMES, FormatOptions, format_modelica};
 pub use lint::{
     LINT_CONFIG_FILE_NAMES, LintConfig, LintLevel, LintMessage, LintResult, lint_file, lint_str,
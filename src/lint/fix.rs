@@ -0,0 +1,246 @@
+//! Machine-applicable fixes for lint messages.
+//!
+//! A [`Fix`] is a described list of [`TextEdit`]s. Lint rules attach one to a
+//! [`LintMessage`](super::LintMessage) when the problem can be fixed without
+//! a human decision, e.g. prefixing an unused variable with an underscore.
+//! The same fixes are applied by `rumoca-lint --fix` (see [`apply_fixes`]) and
+//! offered as quick fixes by the language server.
+//!
+//! Edit positions use the conventions of [`Location`](crate::ir::ast::Location):
+//! 1-based lines and 1-based columns counted in characters, so they stay valid
+//! for the original source whatever its line endings.
+
+use serde::{Deserialize, Serialize};
+
+/// Replacement of the text between two positions
+///
+/// The start is inclusive and the end exclusive, so an edit with equal start
+/// and end inserts text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// Insert text before a position
+    pub fn insert(line: u32, column: u32, text: impl Into<String>) -> Self {
+        Self::replace(line, column, line, column, text)
+    }
+
+    /// Delete the text between two positions
+    pub fn delete(start_line: u32, start_column: u32, end_line: u32, end_column: u32) -> Self {
+        Self::replace(start_line, start_column, end_line, end_column, "")
+    }
+
+    /// Replace the text between two positions
+    pub fn replace(
+        start_line: u32,
+        start_column: u32,
+        end_line: u32,
+        end_column: u32,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+            new_text: text.into(),
+        }
+    }
+
+    fn start(&self) -> (u32, u32) {
+        (self.start_line, self.start_column)
+    }
+
+    fn end(&self) -> (u32, u32) {
+        (self.end_line, self.end_column)
+    }
+}
+
+/// A fix for a lint message: edits that are applied together or not at all
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    /// Short description of the fix, e.g. "Rename to '_x'"
+    pub description: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    pub fn new(description: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        Self {
+            description: description.into(),
+            edits,
+        }
+    }
+}
+
+/// Apply fixes to a source text, returning the fixed text and the number of
+/// fixes applied
+///
+/// Fixes are taken in order, and a fix is skipped if one of its edits overlaps
+/// an edit of a fix taken before it, or lies outside of the source. Running
+/// the linter again on the result finds the problems whose fixes were skipped.
+pub fn apply_fixes<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let offsets = LineOffsets::new(source);
+    let mut accepted: Vec<(usize, usize, &str)> = Vec::new();
+    let mut applied = 0;
+
+    for fix in fixes {
+        let Some(mut edits) = fix
+            .edits
+            .iter()
+            .map(|edit| {
+                let start = offsets.offset(edit.start())?;
+                let end = offsets.offset(edit.end())?;
+                (start <= end).then_some((start, end, edit.new_text.as_str()))
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        edits.sort_by_key(|&(start, end, _)| (start, end));
+        let overlaps = edits.windows(2).any(|w| overlap(w[0], w[1]))
+            || edits
+                .iter()
+                .any(|&edit| accepted.iter().any(|&other| overlap(edit, other)));
+        if !overlaps {
+            accepted.extend(edits);
+            applied += 1;
+        }
+    }
+
+    // Apply back to front, so the offsets of the remaining edits stay valid
+    accepted.sort_by_key(|&(start, end, _)| std::cmp::Reverse((start, end)));
+    let mut fixed = source.to_string();
+    for (start, end, text) in accepted {
+        fixed.replace_range(start..end, text);
+    }
+    (fixed, applied)
+}
+
+/// Whether two edits touch the same text (two insertions at the same
+/// position count as overlapping, since their order would be ambiguous)
+fn overlap(a: (usize, usize, &str), b: (usize, usize, &str)) -> bool {
+    let (a_start, a_end, _) = a;
+    let (b_start, b_end, _) = b;
+    a_start < b_end && b_start < a_end || a_start == b_start
+}
+
+/// Byte offsets of the lines of a source text
+struct LineOffsets<'a> {
+    source: &'a str,
+    /// Byte offset of the start of each line
+    starts: Vec<usize>,
+}
+
+impl<'a> LineOffsets<'a> {
+    fn new(source: &'a str) -> Self {
+        // A byte order mark is not part of the first line's columns
+        let first = if source.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+        let starts = std::iter::once(first)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// Byte offset of a 1-based line and character column
+    ///
+    /// The column may point just past the end of the line, and line endings
+    /// (`\n` or `\r\n`) are not part of the line.
+    fn offset(&self, (line, column): (u32, u32)) -> Option<usize> {
+        let line_start = *self.starts.get((line as usize).checked_sub(1)?)?;
+        let line_text = self.source[line_start..]
+            .split('\n')
+            .next()
+            .unwrap_or_default();
+        let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+        let column = (column as usize).checked_sub(1)?;
+        line_text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line_text.len()))
+            .nth(column)
+            .map(|i| line_start + i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_fixes() {
+        let source = "model M\r\n  Real x;\r\n  Real ü = 1; Real y;\r\nend M;\r\n";
+        let fixes = [
+            Fix::new("Rename to '_x'", vec![TextEdit::insert(2, 8, "_")]),
+            Fix::new("Rename to 'z'", vec![TextEdit::replace(3, 20, 3, 21, "z")]),
+            // Overlaps the first fix, so it is skipped as a whole
+            Fix::new(
+                "Remove x",
+                vec![TextEdit::delete(1, 1, 1, 2), TextEdit::delete(2, 3, 2, 11)],
+            ),
+            // Out of range
+            Fix::new("Append", vec![TextEdit::insert(9, 1, "x")]),
+        ];
+        let (fixed, applied) = apply_fixes(source, &fixes);
+        assert_eq!(applied, 2);
+        assert_eq!(
+            fixed,
+            "model M\r\n  Real _x;\r\n  Real ü = 1; Real z;\r\nend M;\r\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_fixes_line_edits() {
+        let source = "\u{feff}a;\nb;\nc;";
+        let fixes = [
+            Fix::new("Remove b", vec![TextEdit::delete(2, 1, 3, 1)]),
+            Fix::new("Prefix a", vec![TextEdit::insert(1, 1, "_")]),
+            Fix::new("Terminate", vec![TextEdit::insert(3, 3, "\n")]),
+        ];
+        assert_eq!(
+            apply_fixes(source, &fixes),
+            ("\u{feff}_a;\nc;\n".to_string(), 3)
+        );
+    }
+
+    #[test]
+    fn test_lint_fixes() {
+        let source = "model Base\n  Real b;\nend Base;\n\nmodel M\n  extends Base;\n  extends Base;\n  Real x;\n  Real y;\nequation\n  y = 1;\nend M;\n";
+        let lint = |source: &str| crate::lint::lint_str(source, "test.mo", &Default::default());
+        let result = lint(source);
+        let fixes: Vec<_> = result
+            .messages
+            .iter()
+            .filter_map(|m| m.fix.as_ref())
+            .collect();
+        let mut descriptions: Vec<_> = fixes.iter().map(|f| f.description.as_str()).collect();
+        descriptions.sort();
+        // The inherited 'b' is declared in Base, where it is only fixed once
+        assert_eq!(
+            descriptions,
+            [
+                "Remove duplicate extends of 'Base'",
+                "Rename to '_b'",
+                "Rename to '_x'"
+            ]
+        );
+
+        let (fixed, applied) = apply_fixes(source, fixes);
+        assert_eq!(applied, 3);
+        assert_eq!(
+            fixed,
+            "model Base\n  Real _b;\nend Base;\n\nmodel M\n  extends Base;\n  Real _x;\n  Real y;\nequation\n  y = 1;\nend M;\n"
+        );
+        assert!(lint(&fixed).messages.iter().all(|m| m.fix.is_none()));
+    }
+}
@@ -17,10 +17,27 @@
 //! min_level = "warning"
 //! disabled_rules = ["magic-number", "missing-documentation"]
 //! ```
+//!
+//! Individual messages can be suppressed in the source with a
+//! `// rumoca-ignore: <rule>` comment or an `annotation(__rumoca_ignore = "<rule>")`
+//! (see [`Suppressions`]).
+//!
+//! Messages whose problem can be fixed mechanically carry a [`Fix`], which
+//! `rumoca-lint --fix` applies with [`apply_fixes`].
+//!
+//! Results can be written as SARIF (see [`to_sarif`]) for GitHub code scanning.
 
+mod fix;
 mod rules;
+mod sarif;
+mod suppression;
 
+pub use fix::{Fix, TextEdit, apply_fixes};
 pub use rules::*;
+pub use sarif::to_sarif;
+pub use suppression::{
+    DIAGNOSTIC_CODES, IGNORE_ANNOTATION, IGNORE_COMMENT, Suppression, Suppressions, is_known_code,
+};
 
 // Re-export shared symbol analysis from ir/analysis
 pub use crate::ir::analysis::symbols::{
@@ -77,6 +94,8 @@ pub struct LintMessage {
     pub column: u32,
     /// Optional suggestion for fixing the issue
     pub suggestion: Option<String>,
+    /// Edits that fix the issue, if it can be fixed mechanically
+    pub fix: Option<Fix>,
 }
 
 impl LintMessage {
@@ -96,6 +115,7 @@ impl LintMessage {
             line,
             column,
             suggestion: None,
+            fix: None,
         }
     }
 
@@ -103,6 +123,11 @@ impl LintMessage {
         self.suggestion = Some(suggestion.into());
         self
     }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
 }
 
 /// Config file names to search for (in priority order)
@@ -255,12 +280,20 @@ pub fn lint_str(source: &str, file_path: &str, config: &LintConfig) -> LintResul
     use crate::modelica_grammar::ModelicaGrammar;
     use crate::modelica_parser::parse;
 
+    let source = crate::compiler::normalize_source(source);
+    let source = source.as_ref();
     let mut grammar = ModelicaGrammar::new();
     match parse(source, file_path, &mut grammar) {
         Ok(_) => {
             result.parsed = true;
             if let Some(ref ast) = grammar.modelica {
                 lint_ast(ast, source, file_path, config, &mut result);
+                apply_suppressions(
+                    &Suppressions::new(source, ast),
+                    file_path,
+                    config,
+                    &mut result,
+                );
             }
         }
         Err(e) => {
@@ -279,12 +312,59 @@ pub fn lint_str(source: &str, file_path: &str, config: &LintConfig) -> LintResul
     result
 }
 
+/// Drop suppressed messages and report suppressions of unknown codes
+fn apply_suppressions(
+    suppressions: &Suppressions,
+    file_path: &str,
+    config: &LintConfig,
+    result: &mut LintResult,
+) {
+    result
+        .messages
+        .retain(|msg| !suppressions.is_suppressed(msg.rule, msg.line));
+
+    if config.should_run("unknown-suppression") {
+        for suppression in suppressions.unknown() {
+            result.messages.push(
+                LintMessage::new(
+                    "unknown-suppression",
+                    LintLevel::Warning,
+                    format!("unknown code '{}' in suppression", suppression.code),
+                    file_path,
+                    suppression.line,
+                    suppression.column,
+                )
+                .with_suggestion("Use the name of a lint rule, see `rumoca-lint --list-rules`"),
+            );
+        }
+    }
+}
+
 /// Lint a Modelica file
 pub fn lint_file(path: &Path, config: &LintConfig) -> LintResult {
     let file_path = path.to_string_lossy().to_string();
 
-    match std::fs::read_to_string(path) {
-        Ok(source) => lint_str(&source, &file_path, config),
+    match crate::compiler::read_source_with_warning(path) {
+        Ok((source, warning)) => {
+            let mut result = lint_str(&source, &file_path, config);
+            if let Some(warning) = warning
+                && config.should_run("encoding")
+            {
+                result.messages.insert(
+                    0,
+                    LintMessage::new(
+                        "encoding",
+                        LintLevel::Warning,
+                        warning.message,
+                        &file_path,
+                        1,
+                        1,
+                    )
+                    .with_suggestion("Save the file as UTF-8"),
+                );
+            }
+            result
+        }
         Err(e) => {
             let mut result = LintResult::new(&file_path);
             result.messages.push(LintMessage::new(
@@ -341,6 +421,7 @@ fn lint_class(
                 line: class.name.location.start_line,
                 column: class.name.location.start_column,
                 suggestion: None,
+                fix: None,
             });
             None
         }
@@ -389,7 +470,15 @@ fn lint_class(
     }
 
     if config.should_run("redundant-extends") {
-        lint_redundant_extends(class, file_path, result);
+        lint_redundant_extends(class, file_path, source, result);
+    }
+
+    if config.should_run("division-by-zero") {
+        lint_division_by_zero(class, file_path, result);
+    }
+
+    if config.should_run("badly-scaled-equation") {
+        lint_badly_scaled_equations(class, file_path, result);
     }
 
     // Recursively lint nested classes
@@ -4,7 +4,10 @@
 
 use std::collections::HashSet;
 
+use crate::ir::analysis::division_check::find_zero_divisions;
+use crate::ir::analysis::scaling::{DEFAULT_THRESHOLD, find_badly_scaled};
 use crate::ir::ast::{ClassDefinition, Expression, TerminalType};
+use crate::ir::literal::parse_real;
 use crate::lint::{LintLevel, LintMessage, LintResult};
 
 /// Check for magic numbers in equations
@@ -47,7 +50,7 @@ fn check_magic_numbers_in_equation(
     result: &mut LintResult,
 ) {
     match eq {
-        crate::ir::ast::Equation::Simple { lhs, rhs } => {
+        crate::ir::ast::Equation::Simple { lhs, rhs, .. } => {
             check_magic_numbers_in_expr(lhs, file_path, acceptable, result);
             check_magic_numbers_in_expr(rhs, file_path, acceptable, result);
         }
@@ -73,7 +76,7 @@ fn check_magic_numbers_in_expr(
         } => {
             if !acceptable.contains(token.text.as_str()) {
                 // Check if it looks like a "magic number" (specific constants)
-                if let Ok(val) = token.text.parse::<f64>() {
+                if let Some(val) = parse_real(&token.text) {
                     // Skip very small or very large numbers (likely physical constants)
                     if val.abs() > 1e-6 && val.abs() < 1e6 && val.fract() != 0.0 {
                         result.messages.push(
@@ -115,7 +118,7 @@ fn check_magic_numbers_in_expr(
 /// Check for overly complex expressions
 pub fn lint_complex_expressions(class: &ClassDefinition, file_path: &str, result: &mut LintResult) {
     for eq in &class.equations {
-        if let crate::ir::ast::Equation::Simple { lhs, rhs } = eq {
+        if let crate::ir::ast::Equation::Simple { lhs, rhs, .. } = eq {
             let lhs_depth = expression_depth(lhs);
             let rhs_depth = expression_depth(rhs);
 
@@ -138,6 +141,51 @@ pub fn lint_complex_expressions(class: &ClassDefinition, file_path: &str, result
     }
 }
 
+/// Warn about divisions whose denominator is zero at the initial point
+pub fn lint_division_by_zero(class: &ClassDefinition, file_path: &str, result: &mut LintResult) {
+    for division in find_zero_divisions(class) {
+        result.messages.push(
+            LintMessage::new(
+                "division-by-zero",
+                LintLevel::Warning,
+                division.message(),
+                file_path,
+                division.location.start_line,
+                division.location.start_column,
+            )
+            .with_suggestion(format!(
+                "Give '{}' a nonzero default or guard the division with an if-expression",
+                division.denominator
+            )),
+        );
+    }
+}
+
+/// Warn about equations whose terms differ by many orders of magnitude at
+/// the parameter values and nominal values of the variables
+pub fn lint_badly_scaled_equations(
+    class: &ClassDefinition,
+    file_path: &str,
+    result: &mut LintResult,
+) {
+    for scaling in find_badly_scaled(class, DEFAULT_THRESHOLD) {
+        let Some(location) = &scaling.location else {
+            continue;
+        };
+        result.messages.push(
+            LintMessage::new(
+                "badly-scaled-equation",
+                LintLevel::Warning,
+                scaling.message(),
+                file_path,
+                location.start_line,
+                location.start_column,
+            )
+            .with_suggestion(scaling.suggestion()),
+        );
+    }
+}
+
 fn expression_depth(expr: &Expression) -> usize {
     match expr {
         Expression::Empty | Expression::Terminal { .. } | Expression::ComponentReference(_) => 1,
@@ -166,7 +214,9 @@ fn expression_depth(expr: &Expression) -> usize {
                 .max(step_depth)
                 .max(expression_depth(end))
         }
-        Expression::Parenthesized { inner } => expression_depth(inner),
+        Expression::Parenthesized { inner } | Expression::NamedArgument { value: inner, .. } => {
+            expression_depth(inner)
+        }
         Expression::ArrayComprehension { expr, indices } => {
             let index_depth = indices
                 .iter()
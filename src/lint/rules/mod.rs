@@ -4,14 +4,18 @@
 //! - `naming`: Naming convention checks
 //! - `references`: Unused/undefined variable detection
 //! - `structure`: Class structure, parameters, empty sections
-//! - `expressions`: Magic numbers and expression complexity
+//! - `expressions`: Magic numbers, expression complexity, division by zero and
+//!   equation scaling
 
 mod expressions;
 mod naming;
 mod references;
 mod structure;
 
-pub use expressions::{lint_complex_expressions, lint_magic_numbers};
+pub use expressions::{
+    lint_badly_scaled_equations, lint_complex_expressions, lint_division_by_zero,
+    lint_magic_numbers,
+};
 pub use naming::lint_naming_conventions;
 pub use references::{lint_undefined_references, lint_unused_variables};
 pub use structure::{
@@ -73,4 +77,24 @@ pub const LINT_RULES: &[(&str, &str, LintLevel)] = &[
         "Detect redundant or circular extends",
         LintLevel::Warning,
     ),
+    (
+        "division-by-zero",
+        "Detect divisions by a denominator that is zero at the initial point",
+        LintLevel::Warning,
+    ),
+    (
+        "badly-scaled-equation",
+        "Detect equations whose terms differ by many orders of magnitude",
+        LintLevel::Warning,
+    ),
+    (
+        "unknown-suppression",
+        "Report unknown codes in rumoca-ignore comments and annotations",
+        LintLevel::Warning,
+    ),
+    (
+        "encoding",
+        "Report files that are not valid UTF-8 and were decoded as ISO-8859-1",
+        LintLevel::Warning,
+    ),
 ];
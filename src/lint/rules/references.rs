@@ -6,7 +6,7 @@ use std::collections::{HashMap, HashSet};
 
 use crate::ir::ast::{ClassDefinition, Expression};
 use crate::lint::{
-    DefinedSymbol, LintLevel, LintMessage, LintResult, collect_defined_symbols,
+    DefinedSymbol, Fix, LintLevel, LintMessage, LintResult, TextEdit, collect_defined_symbols,
     collect_used_symbols, is_class_instance_type,
 };
 
@@ -32,23 +32,48 @@ pub fn lint_unused_variables(
             continue;
         }
 
-        result.messages.push(
-            LintMessage::new(
-                "unused-variable",
-                LintLevel::Warning,
-                format!("Variable '{}' is declared but never used", name),
-                file_path,
-                sym.line,
-                sym.col,
-            )
-            .with_suggestion(format!(
-                "Remove the variable or prefix with underscore: _{}",
-                name
-            )),
-        );
+        let mut msg = LintMessage::new(
+            "unused-variable",
+            LintLevel::Warning,
+            format!("Variable '{}' is declared but never used", name),
+            file_path,
+            sym.line,
+            sym.col,
+        )
+        .with_suggestion(format!(
+            "Remove the variable or prefix with underscore: _{}",
+            name
+        ));
+        if let Some(fix) = prefix_underscore_fix(class, name) {
+            msg = msg.with_fix(fix);
+        }
+        result.messages.push(msg);
     }
 }
 
+/// Fix that prefixes the declaration of an unused variable with an underscore
+///
+/// Only offered for variables declared in the class itself: the class is
+/// flattened, so inherited variables and the variables of subcomponents are
+/// declared in another class, where they may be used.
+fn prefix_underscore_fix(class: &ClassDefinition, name: &str) -> Option<Fix> {
+    let token = &class.components.get(name)?.name_token.location;
+    let declared_here = !name.contains('.')
+        && token.file_name == class.location.file_name
+        && class.location.start <= token.start
+        && token.end <= class.location.end
+        && !class
+            .classes
+            .values()
+            .any(|nested| nested.location.start <= token.start && token.end <= nested.location.end);
+    declared_here.then(|| {
+        Fix::new(
+            format!("Rename to '_{}'", name),
+            vec![TextEdit::insert(token.start_line, token.start_column, "_")],
+        )
+    })
+}
+
 /// Check for undefined references
 pub fn lint_undefined_references(
     class: &ClassDefinition,
@@ -97,15 +122,17 @@ fn check_equation_references(
 ) {
     match eq {
         crate::ir::ast::Equation::Empty => {}
-        crate::ir::ast::Equation::Simple { lhs, rhs } => {
+        crate::ir::ast::Equation::Simple { lhs, rhs, .. } => {
             check_expression_references(lhs, file_path, defined, globals, result);
             check_expression_references(rhs, file_path, defined, globals, result);
         }
-        crate::ir::ast::Equation::Connect { lhs, rhs } => {
+        crate::ir::ast::Equation::Connect { lhs, rhs, .. } => {
             check_comp_ref_references(lhs, file_path, defined, globals, result);
             check_comp_ref_references(rhs, file_path, defined, globals, result);
         }
-        crate::ir::ast::Equation::For { indices, equations } => {
+        crate::ir::ast::Equation::For {
+            indices, equations, ..
+        } => {
             // Add loop indices as locally defined
             let mut local_defined = defined.clone();
             for index in indices {
@@ -128,7 +155,7 @@ fn check_equation_references(
                 check_equation_references(sub_eq, file_path, &local_defined, globals, result);
             }
         }
-        crate::ir::ast::Equation::When(blocks) => {
+        crate::ir::ast::Equation::When { blocks, .. } => {
             for block in blocks {
                 check_expression_references(&block.cond, file_path, defined, globals, result);
                 for sub_eq in &block.eqs {
@@ -139,6 +166,7 @@ fn check_equation_references(
         crate::ir::ast::Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 check_expression_references(&block.cond, file_path, defined, globals, result);
@@ -152,7 +180,7 @@ fn check_equation_references(
                 }
             }
         }
-        crate::ir::ast::Equation::FunctionCall { comp, args } => {
+        crate::ir::ast::Equation::FunctionCall { comp, args, .. } => {
             // Don't check function name - it might be external
             // But check if it's a locally defined variable being called
             if let Some(first) = comp.parts.first()
@@ -176,11 +204,11 @@ fn check_statement_references(
 ) {
     match stmt {
         crate::ir::ast::Statement::Empty => {}
-        crate::ir::ast::Statement::Assignment { comp, value } => {
+        crate::ir::ast::Statement::Assignment { comp, value, .. } => {
             check_comp_ref_references(comp, file_path, defined, globals, result);
             check_expression_references(value, file_path, defined, globals, result);
         }
-        crate::ir::ast::Statement::FunctionCall { comp, args } => {
+        crate::ir::ast::Statement::FunctionCall { comp, args, .. } => {
             // Don't check function name
             if let Some(first) = comp.parts.first()
                 && defined.contains_key(&first.ident.text)
@@ -191,7 +219,9 @@ fn check_statement_references(
                 check_expression_references(arg, file_path, defined, globals, result);
             }
         }
-        crate::ir::ast::Statement::For { indices, equations } => {
+        crate::ir::ast::Statement::For {
+            indices, equations, ..
+        } => {
             let mut local_defined = defined.clone();
             for index in indices {
                 local_defined.insert(
@@ -222,6 +252,7 @@ fn check_statement_references(
         crate::ir::ast::Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 check_expression_references(&block.cond, file_path, defined, globals, result);
@@ -315,6 +346,9 @@ fn check_expression_references(
         Expression::Parenthesized { inner } => {
             check_expression_references(inner, file_path, defined, globals, result);
         }
+        Expression::NamedArgument { value, .. } => {
+            check_expression_references(value, file_path, defined, globals, result);
+        }
         Expression::ArrayComprehension { expr, indices } => {
             check_expression_references(expr, file_path, defined, globals, result);
             for idx in indices {
@@ -4,8 +4,9 @@
 
 use std::collections::HashSet;
 
+use crate::ir::ast::Extend;
 use crate::ir::ast::{ClassDefinition, ClassType, Expression, Variability};
-use crate::lint::{LintLevel, LintMessage, LintResult};
+use crate::lint::{Fix, LintLevel, LintMessage, LintResult, TextEdit};
 
 /// Check for missing documentation
 pub fn lint_missing_documentation(
@@ -133,7 +134,12 @@ pub fn lint_unit_consistency(class: &ClassDefinition, file_path: &str, result: &
 }
 
 /// Check for redundant extends
-pub fn lint_redundant_extends(class: &ClassDefinition, file_path: &str, result: &mut LintResult) {
+pub fn lint_redundant_extends(
+    class: &ClassDefinition,
+    file_path: &str,
+    source: &str,
+    result: &mut LintResult,
+) {
     let line = class.name.location.start_line;
 
     // Check for duplicate extends
@@ -141,14 +147,21 @@ pub fn lint_redundant_extends(class: &ClassDefinition, file_path: &str, result:
     for ext in &class.extends {
         let ext_name = ext.comp.to_string();
         if seen_extends.contains(&ext_name) {
-            result.messages.push(LintMessage::new(
+            let mut msg = LintMessage::new(
                 "redundant-extends",
                 LintLevel::Warning,
                 format!("Duplicate extends clause for '{}'", ext_name),
                 file_path,
-                line,
-                1,
-            ));
+                ext.location.start_line,
+                ext.location.start_column,
+            );
+            if let Some(edit) = remove_extends_edit(ext, source) {
+                msg = msg.with_fix(Fix::new(
+                    format!("Remove duplicate extends of '{}'", ext_name),
+                    vec![edit],
+                ));
+            }
+            result.messages.push(msg);
         }
         seen_extends.insert(ext_name);
     }
@@ -168,6 +181,29 @@ pub fn lint_redundant_extends(class: &ClassDefinition, file_path: &str, result:
     }
 }
 
+/// Edit removing an extends clause with its semicolon, and its line if the
+/// clause is the only thing on it
+fn remove_extends_edit(ext: &Extend, source: &str) -> Option<TextEdit> {
+    let loc = &ext.location;
+    let (start, end) = (loc.start as usize, loc.end as usize);
+    let rest = source.get(end..)?;
+    let rest = rest.split('\n').next().unwrap_or_default();
+    // Clauses with a description or annotation are left alone
+    let after_semicolon = rest.trim_start().strip_prefix(';')?;
+    let before = source.get(..start)?.rsplit('\n').next().unwrap_or_default();
+
+    if before.trim().is_empty() && after_semicolon.trim().is_empty() {
+        return Some(TextEdit::delete(loc.start_line, 1, loc.end_line + 1, 1));
+    }
+    let semicolon_end = rest.len() - after_semicolon.len();
+    Some(TextEdit::delete(
+        loc.start_line,
+        loc.start_column,
+        loc.end_line,
+        loc.end_column + rest[..semicolon_end].chars().count() as u32,
+    ))
+}
+
 fn format_class_type(ct: &ClassType) -> &'static str {
     match ct {
         ClassType::Model => "Model",
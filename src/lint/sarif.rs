@@ -0,0 +1,129 @@
+//! SARIF output of lint results.
+//!
+//! [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! is the format GitHub code scanning reads, so lint findings can be uploaded
+//! with the `github/codeql-action/upload-sarif` action and shown on pull requests.
+
+use serde_json::{Value, json};
+
+use super::{LINT_RULES, LintConfig, LintLevel, LintResult};
+
+/// Schema of SARIF 2.1.0 logs
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The SARIF log of the reported messages of lint results
+pub fn to_sarif(results: &[LintResult], config: &LintConfig) -> Value {
+    let rules: Vec<Value> = LINT_RULES
+        .iter()
+        .map(|(name, description, level)| {
+            json!({
+                "id": name,
+                "shortDescription": { "text": description },
+                "defaultConfiguration": { "level": sarif_level(*level) },
+            })
+        })
+        .collect();
+
+    let sarif_results: Vec<Value> = results
+        .iter()
+        .flat_map(|result| &result.messages)
+        .filter(|msg| config.should_report(msg))
+        .map(|msg| {
+            let mut result = json!({
+                "ruleId": msg.rule,
+                "level": sarif_level(msg.level),
+                "message": { "text": msg.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": artifact_uri(&msg.file) },
+                        "region": {
+                            "startLine": msg.line.max(1),
+                            "startColumn": msg.column.max(1),
+                        },
+                    },
+                }],
+            });
+            if let Some(index) = LINT_RULES.iter().position(|(name, _, _)| *name == msg.rule) {
+                result["ruleIndex"] = json!(index);
+            }
+            result
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "rumoca-lint",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": sarif_results,
+        }],
+    })
+}
+
+/// SARIF level of a lint level (SARIF has no level below `note`)
+fn sarif_level(level: LintLevel) -> &'static str {
+    match level {
+        LintLevel::Help | LintLevel::Note => "note",
+        LintLevel::Warning => "warning",
+        LintLevel::Error => "error",
+    }
+}
+
+/// URI of a linted file, relative to the working directory if it was given
+/// relative, with forward slashes
+fn artifact_uri(file: &str) -> String {
+    let uri = file.replace('\\', "/");
+    match uri.strip_prefix("./") {
+        Some(relative) => relative.to_string(),
+        None => uri,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::lint_str;
+
+    #[test]
+    fn test_to_sarif() {
+        let source = "model test\n  Real x;\nequation\n  x = 1;\nend test;\n";
+        let result = lint_str(source, "./models/test.mo", &LintConfig::default());
+        assert!(!result.messages.is_empty());
+
+        let sarif = to_sarif(std::slice::from_ref(&result), &LintConfig::default());
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "rumoca-lint");
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), result.messages.len());
+
+        let naming = results
+            .iter()
+            .find(|r| r["ruleId"] == "naming-convention")
+            .unwrap();
+        assert_eq!(naming["level"], "note");
+        let index = naming["ruleIndex"].as_u64().unwrap() as usize;
+        assert_eq!(
+            run["tool"]["driver"]["rules"][index]["id"],
+            "naming-convention"
+        );
+        let location = &naming["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "models/test.mo");
+        assert_eq!(location["region"]["startLine"], 1);
+
+        // Only reported messages are included
+        let config = LintConfig {
+            min_level: LintLevel::Error,
+            ..LintConfig::default()
+        };
+        let sarif = to_sarif(&[result], &config);
+        assert!(sarif["runs"][0]["results"].as_array().unwrap().is_empty());
+    }
+}
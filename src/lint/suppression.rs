@@ -0,0 +1,323 @@
+//! Suppression of lint and diagnostic codes in the source.
+//!
+//! Codes can be suppressed with a structured comment:
+//!
+//! ```modelica
+//! Real unused; // rumoca-ignore: unused-variable
+//! // rumoca-ignore: magic-number, complex-expression
+//! y = 3.7 * x;
+//! ```
+//!
+//! A comment after code applies to its own line, a comment on a line of its own
+//! to the next line of code.
+//!
+//! Codes can also be suppressed with the vendor annotation `__rumoca_ignore`,
+//! given a string or an array of strings. On an equation, statement or component
+//! it applies to that element; as a class annotation, to the whole class:
+//!
+//! ```modelica
+//! model M
+//!   Real x;
+//! equation
+//!   x = 2.5 annotation(__rumoca_ignore = "magic-number");
+//!   annotation(__rumoca_ignore = {"naming-convention", "missing-documentation"});
+//! end M;
+//! ```
+
+use crate::ir::ast::{ClassDefinition, StoredDefinition};
+use crate::modelica_grammar::cst::{SyntaxKind, SyntaxToken, SyntaxTree, TriviaKind};
+
+use super::rules::LINT_RULES;
+
+/// Marker of a suppression comment
+pub const IGNORE_COMMENT: &str = "rumoca-ignore:";
+
+/// Name of the suppression annotation
+pub const IGNORE_ANNOTATION: &str = "__rumoca_ignore";
+
+/// Codes of diagnostics that are not produced by a lint rule
+pub const DIAGNOSTIC_CODES: &[&str] = &[
+    "parse-error",
+    "flatten-error",
+    "type-error",
+    "high-index",
+    "unused-parameter",
+    "unconnected-connector",
+    "connector-causality",
+];
+
+/// Whether a code names a lint rule or diagnostic
+pub fn is_known_code(code: &str) -> bool {
+    LINT_RULES.iter().any(|(name, _, _)| *name == code) || DIAGNOSTIC_CODES.contains(&code)
+}
+
+/// A code suppressed over a range of lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suppression {
+    /// The suppressed code
+    pub code: String,
+    /// First line the suppression applies to (1-based)
+    pub start_line: u32,
+    /// Last line the suppression applies to (1-based, inclusive)
+    pub end_line: u32,
+    /// Line where the code is written (1-based)
+    pub line: u32,
+    /// Column where the code is written (1-based)
+    pub column: u32,
+}
+
+/// All suppressions of a source file
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+    pub entries: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// Collect the suppression comments and annotations of a source file.
+    ///
+    /// The AST of the source is used to find the extent of classes.
+    pub fn new(source: &str, ast: &StoredDefinition) -> Self {
+        let tree = SyntaxTree::parse(source);
+        let lines = LineStarts::new(source);
+        let tokens = tree.tokens();
+        let mut entries = Vec::new();
+
+        // Index of the first token of the current element
+        let mut element_start = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            for (trivia, trailing) in token
+                .leading
+                .iter()
+                .map(|t| (t, false))
+                .chain(token.trailing.iter().map(|t| (t, true)))
+            {
+                if !matches!(
+                    trivia.kind,
+                    TriviaKind::LineComment | TriviaKind::BlockComment
+                ) {
+                    continue;
+                }
+                let Some(offset) = trivia.text.find(IGNORE_COMMENT) else {
+                    continue;
+                };
+                let text = trivia.text[offset + IGNORE_COMMENT.len()..].trim_end_matches("*/");
+                // A trailing comment follows code on its line, a leading one
+                // precedes the line of its token
+                let target = lines.line(if trailing {
+                    trivia.span.start
+                } else {
+                    token.span.start
+                });
+                let mut code_offset = trivia.span.start + offset + IGNORE_COMMENT.len();
+                for part in text.split(',') {
+                    let code = part.trim();
+                    if !code.is_empty() {
+                        let (line, column) =
+                            lines.position(source, code_offset + part.find(code).unwrap_or(0));
+                        entries.push(Suppression {
+                            code: code.to_string(),
+                            start_line: target,
+                            end_line: target,
+                            line,
+                            column,
+                        });
+                    }
+                    code_offset += part.len() + 1;
+                }
+            }
+
+            if token.kind == SyntaxKind::Ident && token.text == IGNORE_ANNOTATION {
+                let annotation = (0..i).rev().find(|&j| tokens[j].is_keyword("annotation"));
+                let (start_line, end_line) = match annotation {
+                    // A class annotation is an element of its own
+                    Some(j) if j == element_start => {
+                        let line = lines.line(token.span.start);
+                        innermost_class(ast, line)
+                            .map(|class| (class.location.start_line, class.location.end_line))
+                            .unwrap_or((line, line))
+                    }
+                    _ => {
+                        let end = tokens[i..]
+                            .iter()
+                            .find(|t| t.is_symbol(";"))
+                            .unwrap_or(token);
+                        (
+                            lines.line(tokens[element_start].span.start),
+                            lines.line(end.span.start),
+                        )
+                    }
+                };
+                for code in annotation_codes(&tokens[i + 1..]) {
+                    let (line, column) = lines.position(source, code.span.start);
+                    entries.push(Suppression {
+                        code: code.text.trim_matches('"').to_string(),
+                        start_line,
+                        end_line,
+                        line,
+                        column,
+                    });
+                }
+            }
+
+            if is_element_boundary(token) {
+                element_start = i + 1;
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Whether a code is suppressed on a line (1-based)
+    pub fn is_suppressed(&self, code: &str, line: u32) -> bool {
+        self.entries
+            .iter()
+            .any(|s| s.code == code && s.start_line <= line && line <= s.end_line)
+    }
+
+    /// Suppressions of codes that are neither lint rules nor diagnostics
+    pub fn unknown(&self) -> impl Iterator<Item = &Suppression> {
+        self.entries.iter().filter(|s| !is_known_code(&s.code))
+    }
+}
+
+/// Whether a token ends an element, so the next token starts one.
+///
+/// The branches of if- and for-equations aren't boundaries, since `then` and
+/// `else` can't be told apart from those of if-expressions here. An annotation
+/// on the first element of such a branch also covers the lines before it, from
+/// the start of the enclosing equation.
+fn is_element_boundary(token: &SyntaxToken) -> bool {
+    token.is_symbol(";")
+        || ["equation", "algorithm", "public", "protected"]
+            .iter()
+            .any(|keyword| token.is_keyword(keyword))
+}
+
+/// The string tokens of an annotation value (`= "code"` or `= {"code", ...}`)
+fn annotation_codes(tokens: &[SyntaxToken]) -> Vec<&SyntaxToken> {
+    let mut tokens = tokens.iter();
+    if !tokens.next().is_some_and(|t| t.is_symbol("=")) {
+        return Vec::new();
+    }
+    match tokens.next() {
+        Some(t) if t.kind == SyntaxKind::String => vec![t],
+        Some(t) if t.is_symbol("{") => tokens
+            .take_while(|t| !t.is_symbol("}"))
+            .filter(|t| t.kind == SyntaxKind::String)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The innermost class whose extent contains a line
+fn innermost_class(ast: &StoredDefinition, line: u32) -> Option<&ClassDefinition> {
+    fn find(class: &ClassDefinition, line: u32) -> Option<&ClassDefinition> {
+        if line < class.location.start_line || line > class.location.end_line {
+            return None;
+        }
+        class
+            .classes
+            .values()
+            .find_map(|nested| find(nested, line))
+            .or(Some(class))
+    }
+    ast.class_list.values().find_map(|class| find(class, line))
+}
+
+/// Byte offsets where lines start, for converting offsets to line numbers
+struct LineStarts(Vec<usize>);
+
+impl LineStarts {
+    fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self(starts)
+    }
+
+    /// Line of a byte offset (1-based)
+    fn line(&self, offset: usize) -> u32 {
+        self.0.partition_point(|&start| start <= offset) as u32
+    }
+
+    /// Line and character column of a byte offset (both 1-based)
+    fn position(&self, source: &str, offset: usize) -> (u32, u32) {
+        let line = self.line(offset);
+        let start = self.0[line as usize - 1];
+        let column = source[start..offset].chars().count() as u32 + 1;
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    fn suppressions(source: &str) -> Suppressions {
+        let ast = parse_source_simple(source, "test.mo").unwrap();
+        Suppressions::new(source, &ast)
+    }
+
+    #[test]
+    fn test_comment_suppressions() {
+        let s = suppressions(
+            "model M\n  Real x; // rumoca-ignore: unused-variable\n  // rumoca-ignore: magic-number, bogus\n  Real y = 2.5;\nend M;\n",
+        );
+        assert!(s.is_suppressed("unused-variable", 2));
+        assert!(!s.is_suppressed("unused-variable", 4));
+        assert!(s.is_suppressed("magic-number", 4));
+        assert!(!s.is_suppressed("magic-number", 3));
+
+        let unknown: Vec<_> = s.unknown().collect();
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].code, "bogus");
+        assert_eq!((unknown[0].line, unknown[0].column), (3, 35));
+    }
+
+    #[test]
+    fn test_annotation_suppressions() {
+        let s = suppressions(
+            r#"model M
+  Real x;
+  Real y;
+equation
+  x = 2.5
+    annotation(__rumoca_ignore = "magic-number");
+  y = 3.5;
+  annotation(__rumoca_ignore = {"naming-convention"});
+end M;
+"#,
+        );
+        // The equation annotation covers the lines of the equation only
+        assert!(s.is_suppressed("magic-number", 5));
+        assert!(s.is_suppressed("magic-number", 6));
+        assert!(!s.is_suppressed("magic-number", 7));
+        // The class annotation covers the whole class
+        assert!(s.is_suppressed("naming-convention", 1));
+        assert!(s.is_suppressed("naming-convention", 9));
+        assert_eq!(s.unknown().count(), 0);
+    }
+
+    #[test]
+    fn test_lint_honors_suppressions() {
+        let source = r#"model M "A model"
+  Real x "State" annotation(__rumoca_ignore = "unused-variable");
+  Real y "Output";
+equation
+  y = 2.5; // rumoca-ignore: magic-number, not-a-rule
+end M;
+"#;
+        let result = crate::lint::lint_str(source, "test.mo", &Default::default());
+        let rules: Vec<_> = result.messages.iter().map(|m| m.rule).collect();
+        assert!(!rules.contains(&"unused-variable"), "{:?}", rules);
+        assert!(!rules.contains(&"magic-number"), "{:?}", rules);
+
+        let unknown: Vec<_> = result
+            .messages
+            .iter()
+            .filter(|m| m.rule == "unknown-suppression")
+            .collect();
+        assert_eq!(unknown.len(), 1);
+        assert_eq!((unknown[0].line, unknown[0].column), (5, 44));
+    }
+}
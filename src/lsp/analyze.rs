@@ -2,13 +2,22 @@
 //!
 //! Provides on-demand compilation and balance analysis for specific classes.
 //! Uses the shared `BalanceResult` from `dae/balance.rs` for balance information.
+//!
+//! Clients run the analysis through `workspace/executeCommand` with the
+//! [`ANALYZE_COMMAND`] command and `[uri, className]` arguments. The same
+//! request also runs the [`EVALUATE_COMMAND`] of [`super::evaluate`].
 
-use lsp_types::Uri;
+use lsp_types::{ExecuteCommandParams, Uri};
+use serde::Serialize;
 
-use crate::dae::balance::BalanceResult;
+use crate::dae::balance::{BalanceResult, ComponentBalance};
 
 use super::WorkspaceState;
-use super::utils::parse_document;
+use super::evaluate::{EVALUATE_COMMAND, evaluate_expression};
+use super::utils::{parse_document, uri_to_path};
+
+/// Command name for analyzing a class via `workspace/executeCommand`
+pub const ANALYZE_COMMAND: &str = "rumoca.analyze";
 
 /// Result of analyzing a class.
 ///
@@ -17,12 +26,15 @@ use super::utils::parse_document;
 /// - `error`: Error message if compilation failed
 ///
 /// For numerical balance information, use the `balance` field directly.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AnalyzeResult {
     /// The class that was analyzed
     pub class_name: String,
     /// Balance information from the DAE (None if compilation failed)
     pub balance: Option<BalanceResult>,
+    /// Equations and unknowns per component instance (empty if compilation failed)
+    pub components: Vec<ComponentBalance>,
     /// Error message if compilation failed
     pub error: Option<String>,
 }
@@ -33,6 +45,7 @@ impl AnalyzeResult {
         Self {
             class_name,
             balance: Some(balance),
+            components: Vec::new(),
             error: None,
         }
     }
@@ -42,6 +55,7 @@ impl AnalyzeResult {
         Self {
             class_name,
             balance: None,
+            components: Vec::new(),
             error: Some(error),
         }
     }
@@ -97,7 +111,7 @@ pub fn analyze_class(workspace: &mut WorkspaceState, uri: &Uri, class_name: &str
         }
     };
 
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     // First verify the class exists by parsing
     let ast = match parse_document(&text, path) {
@@ -121,7 +135,10 @@ pub fn analyze_class(workspace: &mut WorkspaceState, uri: &Uri, class_name: &str
             // Cache the balance result
             workspace.set_balance(uri.clone(), class_name.to_string(), balance.clone());
 
-            AnalyzeResult::success(class_name.to_string(), balance)
+            AnalyzeResult {
+                components: result.dae.component_balance(),
+                ..AnalyzeResult::success(class_name.to_string(), balance)
+            }
         }
         Err(e) => {
             // Check if the class exists in the AST but just failed to compile
@@ -139,6 +156,25 @@ pub fn analyze_class(workspace: &mut WorkspaceState, uri: &Uri, class_name: &str
     }
 }
 
+/// Handle a `workspace/executeCommand` request.
+///
+/// Returns `None` for unknown commands or malformed arguments.
+pub fn handle_execute_command(
+    workspace: &mut WorkspaceState,
+    params: ExecuteCommandParams,
+) -> Option<serde_json::Value> {
+    let uri: Uri = params.arguments.first()?.as_str()?.parse().ok()?;
+    let class_name = params.arguments.get(1)?.as_str()?;
+    match params.command.as_str() {
+        ANALYZE_COMMAND => serde_json::to_value(analyze_class(workspace, &uri, class_name)).ok(),
+        EVALUATE_COMMAND => {
+            let expression = params.arguments.get(2)?.as_str()?;
+            serde_json::to_value(evaluate_expression(workspace, &uri, class_name, expression)).ok()
+        }
+        _ => None,
+    }
+}
+
 /// Check if a class exists in the AST (supports dotted paths for nested classes)
 fn class_exists_in_ast(ast: &crate::ir::ast::StoredDefinition, class_name: &str) -> bool {
     let parts: Vec<&str> = class_name.split('.').collect();
@@ -180,8 +216,10 @@ mod tests {
             num_parameters: 1,
             num_inputs: 0,
             num_external_connectors: 0,
+            num_unequal_if_equations: 0,
             is_balanced: true,
             status: BalanceStatus::Balanced,
+            dae_index: None,
         };
         let result = AnalyzeResult::success("Test".to_string(), balance);
         assert!(result.is_balanced());
@@ -0,0 +1,173 @@
+//! Evaluate command handler for quick model queries.
+//!
+//! Evaluates a constant expression in the scope of a class, e.g. `2*pi*f0`
+//! for a class with a parameter `f0`, from the parameter and constant values
+//! of the flattened class (see [`initial_values`]).
+//!
+//! Clients run the evaluation through `workspace/executeCommand` with the
+//! [`EVALUATE_COMMAND`] command and `[uri, className, expression]` arguments.
+
+use lsp_types::Uri;
+use serde::Serialize;
+
+use crate::ir::analysis::division_check::{evaluate, initial_values};
+use crate::ir::ast::{Expression, Variability};
+use crate::ir::transform::constant_substitutor::ConstantSubstitutor;
+use crate::ir::transform::flatten::flatten;
+use crate::ir::visitor::{MutVisitable, Visitable, Visitor};
+
+use super::WorkspaceState;
+use super::utils::{parse_document, uri_to_path};
+
+/// Command name for evaluating an expression via `workspace/executeCommand`
+pub const EVALUATE_COMMAND: &str = "rumoca.evaluate";
+
+/// Result of evaluating an expression in a class
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateResult {
+    /// The class the expression was evaluated in
+    pub class_name: String,
+    /// The evaluated expression
+    pub expression: String,
+    /// Value of the expression (None if it couldn't be evaluated)
+    pub value: Option<f64>,
+    /// Error message if the expression couldn't be evaluated
+    pub error: Option<String>,
+}
+
+impl EvaluateResult {
+    fn failed(class_name: &str, expression: &str, error: String) -> Self {
+        Self {
+            class_name: class_name.to_string(),
+            expression: expression.to_string(),
+            value: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Evaluate a constant expression in the scope of a class of a document
+pub fn evaluate_expression(
+    workspace: &WorkspaceState,
+    uri: &Uri,
+    class_name: &str,
+    expression: &str,
+) -> EvaluateResult {
+    let failed = |error: String| EvaluateResult::failed(class_name, expression, error);
+
+    let Some(text) = workspace.get_document(uri) else {
+        return failed("Document not found".to_string());
+    };
+    let Some(ast) = parse_document(text, &uri_to_path(uri)) else {
+        return failed("Failed to parse document".to_string());
+    };
+    let mut class = match flatten(&ast, Some(class_name)) {
+        Ok(class) => class,
+        Err(e) => return failed(format!("Flattening '{}' failed: {}", class_name, e)),
+    };
+    let Some(mut expr) = parse_expression(expression) else {
+        return failed(format!("Invalid expression '{}'", expression));
+    };
+
+    // Substitute Modelica.Constants (e.g. pi) with their values
+    class.accept_mut(&mut ConstantSubstitutor::new());
+    expr.accept_mut(&mut ConstantSubstitutor::new());
+
+    // Only parameters and constants, not state start values
+    let mut values = initial_values(&class);
+    values.retain(|name, _| {
+        class.components.get(name).is_some_and(|comp| {
+            matches!(
+                comp.variability,
+                Variability::Parameter(_) | Variability::Constant(_)
+            )
+        })
+    });
+    match evaluate(&expr, &values) {
+        Some(value) => EvaluateResult {
+            class_name: class_name.to_string(),
+            expression: expression.to_string(),
+            value: Some(value),
+            error: None,
+        },
+        None => {
+            let mut unknown = UnknownNames {
+                values: &values,
+                names: Vec::new(),
+            };
+            expr.accept(&mut unknown);
+            failed(if unknown.names.is_empty() {
+                format!("'{}' is not a constant expression", expression)
+            } else {
+                format!("No constant value for {}", unknown.names.join(", "))
+            })
+        }
+    }
+}
+
+/// Parse a single expression, as the binding of a component of a wrapper model
+fn parse_expression(expression: &str) -> Option<Expression> {
+    let source = format!(
+        "model Evaluate\n  constant Real value = {};\nend Evaluate;\n",
+        expression
+    );
+    let def = parse_document(&source, "evaluate.mo")?;
+    let class = def.class_list.get("Evaluate")?;
+    match class.components.values().collect::<Vec<_>>().as_slice() {
+        [value] if class.equations.is_empty() => Some(value.start.clone()),
+        _ => None,
+    }
+}
+
+/// Collect the names of an expression that have no value
+struct UnknownNames<'a> {
+    values: &'a std::collections::HashMap<String, f64>,
+    names: Vec<String>,
+}
+
+impl Visitor for UnknownNames<'_> {
+    fn enter_expression(&mut self, node: &Expression) {
+        if let Expression::ComponentReference(cref) = node {
+            let name = cref.to_string();
+            if !self.values.contains_key(&name) && !self.names.contains(&name) {
+                self.names.push(name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_expression() {
+        let uri: Uri = "file:///tmp/filter.mo".parse().unwrap();
+        let mut workspace = WorkspaceState::new();
+        workspace.open_document(
+            uri.clone(),
+            "model Filter\n  parameter Real f0 = 50;\n  parameter Real w0 = 2 * Modelica.Constants.pi * f0;\n  Real x;\nequation\n  der(x) = -w0 * x;\nend Filter;\n"
+                .to_string(),
+        );
+
+        let result = evaluate_expression(&workspace, &uri, "Filter", "2*pi*f0");
+        let value = result.value.unwrap();
+        assert!((value - 100.0 * std::f64::consts::PI).abs() < 1e-9);
+        let result = evaluate_expression(&workspace, &uri, "Filter", "sqrt(w0 / f0) + 1");
+        assert!((result.value.unwrap() - (2.0 * std::f64::consts::PI).sqrt() - 1.0).abs() < 1e-9);
+
+        // Variables have no constant value
+        let result = evaluate_expression(&workspace, &uri, "Filter", "x + f0");
+        assert_eq!(result.error.unwrap(), "No constant value for x");
+        let result = evaluate_expression(&workspace, &uri, "Filter", "f0 +");
+        assert!(result.error.unwrap().starts_with("Invalid expression"));
+        let result = evaluate_expression(&workspace, &uri, "Missing", "f0");
+        assert!(
+            result
+                .error
+                .unwrap()
+                .starts_with("Flattening 'Missing' failed")
+        );
+    }
+}
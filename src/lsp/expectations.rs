@@ -0,0 +1,222 @@
+//! Expected-diagnostic annotations, for conformance tests of the type checker
+//! and lint rules.
+//!
+//! A fixture is a Modelica file whose comments state the diagnostics it
+//! produces. `//~ ERROR message` expects an error on its line whose message
+//! contains `message`; each `^` of `//~^` moves the expectation a line up, to
+//! annotate a line that can't hold a comment. A line may hold several
+//! annotations, for several diagnostics:
+//!
+//! ```modelica
+//! model M
+//!   Real x;
+//!   Real y;
+//! equation
+//!   y = if x then 1 else 0; //~ ERROR If condition must be Boolean
+//!   der(x) = 1;
+//! end M;
+//! ```
+//!
+//! The severities are `ERROR`, `WARNING`, `INFO` and `HINT`. [`check_fixture`]
+//! compares the diagnostics of a fixture, lint rules included, to its
+//! annotations: every annotation must match a diagnostic, and every error and
+//! warning must be annotated (unannotated information and hints are ignored).
+
+use std::fmt;
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, Uri};
+
+use super::{LspSettings, WorkspaceState, compute_diagnostics};
+
+/// Start of an annotation comment
+const MARKER: &str = "//~";
+
+/// A diagnostic a fixture expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    /// Line of the diagnostic, starting at 0
+    pub line: u32,
+    pub severity: DiagnosticSeverity,
+    /// Text the message of the diagnostic contains
+    pub message: String,
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {}",
+            self.line + 1,
+            severity_name(self.severity),
+            self.message
+        )
+    }
+}
+
+/// The annotations of a fixture, or an error for a malformed annotation
+pub fn parse_expectations(text: &str) -> Result<Vec<Expectation>, String> {
+    let mut expectations = Vec::new();
+    let annotations = text
+        .lines()
+        .enumerate()
+        .flat_map(|(line, content)| content.split(MARKER).skip(1).map(move |a| (line, a)));
+    for (line, annotation) in annotations {
+        let up = annotation.len() - annotation.trim_start_matches('^').len();
+        let (severity, message) = annotation[up..]
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((annotation[up..].trim(), ""));
+        let severity = match severity {
+            "ERROR" => DiagnosticSeverity::ERROR,
+            "WARNING" => DiagnosticSeverity::WARNING,
+            "INFO" => DiagnosticSeverity::INFORMATION,
+            "HINT" => DiagnosticSeverity::HINT,
+            other => {
+                return Err(format!(
+                    "{}: unknown severity '{}' (ERROR, WARNING, INFO or HINT)",
+                    line + 1,
+                    other
+                ));
+            }
+        };
+        let Some(line) = line.checked_sub(up) else {
+            return Err(format!("{}: annotation above the first line", line + 1));
+        };
+        expectations.push(Expectation {
+            line: line as u32,
+            severity,
+            message: message.trim().to_string(),
+        });
+    }
+    Ok(expectations)
+}
+
+/// Compare diagnostics to the annotations of a fixture, returning the
+/// mismatches: annotations no diagnostic matches, and errors and warnings no
+/// annotation expects
+pub fn check_expectations(text: &str, diagnostics: &[Diagnostic]) -> Result<(), Vec<String>> {
+    let expectations = parse_expectations(text).map_err(|e| vec![e])?;
+    let mut unmatched: Vec<&Diagnostic> = diagnostics.iter().collect();
+    let mut mismatches = Vec::new();
+    for expectation in &expectations {
+        let found = unmatched.iter().position(|d| {
+            d.range.start.line == expectation.line
+                && d.severity == Some(expectation.severity)
+                && d.message.contains(&expectation.message)
+        });
+        match found {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => mismatches.push(format!("expected {}", expectation)),
+        }
+    }
+    for diagnostic in unmatched {
+        let severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR);
+        if matches!(
+            severity,
+            DiagnosticSeverity::ERROR | DiagnosticSeverity::WARNING
+        ) {
+            mismatches.push(format!(
+                "unexpected {}: {} {}",
+                diagnostic.range.start.line + 1,
+                severity_name(severity),
+                diagnostic.message
+            ));
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+/// Compute the diagnostics of a fixture, as the language server does with
+/// lint diagnostics enabled, and compare them to its annotations (see
+/// [`check_expectations`])
+pub fn check_fixture(uri: &Uri, text: &str) -> Result<(), Vec<String>> {
+    let mut settings = LspSettings::default();
+    settings.lint.enabled = true;
+    let mut workspace = WorkspaceState::new();
+    workspace.set_settings(settings);
+    workspace.open_document(uri.clone(), text.to_string());
+    let diagnostics = compute_diagnostics(uri, text, &mut workspace);
+    check_expectations(text, &diagnostics)
+}
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "ERROR",
+        DiagnosticSeverity::WARNING => "WARNING",
+        DiagnosticSeverity::INFORMATION => "INFO",
+        _ => "HINT",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, Range};
+
+    fn diagnostic(line: u32, severity: DiagnosticSeverity, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            severity: Some(severity),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_expectations() {
+        let text = "model M\n  Real x; //~ ERROR undefined //~ HINT name\n  Real y;\n//~^ WARNING unused 'y'\nend M;\n";
+        let expectations = parse_expectations(text).unwrap();
+        assert_eq!(
+            expectations,
+            [
+                Expectation {
+                    line: 1,
+                    severity: DiagnosticSeverity::ERROR,
+                    message: "undefined".to_string(),
+                },
+                Expectation {
+                    line: 1,
+                    severity: DiagnosticSeverity::HINT,
+                    message: "name".to_string(),
+                },
+                Expectation {
+                    line: 2,
+                    severity: DiagnosticSeverity::WARNING,
+                    message: "unused 'y'".to_string(),
+                },
+            ]
+        );
+
+        let matching = [
+            diagnostic(1, DiagnosticSeverity::ERROR, "undefined variable 'z'"),
+            diagnostic(2, DiagnosticSeverity::WARNING, "unused 'y'"),
+            diagnostic(1, DiagnosticSeverity::HINT, "short name"),
+            diagnostic(4, DiagnosticSeverity::HINT, "not annotated"),
+        ];
+        assert_eq!(check_expectations(text, &matching), Ok(()));
+
+        let mismatched = [
+            diagnostic(1, DiagnosticSeverity::WARNING, "undefined variable 'z'"),
+            diagnostic(1, DiagnosticSeverity::HINT, "short name"),
+            diagnostic(2, DiagnosticSeverity::WARNING, "unused 'y'"),
+        ];
+        assert_eq!(
+            check_expectations(text, &mismatched),
+            Err(vec![
+                "expected 2: ERROR undefined".to_string(),
+                "unexpected 2: WARNING undefined variable 'z'".to_string(),
+            ])
+        );
+
+        assert_eq!(
+            parse_expectations("model M //~ FATAL oops\nend M;"),
+            Err("1: unknown severity 'FATAL' (ERROR, WARNING, INFO or HINT)".to_string())
+        );
+    }
+}
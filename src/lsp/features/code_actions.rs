@@ -1,20 +1,25 @@
 //! Code Actions handler for Modelica files.
 //!
 //! Provides quick fixes and refactoring suggestions:
+//! - The fixes of lint messages (see [`crate::lint::Fix`]), e.g. prefixing
+//!   an unused variable with an underscore
 //! - Add missing parameter default value
-//! - Remove unused variable
+//! - Declare an undefined variable
+//! - Remove an unused parameter, or a component none of whose connectors is
+//!   connected
 //! - Add missing semicolon (future)
 
 use std::collections::HashMap;
 
 use lsp_types::{
     CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse,
-    Position, Range, TextEdit, Uri, WorkspaceEdit,
+    NumberOrString, Position, Range, TextEdit, Uri, WorkspaceEdit,
 };
 
-use crate::ir::ast::{Expression, Variability};
+use crate::ir::ast::{ClassDefinition, Component, Expression, Variability};
+use crate::lint::{Fix, LintConfig, LintMessage, lint_str};
 
-use crate::lsp::utils::parse_document;
+use crate::lsp::utils::{parse_document, uri_to_path};
 
 /// Handle code action request
 pub fn handle_code_action(
@@ -23,7 +28,7 @@ pub fn handle_code_action(
 ) -> Option<CodeActionResponse> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
     let range = params.range;
 
     let mut actions = Vec::new();
@@ -57,29 +62,22 @@ pub fn handle_code_action(
                     }
                 }
             }
+        }
+    }
 
-            // Check for unused variables (variables that are defined but never used)
-            // This would require more sophisticated analysis - for now, we provide
-            // a remove action for any variable in the range
-            for (comp_name, comp) in &class.components {
-                let comp_line = comp
-                    .type_name
-                    .name
-                    .first()
-                    .map(|t| t.location.start_line.saturating_sub(1))
-                    .unwrap_or(0);
-
-                // Check if this component is in the requested range
-                if comp_line >= range.start.line && comp_line <= range.end.line {
-                    // Only offer remove for non-parameters (variables)
-                    if !matches!(comp.variability, Variability::Parameter(_))
-                        && let Some(action) =
-                            create_remove_variable_action(uri, text, comp_name, comp_line)
-                    {
-                        actions.push(action);
-                    }
-                }
-            }
+    // Offer the fixes of lint messages in the range
+    for msg in lint_str(text, path, &LintConfig::default()).messages {
+        let line = msg.line.saturating_sub(1);
+        if line >= range.start.line
+            && line <= range.end.line
+            && let Some(fix) = &msg.fix
+        {
+            actions.push(create_fix_action(
+                uri,
+                &msg,
+                fix,
+                &params.context.diagnostics,
+            ));
         }
     }
 
@@ -152,46 +150,57 @@ fn create_add_default_action(
     })
 }
 
-/// Create a code action to remove a variable declaration
-fn create_remove_variable_action(
+/// Create a code action applying the fix of a lint message
+fn create_fix_action(
     uri: &Uri,
-    text: &str,
-    var_name: &str,
-    line: u32,
-) -> Option<CodeAction> {
-    let lines: Vec<&str> = text.lines().collect();
-    let _line_text = lines.get(line as usize)?;
-
-    // Remove the entire line
-    let mut changes = HashMap::new();
-    changes.insert(
-        uri.clone(),
-        vec![TextEdit {
+    msg: &LintMessage,
+    fix: &Fix,
+    diagnostics: &[lsp_types::Diagnostic],
+) -> CodeAction {
+    // Fix positions are 1-based, in characters like the handler positions
+    let position = |line: u32, column: u32| Position {
+        line: line.saturating_sub(1),
+        character: column.saturating_sub(1),
+    };
+    let edits = fix
+        .edits
+        .iter()
+        .map(|edit| TextEdit {
             range: Range {
-                start: Position { line, character: 0 },
-                end: Position {
-                    line: line + 1,
-                    character: 0,
-                },
+                start: position(edit.start_line, edit.start_column),
+                end: position(edit.end_line, edit.end_column),
             },
-            new_text: String::new(),
-        }],
-    );
+            new_text: edit.new_text.clone(),
+        })
+        .collect();
+
+    // Attach the diagnostic the lint message was reported as, if the client sent it
+    let fixed: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| {
+            d.code == Some(NumberOrString::String(msg.rule.to_string()))
+                && d.range.start.line + 1 == msg.line
+        })
+        .cloned()
+        .collect();
 
-    Some(CodeAction {
-        title: format!("Remove unused variable '{}'", var_name),
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), edits);
+
+    CodeAction {
+        title: fix.description.clone(),
         kind: Some(CodeActionKind::QUICKFIX),
-        diagnostics: None,
+        diagnostics: (!fixed.is_empty()).then_some(fixed),
         edit: Some(WorkspaceEdit {
             changes: Some(changes),
             document_changes: None,
             change_annotations: None,
         }),
         command: None,
-        is_preferred: Some(false),
+        is_preferred: Some(true),
         disabled: None,
         data: None,
-    })
+    }
 }
 
 /// Create a quick fix based on a diagnostic message
@@ -211,19 +220,27 @@ fn create_quick_fix_for_diagnostic(
         return create_declare_variable_action(uri, text, var_name, diagnostic);
     }
 
-    // Handle "Unused variable" - offer to prefix with underscore
-    if message.starts_with("Unused variable:") {
-        let var_name = message
-            .strip_prefix("Unused variable: '")?
-            .strip_suffix("' is declared but never used")?;
-        return create_prefix_underscore_action(uri, var_name, diagnostic);
-    }
-
     // Handle "Parameter without default"
     if message.contains("has no default value") {
         return create_add_default_from_diagnostic(uri, text, diagnostic);
     }
 
+    // Handle unused parameters and unconnected components - suggest removing them
+    if let Some(name) = message
+        .strip_prefix("Parameter '")
+        .and_then(|m| m.strip_suffix("' is not used in any flattened model"))
+    {
+        let title = format!("Remove unused parameter '{}'", name);
+        return create_remove_declaration_action(uri, text, diagnostic, title);
+    }
+    if let Some((name, _)) = message
+        .strip_prefix("None of the connectors of '")
+        .and_then(|m| m.split_once("' is connected"))
+    {
+        let title = format!("Remove unconnected component '{}'", name);
+        return create_remove_declaration_action(uri, text, diagnostic, title);
+    }
+
     None
 }
 
@@ -293,17 +310,18 @@ fn create_declare_variable_action(
     })
 }
 
-/// Create a code action to prefix an unused variable with underscore
-fn create_prefix_underscore_action(
+/// Create a code action to add default value from a diagnostic
+fn create_add_default_from_diagnostic(
     uri: &Uri,
-    var_name: &str,
+    text: &str,
     diagnostic: &lsp_types::Diagnostic,
 ) -> Option<CodeAction> {
     let line = diagnostic.range.start.line;
-    let start_char = diagnostic.range.start.character;
-    let end_char = diagnostic.range.end.character;
+    let lines: Vec<&str> = text.lines().collect();
+    let line_text = lines.get(line as usize)?;
 
-    let new_name = format!("_{}", var_name);
+    // Find the semicolon
+    let semicolon_pos = line_text.rfind(';')?;
 
     let mut changes = HashMap::new();
     changes.insert(
@@ -312,19 +330,19 @@ fn create_prefix_underscore_action(
             range: Range {
                 start: Position {
                     line,
-                    character: start_char,
+                    character: semicolon_pos as u32,
                 },
                 end: Position {
                     line,
-                    character: end_char,
+                    character: semicolon_pos as u32,
                 },
             },
-            new_text: new_name.clone(),
+            new_text: " = 0".to_string(),
         }],
     );
 
     Some(CodeAction {
-        title: format!("Rename to '{}' to suppress warning", new_name),
+        title: "Add default value".to_string(),
         kind: Some(CodeActionKind::QUICKFIX),
         diagnostics: Some(vec![diagnostic.clone()]),
         edit: Some(WorkspaceEdit {
@@ -333,45 +351,111 @@ fn create_prefix_underscore_action(
             change_annotations: None,
         }),
         command: None,
-        is_preferred: Some(false),
+        is_preferred: Some(true),
         disabled: None,
         data: None,
     })
 }
 
-/// Create a code action to add default value from a diagnostic
-fn create_add_default_from_diagnostic(
+/// Create a code action removing the declaration of the component whose name
+/// is at the start of a diagnostic.
+///
+/// Only declarations of a single component, on lines of their own, are removed.
+fn create_remove_declaration_action(
     uri: &Uri,
     text: &str,
     diagnostic: &lsp_types::Diagnostic,
+    title: String,
 ) -> Option<CodeAction> {
-    let line = diagnostic.range.start.line;
-    let lines: Vec<&str> = text.lines().collect();
-    let line_text = lines.get(line as usize)?;
+    let ast = parse_document(text, &uri_to_path(uri))?;
+    let position = diagnostic.range.start;
+    let comp = ast
+        .class_list
+        .values()
+        .find_map(|class| find_component_at(class, position))?;
+
+    // The declaration starts at its type name, after its prefixes
+    let start = comp.location.start as usize;
+    let line_start = text.get(..start)?.rfind('\n').map_or(0, |i| i + 1);
+    const PREFIXES: &[&str] = &[
+        "parameter",
+        "constant",
+        "discrete",
+        "input",
+        "output",
+        "flow",
+        "stream",
+        "final",
+        "inner",
+        "outer",
+        "replaceable",
+        "redeclare",
+        "each",
+    ];
+    if !text[line_start..start]
+        .split_whitespace()
+        .all(|word| PREFIXES.contains(&word))
+    {
+        return None;
+    }
 
-    // Find the semicolon
-    let semicolon_pos = line_text.rfind(';')?;
+    // Find the end of the declaration, which must not declare other components
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut end = None;
+    for (i, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => return None,
+            ';' if depth == 0 => {
+                end = Some(start + i + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let end = end?;
+    let rest = &text[end..];
+    let line_end = rest.find('\n').map_or(text.len(), |i| end + i + 1);
+    let trailing = text[end..line_end].trim();
+    if !(trailing.is_empty() || trailing.starts_with("//")) {
+        return None;
+    }
 
+    let line = |offset: usize| text[..offset].matches('\n').count() as u32;
     let mut changes = HashMap::new();
     changes.insert(
         uri.clone(),
         vec![TextEdit {
             range: Range {
                 start: Position {
-                    line,
-                    character: semicolon_pos as u32,
+                    line: line(line_start),
+                    character: 0,
                 },
                 end: Position {
-                    line,
-                    character: semicolon_pos as u32,
+                    line: line(line_end),
+                    character: (line_end - text[..line_end].rfind('\n').map_or(0, |i| i + 1))
+                        as u32,
                 },
             },
-            new_text: " = 0".to_string(),
+            new_text: String::new(),
         }],
     );
 
     Some(CodeAction {
-        title: "Add default value".to_string(),
+        title,
         kind: Some(CodeActionKind::QUICKFIX),
         diagnostics: Some(vec![diagnostic.clone()]),
         edit: Some(WorkspaceEdit {
@@ -380,8 +464,26 @@ fn create_add_default_from_diagnostic(
             change_annotations: None,
         }),
         command: None,
-        is_preferred: Some(true),
+        is_preferred: Some(false),
         disabled: None,
         data: None,
     })
 }
+
+/// Find the component of a class (or a nested class) whose name is at a position
+fn find_component_at(class: &ClassDefinition, position: Position) -> Option<&Component> {
+    class
+        .components
+        .values()
+        .find(|comp| {
+            let location = &comp.name_token.location;
+            location.start_line == position.line + 1
+                && location.start_column == position.character + 1
+        })
+        .or_else(|| {
+            class
+                .classes
+                .values()
+                .find_map(|nested| find_component_at(nested, position))
+        })
+}
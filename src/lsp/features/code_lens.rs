@@ -1,17 +1,18 @@
 //! Code Lens handler for Modelica files.
 //!
 //! Provides inline actionable information:
-//! - Balance status for models/blocks (states, unknowns, equations)
+//! - Balance status for models/blocks (states, unknowns, equations), and the
+//!   structural DAE index of balanced models
 //! - Reference counts for classes, functions, and variables
 //! - "Extends" information for models
 
 use lsp_types::{CodeLens, CodeLensParams, Command, Position, Range, Uri};
 
-use crate::dae::balance::BalanceStatus;
+use crate::dae::balance::{BalanceStatus, DaeIndex};
 use crate::ir::ast::{ClassDefinition, ClassType, StoredDefinition};
 
 use crate::lsp::WorkspaceState;
-use crate::lsp::utils::parse_document;
+use crate::lsp::utils::{parse_document, uri_to_path};
 
 /// Handle code lens request
 pub fn handle_code_lens(
@@ -20,7 +21,7 @@ pub fn handle_code_lens(
 ) -> Option<Vec<CodeLens>> {
     let uri = &params.text_document.uri;
     let text = workspace.get_document(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let mut lenses = Vec::new();
 
@@ -64,13 +65,23 @@ fn collect_class_lenses(
     if matches!(
         class.class_type,
         ClassType::Model | ClassType::Block | ClassType::Class | ClassType::Connector
-    ) && let Some(balance) = workspace.get_balance(uri, &class_path)
+    ) && workspace.settings().balance_lens
+        && let Some(balance) = workspace.get_balance(uri, &class_path)
     {
         let title = match &balance.status {
-            BalanceStatus::Balanced => format!(
-                "{} states, {} unknowns, {} equations [✓]",
-                balance.num_states, balance.num_unknowns, balance.num_equations
-            ),
+            BalanceStatus::Balanced => match &balance.dae_index {
+                Some(index) => format!(
+                    "{} states, {} unknowns, {} equations [✓] {}",
+                    balance.num_states,
+                    balance.num_unknowns,
+                    balance.num_equations,
+                    index.description()
+                ),
+                None => format!(
+                    "{} states, {} unknowns, {} equations [✓]",
+                    balance.num_states, balance.num_unknowns, balance.num_equations
+                ),
+            },
             BalanceStatus::Partial => format!(
                 "{} states, {} unknowns, {} equations [◐ partial]",
                 balance.num_states, balance.num_unknowns, balance.num_equations
@@ -107,6 +118,30 @@ fn collect_class_lenses(
             }),
             data: None,
         });
+
+        // List the equations index reduction has to differentiate
+        if let Some(index) = &balance.dae_index
+            && index.is_high_index()
+        {
+            lenses.push(CodeLens {
+                range: Range {
+                    start: Position {
+                        line: class_line,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: class_line,
+                        character: 0,
+                    },
+                },
+                command: Some(Command {
+                    title: index_reduction_title(index),
+                    command: String::new(),
+                    arguments: None,
+                }),
+                data: None,
+            });
+        }
     }
     // No fallback "Analyze" button - balance is computed automatically
 
@@ -206,6 +241,27 @@ fn collect_class_lenses(
     }
 }
 
+/// Maximum number of equations listed in the index reduction lens
+const MAX_LISTED_CONSTRAINTS: usize = 3;
+
+/// Title of the index reduction lens, e.g.
+/// "index reduction: x ^ 2 + y ^ 2 = L ^ 2 (2×), der(x) = vx (1×)"
+fn index_reduction_title(index: &DaeIndex) -> String {
+    let mut listed: Vec<String> = index
+        .constraints
+        .iter()
+        .take(MAX_LISTED_CONSTRAINTS)
+        .map(|c| format!("{} ({}×)", c.equation, c.differentiations))
+        .collect();
+    if index.constraints.len() > MAX_LISTED_CONSTRAINTS {
+        listed.push(format!(
+            "{} more",
+            index.constraints.len() - MAX_LISTED_CONSTRAINTS
+        ));
+    }
+    format!("index reduction: {}", listed.join(", "))
+}
+
 /// Count references to a name in the document (usages only, not declarations)
 fn count_references(name: &str, _text: &str, ast: &StoredDefinition) -> usize {
     let mut count = 0;
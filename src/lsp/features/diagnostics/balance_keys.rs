@@ -0,0 +1,228 @@
+//! Keys of cached balance results.
+//!
+//! The balance of a class depends on its own source, on the classes it uses
+//! and on the declarations of its enclosing classes (e.g. a package constant
+//! used as an array size). The key of a class hashes these sources, so a
+//! class whose key is unchanged after an edit of the document can reuse its
+//! cached balance.
+//!
+//! The classes a class uses are found conservatively: every class of the
+//! document whose name appears as an identifier in the source of the class,
+//! transitively. The source of a class excludes its nested classes, which
+//! are only part of the key when used, and whitespace outside string
+//! literals is collapsed, so whitespace edits keep the keys.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::ir::ast::{ClassDefinition, StoredDefinition};
+use crate::lsp::index_cache::content_hash;
+
+/// A class of the document and its source
+struct ClassSource {
+    path: String,
+    /// Index of the enclosing class
+    parent: Option<usize>,
+    /// Class prefixes and source, without nested classes
+    source: String,
+    /// Identifiers in the source
+    identifiers: HashSet<String>,
+}
+
+/// Key of each class of the document, by class path.
+///
+/// Classes whose source can't be located in `text` have no key.
+pub(super) fn balance_keys(text: &str, ast: &StoredDefinition) -> HashMap<String, String> {
+    let mut classes = Vec::new();
+    for (name, class) in &ast.class_list {
+        collect_sources(text, class, name.clone(), None, &mut classes);
+    }
+
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, class) in classes.iter().enumerate() {
+        let name = class.path.rsplit('.').next().unwrap_or(&class.path);
+        by_name.entry(name).or_default().push(i);
+    }
+
+    let within = ast
+        .within
+        .as_ref()
+        .map(|name| {
+            name.name
+                .iter()
+                .map(|t| t.text.as_str())
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .unwrap_or_default();
+
+    let mut keys = HashMap::new();
+    for (i, class) in classes.iter().enumerate() {
+        // Classes the balance depends on, sorted for a stable key
+        let mut dependencies = BTreeSet::new();
+        let mut pending = vec![i];
+        let mut located = true;
+        while let Some(j) = pending.pop() {
+            if !dependencies.insert(j) {
+                continue;
+            }
+            let Some(dependency) = classes.get(j) else {
+                continue;
+            };
+            located &= !dependency.source.is_empty();
+            pending.extend(dependency.parent);
+            for identifier in &dependency.identifiers {
+                if let Some(used) = by_name.get(identifier.as_str()) {
+                    pending.extend(used);
+                }
+            }
+        }
+        if !located {
+            continue;
+        }
+
+        let mut key = format!("within {}\n", within);
+        for j in dependencies {
+            key.push_str(&classes[j].path);
+            key.push('\n');
+            key.push_str(&classes[j].source);
+            key.push('\n');
+        }
+        keys.insert(class.path.clone(), content_hash(&key));
+    }
+    keys
+}
+
+/// Collect the sources of a class and its nested classes
+fn collect_sources(
+    text: &str,
+    class: &ClassDefinition,
+    path: String,
+    parent: Option<usize>,
+    classes: &mut Vec<ClassSource>,
+) {
+    let source = own_source(text, class)
+        .map(|source| {
+            format!(
+                "{}{:?}{}",
+                if class.partial { "partial " } else { "" },
+                class.class_type,
+                source
+            )
+        })
+        .unwrap_or_default();
+    let identifiers = identifiers(&source);
+    let index = classes.len();
+    classes.push(ClassSource {
+        path: path.clone(),
+        parent,
+        source,
+        identifiers,
+    });
+    for (name, nested) in &class.classes {
+        collect_sources(
+            text,
+            nested,
+            format!("{}.{}", path, name),
+            Some(index),
+            classes,
+        );
+    }
+}
+
+/// Source of a class with its nested classes cut out
+fn own_source(text: &str, class: &ClassDefinition) -> Option<String> {
+    let span = |c: &ClassDefinition| (c.location.start as usize, c.location.end as usize);
+    let (start, end) = span(class);
+    let mut nested: Vec<(usize, usize)> = class.classes.values().map(span).collect();
+    nested.sort_unstable();
+
+    let mut source = String::new();
+    let mut pos = start;
+    for (nested_start, nested_end) in nested {
+        if nested_start < pos || nested_end > end {
+            return None;
+        }
+        source.push_str(text.get(pos..nested_start)?);
+        pos = nested_end;
+    }
+    source.push_str(text.get(pos..end)?);
+    Some(collapse_whitespace(&source))
+}
+
+/// Replace whitespace outside string literals by single spaces
+fn collapse_whitespace(source: &str) -> String {
+    let mut collapsed = String::with_capacity(source.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut space = false;
+    for c in source.chars() {
+        if in_string {
+            collapsed.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                collapsed.push(' ');
+                space = false;
+            }
+            in_string = c == '"';
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// Identifiers (and keywords) in a source
+fn identifiers(source: &str) -> HashSet<String> {
+    source
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    const SOURCE: &str = "package P\n  constant Integer n = 2;\n  model A\n    Real x[n];\n  equation\n    der(x) = -x;\n  end A;\n  model B\n    extends A;\n  end B;\n  model C\n    Real y;\n  equation\n    y = 1;\n  end C;\nend P;\n";
+
+    fn keys(text: &str) -> HashMap<String, String> {
+        balance_keys(text, &parse_source_simple(text, "test.mo").unwrap())
+    }
+
+    #[test]
+    fn test_balance_keys() {
+        let before = keys(SOURCE);
+        assert_eq!(before.len(), 4);
+
+        // Editing A changes the keys of A and of B, which extends it
+        let after = keys(&SOURCE.replace("der(x) = -x;", "der(x) = -2 * x;"));
+        assert_ne!(before["P.A"], after["P.A"]);
+        assert_ne!(before["P.B"], after["P.B"]);
+        assert_eq!(before["P.C"], after["P.C"]);
+
+        // Declarations of the package are used by all its classes
+        let after = keys(&SOURCE.replace("n = 2", "n = 3"));
+        assert!(before.keys().all(|class| before[class] != after[class]));
+
+        // Whitespace edits and moving a class don't change the keys
+        let after = keys(&SOURCE.replace("  model C", "\n\n  model  C"));
+        assert_eq!(before, after);
+        assert_eq!(
+            collapse_whitespace("x  =\n \"a  b\\\"  c\"  ;"),
+            "x = \"a  b\\\"  c\" ;"
+        );
+
+        // The class prefixes are part of the key
+        let after = keys(&SOURCE.replace("  model C", "  partial model C"));
+        assert_ne!(before["P.C"], after["P.C"]);
+    }
+}
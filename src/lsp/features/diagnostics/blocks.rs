@@ -0,0 +1,246 @@
+//! BLT blocks of the equations of the document's classes.
+//!
+//! The BLT results of the compiled classes (see [`BltResult`]) are keyed by
+//! equation, i.e. the class of the document declaring an equation and the
+//! index of the equation in its equation section, so hovers over equations of
+//! cached classes stay valid when edits move the equations.
+
+use std::collections::HashMap;
+
+use crate::compiler::paths::base_name;
+use crate::ir::ast::{
+    ClassDefinition, ComponentReference, Equation, Expression, Location, StoredDefinition,
+};
+use crate::ir::structural::BltResult;
+use crate::ir::visitor::{Visitable, Visitor};
+
+/// An equation of the document: class path and index of the equation in the class
+pub type EquationRef = (String, usize);
+
+/// How BLT solved a scalar equation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolvedEquation {
+    /// The variable the equation is solved for, if it was matched to one
+    pub variable: Option<String>,
+    /// Index of the block in the sorted blocks, starting at 0
+    pub block: usize,
+    /// Number of equations of the block, more than one for an algebraic loop
+    pub size: usize,
+}
+
+/// BLT blocks of the equations of a compiled class, by declared equation
+#[derive(Debug, Clone, Default)]
+pub struct BltBlocks {
+    /// Number of blocks of the compiled class
+    pub blocks: usize,
+    equations: Vec<(EquationRef, SolvedEquation)>,
+}
+
+impl BltBlocks {
+    /// The scalar equations of a declared equation, e.g. one per element of
+    /// an array equation or per iteration of a for-equation
+    pub fn get<'a>(
+        &'a self,
+        equation: &'a EquationRef,
+    ) -> impl Iterator<Item = &'a SolvedEquation> {
+        self.equations
+            .iter()
+            .filter(move |(eq, _)| eq == equation)
+            .map(|(_, solved)| solved)
+    }
+}
+
+/// Equations of a document, by the location of their first token
+pub(super) struct Equations {
+    /// File name of the document, as kept by token locations
+    file_name: Option<String>,
+    by_location: HashMap<(u32, u32), EquationRef>,
+}
+
+impl Equations {
+    pub(super) fn new(ast: &StoredDefinition, path: &str) -> Self {
+        let mut equations = Self {
+            file_name: Some(base_name(path).to_string()),
+            by_location: HashMap::new(),
+        };
+        for (name, class) in &ast.class_list {
+            equations.collect(class, name);
+        }
+        equations
+    }
+
+    fn collect(&mut self, class: &ClassDefinition, class_path: &str) {
+        for (index, eq) in class.equations.iter().enumerate() {
+            self.insert(eq, &(class_path.to_string(), index));
+        }
+        for (name, nested) in &class.classes {
+            self.collect(nested, &format!("{}.{}", class_path, name));
+        }
+    }
+
+    /// Record an equation and the equations nested in it, which become
+    /// separate equations when flattened
+    fn insert(&mut self, eq: &Equation, equation: &EquationRef) {
+        if let Some(location) = eq.get_location() {
+            self.by_location
+                .entry((location.start_line, location.start_column))
+                .or_insert_with(|| equation.clone());
+        }
+        match eq {
+            Equation::For { equations, .. } => {
+                for eq in equations {
+                    self.insert(eq, equation);
+                }
+            }
+            Equation::If {
+                cond_blocks,
+                else_block,
+                ..
+            } => {
+                for eq in cond_blocks
+                    .iter()
+                    .flat_map(|block| &block.eqs)
+                    .chain(else_block.iter().flatten())
+                {
+                    self.insert(eq, equation);
+                }
+            }
+            Equation::When { blocks, .. } => {
+                for eq in blocks.iter().flat_map(|block| &block.eqs) {
+                    self.insert(eq, equation);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The equation of the document at a location
+    fn get(&self, location: &Location) -> Option<EquationRef> {
+        if self.file_name.as_deref() != Some(location.file_name.as_str()) {
+            return None;
+        }
+        self.by_location
+            .get(&(location.start_line, location.start_column))
+            .cloned()
+    }
+
+    /// The blocks of the equations of the document in a BLT result
+    pub(super) fn blocks(&self, blt: &BltResult) -> BltBlocks {
+        let equations = blt
+            .sccs
+            .iter()
+            .enumerate()
+            .flat_map(|(block, scc)| scc.iter().map(move |&index| (block, scc.len(), index)))
+            .filter_map(|(block, size, index)| {
+                let location = blt.locations.get(index)?.as_ref()?;
+                Some((
+                    self.get(location)?,
+                    SolvedEquation {
+                        variable: blt.matching.get(&index).cloned(),
+                        block,
+                        size,
+                    },
+                ))
+            })
+            .collect();
+        BltBlocks {
+            blocks: blt.sccs.len(),
+            equations,
+        }
+    }
+}
+
+/// The innermost class of a document containing a position (1-indexed), and
+/// its path
+pub fn class_at(ast: &StoredDefinition, line: u32, col: u32) -> Option<(String, &ClassDefinition)> {
+    fn find(
+        class: &ClassDefinition,
+        class_path: String,
+        line: u32,
+        col: u32,
+    ) -> Option<(String, &ClassDefinition)> {
+        if !crate::ir::structural::location::position_in_location(&class.location, line, col) {
+            return None;
+        }
+        class
+            .classes
+            .iter()
+            .find_map(|(name, nested)| find(nested, format!("{}.{}", class_path, name), line, col))
+            .or(Some((class_path, class)))
+    }
+    ast.class_list
+        .iter()
+        .find_map(|(name, class)| find(class, name.clone(), line, col))
+}
+
+/// The equation of a class at a position (1-indexed): the last equation
+/// starting at or before the position among those whose lines contain it
+pub fn equation_at(class: &ClassDefinition, line: u32, col: u32) -> Option<usize> {
+    let candidates: Vec<(usize, (u32, u32))> = class
+        .equations
+        .iter()
+        .enumerate()
+        .filter_map(|(index, eq)| {
+            let mut span = TokenSpan::default();
+            eq.accept(&mut span);
+            let (start, end) = (span.start?, span.end?);
+            (start.0 <= line && line <= end.0).then_some((index, start))
+        })
+        .collect();
+    candidates
+        .iter()
+        .rev()
+        .find(|(_, start)| *start <= (line, col))
+        .or(candidates.first())
+        .map(|(index, _)| *index)
+}
+
+/// First and last token positions (line, column) of an equation
+#[derive(Default)]
+struct TokenSpan {
+    start: Option<(u32, u32)>,
+    end: Option<(u32, u32)>,
+}
+
+impl TokenSpan {
+    fn insert(&mut self, location: &Location) {
+        let start = (location.start_line, location.start_column);
+        let end = (location.end_line, location.end_column);
+        self.start = Some(self.start.map_or(start, |s| s.min(start)));
+        self.end = Some(self.end.map_or(end, |e| e.max(end)));
+    }
+}
+
+impl Visitor for TokenSpan {
+    fn enter_expression(&mut self, node: &Expression) {
+        if let Expression::Terminal { token, .. } = node {
+            self.insert(&token.location);
+        }
+    }
+
+    fn enter_component_reference(&mut self, node: &ComponentReference) {
+        for part in &node.parts {
+            self.insert(&part.ident.location);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_source_simple;
+
+    #[test]
+    fn test_equation_at() {
+        let source = "package P\n  model M\n    Real x, y;\n  equation\n    der(x) = -x +\n      y;\n    y = 2 * x; x = y;\n  end M;\nend P;\n";
+        let def = parse_source_simple(source, "test.mo").unwrap();
+        let (path, class) = class_at(&def, 5, 5).unwrap();
+        assert_eq!(path, "P.M");
+        assert_eq!(equation_at(class, 5, 5), Some(0));
+        assert_eq!(equation_at(class, 6, 7), Some(0));
+        assert_eq!(equation_at(class, 7, 2), Some(1));
+        assert_eq!(equation_at(class, 7, 16), Some(2));
+        assert_eq!(equation_at(class, 3, 5), None);
+        assert_eq!(class_at(&def, 1, 10).unwrap().0, "P");
+    }
+}
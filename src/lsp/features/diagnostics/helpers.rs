@@ -1,9 +1,13 @@
 //! Helper functions for diagnostics.
 
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
 
-/// Create a diagnostic at a specific location
+/// Create a diagnostic at a specific location.
+///
+/// `code` identifies the kind of diagnostic (a lint rule name or one of
+/// [`DIAGNOSTIC_CODES`](crate::lint::DIAGNOSTIC_CODES)), for suppressions.
 pub fn create_diagnostic(
+    code: &str,
     line: u32,
     col: u32,
     message: String,
@@ -21,6 +25,7 @@ pub fn create_diagnostic(
             },
         },
         severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_string())),
         source: Some("rumoca".to_string()),
         message,
         ..Default::default()
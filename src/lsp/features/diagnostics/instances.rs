@@ -0,0 +1,217 @@
+//! Unused parameters and unconnected connectors of the document's classes.
+//!
+//! The instance checks of the compiled classes (see [`InstanceCheck`]) are
+//! keyed by declaration, i.e. the class of the document declaring a component
+//! and the component name, so the uses of cached classes stay valid when edits
+//! move their declarations. A parameter is reported if no compiled class uses
+//! it, and a connector of a sub-component if no compiled class connects it.
+//! Causality errors of signal connectors are reported at the declaration of
+//! the sub-component containing the connector, for each compiled class.
+
+use std::collections::HashMap;
+
+use indexmap::{IndexMap, IndexSet};
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::compiler::paths::base_name;
+use crate::ir::analysis::instance_check::InstanceCheck;
+use crate::ir::ast::{ClassDefinition, Location, StoredDefinition};
+
+use super::helpers::create_diagnostic;
+
+/// A component declaration of the document: class path and component name
+type Declaration = (String, String);
+
+/// Parameter and connector uses of a compiled class, by declaration
+#[derive(Debug, Clone, Default)]
+pub struct InstanceUses {
+    /// Parameter declarations and whether they are used
+    parameters: Vec<(Declaration, bool)>,
+    /// Sub-component declarations, connector names and whether they are connected
+    connectors: Vec<(Declaration, String, bool)>,
+    /// Sub-component declarations, connector names and causality errors
+    causality: Vec<(Declaration, String, String)>,
+}
+
+/// Component declarations of a document, by the location of their name
+pub(super) struct Declarations {
+    /// File name of the document, as kept by token locations
+    file_name: Option<String>,
+    by_location: HashMap<(u32, u32), Declaration>,
+    locations: HashMap<Declaration, Location>,
+}
+
+impl Declarations {
+    pub(super) fn new(ast: &StoredDefinition, path: &str) -> Self {
+        let mut declarations = Self {
+            file_name: Some(base_name(path).to_string()),
+            by_location: HashMap::new(),
+            locations: HashMap::new(),
+        };
+        for (name, class) in &ast.class_list {
+            declarations.collect(class, name);
+        }
+        declarations
+    }
+
+    fn collect(&mut self, class: &ClassDefinition, class_path: &str) {
+        for (name, comp) in &class.components {
+            let location = &comp.name_token.location;
+            let declaration = (class_path.to_string(), name.clone());
+            self.by_location.insert(
+                (location.start_line, location.start_column),
+                declaration.clone(),
+            );
+            self.locations.insert(declaration, location.clone());
+        }
+        for (name, nested) in &class.classes {
+            self.collect(nested, &format!("{}.{}", class_path, name));
+        }
+    }
+
+    /// The declaration of the document at a location
+    fn get(&self, location: &Location) -> Option<Declaration> {
+        if self.file_name.as_deref() != Some(location.file_name.as_str()) {
+            return None;
+        }
+        self.by_location
+            .get(&(location.start_line, location.start_column))
+            .cloned()
+    }
+
+    /// The uses of the declarations of the document in an instance check
+    pub(super) fn uses(&self, check: &InstanceCheck) -> InstanceUses {
+        let parameters = check
+            .parameters
+            .iter()
+            .filter_map(|parameter| Some((self.get(&parameter.location)?, parameter.used)))
+            .collect();
+        let connectors = check
+            .connectors
+            .iter()
+            .filter_map(|connector| {
+                let name = connector.name.strip_prefix(&connector.component)?;
+                Some((
+                    self.get(&connector.location)?,
+                    name.trim_start_matches('.').to_string(),
+                    connector.connected,
+                ))
+            })
+            .collect();
+        let causality = check
+            .causality
+            .iter()
+            .filter_map(|error| {
+                let name = error.name.strip_prefix(&error.component)?;
+                Some((
+                    self.get(&error.location)?,
+                    name.trim_start_matches('.').to_string(),
+                    error.message.clone(),
+                ))
+            })
+            .collect();
+        InstanceUses {
+            parameters,
+            connectors,
+            causality,
+        }
+    }
+}
+
+/// Report the parameters no class uses, the connectors no class connects and
+/// the causality errors of connectors
+pub(super) fn instance_diagnostics<'a>(
+    uses: impl IntoIterator<Item = &'a InstanceUses>,
+    declarations: &Declarations,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut parameters: IndexMap<&Declaration, bool> = IndexMap::new();
+    let mut components: IndexMap<&Declaration, IndexMap<&str, bool>> = IndexMap::new();
+    let mut causality: IndexSet<&(Declaration, String, String)> = IndexSet::new();
+    for uses in uses {
+        causality.extend(&uses.causality);
+        for (declaration, used) in &uses.parameters {
+            *parameters.entry(declaration).or_default() |= *used;
+        }
+        for (declaration, connector, connected) in &uses.connectors {
+            *components
+                .entry(declaration)
+                .or_default()
+                .entry(connector.as_str())
+                .or_default() |= *connected;
+        }
+    }
+
+    for (declaration, used) in parameters {
+        let Some(location) = declarations.locations.get(declaration) else {
+            continue;
+        };
+        if !used {
+            diagnostics.push(create_diagnostic(
+                "unused-parameter",
+                location.start_line,
+                location.start_column,
+                format!(
+                    "Parameter '{}' is not used in any flattened model",
+                    declaration.1
+                ),
+                DiagnosticSeverity::WARNING,
+            ));
+        }
+    }
+
+    for (declaration, connectors) in components {
+        let Some(location) = declarations.locations.get(declaration) else {
+            continue;
+        };
+        let component = &declaration.1;
+        let unconnected: Vec<&str> = connectors
+            .iter()
+            .filter(|(_, connected)| !**connected)
+            .map(|(connector, _)| *connector)
+            .collect();
+        // Unconnected inputs with a causality error are only reported as such
+        let has_error = |connector: &str| {
+            causality
+                .iter()
+                .any(|(d, name, _)| d == declaration && name == connector)
+        };
+        let messages = if unconnected.len() == connectors.len() {
+            vec![format!(
+                "None of the connectors of '{}' is connected: {}",
+                component,
+                unconnected.join(", ")
+            )]
+        } else {
+            unconnected
+                .iter()
+                .filter(|connector| !has_error(connector))
+                .map(|connector| {
+                    format!("Connector '{}.{}' is not connected", component, connector)
+                })
+                .collect()
+        };
+        for message in messages {
+            diagnostics.push(create_diagnostic(
+                "unconnected-connector",
+                location.start_line,
+                location.start_column,
+                message,
+                DiagnosticSeverity::WARNING,
+            ));
+        }
+    }
+
+    for (declaration, _, message) in causality {
+        let Some(location) = declarations.locations.get(declaration) else {
+            continue;
+        };
+        diagnostics.push(create_diagnostic(
+            "connector-causality",
+            location.start_line,
+            location.start_column,
+            message.clone(),
+            DiagnosticSeverity::ERROR,
+        ));
+    }
+}
@@ -5,30 +5,62 @@
 //! - Compilation errors
 //! - Undefined variable references
 //! - Unused variable warnings
+//! - Components shadowing built-ins, e.g. `Real time;`
 //! - Missing parameter default warnings
 //! - Type mismatch detection
 //! - Array dimension warnings
+//! - Division by zero at the initial point
+//! - Parameters no flattened model uses, and connectors of sub-components no
+//!   connect equation refers to
+//! - Signal connectors violating the causality rules of connections, e.g.
+//!   inputs of blocks connected to two outputs or to none
+//! - Equations index reduction has to differentiate, in high-index models
+//! - `modelica://` URIs naming no file of the library paths
+//! - Lint messages (when enabled in the workspace settings)
+//!
+//! Diagnostics carry a code (the lint rule name, or e.g. `type-error`) and can be
+//! suppressed with `// rumoca-ignore: <code>` comments or `__rumoca_ignore`
+//! annotations (see [`Suppressions`]).
 //!
 //! This module uses canonical scope resolution functions from
 //! `crate::ir::transform::scope_resolver` to avoid duplication.
 
+mod balance_keys;
+mod blocks;
 mod helpers;
+mod instances;
+mod resources;
 mod symbols;
 
+pub use blocks::{BltBlocks, EquationRef, SolvedEquation, class_at, equation_at};
+pub use instances::InstanceUses;
+
 use std::collections::{HashMap, HashSet};
 
 use indexmap::IndexMap;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Uri};
-use rayon::prelude::*;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Uri};
 
+use crate::compiler::diagnostics::shadowed_builtin_message;
 use crate::compiler::extract_parse_error;
+use crate::compiler::paths::base_name;
 use crate::dae::balance::{BalanceResult, BalanceStatus};
-use crate::ir::analysis::symbols::{DefinedSymbol, is_class_instance_type};
+use crate::ir::analysis::division_check::find_zero_divisions;
+use crate::ir::analysis::symbols::{DefinedSymbol, add_record_fields, is_class_instance_type};
+use crate::ir::analysis::when_check::check_when_equations;
 use crate::ir::ast::{Causality, ClassDefinition, ClassType};
 use crate::ir::transform::constants::global_builtins;
 use crate::ir::transform::scope_resolver::collect_inherited_components;
 
+use crate::lint::{LintConfig, LintLevel, Suppressions, lint_str};
 use crate::lsp::WorkspaceState;
+use crate::lsp::stats::{CompileStats, DiagnosticCounts, millis};
+use crate::lsp::utils::uri_to_path;
+
+// Use web_time on WASM for Instant::now() polyfill
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 use crate::ir::analysis::type_checker;
 use helpers::create_diagnostic;
@@ -38,36 +70,63 @@ use symbols::{
 };
 
 /// Compute diagnostics for a document
+///
+/// The statistics of the pass are recorded in the workspace, see
+/// [`WorkspaceState::compile_stats`].
 pub fn compute_diagnostics(
     uri: &Uri,
     text: &str,
     workspace: &mut WorkspaceState,
 ) -> Vec<Diagnostic> {
+    let start = Instant::now();
     let mut diagnostics = Vec::new();
+    let mut stats = CompileStats::new(uri.clone());
 
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
     if path.ends_with(".mo") {
         use crate::modelica_grammar::ModelicaGrammar;
         use crate::modelica_parser::parse;
 
+        let normalized = crate::compiler::normalize_source(text);
+        let text = normalized.as_ref();
         let mut grammar = ModelicaGrammar::new();
-        match parse(text, path, &mut grammar) {
+        let parse_start = Instant::now();
+        let parsed = parse(text, path, &mut grammar);
+        stats.parse_ms = millis(parse_start.elapsed());
+        match parsed {
             Ok(_) => {
-                // Parsing succeeded - clear stale balance cache since document changed
-                workspace.clear_balances(uri);
-
                 if let Some(ref ast) = grammar.modelica {
                     // Compile each class using the full Compiler pipeline (with library access)
                     // This gives us both the flattened class (for semantic analysis) and balance
-                    compile_and_analyze_classes(uri, text, path, ast, workspace, &mut diagnostics);
+                    compile_and_analyze_classes(
+                        uri,
+                        text,
+                        path,
+                        ast,
+                        workspace,
+                        &mut diagnostics,
+                        &mut stats,
+                    );
+                } else {
+                    workspace.clear_balances(uri);
+                }
+
+                if let Some(config) = workspace.settings().lint_config(path) {
+                    lint_diagnostics(text, path, &config, &mut diagnostics);
+                }
+
+                if let Some(ref ast) = grammar.modelica {
+                    suppress_diagnostics(&Suppressions::new(text, ast), &mut diagnostics);
                 }
             }
             Err(e) => {
-                // Clear cached balance on parse error
-                workspace.clear_balances(uri);
+                // Hide cached balances on parse error, keeping them for reuse
+                // once the document parses again
+                workspace.hide_balances(uri);
                 // Use compiler's error extraction for consistent error messages
                 let (line, col, message) = extract_parse_error(&e, text);
                 diagnostics.push(create_diagnostic(
+                    "parse-error",
                     line,
                     col,
                     message,
@@ -77,9 +136,66 @@ pub fn compute_diagnostics(
         }
     }
 
+    workspace.record_diagnostics(uri, text, &diagnostics);
+    stats.diagnostics = DiagnosticCounts::new(&diagnostics);
+    stats.total_ms = millis(start.elapsed());
+    workspace.record_compile_stats(stats);
     diagnostics
 }
 
+/// Append lint messages for a document as diagnostics
+fn lint_diagnostics(
+    text: &str,
+    path: &str,
+    config: &LintConfig,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let result = lint_str(text, path, config);
+    // Division by zero and unknown suppressions are always reported by the semantic analysis
+    for msg in result.messages.iter().filter(|m| {
+        config.should_report(m) && !matches!(m.rule, "division-by-zero" | "unknown-suppression")
+    }) {
+        let severity = match msg.level {
+            LintLevel::Error => DiagnosticSeverity::ERROR,
+            LintLevel::Warning => DiagnosticSeverity::WARNING,
+            LintLevel::Note => DiagnosticSeverity::INFORMATION,
+            LintLevel::Help => DiagnosticSeverity::HINT,
+        };
+        let mut diagnostic = create_diagnostic(
+            msg.rule,
+            msg.line,
+            msg.column,
+            msg.message.clone(),
+            severity,
+        );
+        diagnostic.source = Some("rumoca-lint".to_string());
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Drop suppressed diagnostics and warn about suppressions of unknown codes
+fn suppress_diagnostics(suppressions: &Suppressions, diagnostics: &mut Vec<Diagnostic>) {
+    diagnostics.retain(|d| match &d.code {
+        Some(NumberOrString::String(code)) => {
+            !suppressions.is_suppressed(code, d.range.start.line + 1)
+        }
+        _ => true,
+    });
+
+    for suppression in suppressions.unknown() {
+        let mut diagnostic = create_diagnostic(
+            "unknown-suppression",
+            suppression.line,
+            suppression.column,
+            format!("unknown code '{}' in suppression", suppression.code),
+            DiagnosticSeverity::WARNING,
+        );
+        diagnostic.range.end.character =
+            diagnostic.range.start.character + suppression.code.chars().count() as u32;
+        diagnostics.push(diagnostic);
+    }
+}
+
 /// Analyze a class for semantic issues
 /// `peer_classes` contains all top-level classes in the file (for looking up peer functions)
 fn analyze_class(
@@ -116,6 +232,7 @@ fn analyze_class(
                     type_name: peer_name.clone(),
                     shape: vec![],
                     function_return,
+                    fields: HashMap::new(),
                 },
             );
         }
@@ -126,6 +243,17 @@ fn analyze_class(
         let (name, symbol) = DefinedSymbol::from_component(comp_name, comp);
         defined.insert(name, symbol);
 
+        // Components named like built-ins, e.g. `Real time;` (warning)
+        if globals.contains(comp_name) {
+            diagnostics.push(create_diagnostic(
+                "shadowed-builtin",
+                comp.name_token.location.start_line,
+                comp.name_token.location.start_column,
+                shadowed_builtin_message(comp_name),
+                DiagnosticSeverity::WARNING,
+            ));
+        }
+
         // Check references in start expression
         collect_used_symbols(&comp.start, &mut used);
     }
@@ -150,6 +278,11 @@ fn analyze_class(
         defined.insert(name, symbol);
     }
 
+    // Fields of record components, for the types of references like `p.origin.x`
+    add_record_fields(&mut defined, &|name: &str| {
+        class.classes.get(name).or_else(|| peer_classes.get(name))
+    });
+
     // Collect symbols used in equations and run type checking
     for eq in &class.equations {
         collect_equation_symbols(eq, &mut used, diagnostics, &defined, &globals);
@@ -198,6 +331,7 @@ fn analyze_class(
                     && !inherited_names.contains(name)
                 {
                     diagnostics.push(create_diagnostic(
+                        "unused-variable",
                         sym.line,
                         sym.col,
                         format!("Variable '{}' is declared but never used", name),
@@ -212,6 +346,7 @@ fn analyze_class(
     for (name, sym) in &defined {
         if sym.is_parameter && !sym.has_default {
             diagnostics.push(create_diagnostic(
+                "parameter-no-default",
                 sym.line,
                 sym.col,
                 format!(
@@ -223,6 +358,28 @@ fn analyze_class(
         }
     }
 
+    // Check for divisions by a denominator that is zero at the initial point (warning)
+    for division in find_zero_divisions(class) {
+        diagnostics.push(create_diagnostic(
+            "division-by-zero",
+            division.location.start_line,
+            division.location.start_column,
+            division.message(),
+            DiagnosticSeverity::WARNING,
+        ));
+    }
+
+    // Nested when-equations and elsewhen-branches assigning other variables
+    for error in check_when_equations(class) {
+        diagnostics.push(create_diagnostic(
+            "when-equation",
+            error.location().start_line,
+            error.location().start_column,
+            error.message(),
+            DiagnosticSeverity::ERROR,
+        ));
+    }
+
     // Recursively analyze nested classes
     for nested_class in class.classes.values() {
         analyze_class(nested_class, peer_classes, diagnostics);
@@ -255,6 +412,7 @@ fn compile_and_analyze_classes(
     ast: &crate::ir::ast::StoredDefinition,
     workspace: &mut WorkspaceState,
     diagnostics: &mut Vec<Diagnostic>,
+    stats: &mut CompileStats,
 ) {
     // First, run semantic analysis on original AST classes (pre-flattening)
     // This checks for undefined/unused variables against what the user wrote
@@ -271,6 +429,7 @@ fn compile_and_analyze_classes(
     for (class_name, class) in &ast.class_list {
         collect_balance_classes(class, class_name, &mut class_paths);
     }
+    stats.classes = class_paths.len();
 
     // Collect all root package names from imports across all classes
     let mut import_roots: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -278,70 +437,191 @@ fn compile_and_analyze_classes(
         collect_import_roots(class, &mut import_roots);
     }
 
-    let uri_clone = uri.clone();
-    let text_owned = text.to_string();
-    let path_owned = path.to_string();
-
     // Get library paths from workspace for import resolution
     let library_paths: Vec<String> = workspace
         .package_roots()
         .iter()
         .filter_map(|p| p.to_str().map(String::from))
         .collect();
+    let path_refs: Vec<&str> = library_paths.iter().map(|s| s.as_str()).collect();
+    resources::resource_diagnostics(ast, path, workspace.package_roots(), diagnostics);
+
+    // Build compiler and include required packages
+    let limits = &workspace.settings().limits;
+    let mut compiler = crate::Compiler::new()
+        .modelica_path(&path_refs)
+        .max_expansion_size(limits.max_expansion_size)
+        .max_instance_depth(limits.max_instance_depth);
+    if let Some(timeout) = limits.timeout() {
+        compiler = compiler.timeout(timeout);
+    }
+    for pkg_name in &import_roots {
+        if let Ok(c) = compiler.clone().include_from_modelica_path(pkg_name) {
+            compiler = c;
+        }
+    }
 
-    // Convert to Vec for use in closure
-    let import_roots_vec: Vec<String> = import_roots.into_iter().collect();
-
-    // Compile all classes in parallel for balance checking only
-    let results: Vec<_> = class_paths
-        .par_iter()
-        .filter_map(|(class_path, is_partial, class_type)| {
-            // Only compile models, blocks, classes, and connectors
-            if !matches!(
-                class_type,
-                ClassType::Model | ClassType::Block | ClassType::Class | ClassType::Connector
-            ) {
-                return None;
-            }
-
-            let path_refs: Vec<&str> = library_paths.iter().map(|s| s.as_str()).collect();
-
-            // Build compiler and include required packages
-            let mut compiler = crate::Compiler::new()
-                .model(class_path)
-                .modelica_path(&path_refs)
-                .threads(1);
-
-            for pkg_name in &import_roots_vec {
-                if let Ok(c) = compiler.clone().include_from_modelica_path(pkg_name) {
-                    compiler = c;
-                }
-            }
+    // Only compile models, blocks, classes, and connectors
+    class_paths.retain(|(_, _, class_type)| {
+        matches!(
+            class_type,
+            ClassType::Model | ClassType::Block | ClassType::Class | ClassType::Connector
+        )
+    });
+
+    // Reuse the balances of classes whose source (and that of the classes they
+    // use) is unchanged. High-index results are recomputed, since the
+    // locations of their constraints move with edits above them.
+    let keys = balance_keys::balance_keys(text, ast);
+    let key = |class_path: &str| keys.get(class_path).cloned().unwrap_or_default();
+    let cached: Vec<Option<(BalanceResult, InstanceUses, BltBlocks)>> = class_paths
+        .iter()
+        .map(|(class_path, _, _)| {
+            workspace
+                .cached_balance(uri, class_path, &key(class_path))
+                .filter(|(balance, _, _)| {
+                    balance
+                        .dae_index
+                        .as_ref()
+                        .is_none_or(|index| index.constraints.is_empty())
+                })
+                .map(|(balance, uses, blocks)| (balance.clone(), uses.clone(), blocks.clone()))
+        })
+        .collect();
+    let models: Vec<&str> = class_paths
+        .iter()
+        .zip(&cached)
+        .filter(|(_, cached)| cached.is_none())
+        .map(|((path, _, _), _)| path.as_str())
+        .collect();
+    stats.compiled_models = models.len();
+    stats.cached_models = cached.iter().filter(|cached| cached.is_some()).count();
+
+    // Compile the other classes for balance and instance checking only, parsing the
+    // document and libraries once and sharing classes resolved for one model with the others
+    let results: Vec<Result<_, crate::Error>> = match compiler.compile_checks(text, path, &models) {
+        Ok(results) => results,
+        Err(e) => {
+            let message = e.to_string();
+            models
+                .iter()
+                .map(|_| Err(crate::Error::Other(message.clone())))
+                .collect()
+        }
+    };
+    let mut results = results.into_iter();
+    let declarations = instances::Declarations::new(ast, path);
+    let equations = blocks::Equations::new(ast, path);
+    let mut all_uses = Vec::new();
 
-            match compiler.compile_str(&text_owned, &path_owned) {
-                Ok(result) => {
-                    // Get balance result from compilation
-                    let mut balance = result.balance;
+    let retained: HashSet<String> = class_paths
+        .iter()
+        .map(|(class_path, _, _)| class_path.clone())
+        .collect();
+    for ((class_path, is_partial, class_type), cached) in class_paths.into_iter().zip(cached) {
+        let mut limited = false;
+        let (balance, uses, blocks) = match cached {
+            Some(cached) => cached,
+            None => match results.next() {
+                Some(Ok(check)) => {
+                    stats.flatten_ms += millis(check.flatten_time);
+                    stats.dae_ms += millis(check.dae_time);
+                    let mut balance = check.balance;
                     let is_connector = matches!(class_type, ClassType::Connector);
-                    if (*is_partial || is_connector) && !balance.is_balanced {
+                    if (is_partial || is_connector) && !balance.is_balanced {
                         balance.status = BalanceStatus::Partial;
                     }
-
-                    Some((class_path.clone(), balance))
+                    // Partial classes and connectors are completed where they are used
+                    let uses = if is_partial || is_connector {
+                        InstanceUses::default()
+                    } else {
+                        declarations.uses(&check.instances)
+                    };
+                    (balance, uses, equations.blocks(&check.blt))
                 }
-                Err(e) => {
-                    // Errors are now raw (no miette formatting), just use the message directly
-                    let balance = BalanceResult::compile_error(e.to_string());
-                    Some((class_path.clone(), balance))
+                // Errors are raw (no miette formatting), just use the message directly.
+                // Equations keep the blocks of the last successful compilation.
+                Some(Err(e)) => {
+                    // Models exceeding a resource limit are reported at the
+                    // class, and compiled again on the next pass
+                    if let crate::Error::Limit(message) = &e {
+                        limited = true;
+                        if let Some(loc) = class_location(ast, &class_path) {
+                            diagnostics.push(create_diagnostic(
+                                "resource-limit",
+                                loc.start_line,
+                                loc.start_column,
+                                format!("Analysis of '{}' stopped: {}", class_path, message),
+                                DiagnosticSeverity::WARNING,
+                            ));
+                        }
+                    }
+                    (
+                        BalanceResult::compile_error(e.to_string()),
+                        InstanceUses::default(),
+                        workspace
+                            .blt_blocks(uri, &class_path)
+                            .cloned()
+                            .unwrap_or_default(),
+                    )
+                }
+                None => continue,
+            },
+        };
+        if let Some(index) = &balance.dae_index
+            && index.is_high_index()
+        {
+            // Token locations only keep the file name, not the path
+            let file_name = Some(base_name(path));
+            for constraint in &index.constraints {
+                let Some(loc) = &constraint.location else {
+                    continue;
+                };
+                if Some(loc.file_name.as_str()) != file_name {
+                    continue;
                 }
+                let times = if constraint.differentiations == 1 {
+                    "once".to_string()
+                } else {
+                    format!("{} times", constraint.differentiations)
+                };
+                diagnostics.push(create_diagnostic(
+                    "high-index",
+                    loc.start_line,
+                    loc.start_column,
+                    format!(
+                        "'{}' is an {}: index reduction differentiates this equation {}",
+                        class_path,
+                        index.description(),
+                        times
+                    ),
+                    DiagnosticSeverity::INFORMATION,
+                ));
             }
-        })
-        .collect();
+        }
+        all_uses.push(uses.clone());
+        let key = if limited {
+            String::new()
+        } else {
+            key(&class_path)
+        };
+        workspace.cache_balance(uri.clone(), class_path, key, balance, uses, blocks);
+    }
+    workspace.retain_balances(uri, &retained);
+    instances::instance_diagnostics(&all_uses, &declarations, diagnostics);
+}
 
-    // Merge balance results (single-threaded)
-    for (class_path, balance) in results {
-        workspace.set_balance(uri_clone.clone(), class_path, balance);
+/// Location of the name of a class of the document
+fn class_location<'a>(
+    ast: &'a crate::ir::ast::StoredDefinition,
+    class_path: &str,
+) -> Option<&'a crate::ir::ast::Location> {
+    let mut parts = class_path.split('.');
+    let mut class = ast.class_list.get(parts.next()?)?;
+    for part in parts {
+        class = class.classes.get(part)?;
     }
+    Some(&class.name.location)
 }
 
 /// Recursively collect all class paths that need balance computation
@@ -0,0 +1,63 @@
+//! `modelica://` URIs of the document that name no file.
+//!
+//! URIs are resolved against the library paths of the workspace and the
+//! packages the document belongs to, like the compiler does, see
+//! [`resources`](crate::compiler::resources).
+
+use std::path::{Path, PathBuf};
+
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::compiler::ResourceResolver;
+use crate::compiler::resources::is_modelica_uri;
+use crate::ir::ast::{Expression, StoredDefinition, TerminalType, Token};
+use crate::ir::visitor::{Visitable, Visitor};
+
+use super::helpers::create_diagnostic;
+
+/// Report the URIs of the document's string literals that can't be resolved
+pub(super) fn resource_diagnostics(
+    ast: &StoredDefinition,
+    path: &str,
+    library_paths: &[PathBuf],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut collector = UriCollector::default();
+    for class in ast.class_list.values() {
+        class.accept(&mut collector);
+    }
+    if collector.uris.is_empty() {
+        return;
+    }
+    let mut resolver = ResourceResolver::new(library_paths.to_vec());
+    resolver.add_file(Path::new(path));
+    for token in collector.uris {
+        if let Err(error) = resolver.resolve(&token.text) {
+            diagnostics.push(create_diagnostic(
+                "missing-resource",
+                token.location.start_line,
+                token.location.start_column,
+                error.to_string(),
+                DiagnosticSeverity::WARNING,
+            ));
+        }
+    }
+}
+
+#[derive(Default)]
+struct UriCollector {
+    uris: Vec<Token>,
+}
+
+impl Visitor for UriCollector {
+    fn enter_expression(&mut self, node: &Expression) {
+        if let Expression::Terminal {
+            terminal_type: TerminalType::String,
+            token,
+        } = node
+            && is_modelica_uri(&token.text)
+        {
+            self.uris.push(token.clone());
+        }
+    }
+}
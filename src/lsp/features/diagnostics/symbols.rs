@@ -24,6 +24,7 @@ pub fn type_errors_to_diagnostics(type_result: &TypeCheckResult) -> Vec<Diagnost
                 TypeErrorSeverity::Error => DiagnosticSeverity::ERROR,
             };
             create_diagnostic(
+                "type-error",
                 err.location.start_line,
                 err.location.start_column,
                 err.message.clone(),
@@ -46,16 +47,18 @@ pub fn collect_equation_symbols(
 ) {
     match eq {
         Equation::Empty => {}
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             collect_and_check_expression(lhs, used, diagnostics, defined, globals);
             collect_and_check_expression(rhs, used, diagnostics, defined, globals);
             // Type checking is handled separately via type_checker::check_equation()
         }
-        Equation::Connect { lhs, rhs } => {
+        Equation::Connect { lhs, rhs, .. } => {
             collect_and_check_component_ref(lhs, used, diagnostics, defined, globals);
             collect_and_check_component_ref(rhs, used, diagnostics, defined, globals);
         }
-        Equation::For { indices, equations } => {
+        Equation::For {
+            indices, equations, ..
+        } => {
             // For loop indices are locally defined
             let mut local_defined = defined.clone();
             for index in indices {
@@ -78,7 +81,7 @@ pub fn collect_equation_symbols(
                 collect_equation_symbols(sub_eq, used, diagnostics, &local_defined, globals);
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
                 collect_and_check_expression(&block.cond, used, diagnostics, defined, globals);
                 for sub_eq in &block.eqs {
@@ -89,6 +92,7 @@ pub fn collect_equation_symbols(
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_and_check_expression(&block.cond, used, diagnostics, defined, globals);
@@ -102,7 +106,7 @@ pub fn collect_equation_symbols(
                 }
             }
         }
-        Equation::FunctionCall { comp, args } => {
+        Equation::FunctionCall { comp, args, .. } => {
             collect_and_check_component_ref(comp, used, diagnostics, defined, globals);
             for arg in args {
                 collect_and_check_expression(arg, used, diagnostics, defined, globals);
@@ -121,17 +125,19 @@ pub fn collect_statement_symbols(
 ) {
     match stmt {
         Statement::Empty => {}
-        Statement::Assignment { comp, value } => {
+        Statement::Assignment { comp, value, .. } => {
             collect_and_check_component_ref(comp, used, diagnostics, defined, globals);
             collect_and_check_expression(value, used, diagnostics, defined, globals);
         }
-        Statement::FunctionCall { comp, args } => {
+        Statement::FunctionCall { comp, args, .. } => {
             collect_and_check_component_ref(comp, used, diagnostics, defined, globals);
             for arg in args {
                 collect_and_check_expression(arg, used, diagnostics, defined, globals);
             }
         }
-        Statement::For { indices, equations } => {
+        Statement::For {
+            indices, equations, ..
+        } => {
             let mut local_defined = defined.clone();
             for index in indices {
                 local_defined.insert(
@@ -162,6 +168,7 @@ pub fn collect_statement_symbols(
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_and_check_expression(&block.cond, used, diagnostics, defined, globals);
@@ -242,6 +249,9 @@ pub fn collect_used_symbols(expr: &Expression, used: &mut HashSet<String>) {
         Expression::Parenthesized { inner } => {
             collect_used_symbols(inner, used);
         }
+        Expression::NamedArgument { value, .. } => {
+            collect_used_symbols(value, used);
+        }
         Expression::ArrayComprehension { expr, indices } => {
             collect_used_symbols(expr, used);
             for idx in indices {
@@ -318,6 +328,9 @@ pub fn collect_and_check_expression(
         Expression::Parenthesized { inner } => {
             collect_and_check_expression(inner, used, diagnostics, defined, globals);
         }
+        Expression::NamedArgument { value, .. } => {
+            collect_and_check_expression(value, used, diagnostics, defined, globals);
+        }
         Expression::ArrayComprehension { expr, indices } => {
             collect_and_check_expression(expr, used, diagnostics, defined, globals);
             for idx in indices {
@@ -344,6 +357,7 @@ pub fn collect_and_check_component_ref(
         // Check if defined
         if !defined.contains_key(name) && !globals.contains(name) {
             diagnostics.push(create_diagnostic(
+                "undefined-reference",
                 first.ident.location.start_line,
                 first.ident.location.start_column,
                 format!("Undefined variable '{}'", name),
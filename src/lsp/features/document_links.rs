@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 use lsp_types::{DocumentLink, DocumentLinkParams, Position, Range, Uri};
 
-use crate::lsp::utils::parse_document;
+use crate::lsp::utils::{parse_document, uri_to_path};
 
 /// Handle document links request
 pub fn handle_document_links(
@@ -18,7 +18,7 @@ pub fn handle_document_links(
 ) -> Option<Vec<DocumentLink>> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let mut links = Vec::new();
 
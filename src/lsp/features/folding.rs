@@ -12,8 +12,8 @@ use std::collections::HashMap;
 
 use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams, Uri};
 
-use crate::ir::ast::{ClassDefinition, Equation, Statement};
-use crate::lsp::utils::parse_document;
+use crate::ir::ast::{ClassDefinition, Equation, Location, SectionKind, Statement};
+use crate::lsp::utils::{parse_document, uri_to_path};
 
 /// Handle folding range request
 pub fn handle_folding_range(
@@ -22,7 +22,7 @@ pub fn handle_folding_range(
 ) -> Option<Vec<FoldingRange>> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let mut ranges = Vec::new();
 
@@ -234,106 +234,58 @@ fn find_matching_paren(lines: &[&str], start_line: usize, start_col: usize) -> O
 fn collect_class_ranges(class: &ClassDefinition, text: &str, ranges: &mut Vec<FoldingRange>) {
     let class_name = &class.name.text;
 
-    // Use the class location (spans from class keyword to end statement)
-    let start_line = class.location.start_line.saturating_sub(1);
-    let end_line = class.location.end_line.saturating_sub(1);
-
-    // Add class folding range
-    if end_line > start_line {
-        ranges.push(FoldingRange {
-            start_line,
-            start_character: None,
-            end_line,
-            end_character: None,
-            kind: Some(FoldingRangeKind::Region),
-            collapsed_text: Some(format!("{:?} {} ...", class.class_type, class_name)),
-        });
-    }
-
-    // Fold equation section using keyword token location
-    if let Some(ref eq_kw) = class.equation_keyword {
-        // Find the end of the equation section (next section or class end)
-        let eq_start = eq_kw.location.start_line.saturating_sub(1);
-        let eq_end = find_section_end(class, eq_start, end_line);
-        if eq_end > eq_start {
-            ranges.push(FoldingRange {
-                start_line: eq_start,
-                start_character: None,
-                end_line: eq_end,
-                end_character: None,
-                kind: Some(FoldingRangeKind::Region),
-                collapsed_text: Some("equation ...".to_string()),
-            });
-        }
-    }
-
-    // Fold initial equation section
-    if let Some(ref eq_kw) = class.initial_equation_keyword {
-        let eq_start = eq_kw.location.start_line.saturating_sub(1);
-        let eq_end = find_section_end(class, eq_start, end_line);
-        if eq_end > eq_start {
-            ranges.push(FoldingRange {
-                start_line: eq_start,
-                start_character: None,
-                end_line: eq_end,
-                end_character: None,
-                kind: Some(FoldingRangeKind::Region),
-                collapsed_text: Some("initial equation ...".to_string()),
-            });
-        }
-    }
-
-    // Fold algorithm section
-    if let Some(ref alg_kw) = class.algorithm_keyword {
-        let alg_start = alg_kw.location.start_line.saturating_sub(1);
-        let alg_end = find_section_end(class, alg_start, end_line);
-        if alg_end > alg_start {
-            ranges.push(FoldingRange {
-                start_line: alg_start,
-                start_character: None,
-                end_line: alg_end,
-                end_character: None,
-                kind: Some(FoldingRangeKind::Region),
-                collapsed_text: Some("algorithm ...".to_string()),
-            });
-        }
-    }
-
-    // Fold initial algorithm section
-    if let Some(ref alg_kw) = class.initial_algorithm_keyword {
-        let alg_start = alg_kw.location.start_line.saturating_sub(1);
-        let alg_end = find_section_end(class, alg_start, end_line);
-        if alg_end > alg_start {
-            ranges.push(FoldingRange {
-                start_line: alg_start,
-                start_character: None,
-                end_line: alg_end,
-                end_character: None,
-                kind: Some(FoldingRangeKind::Region),
-                collapsed_text: Some("initial algorithm ...".to_string()),
-            });
-        }
-    }
-
-    // Collect ranges for equations with blocks (if, for, when)
-    for eq in &class.equations {
-        collect_equation_ranges(eq, text, ranges);
-    }
-
-    for eq in &class.initial_equations {
-        collect_equation_ranges(eq, text, ranges);
+    // Add class folding range, from the first prefix to the end statement
+    push_range(
+        ranges,
+        Some(lines(&class.span)),
+        format!("{:?} {} ...", class.class_type, class_name),
+    );
+
+    // Fold each equation and algorithm section
+    for section in &class.sections {
+        let keyword = match section.kind {
+            SectionKind::Equation => "equation",
+            SectionKind::InitialEquation => "initial equation",
+            SectionKind::Algorithm => "algorithm",
+            SectionKind::InitialAlgorithm => "initial algorithm",
+        };
+        push_range(
+            ranges,
+            Some(lines(&section.location)),
+            format!("{} ...", keyword),
+        );
     }
 
-    // Collect ranges for statements with blocks
-    for algo in &class.algorithms {
-        for stmt in algo {
-            collect_statement_ranges(stmt, text, ranges);
+    // Collect ranges for equations with blocks (if, for, when), using the
+    // source ranges of the sections for the top-level ones
+    for (equations, kind) in [
+        (&class.equations, SectionKind::Equation),
+        (&class.initial_equations, SectionKind::InitialEquation),
+    ] {
+        let items: Vec<&Location> = class
+            .sections
+            .iter()
+            .filter(|s| s.kind == kind)
+            .flat_map(|s| &s.items)
+            .collect();
+        for (i, eq) in equations.iter().enumerate() {
+            collect_equation_ranges(eq, items.get(i).copied(), text, ranges);
         }
     }
 
-    for algo in &class.initial_algorithms {
-        for stmt in algo {
-            collect_statement_ranges(stmt, text, ranges);
+    // Collect ranges for statements with blocks, each algorithm having a
+    // section
+    for (algorithms, kind) in [
+        (&class.algorithms, SectionKind::Algorithm),
+        (&class.initial_algorithms, SectionKind::InitialAlgorithm),
+    ] {
+        let mut sections = class.sections.iter().filter(|s| s.kind == kind);
+        for algo in algorithms {
+            let section = sections.next();
+            for (i, stmt) in algo.iter().enumerate() {
+                let span = section.and_then(|s| s.items.get(i));
+                collect_statement_ranges(stmt, span, text, ranges);
+            }
         }
     }
 
@@ -343,109 +295,101 @@ fn collect_class_ranges(class: &ClassDefinition, text: &str, ranges: &mut Vec<Fo
     }
 }
 
-/// Find the end of a section by looking for the next section keyword or class end
-/// Uses AST keyword tokens to determine section boundaries
-fn find_section_end(class: &ClassDefinition, section_start: u32, class_end: u32) -> u32 {
-    // Collect all section start lines (0-indexed)
-    let mut section_starts: Vec<u32> = vec![];
-
-    if let Some(ref kw) = class.equation_keyword {
-        section_starts.push(kw.location.start_line.saturating_sub(1));
-    }
-    if let Some(ref kw) = class.initial_equation_keyword {
-        section_starts.push(kw.location.start_line.saturating_sub(1));
-    }
-    if let Some(ref kw) = class.algorithm_keyword {
-        section_starts.push(kw.location.start_line.saturating_sub(1));
-    }
-    if let Some(ref kw) = class.initial_algorithm_keyword {
-        section_starts.push(kw.location.start_line.saturating_sub(1));
-    }
+/// The (0-indexed) first and last lines of a source range
+fn lines(location: &Location) -> (u32, u32) {
+    (
+        location.start_line.saturating_sub(1),
+        location.end_line.saturating_sub(1),
+    )
+}
 
-    // Sort section starts
-    section_starts.sort();
+/// The lines of a block equation or statement: those of its source range if
+/// known (for top-level ones), else from the line of its first location to
+/// the matching `end <keyword>`
+fn block_lines(
+    span: Option<&Location>,
+    start: Option<&Location>,
+    text: &str,
+    keyword: &str,
+) -> Option<(u32, u32)> {
+    if let Some(span) = span {
+        return Some(lines(span));
+    }
+    let start_line = start?.start_line.saturating_sub(1);
+    find_end_keyword(text, start_line, keyword).map(|end_line| (start_line, end_line))
+}
 
-    // Find the next section after our current section
-    for &start in &section_starts {
-        if start > section_start {
-            // End one line before the next section
-            return start.saturating_sub(1);
-        }
+/// Add a folding range over several lines
+fn push_range(ranges: &mut Vec<FoldingRange>, lines: Option<(u32, u32)>, collapsed_text: String) {
+    if let Some((start_line, end_line)) = lines
+        && end_line > start_line
+    {
+        ranges.push(FoldingRange {
+            start_line,
+            start_character: None,
+            end_line,
+            end_character: None,
+            kind: Some(FoldingRangeKind::Region),
+            collapsed_text: Some(collapsed_text),
+        });
     }
-
-    // No next section found, end at class end - 1 (before "end ClassName;")
-    class_end.saturating_sub(1)
 }
 
 /// Collect folding ranges for equations with blocks
-fn collect_equation_ranges(eq: &Equation, text: &str, ranges: &mut Vec<FoldingRange>) {
+fn collect_equation_ranges(
+    eq: &Equation,
+    span: Option<&Location>,
+    text: &str,
+    ranges: &mut Vec<FoldingRange>,
+) {
     match eq {
         Equation::If {
             cond_blocks,
-            else_block: _,
+            else_block,
+            ..
         } => {
-            if let Some(first_block) = cond_blocks.first()
-                && let Some(loc) = first_block.cond.get_location()
-            {
-                let start_line = loc.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "if") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("if ... end if".to_string()),
-                    });
-                }
-            }
+            let start = cond_blocks.first().and_then(|b| b.cond.get_location());
+            push_range(
+                ranges,
+                block_lines(span, start, text, "if"),
+                "if ... end if".to_string(),
+            );
 
             // Recursively process inner equations
             for block in cond_blocks {
                 for inner_eq in &block.eqs {
-                    collect_equation_ranges(inner_eq, text, ranges);
+                    collect_equation_ranges(inner_eq, None, text, ranges);
                 }
             }
-        }
-        Equation::For { indices, equations } => {
-            if let Some(first_index) = indices.first() {
-                let start_line = first_index.ident.location.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "for") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("for ... end for".to_string()),
-                    });
-                }
+            for inner_eq in else_block.iter().flatten() {
+                collect_equation_ranges(inner_eq, None, text, ranges);
             }
+        }
+        Equation::For {
+            indices, equations, ..
+        } => {
+            let start = indices.first().map(|i| &i.ident.location);
+            push_range(
+                ranges,
+                block_lines(span, start, text, "for"),
+                "for ... end for".to_string(),
+            );
 
             for inner_eq in equations {
-                collect_equation_ranges(inner_eq, text, ranges);
+                collect_equation_ranges(inner_eq, None, text, ranges);
             }
         }
-        Equation::When(blocks) => {
-            if let Some(first_block) = blocks.first()
-                && let Some(loc) = first_block.cond.get_location()
-            {
-                let start_line = loc.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "when") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("when ... end when".to_string()),
-                    });
-                }
-            }
+        Equation::When { blocks, .. } => {
+            let start = blocks.first().and_then(|b| b.cond.get_location());
+            push_range(
+                ranges,
+                block_lines(span, start, text, "when"),
+                "when ... end when".to_string(),
+            );
 
             for block in blocks {
                 for inner_eq in &block.eqs {
-                    collect_equation_ranges(inner_eq, text, ranges);
+                    collect_equation_ranges(inner_eq, None, text, ranges);
                 }
             }
         }
@@ -454,97 +398,70 @@ fn collect_equation_ranges(eq: &Equation, text: &str, ranges: &mut Vec<FoldingRa
 }
 
 /// Collect folding ranges for statements with blocks
-fn collect_statement_ranges(stmt: &Statement, text: &str, ranges: &mut Vec<FoldingRange>) {
+fn collect_statement_ranges(
+    stmt: &Statement,
+    span: Option<&Location>,
+    text: &str,
+    ranges: &mut Vec<FoldingRange>,
+) {
     match stmt {
-        Statement::For { indices, equations } => {
-            if let Some(first_index) = indices.first() {
-                let start_line = first_index.ident.location.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "for") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("for ... end for".to_string()),
-                    });
-                }
-            }
+        Statement::For {
+            indices, equations, ..
+        } => {
+            let start = indices.first().map(|i| &i.ident.location);
+            push_range(
+                ranges,
+                block_lines(span, start, text, "for"),
+                "for ... end for".to_string(),
+            );
 
             for inner_stmt in equations {
-                collect_statement_ranges(inner_stmt, text, ranges);
+                collect_statement_ranges(inner_stmt, None, text, ranges);
             }
         }
         Statement::While(block) => {
-            if let Some(loc) = block.cond.get_location() {
-                let start_line = loc.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "while") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("while ... end while".to_string()),
-                    });
-                }
-            }
+            push_range(
+                ranges,
+                block_lines(span, block.cond.get_location(), text, "while"),
+                "while ... end while".to_string(),
+            );
 
             for inner_stmt in &block.stmts {
-                collect_statement_ranges(inner_stmt, text, ranges);
+                collect_statement_ranges(inner_stmt, None, text, ranges);
             }
         }
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
-            if let Some(first_block) = cond_blocks.first()
-                && let Some(loc) = first_block.cond.get_location()
-            {
-                let start_line = loc.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "if") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("if ... end if".to_string()),
-                    });
-                }
-            }
+            let start = cond_blocks.first().and_then(|b| b.cond.get_location());
+            push_range(
+                ranges,
+                block_lines(span, start, text, "if"),
+                "if ... end if".to_string(),
+            );
 
             for block in cond_blocks {
                 for inner_stmt in &block.stmts {
-                    collect_statement_ranges(inner_stmt, text, ranges);
+                    collect_statement_ranges(inner_stmt, None, text, ranges);
                 }
             }
-            if let Some(else_stmts) = else_block {
-                for inner_stmt in else_stmts {
-                    collect_statement_ranges(inner_stmt, text, ranges);
-                }
+            for inner_stmt in else_block.iter().flatten() {
+                collect_statement_ranges(inner_stmt, None, text, ranges);
             }
         }
         Statement::When(blocks) => {
-            if let Some(first_block) = blocks.first()
-                && let Some(loc) = first_block.cond.get_location()
-            {
-                let start_line = loc.start_line.saturating_sub(1);
-                if let Some(end_line) = find_end_keyword(text, start_line, "when") {
-                    ranges.push(FoldingRange {
-                        start_line,
-                        start_character: None,
-                        end_line,
-                        end_character: None,
-                        kind: Some(FoldingRangeKind::Region),
-                        collapsed_text: Some("when ... end when".to_string()),
-                    });
-                }
-            }
+            let start = blocks.first().and_then(|b| b.cond.get_location());
+            push_range(
+                ranges,
+                block_lines(span, start, text, "when"),
+                "when ... end when".to_string(),
+            );
 
             for block in blocks {
                 for inner_stmt in &block.stmts {
-                    collect_statement_ranges(inner_stmt, text, ranges);
+                    collect_statement_ranges(inner_stmt, None, text, ranges);
                 }
             }
         }
@@ -717,4 +634,42 @@ end Test;
             ranges
         );
     }
+
+    #[test]
+    fn test_section_and_block_folding() {
+        let text = r#"model Test
+  Real x;
+equation
+  if x > 0 then
+    x = 1;
+  else
+    x = 2;
+  end if;
+  // trailing comment
+
+initial equation
+  x = 0;
+end Test;
+"#;
+        let uri: Uri = "file:///tmp/test.mo".parse().unwrap();
+        let documents = HashMap::from([(uri.clone(), text.to_string())]);
+        let params = FoldingRangeParams {
+            text_document: lsp_types::TextDocumentIdentifier { uri },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let ranges = handle_folding_range(&documents, params).unwrap();
+        let fold = |collapsed_text: &str| {
+            ranges
+                .iter()
+                .find(|r| r.collapsed_text.as_deref() == Some(collapsed_text))
+                .map(|r| (r.start_line, r.end_line))
+        };
+
+        assert_eq!(fold("Model Test ..."), Some((0, 12)));
+        // Sections end at their last equation
+        assert_eq!(fold("equation ..."), Some((2, 7)));
+        assert_eq!(fold("if ... end if"), Some((3, 7)));
+        assert_eq!(fold("initial equation ..."), Some((10, 11)));
+    }
 }
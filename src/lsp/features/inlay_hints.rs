@@ -10,7 +10,7 @@ use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, InlayHintParams, Posit
 use crate::ir::ast::{ClassDefinition, Equation, Expression, Statement};
 use crate::ir::transform::constants::{BuiltinFunction, get_builtin_functions};
 
-use crate::lsp::utils::parse_document;
+use crate::lsp::utils::{parse_document, uri_to_path};
 
 /// Handle inlay hints request
 pub fn handle_inlay_hints(
@@ -19,7 +19,7 @@ pub fn handle_inlay_hints(
 ) -> Option<Vec<InlayHint>> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
     let range = params.range;
 
     let mut hints = Vec::new();
@@ -80,13 +80,14 @@ fn collect_equation_hints(
     hints: &mut Vec<InlayHint>,
 ) {
     match eq {
-        Equation::Simple { lhs, rhs } => {
+        Equation::Simple { lhs, rhs, .. } => {
             collect_expression_hints(lhs, range, builtins, hints);
             collect_expression_hints(rhs, range, builtins, hints);
         }
         Equation::For {
             indices: _,
             equations,
+            ..
         } => {
             for sub_eq in equations {
                 collect_equation_hints(sub_eq, range, builtins, hints);
@@ -95,6 +96,7 @@ fn collect_equation_hints(
         Equation::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_expression_hints(&block.cond, range, builtins, hints);
@@ -108,7 +110,7 @@ fn collect_equation_hints(
                 }
             }
         }
-        Equation::When(blocks) => {
+        Equation::When { blocks, .. } => {
             for block in blocks {
                 collect_expression_hints(&block.cond, range, builtins, hints);
                 for eq in &block.eqs {
@@ -116,7 +118,7 @@ fn collect_equation_hints(
                 }
             }
         }
-        Equation::FunctionCall { comp: _, args } => {
+        Equation::FunctionCall { comp: _, args, .. } => {
             for arg in args {
                 collect_expression_hints(arg, range, builtins, hints);
             }
@@ -133,12 +135,13 @@ fn collect_statement_hints(
     hints: &mut Vec<InlayHint>,
 ) {
     match stmt {
-        Statement::Assignment { comp: _, value } => {
+        Statement::Assignment { comp: _, value, .. } => {
             collect_expression_hints(value, range, builtins, hints);
         }
         Statement::For {
             indices: _,
             equations,
+            ..
         } => {
             for sub_stmt in equations {
                 collect_statement_hints(sub_stmt, range, builtins, hints);
@@ -153,6 +156,7 @@ fn collect_statement_hints(
         Statement::If {
             cond_blocks,
             else_block,
+            ..
         } => {
             for block in cond_blocks {
                 collect_expression_hints(&block.cond, range, builtins, hints);
@@ -174,7 +178,7 @@ fn collect_statement_hints(
                 }
             }
         }
-        Statement::FunctionCall { comp: _, args } => {
+        Statement::FunctionCall { comp: _, args, .. } => {
             for arg in args {
                 collect_expression_hints(arg, range, builtins, hints);
             }
@@ -16,7 +16,7 @@ use lsp_types::{
 use crate::ir::ast::{ClassDefinition, ClassType, ComponentReference, Expression};
 use crate::ir::visitor::{Visitable, Visitor};
 
-use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range};
+use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range, uri_to_path};
 
 /// Visitor that finds all calls to a specific target function
 struct CallRangeFinder<'a> {
@@ -86,7 +86,7 @@ pub fn handle_prepare_call_hierarchy(
     let uri = &params.text_document_position_params.text_document.uri;
     let position = params.text_document_position_params.position;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
     let ast = parse_document(text, path)?;
@@ -111,7 +111,7 @@ pub fn handle_incoming_calls(
 
     // Search all documents for calls to this function
     for (uri, text) in documents {
-        let path = uri.path().as_str();
+        let path = &uri_to_path(uri);
         if let Some(ast) = parse_document(text, path) {
             for class in ast.class_list.values() {
                 collect_incoming_calls(class, target_name, uri, &mut calls);
@@ -129,7 +129,7 @@ pub fn handle_outgoing_calls(
 ) -> Option<Vec<CallHierarchyOutgoingCall>> {
     let uri = &params.item.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
     let source_name = &params.item.name;
 
     let ast = parse_document(text, path)?;
@@ -275,7 +275,7 @@ fn find_function_definition(
     documents: &HashMap<Uri, String>,
 ) -> Option<CallHierarchyItem> {
     for (uri, text) in documents {
-        let path = uri.path().as_str();
+        let path = &uri_to_path(uri);
         if let Some(ast) = parse_document(text, path) {
             for class in ast.class_list.values() {
                 if let Some(item) = find_call_hierarchy_item(class, name, uri) {
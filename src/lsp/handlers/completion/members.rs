@@ -8,6 +8,7 @@
 use lsp_types::{CompletionItem, CompletionItemKind, Position};
 
 use crate::ir::ast::{ClassType, StoredDefinition, Variability};
+use crate::ir::ident;
 use crate::ir::transform::scope_resolver::{
     ImportResolver, find_class_in_ast, resolve_type_candidates,
 };
@@ -22,7 +23,7 @@ pub fn get_member_completions(
 ) -> Vec<CompletionItem> {
     let mut items = Vec::new();
 
-    let parts: Vec<&str> = prefix.split('.').collect();
+    let parts = ident::split_qualified(prefix);
     if parts.len() < 2 {
         return items;
     }
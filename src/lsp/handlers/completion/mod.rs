@@ -12,7 +12,9 @@ mod workspace;
 
 use crate::ir::transform::constants::get_builtin_functions;
 use crate::lsp::data::keywords::get_keyword_completions;
-use crate::lsp::utils::{get_text_before_cursor, parse_document};
+use crate::lsp::utils::{
+    get_qualified_name_before, get_text_before_cursor, parse_document, uri_to_path,
+};
 use crate::lsp::workspace::WorkspaceState;
 use lsp_types::{
     CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, InsertTextFormat,
@@ -43,7 +45,7 @@ pub fn handle_completion_workspace(
     let uri = &params.text_document_position.text_document.uri;
     let position = params.text_document_position.position;
     let text = workspace.get_document(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let mut items = Vec::new();
 
@@ -64,14 +66,7 @@ pub fn handle_completion_workspace(
 
     if is_dot_completion {
         let before_dot = &text_before[..text_before.len() - 1];
-        let prefix: String = before_dot
-            .chars()
-            .rev()
-            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '.')
-            .collect::<String>()
-            .chars()
-            .rev()
-            .collect();
+        let prefix = get_qualified_name_before(before_dot);
 
         if !prefix.is_empty() {
             // Try local AST first - this handles component member access (e.g., ball.h)
@@ -8,7 +8,7 @@ use lsp_types::{
 
 use crate::ir::ast::{Causality, ClassDefinition, ClassType, Variability};
 
-use crate::lsp::utils::{location_to_range, parse_document, token_to_range};
+use crate::lsp::utils::{location_to_range, parse_document, token_to_range, uri_to_path};
 
 /// Handle document symbols request - provides file outline
 pub fn handle_document_symbols(
@@ -17,7 +17,7 @@ pub fn handle_document_symbols(
 ) -> Option<DocumentSymbolResponse> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let ast = parse_document(text, path)?;
     let mut symbols = Vec::new();
@@ -1,31 +1,38 @@
 //! Document Formatting handler for Modelica files.
 //!
-//! Provides LSP integration for code formatting.
-//! The actual formatting logic is in the `fmt` module.
+//! Provides LSP integration for code formatting, of whole documents and of
+//! ranges. The actual formatting logic is in the `fmt` module.
 
 use std::collections::HashMap;
 
-use lsp_types::{DocumentFormattingParams, Position, Range, TextEdit, Uri};
+use lsp_types::{
+    DocumentFormattingParams, DocumentRangeFormattingParams, Position, Range, TextEdit, Uri,
+};
 
-use crate::fmt::{FormatOptions, format_modelica};
+use crate::fmt::format_modelica;
+use crate::ir::ast::{ClassDefinition, Location};
+use crate::lsp::settings::LspSettings;
+use crate::lsp::utils::{char_to_byte, parse_document, uri_to_path};
 
 /// Handle document formatting request
 pub fn handle_formatting(
     documents: &HashMap<Uri, String>,
     params: DocumentFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    handle_formatting_with_settings(documents, params, &LspSettings::default())
+}
+
+/// Handle document formatting request, applying workspace formatter overrides
+pub fn handle_formatting_with_settings(
+    documents: &HashMap<Uri, String>,
+    params: DocumentFormattingParams,
+    settings: &LspSettings,
 ) -> Option<Vec<TextEdit>> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let lsp_options = &params.options;
 
     // Convert LSP options to our format options
-    let options = FormatOptions {
-        indent_size: lsp_options.tab_size as usize,
-        use_tabs: !lsp_options.insert_spaces,
-        max_line_length: 100,
-        preserve_unformatted: true,
-        blank_lines_between_classes: 1,
-    };
+    let options = settings.format_options(&params.options);
 
     let formatted = format_modelica(text, &options);
 
@@ -54,9 +61,161 @@ pub fn handle_formatting(
     }])
 }
 
+/// Handle range formatting request
+pub fn handle_range_formatting(
+    documents: &HashMap<Uri, String>,
+    params: DocumentRangeFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    handle_range_formatting_with_settings(documents, params, &LspSettings::default())
+}
+
+/// Handle range formatting request, applying workspace formatter overrides.
+///
+/// The document is formatted as a whole, and the classes inside the range,
+/// or else the equations and statements overlapping it, are replaced by their
+/// formatted text. Both versions of the document have the same classes,
+/// sections and items, which are matched by their source ranges.
+pub fn handle_range_formatting_with_settings(
+    documents: &HashMap<Uri, String>,
+    params: DocumentRangeFormattingParams,
+    settings: &LspSettings,
+) -> Option<Vec<TextEdit>> {
+    let uri = &params.text_document.uri;
+    let text = documents.get(uri)?;
+    let path = &uri_to_path(uri);
+
+    let options = settings.format_options(&params.options);
+    let formatted = format_modelica(text, &options);
+    let original_ast = parse_document(text, path)?;
+    let formatted_ast = parse_document(&formatted, path)?;
+
+    let range = (
+        byte_offset(text, params.range.start),
+        byte_offset(text, params.range.end),
+    );
+    let mut edits = Vec::new();
+    let mut formatter = RangeFormatter {
+        text,
+        formatted: &formatted,
+        range,
+        edits: &mut edits,
+    };
+    if original_ast.class_list.len() == formatted_ast.class_list.len() {
+        for (original, formatted) in original_ast
+            .class_list
+            .values()
+            .zip(formatted_ast.class_list.values())
+        {
+            formatter.format_class(original, formatted);
+        }
+    }
+    Some(edits)
+}
+
+/// Edits replacing the parts of a document within a range by their
+/// formatted text
+struct RangeFormatter<'a> {
+    text: &'a str,
+    formatted: &'a str,
+    /// Byte offsets of the range
+    range: (usize, usize),
+    edits: &'a mut Vec<TextEdit>,
+}
+
+impl RangeFormatter<'_> {
+    fn format_class(&mut self, original: &ClassDefinition, formatted: &ClassDefinition) {
+        let span = (original.span.start as usize, original.span.end as usize);
+        if self.range.0 <= span.0 && span.1 <= self.range.1 {
+            self.replace(&original.span, &formatted.span);
+            return;
+        }
+        if !self.overlaps(&original.span) {
+            return;
+        }
+        if original.classes.len() == formatted.classes.len() {
+            for (original, formatted) in original.classes.values().zip(formatted.classes.values()) {
+                self.format_class(original, formatted);
+            }
+        }
+        if original.sections.len() != formatted.sections.len() {
+            return;
+        }
+        for (original, formatted) in original.sections.iter().zip(&formatted.sections) {
+            if original.items.len() != formatted.items.len() || !self.overlaps(&original.location) {
+                continue;
+            }
+            for (original, formatted) in original.items.iter().zip(&formatted.items) {
+                if self.overlaps(original) {
+                    self.replace(original, formatted);
+                }
+            }
+        }
+    }
+
+    /// Whether a source range overlaps the range being formatted
+    fn overlaps(&self, location: &Location) -> bool {
+        (location.start as usize) < self.range.1.max(self.range.0 + 1)
+            && self.range.0 < location.end as usize
+    }
+
+    /// Replace the text of a source range of the document by the text of a
+    /// source range of the formatted document, from the start of their lines
+    /// if only indentation comes before them
+    fn replace(&mut self, original: &Location, formatted: &Location) {
+        let start = line_start(self.text, original.start as usize);
+        let formatted_start = line_start(self.formatted, formatted.start as usize);
+        let end = original.end as usize;
+        let new_text = &self.formatted[formatted_start..formatted.end as usize];
+        if self.text[start..end] != *new_text {
+            self.edits.push(TextEdit {
+                range: Range {
+                    start: position(self.text, start),
+                    end: position(self.text, end),
+                },
+                new_text: new_text.to_string(),
+            });
+        }
+    }
+}
+
+/// The start of the line of a byte offset if only whitespace comes before it
+/// on the line, else the offset itself
+fn line_start(text: &str, offset: usize) -> usize {
+    let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    if text[start..offset].trim().is_empty() {
+        start
+    } else {
+        offset
+    }
+}
+
+/// Byte offset of a position
+fn byte_offset(text: &str, pos: Position) -> usize {
+    let mut start = 0;
+    for _ in 0..pos.line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let line = text[start..].lines().next().unwrap_or("");
+    start + char_to_byte(line, pos.character)
+}
+
+/// Position of a byte offset
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].chars().count() as u32,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fmt::FormatOptions;
     use lsp_types::FormattingOptions;
 
     fn default_options() -> FormattingOptions {
@@ -77,6 +236,7 @@ mod tests {
             max_line_length: 100,
             preserve_unformatted: true,
             blank_lines_between_classes: 1,
+            ..Default::default()
         };
         let result = format_modelica(input, &options);
         assert_eq!(result, expected);
@@ -137,4 +297,36 @@ end test;"#;
         let result = format_modelica(input, &options);
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_range_formatting() {
+        let text = "model A\nReal x;\nequation\nx=1;\n  if x>0 then\nx=2;\n  end if;\nend A;\nmodel B\nReal y;\nend B;\n";
+        let uri: Uri = "file:///tmp/range.mo".parse().unwrap();
+        let documents = HashMap::from([(uri.clone(), text.to_string())]);
+        let format = |start: (u32, u32), end: (u32, u32)| {
+            let params = DocumentRangeFormattingParams {
+                text_document: lsp_types::TextDocumentIdentifier { uri: uri.clone() },
+                range: Range {
+                    start: Position::new(start.0, start.1),
+                    end: Position::new(end.0, end.1),
+                },
+                options: default_options(),
+                work_done_progress_params: Default::default(),
+            };
+            handle_range_formatting(&documents, params).unwrap()
+        };
+
+        // Only the equation overlapping the range is formatted
+        let edits = format((5, 0), (5, 0));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position::new(4, 0));
+        assert_eq!(edits[0].range.end, Position::new(6, 9));
+        assert_eq!(edits[0].new_text, "  if x > 0 then\n    x = 2;\n  end if;");
+
+        // A whole class in the range is formatted, the other is kept
+        let edits = format((8, 0), (10, 6));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position::new(8, 0));
+        assert_eq!(edits[0].new_text, "model B\n  Real y;\nend B");
+    }
 }
@@ -14,6 +14,7 @@ use crate::ir::ast::{ClassDefinition, Import, Name, StoredDefinition, Token};
 use crate::ir::transform::scope_resolver::ScopeResolver;
 use crate::lsp::utils::{
     get_qualified_name_at_position, get_word_at_position, parse_document, token_to_range,
+    uri_to_path,
 };
 use crate::lsp::workspace::WorkspaceState;
 
@@ -26,7 +27,7 @@ pub fn handle_goto_definition(
     let position = params.text_document_position_params.position;
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
 
@@ -53,7 +54,7 @@ pub fn handle_goto_definition_workspace(
     let position = params.text_document_position_params.position;
 
     let text = workspace.get_document(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
     let qualified_name = get_qualified_name_at_position(text, position);
@@ -2,6 +2,9 @@
 //!
 //! This module uses canonical scope resolution functions from
 //! `crate::ir::transform::scope_resolver` to avoid duplication.
+//!
+//! In a workspace, hovering an equation also shows how the BLT sorting of the
+//! last successful compilation of the enclosing class solved it.
 
 use std::collections::HashMap;
 
@@ -9,14 +12,18 @@ use lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind, Po
 
 use crate::fmt::{format_equation, format_expression};
 use crate::ir::ast::{
-    Causality, ClassDefinition, ClassType, Component, Expression, Import, StoredDefinition,
-    Variability,
+    Causality, ClassDefinition, ClassType, Component, Equation, Expression, Import, Location,
+    StoredDefinition, Variability,
 };
 use crate::ir::transform::constants::get_builtin_functions;
 use crate::ir::transform::scope_resolver::{ResolvedSymbol, ScopeResolver, find_class_in_ast};
+use crate::ir::visitor::{Visitable, Visitor};
 
 use crate::lsp::data::keywords::get_keyword_hover;
-use crate::lsp::utils::{get_qualified_name_at_position, get_word_at_position, parse_document};
+use crate::lsp::features::diagnostics::{class_at, equation_at};
+use crate::lsp::utils::{
+    get_qualified_name_at_position, get_word_at_position, parse_document, uri_to_path,
+};
 use crate::lsp::workspace::WorkspaceState;
 
 /// Handle hover request
@@ -25,7 +32,7 @@ pub fn handle_hover(documents: &HashMap<Uri, String>, params: HoverParams) -> Op
     let position = params.text_document_position_params.position;
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
 
@@ -88,14 +95,43 @@ pub fn handle_hover_workspace(
     let position = params.text_document_position_params.position;
 
     let text = workspace.get_document(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
+    let ast = parse_document(text, path);
+
+    // The description of the equation at the position and how it was solved,
+    // shown with any other hover
+    let equation_info = ast.as_ref().and_then(|ast| {
+        let parts: Vec<String> = [
+            format_equation_description(ast, position),
+            format_equation_blocks(workspace, uri, ast, position),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join("\n\n"))
+    });
+    let hover = |hover_text: Option<String>| {
+        let value = match (hover_text, &equation_info) {
+            (Some(hover_text), Some(info)) => format!("{}\n\n---\n\n{}", hover_text, info),
+            (hover_text, info) => hover_text.or_else(|| info.clone())?,
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    };
 
     // Get both the simple word and the qualified name (dotted path)
-    let word = get_word_at_position(text, position)?;
+    let Some(word) = get_word_at_position(text, position) else {
+        return hover(None);
+    };
     let qualified_name = get_qualified_name_at_position(text, position);
 
     // Parse the document and use unified scope resolver with workspace lookup
-    if let Some(ast) = parse_document(text, path) {
+    if let Some(ast) = ast {
         // Pre-index: Check if word matches an import alias and ensure package is indexed
         // This is needed because resolve() returns None if the imported symbol isn't indexed yet
         for class in ast.class_list.values() {
@@ -117,13 +153,7 @@ pub fn handle_hover_workspace(
         if let Some(ref resolved) = resolved
             && let Some(hover_text) = format_resolved_symbol_unified(resolved, workspace, &ast)
         {
-            return Some(Hover {
-                contents: HoverContents::Markup(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: hover_text,
-                }),
-                range: None,
-            });
+            return hover(Some(hover_text));
         }
     }
 
@@ -142,26 +172,93 @@ pub fn handle_hover_workspace(
                 func.signature, func.documentation, params_doc
             );
 
-            return Some(Hover {
-                contents: HoverContents::Markup(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: hover_text,
-                }),
-                range: None,
-            });
+            return hover(Some(hover_text));
         }
     }
 
     // Provide hover info for known Modelica keywords and built-ins
-    let hover_text = get_keyword_hover(&word)?;
+    hover(get_keyword_hover(&word))
+}
 
-    Some(Hover {
-        contents: HoverContents::Markup(MarkupContent {
-            kind: MarkupKind::Markdown,
-            value: hover_text,
-        }),
-        range: None,
-    })
+/// Format the description string of the equation at a position: that of an
+/// equation starting on the line of the position, e.g. in the body of a
+/// for-equation, or else that of the top-level equation at the position
+fn format_equation_description(ast: &StoredDefinition, position: Position) -> Option<String> {
+    /// The equations and their descriptions, by their locations
+    #[derive(Default)]
+    struct Starts(Vec<(Location, Option<String>)>);
+    impl Visitor for Starts {
+        fn enter_equation(&mut self, node: &Equation) {
+            if let Some(loc) = node.get_location() {
+                self.0.push((loc.clone(), node.description()));
+            }
+        }
+    }
+
+    let (line, col) = (position.line + 1, position.character + 1);
+    let (_, class) = class_at(ast, line, col)?;
+    let equation = class.equations.get(equation_at(class, line, col)?)?;
+    let mut starts = Starts::default();
+    equation.accept(&mut starts);
+    let on_line = starts
+        .0
+        .iter()
+        .rev()
+        .filter(|(loc, _)| loc.start_line == line)
+        .find_map(|(_, description)| description.clone());
+    let description = on_line.or_else(|| starts.0.first()?.1.clone())?;
+    Some(format!("*{}*", description))
+}
+
+/// Format how the BLT sorting solved the equation at a position, from the
+/// blocks of the last successful compilation of the enclosing class
+fn format_equation_blocks(
+    workspace: &WorkspaceState,
+    uri: &Uri,
+    ast: &StoredDefinition,
+    position: Position,
+) -> Option<String> {
+    const MAX_EQUATIONS: usize = 10;
+
+    let (line, col) = (position.line + 1, position.character + 1);
+    let (class_path, class) = class_at(ast, line, col)?;
+    let equation = (class_path.clone(), equation_at(class, line, col)?);
+    let blocks = workspace.blt_blocks(uri, &class_path)?;
+    let solved: Vec<_> = blocks.get(&equation).collect();
+    if solved.is_empty() {
+        return None;
+    }
+
+    let mut lines: Vec<String> = solved
+        .iter()
+        .take(MAX_EQUATIONS)
+        .map(|solved| {
+            let variable = match &solved.variable {
+                Some(variable) => format!("Solved for `{}`", variable),
+                None => "Not matched to a variable".to_string(),
+            };
+            let algebraic_loop = if solved.size > 1 {
+                format!(", an algebraic loop of {} equations", solved.size)
+            } else {
+                String::new()
+            };
+            format!(
+                "- {} in block {} of {}{}",
+                variable,
+                solved.block + 1,
+                blocks.blocks,
+                algebraic_loop
+            )
+        })
+        .collect();
+    if solved.len() > MAX_EQUATIONS {
+        lines.push(format!("- ... and {} more", solved.len() - MAX_EQUATIONS));
+    }
+    Some(format!(
+        "**BLT** (`{}`)\n\n{}",
+        class_path,
+        lines.join("\n")
+    ))
 }
 
 /// Format a resolved symbol for hover display (unified scope resolver)
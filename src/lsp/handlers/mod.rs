@@ -18,7 +18,10 @@ pub use call_hierarchy::{
 };
 pub use completion::handle_completion_workspace;
 pub use document_symbols::handle_document_symbols;
-pub use formatting::handle_formatting;
+pub use formatting::{
+    handle_formatting, handle_formatting_with_settings, handle_range_formatting,
+    handle_range_formatting_with_settings,
+};
 pub use goto_definition::{handle_goto_definition, handle_goto_definition_workspace};
 pub use hover::{handle_hover, handle_hover_workspace};
 pub use references::handle_references;
@@ -9,7 +9,7 @@ use lsp_types::{Location, ReferenceParams, Uri};
 use crate::ir::ast::{ClassDefinition, Component, ComponentReference, Token};
 use crate::ir::visitor::{Visitable, Visitor};
 
-use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range};
+use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range, uri_to_path};
 
 /// Visitor that finds all references to a specific symbol name
 struct ReferenceFinder<'a> {
@@ -104,7 +104,9 @@ impl ReferenceFinder<'_> {
     /// are not visited by the standard AstVisitor)
     fn check_equation_indices(&mut self, eq: &crate::ir::ast::Equation) {
         match eq {
-            crate::ir::ast::Equation::For { indices, equations } => {
+            crate::ir::ast::Equation::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     if index.ident.text == self.name {
                         self.add_location_from_token(&index.ident);
@@ -114,7 +116,7 @@ impl ReferenceFinder<'_> {
                     self.check_equation_indices(sub_eq);
                 }
             }
-            crate::ir::ast::Equation::When(blocks) => {
+            crate::ir::ast::Equation::When { blocks, .. } => {
                 for block in blocks {
                     for sub_eq in &block.eqs {
                         self.check_equation_indices(sub_eq);
@@ -124,6 +126,7 @@ impl ReferenceFinder<'_> {
             crate::ir::ast::Equation::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     for sub_eq in &block.eqs {
@@ -143,7 +146,9 @@ impl ReferenceFinder<'_> {
     /// Check for loop indices in a statement
     fn check_statement_indices(&mut self, stmt: &crate::ir::ast::Statement) {
         match stmt {
-            crate::ir::ast::Statement::For { indices, equations } => {
+            crate::ir::ast::Statement::For {
+                indices, equations, ..
+            } => {
                 for index in indices {
                     if index.ident.text == self.name {
                         self.add_location_from_token(&index.ident);
@@ -161,6 +166,7 @@ impl ReferenceFinder<'_> {
             crate::ir::ast::Statement::If {
                 cond_blocks,
                 else_block,
+                ..
             } => {
                 for block in cond_blocks {
                     for sub_stmt in &block.stmts {
@@ -195,7 +201,7 @@ pub fn handle_references(
     let include_declaration = params.context.include_declaration;
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
     let ast = parse_document(text, path)?;
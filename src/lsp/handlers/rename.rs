@@ -14,9 +14,13 @@ use lsp_types::{
 };
 
 use crate::ir::ast::{ClassDefinition, Component, ComponentReference, StoredDefinition, Token};
+use crate::ir::ident;
 use crate::ir::visitor::{Visitable, Visitor};
 
-use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range};
+use crate::lsp::utils::{
+    byte_to_char, get_word_at_position, get_word_range_at_position, parse_document, token_to_range,
+    uri_to_path,
+};
 use crate::lsp::workspace::WorkspaceState;
 
 /// Visitor that finds all occurrences of a symbol for renaming
@@ -98,37 +102,22 @@ pub fn handle_prepare_rename(
     let position = params.position;
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
-    let word = get_word_at_position(text, position)?;
+    let (line, range) = get_word_range_at_position(text, position)?;
+    let word = &line[range.clone()];
     let ast = parse_document(text, path)?;
 
     // Check if the word is a renameable symbol
-    if is_renameable_symbol(&ast, &word) {
-        // Find the exact range of the word
-        let lines: Vec<&str> = text.lines().collect();
-        let line = lines.get(position.line as usize)?;
-        let col = position.character as usize;
-
-        // Find word boundaries
-        let start = line[..col]
-            .rfind(|c: char| !c.is_alphanumeric() && c != '_')
-            .map(|i| i + 1)
-            .unwrap_or(0);
-
-        let end = line[col..]
-            .find(|c: char| !c.is_alphanumeric() && c != '_')
-            .map(|i| col + i)
-            .unwrap_or(line.len());
-
+    if is_renameable_symbol(&ast, word) {
         Some(PrepareRenameResponse::Range(Range {
             start: Position {
                 line: position.line,
-                character: start as u32,
+                character: byte_to_char(line, range.start),
             },
             end: Position {
                 line: position.line,
-                character: end as u32,
+                character: byte_to_char(line, range.end),
             },
         }))
     } else {
@@ -143,10 +132,11 @@ pub fn handle_rename(
 ) -> Option<WorkspaceEdit> {
     let uri = &params.text_document_position.text_document.uri;
     let position = params.text_document_position.position;
-    let new_name = &params.new_name;
+    // Names that aren't valid identifiers, e.g. `my var`, become quoted identifiers
+    let new_name = ident::quote(&params.new_name);
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let old_name = get_word_at_position(text, position)?;
     let ast = parse_document(text, path)?;
@@ -157,7 +147,7 @@ pub fn handle_rename(
     }
 
     // Find all occurrences using the visitor
-    let mut finder = SymbolOccurrenceFinder::new(&old_name, new_name);
+    let mut finder = SymbolOccurrenceFinder::new(&old_name, &new_name);
     ast.accept(&mut finder);
 
     if finder.edits.is_empty() {
@@ -233,10 +223,11 @@ pub fn handle_rename_workspace(
 ) -> Option<WorkspaceEdit> {
     let uri = &params.text_document_position.text_document.uri;
     let position = params.text_document_position.position;
-    let new_name = &params.new_name;
+    // Names that aren't valid identifiers, e.g. `my var`, become quoted identifiers
+    let new_name = ident::quote(&params.new_name);
 
     let text = workspace.get_document(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let old_name = get_word_at_position(text, position)?;
     let ast = parse_document(text, path)?;
@@ -250,9 +241,9 @@ pub fn handle_rename_workspace(
 
     // Collect edits for all open documents
     for (doc_uri, doc_text) in workspace.documents() {
-        let doc_path = doc_uri.path().as_str();
+        let doc_path = &uri_to_path(doc_uri);
         if let Some(doc_ast) = parse_document(doc_text, doc_path) {
-            let mut finder = SymbolOccurrenceFinder::new(&old_name, new_name);
+            let mut finder = SymbolOccurrenceFinder::new(&old_name, &new_name);
             doc_ast.accept(&mut finder);
 
             if !finder.edits.is_empty() {
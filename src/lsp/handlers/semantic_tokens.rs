@@ -3,8 +3,8 @@
 use std::collections::HashMap;
 
 use lsp_types::{
-    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensLegend,
-    SemanticTokensParams, SemanticTokensResult, Uri,
+    Position, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensLegend, SemanticTokensParams, SemanticTokensResult, Uri,
 };
 
 use crate::ir::ast::{
@@ -12,7 +12,7 @@ use crate::ir::ast::{
 };
 use crate::ir::visitor::{Visitable, Visitor};
 
-use crate::lsp::utils::parse_document;
+use crate::lsp::utils::{LineIndex, parse_document, uri_to_path, utf16_len};
 
 // Token type indices (must match the order in get_semantic_token_legend)
 const TYPE_NAMESPACE: u32 = 0;
@@ -96,7 +96,7 @@ impl Visitor for SemanticTokenCollector {
             self.add_token(
                 node.class_type_token.location.start_line,
                 node.class_type_token.location.start_column,
-                node.class_type_token.text.len() as u32,
+                utf16_len(&node.class_type_token.text),
                 TYPE_KEYWORD,
                 0,
             );
@@ -113,7 +113,7 @@ impl Visitor for SemanticTokenCollector {
         self.add_token(
             node.name.location.start_line,
             node.name.location.start_column,
-            node.name.text.len() as u32,
+            utf16_len(&node.name.text),
             class_type_idx,
             MOD_DEFINITION,
         );
@@ -133,7 +133,7 @@ impl Visitor for SemanticTokenCollector {
             self.add_token(
                 first_token.location.start_line,
                 first_token.location.start_column,
-                first_token.text.len() as u32,
+                utf16_len(&first_token.text),
                 TYPE_TYPE,
                 0,
             );
@@ -143,7 +143,7 @@ impl Visitor for SemanticTokenCollector {
         self.add_token(
             node.name_token.location.start_line,
             node.name_token.location.start_column,
-            node.name_token.text.len() as u32,
+            utf16_len(&node.name_token.text),
             token_type,
             modifiers,
         );
@@ -167,7 +167,7 @@ impl Visitor for SemanticTokenCollector {
                 self.add_token(
                     token.location.start_line,
                     token.location.start_column,
-                    token.text.len() as u32,
+                    utf16_len(&token.text),
                     token_type,
                     0,
                 );
@@ -197,7 +197,7 @@ impl Visitor for SemanticTokenCollector {
             self.add_token(
                 part.ident.location.start_line,
                 part.ident.location.start_column,
-                part.ident.text.len() as u32,
+                utf16_len(&part.ident.text),
                 token_type,
                 0,
             );
@@ -217,7 +217,7 @@ pub fn handle_semantic_tokens(
 ) -> Option<SemanticTokensResult> {
     let uri = &params.text_document.uri;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let ast = parse_document(text, path)?;
 
@@ -230,12 +230,20 @@ pub fn handle_semantic_tokens(
         .tokens
         .sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
 
-    // Convert to delta-encoded semantic tokens
+    // Convert to delta-encoded semantic tokens. Token data isn't made of positions,
+    // so it is encoded in UTF-16 here rather than at the protocol boundary.
+    let index = LineIndex::new(text);
     let mut tokens: Vec<SemanticToken> = Vec::new();
     let mut prev_line = 0u32;
     let mut prev_start = 0u32;
 
     for (line, col, length, token_type, token_modifiers) in collector.tokens {
+        let col = index
+            .to_utf16(Position {
+                line,
+                character: col,
+            })
+            .character;
         let delta_line = line - prev_line;
         let delta_start = if delta_line == 0 {
             col - prev_start
@@ -9,7 +9,7 @@ use lsp_types::{
 
 use crate::ir::ast::{Causality, ClassType};
 use crate::ir::transform::constants::get_builtin_functions;
-use crate::lsp::utils::{find_function_at_cursor, parse_document};
+use crate::lsp::utils::{find_function_at_cursor, parse_document, uri_to_path};
 
 /// Handle signature help request
 pub fn handle_signature_help(
@@ -19,7 +19,7 @@ pub fn handle_signature_help(
     let uri = &params.text_document_position_params.text_document.uri;
     let position = params.text_document_position_params.position;
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let (func_name, active_param) = find_function_at_cursor(text, position)?;
     let simple_name = func_name.rsplit('.').next().unwrap_or(&func_name);
@@ -62,7 +62,12 @@ pub fn handle_signature_help(
                     for (comp_name, comp) in &func_class.components {
                         match &comp.causality {
                             Causality::Input(_) => {
-                                inputs.push((comp_name.clone(), comp.type_name.to_string()));
+                                // Optional inputs show their default
+                                let type_name = match comp.binding() {
+                                    Some(default) => format!("{} = {}", comp.type_name, default),
+                                    None => comp.type_name.to_string(),
+                                };
+                                inputs.push((comp_name.clone(), type_name));
                             }
                             Causality::Output(_) => {
                                 outputs.push((comp_name.clone(), comp.type_name.to_string()));
@@ -10,7 +10,7 @@ use lsp_types::{GotoDefinitionResponse, Location, TextDocumentPositionParams, Ur
 
 use crate::ir::ast::{ClassDefinition, StoredDefinition, Token};
 
-use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range};
+use crate::lsp::utils::{get_word_at_position, parse_document, token_to_range, uri_to_path};
 
 /// Handle go to type definition request
 pub fn handle_type_definition(
@@ -21,7 +21,7 @@ pub fn handle_type_definition(
     let position = params.position;
 
     let text = documents.get(uri)?;
-    let path = uri.path().as_str();
+    let path = &uri_to_path(uri);
 
     let word = get_word_at_position(text, position)?;
     let ast = parse_document(text, path)?;
@@ -11,7 +11,7 @@ use lsp_types::{Location, SymbolInformation, SymbolKind, Uri, WorkspaceSymbolPar
 
 use crate::ir::ast::{ClassDefinition, ClassType, StoredDefinition, Variability};
 
-use crate::lsp::utils::{parse_document, token_to_range};
+use crate::lsp::utils::{parse_document, token_to_range, uri_to_path};
 
 /// Handle workspace symbol request
 #[allow(deprecated)] // SymbolInformation::deprecated field is deprecated but required
@@ -23,7 +23,7 @@ pub fn handle_workspace_symbol(
     let mut symbols = Vec::new();
 
     for (uri, text) in documents {
-        let path = uri.path().as_str();
+        let path = &uri_to_path(uri);
         if let Some(ast) = parse_document(text, path) {
             collect_symbols_from_ast(&ast, uri, &query, &mut symbols);
         }
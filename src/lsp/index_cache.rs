@@ -0,0 +1,205 @@
+//! Persistent workspace index for fast LSP startup.
+//!
+//! The symbol index of every discovered file and the diagnostics of opened
+//! documents are stored in `~/.cache/rumoca/lsp/`, one JSON file per set of
+//! workspace roots. Entries are keyed by the MD5 hash of the file content:
+//! on startup the symbols are restored immediately (without parsing), and the
+//! workspace then re-parses the files in the background and replaces entries
+//! whose content changed.
+//!
+//! When the `cache` feature is disabled (e.g., for WASM builds), loading returns
+//! an empty index and saving is a no-op.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use lsp_types::Diagnostic;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::dae::balance::BalanceResult;
+use crate::ir::ast::StoredDefinition;
+
+use crate::compiler::outline::parse_outline;
+
+use super::utils::parse_file_cached;
+use super::workspace::SymbolKind;
+
+/// Index format version - increment when the persisted structures change
+const INDEX_VERSION: u32 = 1;
+
+/// Files with at least this many lines are indexed from their outline
+/// (see [`crate::compiler::outline`]) instead of a full parse
+pub const LARGE_FILE_LINES: usize = 20_000;
+
+/// A workspace symbol as stored on disk (the URI is derived from the file path)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedSymbol {
+    pub qualified_name: String,
+    pub line: u32,
+    pub column: u32,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+}
+
+/// Diagnostics and balance results of a document, valid for one content hash
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedDiagnostics {
+    pub hash: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub balances: Vec<(String, BalanceResult)>,
+}
+
+/// Indexed symbols of a file, valid for one content hash
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub hash: String,
+    pub symbols: Vec<CachedSymbol>,
+}
+
+/// Persisted workspace index
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistedIndex {
+    version: String,
+    /// Symbols per discovered file
+    pub files: HashMap<PathBuf, CachedFile>,
+    /// Diagnostics per opened document
+    pub diagnostics: HashMap<PathBuf, CachedDiagnostics>,
+}
+
+/// A file parsed for the workspace index
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub hash: String,
+    pub text: String,
+    pub ast: Option<StoredDefinition>,
+    /// Whether `ast` is only the outline of the file (no equations or algorithms)
+    pub outlined: bool,
+}
+
+/// Compute the content hash used to key index entries
+pub fn content_hash(text: &str) -> String {
+    format!("{:x}", chksum_md5::hash(text.as_bytes()))
+}
+
+/// Read, hash and parse files in parallel.
+///
+/// Very large files are only outlined; their full AST is parsed when it is
+/// first needed.
+///
+/// This does not touch the workspace, so it can run on a background thread.
+/// Files that cannot be read are skipped.
+pub fn index_files(paths: &[PathBuf]) -> Vec<IndexedFile> {
+    paths
+        .par_iter()
+        .filter_map(|path| {
+            let text = crate::compiler::read_source(path).ok()?;
+            let hash = content_hash(&text);
+            let outlined = text.lines().count() >= LARGE_FILE_LINES;
+            let ast = if outlined {
+                Some(parse_outline(&text, &path.to_string_lossy()))
+            } else {
+                // Uses the AST disk cache for files that haven't changed since last parse
+                parse_file_cached(path)
+            };
+            Some(IndexedFile {
+                path: path.clone(),
+                hash,
+                text,
+                ast,
+                outlined,
+            })
+        })
+        .collect()
+}
+
+fn current_version() -> String {
+    format!(
+        "{}:{}:{}",
+        INDEX_VERSION,
+        env!("CARGO_PKG_VERSION"),
+        env!("RUMOCA_GIT_VERSION")
+    )
+}
+
+/// Location of the index file for a set of workspace roots
+#[cfg(feature = "cache")]
+pub fn index_path(roots: &[PathBuf]) -> Option<PathBuf> {
+    let mut roots: Vec<String> = roots.iter().map(|r| r.display().to_string()).collect();
+    roots.sort();
+    let key = content_hash(&roots.join("\n"));
+    dirs::cache_dir().map(|d| d.join("rumoca").join("lsp").join(format!("{}.json", key)))
+}
+
+#[cfg(not(feature = "cache"))]
+pub fn index_path(_roots: &[PathBuf]) -> Option<PathBuf> {
+    None // No persistent index when caching is disabled
+}
+
+impl PersistedIndex {
+    /// Load the index from disk, returning an empty index if it is missing,
+    /// unreadable or was written by a different rumoca build.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<PersistedIndex>(&data).ok())
+            .filter(|index| index.version == current_version())
+            .unwrap_or_default()
+    }
+
+    /// Whether the index has no entries
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.diagnostics.is_empty()
+    }
+
+    /// Write the index to disk
+    pub fn save(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.version = current_version();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write to a temporary file first so a crash never leaves a truncated index
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_round_trip() {
+        let dir = std::env::temp_dir().join(format!("rumoca_index_{}", std::process::id()));
+        let path = dir.join("index.json");
+
+        let mut index = PersistedIndex::default();
+        index.files.insert(
+            PathBuf::from("/ws/A.mo"),
+            CachedFile {
+                hash: content_hash("model A end A;"),
+                symbols: vec![CachedSymbol {
+                    qualified_name: "A".to_string(),
+                    line: 0,
+                    column: 6,
+                    kind: SymbolKind::Model,
+                    detail: Some("Model".to_string()),
+                }],
+            },
+        );
+        index.save(&path).unwrap();
+
+        let loaded = PersistedIndex::load(&path);
+        let file = &loaded.files[&PathBuf::from("/ws/A.mo")];
+        assert_eq!(file.hash, content_hash("model A end A;"));
+        assert_eq!(file.symbols[0].qualified_name, "A");
+
+        // An index written by another build is discarded
+        std::fs::write(&path, r#"{"version":"0","files":{},"diagnostics":{}}"#).unwrap();
+        assert!(PersistedIndex::load(&path).files.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -19,15 +19,25 @@
 //! - Code actions (quick fixes)
 //! - Inlay hints
 //! - Multi-file workspace support
-//! - Code formatting
+//! - Code formatting (documents and ranges)
 //! - Code lenses
 //! - Call hierarchy
 //! - Document links
+//! - Commands to analyze a class and to evaluate constant expressions
+//! - Workspace settings (initialization options and didChangeConfiguration)
+//! - Persistent workspace index for fast startup
+//! - Expected-diagnostic annotations for conformance tests
+//! - Compile statistics notifications for status bars
 
 pub mod analyze;
 pub mod data;
+pub mod evaluate;
+pub mod expectations;
 pub mod features;
 pub mod handlers;
+pub mod index_cache;
+pub mod settings;
+pub mod stats;
 pub mod utils;
 pub mod workspace;
 
@@ -39,12 +49,14 @@ pub use features::{
 };
 pub use handlers::{
     get_semantic_token_legend, handle_completion_workspace, handle_document_symbols,
-    handle_formatting, handle_goto_definition, handle_goto_definition_workspace, handle_hover,
-    handle_hover_workspace, handle_incoming_calls, handle_outgoing_calls,
-    handle_prepare_call_hierarchy, handle_prepare_rename, handle_references, handle_rename,
-    handle_rename_workspace, handle_semantic_tokens, handle_signature_help, handle_type_definition,
-    handle_workspace_symbol,
+    handle_formatting, handle_formatting_with_settings, handle_goto_definition,
+    handle_goto_definition_workspace, handle_hover, handle_hover_workspace, handle_incoming_calls,
+    handle_outgoing_calls, handle_prepare_call_hierarchy, handle_prepare_rename,
+    handle_range_formatting, handle_range_formatting_with_settings, handle_references,
+    handle_rename, handle_rename_workspace, handle_semantic_tokens, handle_signature_help,
+    handle_type_definition, handle_workspace_symbol,
 };
+pub use settings::LspSettings;
 pub use utils::parse_document;
 pub use workspace::WorkspaceState;
 
@@ -0,0 +1,236 @@
+//! Workspace settings supplied by the editor.
+//!
+//! Settings arrive through `initializationOptions` at startup and through
+//! `workspace/didChangeConfiguration` afterwards. Both payloads are accepted either
+//! as the settings object itself or nested under a `rumoca` key, e.g.:
+//!
+//! ```json
+//! {
+//!   "rumoca": {
+//!     "modelicaPath": ["/opt/modelica"],
+//!     "format": { "indentSize": 4 },
+//!     "lint": { "enabled": true, "disabledRules": ["magic-number"] },
+//!     "balanceLens": false,
+//!     "limits": { "maxExpansionSize": 100000, "timeoutSeconds": 5 }
+//!   }
+//! }
+//! ```
+//!
+//! Lint settings are applied on top of the nearest `.rumoca_lint.toml` file.
+//! Models exceeding the [`LimitSettings`] are reported with a `resource-limit`
+//! diagnostic instead of blocking the server.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use lsp_types::FormattingOptions;
+use serde::Deserialize;
+
+use crate::compiler::limits::{DEFAULT_MAX_EXPANSION_SIZE, DEFAULT_MAX_INSTANCE_DEPTH};
+use crate::fmt::{ExponentStyle, FormatOptions};
+use crate::lint::{LintConfig, LintLevel};
+
+/// Settings section name used by editors
+pub const SETTINGS_SECTION: &str = "rumoca";
+
+/// Editor-provided settings for the language server
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LspSettings {
+    /// Verbose logging to stderr
+    pub debug: bool,
+    /// Additional library directories (searched before MODELICAPATH)
+    pub modelica_path: Vec<PathBuf>,
+    /// Formatter overrides
+    pub format: FormatSettings,
+    /// Lint diagnostics
+    pub lint: LintSettings,
+    /// Show balance code lenses on models, blocks and connectors
+    pub balance_lens: bool,
+    /// Resource limits of the compilation of a model
+    pub limits: LimitSettings,
+}
+
+impl Default for LspSettings {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            modelica_path: Vec::new(),
+            format: FormatSettings::default(),
+            lint: LintSettings::default(),
+            balance_lens: true,
+            limits: LimitSettings::default(),
+        }
+    }
+}
+
+/// Resource limits of the compilation of a model, see [`crate::compiler::limits`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LimitSettings {
+    /// Maximum number of elements an array or a for-equation may expand to
+    pub max_expansion_size: usize,
+    /// Maximum depth of nested components
+    pub max_instance_depth: usize,
+    /// Wall-clock time after which the analysis of a model stops (`null`: no limit)
+    pub timeout_seconds: Option<f64>,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self {
+            max_expansion_size: DEFAULT_MAX_EXPANSION_SIZE,
+            max_instance_depth: DEFAULT_MAX_INSTANCE_DEPTH,
+            timeout_seconds: Some(10.0),
+        }
+    }
+}
+
+impl LimitSettings {
+    /// Timeout of the compilation of a model, if any
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_seconds
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+    }
+}
+
+/// Formatter overrides; unset fields fall back to the editor's formatting options
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatSettings {
+    pub indent_size: Option<usize>,
+    pub use_tabs: Option<bool>,
+    pub max_line_length: Option<usize>,
+    pub exponent_style: Option<ExponentStyle>,
+}
+
+/// Lint diagnostics settings
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LintSettings {
+    /// Publish lint messages as diagnostics
+    pub enabled: bool,
+    /// Minimum severity to report
+    pub min_level: Option<LintLevel>,
+    /// Rules to disable
+    pub disabled_rules: Vec<String>,
+    /// Rules to run (if empty, all are run)
+    pub enabled_rules: Vec<String>,
+}
+
+impl LspSettings {
+    /// Parse settings from an `initializationOptions` or `didChangeConfiguration` payload.
+    ///
+    /// Unknown keys are ignored; a malformed payload yields `None`.
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        let value = value.get(SETTINGS_SECTION).unwrap_or(value);
+        if !value.is_object() {
+            return None;
+        }
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    /// Formatting options for a request, applying the workspace overrides
+    pub fn format_options(&self, editor: &FormattingOptions) -> FormatOptions {
+        let mut options = FormatOptions {
+            indent_size: editor.tab_size as usize,
+            use_tabs: !editor.insert_spaces,
+            ..FormatOptions::default()
+        };
+        options.merge_cli_options(
+            self.format.indent_size,
+            self.format.use_tabs,
+            self.format.max_line_length,
+        );
+        if let Some(style) = self.format.exponent_style {
+            options.exponent_style = style;
+        }
+        options
+    }
+
+    /// Lint configuration for a file, or `None` if lint diagnostics are disabled.
+    ///
+    /// Starts from the nearest lint config file (if any) and applies the workspace settings.
+    pub fn lint_config(&self, file_path: &str) -> Option<LintConfig> {
+        if !self.lint.enabled {
+            return None;
+        }
+        let path = std::path::Path::new(file_path);
+        let mut config = path
+            .parent()
+            .and_then(LintConfig::from_config_file)
+            .unwrap_or_default();
+        config.merge_cli_options(
+            self.lint.min_level,
+            &self.lint.disabled_rules,
+            &self.lint.enabled_rules,
+            None,
+        );
+        Some(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_settings_nested_and_flat() {
+        let nested = json!({ "rumoca": { "balanceLens": false, "format": { "indentSize": 4 } } });
+        let settings = LspSettings::from_value(&nested).unwrap();
+        assert!(!settings.balance_lens);
+        assert_eq!(settings.format.indent_size, Some(4));
+
+        let flat = json!({ "debug": true, "modelicaPath": ["/lib"], "unknown": 1 });
+        let settings = LspSettings::from_value(&flat).unwrap();
+        assert!(settings.debug);
+        assert!(settings.balance_lens);
+        assert_eq!(settings.modelica_path, vec![PathBuf::from("/lib")]);
+
+        assert!(LspSettings::from_value(&json!(null)).is_none());
+
+        let limits = json!({ "limits": { "maxExpansionSize": 10, "timeoutSeconds": null } });
+        let settings = LspSettings::from_value(&limits).unwrap();
+        assert_eq!(settings.limits.max_expansion_size, 10);
+        assert_eq!(
+            settings.limits.max_instance_depth,
+            DEFAULT_MAX_INSTANCE_DEPTH
+        );
+        assert_eq!(settings.limits.timeout(), None);
+        assert_eq!(
+            LspSettings::default().limits.timeout(),
+            Some(Duration::from_secs(10))
+        );
+        assert!(LspSettings::from_value(&json!({ "lint": { "enabled": "yes" } })).is_none());
+    }
+
+    #[test]
+    fn test_settings_override_editor_format_options() {
+        let editor = FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        };
+        let settings = LspSettings::default();
+        assert_eq!(settings.format_options(&editor).indent_size, 2);
+
+        let settings = LspSettings::from_value(&json!({ "format": { "indentSize": 4 } })).unwrap();
+        let options = settings.format_options(&editor);
+        assert_eq!(options.indent_size, 4);
+        assert!(!options.use_tabs);
+    }
+
+    #[test]
+    fn test_lint_config_from_settings() {
+        assert!(LspSettings::default().lint_config("/x/a.mo").is_none());
+
+        let settings = LspSettings::from_value(&json!({
+            "lint": { "enabled": true, "minLevel": "warning", "disabledRules": ["magic-number"] }
+        }))
+        .unwrap();
+        let config = settings.lint_config("/nonexistent/a.mo").unwrap();
+        assert_eq!(config.min_level, LintLevel::Warning);
+        assert!(!config.should_run("magic-number"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Compile statistics of the analysis passes.
+//!
+//! After computing the diagnostics of a document, the server sends a
+//! `rumoca/compileStats` notification with the time spent parsing, flattening
+//! and creating DAEs, the number of classes compiled and the diagnostic
+//! counts, e.g.:
+//!
+//! ```json
+//! {
+//!   "uri": "file:///models/Rover.mo",
+//!   "parseMs": 1.2,
+//!   "flattenMs": 8.4,
+//!   "daeMs": 3.1,
+//!   "totalMs": 15.9,
+//!   "classes": 4,
+//!   "compiledModels": 2,
+//!   "cachedModels": 1,
+//!   "diagnostics": { "errors": 0, "warnings": 2, "information": 0, "hints": 1 }
+//! }
+//! ```
+//!
+//! Editors can show it in a status bar to make slow models noticeable.
+
+use std::time::Duration;
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, Uri};
+use serde::{Deserialize, Serialize};
+
+/// The `rumoca/compileStats` notification
+pub enum CompileStatsNotification {}
+
+impl lsp_types::notification::Notification for CompileStatsNotification {
+    type Params = CompileStats;
+    const METHOD: &'static str = "rumoca/compileStats";
+}
+
+/// Statistics of the last analysis pass of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileStats {
+    pub uri: Uri,
+    /// Parsing the document
+    pub parse_ms: f64,
+    /// Flattening its models, summed over the models
+    pub flatten_ms: f64,
+    /// Creating the DAEs of its models, summed over the models
+    pub dae_ms: f64,
+    /// The whole pass, including the semantic checks and lint rules
+    pub total_ms: f64,
+    /// Classes defined in the document, nested ones included
+    pub classes: usize,
+    /// Models, blocks and connectors compiled in this pass
+    pub compiled_models: usize,
+    /// Models, blocks and connectors whose results were reused
+    pub cached_models: usize,
+    pub diagnostics: DiagnosticCounts,
+}
+
+impl CompileStats {
+    pub fn new(uri: Uri) -> Self {
+        Self {
+            uri,
+            parse_ms: 0.0,
+            flatten_ms: 0.0,
+            dae_ms: 0.0,
+            total_ms: 0.0,
+            classes: 0,
+            compiled_models: 0,
+            cached_models: 0,
+            diagnostics: DiagnosticCounts::default(),
+        }
+    }
+}
+
+/// Number of diagnostics of each severity
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticCounts {
+    pub errors: usize,
+    pub warnings: usize,
+    pub information: usize,
+    pub hints: usize,
+}
+
+impl DiagnosticCounts {
+    pub fn new(diagnostics: &[Diagnostic]) -> Self {
+        let mut counts = Self::default();
+        for diagnostic in diagnostics {
+            match diagnostic.severity {
+                Some(DiagnosticSeverity::WARNING) => counts.warnings += 1,
+                Some(DiagnosticSeverity::INFORMATION) => counts.information += 1,
+                Some(DiagnosticSeverity::HINT) => counts.hints += 1,
+                // Clients treat diagnostics without a severity as errors
+                _ => counts.errors += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// Milliseconds of a duration, as reported in [`CompileStats`]
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -1,28 +1,66 @@
 //! Utility functions for LSP handlers.
 
+use std::collections::HashMap;
+use std::ops::Range as ByteRange;
+
+use crate::compiler::paths::{file_uri_to_path, normalize_file_uri};
 use crate::ir::ast::{Location, Token};
-use lsp_types::{Position, Range};
+use crate::modelica_grammar::cst::{SyntaxKind, SyntaxToken, SyntaxTree};
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent, Uri};
 
 // Re-export compiler parsing functions for LSP use
-pub use crate::compiler::{parse_file_cached, parse_source_simple as parse_document};
+pub use crate::compiler::{
+    parse_file_cached, parse_source_lossless as parse_document_lossless,
+    parse_source_simple as parse_document,
+};
+
+/// Byte offset of a character column within a line, clamped to the line length.
+///
+/// Position characters count Unicode characters, like the columns of AST locations.
+pub fn char_to_byte(line: &str, character: u32) -> usize {
+    line.char_indices()
+        .nth(character as usize)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+/// Character column of a byte offset within a line
+pub fn byte_to_char(line: &str, byte: usize) -> u32 {
+    line[..byte].chars().count() as u32
+}
 
 /// Get the text before the cursor on the current line
 pub fn get_text_before_cursor(text: &str, position: Position) -> Option<String> {
     let lines: Vec<&str> = text.lines().collect();
     let line = lines.get(position.line as usize)?;
-    let col = (position.character as usize).min(line.len());
+    let col = char_to_byte(line, position.character);
     Some(line[..col].to_string())
 }
 
 /// Get the word at the given position in text
+///
+/// Quoted identifiers are returned whole, with their quotes (`'my var'`).
 pub fn get_word_at_position(text: &str, position: Position) -> Option<String> {
-    let lines: Vec<&str> = text.lines().collect();
-    let line = lines.get(position.line as usize)?;
-    let col = position.character as usize;
+    let (line, range) = get_word_range_at_position(text, position)?;
+    Some(line[range].to_string())
+}
 
-    if col > line.len() {
+/// Get the line at the given position and the byte range of the word there
+pub fn get_word_range_at_position(
+    text: &str,
+    position: Position,
+) -> Option<(&str, ByteRange<usize>)> {
+    let line = text.lines().nth(position.line as usize)?;
+    if position.character as usize > line.chars().count() {
         return None;
     }
+    let col = char_to_byte(line, position.character);
+
+    if line.contains('\'')
+        && let Some(token) = quoted_ident_at(&line_tokens(line), col)
+    {
+        return Some((line, token.span.clone()));
+    }
 
     // Find word boundaries
     let start = line[..col]
@@ -39,7 +77,63 @@ pub fn get_word_at_position(text: &str, position: Position) -> Option<String> {
         return None;
     }
 
-    Some(line[start..end].to_string())
+    Some((line, start..end))
+}
+
+/// Tokens of a single line
+fn line_tokens(line: &str) -> Vec<SyntaxToken> {
+    SyntaxTree::parse(line).tokens().to_vec()
+}
+
+/// The quoted identifier touching a byte offset, if any
+fn quoted_ident_at(tokens: &[SyntaxToken], col: usize) -> Option<&SyntaxToken> {
+    tokens.iter().find(|t| {
+        t.kind == SyntaxKind::Ident
+            && t.text.starts_with('\'')
+            && t.span.start <= col
+            && col <= t.span.end
+    })
+}
+
+/// The dotted name ending at the end of a text, e.g. `'my sub'.x` for
+/// `y = 'my sub'.x`, or an empty string
+pub fn get_qualified_name_before(text: &str) -> String {
+    let tokens = line_tokens(text);
+    match tokens
+        .iter()
+        .rposition(|t| t.kind != SyntaxKind::Eof)
+        .filter(|&i| is_name_token(&tokens[i]) && tokens[i].span.end == text.len())
+    {
+        Some(last) => text[dotted_span(&tokens, last)].to_string(),
+        None => String::new(),
+    }
+}
+
+fn is_name_token(token: &SyntaxToken) -> bool {
+    matches!(token.kind, SyntaxKind::Ident | SyntaxKind::Keyword)
+}
+
+/// Byte range of the dotted name around the name token at an index, e.g.
+/// `'a'.b.'c'` for any of its three names
+fn dotted_span(tokens: &[SyntaxToken], at: usize) -> ByteRange<usize> {
+    let touching = |i: usize| tokens[i].span.end == tokens[i + 1].span.start;
+    // Whether tokens i, i + 1 and i + 2 are a name, a dot and a name, without spaces
+    let linked = |i: usize| {
+        is_name_token(&tokens[i])
+            && tokens[i + 1].is_symbol(".")
+            && is_name_token(&tokens[i + 2])
+            && touching(i)
+            && touching(i + 1)
+    };
+    let mut first = at;
+    while first >= 2 && linked(first - 2) {
+        first -= 2;
+    }
+    let mut last = at;
+    while last + 2 < tokens.len() && linked(last) {
+        last += 2;
+    }
+    tokens[first].span.start..tokens[last].span.end
 }
 
 /// Get a qualified name (dotted path like SI.Mass) at the given position in text
@@ -47,11 +141,20 @@ pub fn get_word_at_position(text: &str, position: Position) -> Option<String> {
 pub fn get_qualified_name_at_position(text: &str, position: Position) -> Option<String> {
     let lines: Vec<&str> = text.lines().collect();
     let line = lines.get(position.line as usize)?;
-    let col = position.character as usize;
-
-    if col > line.len() {
+    if position.character as usize > line.chars().count() {
         return None;
     }
+    let col = char_to_byte(line, position.character);
+
+    // With quoted identifiers, which may contain dots, use the tokens
+    if line.contains('\'') {
+        let tokens = line_tokens(line);
+        let at = tokens
+            .iter()
+            .position(|t| is_name_token(t) && t.span.start <= col && col <= t.span.end)?;
+        let range = dotted_span(&tokens, at);
+        return Some(line[range].to_string());
+    }
 
     // Find boundaries including dots for qualified names
     let start = line[..col]
@@ -90,13 +193,13 @@ pub fn find_function_at_cursor(text: &str, position: Position) -> Option<(String
     let mut current_arg = 0;
     let mut func_start = None;
 
-    for (i, ch) in text_before.chars().rev().enumerate() {
+    for (i, ch) in text_before.char_indices().rev() {
         match ch {
             ')' => paren_depth += 1,
             '(' => {
                 if paren_depth == 0 {
                     // Found our opening paren
-                    func_start = Some(text_before.len() - i - 1);
+                    func_start = Some(i);
                     break;
                 }
                 paren_depth -= 1;
@@ -187,3 +290,255 @@ pub fn position_in_range(pos: Position, range: &Range) -> bool {
     }
     true
 }
+
+// ============================================================================
+// Position encoding
+// ============================================================================
+//
+// Handlers work with character columns, like the columns of AST locations,
+// while the LSP protocol counts UTF-16 code units. The two only differ on lines
+// with non-ASCII text, and positions are converted when messages cross the
+// protocol boundary (see `positions_from_utf16` and `positions_to_utf16`).
+
+/// Lines of a document, for converting between character and UTF-16 columns
+pub struct LineIndex<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().collect(),
+        }
+    }
+
+    /// Convert a position with a character column to a UTF-16 column.
+    ///
+    /// Columns past the end of the line keep their distance to it.
+    pub fn to_utf16(&self, pos: Position) -> Position {
+        let Some(line) = self.lines.get(pos.line as usize) else {
+            return pos;
+        };
+        let mut chars = 0;
+        let mut units = 0;
+        for c in line.chars().take(pos.character as usize) {
+            chars += 1;
+            units += c.len_utf16() as u32;
+        }
+        Position {
+            line: pos.line,
+            character: units.saturating_add(pos.character - chars),
+        }
+    }
+
+    /// Convert a position with a UTF-16 column to a character column.
+    ///
+    /// A column inside a surrogate pair maps to the character after it.
+    pub fn from_utf16(&self, pos: Position) -> Position {
+        let Some(line) = self.lines.get(pos.line as usize) else {
+            return pos;
+        };
+        let mut chars = 0u32;
+        let mut units = 0;
+        for c in line.chars() {
+            if units >= pos.character {
+                break;
+            }
+            chars += 1;
+            units += c.len_utf16() as u32;
+        }
+        Position {
+            line: pos.line,
+            character: chars.saturating_add(pos.character.saturating_sub(units)),
+        }
+    }
+}
+
+/// UTF-16 length of a string, as LSP counts lengths
+pub fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+/// Byte offset of a position with a UTF-16 column, clamped to the end of its line.
+///
+/// Lines end with `\n` or `\r\n`, and a line past the last one maps to the
+/// end of the text. A column inside a surrogate pair maps to the character
+/// after it.
+pub fn utf16_to_byte(text: &str, pos: Position) -> usize {
+    let mut start = 0;
+    for _ in 0..pos.line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let line = &text[start..];
+    let line = line.find('\n').map_or(line, |i| &line[..i]);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= pos.character {
+            return start + i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    start + line.len()
+}
+
+/// Apply the content changes of a `didChange` notification to the text of a
+/// document.
+///
+/// Changes apply in order, each to the text left by the ones before. A change
+/// with a range (incremental sync) replaces the text of the range, whose
+/// positions count UTF-16 code units, and a change without one replaces the
+/// whole text.
+pub fn apply_content_changes(text: &mut String, changes: Vec<TextDocumentContentChangeEvent>) {
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = utf16_to_byte(text, range.start);
+                let end = utf16_to_byte(text, range.end).max(start);
+                text.replace_range(start..end, &change.text);
+            }
+            None => *text = change.text,
+        }
+    }
+}
+
+/// Convert the positions in the params of a client message from UTF-16 to character columns.
+///
+/// Each position is converted using the text of the document it refers to, which
+/// is the nearest enclosing `uri`, `targetUri` or `textDocument.uri`.
+pub fn positions_from_utf16(params: &mut serde_json::Value, documents: &HashMap<Uri, String>) {
+    PositionMapper::new(documents, LineIndex::from_utf16).map(params, None);
+}
+
+/// Convert the positions in a server message (a response result or notification
+/// params) from character columns to UTF-16.
+///
+/// Positions outside of anything naming a document refer to `uri`, the document
+/// of the request being answered.
+pub fn positions_to_utf16(
+    value: &mut serde_json::Value,
+    documents: &HashMap<Uri, String>,
+    uri: Option<&Uri>,
+) {
+    PositionMapper::new(documents, LineIndex::to_utf16).map(value, uri.map(|uri| uri.as_str()));
+}
+
+/// The file path of a document URI, see [`file_uri_to_path`]
+///
+/// URIs of other schemes, like `untitled:`, give their path as it is.
+pub fn uri_to_path(uri: &Uri) -> String {
+    file_uri_to_path(uri.as_str()).unwrap_or_else(|| uri.path().as_str().to_string())
+}
+
+/// Normalize the `file://` URIs in the params of a client message (see
+/// [`normalize_file_uri`]), so that the server sees a single URI per file
+/// whatever form the client uses, e.g. `file:///c%3A/a.mo` or `file:///C:/a.mo`
+pub fn normalize_uris(params: &mut serde_json::Value) {
+    use serde_json::Value;
+
+    match params {
+        Value::Array(items) => items.iter_mut().for_each(normalize_uris),
+        Value::Object(object) => {
+            for (key, item) in object.iter_mut() {
+                match item {
+                    Value::String(uri) if matches!(key.as_str(), "uri" | "targetUri") => {
+                        if let Some(normalized) = normalize_file_uri(uri) {
+                            *uri = normalized;
+                        }
+                    }
+                    item => normalize_uris(item),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The document a client message refers to (its `textDocument.uri`), if any
+pub fn request_document(params: &serde_json::Value) -> Option<Uri> {
+    params
+        .pointer("/textDocument/uri")
+        .and_then(serde_json::Value::as_str)
+        .and_then(|uri| uri.parse().ok())
+}
+
+struct PositionMapper<'a, F> {
+    documents: &'a HashMap<Uri, String>,
+    indexes: HashMap<String, Option<LineIndex<'a>>>,
+    convert: F,
+}
+
+impl<'a, F: Fn(&LineIndex<'a>, Position) -> Position> PositionMapper<'a, F> {
+    fn new(documents: &'a HashMap<Uri, String>, convert: F) -> Self {
+        Self {
+            documents,
+            indexes: HashMap::new(),
+            convert,
+        }
+    }
+
+    fn map(&mut self, value: &mut serde_json::Value, uri: Option<&str>) {
+        use serde_json::Value;
+
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    self.map(item, uri);
+                }
+            }
+            Value::Object(object) => {
+                if let (Some(line), Some(character), 2) = (
+                    object.get("line").and_then(Value::as_u64),
+                    object.get("character").and_then(Value::as_u64),
+                    object.len(),
+                ) {
+                    if let Some(uri) = uri {
+                        let pos = Position {
+                            line: line as u32,
+                            character: character as u32,
+                        };
+                        if let Some(pos) = self.convert(uri, pos) {
+                            object.insert("character".to_string(), pos.character.into());
+                        }
+                    }
+                    return;
+                }
+
+                let own_uri = ["/uri", "/targetUri", "/textDocument/uri"]
+                    .iter()
+                    .find_map(|pointer| value.pointer(pointer).and_then(Value::as_str))
+                    .map(str::to_string);
+                let Value::Object(object) = value else {
+                    return;
+                };
+                let inner_uri = own_uri.as_deref().or(uri);
+                for (key, item) in object.iter_mut() {
+                    match (key.as_str(), item) {
+                        // A WorkspaceEdit's changes are keyed by document
+                        ("changes", Value::Object(changes)) => {
+                            for (uri, edits) in changes.iter_mut() {
+                                self.map(edits, Some(uri));
+                            }
+                        }
+                        // A LocationLink's origin is in the requesting document
+                        ("originSelectionRange", item) => self.map(item, uri),
+                        (_, item) => self.map(item, inner_uri),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn convert(&mut self, uri: &str, pos: Position) -> Option<Position> {
+        let documents = self.documents;
+        let index = self.indexes.entry(uri.to_string()).or_insert_with(|| {
+            let uri: Uri = uri.parse().ok()?;
+            documents.get(&uri).map(|text| LineIndex::new(text))
+        });
+        Some((self.convert)(index.as_ref()?, pos))
+    }
+}
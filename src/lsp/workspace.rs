@@ -5,13 +5,16 @@
 //! - Package structure discovery and management
 //! - Cross-file symbol lookup
 //! - Dependency tracking between files
+//! - Persistent index for fast startup (see [`super::index_cache`])
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use lsp_types::Uri;
-use rayon::prelude::*;
+use lsp_types::{Diagnostic, TextDocumentContentChangeEvent, Uri};
+use serde::{Deserialize, Serialize};
 
+use crate::compiler::paths::{path_to_file_uri, starts_with_path};
 use crate::dae::balance::BalanceResult;
 use crate::ir::ast::{ClassDefinition, ClassType, Import, StoredDefinition};
 use crate::ir::transform::multi_file::{
@@ -19,7 +22,14 @@ use crate::ir::transform::multi_file::{
 };
 use crate::ir::transform::scope_resolver::{SymbolCategory, SymbolInfo, SymbolLookup};
 
-use super::utils::{parse_document, parse_file_cached};
+use super::features::diagnostics::{BltBlocks, InstanceUses};
+use super::index_cache::{
+    CachedDiagnostics, CachedFile, CachedSymbol, IndexedFile, PersistedIndex, content_hash,
+    index_files, index_path,
+};
+use super::settings::LspSettings;
+use super::stats::CompileStats;
+use super::utils::{apply_content_changes, parse_document, uri_to_path};
 
 /// Information about a symbol in the workspace
 #[derive(Debug, Clone)]
@@ -39,7 +49,7 @@ pub struct WorkspaceSymbol {
 }
 
 /// Kind of workspace symbol
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SymbolKind {
     Package,
     Model,
@@ -71,12 +81,30 @@ impl From<&ClassType> for SymbolKind {
     }
 }
 
+/// A cached balance result, and what it was computed from
+#[derive(Debug, Clone)]
+struct CachedBalance {
+    /// Hash of the class source, empty if unknown
+    key: String,
+    /// Edits of other documents when it was computed
+    external_edits: u64,
+    balance: BalanceResult,
+    /// Parameter and connector uses of the class
+    instances: InstanceUses,
+    /// BLT blocks of the equations of the class
+    blocks: BltBlocks,
+    /// Whether it is shown, e.g. by code lenses
+    visible: bool,
+}
+
 /// Workspace state for multi-file support
 pub struct WorkspaceState {
     /// All open documents and their content
     documents: HashMap<Uri, String>,
     /// Parsed ASTs for each document (last successful parse)
     parsed_asts: HashMap<Uri, StoredDefinition>,
+    /// Full ASTs of files indexed from their outline, parsed on first use
+    outlined_asts: HashMap<Uri, OnceLock<Option<StoredDefinition>>>,
     /// Global symbol index: qualified name -> symbol info
     symbol_index: HashMap<String, WorkspaceSymbol>,
     /// Reverse index: URI -> list of symbols defined in that file
@@ -92,7 +120,23 @@ pub struct WorkspaceState {
     cached_asts: HashMap<Uri, StoredDefinition>,
     /// Cache of balance check results per class name (computed during diagnostics)
     /// Key is (Uri, class_name) to support multiple classes per file
-    balance_cache: HashMap<(Uri, String), BalanceResult>,
+    balance_cache: HashMap<(Uri, String), CachedBalance>,
+    /// Statistics of the last diagnostics pass of each document
+    compile_stats: HashMap<Uri, CompileStats>,
+    /// Number of edits of each open document, and of all documents, so cached
+    /// balances are only reused while no other document changed
+    document_edits: HashMap<Uri, u64>,
+    total_edits: u64,
+    /// Editor-provided workspace settings
+    settings: LspSettings,
+    /// Library paths from settings (kept to detect changes)
+    extra_library_paths: Vec<PathBuf>,
+    /// Documents opened in the editor (their text is not replaced by background indexing)
+    open_uris: HashSet<Uri>,
+    /// Persisted symbol index and diagnostics
+    persisted: PersistedIndex,
+    /// Where the persisted index is stored (None disables persistence)
+    index_path: Option<PathBuf>,
     /// Debug mode flag for verbose logging
     debug: bool,
 }
@@ -109,6 +153,7 @@ impl WorkspaceState {
         Self {
             documents: HashMap::new(),
             parsed_asts: HashMap::new(),
+            outlined_asts: HashMap::new(),
             symbol_index: HashMap::new(),
             file_symbols: HashMap::new(),
             package_roots: Vec::new(),
@@ -116,6 +161,14 @@ impl WorkspaceState {
             discovered_files: HashSet::new(),
             cached_asts: HashMap::new(),
             balance_cache: HashMap::new(),
+            compile_stats: HashMap::new(),
+            document_edits: HashMap::new(),
+            total_edits: 0,
+            settings: LspSettings::default(),
+            extra_library_paths: Vec::new(),
+            open_uris: HashSet::new(),
+            persisted: PersistedIndex::default(),
+            index_path: None,
             debug: false,
         }
     }
@@ -192,7 +245,8 @@ impl WorkspaceState {
         ws
     }
 
-    /// Add a document to the workspace (for WASM use).
+    /// Add a document to the workspace without opening it in the editor
+    /// (library files, WASM use).
     ///
     /// This parses the document and indexes its symbols.
     pub fn add_document(&mut self, uri: Uri, text: String) {
@@ -205,6 +259,68 @@ impl WorkspaceState {
         self.debug = debug;
     }
 
+    /// Get the current workspace settings
+    pub fn settings(&self) -> &LspSettings {
+        &self.settings
+    }
+
+    /// Replace the workspace settings.
+    ///
+    /// Re-discovers libraries when the configured library paths changed.
+    /// Returns true if the library paths changed.
+    pub fn set_settings(&mut self, settings: LspSettings) -> bool {
+        self.debug = settings.debug;
+        let paths_changed = settings.modelica_path != self.extra_library_paths;
+        self.settings = settings;
+        if paths_changed {
+            let paths = self.settings.modelica_path.clone();
+            self.set_library_paths(paths);
+        }
+        paths_changed
+    }
+
+    /// Replace the extra library paths and re-index the workspace.
+    ///
+    /// Symbols from files under removed library paths are dropped unless the file is open.
+    fn set_library_paths(&mut self, extra_library_paths: Vec<PathBuf>) {
+        let removed: Vec<PathBuf> = self
+            .extra_library_paths
+            .iter()
+            .filter(|p| !extra_library_paths.contains(p))
+            .cloned()
+            .collect();
+        let stale: Vec<Uri> = self
+            .file_symbols
+            .keys()
+            .filter(|uri| {
+                !self.documents.contains_key(*uri)
+                    && removed
+                        .iter()
+                        .any(|root| starts_with_path(&uri_to_path(uri), &root.to_string_lossy()))
+            })
+            .cloned()
+            .collect();
+        for uri in &stale {
+            self.remove_file_symbols(uri);
+            self.parsed_asts.remove(uri);
+            self.outlined_asts.remove(uri);
+            self.cached_asts.remove(uri);
+        }
+        self.discovered_files.retain(|file| {
+            !removed
+                .iter()
+                .any(|root| starts_with_path(&file.to_string_lossy(), &root.to_string_lossy()))
+        });
+        self.symbol_index
+            .retain(|_, symbol| !stale.contains(&symbol.uri));
+
+        // Library classes may have changed: invalidate all cached balances
+        self.total_edits += 1;
+        self.package_roots.clear();
+        let workspace_roots = self.workspace_roots.clone();
+        self.initialize(workspace_roots, extra_library_paths);
+    }
+
     /// Log a debug message if debug mode is enabled
     fn debug_log(&self, msg: &str) {
         if self.debug {
@@ -214,13 +330,77 @@ impl WorkspaceState {
 
     /// Set the cached balance result for a specific class in a document
     pub fn set_balance(&mut self, uri: Uri, class_name: String, balance: BalanceResult) {
-        self.balance_cache.insert((uri, class_name), balance);
+        self.cache_balance(
+            uri,
+            class_name,
+            String::new(),
+            balance,
+            Default::default(),
+            Default::default(),
+        );
+    }
+
+    /// Set the cached balance result for a class, computed from the class
+    /// source with hash `key`, so it can be reused while the source is
+    /// unchanged (see [`cached_balance`](Self::cached_balance)), with the
+    /// uses of the parameters and connectors of the class and the BLT blocks
+    /// of its equations
+    pub fn cache_balance(
+        &mut self,
+        uri: Uri,
+        class_name: String,
+        key: String,
+        balance: BalanceResult,
+        instances: InstanceUses,
+        blocks: BltBlocks,
+    ) {
+        let external_edits = self.external_edits(&uri);
+        self.balance_cache.insert(
+            (uri, class_name),
+            CachedBalance {
+                key,
+                external_edits,
+                balance,
+                instances,
+                blocks,
+                visible: true,
+            },
+        );
     }
 
     /// Get the cached balance result for a specific class in a document
     pub fn get_balance(&self, uri: &Uri, class_name: &str) -> Option<&BalanceResult> {
         self.balance_cache
             .get(&(uri.clone(), class_name.to_string()))
+            .filter(|cached| cached.visible)
+            .map(|cached| &cached.balance)
+    }
+
+    /// The cached balance result, instance uses and BLT blocks of a class, if
+    /// they were computed from a class source with the same hash `key` and no
+    /// other document changed since
+    pub fn cached_balance(
+        &self,
+        uri: &Uri,
+        class_name: &str,
+        key: &str,
+    ) -> Option<(&BalanceResult, &InstanceUses, &BltBlocks)> {
+        self.balance_cache
+            .get(&(uri.clone(), class_name.to_string()))
+            .filter(|cached| {
+                !key.is_empty()
+                    && cached.key == key
+                    && cached.external_edits == self.external_edits(uri)
+            })
+            .map(|cached| (&cached.balance, &cached.instances, &cached.blocks))
+    }
+
+    /// The BLT blocks of the equations of a class from its last successful
+    /// compilation
+    pub fn blt_blocks(&self, uri: &Uri, class_name: &str) -> Option<&BltBlocks> {
+        self.balance_cache
+            .get(&(uri.clone(), class_name.to_string()))
+            .map(|cached| &cached.blocks)
     }
 
     /// Clear all cached balance results for a document
@@ -228,6 +408,35 @@ impl WorkspaceState {
         self.balance_cache.retain(|(u, _), _| u != uri);
     }
 
+    /// Hide the cached balance results of a document (e.g. while it doesn't
+    /// parse), keeping them for reuse until the next
+    /// [`retain_balances`](Self::retain_balances)
+    pub fn hide_balances(&mut self, uri: &Uri) {
+        for ((u, _), cached) in self.balance_cache.iter_mut() {
+            if u == uri {
+                cached.visible = false;
+            }
+        }
+    }
+
+    /// Drop the cached balance results of a document's classes that are not
+    /// in `class_names`
+    pub fn retain_balances(&mut self, uri: &Uri, class_names: &HashSet<String>) {
+        self.balance_cache
+            .retain(|(u, class_name), _| u != uri || class_names.contains(class_name));
+    }
+
+    /// Number of edits of documents other than `uri`
+    fn external_edits(&self, uri: &Uri) -> u64 {
+        self.total_edits - self.document_edits.get(uri).copied().unwrap_or(0)
+    }
+
+    /// Count an edit of a document, see [`cached_balance`](Self::cached_balance)
+    fn count_edit(&mut self, uri: &Uri) {
+        *self.document_edits.entry(uri.clone()).or_default() += 1;
+        self.total_edits += 1;
+    }
+
     /// Initialize workspace with root folders and optional additional library paths
     ///
     /// # Arguments
@@ -238,12 +447,98 @@ impl WorkspaceState {
         workspace_folders: Vec<PathBuf>,
         extra_library_paths: Vec<PathBuf>,
     ) {
+        self.discover(workspace_folders, extra_library_paths);
+
+        // Index all discovered files upfront for reliable hover/goto-definition
+        let start = std::time::Instant::now();
+        let files = index_files(&self.files_to_index());
+        self.apply_indexed_files(files);
+        self.debug_log(&format!(
+            "[workspace] Upfront indexing complete in {:?}: {} symbols",
+            start.elapsed(),
+            self.symbol_index.len()
+        ));
+    }
+
+    /// Initialize workspace from the persisted index without parsing any file.
+    ///
+    /// Symbols of discovered files are restored from disk so completion and workspace
+    /// symbols work immediately. The caller is expected to reconcile the index afterwards
+    /// by running [`index_files`] on [`Self::files_to_index`] (e.g. on a background thread)
+    /// and passing the result to [`Self::apply_indexed_files`].
+    ///
+    /// Returns the number of files whose symbols were restored.
+    pub fn initialize_from_index(
+        &mut self,
+        workspace_folders: Vec<PathBuf>,
+        extra_library_paths: Vec<PathBuf>,
+    ) -> usize {
+        self.discover(workspace_folders, extra_library_paths);
+
+        let mut restored = 0;
+        let entries: Vec<(PathBuf, Vec<CachedSymbol>)> = self
+            .persisted
+            .files
+            .iter()
+            .filter(|(path, _)| self.discovered_files.contains(*path))
+            .map(|(path, file)| (path.clone(), file.symbols.clone()))
+            .collect();
+        for (path, symbols) in entries {
+            let Some(uri) = path_to_uri(&path) else {
+                continue;
+            };
+            let mut file_symbols = Vec::with_capacity(symbols.len());
+            for symbol in symbols {
+                file_symbols.push(symbol.qualified_name.clone());
+                self.symbol_index.insert(
+                    symbol.qualified_name.clone(),
+                    WorkspaceSymbol {
+                        qualified_name: symbol.qualified_name,
+                        uri: uri.clone(),
+                        line: symbol.line,
+                        column: symbol.column,
+                        kind: symbol.kind,
+                        detail: symbol.detail,
+                    },
+                );
+            }
+            self.file_symbols.insert(uri, file_symbols);
+            restored += 1;
+        }
+
+        self.debug_log(&format!(
+            "[workspace] Restored {} of {} files from persisted index ({} symbols)",
+            restored,
+            self.discovered_files.len(),
+            self.symbol_index.len()
+        ));
+        restored
+    }
+
+    /// Discover packages and files in the workspace and library paths
+    fn discover(&mut self, workspace_folders: Vec<PathBuf>, extra_library_paths: Vec<PathBuf>) {
         self.debug_log(&format!(
             "[workspace] initialize() called with {} folders, {} extra library paths",
             workspace_folders.len(),
             extra_library_paths.len()
         ));
         self.workspace_roots = workspace_folders.clone();
+        self.extra_library_paths = extra_library_paths.clone();
+
+        let roots: Vec<PathBuf> = workspace_folders
+            .iter()
+            .chain(&extra_library_paths)
+            .cloned()
+            .collect();
+        if self.index_path.is_none() {
+            self.index_path = index_path(&roots);
+        }
+        // Load once; re-initialization (e.g. new library paths) keeps the in-memory index
+        if self.persisted.is_empty()
+            && let Some(path) = &self.index_path
+        {
+            self.persisted = PersistedIndex::load(path);
+        }
 
         // Add extra library paths from settings (these take priority)
         if !extra_library_paths.is_empty() {
@@ -301,75 +596,136 @@ impl WorkspaceState {
             "[workspace] initialize() complete - {} files discovered",
             self.discovered_files.len()
         ));
+    }
 
-        // Index all discovered files upfront for reliable hover/goto-definition
-        self.index_all_discovered_files();
+    /// Files to (re)index: all discovered files
+    pub fn files_to_index(&self) -> Vec<PathBuf> {
+        self.discovered_files.iter().cloned().collect()
     }
 
-    /// Index all discovered Modelica files upfront
+    /// Merge indexed files into the workspace and persist the updated index.
     ///
-    /// This provides immediate hover/goto-definition support without lazy loading
-    /// Uses parallel parsing with rayon for faster initialization
-    fn index_all_discovered_files(&mut self) {
-        let start = std::time::Instant::now();
-        let files: Vec<PathBuf> = self.discovered_files.iter().cloned().collect();
-        let total = files.len();
-
-        self.debug_log(&format!(
-            "[workspace] Starting upfront indexing of {} files",
-            total
-        ));
-
-        // Parse all files in parallel using the global rayon thread pool
-        // (configured at startup to use num_cpus - 1 threads)
-        // Uses disk cache for files that haven't changed since last parse
-        let parsed_results: Vec<(PathBuf, Option<(String, StoredDefinition)>)> = files
-            .par_iter()
-            .map(|path| {
-                // Use cached parsing - checks disk cache first, then parses if needed
-                if let Some(ast) = parse_file_cached(path) {
-                    // Read text for document storage (needed for LSP features)
-                    let text = std::fs::read_to_string(path).unwrap_or_default();
-                    (path.clone(), Some((text, ast)))
-                } else {
-                    (path.clone(), None)
-                }
-            })
-            .collect();
-
-        // Now merge results into workspace state (single-threaded to avoid lock contention)
+    /// Documents open in the editor keep their current text. Persisted entries of files
+    /// that are no longer discovered are dropped.
+    pub fn apply_indexed_files(&mut self, files: Vec<IndexedFile>) {
         let mut indexed = 0;
         let mut failed = 0;
 
-        for (path, result) in parsed_results {
-            if let Some((text, ast)) = result {
-                // Convert path to URI
-                if let Some(uri) = path_to_uri(&path) {
-                    // Store document and AST
-                    self.documents.insert(uri.clone(), text);
-
-                    // Index symbols from AST
-                    self.remove_file_symbols(&uri);
-                    self.index_stored_definition(&uri, &ast);
-                    self.parsed_asts.insert(uri.clone(), ast.clone());
-                    self.cached_asts.insert(uri, ast);
-
-                    indexed += 1;
-                } else {
-                    failed += 1;
-                }
-            } else {
+        for file in files {
+            let (Some(uri), Some(ast)) = (path_to_uri(&file.path), file.ast) else {
                 failed += 1;
+                continue;
+            };
+            if self.open_uris.contains(&uri) {
+                continue;
+            }
+
+            // Store document and AST
+            self.documents.insert(uri.clone(), file.text);
+
+            // Index symbols from AST
+            self.remove_file_symbols(&uri);
+            self.index_stored_definition(&uri, &ast);
+            if file.outlined {
+                // Parsing the whole file is deferred until its AST is needed
+                self.parsed_asts.remove(&uri);
+                self.cached_asts.remove(&uri);
+                self.outlined_asts.insert(uri.clone(), OnceLock::new());
+            } else {
+                self.outlined_asts.remove(&uri);
+                self.parsed_asts.insert(uri.clone(), ast.clone());
+                self.cached_asts.insert(uri.clone(), ast);
             }
+
+            let symbols = self.cached_symbols(&uri);
+            self.persisted.files.insert(
+                file.path,
+                CachedFile {
+                    hash: file.hash,
+                    symbols,
+                },
+            );
+            indexed += 1;
         }
 
+        let discovered = &self.discovered_files;
+        self.persisted
+            .files
+            .retain(|path, _| discovered.contains(path));
+
         self.debug_log(&format!(
-            "[workspace] Upfront indexing complete in {:?}: {} indexed, {} failed, {} symbols",
-            start.elapsed(),
+            "[workspace] Indexed {} files ({} failed), {} symbols",
             indexed,
             failed,
             self.symbol_index.len()
         ));
+        self.save_index();
+    }
+
+    /// Set where the persisted index is stored, overriding the default location
+    /// derived from the workspace roots. Must be called before initialization.
+    pub fn set_index_path(&mut self, path: PathBuf) {
+        self.index_path = Some(path);
+    }
+
+    /// Write the persisted index to disk (no-op if persistence is unavailable)
+    pub fn save_index(&mut self) {
+        if let Some(path) = self.index_path.clone()
+            && let Err(e) = self.persisted.save(&path)
+        {
+            self.debug_log(&format!("[workspace] Failed to save index: {}", e));
+        }
+    }
+
+    /// Symbols of a file in their persisted form
+    fn cached_symbols(&self, uri: &Uri) -> Vec<CachedSymbol> {
+        self.file_symbols
+            .get(uri)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.symbol_index.get(name))
+            .map(|symbol| CachedSymbol {
+                qualified_name: symbol.qualified_name.clone(),
+                line: symbol.line,
+                column: symbol.column,
+                kind: symbol.kind,
+                detail: symbol.detail.clone(),
+            })
+            .collect()
+    }
+
+    /// Diagnostics persisted for a document, if its content is unchanged.
+    ///
+    /// Also restores the balance results used by code lenses.
+    pub fn cached_diagnostics(&mut self, uri: &Uri, text: &str) -> Option<Vec<Diagnostic>> {
+        let path = PathBuf::from(&uri_to_path(uri));
+        let entry = self.persisted.diagnostics.get(&path)?;
+        if entry.hash != content_hash(text) {
+            return None;
+        }
+        let entry = entry.clone();
+        for (class_name, balance) in entry.balances {
+            self.set_balance(uri.clone(), class_name, balance);
+        }
+        Some(entry.diagnostics)
+    }
+
+    /// Remember the diagnostics (and balance results) computed for a document
+    pub fn record_diagnostics(&mut self, uri: &Uri, text: &str, diagnostics: &[Diagnostic]) {
+        let balances = self
+            .balance_cache
+            .iter()
+            .filter(|((u, _), cached)| u == uri && cached.visible)
+            .map(|((_, class_name), cached)| (class_name.clone(), cached.balance.clone()))
+            .collect();
+        self.persisted.diagnostics.insert(
+            PathBuf::from(&uri_to_path(uri)),
+            CachedDiagnostics {
+                hash: content_hash(text),
+                diagnostics: diagnostics.to_vec(),
+                balances,
+            },
+        );
     }
 
     /// Discover Modelica packages in a folder
@@ -388,14 +744,10 @@ impl WorkspaceState {
             self.package_roots.push(folder.to_path_buf());
 
             // Register the package name as a symbol for import autocompletion
-            if let Some(package_name) = folder.file_name().and_then(|n| n.to_str()) {
-                let package_mo = folder.join("package.mo");
-                if let Some(path_str) = package_mo.to_str() {
-                    let uri_str = format!("file://{}", path_str);
-                    if let Ok(uri) = uri_str.parse::<Uri>() {
-                        self.register_package_symbol(package_name, &uri);
-                    }
-                }
+            if let Some(package_name) = folder.file_name().and_then(|n| n.to_str())
+                && let Some(uri) = path_to_uri(&folder.join("package.mo"))
+            {
+                self.register_package_symbol(package_name, &uri);
             }
 
             // Discover all files in this package
@@ -435,21 +787,51 @@ impl WorkspaceState {
 
     /// Open a document (called when file is opened in editor)
     pub fn open_document(&mut self, uri: Uri, text: String) {
+        self.count_edit(&uri);
+        self.open_uris.insert(uri.clone());
         self.documents.insert(uri.clone(), text.clone());
         self.reparse_document(&uri);
     }
 
     /// Update a document (called when file is changed)
     pub fn update_document(&mut self, uri: Uri, text: String) {
+        self.count_edit(&uri);
         self.documents.insert(uri.clone(), text.clone());
         self.reparse_document(&uri);
     }
 
+    /// Apply the content changes of a `didChange` notification to a document
+    /// (see [`apply_content_changes`]), returning its new text
+    pub fn change_document(
+        &mut self,
+        uri: Uri,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> String {
+        let mut text = self.documents.get(&uri).cloned().unwrap_or_default();
+        apply_content_changes(&mut text, changes);
+        self.update_document(uri, text.clone());
+        text
+    }
+
     /// Close a document
     pub fn close_document(&mut self, uri: &Uri) {
+        self.count_edit(uri);
+        self.open_uris.remove(uri);
         self.documents.remove(uri);
         self.remove_file_symbols(uri);
         self.parsed_asts.remove(uri);
+        self.outlined_asts.remove(uri);
+        self.compile_stats.remove(uri);
+    }
+
+    /// Remember the statistics of the diagnostics pass of a document
+    pub fn record_compile_stats(&mut self, stats: CompileStats) {
+        self.compile_stats.insert(stats.uri.clone(), stats);
+    }
+
+    /// Statistics of the last diagnostics pass of a document
+    pub fn compile_stats(&self, uri: &Uri) -> Option<&CompileStats> {
+        self.compile_stats.get(uri)
     }
 
     /// Get document text
@@ -469,7 +851,7 @@ impl WorkspaceState {
             None => return,
         };
 
-        let path = uri.path().as_str();
+        let path = &uri_to_path(uri);
 
         // Parse the document
         if let Some(ast) = parse_document(&text, path) {
@@ -477,6 +859,7 @@ impl WorkspaceState {
             self.remove_file_symbols(uri);
             self.index_stored_definition(uri, &ast);
             self.parsed_asts.insert(uri.clone(), ast.clone());
+            self.outlined_asts.remove(uri);
             // Also update the cache with the successful parse
             self.cached_asts.insert(uri.clone(), ast);
         }
@@ -487,7 +870,7 @@ impl WorkspaceState {
     /// Get the cached AST for a document (from last successful parse)
     /// This is useful for completions when the current document has syntax errors
     pub fn get_cached_ast(&self, uri: &Uri) -> Option<&StoredDefinition> {
-        self.cached_asts.get(uri)
+        self.cached_asts.get(uri).or_else(|| self.parsed_ast(uri))
     }
 
     /// Set a cached AST for a URI (used for WASM fallback when parsing fails)
@@ -683,7 +1066,7 @@ impl WorkspaceState {
         // Try looking up with the file's within prefix
         // This handles cases like "Interfaces.DiscreteSISO" in a file with
         // "within Modelica.Blocks;" -> "Modelica.Blocks.Interfaces.DiscreteSISO"
-        if let Some(ast) = self.parsed_asts.get(context_uri)
+        if let Some(ast) = self.parsed_ast(context_uri)
             && let Some(within) = &ast.within
         {
             let qualified = format!("{}.{}", within, type_name);
@@ -705,12 +1088,24 @@ impl WorkspaceState {
     /// This looks up the symbol, finds its file URI, and returns the parsed AST for that file.
     pub fn get_parsed_ast_by_name(&self, qualified_name: &str) -> Option<&StoredDefinition> {
         let sym = self.lookup_symbol(qualified_name)?;
-        self.parsed_asts.get(&sym.uri)
+        self.parsed_ast(&sym.uri)
     }
 
     /// Get the parsed AST for a URI
     pub fn get_parsed_ast(&self, uri: &Uri) -> Option<&StoredDefinition> {
-        self.parsed_asts.get(uri)
+        self.parsed_ast(uri)
+    }
+
+    /// The parsed AST of a file, parsing outlined files on first use
+    fn parsed_ast(&self, uri: &Uri) -> Option<&StoredDefinition> {
+        if let Some(ast) = self.parsed_asts.get(uri) {
+            return Some(ast);
+        }
+        let text = self.documents.get(uri)?;
+        self.outlined_asts
+            .get(uri)?
+            .get_or_init(|| parse_document(text, &uri_to_path(uri)))
+            .as_ref()
     }
 
     /// Get all package roots
@@ -739,8 +1134,8 @@ impl WorkspaceState {
         }
 
         // Read and parse the file
-        let text = std::fs::read_to_string(path).ok()?;
-        self.open_document(uri.clone(), text);
+        let text = crate::compiler::read_source(path).ok()?;
+        self.add_document(uri.clone(), text);
 
         Some(uri)
     }
@@ -812,12 +1207,8 @@ impl WorkspaceState {
                             && let Some(subpkg_name) = path.file_name().and_then(|n| n.to_str())
                         {
                             let qualified_name = format!("{}.{}", package_prefix, subpkg_name);
-                            let subpkg_mo = path.join("package.mo");
-                            if let Some(path_str) = subpkg_mo.to_str() {
-                                let uri_str = format!("file://{}", path_str);
-                                if let Ok(uri) = uri_str.parse::<Uri>() {
-                                    self.register_package_symbol(&qualified_name, &uri);
-                                }
+                            if let Some(uri) = path_to_uri(&path.join("package.mo")) {
+                                self.register_package_symbol(&qualified_name, &uri);
                             }
                         }
                     } else if path.extension().is_some_and(|ext| ext == "mo") {
@@ -841,8 +1232,7 @@ impl WorkspaceState {
 
     /// Get imports from a file
     pub fn get_imports(&self, uri: &Uri) -> Vec<String> {
-        self.parsed_asts
-            .get(uri)
+        self.parsed_ast(uri)
             .map(|ast| {
                 let mut imports = Vec::new();
                 for class in ast.class_list.values() {
@@ -886,9 +1276,7 @@ fn path_to_uri(path: &Path) -> Option<Uri> {
         std::env::current_dir().ok()?.join(path)
     };
 
-    let path_str = abs_path.to_str()?;
-    let uri_str = format!("file://{}", path_str);
-    uri_str.parse().ok()
+    path_to_file_uri(abs_path.to_str()?).parse().ok()
 }
 
 // ============================================================================
@@ -970,7 +1358,7 @@ impl SymbolLookup for WorkspaceState {
         }
 
         let sym = sym?;
-        self.parsed_asts.get(&sym.uri)
+        self.parsed_ast(&sym.uri)
     }
 }
 
@@ -996,4 +1384,65 @@ mod tests {
         ws.close_document(&uri);
         assert!(ws.get_document(&uri).is_none());
     }
+
+    #[test]
+    fn test_cached_balance() {
+        let mut ws = WorkspaceState::new();
+        let uri: Uri = "file:///tmp/a.mo".parse().unwrap();
+        let other: Uri = "file:///tmp/b.mo".parse().unwrap();
+        ws.open_document(uri.clone(), "model A end A;".to_string());
+        ws.cache_balance(
+            uri.clone(),
+            "A".to_string(),
+            "hash".to_string(),
+            BalanceResult::compile_error("error".to_string()),
+            Default::default(),
+            Default::default(),
+        );
+        assert!(ws.cached_balance(&uri, "A", "hash").is_some());
+        assert!(ws.cached_balance(&uri, "A", "other").is_none());
+
+        // Edits of the document itself keep it
+        ws.update_document(uri.clone(), "model A  end A;".to_string());
+        assert!(ws.cached_balance(&uri, "A", "hash").is_some());
+
+        // Hidden balances are kept for reuse
+        ws.hide_balances(&uri);
+        assert!(ws.get_balance(&uri, "A").is_none());
+        assert!(ws.cached_balance(&uri, "A", "hash").is_some());
+
+        // Edits of another document invalidate it
+        ws.open_document(other, "model B end B;".to_string());
+        assert!(ws.cached_balance(&uri, "A", "hash").is_none());
+
+        // Unkeyed balances are never reused
+        ws.set_balance(
+            uri.clone(),
+            "A".to_string(),
+            BalanceResult::compile_error("error".to_string()),
+        );
+        assert!(ws.get_balance(&uri, "A").is_some());
+        assert!(ws.cached_balance(&uri, "A", "").is_none());
+    }
+
+    #[test]
+    fn test_outlined_file_parsed_on_demand() {
+        let mut ws = WorkspaceState::new();
+        let path = PathBuf::from("/tmp/Big.mo");
+        let text = "package Big\n  model M\n    Real x;\n  equation\n    der(x) = 1;\n  end M;\nend Big;\n";
+        ws.apply_indexed_files(vec![IndexedFile {
+            path: path.clone(),
+            hash: content_hash(text),
+            text: text.to_string(),
+            ast: Some(crate::compiler::outline::parse_outline(text, "/tmp/Big.mo")),
+            outlined: true,
+        }]);
+
+        let sym = ws.lookup_symbol("Big.M").unwrap();
+        assert_eq!(sym.kind, SymbolKind::Model);
+
+        // The full AST (with equations) is parsed when first requested
+        let ast = ws.get_parsed_ast_by_name("Big.M").unwrap();
+        assert_eq!(ast.class_list["Big"].classes["M"].equations.len(), 1);
+    }
 }
@@ -12,18 +12,68 @@
 //!
 //! ## Command-Line Arguments
 //! - `--template-file` (`-t`): Optional path to a template file for rendering the DAE.
-//! - `MODELICA_FILE`: Path to the Modelica file to parse.
+//! - `MODELICA_FILE`: Path to the Modelica file to parse, or `-` to read from stdin.
+//! - `--lib-path` (`-L`, `--lib`): Library search path, e.g. `builtin:msl-mini` for the
+//!   subset of the Modelica Standard Library shipped with rumoca.
+//! - `--stdin`: Read the Modelica source from stdin (same as passing `-`).
 //! - `--verbose` (`-v`): Enables verbose output for detailed logging and debugging.
+//! - `--quiet` (`-q`): Suppresses warnings and notes on stderr.
+//! - `--strict`: Fails on constructs outside of the Modelica specification that are
+//!   accepted by default, e.g. `Bool` for `Boolean` (see [`rumoca::compiler::conformance`]).
+//! - `--explain-relaxations`: Lists those constructs, what rumoca accepts and what the
+//!   specification requires.
+//! - `--deny`: Fails on warnings with the given codes, e.g. `--deny unused-variable,unbalanced`.
+//! - `--analyze report`: Prints equations and unknowns per component instance (a
+//!   balance "heat-map", JSON with `--json`) instead of rendering the model.
+//! - `--analyze steady-state`: Solves for the equilibrium (`der(x) = 0`) with Newton's
+//!   method and prints the states and algebraic variables (JSON with `--json`).
+//! - `--analyze scaling`: Estimates the range of the terms of each equation from parameter
+//!   values and nominal attributes, and warns about badly scaled equations (JSON with
+//!   `--json`, see [`rumoca::dae::scaling`]).
+//! - `--emit depgraph`: Prints the inter-package dependency graph of the file and all
+//!   `--lib-path` libraries (DOT, or JSON with `--json`) instead of compiling.
+//! - `--emit context-schema`: Prints the version, changelog and fields of the template
+//!   context (Markdown, or JSON with `--json`), see [`rumoca::compiler::context`].
+//! - `--params`: Parameter file (TOML, or JSON for `.json` files) with parameter values
+//!   for the compiled model, e.g. `--params params.toml --experiment stiff` to use the
+//!   values of the experiment `stiff` (see [`rumoca::dae::params`]).
+//! - `--header-file`: Custom text (e.g. a license notice) for the header of generated code,
+//!   available to templates as `provenance.header`.
+//! - `--stamp`: Prepends the header (model, rumoca version, compile date and source hashes)
+//!   to the template output as a comment with the given prefix, e.g. `--stamp '#'`.
+//! - `--template-debug`: Writes the rendering context of the template to
+//!   `MODEL.context.json`, warns about the undefined variables the template looks up
+//!   and gives rendering errors with the template line and column and a code frame.
+//!
+//! `rumoca repl [MODELICA_FILE -m MODEL]` starts an interactive shell instead, to
+//! query the variables, parameters and equations of a model, evaluate constant
+//! expressions, change parameters and render templates (see [`rumoca::compiler::repl`]).
+//! `rumoca explain MODELICA_FILE [-m MODEL] --var NAME` prints the declaration, start
+//! value and equations of a variable, the equation BLT solves for it and whether it is
+//! a state or algebraic (see [`rumoca::compiler::explain`]).
+//! `rumoca verify MODELICA_FILE [-m MODEL] --against golden.json` compares the DAE of
+//! the model to one exported with `--json`, e.g. by an earlier compiler version, and
+//! fails if it drifted (see [`rumoca::dae::golden`]).
+//! `rumoca solve-steady MODELICA_FILE [-m MODEL] [--json]` solves for the equilibrium
+//! of the model like `--analyze steady-state` (see [`rumoca::dae::steady_state`]).
+//!
+//! Rendered output is the only thing written to stdout; logging and diagnostics
+//! go to stderr, so the compiler composes with Unix pipelines.
 //!
 //! ## Usage
 //! ```sh
 //! rumoca_parol --template-file template.j2 example.mo --verbose
+//! cat example.mo | rumoca -m Example -t template.j2 - > output.py
+//! rumoca repl example.mo -m Example
+//! rumoca explain example.mo -m Example --var motor.w
+//! rumoca solve-steady example.mo -m Example --json
 //! ```
 //!
 //! ## Error Handling
 //! Errors encountered during file reading, parsing, or processing are reported using
-//! the `anyhow` crate for detailed context. Parsing errors are handled by the custom
-//! `ErrorReporter` implementation.
+//! the `anyhow` crate for detailed context. Parsing errors are reported with a code
+//! frame of the offending line, the expected tokens and a suggested fix, e.g. a missing
+//! semicolon or `end X;`.
 //!
 //! ## Dependencies
 //! - `parol_runtime`: Used for parsing Modelica files.
@@ -40,10 +90,17 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-use clap::Parser;
-use rumoca::Compiler;
+use clap::{Parser, Subcommand, ValueEnum};
+use rumoca::compiler::repl::{Repl, Reply};
+use rumoca::dae::params::ParameterFile;
+use rumoca::ir::analysis::scaling::DEFAULT_THRESHOLD;
+use rumoca::{Compiler, DiagnosticCode, SortMode};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::io::{BufRead, IsTerminal, Read};
+
+/// File name used in diagnostics when the model is read from stdin
+const STDIN_FILE_NAME: &str = "<stdin>";
 
 /// Git version string including commit hash and build timestamp for dirty builds
 /// Format: "v0.7.18" (clean release), "v0.7.18-dirty-1234567890" (dirty with timestamp)
@@ -51,8 +108,17 @@ use anyhow::Result;
 const GIT_VERSION: &str = env!("RUMOCA_GIT_VERSION");
 
 #[derive(Parser, Debug)]
-#[command(version = GIT_VERSION, about = "Rumoca Modelica Compiler", long_about = None)]
+#[command(
+    version = GIT_VERSION,
+    about = "Rumoca Modelica Compiler",
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Export to Base Modelica JSON (native, recommended)
     #[arg(long, conflicts_with = "template_file")]
     json: bool,
@@ -61,33 +127,302 @@ struct Args {
     #[arg(short, long)]
     template_file: Option<String>,
 
-    /// Main model/class to simulate (required)
-    #[arg(short, long, required = true)]
-    model: String,
+    /// Main model/class to simulate (required unless using --emit)
+    #[arg(short, long, required_unless_present_any = ["emit", "explain_relaxations"])]
+    model: Option<String>,
 
-    /// Modelica file to parse
-    #[arg(name = "MODELICA_FILE")]
-    model_file: String,
+    /// Modelica file to parse (use `-` to read from stdin)
+    #[arg(name = "MODELICA_FILE", required_unless_present_any = ["stdin", "emit", "explain_relaxations"])]
+    model_file: Option<String>,
+
+    /// Read the Modelica source from stdin
+    #[arg(long, conflicts_with = "MODELICA_FILE")]
+    stdin: bool,
 
     /// Library search paths (alternative to MODELICAPATH env var)
     /// Can be specified multiple times: -L /path1 -L /path2
-    #[arg(short = 'L', long = "lib-path")]
+    /// `builtin:msl-mini` is a subset of the Modelica Standard Library shipped with rumoca
+    #[arg(short = 'L', long = "lib-path", visible_alias = "lib")]
     lib_paths: Vec<String>,
 
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Suppress warnings and notes (errors are still reported)
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Emit an analysis instead of compiling the model
+    #[arg(long, value_enum, conflicts_with = "template_file")]
+    emit: Option<Emit>,
+
+    /// Add runtime assertions that division denominators are nonzero
+    /// (emitted by the JSON export and templates that render `dae.asserts`)
+    #[arg(long)]
+    guard_divisions: bool,
+
+    /// Compile models whose equations could only be solved for a parameter
+    /// or an input (structurally singular) with a warning instead of an error
+    #[arg(long)]
+    permissive: bool,
+
+    /// Fail on constructs outside of the Modelica specification that are
+    /// accepted by default (see --explain-relaxations)
+    #[arg(long)]
+    strict: bool,
+
+    /// List the constructs accepted outside of the Modelica specification,
+    /// which --strict rejects, instead of compiling
+    #[arg(long)]
+    explain_relaxations: bool,
+
+    /// Fail on warnings with these codes, e.g. `--deny unused-variable,unbalanced`
+    /// (codes: unbalanced, unused-variable, singular, shadowed-builtin, missing-resource, encoding)
+    #[arg(long, value_name = "CODES", value_delimiter = ',')]
+    deny: Vec<DiagnosticCode>,
+
+    /// Keep for-equations as loops in the DAE when their equations stay in
+    /// order, for templates that generate loops (e.g. for discretized PDEs)
+    #[arg(long)]
+    symbolic_loops: bool,
+
+    /// Order of the continuous-time equations: `blt` sorts and solves them,
+    /// `none` keeps them as declared (e.g. for symbolic or acausal backends)
+    #[arg(long, value_name = "MODE", default_value = "blt")]
+    sort: SortMode,
+
+    /// Maximum depth of nested components, a safety net against
+    /// unreasonably deep (or recursive) model hierarchies
+    #[arg(long, value_name = "DEPTH", default_value_t = rumoca::compiler::limits::DEFAULT_MAX_INSTANCE_DEPTH)]
+    max_instance_depth: usize,
+
+    /// Maximum number of elements an array, a for-equation or an array
+    /// equation may expand to
+    #[arg(long, value_name = "SIZE", default_value_t = rumoca::compiler::limits::DEFAULT_MAX_EXPANSION_SIZE)]
+    max_expansion_size: usize,
+
+    /// Variables to export as outputs, e.g. `--outputs y1,y2`; algebraic
+    /// variables no output depends on are removed from the DAE
+    #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+    outputs: Vec<String>,
+
+    /// Abort the compilation of the model after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// Print an analysis of the compiled model instead of rendering it
+    #[arg(long, value_enum, conflicts_with_all = ["template_file", "emit"])]
+    analyze: Option<Analysis>,
+
+    /// Parameter file (TOML, or JSON for .json files) setting parameter values
+    /// of the compiled model
+    #[arg(long, value_name = "FILE")]
+    params: Option<String>,
+
+    /// Experiment of the parameter file whose values override its top-level
+    /// values
+    #[arg(long, value_name = "NAME", requires = "params")]
+    experiment: Option<String>,
+
+    /// File with a custom header text (e.g. a license notice) for generated code,
+    /// available to templates as `provenance.header`
+    #[arg(long)]
+    header_file: Option<String>,
+
+    /// Prepend the header (model, rumoca version, compile date and source
+    /// hashes) to the template output, commented with this prefix (e.g. `#`)
+    #[arg(long, value_name = "COMMENT_PREFIX", requires = "template_file")]
+    stamp: Option<String>,
+
+    /// Debug the template: write its rendering context as JSON to
+    /// MODEL.context.json, warn about undefined variables it looks up and
+    /// locate rendering errors in it
+    #[arg(long, requires = "template_file")]
+    template_debug: bool,
+}
+
+/// Commands other than compiling a model
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Interactive shell to explore a model (`:help` lists the commands)
+    Repl {
+        /// Modelica file to load at startup
+        #[arg(name = "MODELICA_FILE", requires = "model")]
+        model_file: Option<String>,
+
+        /// Model of the file to load
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Library search paths (alternative to MODELICAPATH env var)
+        #[arg(short = 'L', long = "lib-path", visible_alias = "lib")]
+        lib_paths: Vec<String>,
+    },
+    /// Explain a variable: declaration, start value, equations and how BLT
+    /// solves it
+    Explain {
+        /// Modelica file of the model
+        #[arg(name = "MODELICA_FILE")]
+        model_file: String,
+
+        /// Model of the file (the last class of the file by default)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Variable to explain, e.g. `motor.w`
+        #[arg(long)]
+        var: String,
+
+        /// Library search paths (alternative to MODELICAPATH env var)
+        #[arg(short = 'L', long = "lib-path", visible_alias = "lib")]
+        lib_paths: Vec<String>,
+    },
+    /// Compare the DAE of a model to a golden DAE IR JSON export and fail if
+    /// it drifted
+    Verify {
+        /// Modelica file of the model
+        #[arg(name = "MODELICA_FILE")]
+        model_file: String,
+
+        /// Model of the file (the last class of the file by default)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Golden DAE, exported with --json
+        #[arg(long)]
+        against: String,
+
+        /// Library search paths (alternative to MODELICAPATH env var)
+        #[arg(short = 'L', long = "lib-path", visible_alias = "lib")]
+        lib_paths: Vec<String>,
+    },
+    /// Solve for the equilibrium of the states and algebraic variables of a
+    /// model, with der(x) = 0
+    SolveSteady {
+        /// Modelica file of the model
+        #[arg(name = "MODELICA_FILE")]
+        model_file: String,
+
+        /// Model of the file (the last class of the file by default)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Print the steady state as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Library search paths (alternative to MODELICAPATH env var)
+        #[arg(short = 'L', long = "lib-path", visible_alias = "lib")]
+        lib_paths: Vec<String>,
+    },
+}
+
+/// Analyses of a compiled model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Analysis {
+    /// Equations and unknowns contributed by each component instance
+    Report,
+    /// Equilibrium of the states and algebraic variables, with der(x) = 0
+    SteadyState,
+    /// Range of the terms of each equation, and the badly scaled equations
+    Scaling,
+}
+
+/// Analyses that can be emitted instead of a compiled model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Inter-package dependency graph (DOT, or JSON with --json)
+    Depgraph,
+    /// Version, changelog and fields of the template context (Markdown, or
+    /// JSON with --json)
+    ContextSchema,
+}
+
+impl Args {
+    /// Whether the model source should be read from stdin
+    fn reads_stdin(&self) -> bool {
+        self.stdin || self.model_file.as_deref() == Some("-")
+    }
 }
 
 fn main() -> Result<()> {
     env_logger::init();
     let args = Args::parse();
 
+    match &args.command {
+        Some(Command::Repl {
+            model_file,
+            model,
+            lib_paths,
+        }) => return run_repl(model_file.as_deref(), model.as_deref(), lib_paths),
+        Some(Command::Explain {
+            model_file,
+            model,
+            var,
+            lib_paths,
+        }) => return run_explain(model_file, model.as_deref(), var, lib_paths),
+        Some(Command::Verify {
+            model_file,
+            model,
+            against,
+            lib_paths,
+        }) => return run_verify(model_file, model.as_deref(), against, lib_paths),
+        Some(Command::SolveSteady {
+            model_file,
+            model,
+            json,
+            lib_paths,
+        }) => return run_solve_steady(model_file, model.as_deref(), *json, lib_paths),
+        None => {}
+    }
+    if args.explain_relaxations {
+        let explanation = rumoca::compiler::conformance::explain_relaxations();
+        return write_stdout(explanation.trim_end());
+    }
+    if args.emit == Some(Emit::ContextSchema) {
+        let schema = rumoca::compiler::context::context_schema();
+        let output = if args.json {
+            serde_json::to_string_pretty(&schema)?
+        } else {
+            schema.to_markdown()
+        };
+        return write_stdout(output.trim_end());
+    }
+    if args.model_file.is_none() && !args.reads_stdin() {
+        anyhow::bail!("--emit depgraph needs a MODELICA_FILE (or --stdin)");
+    }
+
     // Use the new Compiler API
-    let mut compiler = Compiler::new().verbose(args.verbose);
+    let mut compiler = Compiler::new()
+        .verbose(args.verbose)
+        .guard_divisions(args.guard_divisions)
+        .permissive(args.permissive)
+        .strict(args.strict)
+        .symbolic_loops(args.symbolic_loops)
+        .sort(args.sort)
+        .max_instance_depth(args.max_instance_depth)
+        .max_expansion_size(args.max_expansion_size)
+        .deny(&args.deny);
+    if !args.outputs.is_empty() {
+        let outputs: Vec<&str> = args.outputs.iter().map(String::as_str).collect();
+        compiler = compiler.outputs(&outputs);
+    }
+    if let Some(timeout) = args.timeout {
+        let timeout = std::time::Duration::try_from_secs_f64(timeout)
+            .context("--timeout must be a non-negative number of seconds")?;
+        compiler = compiler.timeout(timeout);
+    }
 
-    // Set main model (required)
-    compiler = compiler.model(&args.model);
+    if let Some(header_file) = &args.header_file {
+        let header = std::fs::read_to_string(header_file)
+            .with_context(|| format!("Failed to read header file '{}'", header_file))?;
+        compiler = compiler.license_header(&header);
+    }
+
+    // Set main model (required for compilation)
+    let model = args.model.as_deref().unwrap_or_default();
+    compiler = compiler.model(model);
 
     // Set library paths if provided (overrides MODELICAPATH env var)
     if !args.lib_paths.is_empty() {
@@ -98,17 +433,19 @@ fn main() -> Result<()> {
     // Auto-include packages from MODELICAPATH
     // Include root package from model name (e.g., "Modelica.Blocks.PID" -> "Modelica")
     // Also include "Modelica" if not already included (for user files with MSL imports)
-    let root_package = args.model.split('.').next().unwrap_or("");
+    let root_package = model.split('.').next().unwrap_or("");
 
     if !root_package.is_empty() {
         match compiler.clone().include_from_modelica_path(root_package) {
             Ok(c) => compiler = c,
             Err(e) => {
-                eprintln!(
-                    "warning: could not load package '{}' from MODELICAPATH: {}",
-                    root_package,
-                    e.to_string().lines().next().unwrap_or("")
-                );
+                if !args.quiet {
+                    eprintln!(
+                        "warning: could not load package '{}' from MODELICAPATH: {}",
+                        root_package,
+                        e.to_string().lines().next().unwrap_or("")
+                    );
+                }
             }
         }
     }
@@ -128,17 +465,281 @@ fn main() -> Result<()> {
         }
     }
 
-    let mut result = compiler.compile_file(&args.model_file)?;
+    if args.emit == Some(Emit::Depgraph) {
+        // The dependency graph covers every library on the search path
+        for lib_path in &args.lib_paths {
+            let lib_path = rumoca::compiler::builtin::resolve_library_path(lib_path)?;
+            compiler = compiler.include_package(&lib_path.to_string_lossy())?;
+        }
+        let (source, file_name) = read_model_source(&args)?;
+        let graph = compiler.dependency_graph(&source, &file_name)?;
+        if !args.quiet {
+            for cycle in graph.cycles() {
+                eprintln!("warning: cyclic package dependency: {}", cycle.join(" -> "));
+            }
+        }
+        let output = if args.json {
+            graph.to_json()?
+        } else {
+            graph.to_dot()
+        };
+        return write_stdout(output.trim_end());
+    }
+
+    let mut result = if args.reads_stdin() {
+        let (source, file_name) = read_model_source(&args)?;
+        compiler.compile_str(&source, &file_name)?
+    } else {
+        // `required_unless_present` guarantees a file when not reading stdin
+        let model_file = args.model_file.as_deref().unwrap_or_default();
+        compiler.compile_file(model_file)?
+    };
+
+    if !args.quiet {
+        for warning in &result.warnings {
+            eprintln!("warning: {}", warning);
+        }
+    }
+
+    if let Some(params) = &args.params {
+        let values = ParameterFile::read(params)?.values(args.experiment.as_deref())?;
+        result.dae.set_parameters(&values)?;
+    }
+
+    if args.analyze == Some(Analysis::Report) {
+        let components = result.dae.component_balance();
+        let output = if args.json {
+            serde_json::to_string_pretty(&components)?
+        } else {
+            format!(
+                "{}{}",
+                rumoca::dae::balance::format_component_balance(&components),
+                result.dae.check_balance().status_message()
+            )
+        };
+        return write_stdout(&output);
+    }
+
+    if args.analyze == Some(Analysis::SteadyState) {
+        return write_steady_state(&result.dae, args.json);
+    }
+
+    if args.analyze == Some(Analysis::Scaling) {
+        let scaling = result.dae.scaling(DEFAULT_THRESHOLD);
+        let output = if args.json {
+            serde_json::to_string_pretty(&scaling)?
+        } else {
+            scaling.to_string().trim_end().to_string()
+        };
+        return write_stdout(&output);
+    }
 
     // Export using native JSON or template
     if args.json {
         // Native JSON export (recommended)
         let json = result.dae.to_dae_ir_json()?;
-        println!("{}", json);
-    } else if let Some(template_file) = args.template_file {
+        write_stdout(&json)?;
+    } else if let Some(template_file) = &args.template_file {
         // Template-based export (advanced)
-        result.render_template(&template_file)?;
+        let mut output = if args.template_debug {
+            debug_template(&mut result, template_file, args.quiet)?
+        } else {
+            result.render_template_to_string(template_file)?
+        };
+        if let Some(prefix) = &args.stamp {
+            // Keep a shebang line first
+            let at = if output.starts_with("#!") {
+                output.find('\n').map_or(output.len(), |i| i + 1)
+            } else {
+                0
+            };
+            output.insert_str(at, &result.provenance.stamp(prefix));
+        }
+        write_stdout(&output)?;
     }
 
     Ok(())
 }
+
+/// Render a template with `--template-debug`: the context is written even
+/// if rendering fails, to see what the template could use
+fn debug_template(
+    result: &mut rumoca::CompilationResult,
+    template_file: &str,
+    quiet: bool,
+) -> Result<String> {
+    let output = result.render_template_debug(template_file);
+    let context_file = format!("{}.context.json", result.dae.model_name);
+    std::fs::write(&context_file, result.template_context_json()?)
+        .with_context(|| format!("Failed to write '{}'", context_file))?;
+    if !quiet {
+        eprintln!("note: wrote the template context to {}", context_file);
+        for warning in result.check_template_lookups(template_file)? {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    Ok(output?)
+}
+
+/// Run the interactive shell on stdin, prompting only if stdin is a terminal
+fn run_repl(model_file: Option<&str>, model: Option<&str>, lib_paths: &[String]) -> Result<()> {
+    let mut compiler = Compiler::new();
+    if !lib_paths.is_empty() {
+        let paths: Vec<&str> = lib_paths.iter().map(|s| s.as_str()).collect();
+        compiler = compiler.modelica_path(&paths);
+    }
+    let mut repl = Repl::new(compiler);
+    if let (Some(model_file), Some(model)) = (model_file, model) {
+        match repl.load_file(model_file, model) {
+            Ok(summary) => write_stdout(&summary)?,
+            Err(e) => eprintln!("error: {:#}", e),
+        }
+    }
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        eprintln!("rumoca {} repl, :help lists the commands", GIT_VERSION);
+    }
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        if interactive {
+            eprint!("rumoca> ");
+        }
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        match repl.execute(&line.context("Failed to read from stdin")?) {
+            Ok(Reply::Quit) => return Ok(()),
+            Ok(Reply::Output(output)) if output.is_empty() => {}
+            Ok(Reply::Output(output)) => write_stdout(&output)?,
+            Err(e) => eprintln!("error: {:#}", e),
+        }
+    }
+}
+
+/// Compile a model and print the explanation of one of its variables
+fn run_explain(
+    model_file: &str,
+    model: Option<&str>,
+    var: &str,
+    lib_paths: &[String],
+) -> Result<()> {
+    let (model, result) = compile_model(model_file, model, lib_paths)?;
+    let explanation = result
+        .explain(var)
+        .with_context(|| format!("'{}' has no variable '{}'", model, var))?;
+    write_stdout(&explanation.to_string())
+}
+
+fn run_verify(
+    model_file: &str,
+    model: Option<&str>,
+    against: &str,
+    lib_paths: &[String],
+) -> Result<()> {
+    let golden = std::fs::read_to_string(against)
+        .with_context(|| format!("Failed to read golden DAE {}", against))?;
+    let (_, result) = compile_model(model_file, model, lib_paths)?;
+    let comparison = result
+        .dae
+        .compare_to_golden(&golden)
+        .with_context(|| format!("Failed to compare to {}", against))?;
+    write_stdout(&comparison.to_string())?;
+    if !comparison.is_equivalent() {
+        anyhow::bail!("{} drifted from {}", comparison.model_name, against);
+    }
+    Ok(())
+}
+
+/// Compile a model and print its steady state
+fn run_solve_steady(
+    model_file: &str,
+    model: Option<&str>,
+    json: bool,
+    lib_paths: &[String],
+) -> Result<()> {
+    let (_, result) = compile_model(model_file, model, lib_paths)?;
+    write_steady_state(&result.dae, json)
+}
+
+/// Print the steady state of a DAE, as JSON with `json`
+fn write_steady_state(dae: &rumoca::dae::ast::Dae, json: bool) -> Result<()> {
+    let steady_state = dae.steady_state()?;
+    let output = if json {
+        serde_json::to_string_pretty(&steady_state)?
+    } else {
+        steady_state.to_string().trim_end().to_string()
+    };
+    write_stdout(&output)
+}
+
+/// Compile a model of a file, the last class of the file by default, for the
+/// subcommands
+fn compile_model(
+    model_file: &str,
+    model: Option<&str>,
+    lib_paths: &[String],
+) -> Result<(String, rumoca::CompilationResult)> {
+    let model = match model {
+        Some(model) => model.to_string(),
+        None => {
+            let source = rumoca::read_source(std::path::Path::new(model_file))?;
+            let def = rumoca::parse_source(&source, model_file)?;
+            def.class_list
+                .keys()
+                .last()
+                .cloned()
+                .context("The file has no classes, select a model with -m")?
+        }
+    };
+    let mut compiler = Compiler::new().model(&model);
+    if !lib_paths.is_empty() {
+        let paths: Vec<&str> = lib_paths.iter().map(|s| s.as_str()).collect();
+        compiler = compiler.modelica_path(&paths);
+    }
+    let result = compiler.compile_file(model_file)?;
+    Ok((model, result))
+}
+
+/// Read the model source from stdin or the model file, returning it with the
+/// file name to use in diagnostics.
+///
+/// Reports the [`DiagnosticCode::Encoding`] warning of a source that isn't
+/// valid UTF-8 like the compiler does, as it isn't compiled from its file.
+fn read_model_source(args: &Args) -> Result<(String, String)> {
+    let (source, file_name, warning) = if args.reads_stdin() {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read Modelica source from stdin")?;
+        let (source, fallback) = rumoca::compiler::source::decode_source(bytes);
+        let warning = fallback.then(|| rumoca::compiler::source::encoding_warning(STDIN_FILE_NAME));
+        (source, STDIN_FILE_NAME.to_string(), warning)
+    } else {
+        let model_file = args.model_file.as_deref().unwrap_or_default();
+        let (source, warning) = rumoca::read_source_with_warning(std::path::Path::new(model_file))?;
+        (source, model_file.to_string(), warning)
+    };
+    if let Some(warning) = warning {
+        if args.deny.contains(&warning.code) {
+            return Err(rumoca::Error::Denied(vec![warning]).into());
+        }
+        if !args.quiet {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    Ok((source, file_name))
+}
+
+/// Write rendered output to stdout, treating a closed pipe (e.g. `| head`) as success.
+fn write_stdout(text: &str) -> Result<()> {
+    use std::io::{ErrorKind, Write};
+
+    let mut stdout = std::io::stdout().lock();
+    match writeln!(stdout, "{}", text).and_then(|_| stdout.flush()) {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+            Err(e).context("Failed to write output to stdout")
+        }
+        _ => Ok(()),
+    }
+}
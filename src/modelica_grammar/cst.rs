@@ -0,0 +1,546 @@
+//! Lossless concrete syntax tree (tokens + trivia).
+//!
+//! The parser builds an AST that drops whitespace, comments and the exact
+//! spelling of tokens it normalizes. Tools that rewrite source code (the
+//! formatter, refactorings, code actions) need the original text of regions
+//! they don't touch. This module provides a token stream where every byte of
+//! the source belongs either to a token or to the trivia attached to one, so
+//! concatenating all tokens with their trivia reproduces the input exactly.
+//!
+//! Trivia attachment follows the usual convention:
+//! - trailing trivia: whitespace and comments after a token, up to (not
+//!   including) the next newline
+//! - leading trivia: everything else before a token (newlines, indentation,
+//!   comments on their own lines)
+//!
+//! The final [`SyntaxKind::Eof`] token carries the trivia at the end of file.
+//!
+//! # Example
+//!
+//! ```
+//! use rumoca::modelica_grammar::cst::SyntaxTree;
+//!
+//! let source = "model M // comment\n  Real x;\nend M;\n";
+//! let tree = SyntaxTree::parse(source);
+//! assert_eq!(tree.to_source(), source);
+//! assert_eq!(tree.tokens()[0].text, "model");
+//! ```
+
+use std::ops::Range;
+
+/// Modelica reserved words (Modelica spec 2.3.3)
+pub const KEYWORDS: &[&str] = &[
+    "algorithm",
+    "and",
+    "annotation",
+    "block",
+    "break",
+    "class",
+    "connect",
+    "connector",
+    "constant",
+    "constrainedby",
+    "der",
+    "discrete",
+    "each",
+    "else",
+    "elseif",
+    "elsewhen",
+    "encapsulated",
+    "end",
+    "enumeration",
+    "equation",
+    "expandable",
+    "extends",
+    "external",
+    "false",
+    "final",
+    "flow",
+    "for",
+    "function",
+    "if",
+    "import",
+    "impure",
+    "in",
+    "initial",
+    "inner",
+    "input",
+    "loop",
+    "model",
+    "not",
+    "operator",
+    "or",
+    "outer",
+    "output",
+    "package",
+    "parameter",
+    "partial",
+    "protected",
+    "public",
+    "pure",
+    "record",
+    "redeclare",
+    "replaceable",
+    "return",
+    "stream",
+    "then",
+    "true",
+    "type",
+    "when",
+    "while",
+    "within",
+];
+
+/// Kind of a significant token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// Reserved word (e.g., `model`, `equation`, `end`)
+    Keyword,
+    /// Identifier, including quoted identifiers (`'my var'`)
+    Ident,
+    /// Unsigned integer literal
+    Integer,
+    /// Unsigned real literal (e.g., `1.5`, `2e-3`)
+    Real,
+    /// String literal, including quotes
+    String,
+    /// Operator or punctuation (e.g., `+`, `:=`, `.*`, `(`, `;`)
+    Symbol,
+    /// Character sequence that is not valid Modelica (kept for losslessness)
+    Error,
+    /// End of file (empty text, carries the trailing trivia of the file)
+    Eof,
+}
+
+/// Kind of trivia
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces and tabs
+    Whitespace,
+    /// A line break (`\n`, `\r\n` or `\r`)
+    Newline,
+    /// `// ...` comment (without the line break)
+    LineComment,
+    /// `/* ... */` comment
+    BlockComment,
+}
+
+/// A piece of trivia (whitespace or comment) with its byte span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    /// Byte range in the source
+    pub span: Range<usize>,
+}
+
+/// A significant token with its surrounding trivia
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+    /// Byte range of the token text in the source (excluding trivia)
+    pub span: Range<usize>,
+    /// Trivia preceding the token
+    pub leading: Vec<Trivia>,
+    /// Trivia following the token on the same line
+    pub trailing: Vec<Trivia>,
+}
+
+impl SyntaxToken {
+    /// Byte range covering the token and all its trivia
+    pub fn full_span(&self) -> Range<usize> {
+        let start = self
+            .leading
+            .first()
+            .map_or(self.span.start, |t| t.span.start);
+        let end = self.trailing.last().map_or(self.span.end, |t| t.span.end);
+        start..end
+    }
+
+    /// Check if this token is the given keyword
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == SyntaxKind::Keyword && self.text == keyword
+    }
+
+    /// Check if this token is the given operator or punctuation
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == SyntaxKind::Symbol && self.text == symbol
+    }
+
+    /// Comments attached to this token (leading and trailing)
+    pub fn comments(&self) -> impl Iterator<Item = &Trivia> {
+        self.leading
+            .iter()
+            .chain(&self.trailing)
+            .filter(|t| matches!(t.kind, TriviaKind::LineComment | TriviaKind::BlockComment))
+    }
+}
+
+/// Lossless token stream for a Modelica source file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyntaxTree {
+    source: String,
+    tokens: Vec<SyntaxToken>,
+}
+
+impl SyntaxTree {
+    /// Build the syntax tree for a source text.
+    ///
+    /// Lexing never fails: characters that don't form valid Modelica tokens
+    /// become [`SyntaxKind::Error`] tokens so the tree stays lossless.
+    pub fn parse(source: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut pending: Vec<Trivia> = Vec::new();
+        let trivia = |kind, span: Range<usize>| Trivia {
+            kind,
+            text: source[span.clone()].to_string(),
+            span,
+        };
+
+        let mut iter = Lexer::new(source).peekable();
+        while let Some(item) = iter.next() {
+            match item {
+                RawItem::Trivia(kind, span) => pending.push(trivia(kind, span)),
+                RawItem::Token(kind, span) => {
+                    let mut trailing = Vec::new();
+                    while let Some(RawItem::Trivia(kind, _)) = iter.peek() {
+                        if *kind == TriviaKind::Newline {
+                            break;
+                        }
+                        if let Some(RawItem::Trivia(kind, span)) = iter.next() {
+                            trailing.push(trivia(kind, span));
+                        }
+                    }
+                    tokens.push(SyntaxToken {
+                        kind,
+                        text: source[span.clone()].to_string(),
+                        span,
+                        leading: std::mem::take(&mut pending),
+                        trailing,
+                    });
+                }
+            }
+        }
+
+        tokens.push(SyntaxToken {
+            kind: SyntaxKind::Eof,
+            text: String::new(),
+            span: source.len()..source.len(),
+            leading: pending,
+            trailing: Vec::new(),
+        });
+
+        Self {
+            source: source.to_string(),
+            tokens,
+        }
+    }
+
+    /// The original source text
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// All tokens in source order, ending with an [`SyntaxKind::Eof`] token
+    pub fn tokens(&self) -> &[SyntaxToken] {
+        &self.tokens
+    }
+
+    /// Reconstruct the source text from tokens and trivia.
+    ///
+    /// This is always byte-identical to the input of [`SyntaxTree::parse`].
+    pub fn to_source(&self) -> String {
+        let mut out = String::with_capacity(self.source.len());
+        for token in &self.tokens {
+            for trivia in &token.leading {
+                out.push_str(&trivia.text);
+            }
+            out.push_str(&token.text);
+            for trivia in &token.trailing {
+                out.push_str(&trivia.text);
+            }
+        }
+        out
+    }
+
+    /// Exact source text of a byte range (e.g., an untouched region to copy)
+    pub fn text(&self, span: Range<usize>) -> Option<&str> {
+        self.source.get(span)
+    }
+
+    /// Index of the token whose text contains the byte offset
+    pub fn token_index_at_offset(&self, offset: usize) -> Option<usize> {
+        let idx = self.tokens.partition_point(|t| t.span.end <= offset);
+        self.tokens
+            .get(idx)
+            .filter(|t| t.span.start <= offset && offset < t.span.end)
+            .map(|_| idx)
+    }
+
+    /// Index of the first token starting at or after the byte offset
+    pub fn token_index_from_offset(&self, offset: usize) -> usize {
+        self.tokens.partition_point(|t| t.span.start < offset)
+    }
+
+    /// Token whose text contains the byte offset
+    pub fn token_at_offset(&self, offset: usize) -> Option<&SyntaxToken> {
+        self.token_index_at_offset(offset).map(|i| &self.tokens[i])
+    }
+
+    /// Tokens whose text lies entirely within a byte range
+    pub fn tokens_in_range(&self, span: Range<usize>) -> &[SyntaxToken] {
+        let start = self.tokens.partition_point(|t| t.span.start < span.start);
+        let end = self.tokens.partition_point(|t| t.span.end <= span.end);
+        &self.tokens[start..end.max(start)]
+    }
+
+    /// Given the index of an opening `(`, `[` or `{`, find the index of its matching
+    /// closing bracket.
+    pub fn matching_close(&self, open_idx: usize) -> Option<usize> {
+        let open = self.tokens.get(open_idx)?;
+        let close = match open.text.as_str() {
+            "(" => ")",
+            "[" => "]",
+            "{" => "}",
+            _ => return None,
+        };
+        let mut depth = 0usize;
+        for (i, token) in self.tokens.iter().enumerate().skip(open_idx) {
+            if token.is_symbol(&open.text) {
+                depth += 1;
+            } else if token.is_symbol(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Byte span of an `annotation(...)` clause starting at the given token index,
+    /// from the `annotation` keyword to the closing parenthesis.
+    pub fn annotation_span_at(&self, idx: usize) -> Option<Range<usize>> {
+        let keyword = self.tokens.get(idx)?;
+        if !keyword.is_keyword("annotation") || !self.tokens.get(idx + 1)?.is_symbol("(") {
+            return None;
+        }
+        let close = self.matching_close(idx + 1)?;
+        Some(keyword.span.start..self.tokens[close].span.end)
+    }
+}
+
+/// Significant tokens of a source text, without trivia.
+///
+/// Tokens are lexed on demand and only their byte spans are produced, so this
+/// scans large files without holding a token list or copies of the text (see
+/// [`SyntaxTree::parse`] for the lossless tree).
+///
+/// # Example
+///
+/// ```
+/// use rumoca::modelica_grammar::cst::{SyntaxKind, lex_tokens};
+///
+/// let source = "model M // comment\n  Real x;\nend M;";
+/// let (kind, span) = lex_tokens(source).nth(2).unwrap();
+/// assert_eq!((kind, &source[span]), (SyntaxKind::Ident, "Real"));
+/// ```
+pub fn lex_tokens(source: &str) -> Tokens<'_> {
+    Tokens(Lexer::new(source))
+}
+
+/// Iterator over the significant tokens of a source text, see [`lex_tokens`]
+pub struct Tokens<'a>(Lexer<'a>);
+
+impl Iterator for Tokens<'_> {
+    type Item = (SyntaxKind, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(|item| match item {
+            RawItem::Token(kind, span) => Some((kind, span)),
+            RawItem::Trivia(..) => None,
+        })
+    }
+}
+
+/// Lexer output before trivia is attached to tokens
+enum RawItem {
+    Token(SyntaxKind, Range<usize>),
+    Trivia(TriviaKind, Range<usize>),
+}
+
+/// Multi-character operators, longest first
+const MULTI_CHAR_SYMBOLS: &[&str] = &[".+", ".-", ".*", "./", ".^", ":=", "==", "<>", "<=", ">="];
+
+/// Lexer producing tokens and trivia in source order
+struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source, pos: 0 }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = RawItem;
+
+    fn next(&mut self) -> Option<RawItem> {
+        let source = self.source;
+        let bytes = source.as_bytes();
+        if self.pos >= bytes.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        let mut pos = self.pos;
+        let rest = &source[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        let item = if c == '\n' || c == '\r' {
+            pos += if rest.starts_with("\r\n") { 2 } else { 1 };
+            RawItem::Trivia(TriviaKind::Newline, start..pos)
+        } else if c == ' ' || c == '\t' || c == '\u{feff}' || c == '\u{c}' {
+            while let Some(ch) = source[pos..].chars().next()
+                && matches!(ch, ' ' | '\t' | '\u{feff}' | '\u{c}')
+            {
+                pos += ch.len_utf8();
+            }
+            RawItem::Trivia(TriviaKind::Whitespace, start..pos)
+        } else if rest.starts_with("//") {
+            pos += rest.find(['\n', '\r']).unwrap_or(rest.len());
+            RawItem::Trivia(TriviaKind::LineComment, start..pos)
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            pos += body.find("*/").map_or(rest.len(), |i| i + 4);
+            RawItem::Trivia(TriviaKind::BlockComment, start..pos)
+        } else if c == '"' {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos] != b'"' {
+                pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+            }
+            pos = (pos + 1).min(bytes.len());
+            RawItem::Token(SyntaxKind::String, start..pos)
+        } else if c == '\'' {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos] != b'\'' {
+                pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+            }
+            pos = (pos + 1).min(bytes.len());
+            RawItem::Token(SyntaxKind::Ident, start..pos)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            pos += rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let kind = if KEYWORDS.contains(&&source[start..pos]) {
+                SyntaxKind::Keyword
+            } else {
+                SyntaxKind::Ident
+            };
+            RawItem::Token(kind, start..pos)
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|ch: char| ch.is_ascii_digit()))
+        {
+            let (len, is_real) = lex_number(rest);
+            pos += len;
+            let kind = if is_real {
+                SyntaxKind::Real
+            } else {
+                SyntaxKind::Integer
+            };
+            RawItem::Token(kind, start..pos)
+        } else if let Some(sym) = MULTI_CHAR_SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            pos += sym.len();
+            RawItem::Token(SyntaxKind::Symbol, start..pos)
+        } else if "+-*/^=<>()[]{},;:.".contains(c) {
+            pos += 1;
+            RawItem::Token(SyntaxKind::Symbol, start..pos)
+        } else {
+            pos += c.len_utf8();
+            RawItem::Token(SyntaxKind::Error, start..pos)
+        };
+
+        self.pos = pos;
+        Some(item)
+    }
+}
+
+/// Length of the numeric literal at the start of `text`, and whether it is a Real
+fn lex_number(text: &str) -> (usize, bool) {
+    let bytes = text.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut end = digits(0);
+    let mut is_real = false;
+    if end < bytes.len() && bytes[end] == b'.' {
+        is_real = true;
+        end = digits(end + 1);
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exp = end + 1;
+        if exp < bytes.len() && (bytes[exp] == b'+' || bytes[exp] == b'-') {
+            exp += 1;
+        }
+        if exp < bytes.len() && bytes[exp].is_ascii_digit() {
+            is_real = true;
+            end = digits(exp);
+        }
+    }
+    (end, is_real)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let source = "within Lib;\r\n/* header */\nmodel M \"doc\" // trailing\n\tReal 'x y'(start = 1.5e-3);\nequation\n  der('x y') = .5 .* 2;\nend M;\n\n// eof\n";
+        let tree = SyntaxTree::parse(source);
+        assert_eq!(tree.to_source(), source);
+        assert_eq!(tree.tokens().last().unwrap().kind, SyntaxKind::Eof);
+    }
+
+    #[test]
+    fn test_token_kinds_and_trivia() {
+        let tree = SyntaxTree::parse("model M // note\n  Real x = 2.0;\nend M;");
+        let tokens = tree.tokens();
+        assert!(tokens[0].is_keyword("model"));
+        assert_eq!(tokens[1].kind, SyntaxKind::Ident);
+        // Same-line comment is trailing trivia of `M`
+        assert_eq!(tokens[1].comments().next().unwrap().text, "// note");
+        // Newline and indentation lead the next token
+        assert_eq!(tokens[2].text, "Real");
+        assert_eq!(tokens[2].leading[0].kind, TriviaKind::Newline);
+        assert_eq!(tokens[5].kind, SyntaxKind::Real);
+    }
+
+    #[test]
+    fn test_annotation_span() {
+        let source = "model M\n  annotation(Icon(graphics = {Line(points = {{0, 0}})}));\nend M;";
+        let tree = SyntaxTree::parse(source);
+        let idx = tree
+            .tokens()
+            .iter()
+            .position(|t| t.is_keyword("annotation"))
+            .unwrap();
+        let span = tree.annotation_span_at(idx).unwrap();
+        assert_eq!(
+            tree.text(span).unwrap(),
+            "annotation(Icon(graphics = {Line(points = {{0, 0}})}))"
+        );
+        assert_eq!(
+            tree.token_at_offset(source.find("Icon").unwrap())
+                .unwrap()
+                .text,
+            "Icon"
+        );
+    }
+}
@@ -1,9 +1,11 @@
 //! Conversion for class definitions and composition structures.
 
+use super::expressions::ExpressionList;
 use super::helpers::{loc_info, span_location};
+use crate::compiler::paths::base_name;
 use crate::ir;
 use crate::modelica_grammar_trait;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use parol_runtime::Token;
 
 //-----------------------------------------------------------------------------
@@ -48,14 +50,7 @@ impl TryFrom<&Token<'_>> for ir::ast::Token {
                 end_column: value.location.end_column,
                 start: value.location.start,
                 end: value.location.end,
-                file_name: value
-                    .location
-                    .file_name
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
+                file_name: base_name(&value.location.file_name.to_string_lossy()).to_string(),
             },
             token_number: value.token_number,
             token_type: value.token_type,
@@ -82,6 +77,26 @@ fn convert_class_type(class_type: &modelica_grammar_trait::ClassType) -> ir::ast
     }
 }
 
+/// Convert the `pure`/`impure` prefix of a function
+fn convert_purity(class_type: &modelica_grammar_trait::ClassType) -> ir::ast::Purity {
+    match class_type {
+        modelica_grammar_trait::ClassType::ClassTypeOpt1ClassTypeOpt2Function(f) => {
+            match f
+                .class_type_opt1
+                .as_ref()
+                .map(|opt| &opt.class_type_opt1_group)
+            {
+                Some(modelica_grammar_trait::ClassTypeOpt1Group::Pure(_)) => ir::ast::Purity::Pure,
+                Some(modelica_grammar_trait::ClassTypeOpt1Group::Impure(_)) => {
+                    ir::ast::Purity::Impure
+                }
+                None => ir::ast::Purity::Unspecified,
+            }
+        }
+        _ => ir::ast::Purity::Unspecified,
+    }
+}
+
 /// Extract the keyword token from grammar ClassType for semantic highlighting
 fn get_class_type_token(class_type: &modelica_grammar_trait::ClassType) -> ir::ast::Token {
     match class_type {
@@ -101,6 +116,42 @@ fn get_class_type_token(class_type: &modelica_grammar_trait::ClassType) -> ir::a
     }
 }
 
+/// The first token of a class definition: its first prefix or class keyword
+fn first_class_token(ast: &modelica_grammar_trait::ClassDefinition) -> &ir::ast::Token {
+    if let Some(opt) = &ast.class_definition_opt {
+        return &opt.encapsulated.encapsulated;
+    }
+    if let Some(opt) = &ast.class_prefixes.class_prefixes_opt {
+        return &opt.partial.partial;
+    }
+    match &ast.class_prefixes.class_type {
+        modelica_grammar_trait::ClassType::ClassTypeOptRecord(r) => match &r.class_type_opt {
+            Some(opt) => &opt.operator.operator,
+            None => &r.record.record,
+        },
+        modelica_grammar_trait::ClassType::ClassTypeOpt0Connector(c) => match &c.class_type_opt0 {
+            Some(opt) => &opt.expandable.expandable,
+            None => &c.connector.connector,
+        },
+        modelica_grammar_trait::ClassType::ClassTypeOpt1ClassTypeOpt2Function(f) => {
+            match (&f.class_type_opt1, &f.class_type_opt2) {
+                (Some(opt), _) => match &opt.class_type_opt1_group {
+                    modelica_grammar_trait::ClassTypeOpt1Group::Pure(p) => &p.pure.pure,
+                    modelica_grammar_trait::ClassTypeOpt1Group::Impure(i) => &i.impure.impure,
+                },
+                (None, Some(opt)) => &opt.operator.operator,
+                (None, None) => &f.function.function,
+            }
+        }
+        modelica_grammar_trait::ClassType::Class(c) => &c.class.class,
+        modelica_grammar_trait::ClassType::Model(m) => &m.model.model,
+        modelica_grammar_trait::ClassType::Block(b) => &b.block.block,
+        modelica_grammar_trait::ClassType::Type(t) => &t.r#type.r#type,
+        modelica_grammar_trait::ClassType::Package(p) => &p.package.package,
+        modelica_grammar_trait::ClassType::Operator(o) => &o.operator.operator,
+    }
+}
+
 //-----------------------------------------------------------------------------
 impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinition {
     type Error = anyhow::Error;
@@ -110,6 +161,8 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
     ) -> std::result::Result<Self, Self::Error> {
         let class_type = convert_class_type(&ast.class_prefixes.class_type);
         let class_type_token = get_class_type_token(&ast.class_prefixes.class_type);
+        let purity = convert_purity(&ast.class_prefixes.class_type);
+        let first_token = first_class_token(ast);
         match &ast.class_specifier {
             modelica_grammar_trait::ClassSpecifier::LongClassSpecifier(long) => {
                 match &long.long_class_specifier {
@@ -123,6 +176,7 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             class_type_token,
                             description: spec.description_string.tokens.clone(),
                             location: span_location(&spec.name, &spec.ident),
+                            span: span_location(first_token, &spec.ident),
                             extends: spec.composition.extends.clone(),
                             imports: spec.composition.imports.clone(),
                             classes: spec.composition.classes.clone(),
@@ -133,6 +187,8 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             components: spec.composition.components.clone(),
                             encapsulated: ast.class_definition_opt.is_some(),
                             partial: ast.class_prefixes.class_prefixes_opt.is_some(),
+                            purity,
+                            external: spec.composition.external,
                             causality: ir::ast::Causality::Empty,
                             equation_keyword: spec.composition.equation_keyword.clone(),
                             initial_equation_keyword: spec
@@ -144,9 +200,15 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                                 .composition
                                 .initial_algorithm_keyword
                                 .clone(),
+                            sections: spec.composition.sections.clone(),
                             end_name_token: Some(spec.ident.clone()),
                             enum_literals: vec![],
                             annotation: spec.composition.annotation.clone(),
+                            internal_inputs: IndexSet::new(),
+                            short_class: None,
+                            replaceable: false,
+                            redeclare: false,
+                            constrainedby: None,
                         })
                     }
                     modelica_grammar_trait::LongClassSpecifier::ExtendsClassSpecifier(ext) => {
@@ -155,18 +217,14 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                         let spec = &ext.extends_class_specifier;
 
                         // Create an extends clause for the inherited class
-                        let extends_modifiers =
-                            if let Some(class_mod) = &spec.extends_class_specifier_opt {
-                                if let Some(arg_list) =
-                                    &class_mod.class_modification.class_modification_opt
-                                {
-                                    arg_list.argument_list.args.clone()
-                                } else {
-                                    vec![]
-                                }
-                            } else {
-                                vec![]
-                            };
+                        let extends_modifiers = spec
+                            .extends_class_specifier_opt
+                            .as_ref()
+                            .and_then(|class_mod| {
+                                class_mod.class_modification.class_modification_opt.as_ref()
+                            })
+                            .map(|arg_list| arg_list.argument_list.clone())
+                            .unwrap_or_default();
 
                         let extends_name = ir::ast::Name {
                             name: vec![spec.ident.clone()],
@@ -175,7 +233,8 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                         let inherited_extends = ir::ast::Extend {
                             comp: extends_name,
                             location: spec.ident.location.clone(),
-                            modifications: extends_modifiers,
+                            modification_prefixes: extends_modifiers.modification_prefixes(),
+                            modifications: extends_modifiers.args,
                         };
 
                         // Combine inherited extends with composition extends
@@ -188,6 +247,7 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             class_type_token,
                             description: spec.description_string.tokens.clone(),
                             location: span_location(&spec.ident, &spec.ident0),
+                            span: span_location(first_token, &spec.ident0),
                             extends: all_extends,
                             imports: spec.composition.imports.clone(),
                             classes: spec.composition.classes.clone(),
@@ -198,6 +258,8 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             components: spec.composition.components.clone(),
                             encapsulated: ast.class_definition_opt.is_some(),
                             partial: ast.class_prefixes.class_prefixes_opt.is_some(),
+                            purity,
+                            external: spec.composition.external,
                             causality: ir::ast::Causality::Empty,
                             equation_keyword: spec.composition.equation_keyword.clone(),
                             initial_equation_keyword: spec
@@ -209,9 +271,15 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                                 .composition
                                 .initial_algorithm_keyword
                                 .clone(),
+                            sections: spec.composition.sections.clone(),
                             end_name_token: Some(spec.ident0.clone()),
                             enum_literals: vec![],
                             annotation: spec.composition.annotation.clone(),
+                            internal_inputs: IndexSet::new(),
+                            short_class: None,
+                            replaceable: false,
+                            redeclare: false,
+                            constrainedby: None,
                         })
                     }
                 }
@@ -253,6 +321,7 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             class_type_token,
                             description: vec![],
                             location: enum_spec.ident.location.clone(),
+                            span: span_location(first_token, &enum_spec.ident),
                             extends: vec![],
                             imports: vec![],
                             classes: IndexMap::new(),
@@ -263,14 +332,22 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             components: IndexMap::new(),
                             encapsulated: ast.class_definition_opt.is_some(),
                             partial: ast.class_prefixes.class_prefixes_opt.is_some(),
+                            purity,
+                            external: false,
                             causality: ir::ast::Causality::Empty,
                             equation_keyword: None,
                             initial_equation_keyword: None,
                             algorithm_keyword: None,
                             initial_algorithm_keyword: None,
+                            sections: vec![],
                             end_name_token: None,
                             enum_literals,
                             annotation: vec![],
+                            internal_inputs: IndexSet::new(),
+                            short_class: None,
+                            replaceable: false,
+                            redeclare: false,
+                            constrainedby: None,
                         })
                     }
                     modelica_grammar_trait::ShortClassSpecifier::TypeClassSpecifier(spec) => {
@@ -296,25 +373,50 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
 
                         // Extract modifications from class_modification if present
                         // e.g., Real(unit="s") -> modifications = [unit = "s"]
-                        let modifications =
-                            if let Some(class_mod_opt0) = &type_spec.type_class_specifier_opt0 {
-                                if let Some(arg_list) =
-                                    &class_mod_opt0.class_modification.class_modification_opt
-                                {
-                                    arg_list.argument_list.args.clone()
-                                } else {
-                                    vec![]
-                                }
-                            } else {
-                                vec![]
-                            };
+                        let modifications = type_spec
+                            .type_class_specifier_opt0
+                            .as_ref()
+                            .and_then(|class_mod_opt0| {
+                                class_mod_opt0
+                                    .class_modification
+                                    .class_modification_opt
+                                    .as_ref()
+                            })
+                            .map(|arg_list| arg_list.argument_list.clone())
+                            .unwrap_or_default();
+
+                        // Array dimensions of the base type, e.g. `type Vec = Real[3]`
+                        let shape_expr = type_spec
+                            .type_class_specifier_opt
+                            .as_ref()
+                            .map(|opt| opt.array_subscripts.subscripts.clone())
+                            .unwrap_or_default();
+                        let shape = shape_expr
+                            .iter()
+                            .filter_map(|subscript| match subscript {
+                                ir::ast::Subscript::Expression(ir::ast::Expression::Terminal {
+                                    token,
+                                    terminal_type: ir::ast::TerminalType::UnsignedInteger,
+                                }) => token.text.parse::<usize>().ok(),
+                                _ => None,
+                            })
+                            .collect();
+
+                        let short_class = ir::ast::ShortClassSpecifier {
+                            base_type: base_type_name.clone(),
+                            shape_expr,
+                            shape,
+                            modifications: modifications.args.clone(),
+                            modification_prefixes: modifications.modification_prefixes(),
+                        };
 
                         // Create an Extend clause for the base type
                         // For short class specifiers, use ident location for both start and end
                         let extend = ir::ast::Extend {
                             comp: base_type_name,
                             location: type_spec.ident.location.clone(),
-                            modifications,
+                            modification_prefixes: modifications.modification_prefixes(),
+                            modifications: modifications.args,
                         };
 
                         Ok(ir::ast::ClassDefinition {
@@ -323,6 +425,7 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             class_type_token,
                             description: vec![],
                             location: type_spec.ident.location.clone(),
+                            span: span_location(first_token, &type_spec.ident),
                             extends: vec![extend],
                             imports: vec![],
                             classes: IndexMap::new(),
@@ -333,14 +436,22 @@ impl TryFrom<&modelica_grammar_trait::ClassDefinition> for ir::ast::ClassDefinit
                             components: IndexMap::new(),
                             encapsulated: ast.class_definition_opt.is_some(),
                             partial: ast.class_prefixes.class_prefixes_opt.is_some(),
+                            purity,
+                            external: false,
                             causality,
                             equation_keyword: None,
                             initial_equation_keyword: None,
                             algorithm_keyword: None,
                             initial_algorithm_keyword: None,
+                            sections: vec![],
                             end_name_token: None, // Short class specifiers don't have "end Name"
                             enum_literals: vec![],
                             annotation: vec![],
+                            internal_inputs: IndexSet::new(),
+                            short_class: Some(short_class),
+                            replaceable: false,
+                            redeclare: false,
+                            constrainedby: None,
                         })
                     }
                 }
@@ -369,8 +480,12 @@ pub struct Composition {
     pub algorithm_keyword: Option<ir::ast::Token>,
     /// Token for "initial algorithm" keyword (if present)
     pub initial_algorithm_keyword: Option<ir::ast::Token>,
+    /// Source ranges of the equation and algorithm sections
+    pub sections: Vec<ir::ast::Section>,
     /// Annotation clause for this class
     pub annotation: Vec<ir::ast::Expression>,
+    /// True if the composition has an `external` clause
+    pub external: bool,
 }
 
 impl TryFrom<&modelica_grammar_trait::Composition> for Composition {
@@ -410,6 +525,7 @@ impl TryFrom<&modelica_grammar_trait::Composition> for Composition {
                 }
                 modelica_grammar_trait::CompositionListGroup::EquationSection(eq_sec) => {
                     let sec = &eq_sec.equation_section;
+                    comp.sections.push(sec.section.clone());
                     for eq in &sec.equations {
                         if sec.initial {
                             comp.initial_equations.push(eq.clone());
@@ -433,6 +549,7 @@ impl TryFrom<&modelica_grammar_trait::Composition> for Composition {
                 }
                 modelica_grammar_trait::CompositionListGroup::AlgorithmSection(alg_sec) => {
                     let sec = &alg_sec.algorithm_section;
+                    comp.sections.push(sec.section.clone());
                     let mut algo = vec![];
                     for stmt in &sec.statements {
                         algo.push(stmt.clone());
@@ -457,6 +574,8 @@ impl TryFrom<&modelica_grammar_trait::Composition> for Composition {
             }
         }
 
+        comp.external = ast.composition_opt.is_some();
+
         // Extract annotation from composition_opt0
         if let Some(annotation_opt) = &ast.composition_opt0
             && let Some(class_mod_opt) = &annotation_opt
@@ -481,6 +600,12 @@ pub struct ElementList {
     pub extends: Vec<ir::ast::Extend>,
 }
 
+/// Class definition or component clause of an element, with or without `replaceable`
+enum ElementDeclaration<'a> {
+    Class(&'a ir::ast::ClassDefinition),
+    Components(&'a modelica_grammar_trait::ComponentClause),
+}
+
 impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
     type Error = anyhow::Error;
 
@@ -494,37 +619,64 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
         for elem_list in &ast.element_list_list {
             match &elem_list.element {
                 modelica_grammar_trait::Element::ElementDefinition(edef) => {
-                    match &edef.element_definition.element_definition_group {
-                        modelica_grammar_trait::ElementDefinitionGroup::ClassDefinition(class) => {
-                            let nested_class = class.class_definition.clone();
+                    let redeclare = edef.element_definition.element_definition_opt.is_some();
+                    // Replaceable elements are declared like the others, and keep
+                    // their constraining type
+                    let (element, replaceable, constrainedby) =
+                        match &edef.element_definition.element_definition_group {
+                            modelica_grammar_trait::ElementDefinitionGroup::ClassDefinition(class) => {
+                                (ElementDeclaration::Class(&class.class_definition), false, None)
+                            }
+                            modelica_grammar_trait::ElementDefinitionGroup::ComponentClause(clause) => {
+                                (ElementDeclaration::Components(&clause.component_clause), false, None)
+                            }
+                            modelica_grammar_trait::ElementDefinitionGroup::ReplaceableElementDefinitionGroupGroupElementDefinitionOpt3(repl) => {
+                                let constrainedby = repl
+                                    .element_definition_opt3
+                                    .as_ref()
+                                    .map(|opt| opt.constraining_clause.type_specifier.name.clone());
+                                let element = match &repl.element_definition_group_group {
+                                    modelica_grammar_trait::ElementDefinitionGroupGroup::ClassDefinition(class) => {
+                                        ElementDeclaration::Class(&class.class_definition)
+                                    }
+                                    modelica_grammar_trait::ElementDefinitionGroupGroup::ComponentClause(clause) => {
+                                        ElementDeclaration::Components(&clause.component_clause)
+                                    }
+                                };
+                                (element, true, constrainedby)
+                            }
+                        };
+                    match element {
+                        ElementDeclaration::Class(class) => {
+                            let mut nested_class = class.clone();
+                            nested_class.replaceable = replaceable;
+                            nested_class.redeclare = redeclare;
+                            nested_class.constrainedby = constrainedby;
                             let name = nested_class.name.text.clone();
                             def.classes.insert(name, nested_class);
                         }
-                        modelica_grammar_trait::ElementDefinitionGroup::ComponentClause(clause) => {
+                        ElementDeclaration::Components(component_clause) => {
                             // Extract inner/outer flags from element definition
-                            let is_inner = edef.element_definition.element_definition_opt1.is_some();
-                            let is_outer = edef.element_definition.element_definition_opt2.is_some();
-
-                            let connection =
-                                match &clause.component_clause.type_prefix.type_prefix_opt {
-                                    Some(opt) => match &opt.type_prefix_opt_group {
-                                        modelica_grammar_trait::TypePrefixOptGroup::Flow(flow) => {
-                                            ir::ast::Connection::Flow(flow.flow.flow.clone())
-                                        }
-                                        modelica_grammar_trait::TypePrefixOptGroup::Stream(
-                                            stream,
-                                        ) => ir::ast::Connection::Stream(
-                                            stream.stream.stream.clone(),
-                                        ),
-                                    },
-                                    None => ir::ast::Connection::Empty,
-                                };
+                            let is_final =
+                                edef.element_definition.element_definition_opt0.is_some();
+                            let is_inner =
+                                edef.element_definition.element_definition_opt1.is_some();
+                            let is_outer =
+                                edef.element_definition.element_definition_opt2.is_some();
 
-                            let variability = match &clause
-                                .component_clause
-                                .type_prefix
-                                .type_prefix_opt0
-                            {
+                            let connection = match &component_clause.type_prefix.type_prefix_opt {
+                                Some(opt) => match &opt.type_prefix_opt_group {
+                                    modelica_grammar_trait::TypePrefixOptGroup::Flow(flow) => {
+                                        ir::ast::Connection::Flow(flow.flow.flow.clone())
+                                    }
+                                    modelica_grammar_trait::TypePrefixOptGroup::Stream(stream) => {
+                                        ir::ast::Connection::Stream(stream.stream.stream.clone())
+                                    }
+                                },
+                                None => ir::ast::Connection::Empty,
+                            };
+
+                            let variability = match &component_clause.type_prefix.type_prefix_opt0 {
                                 Some(opt) => match &opt.type_prefix_opt0_group {
                                     modelica_grammar_trait::TypePrefixOpt0Group::Constant(c) => {
                                         ir::ast::Variability::Constant(c.constant.constant.clone())
@@ -541,24 +693,22 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                 None => ir::ast::Variability::Empty,
                             };
 
-                            let causality =
-                                match &clause.component_clause.type_prefix.type_prefix_opt1 {
-                                    Some(opt) => match &opt.type_prefix_opt1_group {
-                                        modelica_grammar_trait::TypePrefixOpt1Group::Input(c) => {
-                                            ir::ast::Causality::Input(c.input.input.clone())
-                                        }
-                                        modelica_grammar_trait::TypePrefixOpt1Group::Output(c) => {
-                                            ir::ast::Causality::Output(c.output.output.clone())
-                                        }
-                                    },
-                                    None => ir::ast::Causality::Empty,
-                                };
+                            let causality = match &component_clause.type_prefix.type_prefix_opt1 {
+                                Some(opt) => match &opt.type_prefix_opt1_group {
+                                    modelica_grammar_trait::TypePrefixOpt1Group::Input(c) => {
+                                        ir::ast::Causality::Input(c.input.input.clone())
+                                    }
+                                    modelica_grammar_trait::TypePrefixOpt1Group::Output(c) => {
+                                        ir::ast::Causality::Output(c.output.output.clone())
+                                    }
+                                },
+                                None => ir::ast::Causality::Empty,
+                            };
 
                             // Extract type-level array subscripts (e.g., Real[3] z)
                             // These apply to all components in this clause
                             let mut type_level_shape = Vec::new();
-                            if let Some(clause_opt) = &clause.component_clause.component_clause_opt
-                            {
+                            if let Some(clause_opt) = &component_clause.component_clause_opt {
                                 for subscript in &clause_opt.array_subscripts.subscripts {
                                     if let ir::ast::Subscript::Expression(
                                         ir::ast::Expression::Terminal {
@@ -566,13 +716,14 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                             terminal_type: ir::ast::TerminalType::UnsignedInteger,
                                         },
                                     ) = subscript
-                                        && let Ok(dim) = token.text.parse::<usize>() {
-                                            type_level_shape.push(dim);
-                                        }
+                                        && let Ok(dim) = token.text.parse::<usize>()
+                                    {
+                                        type_level_shape.push(dim);
+                                    }
                                 }
                             }
 
-                            for c in &clause.component_clause.component_list.components {
+                            for c in &component_clause.component_list.components {
                                 // Extract annotation arguments if present
                                 let annotation =
                                     if let Some(desc_opt) = &c.description.description_opt {
@@ -590,8 +741,7 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                     };
 
                                 // Compute location spanning from type_specifier to declaration ident
-                                let comp_location = clause
-                                    .component_clause
+                                let comp_location = component_clause
                                     .type_specifier
                                     .name
                                     .name
@@ -608,7 +758,7 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                 let mut value = ir::ast::Component {
                                     name: c.declaration.ident.text.clone(),
                                     name_token: c.declaration.ident.clone(),
-                                    type_name: clause.component_clause.type_specifier.name.clone(),
+                                    type_name: component_clause.type_specifier.name.clone(),
                                     variability: variability.clone(),
                                     causality: causality.clone(),
                                     connection: connection.clone(),
@@ -627,10 +777,15 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                     shape_is_modification: false,
                                     annotation,
                                     modifications: indexmap::IndexMap::new(),
+                                    modification_prefixes: indexmap::IndexMap::new(),
                                     location: comp_location,
                                     condition,
                                     inner: is_inner,
                                     outer: is_outer,
+                                    r#final: is_final,
+                                    replaceable,
+                                    redeclare,
+                                    constrainedby: constrainedby.clone(),
                                 };
 
                                 // set default start value
@@ -649,10 +804,10 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                             ..Default::default()
                                         },
                                     },
-                                    "Bool" => ir::ast::Expression::Terminal {
+                                    "Boolean" | "Bool" => ir::ast::Expression::Terminal {
                                         terminal_type: ir::ast::TerminalType::Bool,
                                         token: ir::ast::Token {
-                                            text: "0".to_string(),
+                                            text: "false".to_string(),
                                             ..Default::default()
                                         },
                                     },
@@ -665,13 +820,17 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                         // Store the full subscript (Expression or Range) for formatting
                                         value.shape_expr.push(subscript.clone());
                                         // Also try to extract integer dimension if it's a literal expression
-                                        if let ir::ast::Subscript::Expression(ir::ast::Expression::Terminal {
-                                            token,
-                                            terminal_type: ir::ast::TerminalType::UnsignedInteger,
-                                        }) = subscript
-                                            && let Ok(dim) = token.text.parse::<usize>() {
-                                                value.shape.push(dim);
-                                            }
+                                        if let ir::ast::Subscript::Expression(
+                                            ir::ast::Expression::Terminal {
+                                                token,
+                                                terminal_type:
+                                                    ir::ast::TerminalType::UnsignedInteger,
+                                            },
+                                        ) = subscript
+                                            && let Ok(dim) = token.text.parse::<usize>()
+                                        {
+                                            value.shape.push(dim);
+                                        }
                                     }
                                 }
 
@@ -684,23 +843,50 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                             let modif = &*(class_mod.class_modification);
                                             if let Some(opt) = &modif.class_modification_opt {
                                                 // Look for start=, shape=, and other parameter modifications
+                                                let prefixes = opt.argument_list.modification_prefixes();
+                                                let mut seen = std::collections::HashSet::new();
                                                 for (idx, arg) in opt.argument_list.args.iter().enumerate() {
-                                                    if let ir::ast::Expression::Binary { op, lhs, rhs } = arg
-                                                        && matches!(op, ir::ast::OpBinary::Assign(_)) {
+                                                    // Nested class modifications like inertia(J=2) are flattened
+                                                    // to dotted paths; redeclarations only keep direct values
+                                                    let pairs = if opt.argument_list.redeclare_flags.get(idx).copied().unwrap_or(false) {
+                                                        match arg {
+                                                            ir::ast::Expression::Binary { op: ir::ast::OpBinary::Assign(_), lhs, rhs } => match &**lhs {
+                                                                ir::ast::Expression::ComponentReference(comp) => vec![(comp.clone(), (**rhs).clone())],
+                                                                _ => vec![],
+                                                            },
+                                                            _ => vec![],
+                                                        }
+                                                    } else {
+                                                        arg.modification_pairs()
+                                                    };
+                                                    for (comp, rhs) in &pairs {
                                                             // This is a named argument like start=2.5, shape=(3), or R=10
-                                                            if let ir::ast::Expression::ComponentReference(comp) = &**lhs {
+                                                            {
                                                                 let param_name = comp.to_string();
+                                                                if !seen.insert(param_name.clone()) {
+                                                                    anyhow::bail!(
+                                                                        "Duplicate modification of '{}' in declaration of '{}'{}",
+                                                                        param_name,
+                                                                        c.declaration.ident.text,
+                                                                        comp.parts.first().map(|p| loc_info(&p.ident)).unwrap_or_default()
+                                                                    );
+                                                                }
+                                                                let prefix = prefixes.get(&param_name).copied().unwrap_or_default();
+                                                                if !prefix.is_empty() {
+                                                                    value.modification_prefixes.insert(param_name.clone(), prefix);
+                                                                }
                                                                 // Check if this argument has the `each` modifier
-                                                                let has_each = opt.argument_list.each_flags.get(idx).copied().unwrap_or(false);
+                                                                let has_each = prefix.each;
                                                                 match param_name.as_str() {
                                                                     "start" => {
-                                                                        value.start = (**rhs).clone();
+                                                                        value.start = rhs.clone();
                                                                         value.start_is_modification = true;
                                                                         value.start_has_each = has_each;
                                                                     }
                                                                     "shape" => {
+                                                                        value.shape_is_modification = true;
                                                                         // Extract shape from expression like (3) or {3, 2}
-                                                                        match &**rhs {
+                                                                        match rhs {
                                                                             // Handle shape=3 - single dimension without parens
                                                                             ir::ast::Expression::Terminal {
                                                                                 token,
@@ -804,7 +990,7 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                                                         }
 
                                                                         // Store modification (for user-defined types or valid built-in attrs)
-                                                                        value.modifications.insert(param_name, (**rhs).clone());
+                                                                        value.modifications.insert(param_name, rhs.clone());
                                                                     }
                                                                 }
                                                             }
@@ -834,63 +1020,6 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                                     .insert(c.declaration.ident.text.clone(), value);
                             }
                         }
-                        modelica_grammar_trait::ElementDefinitionGroup::ReplaceableElementDefinitionGroupGroupElementDefinitionOpt3(repl) => {
-                            // Handle replaceable ( class_definition | component_clause )
-                            // Extract inner/outer flags from element definition
-                            let is_inner_repl = edef.element_definition.element_definition_opt1.is_some();
-                            let is_outer_repl = edef.element_definition.element_definition_opt2.is_some();
-
-                            match &repl.element_definition_group_group {
-                                modelica_grammar_trait::ElementDefinitionGroupGroup::ClassDefinition(class) => {
-                                    let nested_class = class.class_definition.clone();
-                                    let name = nested_class.name.text.clone();
-                                    def.classes.insert(name, nested_class);
-                                }
-                                modelica_grammar_trait::ElementDefinitionGroupGroup::ComponentClause(clause) => {
-                                    // Process replaceable component clause - simplified handling
-                                    for c in &clause.component_clause.component_list.components {
-                                        let comp_location = clause
-                                            .component_clause
-                                            .type_specifier
-                                            .name
-                                            .name
-                                            .first()
-                                            .map(|start_tok| span_location(start_tok, &c.declaration.ident))
-                                            .unwrap_or_else(|| c.declaration.ident.location.clone());
-
-                                        // Extract condition attribute for replaceable components
-                                        let condition = c
-                                            .component_declaration_opt
-                                            .as_ref()
-                                            .map(|opt| opt.condition_attribute.expression.clone());
-
-                                        let value = ir::ast::Component {
-                                            name: c.declaration.ident.text.clone(),
-                                            name_token: c.declaration.ident.clone(),
-                                            type_name: clause.component_clause.type_specifier.name.clone(),
-                                            variability: ir::ast::Variability::Empty,
-                                            causality: ir::ast::Causality::Empty,
-                                            connection: ir::ast::Connection::Empty,
-                                            description: c.description.description_string.tokens.clone(),
-                                            start: ir::ast::Expression::Empty,
-                                            start_is_modification: false,
-                                            start_has_each: false,
-                                            shape: Vec::new(),
-                                            shape_expr: Vec::new(),
-                                            shape_is_modification: false,
-                                            annotation: Vec::new(),
-                                            modifications: indexmap::IndexMap::new(),
-                                            location: comp_location,
-                                            condition,
-                                            inner: is_inner_repl,
-                                            outer: is_outer_repl,
-                                        };
-
-                                        def.components.insert(c.declaration.ident.text.clone(), value);
-                                    }
-                                }
-                            }
-                        }
                     }
                 }
                 modelica_grammar_trait::Element::ImportClause(import_elem) => {
@@ -970,12 +1099,12 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                         {
                             // Extract all arguments from the modification list
                             let list = &mod_opt.argument_or_inheritance_modification_list;
-                            let mut mods = Vec::new();
+                            let mut mods = ExpressionList::default();
 
                             // First item
                             match &list.argument_or_inheritance_modification_list_group {
                                 modelica_grammar_trait::ArgumentOrInheritanceModificationListGroup::Argument(arg) => {
-                                    mods.push(arg.argument.expression.clone());
+                                    mods.push_modification(&arg.argument);
                                 }
                                 modelica_grammar_trait::ArgumentOrInheritanceModificationListGroup::InheritanceModification(_) => {
                                     // Inheritance modifications (break/connect) not yet supported
@@ -986,7 +1115,7 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                             for item in &list.argument_or_inheritance_modification_list_list {
                                 match &item.argument_or_inheritance_modification_list_list_group {
                                     modelica_grammar_trait::ArgumentOrInheritanceModificationListListGroup::Argument(arg) => {
-                                        mods.push(arg.argument.expression.clone());
+                                        mods.push_modification(&arg.argument);
                                     }
                                     modelica_grammar_trait::ArgumentOrInheritanceModificationListListGroup::InheritanceModification(_) => {
                                         // Inheritance modifications (break/connect) not yet supported
@@ -996,10 +1125,10 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
 
                             mods
                         } else {
-                            Vec::new()
+                            ExpressionList::default()
                         }
                     } else {
-                        Vec::new()
+                        ExpressionList::default()
                     };
 
                     // Note: Annotations in extends clauses are currently ignored
@@ -1008,7 +1137,8 @@ impl TryFrom<&modelica_grammar_trait::ElementList> for ElementList {
                     def.extends.push(ir::ast::Extend {
                         comp: clause.extends_clause.type_specifier.name.clone(),
                         location: extend_location,
-                        modifications,
+                        modification_prefixes: modifications.modification_prefixes(),
+                        modifications: modifications.args,
                     });
                 }
             }
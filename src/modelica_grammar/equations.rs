@@ -100,12 +100,14 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
     fn try_from(
         ast: &modelica_grammar_trait::SomeEquation,
     ) -> std::result::Result<Self, Self::Error> {
+        let description = ast.description.description_string.tokens.clone();
         match &ast.some_equation_option {
             modelica_grammar_trait::SomeEquationOption::SimpleEquation(eq) => {
                 match &eq.simple_equation.simple_equation_opt {
                     Some(rhs) => Ok(ir::ast::Equation::Simple {
                         lhs: eq.simple_equation.simple_expression.clone(),
                         rhs: rhs.expression.clone(),
+                        description,
                     }),
                     None => {
                         // this is a function call eq (reinit, assert, terminate, etc.)
@@ -115,6 +117,7 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
                                 Ok(ir::ast::Equation::FunctionCall {
                                     comp: comp.clone(),
                                     args: args.clone(),
+                                    description,
                                 })
                             }
                             _ => Err(anyhow::anyhow!(
@@ -129,6 +132,7 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
                 Ok(ir::ast::Equation::Connect {
                     lhs: eq.connect_equation.component_reference.clone(),
                     rhs: eq.connect_equation.component_reference0.clone(),
+                    description,
                 })
             }
             modelica_grammar_trait::SomeEquationOption::ForEquation(eq) => {
@@ -169,7 +173,11 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
                     .map(|eq_item| eq_item.some_equation.clone())
                     .collect();
 
-                Ok(ir::ast::Equation::For { indices, equations })
+                Ok(ir::ast::Equation::For {
+                    indices,
+                    equations,
+                    description,
+                })
             }
             modelica_grammar_trait::SomeEquationOption::IfEquation(eq) => {
                 let mut blocks = vec![eq.if_equation.if0.clone()];
@@ -184,6 +192,7 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
                             .map(|x| x.some_equation.clone())
                             .collect()
                     }),
+                    description,
                 })
             }
             modelica_grammar_trait::SomeEquationOption::WhenEquation(eq) => {
@@ -191,7 +200,10 @@ impl TryFrom<&modelica_grammar_trait::SomeEquation> for ir::ast::Equation {
                 for when in &eq.when_equation.when_equation_list {
                     cond_blocks.push(when.elsewhen0.clone());
                 }
-                Ok(ir::ast::Equation::When(cond_blocks))
+                Ok(ir::ast::Equation::When {
+                    blocks: cond_blocks,
+                    description,
+                })
             }
         }
     }
@@ -202,6 +214,7 @@ impl TryFrom<&modelica_grammar_trait::Statement> for ir::ast::Statement {
     type Error = anyhow::Error;
 
     fn try_from(ast: &modelica_grammar_trait::Statement) -> std::result::Result<Self, Self::Error> {
+        let description = ast.description.description_string.tokens.clone();
         match &ast.statement_option {
             modelica_grammar_trait::StatementOption::ComponentStatement(stmt) => {
                 match &stmt.component_statement.component_statement_group {
@@ -209,22 +222,26 @@ impl TryFrom<&modelica_grammar_trait::Statement> for ir::ast::Statement {
                         Ok(ir::ast::Statement::Assignment {
                             comp: stmt.component_statement.component_reference.clone(),
                             value: assign.expression.clone(),
+                            description,
                         })
                     }
                     modelica_grammar_trait::ComponentStatementGroup::FunctionCallArgs(args) => {
                         Ok(ir::ast::Statement::FunctionCall {
                             comp: stmt.component_statement.component_reference.clone(),
                             args: args.function_call_args.args.clone(),
+                            description,
                         })
                     }
                 }
             }
             modelica_grammar_trait::StatementOption::Break(tok) => Ok(ir::ast::Statement::Break {
                 token: tok.r#break.r#break.clone(),
+                description,
             }),
             modelica_grammar_trait::StatementOption::Return(tok) => {
                 Ok(ir::ast::Statement::Return {
                     token: tok.r#return.r#return.clone(),
+                    description,
                 })
             }
             modelica_grammar_trait::StatementOption::ForStatement(stmt) => {
@@ -265,7 +282,11 @@ impl TryFrom<&modelica_grammar_trait::Statement> for ir::ast::Statement {
                     .map(|stmt_item| stmt_item.statement.clone())
                     .collect();
 
-                Ok(ir::ast::Statement::For { indices, equations })
+                Ok(ir::ast::Statement::For {
+                    indices,
+                    equations,
+                    description,
+                })
             }
             modelica_grammar_trait::StatementOption::IfStatement(stmt) => {
                 let if_stmt = &stmt.if_statement;
@@ -288,6 +309,7 @@ impl TryFrom<&modelica_grammar_trait::Statement> for ir::ast::Statement {
                 Ok(ir::ast::Statement::If {
                     cond_blocks,
                     else_block,
+                    description,
                 })
             }
             modelica_grammar_trait::StatementOption::WhenStatement(stmt) => {
@@ -325,6 +347,7 @@ impl TryFrom<&modelica_grammar_trait::Statement> for ir::ast::Statement {
                 Ok(ir::ast::Statement::FunctionCall {
                     comp: fcall.component_reference.clone(),
                     args: fcall.function_call_args.args.clone(),
+                    description,
                 })
             }
         }
@@ -3,6 +3,7 @@
 use super::helpers::{collect_array_elements, loc_info};
 use crate::ir;
 use crate::modelica_grammar_trait;
+use indexmap::IndexMap;
 
 //-----------------------------------------------------------------------------
 #[derive(Debug, Default, Clone)]
@@ -49,6 +50,11 @@ pub struct ModificationArg {
     pub each: bool,
     /// True if this argument has `final` prefix
     pub r#final: bool,
+    /// Prefixes of nested modifications, keyed by path relative to this argument
+    /// (e.g. `inertia(each w(start=0), final J=2)` records `w` and `J`)
+    pub nested_prefixes: IndexMap<String, ir::ast::ModificationPrefix>,
+    /// True if this argument is an element redeclaration (`redeclare ...`)
+    pub redeclare: bool,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -57,27 +63,85 @@ pub struct ExpressionList {
     pub args: Vec<ir::ast::Expression>,
     /// Parallel to args - true if the corresponding arg has `each` modifier prefix
     pub each_flags: Vec<bool>,
+    /// Parallel to args - true if the corresponding arg has `final` modifier prefix
+    /// (only populated for modification argument lists)
+    pub final_flags: Vec<bool>,
+    /// Parallel to args - prefixes of nested modifications inside each argument
+    /// (only populated for modification argument lists)
+    pub nested_prefixes: Vec<IndexMap<String, ir::ast::ModificationPrefix>>,
+    /// Parallel to args - true if the corresponding arg is a redeclaration
+    /// (only populated for modification argument lists)
+    pub redeclare_flags: Vec<bool>,
+}
+
+impl ExpressionList {
+    /// Append a modification argument, keeping its prefixes
+    pub fn push_modification(&mut self, arg: &ModificationArg) {
+        self.args.push(arg.expression.clone());
+        self.each_flags.push(arg.each);
+        self.final_flags.push(arg.r#final);
+        self.nested_prefixes.push(arg.nested_prefixes.clone());
+        self.redeclare_flags.push(arg.redeclare);
+    }
+
+    /// Prefixes of all modifications in this argument list, keyed by dotted path
+    /// (e.g. `(each start=0, inertia(final J=2))` gives `start` and `inertia.J`)
+    pub fn modification_prefixes(&self) -> IndexMap<String, ir::ast::ModificationPrefix> {
+        let mut prefixes = IndexMap::new();
+        for (idx, arg) in self.args.iter().enumerate() {
+            let Some(target) = modification_target(arg) else {
+                continue;
+            };
+            let prefix = ir::ast::ModificationPrefix {
+                each: self.each_flags.get(idx).copied().unwrap_or(false),
+                r#final: self.final_flags.get(idx).copied().unwrap_or(false),
+            };
+            if !prefix.is_empty() {
+                prefixes.insert(target.clone(), prefix);
+            }
+            for (path, nested) in self.nested_prefixes.get(idx).into_iter().flatten() {
+                prefixes.insert(format!("{}.{}", target, path), *nested);
+            }
+        }
+        prefixes
+    }
+}
+
+/// Dotted name of the element a modification argument applies to
+/// (`J = 2`, `inertia(J = 2)` and `inertia(J = 2) = x` give `J`, `inertia`, `inertia`)
+pub fn modification_target(arg: &ir::ast::Expression) -> Option<String> {
+    let comp = match arg {
+        ir::ast::Expression::Binary {
+            op: ir::ast::OpBinary::Assign(_),
+            lhs,
+            ..
+        } => match &**lhs {
+            ir::ast::Expression::ComponentReference(comp) => comp,
+            ir::ast::Expression::FunctionCall { comp, .. } => comp,
+            _ => return None,
+        },
+        ir::ast::Expression::FunctionCall { comp, .. } => comp,
+        _ => return None,
+    };
+    Some(dotted_name(comp))
+}
+
+/// Dotted name of a component reference, without subscripts
+fn dotted_name(comp: &ir::ast::ComponentReference) -> String {
+    comp.parts
+        .iter()
+        .map(|p| p.ident.text.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
 }
 
 /// Convert a NamedArgument to an Expression representing `name = value`
 fn named_argument_to_expr(
     named_arg: &modelica_grammar_trait::NamedArgument,
 ) -> ir::ast::Expression {
-    // Create a component reference for the argument name
-    let name_expr = ir::ast::Expression::ComponentReference(ir::ast::ComponentReference {
-        local: false,
-        parts: vec![ir::ast::ComponentRefPart {
-            ident: named_arg.ident.clone(),
-            subs: None,
-        }],
-    });
-
-    // Create a binary expression: name = value
-    // Use Assign (not Eq) because this is a modification assignment, not equality comparison
-    ir::ast::Expression::Binary {
-        op: ir::ast::OpBinary::Assign(ir::ast::Token::default()),
-        lhs: Box::new(name_expr),
-        rhs: Box::new(named_arg.function_argument.clone()),
+    ir::ast::Expression::NamedArgument {
+        name: named_arg.ident.clone(),
+        value: Box::new(named_arg.function_argument.clone()),
     }
 }
 
@@ -171,12 +235,17 @@ impl TryFrom<&modelica_grammar_trait::FunctionArguments> for ExpressionList {
                             return Ok(ExpressionList {
                                 args: vec![comprehension],
                                 each_flags: vec![false],
+                                ..Default::default()
                             });
                         }
                     }
                 }
                 let each_flags = vec![false; args.len()];
-                Ok(ExpressionList { args, each_flags })
+                Ok(ExpressionList {
+                    args,
+                    each_flags,
+                    ..Default::default()
+                })
             }
             modelica_grammar_trait::FunctionArguments::FunctionPartialApplicationFunctionArgumentsOpt0(fpa) => {
                 // Convert 'function Foo.Bar(arg=val)' to a function call expression
@@ -217,12 +286,20 @@ impl TryFrom<&modelica_grammar_trait::FunctionArguments> for ExpressionList {
                 }
 
                 let each_flags = vec![false; args.len()];
-                Ok(ExpressionList { args, each_flags })
+                Ok(ExpressionList {
+                    args,
+                    each_flags,
+                    ..Default::default()
+                })
             }
             modelica_grammar_trait::FunctionArguments::NamedArguments(named) => {
                 let args = collect_named_arguments(&named.named_arguments);
                 let each_flags = vec![false; args.len()];
-                Ok(ExpressionList { args, each_flags })
+                Ok(ExpressionList {
+                    args,
+                    each_flags,
+                    ..Default::default()
+                })
             }
         }
     }
@@ -241,12 +318,20 @@ impl TryFrom<&modelica_grammar_trait::FunctionArgumentsNonFirst> for ExpressionL
                     args.append(&mut opt.function_arguments_non_first.args.clone());
                 }
                 let each_flags = vec![false; args.len()];
-                Ok(ExpressionList { args, each_flags })
+                Ok(ExpressionList {
+                    args,
+                    each_flags,
+                    ..Default::default()
+                })
             }
             modelica_grammar_trait::FunctionArgumentsNonFirst::NamedArguments(named) => {
                 let args = collect_named_arguments(&named.named_arguments);
                 let each_flags = vec![false; args.len()];
-                Ok(ExpressionList { args, each_flags })
+                Ok(ExpressionList {
+                    args,
+                    each_flags,
+                    ..Default::default()
+                })
             }
         }
     }
@@ -261,13 +346,13 @@ impl TryFrom<&modelica_grammar_trait::ArgumentList> for ExpressionList {
     ) -> std::result::Result<Self, Self::Error> {
         // After grammar change, ast.argument is ModificationArg
         // Extract expressions and each_flags from ModificationArgs
-        let mut args = vec![ast.argument.expression.clone()];
-        let mut each_flags = vec![ast.argument.each];
-        for arg in &ast.argument_list_list {
-            args.push(arg.argument.expression.clone());
-            each_flags.push(arg.argument.each);
+        let mut list = ExpressionList::default();
+        for arg in
+            std::iter::once(&ast.argument).chain(ast.argument_list_list.iter().map(|a| &a.argument))
+        {
+            list.push_modification(arg);
         }
-        Ok(ExpressionList { args, each_flags })
+        Ok(list)
     }
 }
 
@@ -646,10 +731,28 @@ impl TryFrom<&modelica_grammar_trait::Argument> for ModificationArg {
         // Use the existing conversion to get the expression
         let expression: ir::ast::Expression = ast.try_into()?;
 
+        // Keep the prefixes of nested modifications like `inertia(final J=2)`
+        let mut nested_prefixes = IndexMap::new();
+        if let modelica_grammar_trait::Argument::ElementModificationOrReplaceable(modif) = ast
+            && let modelica_grammar_trait::ElementModificationOrReplaceableGroup::ElementModification(elem) =
+                &modif.element_modification_or_replaceable.element_modification_or_replaceable_group
+            && let Some(opt) = &elem.element_modification.element_modification_opt
+            && let modelica_grammar_trait::Modification::ClassModificationModificationOpt(class_modif) =
+                &opt.modification
+            && let Some(class_opt) = &class_modif.class_modification.class_modification_opt
+        {
+            nested_prefixes = class_opt.argument_list.modification_prefixes();
+        }
+
         Ok(ModificationArg {
             expression,
             each,
             r#final,
+            nested_prefixes,
+            redeclare: matches!(
+                ast,
+                modelica_grammar_trait::Argument::ElementRedeclaration(_)
+            ),
         })
     }
 }
@@ -664,16 +767,22 @@ impl TryFrom<&modelica_grammar_trait::OutputExpressionList> for ExpressionList {
         let mut v = Vec::new();
         if let Some(opt) = &ast.output_expression_list_opt {
             v.push(opt.expression.clone());
+        } else if !ast.output_expression_list_list.is_empty() {
+            // Skipped output, e.g. the first of `(, r) = divmod(a, b)`
+            v.push(ir::ast::Expression::Empty);
         }
         for expr in &ast.output_expression_list_list {
             if let Some(opt) = &expr.output_expression_list_opt0 {
                 v.push(opt.expression.clone());
+            } else {
+                v.push(ir::ast::Expression::Empty);
             }
         }
         let each_flags = vec![false; v.len()];
         Ok(ExpressionList {
             args: v,
             each_flags,
+            ..Default::default()
         })
     }
 }
@@ -687,12 +796,13 @@ impl TryFrom<&modelica_grammar_trait::FunctionCallArgs> for ExpressionList {
         if let Some(opt) = &ast.function_call_args_opt {
             let args = opt.function_arguments.args.clone();
             let each_flags = opt.function_arguments.each_flags.clone();
-            Ok(ExpressionList { args, each_flags })
-        } else {
             Ok(ExpressionList {
-                args: vec![],
-                each_flags: vec![],
+                args,
+                each_flags,
+                ..Default::default()
             })
+        } else {
+            Ok(ExpressionList::default())
         }
     }
 }
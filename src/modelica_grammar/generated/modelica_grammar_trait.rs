@@ -902,12 +902,12 @@ pub struct IdentBasicIdent {
 ///
 /// Type derived for production 60
 ///
-/// `ident: /\'[_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]*\'/@q_ident;`
+/// `ident: /\'([_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]|\\[\'\"\?\\abfnrtv])*\'/@q_ident;`
 ///
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct IdentQIdent {
-    pub q_ident: crate::ir::ast::Token, /* \'[_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]*\' */
+    pub q_ident: crate::ir::ast::Token, /* \'([_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]|\\[\'\"\?\\abfnrtv])*\' */
 }
 
 ///
@@ -2432,6 +2432,7 @@ pub struct AlgorithmSection {
 #[derive(Debug, Clone)]
 pub struct AlgorithmSectionList {
     pub statement: crate::ir::ast::Statement,
+    pub semicolon: crate::ir::ast::Token, /* ; */
 }
 
 ///
@@ -3131,6 +3132,7 @@ pub struct Connect {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ConnectEquation {
+    pub connect: Connect,
     pub component_reference: crate::ir::ast::ComponentReference,
     pub component_reference0: crate::ir::ast::ComponentReference,
 }
@@ -3708,6 +3710,7 @@ pub struct EquationSection {
 #[derive(Debug, Clone)]
 pub struct EquationSectionList {
     pub some_equation: crate::ir::ast::Equation,
+    pub semicolon: crate::ir::ast::Token, /* ; */
 }
 
 ///
@@ -3928,6 +3931,7 @@ pub struct For {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ForEquation {
+    pub r#for: For,
     pub for_indices: ForIndices,
     pub for_equation_list: Vec<ForEquationList>,
 }
@@ -3985,6 +3989,7 @@ pub struct ForIndicesList {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ForStatement {
+    pub r#for: For,
     pub for_indices: ForIndices,
     pub for_statement_list: Vec<ForStatementList>,
 }
@@ -4103,6 +4108,7 @@ pub struct FunctionCallArgsOpt {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct FunctionCallOutputStatement {
+    pub l_paren: crate::ir::ast::Token, /* ( */
     pub output_expression_list: crate::modelica_grammar::ExpressionList,
     pub component_reference: crate::ir::ast::ComponentReference,
     pub function_call_args: crate::modelica_grammar::ExpressionList,
@@ -4174,6 +4180,7 @@ pub struct If {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct IfEquation {
+    pub r#if: If,
     pub r#if0: crate::ir::ast::EquationBlock,
     pub if_equation_list: Vec<IfEquationList>,
     pub if_equation_opt: Option<IfEquationOpt>,
@@ -4234,6 +4241,7 @@ pub struct IfExpressionList {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct IfStatement {
+    pub r#if: If,
     pub r#if0: crate::ir::ast::StatementBlock,
     pub if_statement_list: Vec<IfStatementList>,
     pub if_statement_opt: Option<IfStatementOpt>,
@@ -5298,6 +5306,7 @@ pub struct When {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct WhenEquation {
+    pub when: When,
     pub when0: crate::ir::ast::EquationBlock,
     pub when_equation_list: Vec<WhenEquationList>,
 }
@@ -5317,6 +5326,7 @@ pub struct WhenEquationList {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct WhenStatement {
+    pub when: When,
     pub when0: crate::ir::ast::StatementBlock,
     pub when_statement_list: Vec<WhenStatementList>,
 }
@@ -5345,6 +5355,7 @@ pub struct While {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct WhileStatement {
+    pub r#while: While,
     pub expression: crate::ir::ast::Expression,
     pub while_statement_list: Vec<WhileStatementList>,
 }
@@ -6889,7 +6900,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 60:
     ///
-    /// `ident: /\'[_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]*\'/@q_ident;`
+    /// `ident: /\'([_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]|\\[\'\"\?\\abfnrtv])*\'/@q_ident;`
     ///
     #[parol_runtime::function_name::named]
     fn ident_1(&mut self, q_ident: &ParseTreeType<'t>) -> Result<()> {
@@ -11802,21 +11813,26 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 282:
     ///
-    /// `equation_sectionList /* Vec<T>::Push */: some_equation ';'^ /* Clipped */ equation_sectionList;`
+    /// `equation_sectionList /* Vec<T>::Push */: some_equation ';' equation_sectionList;`
     ///
     #[parol_runtime::function_name::named]
     fn equation_section_list_0(
         &mut self,
         _some_equation: &ParseTreeType<'t>,
-        _semicolon: &ParseTreeType<'t>,
+        semicolon: &ParseTreeType<'t>,
         _equation_section_list: &ParseTreeType<'t>,
     ) -> Result<()> {
         let context = function_name!();
         trace!("{}", self.trace_item_stack(context));
+        let semicolon = semicolon
+            .token()?
+            .try_into()
+            .map_err(parol_runtime::ParolError::UserError)?;
         let mut equation_section_list =
             pop_item!(self, equation_section_list, EquationSectionList, context);
         let some_equation = pop_item!(self, some_equation, SomeEquation, context);
         let equation_section_list_0_built = EquationSectionList {
+            semicolon,
             some_equation: (&some_equation)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -11904,21 +11920,26 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 287:
     ///
-    /// `algorithm_sectionList /* Vec<T>::Push */: statement ';'^ /* Clipped */ algorithm_sectionList;`
+    /// `algorithm_sectionList /* Vec<T>::Push */: statement ';' algorithm_sectionList;`
     ///
     #[parol_runtime::function_name::named]
     fn algorithm_section_list_0(
         &mut self,
         _statement: &ParseTreeType<'t>,
-        _semicolon: &ParseTreeType<'t>,
+        semicolon: &ParseTreeType<'t>,
         _algorithm_section_list: &ParseTreeType<'t>,
     ) -> Result<()> {
         let context = function_name!();
         trace!("{}", self.trace_item_stack(context));
+        let semicolon = semicolon
+            .token()?
+            .try_into()
+            .map_err(parol_runtime::ParolError::UserError)?;
         let mut algorithm_section_list =
             pop_item!(self, algorithm_section_list, AlgorithmSectionList, context);
         let statement = pop_item!(self, statement, Statement, context);
         let algorithm_section_list_0_built = AlgorithmSectionList {
+            semicolon,
             statement: (&statement)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -12423,12 +12444,12 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 311:
     ///
-    /// `function_call_output_statement: '('^ /* Clipped */ output_expression_list ')'^ /* Clipped */ ':='^ /* Clipped */ component_reference function_call_args;`
+    /// `function_call_output_statement: '(' output_expression_list ')'^ /* Clipped */ ':='^ /* Clipped */ component_reference function_call_args;`
     ///
     #[parol_runtime::function_name::named]
     fn function_call_output_statement(
         &mut self,
-        _l_paren: &ParseTreeType<'t>,
+        l_paren: &ParseTreeType<'t>,
         _output_expression_list: &ParseTreeType<'t>,
         _r_paren: &ParseTreeType<'t>,
         _colon_equ: &ParseTreeType<'t>,
@@ -12437,11 +12458,16 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
     ) -> Result<()> {
         let context = function_name!();
         trace!("{}", self.trace_item_stack(context));
+        let l_paren = l_paren
+            .token()?
+            .try_into()
+            .map_err(parol_runtime::ParolError::UserError)?;
         let function_call_args = pop_item!(self, function_call_args, FunctionCallArgs, context);
         let component_reference = pop_item!(self, component_reference, ComponentReference, context);
         let output_expression_list =
             pop_item!(self, output_expression_list, OutputExpressionList, context);
         let function_call_output_statement_built = FunctionCallOutputStatement {
+            l_paren,
             output_expression_list: (&output_expression_list)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -12560,7 +12586,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 316:
     ///
-    /// `if_equation: if^ /* Clipped */ equation_block@if if_equationList /* Vec */ if_equationOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;`
+    /// `if_equation: if equation_block@if if_equationList /* Vec */ if_equationOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn if_equation(
@@ -12580,8 +12606,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
         let if_equation_list =
             pop_and_reverse_item!(self, if_equation_list, IfEquationList, context);
         let r#if0 = pop_item!(self, r#if0, EquationBlock, context);
-        self.pop(context);
+        let r#if = pop_item!(self, r#if, If, context);
         let if_equation_built = IfEquation {
+            r#if,
             r#if0: (&r#if0)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -12788,7 +12815,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 326:
     ///
-    /// `if_statement: if^ /* Clipped */ statement_block@if if_statementList /* Vec */ if_statementOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;`
+    /// `if_statement: if statement_block@if if_statementList /* Vec */ if_statementOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn if_statement(
@@ -12808,8 +12835,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
         let if_statement_list =
             pop_and_reverse_item!(self, if_statement_list, IfStatementList, context);
         let r#if0 = pop_item!(self, r#if0, StatementBlock, context);
-        self.pop(context);
+        let r#if = pop_item!(self, r#if, If, context);
         let if_statement_built = IfStatement {
+            r#if,
             r#if0: (&r#if0)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -12944,7 +12972,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 333:
     ///
-    /// `for_equation: for^ /* Clipped */ for_indices loop^ /* Clipped */ for_equationList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;`
+    /// `for_equation: for for_indices loop^ /* Clipped */ for_equationList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn for_equation(
@@ -12964,8 +12992,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
             pop_and_reverse_item!(self, for_equation_list, ForEquationList, context);
         self.pop(context);
         let for_indices = pop_item!(self, for_indices, ForIndices, context);
-        self.pop(context);
+        let r#for = pop_item!(self, r#for, For, context);
         let for_equation_built = ForEquation {
+            r#for,
             for_indices,
             for_equation_list,
         };
@@ -13016,7 +13045,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 336:
     ///
-    /// `for_statement: for^ /* Clipped */ for_indices loop^ /* Clipped */ for_statementList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;`
+    /// `for_statement: for for_indices loop^ /* Clipped */ for_statementList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn for_statement(
@@ -13036,8 +13065,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
             pop_and_reverse_item!(self, for_statement_list, ForStatementList, context);
         self.pop(context);
         let for_indices = pop_item!(self, for_indices, ForIndices, context);
-        self.pop(context);
+        let r#for = pop_item!(self, r#for, For, context);
         let for_statement_built = ForStatement {
+            r#for,
             for_indices,
             for_statement_list,
         };
@@ -13212,7 +13242,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 345:
     ///
-    /// `while_statement: while^ /* Clipped */ expression loop^ /* Clipped */ while_statementList /* Vec */ end^ /* Clipped */ while^ /* Clipped */;`
+    /// `while_statement: while expression loop^ /* Clipped */ while_statementList /* Vec */ end^ /* Clipped */ while^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn while_statement(
@@ -13232,8 +13262,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
             pop_and_reverse_item!(self, while_statement_list, WhileStatementList, context);
         self.pop(context);
         let expression = pop_item!(self, expression, Expression, context);
-        self.pop(context);
+        let r#while = pop_item!(self, r#while, While, context);
         let while_statement_built = WhileStatement {
+            r#while,
             expression: (&expression)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -13290,7 +13321,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 348:
     ///
-    /// `when_equation: when^ /* Clipped */ equation_block@when when_equationList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;`
+    /// `when_equation: when equation_block@when when_equationList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn when_equation(
@@ -13308,8 +13339,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
         let when_equation_list =
             pop_and_reverse_item!(self, when_equation_list, WhenEquationList, context);
         let when0 = pop_item!(self, when0, EquationBlock, context);
-        self.pop(context);
+        let when = pop_item!(self, when, When, context);
         let when_equation_built = WhenEquation {
+            when,
             when0: (&when0)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -13366,7 +13398,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 351:
     ///
-    /// `when_statement: when^ /* Clipped */ statement_block@when when_statementList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;`
+    /// `when_statement: when statement_block@when when_statementList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn when_statement(
@@ -13384,8 +13416,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
         let when_statement_list =
             pop_and_reverse_item!(self, when_statement_list, WhenStatementList, context);
         let when0 = pop_item!(self, when0, StatementBlock, context);
-        self.pop(context);
+        let when = pop_item!(self, when, When, context);
         let when_statement_built = WhenStatement {
+            when,
             when0: (&when0)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -13443,7 +13476,7 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
 
     /// Semantic action for production 354:
     ///
-    /// `connect_equation: connect^ /* Clipped */ '('^ /* Clipped */ component_reference ','^ /* Clipped */ component_reference ')'^ /* Clipped */;`
+    /// `connect_equation: connect '('^ /* Clipped */ component_reference ','^ /* Clipped */ component_reference ')'^ /* Clipped */;`
     ///
     #[parol_runtime::function_name::named]
     fn connect_equation(
@@ -13460,8 +13493,9 @@ impl<'t, 'u> ModelicaGrammarAuto<'t, 'u> {
         let component_reference0 =
             pop_item!(self, component_reference0, ComponentReference, context);
         let component_reference = pop_item!(self, component_reference, ComponentReference, context);
-        self.pop(context);
+        let connect = pop_item!(self, connect, Connect, context);
         let connect_equation_built = ConnectEquation {
+            connect,
             component_reference: (&component_reference)
                 .try_into()
                 .map_err(parol_runtime::ParolError::UserError)?,
@@ -84,7 +84,7 @@ pub const TERMINAL_NAMES: &[&str; 101] = &[
     /*  64 */
     "LBracketUnderscoreAMinusZAMinusZRBracketLBracketUnderscoreAMinusZAMinusZ0Minus9RBracketStar",
     /*  65 */
-    "TickLBracketUnderscoreAMinusZAMinusZ0Minus9BangHashDollarPercentAmpLParenRParenStarPlusCommaMinusDotSlashColonSemicolonLTGTEquQuestAtLBracketRBracketCircumflexLBraceRBraceOrTilde_QuoteRBracketStarTick",
+    "TickLParenLBracketUnderscoreAMinusZAMinusZ0Minus9BangHashDollarPercentAmpLParenRParenStarPlusCommaMinusDotSlashColonSemicolonLTGTEquQuestAtLBracketRBracketCircumflexLBraceRBraceOrTilde_QuoteRBracketOrLBracketTickQuoteQuestAbfnrtvRBracketRParenStarTick",
     /*  66 */ "String",
     /*  67 */ "UnsignedInteger",
     /*  68 */ "LBracket0Minus9RBracketPlusDotLBracket0Minus9RBracketPlus",
@@ -192,7 +192,7 @@ scanner! {
             token r"while" => 62; // "While"
             token r"within" => 63; // "Within"
             token r"[_a-zA-Z][_a-zA-Z0-9]*" => 64; // "LBracketUnderscoreAMinusZAMinusZRBracketLBracketUnderscoreAMinusZAMinusZ0Minus9RBracketStar"
-            token r#"\'[_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]*\'"# => 65; // "TickLBracketUnderscoreAMinusZAMinusZ0Minus9BangHashDollarPercentAmpLParenRParenStarPlusCommaMinusDotSlashColonSemicolonLTGTEquQuestAtLBracketRBracketCircumflexLBraceRBraceOrTilde_QuoteRBracketStarTick"
+            token r#"\'([_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]|\\[\'\"\?\\abfnrtv])*\'"# => 65; // "TickLParenLBracketUnderscoreAMinusZAMinusZ0Minus9BangHashDollarPercentAmpLParenRParenStarPlusCommaMinusDotSlashColonSemicolonLTGTEquQuestAtLBracketRBracketCircumflexLBraceRBraceOrTilde_QuoteRBracketOrLBracketTickQuoteQuestAbfnrtvRBracketRParenStarTick"
             token r#""([^"\\]|\\.)*""# => 66; // "String"
             token r"[0-9]+" => 67; // "UnsignedInteger"
             token r"[0-9]+\.[0-9]+" => 68; // "LBracket0Minus9RBracketPlusDotLBracket0Minus9RBracketPlus"
@@ -6414,7 +6414,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 180,
         production: &[ParseType::T(64)],
     },
-    // 60 - ident: /\'[_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]*\'/;
+    // 60 - ident: /\'([_a-zA-Z0-9!#\$%&\(\)\*\+,-\.\/:;<>=\?@\[\]\^\{\}\|~ \"]|\\[\'\"\?\\abfnrtv])*\'/;
     Production {
         lhs: 180,
         production: &[ParseType::T(65)],
@@ -7608,7 +7608,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 133,
         production: &[ParseType::N(134), ParseType::N(130), ParseType::N(135)],
     },
-    // 282 - equation_sectionList: some_equation : crate::ir::ast::Equation  ';'^ /* Clipped */ equation_sectionList;
+    // 282 - equation_sectionList: some_equation : crate::ir::ast::Equation  ';' equation_sectionList;
     Production {
         lhs: 134,
         production: &[ParseType::N(134), ParseType::T(72), ParseType::N(261)],
@@ -7633,7 +7633,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 2,
         production: &[ParseType::N(3), ParseType::N(1), ParseType::N(4)],
     },
-    // 287 - algorithm_sectionList: statement : crate::ir::ast::Statement  ';'^ /* Clipped */ algorithm_sectionList;
+    // 287 - algorithm_sectionList: statement : crate::ir::ast::Statement  ';' algorithm_sectionList;
     Production {
         lhs: 3,
         production: &[ParseType::N(3), ParseType::T(72), ParseType::N(264)],
@@ -7753,7 +7753,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 64,
         production: &[ParseType::N(173)],
     },
-    // 311 - function_call_output_statement: '('^ /* Clipped */ output_expression_list : crate::modelica_grammar::ExpressionList  ')'^ /* Clipped */ ':='^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  function_call_args : crate::modelica_grammar::ExpressionList ;
+    // 311 - function_call_output_statement: '(' output_expression_list : crate::modelica_grammar::ExpressionList  ')'^ /* Clipped */ ':='^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  function_call_args : crate::modelica_grammar::ExpressionList ;
     Production {
         lhs: 175,
         production: &[
@@ -7785,7 +7785,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 132,
         production: &[],
     },
-    // 316 - if_equation: if^ /* Clipped */ equation_block@if : crate::ir::ast::EquationBlock  if_equationList /* Vec */ if_equationOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;
+    // 316 - if_equation: if equation_block@if : crate::ir::ast::EquationBlock  if_equationList /* Vec */ if_equationOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;
     Production {
         lhs: 182,
         production: &[
@@ -7842,7 +7842,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 266,
         production: &[],
     },
-    // 326 - if_statement: if^ /* Clipped */ statement_block@if : crate::ir::ast::StatementBlock  if_statementList /* Vec */ if_statementOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;
+    // 326 - if_statement: if statement_block@if : crate::ir::ast::StatementBlock  if_statementList /* Vec */ if_statementOpt /* Option */ end^ /* Clipped */ if^ /* Clipped */;
     Production {
         lhs: 188,
         production: &[
@@ -7884,7 +7884,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 190,
         production: &[],
     },
-    // 333 - for_equation: for^ /* Clipped */ for_indices loop^ /* Clipped */ for_equationList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;
+    // 333 - for_equation: for for_indices loop^ /* Clipped */ for_equationList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;
     Production {
         lhs: 157,
         production: &[
@@ -7906,7 +7906,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 158,
         production: &[],
     },
-    // 336 - for_statement: for^ /* Clipped */ for_indices loop^ /* Clipped */ for_statementList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;
+    // 336 - for_statement: for for_indices loop^ /* Clipped */ for_statementList /* Vec */ end^ /* Clipped */ for^ /* Clipped */;
     Production {
         lhs: 163,
         production: &[
@@ -7958,7 +7958,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 160,
         production: &[],
     },
-    // 345 - while_statement: while^ /* Clipped */ expression : crate::ir::ast::Expression  loop^ /* Clipped */ while_statementList /* Vec */ end^ /* Clipped */ while^ /* Clipped */;
+    // 345 - while_statement: while expression : crate::ir::ast::Expression  loop^ /* Clipped */ while_statementList /* Vec */ end^ /* Clipped */ while^ /* Clipped */;
     Production {
         lhs: 302,
         production: &[
@@ -7980,7 +7980,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 303,
         production: &[],
     },
-    // 348 - when_equation: when^ /* Clipped */ equation_block@when : crate::ir::ast::EquationBlock  when_equationList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;
+    // 348 - when_equation: when equation_block@when : crate::ir::ast::EquationBlock  when_equationList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;
     Production {
         lhs: 297,
         production: &[
@@ -8001,7 +8001,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 298,
         production: &[],
     },
-    // 351 - when_statement: when^ /* Clipped */ statement_block@when : crate::ir::ast::StatementBlock  when_statementList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;
+    // 351 - when_statement: when statement_block@when : crate::ir::ast::StatementBlock  when_statementList /* Vec */ end^ /* Clipped */ when^ /* Clipped */;
     Production {
         lhs: 299,
         production: &[
@@ -8022,7 +8022,7 @@ pub const PRODUCTIONS: &[Production; 505] = &[
         lhs: 300,
         production: &[],
     },
-    // 354 - connect_equation: connect^ /* Clipped */ '('^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  ','^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  ')'^ /* Clipped */;
+    // 354 - connect_equation: connect '('^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  ','^ /* Clipped */ component_reference : crate::ir::ast::ComponentReference  ')'^ /* Clipped */;
     Production {
         lhs: 75,
         production: &[
@@ -5,6 +5,7 @@
 //! the `modelica_grammar_trait` types and the internal `ir::ast` types.
 
 mod components;
+pub mod cst;
 mod definitions;
 mod equations;
 mod expressions;
@@ -16,6 +17,7 @@ mod sections;
 use crate::ir;
 use generated::modelica_grammar_trait;
 use parol_runtime::{Result, Token};
+use std::collections::HashMap;
 use std::fmt::{Display, Error, Formatter};
 
 // Re-export types used by modelica_grammar_trait (generated code references these)
@@ -42,6 +44,9 @@ pub struct ModelicaGrammar<'t> {
     pub modelica: Option<ir::ast::StoredDefinition>,
     /// Comments collected during parsing, in order of appearance
     pub comments: Vec<ParsedComment>,
+    /// First tokens of the equations and statements whose IR location starts
+    /// later, like the `if` of if-equations, by the start of the IR location
+    item_starts: HashMap<u32, ir::ast::Location>,
     _phantom: std::marker::PhantomData<&'t str>,
 }
 
@@ -68,7 +73,27 @@ impl Display for ModelicaGrammar<'_> {
 
 impl<'t> modelica_grammar_trait::ModelicaGrammarTrait for ModelicaGrammar<'t> {
     fn stored_definition(&mut self, arg: &modelica_grammar_trait::StoredDefinition) -> Result<()> {
-        self.modelica = Some(arg.try_into()?);
+        let mut def: ir::ast::StoredDefinition = arg.try_into()?;
+        for class in def.class_list.values_mut() {
+            extend_item_starts(class, &self.item_starts);
+        }
+        self.modelica = Some(def);
+        Ok(())
+    }
+
+    /// Keep the first token of an equation, which its IR drops
+    fn some_equation(&mut self, arg: &modelica_grammar_trait::SomeEquation) -> Result<()> {
+        if let Some((token, Some(loc))) = equation_keyword(arg) {
+            self.item_starts.insert(loc.start, token.location.clone());
+        }
+        Ok(())
+    }
+
+    /// Keep the first token of a statement, which its IR drops
+    fn statement(&mut self, arg: &modelica_grammar_trait::Statement) -> Result<()> {
+        if let Some((token, Some(loc))) = statement_keyword(arg) {
+            self.item_starts.insert(loc.start, token.location.clone());
+        }
         Ok(())
     }
 
@@ -85,3 +110,83 @@ impl<'t> modelica_grammar_trait::ModelicaGrammarTrait for ModelicaGrammar<'t> {
         });
     }
 }
+
+/// The keyword an equation starts with, before the token of its IR location,
+/// and that location (the one of [`ir::ast::Equation::get_location`])
+fn equation_keyword(
+    arg: &modelica_grammar_trait::SomeEquation,
+) -> Option<(&ir::ast::Token, Option<&ir::ast::Location>)> {
+    use modelica_grammar_trait::SomeEquationOption;
+    match &arg.some_equation_option {
+        SomeEquationOption::SimpleEquation(_) => None,
+        SomeEquationOption::IfEquation(eq) => Some((
+            &eq.if_equation.r#if.r#if,
+            eq.if_equation.if0.cond.get_location(),
+        )),
+        SomeEquationOption::ForEquation(eq) => Some((
+            &eq.for_equation.r#for.r#for,
+            Some(&eq.for_equation.for_indices.for_index.ident.location),
+        )),
+        SomeEquationOption::ConnectEquation(eq) => Some((
+            &eq.connect_equation.connect.connect,
+            eq.connect_equation.component_reference.get_location(),
+        )),
+        SomeEquationOption::WhenEquation(eq) => Some((
+            &eq.when_equation.when.when,
+            eq.when_equation.when0.cond.get_location(),
+        )),
+    }
+}
+
+/// The token a statement starts with, if it comes before the token of its IR
+/// location, and that location (the one of [`ir::ast::Statement::get_location`])
+fn statement_keyword(
+    arg: &modelica_grammar_trait::Statement,
+) -> Option<(&ir::ast::Token, Option<&ir::ast::Location>)> {
+    use modelica_grammar_trait::StatementOption;
+    match &arg.statement_option {
+        StatementOption::FunctionCallOutputStatement(stmt) => Some((
+            &stmt.function_call_output_statement.l_paren,
+            stmt.function_call_output_statement
+                .component_reference
+                .get_location(),
+        )),
+        StatementOption::IfStatement(stmt) => Some((
+            &stmt.if_statement.r#if.r#if,
+            stmt.if_statement.r#if0.cond.get_location(),
+        )),
+        StatementOption::ForStatement(stmt) => Some((
+            &stmt.for_statement.r#for.r#for,
+            Some(&stmt.for_statement.for_indices.for_index.ident.location),
+        )),
+        StatementOption::WhileStatement(stmt) => Some((
+            &stmt.while_statement.r#while.r#while,
+            stmt.while_statement.expression.get_location(),
+        )),
+        StatementOption::WhenStatement(stmt) => Some((
+            &stmt.when_statement.when.when,
+            stmt.when_statement.when0.cond.get_location(),
+        )),
+        StatementOption::ComponentStatement(_)
+        | StatementOption::Break(_)
+        | StatementOption::Return(_) => None,
+    }
+}
+
+/// Start the source ranges of the equations and statements of a class and its
+/// nested classes at their first tokens
+fn extend_item_starts(
+    class: &mut ir::ast::ClassDefinition,
+    item_starts: &HashMap<u32, ir::ast::Location>,
+) {
+    for item in class.sections.iter_mut().flat_map(|s| &mut s.items) {
+        if let Some(start) = item_starts.get(&item.start) {
+            item.start_line = start.start_line;
+            item.start_column = start.start_column;
+            item.start = start.start;
+        }
+    }
+    for nested in class.classes.values_mut() {
+        extend_item_starts(nested, item_starts);
+    }
+}
@@ -1,8 +1,37 @@
 //! Conversion for equation and algorithm sections.
 
+use super::helpers::span_location;
 use crate::ir;
 use crate::modelica_grammar_trait;
 
+/// Source ranges of a section, from its first keyword to the last semicolon,
+/// and of its items, from their location (see [`ModelicaGrammar`]) to their
+/// semicolon
+///
+/// [`ModelicaGrammar`]: super::ModelicaGrammar
+fn section_ranges<'a>(
+    kind: ir::ast::SectionKind,
+    keyword: &ir::ast::Token,
+    items: impl Iterator<Item = (Option<&'a ir::ast::Location>, &'a ir::ast::Token)>,
+) -> ir::ast::Section {
+    let mut section = ir::ast::Section {
+        kind,
+        location: keyword.location.clone(),
+        items: vec![],
+    };
+    for (start, semicolon) in items {
+        let mut location = semicolon.location.clone();
+        if let Some(start) = start {
+            location.start_line = start.start_line;
+            location.start_column = start.start_column;
+            location.start = start.start;
+        }
+        section.items.push(location);
+        section.location = span_location(keyword, semicolon);
+    }
+    section
+}
+
 //-----------------------------------------------------------------------------
 #[derive(Debug, Default, Clone)]
 
@@ -13,6 +42,8 @@ pub struct EquationSection {
     pub equation_keyword: ir::ast::Token,
     /// Token for "initial" keyword (if present)
     pub initial_keyword: Option<ir::ast::Token>,
+    /// Source ranges of the section and its equations
+    pub section: ir::ast::Section,
 }
 
 impl TryFrom<&modelica_grammar_trait::EquationSection> for EquationSection {
@@ -25,11 +56,23 @@ impl TryFrom<&modelica_grammar_trait::EquationSection> for EquationSection {
             .equation_section_opt
             .as_ref()
             .map(|opt| opt.initial.initial.clone());
+        let section = section_ranges(
+            if initial_keyword.is_some() {
+                ir::ast::SectionKind::InitialEquation
+            } else {
+                ir::ast::SectionKind::Equation
+            },
+            initial_keyword.as_ref().unwrap_or(&ast.equation.equation),
+            ast.equation_section_list
+                .iter()
+                .map(|eq| (eq.some_equation.get_location(), &eq.semicolon)),
+        );
         let mut def = EquationSection {
             initial: ast.equation_section_opt.is_some(),
             equations: vec![],
             equation_keyword: ast.equation.equation.clone(),
             initial_keyword,
+            section,
         };
         for eq in &ast.equation_section_list {
             def.equations.push(eq.some_equation.clone());
@@ -48,6 +91,8 @@ pub struct AlgorithmSection {
     pub algorithm_keyword: ir::ast::Token,
     /// Token for "initial" keyword (if present)
     pub initial_keyword: Option<ir::ast::Token>,
+    /// Source ranges of the section and its statements
+    pub section: ir::ast::Section,
 }
 
 impl TryFrom<&modelica_grammar_trait::AlgorithmSection> for AlgorithmSection {
@@ -60,11 +105,23 @@ impl TryFrom<&modelica_grammar_trait::AlgorithmSection> for AlgorithmSection {
             .algorithm_section_opt
             .as_ref()
             .map(|opt| opt.initial.initial.clone());
+        let section = section_ranges(
+            if initial_keyword.is_some() {
+                ir::ast::SectionKind::InitialAlgorithm
+            } else {
+                ir::ast::SectionKind::Algorithm
+            },
+            initial_keyword.as_ref().unwrap_or(&ast.algorithm.algorithm),
+            ast.algorithm_section_list
+                .iter()
+                .map(|alg| (alg.statement.get_location(), &alg.semicolon)),
+        );
         let mut def = AlgorithmSection {
             initial: ast.algorithm_section_opt.is_some(),
             statements: vec![],
             algorithm_keyword: ast.algorithm.algorithm.clone(),
             initial_keyword,
+            section,
         };
         for alg in &ast.algorithm_section_list {
             def.statements.push(alg.statement.clone());
@@ -370,7 +370,7 @@ struct WasmCompileResult {
 /// Handle the result of a compilation, converting panics to errors.
 fn handle_compile_result(
     result: std::result::Result<
-        anyhow::Result<crate::CompilationResult>,
+        crate::Result<crate::CompilationResult>,
         Box<dyn std::any::Any + Send>,
     >,
 ) -> Result<String, JsError> {
@@ -658,6 +658,7 @@ pub fn lsp_semantic_token_legend() -> Result<String, JsError> {
 /// - dae.model_name, dae.rumoca_version
 /// - dae.x (states), dae.y (algebraics), dae.p (parameters), etc.
 /// - dae.fx (continuous equations), dae.fz (algebraic equations), etc.
+/// - dae.eq_ids (stable equation ids, parallel to dae.fx, dae.fz, ...)
 ///
 /// Example template:
 /// ```jinja
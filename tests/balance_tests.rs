@@ -4,7 +4,7 @@
 
 mod common;
 
-use common::compile_fixture;
+use common::{compile_fixture, compile_source};
 
 #[test]
 fn test_balanced_integrator() {
@@ -963,3 +963,663 @@ fn test_algorithm_sections() {
         }
     }
 }
+
+#[test]
+fn test_component_balance_report() {
+    let source = r#"
+model Inertia
+  Real phi(start = 0);
+  Real w;
+  parameter Real J = 1;
+equation
+  der(phi) = w;
+  J * der(w) = 1;
+end Inertia;
+
+model Motor
+  Inertia inertia;
+  Real i;
+  Real v;
+equation
+  v = 2 * i;
+end Motor;
+
+model Top
+  Motor motor;
+  Inertia load;
+equation
+  motor.inertia.w = load.w;
+end Top;
+"#;
+    let result = compile_source(source, "Top").unwrap();
+    let components = result.dae.component_balance();
+    let names: Vec<&str> = components.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["", "load", "motor", "motor.inertia"]);
+
+    // The connection-style equation between siblings lands on the top level
+    assert_eq!(components[0].num_equations, 1);
+    assert_eq!(components[0].total_equations, 6);
+    assert_eq!(components[0].total_unknowns, 6);
+
+    // Motor declares two unknowns but only one equation
+    let motor = &components[2];
+    assert_eq!((motor.num_equations, motor.num_unknowns), (1, 2));
+    assert_eq!(motor.difference(), -1);
+    assert_eq!(motor.total_difference(), -1);
+
+    let inertia = &components[3];
+    assert_eq!((inertia.num_equations, inertia.num_unknowns), (2, 2));
+
+    let report = rumoca::dae::balance::format_component_balance(&components);
+    assert!(report.contains("    motor.inertia"), "{}", report);
+}
+
+#[test]
+fn test_modification_replaces_declaration_binding() {
+    // `s(v = a)` replaces the binding `v = 1` instead of adding a second equation
+    let source = r#"
+model S
+  Real v = 1;
+end S;
+
+model T
+  Real a;
+  S s(v = a);
+equation
+  a = time;
+end T;
+"#;
+    let result = compile_source(source, "T").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+}
+
+#[test]
+fn test_if_equation_branch_counts() {
+    // A branch contributes its number of equations, not a single equation
+    let source = r#"
+model IfTime
+  Real x;
+  Real y;
+equation
+  if time > 0.5 then
+    x = 1;
+    y = 2;
+  else
+    x = 2;
+    y = 3;
+  end if;
+end IfTime;
+"#;
+    let result = compile_source(source, "IfTime").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert_eq!(result.balance.num_equations, 2);
+
+    // Branches holding only asserts contribute no equations
+    let source = r#"
+model IfNoElse
+  Real x;
+  Real y;
+equation
+  y = 1;
+  if time > 1 then
+    assert(y > 0, "y positive");
+  end if;
+  x = 2;
+end IfNoElse;
+"#;
+    let result = compile_source(source, "IfNoElse").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+
+    // Loop indices may appear in the condition of an if-equation
+    let source = r#"
+model IfInFor
+  Real x[3];
+equation
+  for i in 1:3 loop
+    if i > 1 then
+      x[i] = i;
+    else
+      x[i] = 0;
+    end if;
+  end for;
+end IfInFor;
+"#;
+    let result = compile_source(source, "IfInFor").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+}
+
+#[test]
+fn test_if_equation_unequal_branches() {
+    // Non-parameter conditions require the same equation count in every branch
+    let source = r#"
+model IfUnequal
+  Real x;
+  Real y;
+equation
+  if time > 1 then
+    x = 1;
+    y = 2;
+  else
+    x = 2;
+  end if;
+end IfUnequal;
+"#;
+    let result = compile_source(source, "IfUnequal").unwrap();
+    assert!(!result.is_balanced());
+    assert_eq!(result.balance.num_unequal_if_equations, 1);
+    assert!(
+        result
+            .balance_status()
+            .contains("different equation counts"),
+        "{}",
+        result.balance_status()
+    );
+
+    // Parameter conditions are allowed to select branches of different size
+    let source = r#"
+model IfParam
+  parameter Boolean use_y = true;
+  Real x;
+  Real y;
+equation
+  if use_y then
+    x = 1;
+    y = 2;
+  else
+    x = 2;
+  end if;
+end IfParam;
+"#;
+    let result = compile_source(source, "IfParam").unwrap();
+    assert_eq!(result.balance.num_unequal_if_equations, 0);
+}
+
+#[test]
+fn test_when_equation_discrete_targets() {
+    let source = r#"
+model WhenDisc
+  discrete Real d(start = 0);
+  Integer n(start = 0);
+  Real x(start = 1);
+equation
+  der(x) = -x;
+  when sample(0, 0.1) then
+    d = pre(d) + 1;
+    n = pre(n) + 1;
+  end when;
+end WhenDisc;
+"#;
+    let result = compile_source(source, "WhenDisc").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+}
+
+#[test]
+fn test_compile_balances_matches_single_compilation() {
+    let source = r#"
+package P
+  model Base
+    Real x;
+  equation
+    der(x) = -x;
+  end Base;
+  model A
+    extends Base;
+    Real y;
+  equation
+    y = 2 * x;
+  end A;
+  model B
+    Base b1;
+    Base b2;
+    Real z;
+  end B;
+  model C
+    Real w = undefinedVar;
+  end C;
+end P;
+"#;
+    let models = ["P.A", "P.B", "P.C", "P.Missing"];
+    let balances = rumoca::Compiler::new()
+        .compile_balances(source, "p.mo", &models)
+        .unwrap();
+    assert_eq!(balances.len(), models.len());
+
+    for (model, balance) in models.iter().zip(&balances) {
+        match rumoca::Compiler::new()
+            .model(model)
+            .compile_str(source, "p.mo")
+        {
+            Ok(result) => assert_eq!(balance.as_ref().unwrap(), &result.balance, "{}", model),
+            Err(e) => assert_eq!(
+                balance.as_ref().unwrap_err().to_string(),
+                e.to_string(),
+                "{}",
+                model
+            ),
+        }
+    }
+    assert!(balances[0].as_ref().unwrap().is_balanced);
+    assert!(!balances[1].as_ref().unwrap().is_balanced);
+    assert!(balances[2].is_err());
+}
+
+#[test]
+fn test_equations_solved_for_parameter_or_input() {
+    let source = r#"
+model M
+  parameter Real p = 1;
+  input Real u;
+  Real x(start = 0);
+  Real y;
+  Real z;
+equation
+  der(x) = -x + y;
+  y = 2 * x;
+  y = p;
+  z = u;
+  z = 3;
+end M;
+"#;
+    let err = rumoca::Compiler::new()
+        .model("M")
+        .compile_str(source, "m.mo")
+        .unwrap_err();
+    assert!(matches!(err, rumoca::Error::Balance(_)), "{err:?}");
+    let message = err.to_string();
+    assert!(message.contains("parameter 'p'"), "{message}");
+    assert!(message.contains("input 'u'"), "{message}");
+    // The unmatched equation is listed first, with the equations it competes with
+    assert!(
+        message.contains("m.mo:11:3: y = p\n  m.mo:10:3: y = 2 * x"),
+        "{message}"
+    );
+
+    // Through an algebraic variable
+    let chained = source
+        .replace("  y = 2 * x;\n", "  y = 2 * w;\n  w = 1;\n")
+        .replace("  Real y;\n", "  Real w;\n  Real y;\n");
+    let err = rumoca::Compiler::new()
+        .model("M")
+        .compile_str(&chained, "m.mo")
+        .unwrap_err();
+    assert!(err.to_string().contains("parameter 'p'"), "{err}");
+
+    // A constraint on states needs index reduction, it isn't solved for 'L'
+    let pendulum = r#"
+model Pendulum
+  parameter Real L = 1;
+  Real x(start = 1);
+  Real y;
+  Real vx;
+  Real vy;
+  Real lambda;
+equation
+  der(x) = vx;
+  der(y) = vy;
+  der(vx) = -lambda * x;
+  der(vy) = -lambda * y - 9.81;
+  x^2 + y^2 = L^2;
+end Pendulum;
+"#;
+    let result = rumoca::Compiler::new()
+        .model("Pendulum")
+        .compile_str(pendulum, "pendulum.mo")
+        .unwrap();
+    assert!(result.dae.singular.is_empty());
+
+    let result = rumoca::Compiler::new()
+        .model("M")
+        .permissive(true)
+        .compile_str(source, "m.mo")
+        .unwrap();
+    assert_eq!(result.dae.singular.len(), 2);
+    assert_eq!(result.dae.singular[0].parameters, ["p"]);
+    assert_eq!(result.dae.singular[1].inputs, ["u"]);
+}
+
+#[test]
+fn test_structural_dae_index() {
+    let result = compile_fixture("integrator", "Integrator").unwrap();
+    let index = result.balance.dae_index.as_ref().unwrap();
+    assert_eq!(index.description(), "ODE");
+    assert!(index.constraints.is_empty());
+
+    let result = compile_source(
+        "model M\n  Real x(start = 1);\n  Real y;\nequation\n  der(x) = -y;\n  y = 2 * x;\nend M;",
+        "M",
+    )
+    .unwrap();
+    assert_eq!(result.balance.dae_index.as_ref().unwrap().index, 1);
+
+    // x1 = time has to be differentiated once, so x2 = der(x1) is index 2
+    let result = compile_source(
+        "model M\n  Real x1;\n  Real x2;\nequation\n  der(x1) = x2;\n  x1 = time;\nend M;",
+        "M",
+    )
+    .unwrap();
+    let index = result.balance.dae_index.as_ref().unwrap();
+    assert_eq!(index.description(), "index-2 DAE");
+    assert_eq!(index.constraints.len(), 1);
+    assert_eq!(index.constraints[0].equation, "x1 = time");
+    assert!(
+        result
+            .to_dae_ir_json()
+            .unwrap()
+            .contains("\"dae_index\": 2")
+    );
+}
+
+#[test]
+fn test_balanced_component_arrays() {
+    let source = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+
+model Resistor
+  parameter Real R = 1;
+  Pin p, n;
+equation
+  p.v - n.v = R * p.i;
+  p.i + n.i = 0;
+end Resistor;
+
+model Capacitor
+  parameter Real C = 1;
+  Pin p, n;
+  Real v(start = 1);
+equation
+  v = p.v - n.v;
+  C * der(v) = p.i;
+  p.i + n.i = 0;
+end Capacitor;
+
+model Ground
+  Pin p;
+equation
+  p.v = 0;
+end Ground;
+
+model Filter
+  parameter Integer n = 3;
+  parameter Real Rs[n] = {1, 2, 3};
+  Resistor r[n](R = Rs);
+  Capacitor c[n](each C = 0.1);
+  Ground g;
+equation
+  for i in 1:n loop
+    connect(r[i].n, c[i].p);
+    connect(c[i].n, g.p);
+  end for;
+  for i in 1:n - 1 loop
+    connect(r[i].n, r[i + 1].p);
+  end for;
+  connect(r[1].p, g.p);
+end Filter;
+
+model Filters
+  Filter f[2](each n = 2, each Rs = {1, 2});
+end Filters;
+"#;
+    let result = compile_source(source, "Filter").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert_eq!(result.balance.num_states, 3);
+
+    let result = compile_source(source, "Filters").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert_eq!(result.balance.num_states, 4);
+}
+
+#[test]
+fn test_nested_block_causality() {
+    let source = r#"
+connector RealInput = input Real;
+connector Signal
+  Real v;
+end Signal;
+
+block Gain
+  RealInput u;
+  output Real y;
+  parameter Real k = 2;
+equation
+  y = k * u;
+end Gain;
+
+block Integrator
+  input Real u;
+  output Real y;
+equation
+  der(y) = u;
+end Integrator;
+
+block Loop
+  input Signal r;
+  output Real out;
+  Gain g;
+  Integrator i;
+equation
+  g.u = r.v - i.y;
+  i.u = g.y;
+  out = i.y;
+end Loop;
+
+model Closed
+  Loop l;
+equation
+  l.r.v = 1;
+end Closed;
+
+model Open
+  Loop l;
+  Integrator open;
+equation
+  l.r.v = 1;
+end Open;
+"#;
+    // Inputs of the block and its connectors are inputs of the model, those of
+    // its blocks are unknowns given by its equations
+    let result = compile_source(source, "Loop").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    let inputs: Vec<&String> = result.dae.u.keys().collect();
+    assert_eq!(inputs, ["r.v"]);
+    assert!(result.dae.y.contains_key("g.u"));
+    assert!(result.dae.y.contains_key("i.u"));
+    for component in result.dae.component_balance() {
+        assert_eq!(component.difference(), 0, "{:?}", component);
+    }
+
+    let result = compile_source(source, "Closed").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert!(result.dae.u.is_empty());
+    assert_eq!(result.balance.num_unknowns, 6);
+
+    // The unconnected input of `open` (unlike a `RealInput`, not an error) is an
+    // unknown without an equation, missing at the top level that should give it
+    let result = compile_source(source, "Open").unwrap();
+    assert!(!result.is_balanced());
+    assert!(result.balance_status().contains("under-determined by 1"));
+    let components = result.dae.component_balance();
+    assert_eq!(components[0].name, "");
+    assert_eq!(components[0].difference(), -1);
+    assert!(components[1..].iter().all(|c| c.difference() == 0));
+}
+
+#[test]
+fn test_array_element_derivatives() {
+    let source = r#"
+model Pendulum
+  parameter Real m = 1;
+  parameter Real L = 1;
+  parameter Real g = 9.81;
+  Real x[2](start = {1, 0});
+  Real v[2];
+  Real lambda;
+equation
+  for i in 1:2 loop
+    der(x[i]) = v[i];
+  end for;
+  m * der(v[1]) = -lambda * x[1];
+  m * der(v[2]) = -lambda * x[2] - m * g;
+  x[1]^2 + x[2]^2 = L^2;
+end Pendulum;
+
+model Pendulums
+  parameter Integer n = 2;
+  parameter Real L[n] = {1, 2};
+  Real theta[n](start = {0.1, 0.2});
+  Real w[n];
+  Real p[2, 2];
+equation
+  for i in 1:n loop
+    der(theta[i]) = w[i];
+    der(w[i]) = -9.81 / L[i] * sin(theta[i]);
+    p[i, 1] = L[i] * sin(theta[i]);
+    p[i, 2] = -L[i] * cos(theta[i]);
+  end for;
+end Pendulums;
+
+model Mixed
+  Real p[2](each start = 1);
+  Real q;
+equation
+  der(p[1]) = -p[1];
+  p[2] = 2 * p[1];
+  q = 2 * der(p[1]);
+end Mixed;
+"#;
+    // der(x[i]) in a for-equation refers to the states x[1] and x[2], so the
+    // position constraint of the pendulum makes it an index-3 DAE
+    let result = compile_source(source, "Pendulum").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    let states: Vec<&String> = result.dae.x.keys().collect();
+    assert_eq!(states, ["x[1]", "x[2]", "v[1]", "v[2]"]);
+    let index = result.balance.dae_index.as_ref().unwrap();
+    assert_eq!(index.index, 3);
+    assert_eq!(index.constraints[0].equation, "x[1] ^ 2 + x[2] ^ 2 = L ^ 2");
+    assert_eq!(index.constraints[0].differentiations, 2);
+
+    let result = compile_source(source, "Pendulums").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert_eq!(result.balance.num_states, 4);
+    assert!(result.dae.y.contains_key("p[2,1]"));
+    assert_eq!(result.balance.dae_index.as_ref().unwrap().index, 1);
+
+    // Only the differentiated element is a state, p[2] is algebraic
+    let result = compile_source(source, "Mixed").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    let states: Vec<&String> = result.dae.x.keys().collect();
+    assert_eq!(states, ["p[1]"]);
+    assert!(result.dae.y.contains_key("p[2]"));
+    let index = result.balance.dae_index.as_ref().unwrap();
+    assert_eq!(index.index, 1);
+    assert!(index.constraints.is_empty());
+}
+
+#[test]
+fn test_matrix_derivatives() {
+    let source = r#"
+model Matrix
+  Real x[2, 2];
+  Real y;
+equation
+  der(x) = {{1, 2}, {3, 4}};
+  y = x[2, 1];
+end Matrix;
+"#;
+    // Every element of the matrix is a state, by its two-dimensional name
+    let result = compile_source(source, "Matrix").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+    assert_eq!(result.balance.num_states, 4);
+    let states: Vec<&String> = result.dae.x.keys().collect();
+    assert_eq!(states, ["x[1,1]", "x[1,2]", "x[2,1]", "x[2,2]"]);
+    let algebraic: Vec<&String> = result.dae.y.keys().collect();
+    assert_eq!(algebraic, ["y"]);
+}
+
+#[test]
+fn test_resource_limits() {
+    let source = r#"
+model Loop
+  Real x;
+equation
+  for i in 1:100000000 loop
+    x = i;
+  end for;
+end Loop;
+
+model Big
+  Real x[2000000];
+equation
+  der(x) = -x;
+end Big;
+
+model Small
+  Real x;
+equation
+  der(x) = -x;
+end Small;
+
+model Nested
+  Small inner1;
+end Nested;
+
+model Outer
+  Nested nested;
+end Outer;
+"#;
+    let compile = |model: &str| {
+        rumoca::Compiler::new()
+            .model(model)
+            .compile_str(source, "m.mo")
+    };
+    let err = compile("Loop").unwrap_err();
+    assert!(matches!(err, rumoca::Error::Limit(_)), "{err:?}");
+    assert_eq!(
+        err.to_string(),
+        "m.mo:5:7: for-equation index 'i' expands to 100000000 elements, more than the limit \
+         of 1000000; raise the limit with --max-expansion-size"
+    );
+    let err = compile("Big").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("m.mo:11:8: array 'x' expands to 2000000"),
+        "{err}"
+    );
+
+    let err = rumoca::Compiler::new()
+        .model("Small")
+        .timeout(std::time::Duration::ZERO)
+        .compile_str(source, "m.mo")
+        .unwrap_err();
+    assert!(matches!(err, rumoca::Error::Limit(_)), "{err:?}");
+    assert_eq!(
+        err.to_string(),
+        "compilation aborted after the timeout of 0 s"
+    );
+
+    // The limits are options of each compiler
+    let err = rumoca::Compiler::new()
+        .model("Big")
+        .max_expansion_size(10)
+        .compile_str(source, "m.mo")
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("array 'x' expands to 2000000 elements, more than the limit of 10"),
+        "{err}"
+    );
+    assert!(compile("Outer").is_ok());
+    let err = rumoca::Compiler::new()
+        .model("Outer")
+        .max_instance_depth(1)
+        .compile_str(source, "m.mo")
+        .unwrap_err();
+    assert!(matches!(err, rumoca::Error::Limit(_)), "{err:?}");
+    assert!(
+        err.to_string()
+            .contains("is nested more than 1 levels deep"),
+        "{err}"
+    );
+}
@@ -6,6 +6,7 @@ use std::fs;
 use std::io::Write;
 use tempfile::NamedTempFile;
 
+use rumoca::compiler::limits::Limits;
 use rumoca::compiler::pipeline::{check_balance_only, clear_dae_cache};
 use rumoca::compiler::{Compiler, parse_source};
 use rumoca::ir::transform::flatten::clear_caches;
@@ -37,14 +38,15 @@ end TestModel;
     let def1 = parse_source(initial_model, path).expect("Failed to parse initial model");
 
     // First call - should compute and cache
-    let result1 = check_balance_only(&def1, Some("TestModel")).expect("First balance check failed");
+    let result1 = check_balance_only(&def1, Some("TestModel"), &Limits::default())
+        .expect("First balance check failed");
     assert!(result1.is_balanced, "Initial model should be balanced");
     assert_eq!(result1.num_states, 1, "Should have 1 state");
     assert_eq!(result1.num_equations, 1, "Should have 1 equation");
 
     // Second call with same content - should use cache
-    let result2 =
-        check_balance_only(&def1, Some("TestModel")).expect("Second balance check failed");
+    let result2 = check_balance_only(&def1, Some("TestModel"), &Limits::default())
+        .expect("Second balance check failed");
     assert_eq!(
         result1.num_states, result2.num_states,
         "Cached result should match"
@@ -71,7 +73,8 @@ end TestModel;
     let def2 = parse_source(modified_model, path).expect("Failed to parse modified model");
 
     // This should NOT use the old cache because the file changed
-    let result3 = check_balance_only(&def2, Some("TestModel")).expect("Third balance check failed");
+    let result3 = check_balance_only(&def2, Some("TestModel"), &Limits::default())
+        .expect("Third balance check failed");
 
     assert!(result3.is_balanced, "Modified model should be balanced");
     assert_eq!(
@@ -0,0 +1,556 @@
+//! Tests for the CasADi MX template, examples/templates/casadi.jinja
+//!
+//! The generated Python is only run with the `casadi-tests` feature, which
+//! needs python3 with casadi and numpy (set `PYTHON` to use another
+//! interpreter):
+//!
+//! ```sh
+//! cargo test --features casadi-tests --test casadi_tests
+//! ```
+
+mod common;
+
+use common::compile_source;
+
+const TEMPLATE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/templates/casadi.jinja"
+);
+
+/// A bouncing ball with a drag that switches on the direction of motion
+const BOUNCING_BALL: &str = r#"
+model Bouncing
+  parameter Real e = 0.8;
+  Real h(start = 1);
+  Real v(start = 0);
+  Real drag;
+equation
+  der(h) = v;
+  der(v) = -9.81 - drag;
+  drag = if v > 0 then 0.1 * v else noEvent(if h > 0.5 then 0 else 0.05 * v);
+  when h < 0 then
+    reinit(v, -e * pre(v));
+  end when;
+end Bouncing;
+"#;
+
+fn render(source: &str, model: &str) -> String {
+    compile_source(source, model)
+        .unwrap()
+        .render_template_to_string(TEMPLATE)
+        .unwrap()
+}
+
+#[test]
+fn test_casadi_conditions_and_events() {
+    let code = render(BOUNCING_BALL, "Bouncing");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+
+    // Relations become switch conditions, if-expressions switch on them
+    assert!(code.contains("c0 = _sw.gt(v, 0)"), "{}", code);
+    assert!(code.contains("c1 = _sw.lt(h, 0)"), "{}", code);
+    assert!(
+        code.contains(
+            "drag - (_sw.if_else(c0, (0.1 * v), _sw.if_else(_sw.gt(h, 0.5), 0, (0.05 * v))))"
+        ),
+        "{}",
+        code
+    );
+
+    // Equations are residuals in der(x)
+    assert!(code.contains("der_h - (v)"), "{}", code);
+
+    // The reinit of the when-clause resets v from its value before the event
+    assert!(
+        code.contains(r#"_x[self.x_names.index("v")] = -((e * v))"#),
+        "{}",
+        code
+    );
+    assert!(code.contains(r#"self._resets["c1"]"#), "{}", code);
+    assert!(
+        code.contains(r#"self._reset_states["c1"] = "v""#),
+        "{}",
+        code
+    );
+
+    // Conditions are located at their source, for the coverage report
+    assert!(
+        code.contains(r#""c0": ("if-expression", "\u003ctest\u003e:10:13"),"#),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains(r#""c1": ("when-clause", "\u003ctest\u003e:11:8"),"#),
+        "{}",
+        code
+    );
+}
+
+#[test]
+fn test_casadi_array_names() {
+    let source = r#"
+model A
+  Real x[2](each start = 1);
+  Real m[2, 2];
+equation
+  der(x[1]) = -x[2];
+  der(x[2]) = x[1];
+  for i in 1:2 loop
+    for j in 1:2 loop
+      m[i, j] = x[i] * j;
+    end for;
+  end for;
+end A;
+"#;
+    let code = render(source, "A");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+    assert!(code.contains(r#"m_1_2 = ca.MX.sym("m[1,2]")"#), "{}", code);
+    assert!(code.contains("m_1_2 - ((x_1 * 2))"), "{}", code);
+    assert!(code.contains("der_x_1 - (-(x_2))"), "{}", code);
+}
+
+#[test]
+fn test_casadi_outputs() {
+    let source = r#"
+model O
+  Real x(start = 1);
+  Real y;
+  Real unused;
+equation
+  der(x) = -x;
+  y = 2 * x;
+  unused = x + 1;
+end O;
+"#;
+    let code = rumoca::Compiler::new()
+        .model("O")
+        .outputs(&["y"])
+        .compile_str(source, "o.mo")
+        .unwrap()
+        .render_template_to_string(TEMPLATE)
+        .unwrap();
+    assert!(code.contains(r#"self.output_names = ["y"]"#), "{}", code);
+    // Variables no output depends on aren't computed
+    assert!(!code.contains(r#"ca.MX.sym("unused")"#), "{}", code);
+
+    let code = render(source, "O");
+    assert!(
+        code.contains("self.output_names = self.x_names + self.y_names"),
+        "{}",
+        code
+    );
+    assert!(code.contains(r#"ca.MX.sym("unused")"#), "{}", code);
+}
+
+/// A first-order low-pass filter of its input
+const LOW_PASS: &str = r#"
+model LowPass
+  parameter Real tau = 0.1;
+  input Real u;
+  Real x(start = 0);
+  output Real y;
+equation
+  der(x) = (u - x) / tau;
+  y = x;
+end LowPass;
+"#;
+
+#[test]
+fn test_casadi_input_sources() {
+    let code = render(LOW_PASS, "LowPass");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+    assert!(
+        code.contains(r#"def set_input(self, name, source, interpolation="zoh"):"#),
+        "{}",
+        code
+    );
+
+    // Each interpolation mode builds its own function of time
+    assert!(
+        code.contains(
+            r#"if interpolation == "linear":
+            self.inputs[name] = lambda t: np.interp(t, times, values)"#
+        ),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains(
+            r#"elif interpolation == "zoh":
+            def zoh(t):
+                i = np.searchsorted(times, t, side="right") - 1"#
+        ),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains(r#"raise ValueError("unknown interpolation '{}'".format(interpolation))"#),
+        "{}",
+        code
+    );
+
+    // The input function overrides the constant inputs by their sources,
+    // and simulate() samples it at each output time
+    assert!(
+        code.contains("def input_function(self, u=None):"),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains(r#"constant = self._values("u", u)"#),
+        "{}",
+        code
+    );
+    assert!(code.contains("f_u = self.input_function(u)"), "{}", code);
+    assert!(code.contains("known[:nu] = f_u(t[k + 1])"), "{}", code);
+}
+
+#[test]
+fn test_casadi_boolean_relations() {
+    let source = r#"
+model B
+  Real x(start = 1);
+  Boolean b;
+  Real y;
+equation
+  der(x) = -1;
+  b = x > 0;
+  y = if b then 1 else 2;
+end B;
+"#;
+    let code = render(source, "B");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+    // b is updated from its condition at events, not solved continuously
+    assert!(code.contains(r#""b": False,"#), "{}", code);
+    assert!(
+        code.contains(r#"_m[self.m_names.index("b")] = ca.if_else(c0 > 0.5, 1, 0)"#),
+        "{}",
+        code
+    );
+    assert!(!code.contains("b - (c0)"), "{}", code);
+}
+
+#[test]
+fn test_casadi_when_priority() {
+    let source = r#"
+model W
+  Real h(start = 1);
+  Real v(start = 0);
+equation
+  der(h) = v;
+  der(v) = -9.81;
+  when h < 0 then
+    reinit(v, -0.8 * pre(v));
+  elsewhen h < 0.5 then
+    reinit(v, 0);
+  end when;
+end W;
+"#;
+    let mut result = compile_source(source, "W").unwrap();
+    assert_eq!(result.dae.when_clauses, [["c0", "c1"]]);
+    let code = result.render_template_to_string(TEMPLATE).unwrap();
+    // The elsewhen-branch c1 doesn't fire with the when-branch c0
+    assert!(
+        code.contains("self._when_branches = [\n            [\"c0\",\"c1\"],\n        ]"),
+        "{}",
+        code
+    );
+}
+
+#[test]
+fn test_casadi_when_same_condition() {
+    let source = r#"
+model W
+  Real h(start = 1);
+  Real v(start = 0);
+equation
+  der(h) = v;
+  der(v) = -9.81;
+  when h < 0 then
+    reinit(v, -0.8 * pre(v));
+  end when;
+  when h < 0 then
+    reinit(h, 0);
+  end when;
+end W;
+"#;
+    let mut result = compile_source(source, "W").unwrap();
+    // Each when-equation keeps its own branches, even on the same relation
+    let mut clauses = result.dae.when_clauses.clone();
+    clauses.sort();
+    assert_eq!(clauses, [["c0"], ["c1"]]);
+    let code = result.render_template_to_string(TEMPLATE).unwrap();
+    for clause in &result.dae.when_clauses {
+        assert!(
+            code.contains(&format!("\n            [\"{}\"],\n", clause[0])),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_casadi_when_array_condition() {
+    let source = r#"
+model W
+  Real h(start = 1);
+  Real v(start = 0);
+  discrete Real n(start = 0);
+equation
+  der(h) = v;
+  der(v) = -9.81;
+  when {h < 0, v > 1} then
+    n = pre(n) + 1;
+  end when;
+end W;
+"#;
+    let result = compile_source(source, "W").unwrap();
+    // Each relation of the array is a branch of its own
+    assert_eq!(result.dae.when_clauses, [["c0", "c1"]]);
+    let conditions: Vec<String> = result.dae.fc.values().map(|c| c.to_string()).collect();
+    assert_eq!(conditions, ["h < 0", "v > 1"]);
+    assert_eq!(result.dae.fr.len(), 2);
+}
+
+#[cfg(feature = "casadi-tests")]
+#[test]
+fn test_casadi_simulation() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("bouncing.py"),
+        render(BOUNCING_BALL, "Bouncing"),
+    )
+    .unwrap();
+
+    let script = r#"
+import numpy as np
+from bouncing import Model
+
+for switch in ("exact", "smooth"):
+    model = Model(switch=switch, eps=1e-4)
+    res = model.simulate(t=np.linspace(0, 2, 2001))
+    h = res["x"][model.x_names.index("h")]
+    assert [c for _, c in res["events"]][:2] == ["c1", "c1"], res["events"]
+    assert 0.4 < res["events"][0][0] < 0.5, res["events"]
+    assert h.min() > -0.05, h.min()
+    assert h.max() <= 1.0 + 1e-6, h.max()
+
+# The drag switches with the direction of motion, and the ball bounces
+coverage = res["coverage"]
+assert coverage["c0"]["true"] > 0 and coverage["c0"]["false"] > 0, coverage
+assert coverage["c1"]["fired"] >= 2, coverage
+report = model.coverage_report(res)
+assert "when-clause c1: fired" in report and "NOT COVERED" not in report, report
+
+# The event log records the bounces with their reinit of v
+import json
+model.simulate(t=np.linspace(0, 2, 2001), event_log="events.json")
+with open("events.json") as f:
+    log = json.load(f)
+assert log["count"] == len(log["events"]), log
+bounce = next(e for e in log["events"] if e["when_clauses"])
+assert bounce["when_clauses"][0]["condition"] == "c1", bounce
+assert bounce["when_clauses"][0]["location"].endswith(":11:8"), bounce
+[reinit] = bounce["reinits"]
+assert reinit["state"] == "v" and reinit["before"] < 0 < reinit["after"], reinit
+assert any(c["kind"] == "if-expression" for e in log["events"] for c in e["crossings"]), log
+
+# The ball doesn't chatter, and a minimum event interval keeps one bounce
+assert res["chattering"] == {}, res["chattering"]
+res = model.simulate(t=np.linspace(0, 2, 2001), min_event_interval=10.0)
+assert len(res["events"]) == 1, res["events"]
+
+A, B, C, D = Model().linearize()
+assert A.shape == (2, 2) and C.shape == (1, 2), (A, C)
+assert abs(A[0, 1] - 1) < 1e-9, A
+"#;
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = std::process::Command::new(python)
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to run python");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[cfg(feature = "casadi-tests")]
+#[test]
+fn test_casadi_input_simulation() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lowpass.py"), render(LOW_PASS, "LowPass")).unwrap();
+    std::fs::write(dir.path().join("u.csv"), "time,u\n0,0\n0.5,1\n1,1\n").unwrap();
+
+    let script = r#"
+import numpy as np
+from lowpass import Model
+
+t = np.linspace(0, 1, 101)
+
+# A step held from t = 0.5
+model = Model()
+model.set_input("u", "u.csv", interpolation="zoh")
+res = model.simulate(t=t)
+u = res["u"][0]
+assert u[49] == 0 and u[50] == 1, u
+assert res["outputs"]["y"][50] < 1e-9 < res["outputs"]["y"][-1] < 1, res["outputs"]["y"]
+
+# A ramp between the samples
+model = Model()
+model.set_input("u", ([0, 1], [0, 2]), interpolation="linear")
+assert abs(model.input_function()(0.25)[0] - 0.5) < 1e-12
+res = model.simulate(t=t)
+assert abs(res["u"][0][50] - 1) < 1e-12, res["u"]
+
+# Functions of time, and constant inputs without a source
+model = Model()
+model.set_input("u", np.sin)
+assert abs(model.input_function()(1.0)[0] - np.sin(1.0)) < 1e-12
+assert Model().input_function({"u": 2})(0.3)[0] == 2
+
+for bad in (lambda: model.set_input("x", np.sin),
+            lambda: model.set_input("u", ([0], [0]), interpolation="cubic")):
+    try:
+        bad()
+        raise AssertionError("expected an error")
+    except (KeyError, ValueError):
+        pass
+"#;
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = std::process::Command::new(python)
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to run python");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[cfg(feature = "casadi-tests")]
+#[test]
+fn test_casadi_chattering() {
+    // A when-condition crossing at each output time of a 0.01 s grid
+    let source = r#"
+model Chatter
+  Real x(start = 0);
+equation
+  der(x) = 1;
+  when sin(100 * 3.14159265 * time + 0.5) > 0 then
+    reinit(x, 0);
+  end when;
+end Chatter;
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("chatter.py"), render(source, "Chatter")).unwrap();
+
+    let script = r#"
+import warnings
+import numpy as np
+from chatter import Model
+
+t = np.linspace(0, 1, 101)
+with warnings.catch_warnings(record=True) as caught:
+    warnings.simplefilter("always")
+    res = Model().simulate(t=t)
+chatter = res["chattering"]["c0"]
+assert chatter["kind"] == "when-clause", chatter
+assert chatter["location"].endswith(":6:8"), chatter
+assert chatter["start"] < 0.05 and chatter["crossings"] == 100, chatter
+assert len(res["events"]) == 50, res["events"]
+[warning] = caught
+assert ":6:8: when-clause c0 is chattering: 5 crossings" in str(warning.message), warning
+
+# A minimum interval between events keeps one reset per 0.1 s
+with warnings.catch_warnings():
+    warnings.simplefilter("ignore")
+    res = Model().simulate(t=t, min_event_interval=0.095)
+assert len(res["events"]) == 10, res["events"]
+"#;
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = std::process::Command::new(python)
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to run python");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[cfg(feature = "casadi-tests")]
+#[test]
+fn test_casadi_frequency_response() {
+    let source = r#"
+model LowPass
+  parameter Real tau = 0.5;
+  input Real u;
+  Real x;
+  output Real y;
+equation
+  der(x) = (u - x) / tau;
+  y = 2 * x;
+end LowPass;
+"#;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("low_pass.py"), render(source, "LowPass")).unwrap();
+
+    let script = r#"
+import csv, json
+import numpy as np
+from low_pass import Model
+
+# G(jw) = 2 / (1 + jw tau): -3 dB and -45 degrees below the DC gain at 1/tau
+model = Model()
+res = model.frequency_response(w=[0.01, 2.0, 200.0])
+(r,) = res["responses"]
+assert (r["input"], r["output"]) == ("u", "y"), r
+assert abs(r["magnitude_db"][0] - 20 * np.log10(2)) < 1e-3, r
+assert abs(r["magnitude_db"][1] - (20 * np.log10(2) - 10 * np.log10(2))) < 1e-9, r
+assert abs(r["phase_deg"][1] + 45) < 1e-9, r
+assert -90 < r["phase_deg"][2] < -89, r
+
+res = model.frequency_response(outputs=["x", "y"])
+assert [r["output"] for r in res["responses"]] == ["x", "y"]
+model.write_frequency_response(res, "bode.csv")
+rows = list(csv.reader(open("bode.csv")))
+assert rows[0] == ["w", "x/u magnitude_db", "x/u phase_deg", "y/u magnitude_db", "y/u phase_deg"], rows[0]
+assert len(rows) == 201, len(rows)
+model.write_frequency_response(res, "bode.json")
+data = json.load(open("bode.json"))
+assert len(data["w"]) == 200 and data["responses"][1]["output"] == "y", data.keys()
+
+# Parameter files, as for rumoca --params
+open("params.toml", "w").write("tau = 0.25\n[experiments.slow]\ntau = 1.0\n")
+assert model.read_params("params.toml") == {"tau": 0.25}
+p = model.read_params("params.toml", experiment="slow")
+res = model.frequency_response(w=[1.0], p=p)
+assert abs(res["responses"][0]["phase_deg"][0] + 45) < 1e-9, res
+"#;
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = std::process::Command::new(python)
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to run python");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
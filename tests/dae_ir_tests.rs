@@ -1,6 +1,7 @@
 mod common;
 
 use common::parse_test_file;
+use rumoca::compiler::context::CONTEXT_VERSION;
 use rumoca::dae::dae_ir::DaeIR;
 use rumoca::ir::structural::create_dae::create_dae;
 use rumoca::ir::transform::flatten::flatten;
@@ -187,3 +188,404 @@ fn test_der_in_equations() {
 
     println!("✓ der() function calls appear in equations");
 }
+
+#[test]
+fn test_real_literals_keep_their_text() {
+    // Real literals give their value, and their text when JSON spells the
+    // value differently
+    let source = "model M\n  Real x;\nequation\n  der(x) = 1.5E-3 * x + 0.25 + .5e1;\nend M;\n";
+    let result = rumoca::Compiler::new()
+        .model("M")
+        .compile_str(source, "m.mo")
+        .unwrap();
+    let json: Value = serde_json::from_str(&result.to_dae_ir_json().unwrap()).unwrap();
+    let json_str = json.to_string();
+    assert!(
+        json_str.contains(r#"{"op":"literal","text":"1.5E-3","value":0.0015}"#),
+        "{}",
+        json_str
+    );
+    assert!(
+        json_str.contains(r#"{"op":"literal","value":0.25}"#),
+        "{}",
+        json_str
+    );
+    assert!(
+        json_str.contains(r#"{"op":"literal","text":".5e1","value":5.0}"#),
+        "{}",
+        json_str
+    );
+}
+
+#[test]
+fn test_equation_ids_stable() {
+    use common::compile_source;
+
+    let original = r#"
+model Ids
+  Real x(start = 1);
+  Real y;
+  Real z;
+equation
+  der(x) = -x;
+  y = 2 * x;
+  z = y + x;
+end Ids;
+"#;
+    // Same equations, reordered and with an added one
+    let edited = r#"
+model Ids
+  Real x(start = 1);
+  Real y;
+  Real z;
+  Real w;
+equation
+  w = z;
+  z = y + x;
+  der(x) = -x;
+  y = 2 * x;
+end Ids;
+"#;
+    let a = compile_source(original, "Ids").unwrap().dae;
+    let b = compile_source(edited, "Ids").unwrap().dae;
+
+    assert_eq!(a.eq_ids.fx.len(), a.fx.len());
+    for (eq, id) in a.fx.iter().zip(&a.eq_ids.fx) {
+        assert!(id.starts_with("fx_"), "unexpected id {}", id);
+        let pos = b.eq_ids.fx.iter().position(|other| other == id);
+        let pos = pos.unwrap_or_else(|| panic!("id {} of '{}' not stable", id, eq));
+        assert_eq!(b.fx[pos].to_string(), eq.to_string());
+    }
+
+    // Ids and source locations are exported to JSON
+    let json: Value = serde_json::from_str(&a.to_dae_ir_json().unwrap()).unwrap();
+    let continuous = json["equations"]["continuous"].as_array().unwrap();
+    for (eq, id) in continuous.iter().zip(&a.eq_ids.fx) {
+        assert_eq!(eq["id"], id.as_str());
+        assert!(eq["source"].as_str().unwrap().starts_with("<test>:"));
+    }
+}
+
+#[test]
+fn test_equation_descriptions() {
+    use common::compile_source;
+
+    let source = r#"
+model Base
+  Real z;
+equation
+  z = 3 "base";
+end Base;
+model Desc
+  extends Base;
+  Real x(start = 1);
+  Real v[2];
+  Real a, b;
+equation
+  der(x) = -x "exponential " + "decay";
+  for i in 1:2 loop
+    v[i] = i * x "element";
+  end for;
+  2 * a = b "\"scaled\" a";
+  a + b = 1;
+end Desc;
+"#;
+    let dae = compile_source(source, "Desc").unwrap().dae;
+    let description = |eq: &str| {
+        let found = dae.fx.iter().find(|e| e.to_string().starts_with(eq));
+        found
+            .unwrap_or_else(|| panic!("no equation {}", eq))
+            .description()
+    };
+    assert_eq!(description("z").as_deref(), Some("base"));
+    assert_eq!(description("der(x)").as_deref(), Some("exponential decay"));
+    // Solved equations keep the description, which is unescaped
+    let descriptions: Vec<Option<String>> = dae.fx.iter().map(|eq| eq.description()).collect();
+    assert!(
+        descriptions.contains(&Some("\"scaled\" a".to_string())),
+        "{descriptions:?}"
+    );
+    assert_eq!(descriptions.iter().filter(|d| d.is_none()).count(), 1);
+
+    // Descriptions are exported to JSON as comments
+    let json: Value = serde_json::from_str(&dae.to_dae_ir_json().unwrap()).unwrap();
+    let comments: Vec<&str> = json["equations"]["continuous"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|eq| eq["comment"].as_str())
+        .collect();
+    assert_eq!(comments.len(), 5);
+    assert!(comments.contains(&"element"));
+
+    // and read by templates with the description filter
+    let txt = rumoca::dae::jinja::render_template_str(
+        &dae,
+        "{% for eq in dae.fx %}{{ eq | description }};{% endfor %}",
+    )
+    .unwrap();
+    assert_eq!(txt.matches("element;").count(), 2, "{txt}");
+    assert!(txt.contains("\"scaled\" a;"), "{txt}");
+}
+
+#[test]
+fn test_equation_ids_in_template_context() {
+    use common::compile_source;
+
+    let source = r#"
+model Dup
+  Real a;
+  Real b;
+equation
+  a = 1;
+  b = 1;
+end Dup;
+"#;
+    let dae = compile_source(source, "Dup").unwrap().dae;
+    let ids = &dae.eq_ids.fx;
+    assert_eq!(ids.len(), 2);
+    assert_ne!(ids[0], ids[1]);
+
+    let txt = rumoca::dae::jinja::render_template_str(
+        &dae,
+        "{% for eq in dae.fx %}{{ dae.eq_ids.fx[loop.index0] }};{% endfor %}",
+    )
+    .unwrap();
+    assert_eq!(txt, format!("{};{};", ids[0], ids[1]));
+}
+
+#[test]
+fn test_quoted_identifiers_in_templates() {
+    use common::compile_source;
+
+    let source = r#"
+model Sub
+  Real 'a b'(start = 1);
+equation
+  der('a b') = -'a b';
+end Sub;
+
+model Q
+  Sub 'my sub';
+  parameter Real 'k\'s' = 2;
+  Real 'my var+1';
+equation
+  der('my var+1') = -'k\'s' * 'my var+1';
+end Q;
+"#;
+    let dae = compile_source(source, "Q").unwrap().dae;
+    let txt = rumoca::dae::jinja::render_template_str(
+        &dae,
+        "{% for name, _ in dae.x | items %}{{ name | py_ident }} {{ name | tojson }};{% endfor %}\
+         {% for name, _ in dae.p | items %}{{ name | c_ident }}{% endfor %}",
+    )
+    .unwrap();
+    assert_eq!(
+        txt,
+        r#"my_20sub_a_20b "\u0027my sub\u0027.\u0027a b\u0027";my_20var_2B1 "\u0027my var+1\u0027";k_5Cs"#
+    );
+}
+
+#[test]
+fn test_modelica_constants_in_templates() {
+    use common::compile_source;
+
+    let source = r#"
+model K
+  parameter Real big = Modelica.Constants.inf;
+  parameter Real tol = 10 * Modelica.Constants.eps;
+  parameter Integer n = integer(Modelica.Constants.pi);
+  Real x[n];
+equation
+  der(x) = -tol * x;
+end K;
+"#;
+    let dae = compile_source(source, "K").unwrap().dae;
+    assert_eq!(dae.x.len(), 3);
+    let txt = rumoca::dae::jinja::render_template_str(
+        &dae,
+        "{% set big = dae.p.big.start.Terminal.token.text %}{{ big | py_literal }} {{ big | c_literal }}",
+    )
+    .unwrap();
+    assert_eq!(txt, "float('inf') DBL_MAX");
+}
+
+#[test]
+fn test_template_context_version() {
+    use common::compile_source;
+
+    let mut result = compile_source("model V\n  Real x;\nequation\n  x = 1;\nend V;", "V").unwrap();
+    let txt = rumoca::dae::jinja::render_template_str(
+        &result.dae,
+        "{{- require_context_version(\"1.0\") -}}\n{{ context_version }} {{ dae.model_name }}",
+    )
+    .unwrap();
+    assert_eq!(txt, format!("{} V", CONTEXT_VERSION));
+
+    // An incompatible template fails before rendering, from a file too
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("future.jinja");
+    std::fs::write(
+        &template,
+        "{{ panic(\"rendered\") }}{{ require_context_version('2.0') }}",
+    )
+    .unwrap();
+    let err = result
+        .render_template_to_string(template.to_str().unwrap())
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("the template requires context version 2.0")
+            && err.contains(&format!("provides version {}", CONTEXT_VERSION)),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_template_debug() {
+    use common::compile_source;
+
+    let mut result = compile_source(
+        "model V\n  Real x(start = 1);\nequation\n  der(x) = -x;\nend V;",
+        "V",
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("debug.jinja");
+    std::fs::write(
+        &template,
+        "# {{ dae.model_name }}\n{% for name, v in dae.x | items %}\n{{ name }} {{ dae.statse }}\n{{ v.start.missing.more }}\n{% endfor %}\n",
+    )
+    .unwrap();
+    let template = template.to_str().unwrap();
+
+    // Errors give the template line and column, with a code frame
+    let err = result
+        .render_template_debug(template)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("debug.jinja:4:11: undefined value"), "{}", err);
+    assert!(
+        err.contains("   4 > {{ v.start.missing.more }}\n     |           ^^^^^^^^^^^^^"),
+        "{}",
+        err
+    );
+
+    // Undefined lookups of the context are warned about
+    assert_eq!(
+        result.check_template_lookups(template).unwrap(),
+        ["debug.jinja:3: undefined variable 'dae.statse'"]
+    );
+
+    let context: serde_json::Value =
+        serde_json::from_str(&result.template_context_json().unwrap()).unwrap();
+    assert_eq!(context["dae"]["model_name"], "V");
+    assert!(context["provenance"].is_object() && context["topology"].is_object());
+}
+
+#[test]
+fn test_division_guards() {
+    let source = r#"
+model Div
+  parameter Real R = 0;
+  Real i(start = 1);
+  Real v;
+equation
+  der(i) = v / 2;
+  v = 1 / R + i / R + (if i > 0 then 1 / i else 0);
+end Div;
+"#;
+    // Off by default
+    let result = rumoca::Compiler::new()
+        .model("Div")
+        .compile_str(source, "div.mo")
+        .unwrap();
+    assert!(result.dae.asserts.is_empty());
+
+    let result = rumoca::Compiler::new()
+        .model("Div")
+        .guard_divisions(true)
+        .compile_str(source, "div.mo")
+        .unwrap();
+
+    // One guard per non-constant denominator, skipping guarded divisions
+    assert_eq!(result.dae.asserts.len(), 1, "{:?}", result.dae.asserts);
+    assert_eq!(
+        result.dae.asserts[0].to_string(),
+        "assert(R <> 0, \"division by zero: 'R' is 0 at div.mo:8:9\")"
+    );
+
+    let json: Value = serde_json::from_str(&result.dae.to_dae_ir_json().unwrap()).unwrap();
+    let assertions = json["assertions"].as_array().unwrap();
+    assert_eq!(assertions.len(), 1);
+    assert_eq!(assertions[0]["condition"]["op"], "!=");
+}
+
+#[test]
+fn test_when_clauses_export() {
+    let source = r#"
+model W
+  Real h(start = 1);
+  Real v(start = 0);
+equation
+  der(h) = v;
+  der(v) = -9.81;
+  when h < 0.5 then
+    reinit(v, 0);
+  elsewhen h < 0 then
+    reinit(v, -0.8 * pre(v));
+  end when;
+end W;
+"#;
+    let result = rumoca::Compiler::new()
+        .model("W")
+        .compile_str(source, "w.mo")
+        .unwrap();
+    let json: Value = serde_json::from_str(&result.dae.to_dae_ir_json().unwrap()).unwrap();
+
+    // The branches keep the order of the when-equation: the elsewhen-branch
+    // c1 only fires when c0 doesn't
+    assert_eq!(
+        json["when_clauses"],
+        serde_json::json!([{ "branches": ["c0", "c1"] }])
+    );
+    let indicators: Vec<&str> = json["event_indicators"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|indicator| indicator["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(indicators, ["c0", "c1"]);
+
+    // The resets of each branch refer to its condition
+    let statements = json["algorithms"][0]["statements"].as_array().unwrap();
+    let sources: Vec<&str> = statements
+        .iter()
+        .map(|stmt| stmt["source_ref"].as_str().unwrap())
+        .collect();
+    assert_eq!(sources, ["c0", "c1"]);
+    assert_eq!(statements[0]["expr"]["value"], 0);
+}
+
+#[test]
+fn test_compilation_result_serde_roundtrip() {
+    let source = std::fs::read_to_string("tests/fixtures/bouncing_ball.mo").unwrap();
+    let result = rumoca::Compiler::new()
+        .model("BouncingBall")
+        .compile_str(&source, "bouncing_ball.mo")
+        .unwrap();
+
+    let json = serde_json::to_string(&result).unwrap();
+    let snapshot: rumoca::CompilationResult = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(snapshot.dae, result.dae);
+    assert_eq!(snapshot.def, result.def);
+    assert_eq!(snapshot.expanded_class, result.expanded_class);
+    assert_eq!(snapshot.balance, result.balance);
+    assert_eq!(snapshot.flatten_time, result.flatten_time);
+    assert_eq!(
+        snapshot.dae.to_dae_ir_json().unwrap(),
+        result.dae.to_dae_ir_json().unwrap()
+    );
+}
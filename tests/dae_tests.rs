@@ -7,6 +7,7 @@ mod common;
 use common::{create_dae_from_fixture, parse_test_file};
 use rumoca::ir::structural::create_dae::create_dae;
 use rumoca::ir::transform::flatten::flatten;
+use rumoca::{Compiler, SortMode};
 
 // =============================================================================
 // Basic DAE Creation Tests
@@ -239,7 +240,7 @@ fn test_simple_circuit_equation_order() {
 
     // Process equations in order - each should only use known variables
     for (i, eq) in dae.fx.iter().enumerate() {
-        if let Equation::Simple { lhs, rhs } = eq {
+        if let Equation::Simple { lhs, rhs, .. } = eq {
             // Get the variable being defined
             let defined_var = match lhs {
                 Expression::ComponentReference(cref) => Some(cref.to_string()),
@@ -281,6 +282,278 @@ fn test_simple_circuit_equation_order() {
     }
 }
 
+// =============================================================================
+// Vectorized Call Tests
+// =============================================================================
+
+#[test]
+fn test_vectorized_function_calls() {
+    let source = r#"
+model Vectorized
+  Real x[3](each start = 1);
+  Real y[3];
+  Real z[3] = cos(x);
+equation
+  der(x) = -x;
+  y = 2 * sin(x);
+end Vectorized;
+"#;
+    let result = common::compile_source(source, "Vectorized").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+
+    // Every scalar equation must reference a single element of x
+    let eqs: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert_eq!(eqs.len(), 9, "{:?}", eqs);
+    for eq in &eqs {
+        assert!(
+            !eq.contains("(x)") && !eq.ends_with("-x"),
+            "not scalarized: {}",
+            eq
+        );
+    }
+    assert!(eqs.iter().any(|eq| eq.contains("2 * sin(x[")));
+    assert!(eqs.iter().any(|eq| eq.contains("cos(x[")));
+}
+
+#[test]
+fn test_vectorized_call_shapes() {
+    let source = r#"
+model Shapes
+  Real x[3](each start = 1);
+  Real w[3];
+  Real z[3];
+equation
+  der(x) = -x;
+  w = {1, 2, 3};
+  z = atan2(x, w);
+end Shapes;
+"#;
+    let result = rumoca::Compiler::new()
+        .model("Shapes")
+        .compile_str(source, "shapes.mo")
+        .unwrap();
+    let eqs: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert!(
+        eqs.iter().any(|eq| eq.contains("atan2(x[3], w[3])")),
+        "{:?}",
+        eqs
+    );
+
+    let err = rumoca::Compiler::new()
+        .model("Shapes")
+        .compile_str(
+            &source
+                .replace("w[3]", "w[2]")
+                .replace("{1, 2, 3}", "{1, 2}"),
+            "shapes.mo",
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "shapes.mo:9:7: Vectorized call to 'atan2' has array arguments of different shapes: Real[3] and Real[2]"
+    );
+}
+
+#[test]
+fn test_vectorized_matrix_calls() {
+    let source = r#"
+model Matrix
+  Real x[2, 2];
+  Real y[2, 2];
+  Real z[2, 2] = cos(x);
+equation
+  der(x) = {{1, 2}, {3, 4}};
+  y = sin(x);
+end Matrix;
+"#;
+    let result = common::compile_source(source, "Matrix").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+
+    // Elements are subscripted in the declared shape, in row-major order
+    let eqs: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert_eq!(eqs.len(), 12, "{:?}", eqs);
+    for (i, j) in [(1, 1), (1, 2), (2, 1), (2, 2)] {
+        let n = 2 * (i - 1) + j;
+        for eq in [
+            format!("der(x[{i},{j}]) = {n}"),
+            format!("y[{i},{j}] = sin(x[{i},{j}])"),
+            format!("z[{i},{j}] = cos(x[{i},{j}])"),
+        ] {
+            assert!(eqs.contains(&eq), "missing {}: {:?}", eq, eqs);
+        }
+    }
+    assert!(
+        !eqs.iter()
+            .any(|eq| eq.contains("x[3]") || eq.contains("x[4]")),
+        "{:?}",
+        eqs
+    );
+}
+
+// =============================================================================
+// Lookup Table Tests
+// =============================================================================
+
+#[test]
+fn test_table_lookups_lowered() {
+    let source = r#"
+model Tables
+  parameter Real gain = 2;
+  parameter Real tab[3, 2] = [0, 0; 1, gain; 2, 1];
+  Real y;
+  Real z;
+  Real w;
+equation
+  y = table1D(tab, time);
+  z = table1D(tab, time, Smoothness.ConstantSegments);
+  w = table1D("data.csv", time);
+end Tables;
+"#;
+    let result = common::compile_source(source, "Tables").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+
+    let eqs: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert!(
+        eqs.iter().any(|eq| eq
+            == "y = if noEvent(time < 1.0) then (0.0 + 2.0 * time) else (2.0 + (-1.0) * (time - 1.0))"),
+        "{:?}",
+        eqs
+    );
+    // Constant segments jump at the breakpoints, which are events
+    assert!(
+        eqs.iter()
+            .any(|eq| eq == "z = if c0 then 0.0 elseif c1 then 2.0 else 1.0"),
+        "{:?}",
+        eqs
+    );
+    assert_eq!(result.dae.fc["c0"].to_string(), "time < 1.0");
+    assert_eq!(result.dae.fc["c1"].to_string(), "time < 2.0");
+    // Tables only known at run time are left to the backend
+    assert!(
+        eqs.iter().any(|eq| eq == "w = table1D(\"data.csv\", time)"),
+        "{:?}",
+        eqs
+    );
+}
+
+#[test]
+fn test_condition_names_avoid_model_names() {
+    let source = r#"
+model Names
+  Real c0;
+  Real c2;
+  Real y;
+equation
+  c0 = time;
+  c2 = if c0 > 1 then 1 else 0;
+  y = if time < 2 then c0 else c2;
+end Names;
+"#;
+    let result = common::compile_source(source, "Names").unwrap();
+    let conditions: Vec<&str> = result.dae.c.keys().map(|name| name.as_str()).collect();
+    assert_eq!(conditions, ["c1", "c3"]);
+    assert_eq!(result.dae.fc["c1"].to_string(), "c0 > 1");
+    assert_eq!(result.dae.fc["c3"].to_string(), "time < 2");
+}
+
+#[test]
+fn test_sort_mode() {
+    let source = r#"
+model Order
+  Real x(start = 1);
+  Real y;
+  Real z;
+equation
+  y + z = x;
+  der(x) = -y;
+  z = 2 * y;
+end Order;
+"#;
+    let compile = |mode: SortMode| {
+        Compiler::new()
+            .model("Order")
+            .sort(mode)
+            .compile_str(source, "order.mo")
+            .unwrap()
+    };
+    let sorted = compile(SortMode::Blt);
+    assert_eq!(sorted.dae.sort, SortMode::Blt);
+    assert_ne!(sorted.dae.fx[0].to_string(), "y + z = x");
+
+    // Equations keep their declared order and form, with the same balance
+    let declared = compile(SortMode::None);
+    assert_eq!(declared.dae.sort, SortMode::None);
+    let eqs: Vec<String> = declared.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert_eq!(eqs, ["y + z = x", "der(x) = -y", "z = 2 * y"]);
+    // Equation ids don't depend on the order
+    assert_eq!(sorted_ids(&declared.dae), sorted_ids(&sorted.dae));
+    assert!(declared.balance.is_balanced);
+    assert_eq!(serde_json::to_value(&declared.dae).unwrap()["sort"], "none");
+}
+
+/// Ids of the continuous-time equations of the DAE, sorted
+fn sorted_ids(dae: &rumoca::dae::ast::Dae) -> Vec<String> {
+    let mut ids = dae.eq_ids.fx.clone();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_steady_state() {
+    let source = r#"
+model Equilibrium
+  Real x[2](each start = 1);
+  Real y;
+  Real f;
+  parameter Real k = 2;
+  parameter Real c = k / 4;
+equation
+  der(x[1]) = 1 - x[1]^2 - y;
+  der(x[2]) = f - k * x[2];
+  y = c * x[1];
+  if x[1] > 0.5 then
+    f = 2 * (x[1] - 0.5);
+  else
+    f = 0;
+  end if;
+end Equilibrium;
+"#;
+    let result = common::compile_source(source, "Equilibrium").unwrap();
+    let steady = result.dae.steady_state().unwrap();
+    // x1^2 + x1 / 2 - 1 = 0
+    let x1 = (-0.5 + (0.25_f64 + 4.0).sqrt()) / 2.0;
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert!(close(steady.states["x[1]"], x1));
+    assert!(close(steady.states["x[2]"], x1 - 0.5));
+    assert!(close(steady.algebraics["y"], x1 / 2.0));
+    assert!(close(steady.algebraics["f"], 2.0 * (x1 - 0.5)));
+    assert!(steady.residual <= 1e-10);
+
+    // Without a unique equilibrium the Jacobian is singular
+    let source = r#"
+model Drift
+  Real x(start = 1);
+equation
+  der(x) = 1 + 0 * x;
+end Drift;
+"#;
+    let result = common::compile_source(source, "Drift").unwrap();
+    let err = result.dae.steady_state().unwrap_err();
+    assert!(matches!(err, rumoca::Error::SteadyState(_)), "{err:?}");
+    let err = err.to_string();
+    assert!(err.contains("singular Jacobian"), "{err}");
+    // The error names the equation that can't be zeroed, not a variable
+    assert!(err.contains("equation 'der(x) = 1 + 0 * x'"), "{err}");
+
+    let source = "model Ramp\n  Real x(start = 1);\nequation\n  der(x) = 1;\nend Ramp;\n";
+    let result = common::compile_source(source, "Ramp").unwrap();
+    let err = result.dae.steady_state().unwrap_err().to_string();
+    assert!(
+        err.contains("equation 'der(x) = 1' (residual 0 - (1) = -1e0)"),
+        "{err}"
+    );
+}
+
 // =============================================================================
 // Helper Functions
 // =============================================================================
@@ -331,7 +604,7 @@ fn extract_variables(expr: &rumoca::ir::ast::Expression) -> Vec<String> {
         Expression::Terminal { .. } | Expression::Empty => {
             // No variables in terminals/literals
         }
-        Expression::Parenthesized { inner } => {
+        Expression::Parenthesized { inner } | Expression::NamedArgument { value: inner, .. } => {
             vars.extend(extract_variables(inner));
         }
         Expression::ArrayComprehension { expr, indices } => {
@@ -0,0 +1,33 @@
+//! Conformance tests of the diagnostics, from the fixtures in
+//! tests/fixtures/diagnostics
+//!
+//! Each fixture annotates the diagnostics it produces with `//~ ERROR message`
+//! comments (see `rumoca::lsp::expectations`). To add a test, add a fixture.
+
+use lsp_types::Uri;
+use rumoca::lsp::expectations::check_fixture;
+
+#[test]
+fn test_diagnostic_fixtures() {
+    let mut paths: Vec<_> = std::fs::read_dir("tests/fixtures/diagnostics")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mo"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let text = std::fs::read_to_string(path).unwrap();
+        let uri: Uri = format!("file:///fixtures/{}", path.file_name().unwrap().display())
+            .parse()
+            .unwrap();
+        if let Err(mismatches) = check_fixture(&uri, &text) {
+            for mismatch in mismatches {
+                failures.push(format!("{}:{}", path.display(), mismatch));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
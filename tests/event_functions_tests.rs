@@ -167,3 +167,148 @@ fn test_initial_in_when() {
     let json = result.to_dae_ir_json().unwrap();
     assert!(json.contains("initial"), "JSON should contain initial");
 }
+
+#[test]
+fn test_random_in_sampled_when() {
+    let result = Compiler::new().model("NoiseTest").compile_str(
+        r#"
+            model NoiseTest
+                parameter Integer seed = 42;
+                discrete Real n(start=0.0);
+                Real x(start=0.0);
+            equation
+                der(x) = -x + n;
+            when sample(0, 0.1) then
+                n = 0.01 * (random(seed) - 0.5);
+            end when;
+            end NoiseTest;
+            "#,
+        "noise_test.mo",
+    );
+
+    assert!(result.is_ok(), "Failed to compile: {:?}", result.err());
+
+    let result = result.unwrap();
+    let pretty = result.dae.to_pretty_string();
+    assert!(
+        pretty.contains("random(seed, 1)"),
+        "random() should be numbered as stream 1:\n{}",
+        pretty
+    );
+}
+
+#[test]
+fn test_random_outside_when_is_rejected() {
+    let result = Compiler::new().model("NoiseTest").compile_str(
+        r#"
+            model NoiseTest
+                Real x;
+            equation
+                x = random(1);
+            end NoiseTest;
+            "#,
+        "noise_test.mo",
+    );
+
+    let error = result.expect_err("random() outside when should fail");
+    assert!(
+        error
+            .to_string()
+            .contains("only allowed inside when-clauses"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_saturation_generates_events() {
+    let result = Compiler::new().model("SaturationTest").compile_str(
+        r#"
+            model SaturationTest
+                parameter Real uMax = 1;
+                Real u;
+                Real y;
+                Real z;
+            equation
+                u = sin(time);
+                y = if u > uMax then uMax elseif u < -uMax then -uMax else u;
+                z = max(u, 0);
+            end SaturationTest;
+            "#,
+        "saturation_test.mo",
+    );
+
+    assert!(result.is_ok(), "Failed to compile: {:?}", result.err());
+
+    let result = result.unwrap();
+    let conditions: Vec<String> = result.dae.fc.values().map(|e| e.to_string()).collect();
+    assert_eq!(conditions, vec!["u > uMax", "u < -uMax", "u > 0"]);
+
+    // min/max become if-expressions on their condition
+    let eqs: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    assert!(
+        eqs.contains(&"z = if c2 then u else 0".to_string()),
+        "{:?}",
+        eqs
+    );
+}
+
+#[test]
+fn test_noevent_and_parameter_relations_generate_no_events() {
+    let result = Compiler::new().model("NoEventTest").compile_str(
+        r#"
+            model NoEventTest
+                parameter Real k = 2;
+                Real u;
+                Real y;
+                Real z;
+            equation
+                u = sin(time);
+                y = noEvent(if u > 0 then u else 0) + noEvent(max(u, 0));
+                z = if k > 1 then min(k, 3) * u else u;
+            end NoEventTest;
+            "#,
+        "noevent_test.mo",
+    );
+
+    assert!(result.is_ok(), "Failed to compile: {:?}", result.err());
+
+    let result = result.unwrap();
+    assert!(result.dae.fc.is_empty(), "{:?}", result.dae.fc);
+}
+
+#[test]
+fn test_boolean_relations_are_discrete() {
+    let result = Compiler::new().model("BooleanTest").compile_str(
+        r#"
+            model BooleanTest
+                Real x(start = 1);
+                Boolean b;
+                Real y;
+            equation
+                der(x) = 2 - x;
+                b = x > 1;
+                y = if b then 1 else 2;
+            end BooleanTest;
+            "#,
+        "boolean_test.mo",
+    );
+
+    assert!(result.is_ok(), "Failed to compile: {:?}", result.err());
+
+    let result = result.unwrap();
+    let dae = &result.dae;
+    // b only changes at the events of its relation
+    assert_eq!(dae.m.keys().collect::<Vec<_>>(), ["b"]);
+    assert_eq!(dae.c["c0"].description[0].text, "Boolean equation");
+    assert_eq!(dae.fc["c0"].to_string(), "x > 1");
+    let fm: Vec<String> = dae.fm.iter().map(|eq| eq.to_string()).collect();
+    assert_eq!(fm, ["b = c0"]);
+    assert_eq!(dae.eq_ids.fm.len(), 1);
+    assert!(!dae.fx.iter().any(|eq| eq.to_string().starts_with("b =")));
+    assert!(dae.check_balance().is_balanced);
+
+    // At the equilibrium x = 2, so b is true
+    let steady = dae.steady_state().unwrap();
+    assert!((steady.algebraics["y"] - 1.0).abs() < 1e-9, "{}", steady);
+}
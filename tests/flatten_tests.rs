@@ -438,3 +438,857 @@ fn test_flatten_with_deps_tracks_file_dependencies() {
         result.dependencies.files.keys().collect::<Vec<_>>()
     );
 }
+
+#[test]
+fn test_flatten_nested_modifications() {
+    use common::parse_source;
+
+    let source = r#"
+model Inertia
+  parameter Real J = 1;
+  Real w(start = 0);
+equation
+  der(w) = 1 / J;
+end Inertia;
+
+model Motor
+  Inertia inertia;
+end Motor;
+
+model Test
+  Motor motor(inertia(J = 2, w(each start = 5)));
+  Motor m2(inertia.J = 4);
+end Test;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Test")).expect("Flatten failed");
+
+    let start_of = |name: &str| format!("{}", fclass.components[name].start);
+    assert_eq!(start_of("motor.inertia.J"), "2");
+    assert_eq!(start_of("m2.inertia.J"), "4");
+    assert_eq!(start_of("m2.inertia.w"), "0");
+
+    let w = &fclass.components["motor.inertia.w"];
+    assert_eq!(format!("{}", w.start), "5");
+    assert!(w.start_is_modification && w.start_has_each);
+}
+
+#[test]
+fn test_flatten_final_modification_conflict() {
+    use common::parse_source;
+
+    let source = r#"
+model Inertia
+  parameter Real J = 1;
+end Inertia;
+
+model FinalMotor
+  Inertia inertia(final J = 3);
+end FinalMotor;
+
+model Test
+  FinalMotor fm(inertia.J = 2);
+end Test;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let err = flatten(&def, Some("Test")).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Cannot modify final element 'fm.inertia.J'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_duplicate_modification_rejected() {
+    use common::parse_source;
+
+    let source = r#"
+model Inertia
+  parameter Real J = 1;
+end Inertia;
+
+model Test
+  Inertia a(J = 2, J = 3);
+end Test;
+"#;
+
+    let err = parse_source(source).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("Duplicate modification of 'J'"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn test_modification_precedence() {
+    use common::parse_source;
+
+    // Modelica spec 7.2.4: the outermost modification wins. Declaration
+    // modifications are overridden by extends modifications, which are overridden
+    // by modifications of the enclosing instance.
+    let source = r#"
+model Transistor
+  parameter Real a = 1, b = 2, c = 3;
+end Transistor;
+
+model Circuit
+  Transistor t1(b = 5, c = 6);
+end Circuit;
+
+model Derived
+  extends Circuit(t1(c = 7));
+end Derived;
+
+model Twice
+  extends Derived(t1.c = 8);
+end Twice;
+
+model Test
+  Circuit plain;
+  Derived derived;
+  Twice twice;
+  Derived enclosing(t1(b = 9, c = 10));
+end Test;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Test")).expect("Flatten failed");
+
+    let value_of = |name: &str| format!("{}", fclass.components[name].start);
+    assert_eq!(value_of("plain.t1.a"), "1");
+    assert_eq!(value_of("plain.t1.b"), "5");
+    assert_eq!(value_of("plain.t1.c"), "6");
+    assert_eq!(value_of("derived.t1.b"), "5");
+    assert_eq!(value_of("derived.t1.c"), "7");
+    assert_eq!(value_of("twice.t1.c"), "8");
+    assert_eq!(value_of("enclosing.t1.b"), "9");
+    assert_eq!(value_of("enclosing.t1.c"), "10");
+}
+
+#[test]
+fn test_extends_modification_of_inherited_element() {
+    use common::parse_source;
+
+    let source = r#"
+model Base
+  parameter Real k = 1;
+  Real y(start = 0);
+equation
+  der(y) = k;
+end Base;
+
+model Derived
+  extends Base(k = 2, y(start = 3));
+end Derived;
+
+model Test
+  Derived d;
+end Test;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Test")).expect("Flatten failed");
+
+    assert_eq!(format!("{}", fclass.components["d.k"].start), "2");
+    let y = &fclass.components["d.y"];
+    assert_eq!(format!("{}", y.start), "3");
+    assert!(y.start_is_modification);
+}
+
+#[test]
+fn test_final_stops_further_modification() {
+    use common::parse_source;
+
+    // Modelica spec 7.2.6: an element given a final modification (or declared
+    // final) cannot be modified by an enclosing or extending class.
+    let source = r#"
+model Transistor
+  parameter Real a = 1, b = 2;
+  final parameter Real c = 3;
+end Transistor;
+
+model Circuit
+  Transistor t1(final a = 2, b = 5);
+end Circuit;
+
+model ModifyB
+  extends Circuit(t1(b = 6));
+end ModifyB;
+
+model ModifyA
+  extends Circuit(t1(a = 5));
+end ModifyA;
+
+model ModifyC
+  Transistor t(c = 4);
+end ModifyC;
+
+model FinalExtends
+  extends Transistor(final b = 4);
+end FinalExtends;
+
+model ModifyFinalExtends
+  FinalExtends t(b = 5);
+end ModifyFinalExtends;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+
+    let fclass = flatten(&def, Some("ModifyB")).expect("Non-final element is modifiable");
+    assert_eq!(format!("{}", fclass.components["t1.b"].start), "6");
+
+    for (model, element) in [
+        ("ModifyA", "t1.a"),
+        ("ModifyC", "t.c"),
+        ("ModifyFinalExtends", "t.b"),
+    ] {
+        let err = flatten(&def, Some(model)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("Cannot modify final element '{}'", element)),
+            "{}: {}",
+            model,
+            err
+        );
+    }
+}
+
+#[test]
+fn test_flatten_short_class_aliases() {
+    use common::parse_source;
+    use rumoca::ir::ast::Causality;
+
+    let source = r#"
+package P
+  type Vec = Real[3](each start = 1);
+  type Voltage = Real(unit = "V", start = 2);
+  type V2 = Voltage[2];
+  connector RealInput = input Real;
+  connector RealOutput = output Real[2];
+
+  model M
+    Vec v;
+    Voltage u(start = 5);
+    V2 w[4];
+    RealInput i;
+    RealOutput o;
+  equation
+    der(v) = -v;
+    der(u) = 1;
+    der(w) = -w;
+    o = {i, i};
+  end M;
+end P;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("P.M")).expect("Flatten failed");
+
+    let v = &fclass.components["v"];
+    assert_eq!(v.shape, vec![3]);
+    assert_eq!(format!("{}", v.start), "1");
+    assert!(v.start_has_each);
+
+    // The component's own start wins over the alias start; other attributes are kept
+    let u = &fclass.components["u"];
+    assert_eq!(format!("{}", u.start), "5");
+    assert!(u.modifications.contains_key("unit"));
+
+    // Declared dimensions come before the alias dimensions
+    let w = &fclass.components["w"];
+    assert_eq!(w.shape, vec![4, 2]);
+    assert_eq!(format!("{}", w.start), "2");
+
+    assert!(matches!(
+        fclass.components["i"].causality,
+        Causality::Input(_)
+    ));
+    let o = &fclass.components["o"];
+    assert!(matches!(o.causality, Causality::Output(_)));
+    assert_eq!(o.shape, vec![2]);
+}
+
+#[test]
+fn test_short_class_final_attribute() {
+    use common::parse_source;
+
+    // Modelica spec 7.2.6
+    let source = r#"
+type Angle = Real(final quantity = "Angle", final unit = "rad", displayUnit = "deg");
+
+model Test
+  Angle a1(unit = "deg");
+end Test;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let err = flatten(&def, Some("Test")).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Cannot modify final element 'a1.unit'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_redeclaration_plug_compatibility() {
+    use common::parse_source;
+
+    let source = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+
+partial model OnePort
+  Pin p, n;
+  Real v;
+equation
+  v = p.v - n.v;
+  p.i + n.i = 0;
+end OnePort;
+
+model Resistor
+  extends OnePort;
+  parameter Real R = 1;
+equation
+  v = R * p.i;
+end Resistor;
+
+model Probe
+  Pin p;
+  output Real v;
+end Probe;
+
+model Base
+  replaceable model Load = Resistor constrainedby OnePort;
+  replaceable Resistor r constrainedby OnePort;
+  input Real u;
+  Load l;
+end Base;
+
+model Good
+  extends Base;
+  redeclare model Load = Resistor;
+  redeclare Resistor r;
+end Good;
+
+model BadComponent
+  extends Base;
+  redeclare Probe r;
+end BadComponent;
+
+model BadClass
+  extends Base;
+  redeclare model Load = Probe;
+end BadClass;
+
+model NotReplaceable
+  extends Base;
+  redeclare output Real u;
+end NotReplaceable;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let base = &def.class_list["Base"];
+    let r = &base.components["r"];
+    assert!(r.replaceable);
+    assert_eq!(r.constrainedby.as_ref().unwrap().to_string(), "OnePort");
+    assert_eq!(
+        base.classes["Load"]
+            .constrainedby
+            .as_ref()
+            .unwrap()
+            .to_string(),
+        "OnePort"
+    );
+    assert!(def.class_list["Good"].components["r"].redeclare);
+
+    // Components of a replaceable class declared in the same class
+    let fclass = flatten(&def, Some("Good")).unwrap();
+    assert!(fclass.components.contains_key("l.p.v"));
+    assert!(fclass.components.contains_key("r.R"));
+
+    let err = flatten(&def, Some("BadComponent")).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "<test>:42:13: Redeclaration of component 'r' as 'Probe' is not compatible \
+         with its constraining type 'OnePort':\n  \
+         'r.n' is missing\n  \
+         <test>:24:10: 'r.v' is an output, but the constraining type declares it \
+         neither input nor output"
+    );
+
+    let err = flatten(&def, Some("BadClass")).unwrap_err().to_string();
+    assert!(
+        err.starts_with(
+            "<test>:47:19: Redeclaration of class 'Load' as 'Probe' is not compatible \
+             with its constraining type 'OnePort':\n  'n' is missing"
+        ),
+        "{err}"
+    );
+
+    let err = flatten(&def, Some("NotReplaceable"))
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "<test>:52:20: Component 'u' is redeclared, but it is not replaceable in 'Base'"
+    );
+}
+
+#[test]
+fn test_connector_compatibility() {
+    use common::parse_source;
+
+    let source = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+
+connector PositivePin
+  extends Pin;
+end PositivePin;
+
+connector Flange
+  Real phi;
+  flow Real tau;
+end Flange;
+
+connector PotentialPin
+  Real v;
+  Real i;
+end PotentialPin;
+
+connector RealInput = input Real;
+connector BooleanOutput = output Boolean;
+
+model Good
+  Pin a;
+  PositivePin b;
+  RealInput u[2];
+  RealInput w[2];
+equation
+  connect(a, b);
+  connect(u, w);
+end Good;
+
+model Mismatched
+  Pin a;
+  Flange f;
+equation
+  connect(a, f);
+end Mismatched;
+
+model FlowMismatch
+  Pin a;
+  PotentialPin b;
+equation
+  connect(a, b);
+end FlowMismatch;
+
+model SignalMismatch
+  RealInput u;
+  BooleanOutput y;
+  Pin a;
+equation
+  connect(u, y);
+  connect(u, a);
+end SignalMismatch;
+
+model ShapeMismatch
+  RealInput u[2];
+  RealInput w[3];
+equation
+  connect(u, w);
+end ShapeMismatch;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Good")).unwrap();
+    assert!(fclass.components.contains_key("b.v"));
+
+    let err = flatten(&def, Some("Mismatched")).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "<test>:38:11: connect(a, f): connectors 'Pin' and 'Flange' are incompatible:\n  \
+         'v' is missing in 'Flange'\n  \
+         'i' is missing in 'Flange'\n  \
+         'phi' is missing in 'Pin'\n  \
+         'tau' is missing in 'Pin'"
+    );
+
+    let err = flatten(&def, Some("FlowMismatch")).unwrap_err().to_string();
+    assert!(
+        err.ends_with(
+            "connectors 'Pin' and 'PotentialPin' are incompatible:\n  \
+             'i' is a flow variable in 'Pin', but a potential variable in 'PotentialPin'"
+        ),
+        "{err}"
+    );
+
+    let err = flatten(&def, Some("SignalMismatch"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.ends_with("'RealInput' is a 'Real' signal, but 'BooleanOutput' is a 'Boolean' signal"),
+        "{err}"
+    );
+
+    let err = flatten(&def, Some("ShapeMismatch"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.ends_with("'u' has dimensions [2], but 'w' has [3]"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_component_arrays() {
+    use common::parse_source;
+
+    let source = r#"
+connector Pin
+  Real v;
+  flow Real i;
+end Pin;
+
+model Resistor
+  parameter Real R = 1;
+  Pin p, n;
+equation
+  p.v - n.v = R * p.i;
+  p.i + n.i = 0;
+end Resistor;
+
+model Ground
+  Pin p;
+equation
+  p.v = 0;
+end Ground;
+
+model Chain
+  parameter Integer n = 3;
+  Resistor r[n](each R = 5);
+  Resistor s[2](R = {7, 8});
+  Ground g[2];
+equation
+  for i in 1:n - 1 loop
+    connect(r[i].n, r[i + 1].p);
+  end for;
+  connect(r[n].n, s[1].p);
+  connect(s.n, g.p);
+end Chain;
+
+model Mismatched
+  Resistor r[2](R = {1, 2, 3});
+end Mismatched;
+
+model SliceMismatch
+  Resistor r[3];
+  Ground g[2];
+equation
+  connect(r[1:3].n, g.p);
+end SliceMismatch;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Chain")).unwrap();
+    assert!(!fclass.components.contains_key("r"));
+    for name in ["r[1].p.v", "r[3].n.i", "s[2].R"] {
+        assert!(fclass.components.contains_key(name), "{name}");
+    }
+    let binding = |name: &str| fclass.components[name].start.to_string();
+    assert_eq!(binding("r[2].R"), "5");
+    assert_eq!(binding("s[1].R"), "7");
+    assert_eq!(binding("s[2].R"), "8");
+
+    let equations: Vec<String> = fclass.equations.iter().map(|eq| eq.to_string()).collect();
+    let connected = |a: &str, b: &str| {
+        equations
+            .iter()
+            .any(|eq| *eq == format!("{a} = {b}") || *eq == format!("{b} = {a}"))
+    };
+    // For-equations and slices are expanded to connections of elements
+    assert!(connected("r[1].n.v", "r[2].p.v"), "{equations:#?}");
+    assert!(connected("r[2].n.v", "r[3].p.v"), "{equations:#?}");
+    assert!(connected("r[3].n.v", "s[1].p.v"), "{equations:#?}");
+    assert!(connected("s[2].n.v", "g[2].p.v"), "{equations:#?}");
+
+    let err = flatten(&def, Some("Mismatched")).unwrap_err().to_string();
+    assert!(
+        err.contains("{1, 2, 3} has 3 elements, but the array has 2"),
+        "{err}"
+    );
+
+    let err = flatten(&def, Some("SliceMismatch"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.ends_with("connect(r[1:3].n, g.p) connects 3 connectors to 2"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_flatten_recursive_instantiation() {
+    let source = r#"
+model A
+  B b;
+end A;
+
+model B
+  Real x;
+  A a;
+end B;
+
+model M
+  A a;
+end M;
+
+model Self
+  Self s;
+end Self;
+"#;
+    let def = common::parse_source(source).unwrap();
+    let err = flatten(&def, Some("M")).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "<test>:8:5: recursive instantiation: component 'a.b.a' is an instance of a \
+         class containing it (A -> B -> A)"
+    );
+    let err = flatten(&def, Some("Self")).unwrap_err().to_string();
+    assert!(err.ends_with("(Self -> Self)"), "{err}");
+}
+
+#[test]
+fn test_flatten_function_calls_in_class_scope() {
+    // Calls are resolved with the imports and the enclosing packages of the
+    // class declaring them, also in component classes and inherited equations
+    let source = r#"
+package Lib
+  function sq
+    input Real x;
+    output Real y;
+  algorithm
+    y := x * x;
+  end sq;
+  function cube
+    input Real x;
+    output Real y;
+  algorithm
+    y := x * x * x;
+  end cube;
+  type Len = Real(unit = "m");
+  model Sub
+    import mysq = Lib.sq;
+    Real u = 2;
+    Real v;
+  equation
+    v = mysq(u) + sq(u) + Lib.sq(u);
+  end Sub;
+  model Base
+    Real w;
+  equation
+    w = cube(2);
+  end Base;
+end Lib;
+
+model M
+  import mysq = Lib.sq;
+  import Lib.{cube};
+  import L = Lib;
+  import Lib.*;
+  extends Lib.Base;
+  Lib.Sub s;
+  Real a;
+  Real b;
+  Real c;
+  Len d;
+equation
+  a = mysq(time);
+  b = cube(time);
+  c = L.sq(time) + sin(time);
+  d = a;
+end M;
+"#;
+    let def = common::parse_source(source).unwrap();
+    let fclass = flatten(&def, Some("M")).unwrap();
+    let equations: Vec<String> = fclass.equations.iter().map(|eq| eq.to_string()).collect();
+    for expected in [
+        "w = Lib.cube(2)",
+        "s.v = Lib.sq(s.u) + Lib.sq(s.u) + Lib.sq(s.u)",
+        "a = Lib.sq(time)",
+        "b = Lib.cube(time)",
+        "c = Lib.sq(time) + sin(time)",
+    ] {
+        assert!(
+            equations.iter().any(|eq| eq == expected),
+            "{} not in {:?}",
+            expected,
+            equations
+        );
+    }
+    assert_eq!(fclass.components["d"].type_name.to_string(), "Len");
+
+    let result = common::compile_source(source, "M").unwrap();
+    assert!(result.is_balanced());
+}
+
+#[test]
+fn test_flatten_global_references() {
+    // `.P.f` is looked up at the top level, not in the enclosing package,
+    // and global references aren't prefixed with the component name
+    let source = r#"
+package P
+  function f
+    input Real x;
+    output Real y;
+  algorithm
+    y := 2 * x;
+  end f;
+end P;
+
+package Outer
+  package P
+    function f
+      input Real x;
+      output Real y;
+    algorithm
+      y := 5 * x;
+    end f;
+  end P;
+  model Sub
+    Real v;
+  equation
+    v = .P.f(time);
+  end Sub;
+  model M
+    Sub s;
+    Real x;
+    Real z;
+  equation
+    x = .P.f(time);
+    z = P.f(time);
+  end M;
+end Outer;
+"#;
+    let def = common::parse_source(source).unwrap();
+    let fclass = flatten(&def, Some("Outer.M")).unwrap();
+    let equations: Vec<String> = fclass.equations.iter().map(|eq| eq.to_string()).collect();
+    for expected in ["s.v = P.f(time)", "x = P.f(time)", "z = Outer.P.f(time)"] {
+        assert!(
+            equations.iter().any(|eq| eq == expected),
+            "{} not in {:?}",
+            expected,
+            equations
+        );
+    }
+
+    let result = common::compile_source(source, "Outer.M").unwrap();
+    let equations: Vec<String> = result.dae.fx.iter().map(|eq| eq.to_string()).collect();
+    for expected in ["s.v = 2 * time", "x = 2 * time", "z = 5 * time"] {
+        assert!(
+            equations.iter().any(|eq| eq == expected),
+            "{} not in {:?}",
+            expected,
+            equations
+        );
+    }
+}
+
+#[test]
+fn test_flatten_record_fields() {
+    use rumoca::ir::ast::Variability;
+
+    let source = r#"
+model RecordFields
+  record Point
+    Real x = 0;
+    Real y = 0;
+  end Point;
+  record Frame
+    Point origin;
+    Real angle = 0;
+  end Frame;
+  parameter Frame p(origin(x = 1), angle = 0.5);
+  parameter Frame p2 = p;
+  Frame q(origin = p.origin);
+  Real z;
+equation
+  q.angle = p2.angle;
+  z = q.origin.x + p.origin.y;
+end RecordFields;
+"#;
+    let def = common::parse_source(source).unwrap();
+    let fclass = flatten(&def, Some("RecordFields")).unwrap();
+
+    // The prefixes of a record apply to its fields
+    let x = &fclass.components["p.origin.x"];
+    assert!(matches!(x.variability, Variability::Parameter(_)));
+    assert_eq!(x.start.to_string(), "1");
+    assert!(matches!(
+        fclass.components["q.origin.y"].variability,
+        Variability::Empty
+    ));
+
+    // Bindings to records are split into the bindings of their fields
+    let initial: Vec<String> = fclass
+        .initial_equations
+        .iter()
+        .map(|eq| eq.to_string())
+        .collect();
+    let equations: Vec<String> = fclass.equations.iter().map(|eq| eq.to_string()).collect();
+    for expected in ["p2.origin.x = p.origin.x", "p2.angle = p.angle"] {
+        assert!(initial.iter().any(|eq| eq == expected), "{:?}", initial);
+    }
+    for expected in ["q.origin.x = p.origin.x", "q.origin.y = p.origin.y"] {
+        assert!(equations.iter().any(|eq| eq == expected), "{:?}", equations);
+    }
+
+    let result = common::compile_source(source, "RecordFields").unwrap();
+    assert!(result.is_balanced(), "{}", result.balance_status());
+}
+
+#[test]
+fn test_component_array_fields_in_expressions() {
+    use common::parse_source;
+
+    let source = r#"
+model Mass
+  Real w(start = 1);
+equation
+  der(w) = -w;
+end Mass;
+
+model Total
+  parameter Integer n = 3;
+  Mass m[n];
+  Real total;
+  Real first2;
+equation
+  total = sum(m.w);
+  first2 = sum(m[i].w for i in 1:2);
+end Total;
+"#;
+
+    let def = parse_source(source).expect("Parse failed");
+    let fclass = flatten(&def, Some("Total")).unwrap();
+    let equations: Vec<String> = fclass.equations.iter().map(|eq| eq.to_string()).collect();
+    assert!(
+        equations.contains(&"total = sum({m[1].w, m[2].w, m[3].w})".to_string()),
+        "{equations:#?}"
+    );
+    assert!(
+        equations.contains(&"first2 = sum({m[1].w, m[2].w})".to_string()),
+        "{equations:#?}"
+    );
+}
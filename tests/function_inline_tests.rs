@@ -0,0 +1,182 @@
+use rumoca::Compiler;
+
+const SOURCE: &str = r#"
+    model InlineTest
+        function sq
+            input Real x;
+            output Real y;
+        protected
+            Real t = 2 * x;
+        algorithm
+            y := t * x;
+        end sq;
+        function keep
+            input Real x;
+            output Real y;
+        algorithm
+            y := x + 1;
+            annotation(Inline=false);
+        end keep;
+        function sat
+            input Real x;
+            output Real y;
+        algorithm
+            if x > 1 then
+                y := 1;
+            else
+                y := x;
+            end if;
+            annotation(Inline=true, smoothOrder=1);
+        end sat;
+        function clip
+            input Real x;
+            output Real y;
+        algorithm
+            if x > 1 then
+                y := 1;
+            else
+                y := x;
+            end if;
+        end clip;
+        Real a, b, c, d;
+    equation
+        a = sq(time);
+        b = keep(time);
+        c = sat(time);
+        d = clip(time);
+    end InlineTest;
+"#;
+
+#[test]
+fn test_inline_intermediate_variables() {
+    let result = Compiler::new()
+        .model("InlineTest")
+        .compile_str(SOURCE, "inline_test.mo")
+        .expect("Failed to compile");
+    let dae = result.dae.to_pretty_string();
+
+    // Protected variables are substituted into the output expression
+    assert!(dae.contains("a = 2 * time * time;"), "{}", dae);
+}
+
+#[test]
+fn test_inline_annotations() {
+    let result = Compiler::new()
+        .model("InlineTest")
+        .compile_str(SOURCE, "inline_test.mo")
+        .expect("Failed to compile");
+    let dae = result.dae.to_pretty_string();
+
+    // Inline=false keeps the call
+    assert!(dae.contains("b = keep(time);"), "{}", dae);
+
+    // Inline=true turns the if-statement into an if-expression, and
+    // smoothOrder suppresses events of its relations
+    assert!(
+        dae.contains("c = if noEvent(time > 1) then 1 else time;"),
+        "{}",
+        dae
+    );
+
+    // Without Inline=true a body with control flow is kept as a call
+    assert!(dae.contains("d = clip(time);"), "{}", dae);
+}
+
+const DEFAULTS_SOURCE: &str = r#"
+    model DefaultArgs
+        function scale
+            input Real x;
+            input Real k = 2;
+            input Real offset = k / 2;
+            output Real y;
+        algorithm
+            y := k * x + offset;
+        end scale;
+        Real a, b, c;
+    equation
+        a = scale(time);
+        b = scale(time, 3);
+        c = scale(time, offset = 1);
+    end DefaultArgs;
+"#;
+
+#[test]
+fn test_inline_default_arguments() {
+    let result = Compiler::new()
+        .model("DefaultArgs")
+        .compile_str(DEFAULTS_SOURCE, "default_args.mo")
+        .expect("Failed to compile");
+    let dae = result.dae.to_pretty_string();
+
+    // Missing inputs take their defaults, which may use other inputs
+    assert!(dae.contains("a = 2 * time + 2 / 2;"), "{}", dae);
+    assert!(dae.contains("b = 3 * time + 3 / 2;"), "{}", dae);
+    // Named arguments bind by name
+    assert!(dae.contains("c = 2 * time + 1;"), "{}", dae);
+}
+
+#[test]
+fn test_invalid_function_call_arguments() {
+    let missing = DEFAULTS_SOURCE.replace("scale(time, 3)", "scale(k = 3)");
+    let err = Compiler::new()
+        .model("DefaultArgs")
+        .compile_str(&missing, "default_args.mo")
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("call of 'scale': missing argument 'x', which has no default"),
+        "{}",
+        err
+    );
+
+    let unknown = DEFAULTS_SOURCE.replace("offset = 1", "gain = 1");
+    let err = Compiler::new()
+        .model("DefaultArgs")
+        .compile_str(&unknown, "default_args.mo")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("no input named 'gain'"), "{}", err);
+}
+
+const NAMED_ARGS_SOURCE: &str = r#"
+    package P
+        function f
+            input Real x;
+            input Real eps = 2;
+            output Real y;
+        algorithm
+            y := x + eps;
+        end f;
+        model Sub
+            Real x;
+            Real a = time;
+        equation
+            x = f(x = a);
+        end Sub;
+        model NamedArgs
+            Real x, a, b, c;
+            Sub sub;
+        equation
+            a = time;
+            x = P.f(x = a);
+            b = f(a, eps = 3);
+            c = f(x = a, eps = 4);
+        end NamedArgs;
+    end P;
+"#;
+
+#[test]
+fn test_named_arguments_shadowing_names_in_scope() {
+    let result = Compiler::new()
+        .model("P.NamedArgs")
+        .compile_str(NAMED_ARGS_SOURCE, "named_args.mo")
+        .expect("Failed to compile");
+    let dae = result.dae.to_pretty_string();
+
+    // Argument names are inputs of the function, not the component `x` of
+    // the model, of an instance, or the built-in constant `eps`
+    assert!(dae.contains("x = a + 2;"), "{}", dae);
+    assert!(dae.contains("sub.x = sub.a + 2;"), "{}", dae);
+    assert!(dae.contains("b = a + 3;"), "{}", dae);
+    assert!(dae.contains("c = a + 4;"), "{}", dae);
+}
@@ -20,24 +20,31 @@
 //! - Code lenses
 //! - Call hierarchy
 //! - Document links
+//! - Workspace settings
 
 mod common;
 
 use lsp_types::{
-    CallHierarchyPrepareParams, CodeActionContext, CodeActionParams, CodeLensParams,
-    CompletionParams, CompletionTriggerKind, DocumentFormattingParams, DocumentLinkParams,
-    DocumentSymbolParams, FoldingRangeParams, FormattingOptions, GotoDefinitionParams,
-    HoverContents, HoverParams, InlayHintParams, Position, Range, ReferenceContext,
-    ReferenceParams, SemanticTokensParams, SignatureHelpParams, TextDocumentIdentifier,
-    TextDocumentPositionParams, Uri, WorkspaceSymbolParams,
+    CallHierarchyPrepareParams, CodeActionContext, CodeActionOrCommand, CodeActionParams,
+    CodeLensParams, CompletionParams, CompletionTriggerKind, DocumentFormattingParams,
+    DocumentLinkParams, DocumentSymbolParams, FoldingRangeParams, FormattingOptions,
+    GotoDefinitionParams, HoverContents, HoverParams, InlayHintParams, Position,
+    PrepareRenameResponse, Range, ReferenceContext, ReferenceParams, RenameParams,
+    SemanticTokensParams, SignatureHelpParams, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentPositionParams, Uri, WorkspaceSymbolParams,
 };
 
 use rumoca::lsp::{
-    WorkspaceState, compute_diagnostics, create_documents, get_semantic_token_legend,
+    LspSettings, WorkspaceState, compute_diagnostics, create_documents, get_semantic_token_legend,
     handle_code_action, handle_code_lens, handle_completion_workspace, handle_document_links,
-    handle_document_symbols, handle_folding_range, handle_formatting, handle_goto_definition,
-    handle_hover, handle_inlay_hints, handle_prepare_call_hierarchy, handle_references,
-    handle_semantic_tokens, handle_signature_help, handle_workspace_symbol,
+    handle_document_symbols, handle_folding_range, handle_formatting,
+    handle_formatting_with_settings, handle_goto_definition, handle_hover, handle_hover_workspace,
+    handle_inlay_hints, handle_prepare_call_hierarchy, handle_prepare_rename, handle_references,
+    handle_rename, handle_semantic_tokens, handle_signature_help, handle_workspace_symbol,
+};
+
+use rumoca::lsp::utils::{
+    LineIndex, apply_content_changes, positions_from_utf16, positions_to_utf16,
 };
 
 // Use common LSP test utilities
@@ -65,6 +72,50 @@ end Test;"#;
     );
 }
 
+#[test]
+fn test_diagnostics_non_boolean_condition() {
+    let uri = test_uri();
+    let text = "model Test\n  Real x;\n  Real y;\nequation\n  y = if x then 1 else 0;\n  der(x) = 1;\nend Test;";
+
+    let mut workspace = WorkspaceState::new();
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let diagnostic = diagnostics
+        .iter()
+        .find(|d| d.message == "If condition must be Boolean")
+        .unwrap_or_else(|| panic!("Expected a type error, got: {:?}", diagnostics));
+    assert_eq!(
+        diagnostic.severity,
+        Some(lsp_types::DiagnosticSeverity::ERROR)
+    );
+    assert_eq!(diagnostic.range.start.line, 4);
+}
+
+#[test]
+fn test_diagnostics_when_equations() {
+    let uri = test_uri();
+    let text = "model Test\n  Real x(start = 1);\n  discrete Real y;\n  discrete Real z;\nequation\n  der(x) = -x;\n  when x < 0.5 then\n    y = 1;\n    when x < 0.2 then\n      z = 1;\n    end when;\n  elsewhen x < 0.1 then\n    z = 2;\n  end when;\nend Test;";
+
+    let mut workspace = WorkspaceState::new();
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let when_errors: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.code == Some(lsp_types::NumberOrString::String("when-equation".into())))
+        .map(|d| (d.range.start.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        when_errors,
+        [
+            (
+                11,
+                "the branches of a when-equation must assign the same variables, but this elsewhen-branch doesn't assign 'y' and assigns 'z', which the when-branch doesn't"
+            ),
+            (8, "when-equations and when-statements cannot be nested"),
+        ],
+        "{:?}",
+        diagnostics
+    );
+}
+
 #[test]
 fn test_diagnostics_syntax_error() {
     let uri = test_uri();
@@ -78,6 +129,99 @@ fn test_diagnostics_syntax_error() {
     );
 }
 
+#[test]
+fn test_diagnostics_balance_cache() {
+    let uri = test_uri();
+    let text = "package P\n  model A\n    Real x;\n  equation\n    der(x) = -x;\n  end A;\n  model C\n    Real y;\n  end C;\nend P;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    compute_diagnostics(&uri, text, &mut workspace);
+    assert!(workspace.get_balance(&uri, "P.A").unwrap().is_balanced);
+    assert!(!workspace.get_balance(&uri, "P.C").unwrap().is_balanced);
+
+    // Editing C recomputes its balance
+    let text = text.replace("Real y;", "Real y;\n  equation\n    y = 1;");
+    workspace.update_document(uri.clone(), text.clone());
+    compute_diagnostics(&uri, &text, &mut workspace);
+    assert!(workspace.get_balance(&uri, "P.A").unwrap().is_balanced);
+    assert!(workspace.get_balance(&uri, "P.C").unwrap().is_balanced);
+
+    // Balances are hidden while the document doesn't parse
+    let broken = text.replace("Real x;", "Real x");
+    workspace.update_document(uri.clone(), broken.clone());
+    compute_diagnostics(&uri, &broken, &mut workspace);
+    assert!(workspace.get_balance(&uri, "P.A").is_none());
+
+    // and shown again once it does, without the balances of removed classes
+    let text = text
+        .replace("  model C\n", "  model D\n")
+        .replace("end C;", "end D;");
+    workspace.update_document(uri.clone(), text.clone());
+    compute_diagnostics(&uri, &text, &mut workspace);
+    assert!(workspace.get_balance(&uri, "P.A").is_some());
+    assert!(workspace.get_balance(&uri, "P.D").unwrap().is_balanced);
+    assert!(workspace.get_balance(&uri, "P.C").is_none());
+}
+
+#[test]
+fn test_diagnostics_compile_stats() {
+    let uri = test_uri();
+    let text = "package P\n  model A\n    Real x;\n  equation\n    der(x) = -x;\n  end A;\n  model C\n    Real y;\n  end C;\nend P;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    compute_diagnostics(&uri, text, &mut workspace);
+    let stats = workspace.compile_stats(&uri).unwrap();
+    assert_eq!(stats.uri, uri);
+    assert_eq!(stats.classes, 3);
+    assert_eq!((stats.compiled_models, stats.cached_models), (2, 0));
+    assert!(stats.total_ms >= stats.parse_ms + stats.flatten_ms + stats.dae_ms);
+    assert_eq!(stats.diagnostics.errors, 0);
+
+    // The notification params use camelCase names
+    let json = serde_json::to_value(stats).unwrap();
+    assert!(json["flattenMs"].is_number() && json["diagnostics"]["warnings"].is_number());
+
+    // Unchanged models are reused
+    compute_diagnostics(&uri, text, &mut workspace);
+    let stats = workspace.compile_stats(&uri).unwrap();
+    assert_eq!((stats.compiled_models, stats.cached_models), (0, 2));
+}
+
+#[test]
+fn test_diagnostics_resource_limit() {
+    let uri = test_uri();
+    let text = "package P\n  model Huge\n    Real x;\n  equation\n    for i in 1:100000000 loop\n      x = i;\n    end for;\n  end Huge;\nend P;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let limit: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| {
+            d.code
+                == Some(lsp_types::NumberOrString::String(
+                    "resource-limit".to_string(),
+                ))
+        })
+        .collect();
+    assert_eq!(limit.len(), 1, "{diagnostics:?}");
+    assert_eq!(limit[0].range.start, Position::new(1, 8));
+    assert!(
+        limit[0]
+            .message
+            .starts_with("Analysis of 'P.Huge' stopped: test.mo:5:9: for-equation index 'i'"),
+        "{}",
+        limit[0].message
+    );
+
+    // Models exceeding a limit aren't reused, as the limits may change
+    compute_diagnostics(&uri, text, &mut workspace);
+    let stats = workspace.compile_stats(&uri).unwrap();
+    assert_eq!((stats.compiled_models, stats.cached_models), (1, 0));
+}
+
 #[test]
 fn test_diagnostics_inherited_variables() {
     // Test that inherited variables from extends clause are recognized
@@ -381,6 +525,28 @@ end Test;"#;
     assert!(result.is_some(), "Expected hover information for variable");
 }
 
+#[test]
+fn test_hover_after_non_ascii_text() {
+    let uri = test_uri();
+    // Positions count characters, so the multi-byte '°' counts as one column
+    let text = "model Test\r\n  /* °C */ Real x;\r\nend Test;";
+
+    let documents = create_documents(&uri, text);
+    let params = HoverParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position {
+                line: 1,
+                character: 16,
+            }, // "x"
+        },
+        work_done_progress_params: Default::default(),
+    };
+
+    let result = handle_hover(&documents, params);
+    assert!(result.is_some(), "Expected hover information for variable");
+}
+
 #[test]
 fn test_hover_on_inherited_variable() {
     let uri = test_uri();
@@ -920,6 +1086,133 @@ end Test;"#;
     }
 }
 
+// ============================================================================
+// Quoted Identifier Tests
+// ============================================================================
+
+const QUOTED_MODEL: &str = r#"model Sub
+  Real 'a b';
+equation
+  'a b' = 1;
+end Sub;
+
+model Test
+  Sub 'my sub';
+  Real 'my var+1';
+equation
+  der('my var+1') = -'my var+1' + 'my sub'.'a b';
+end Test;"#;
+
+fn position_params(uri: &Uri, line: u32, character: u32) -> TextDocumentPositionParams {
+    TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        position: Position { line, character },
+    }
+}
+
+#[test]
+fn test_references_quoted_identifier() {
+    let uri = test_uri();
+    let documents = create_documents(&uri, QUOTED_MODEL);
+    let params = ReferenceParams {
+        // Inside "'my var+1'", between "var" and "+1"
+        text_document_position: position_params(&uri, 8, 14),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext {
+            include_declaration: true,
+        },
+    };
+
+    let refs = handle_references(&documents, params).unwrap();
+    let ranges: Vec<_> = refs
+        .iter()
+        .map(|r| {
+            (
+                r.range.start.line,
+                r.range.start.character,
+                r.range.end.character,
+            )
+        })
+        .collect();
+    assert_eq!(ranges, [(8, 7, 17), (10, 6, 16), (10, 21, 31)]);
+}
+
+#[test]
+fn test_rename_quoted_identifier() {
+    let uri = test_uri();
+    let documents = create_documents(&uri, QUOTED_MODEL);
+
+    let prepared = handle_prepare_rename(&documents, position_params(&uri, 8, 14));
+    let Some(PrepareRenameResponse::Range(range)) = prepared else {
+        panic!("expected a range, got {prepared:?}");
+    };
+    assert_eq!(
+        (range.start, range.end),
+        (Position::new(8, 7), Position::new(8, 17))
+    );
+
+    // A name that isn't a valid identifier is quoted
+    let params = RenameParams {
+        text_document_position: position_params(&uri, 1, 9),
+        new_name: "the b".to_string(),
+        work_done_progress_params: Default::default(),
+    };
+    let edit = handle_rename(&documents, params).unwrap();
+    let edits = &edit.changes.unwrap()[&uri];
+    assert_eq!(edits.len(), 3);
+    assert!(edits.iter().all(|e| e.new_text == "'the b'"));
+    assert!(
+        edits
+            .iter()
+            .any(|e| e.range.start == Position::new(10, 43) && e.range.end == Position::new(10, 48))
+    );
+}
+
+#[test]
+fn test_rename_keeps_global_reference_dot() {
+    let uri = test_uri();
+    let text = "package P\n  constant Real c = 2;\nend P;\n\nmodel M\n  Real x;\nequation\n  x = .P.c * time;\nend M;\n";
+    let documents = create_documents(&uri, text);
+    let params = RenameParams {
+        text_document_position: position_params(&uri, 1, 16),
+        new_name: "k".to_string(),
+        work_done_progress_params: Default::default(),
+    };
+    let edit = handle_rename(&documents, params).unwrap();
+    let edits = &edit.changes.unwrap()[&uri];
+    // Only the name is replaced, after the leading dot of `.P.c`
+    assert!(
+        edits
+            .iter()
+            .any(|e| e.range.start == Position::new(7, 9) && e.range.end == Position::new(7, 10)),
+        "{:?}",
+        edits
+    );
+}
+
+#[test]
+fn test_completion_quoted_member_access() {
+    let uri = test_uri();
+    let mut workspace = WorkspaceState::new();
+    workspace.update_document(uri.clone(), QUOTED_MODEL.to_string());
+    let params = CompletionParams {
+        // After "'my sub'."
+        text_document_position: position_params(&uri, 10, 43),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: None,
+    };
+
+    let Some(lsp_types::CompletionResponse::Array(items)) =
+        handle_completion_workspace(&mut workspace, params)
+    else {
+        panic!("expected completion items");
+    };
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"'a b'"), "{:?}", labels);
+}
+
 // ============================================================================
 // Folding Range Tests
 // ============================================================================
@@ -1008,6 +1301,180 @@ end Test;"#;
     assert!(result.is_some());
 }
 
+#[test]
+fn test_code_action_from_lint_fix() {
+    let uri = test_uri();
+    let text = "model Test\n  Real x;\n  Real y;\nequation\n  y = 1;\nend Test;";
+
+    let documents = create_documents(&uri, text);
+    let line_range = |line| Range {
+        start: Position { line, character: 0 },
+        end: Position { line, character: 0 },
+    };
+    let params = |range| CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range,
+        context: CodeActionContext {
+            diagnostics: vec![],
+            only: None,
+            trigger_kind: None,
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+
+    let actions = handle_code_action(&documents, params(line_range(1))).unwrap();
+    let action = actions
+        .iter()
+        .find_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) if action.title == "Rename to '_x'" => {
+                Some(action)
+            }
+            _ => None,
+        })
+        .expect("a quick fix for the unused variable");
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range.start, Position::new(1, 7));
+    assert_eq!(edits[0].new_text, "_");
+
+    // No fixable lint message on the line of y
+    assert!(handle_code_action(&documents, params(line_range(2))).is_none());
+}
+
+#[test]
+fn test_instance_diagnostics_and_removal() {
+    let uri = test_uri();
+    let text = "package P\n  connector Pin\n    Real v;\n    flow Real i;\n  end Pin;\n  model R\n    parameter Real r = 1;\n    Pin p, n;\n  equation\n    p.v - n.v = r * p.i;\n    p.i + n.i = 0;\n  end R;\n  model C\n    parameter Real gain = 2 \"Not used\";\n    R r1;\n    R r2;\n    R r3;\n  equation\n    connect(r1.p, r2.p);\n    connect(r1.n, r2.n);\n    connect(r3.p, r2.p);\n  end C;\nend P;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let found = |code: &str| -> Vec<_> {
+        diagnostics
+            .iter()
+            .filter(|d| d.code == Some(lsp_types::NumberOrString::String(code.to_string())))
+            .collect()
+    };
+
+    // r is used by R, gain by no model
+    let unused = found("unused-parameter");
+    assert_eq!(unused.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(
+        unused[0].message,
+        "Parameter 'gain' is not used in any flattened model"
+    );
+    assert_eq!(unused[0].range.start, Position::new(13, 19));
+
+    let unconnected = found("unconnected-connector");
+    assert_eq!(unconnected.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(unconnected[0].message, "Connector 'r3.n' is not connected");
+    assert_eq!(unconnected[0].range.start, Position::new(16, 6));
+
+    // Removing the parameter removes its declaration line
+    let documents = create_documents(&uri, text);
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range: unused[0].range,
+        context: CodeActionContext {
+            diagnostics: vec![unused[0].clone()],
+            only: None,
+            trigger_kind: None,
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let actions = handle_code_action(&documents, params).unwrap();
+    let action = actions
+        .iter()
+        .find_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action)
+                if action.title == "Remove unused parameter 'gain'" =>
+            {
+                Some(action)
+            }
+            _ => None,
+        })
+        .expect("a quick fix for the unused parameter");
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range.start, Position::new(13, 0));
+    assert_eq!(edits[0].range.end, Position::new(14, 0));
+
+    // A component none of whose connectors is connected can be removed
+    let text = text.replace("    connect(r3.p, r2.p);\n", "");
+    workspace.update_document(uri.clone(), text.clone());
+    let diagnostics = compute_diagnostics(&uri, &text, &mut workspace);
+    let unconnected = diagnostics
+        .iter()
+        .find(|d| {
+            d.code
+                == Some(lsp_types::NumberOrString::String(
+                    "unconnected-connector".to_string(),
+                ))
+        })
+        .unwrap();
+    assert_eq!(
+        unconnected.message,
+        "None of the connectors of 'r3' is connected: p, n"
+    );
+    let documents = create_documents(&uri, &text);
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range: unconnected.range,
+        context: CodeActionContext {
+            diagnostics: vec![unconnected.clone()],
+            only: None,
+            trigger_kind: None,
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let actions = handle_code_action(&documents, params).unwrap();
+    assert!(actions.iter().any(|action| matches!(
+        action,
+        CodeActionOrCommand::CodeAction(action) if action.title == "Remove unconnected component 'r3'"
+    )));
+}
+
+#[test]
+fn test_causality_diagnostics() {
+    let uri = test_uri();
+    let text = "package P\n  connector RealInput = input Real;\n  connector RealOutput = output Real;\n  block Gain\n    RealInput u;\n    RealOutput y;\n  equation\n    y = 2 * u;\n  end Gain;\n  model Loop\n    Gain a, b, c;\n  equation\n    connect(a.y, c.u);\n    connect(b.y, c.u);\n    connect(a.y, b.u);\n  end Loop;\nend P;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let messages = |code: &str| -> Vec<(Position, &str)> {
+        diagnostics
+            .iter()
+            .filter(|d| d.code == Some(lsp_types::NumberOrString::String(code.to_string())))
+            .map(|d| (d.range.start, d.message.as_str()))
+            .collect()
+    };
+
+    assert_eq!(
+        messages("connector-causality"),
+        [
+            (
+                Position::new(10, 15),
+                "Input 'c.u' is connected to more than one output: 'a.y', 'b.y'"
+            ),
+            (
+                Position::new(10, 9),
+                "Input 'a.u' is not connected and has no binding"
+            ),
+        ],
+        "{:#?}",
+        diagnostics
+    );
+    // The unconnected input is only reported as a causality error
+    assert_eq!(
+        messages("unconnected-connector"),
+        [(Position::new(10, 15), "Connector 'c.y' is not connected")]
+    );
+}
+
 // ============================================================================
 // Inlay Hints Tests
 // ============================================================================
@@ -1264,6 +1731,271 @@ end Derived;"#;
     assert!(result.is_some());
 }
 
+#[test]
+fn test_code_lens_balance_hidden_by_settings() {
+    let uri = test_uri();
+    let text = r#"model Test
+  Real x;
+equation
+  der(x) = 1;
+end Test;"#;
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    compute_diagnostics(&uri, text, &mut workspace);
+
+    let params = || CodeLensParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let has_balance_lens = |lenses: &[lsp_types::CodeLens]| {
+        lenses.iter().any(|lens| {
+            lens.command
+                .as_ref()
+                .is_some_and(|c| c.title.contains("equations"))
+        })
+    };
+
+    let lenses = handle_code_lens(&workspace, params()).unwrap();
+    assert!(has_balance_lens(&lenses));
+
+    let settings =
+        LspSettings::from_value(&serde_json::json!({ "rumoca": { "balanceLens": false } }))
+            .unwrap();
+    workspace.set_settings(settings);
+    let lenses = handle_code_lens(&workspace, params()).unwrap();
+    assert!(!has_balance_lens(&lenses));
+}
+
+#[test]
+fn test_code_lens_dae_index() {
+    let uri = test_uri();
+    let text = r#"model Pendulum
+  parameter Real L = 1;
+  Real x(start = 1);
+  Real y;
+  Real vx;
+  Real vy;
+  Real lambda;
+equation
+  der(x) = vx;
+  der(y) = vy;
+  der(vx) = -lambda * x;
+  der(vy) = -lambda * y - 9.81;
+  x^2 + y^2 = L^2;
+end Pendulum;
+
+model Decay
+  Real x(start = 1);
+equation
+  der(x) = -x;
+end Decay;"#;
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+
+    // The position constraint is differentiated twice
+    let high_index: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.code == Some(lsp_types::NumberOrString::String("high-index".to_string())))
+        .collect();
+    let constraint = high_index
+        .iter()
+        .find(|d| d.range.start.line == 12)
+        .expect("diagnostic on the constraint");
+    assert_eq!(
+        constraint.message,
+        "'Pendulum' is an index-3 DAE: index reduction differentiates this equation 2 times"
+    );
+    assert_eq!(
+        constraint.severity,
+        Some(lsp_types::DiagnosticSeverity::INFORMATION)
+    );
+
+    let params = CodeLensParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let titles: Vec<String> = handle_code_lens(&workspace, params)
+        .unwrap()
+        .into_iter()
+        .filter_map(|lens| lens.command.map(|c| c.title))
+        .collect();
+    assert!(
+        titles.iter().any(|t| t.ends_with("[✓] index-3 DAE")),
+        "{titles:?}"
+    );
+    assert!(
+        titles
+            .iter()
+            .any(|t| t.starts_with("index reduction: x ^ 2 + y ^ 2 = L ^ 2 (2×)")),
+        "{titles:?}"
+    );
+    assert!(titles.iter().any(|t| t.ends_with("[✓] ODE")), "{titles:?}");
+}
+
+// ============================================================================
+// Settings Tests
+// ============================================================================
+
+#[test]
+fn test_lint_diagnostics_from_settings() {
+    let uri = test_uri();
+    let text = r#"model Test
+  Real x;
+equation
+  x = 42.5;
+end Test;"#;
+
+    let is_lint = |d: &lsp_types::Diagnostic| d.source.as_deref() == Some("rumoca-lint");
+
+    // Lint diagnostics are off by default
+    let mut workspace = WorkspaceState::new();
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    assert!(!diagnostics.iter().any(is_lint));
+
+    let settings =
+        LspSettings::from_value(&serde_json::json!({ "lint": { "enabled": true } })).unwrap();
+    workspace.set_settings(settings);
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    assert!(
+        diagnostics.iter().any(|d| is_lint(d)
+            && d.code == Some(lsp_types::NumberOrString::String("magic-number".into()))),
+        "{:?}",
+        diagnostics
+    );
+
+    let settings = LspSettings::from_value(&serde_json::json!({
+        "lint": { "enabled": true, "disabledRules": ["magic-number"] }
+    }))
+    .unwrap();
+    workspace.set_settings(settings);
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    assert!(
+        !diagnostics.iter().any(|d| is_lint(d)
+            && d.code == Some(lsp_types::NumberOrString::String("magic-number".into())))
+    );
+}
+
+#[test]
+fn test_diagnostic_suppressions() {
+    let uri = test_uri();
+    let text = r#"model Test
+  Real unused; // rumoca-ignore: unused-variable
+  Real other;
+  Real x;
+equation
+  x = 42.5 annotation(__rumoca_ignore = {"magic-number", "no-such-rule"});
+end Test;"#;
+
+    let has_code = |d: &lsp_types::Diagnostic, code: &str| {
+        d.code == Some(lsp_types::NumberOrString::String(code.into()))
+    };
+
+    let mut workspace = WorkspaceState::new();
+    let settings =
+        LspSettings::from_value(&serde_json::json!({ "lint": { "enabled": true } })).unwrap();
+    workspace.set_settings(settings);
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+
+    // Only the unsuppressed variable is reported as unused
+    let unused: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| has_code(d, "unused-variable") && d.source.as_deref() == Some("rumoca"))
+        .collect();
+    assert_eq!(unused.len(), 1, "{:?}", diagnostics);
+    assert!(unused[0].message.contains("'other'"));
+
+    assert!(!diagnostics.iter().any(|d| has_code(d, "magic-number")));
+
+    // Unknown codes are reported once, at the code
+    let unknown: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| has_code(d, "unknown-suppression"))
+        .collect();
+    assert_eq!(unknown.len(), 1, "{:?}", diagnostics);
+    assert_eq!(unknown[0].range.start, Position::new(5, 57));
+    assert!(unknown[0].message.contains("'no-such-rule'"));
+}
+
+#[test]
+fn test_division_by_zero_diagnostic() {
+    let uri = test_uri();
+    let text = r#"model Test
+  parameter Real R = 0;
+  Real i, v;
+equation
+  v = 1;
+  i = v / R;
+end Test;"#;
+
+    let is_division = |d: &lsp_types::Diagnostic| d.message.contains("division by zero");
+
+    // Reported without lint settings, at the division operator
+    let mut workspace = WorkspaceState::new();
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    let division: Vec<_> = diagnostics.iter().filter(|d| is_division(d)).collect();
+    assert_eq!(division.len(), 1, "{:?}", diagnostics);
+    assert_eq!(
+        division[0].severity,
+        Some(lsp_types::DiagnosticSeverity::WARNING)
+    );
+    assert_eq!(division[0].range.start.line, 5);
+    assert!(division[0].message.contains("parameter 'R' defaults to 0"));
+
+    // Not reported twice when lint diagnostics are enabled
+    let settings =
+        LspSettings::from_value(&serde_json::json!({ "lint": { "enabled": true } })).unwrap();
+    workspace.set_settings(settings);
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    assert_eq!(diagnostics.iter().filter(|d| is_division(d)).count(), 1);
+}
+
+#[test]
+fn test_diagnostics_with_bom_and_crlf() {
+    let uri = test_uri();
+    let text = "\u{feff}model Test\r\n  Real x;\r\nequation\r\n  der(x) = 1;\r\nend Test;\r\n";
+
+    let mut workspace = WorkspaceState::new();
+    let diagnostics = compute_diagnostics(&uri, text, &mut workspace);
+    assert!(
+        !diagnostics
+            .iter()
+            .any(|d| d.severity == Some(lsp_types::DiagnosticSeverity::ERROR)),
+        "{:?}",
+        diagnostics
+    );
+}
+
+#[test]
+fn test_formatting_indent_from_settings() {
+    let uri = test_uri();
+    let text = "model Test\nReal x;\nend Test;";
+
+    let documents = create_documents(&uri, text);
+    let params = DocumentFormattingParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        options: FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        },
+        work_done_progress_params: Default::default(),
+    };
+    let settings =
+        LspSettings::from_value(&serde_json::json!({ "format": { "indentSize": 4 } })).unwrap();
+
+    let edits = handle_formatting_with_settings(&documents, params, &settings).unwrap();
+    assert!(
+        edits[0].new_text.contains("\n    Real x;"),
+        "{}",
+        edits[0].new_text
+    );
+}
+
 // ============================================================================
 // Call Hierarchy Tests
 // ============================================================================
@@ -1423,6 +2155,54 @@ end TestFunction;"#;
     );
 }
 
+#[test]
+fn test_workspace_persisted_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("ws");
+    std::fs::create_dir(&root).unwrap();
+    let file = root.join("Tank.mo");
+    std::fs::write(&file, "model Tank\n  Real level;\nend Tank;\n").unwrap();
+    let index = dir.path().join("index.json");
+
+    // First session: full indexing writes the persisted index
+    let mut ws = WorkspaceState::new();
+    ws.set_index_path(index.clone());
+    ws.initialize(vec![root.clone()], vec![]);
+    assert!(ws.lookup_symbol("Tank.level").is_some());
+    assert!(index.exists());
+
+    // Second session: symbols are available before any file is parsed
+    let mut ws = WorkspaceState::new();
+    ws.set_index_path(index.clone());
+    assert_eq!(ws.initialize_from_index(vec![root.clone()], vec![]), 1);
+    assert!(ws.lookup_symbol("Tank.level").is_some());
+    assert!(ws.get_parsed_ast_by_name("Tank").is_none());
+
+    // Reconciling replaces stale entries
+    std::fs::write(&file, "model Tank\n  Real volume;\nend Tank;\n").unwrap();
+    let files = rumoca::lsp::index_cache::index_files(&ws.files_to_index());
+    ws.apply_indexed_files(files);
+    assert!(ws.lookup_symbol("Tank.level").is_none());
+    assert!(ws.lookup_symbol("Tank.volume").is_some());
+
+    // Diagnostics of unchanged documents are restored in the next session
+    let uri: Uri = format!("file://{}", file.display()).parse().unwrap();
+    let text = std::fs::read_to_string(&file).unwrap();
+    ws.open_document(uri.clone(), text.clone());
+    let diagnostics = compute_diagnostics(&uri, &text, &mut ws);
+    ws.save_index();
+
+    let mut ws = WorkspaceState::new();
+    ws.set_index_path(index);
+    ws.initialize_from_index(vec![root], vec![]);
+    assert_eq!(ws.cached_diagnostics(&uri, &text), Some(diagnostics));
+    assert!(ws.get_balance(&uri, "Tank").is_some());
+    assert!(
+        ws.cached_diagnostics(&uri, "model Tank end Tank;")
+            .is_none()
+    );
+}
+
 // ============================================================================
 // Edge Cases and Error Handling Tests
 // ============================================================================
@@ -1966,3 +2746,287 @@ end Main;"#;
         );
     }
 }
+
+// ============================================================================
+// Position Encoding Tests
+// ============================================================================
+
+/// A model whose second line has an emoji (two UTF-16 code units) and an accent
+/// (one code unit) before a quoted identifier
+const NON_ASCII_MODEL: &str =
+    "model Test\n  /* 😀 é */ Real 'v 1' \"vélocité\";\n  Real x = 'v 1';\nend Test;";
+
+#[test]
+fn test_line_index_utf16() {
+    let index = LineIndex::new(NON_ASCII_MODEL);
+    let pos = |line, character| Position { line, character };
+
+    // The quoted identifier starts at character 17, or UTF-16 column 18
+    assert_eq!(index.to_utf16(pos(1, 17)), pos(1, 18));
+    assert_eq!(index.from_utf16(pos(1, 18)), pos(1, 17));
+    // A column inside the emoji's surrogate pair maps past it
+    assert_eq!(index.from_utf16(pos(1, 6)), pos(1, 6));
+    // ASCII lines and columns past the end of a line are unchanged
+    assert_eq!(index.to_utf16(pos(2, 7)), pos(2, 7));
+    assert_eq!(index.to_utf16(pos(1, 30)), pos(1, 31));
+    assert_eq!(index.from_utf16(pos(9, 3)), pos(9, 3));
+}
+
+fn find_symbol<'a>(
+    symbols: &'a [lsp_types::DocumentSymbol],
+    name: &str,
+) -> Option<&'a lsp_types::DocumentSymbol> {
+    symbols.iter().find_map(|s| {
+        if s.name == name {
+            Some(s)
+        } else {
+            find_symbol(s.children.as_deref().unwrap_or_default(), name)
+        }
+    })
+}
+
+#[test]
+fn test_positions_to_utf16() {
+    let uri = test_uri();
+    let documents = create_documents(&uri, NON_ASCII_MODEL);
+    let params = DocumentSymbolParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+
+    let result = handle_document_symbols(&documents, params).unwrap();
+    let mut value = serde_json::to_value(result).unwrap();
+    positions_to_utf16(&mut value, &documents, Some(&uri));
+
+    let symbols: lsp_types::DocumentSymbolResponse = serde_json::from_value(value).unwrap();
+    let lsp_types::DocumentSymbolResponse::Nested(symbols) = symbols else {
+        panic!("Expected nested document symbols");
+    };
+    let quoted =
+        find_symbol(&symbols, "'v 1'").expect("Expected a symbol for the quoted identifier");
+    assert_eq!(quoted.selection_range.start, Position::new(1, 18));
+    assert_eq!(quoted.selection_range.end, Position::new(1, 23));
+}
+
+#[test]
+fn test_positions_from_utf16() {
+    let uri = test_uri();
+    let text = "model Test\n  /* 😀 */ Real x;\nend Test;";
+    let documents = create_documents(&uri, text);
+
+    // The client points at "x" in UTF-16 code units
+    let mut params = serde_json::json!({
+        "textDocument": { "uri": uri.as_str() },
+        "position": { "line": 1, "character": 16 },
+    });
+    positions_from_utf16(&mut params, &documents);
+    assert_eq!(params["position"]["character"], 15);
+
+    let params: HoverParams = serde_json::from_value(params).unwrap();
+    let result = handle_hover(&documents, params);
+    assert!(result.is_some(), "Expected hover information for variable");
+}
+
+/// A content change replacing a range, as clients send with incremental sync
+fn range_change(start: Position, end: Position, text: &str) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(Range { start, end }),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn test_incremental_changes() {
+    let mut text = "model Test\r\n  /* 😀 */ Real x;\r\nend Test;".to_string();
+    let changes = vec![
+        // "x" is at UTF-16 column 16, after the emoji's surrogate pair
+        range_change(Position::new(1, 16), Position::new(1, 17), "'é'"),
+        // Columns past the end of a line stop before its line ending
+        range_change(Position::new(1, 19), Position::new(1, 99), "; // ok"),
+        // Each change applies to the text left by the previous ones
+        range_change(Position::new(2, 0), Position::new(2, 3), "end"),
+        range_change(Position::new(0, 10), Position::new(0, 10), " \"doc\""),
+    ];
+    apply_content_changes(&mut text, changes);
+    assert_eq!(
+        text,
+        "model Test \"doc\"\r\n  /* 😀 */ Real 'é'; // ok\r\nend Test;"
+    );
+
+    // A change without a range replaces the whole text
+    let full = TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "model M\nend M;".to_string(),
+    };
+    apply_content_changes(&mut text, vec![full]);
+    assert_eq!(text, "model M\nend M;");
+}
+
+#[test]
+fn test_incremental_changes_fuzz() {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const PIECES: &[&str] = &["a", "Real", " ", "é", "😀", "\n", "\r\n", "'q'", "∂x"];
+
+    /// LSP position of a character offset of a text, in UTF-16 code units
+    fn position(chars: &[char], offset: usize) -> Position {
+        let line_start = chars[..offset]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1);
+        let line = chars[..offset].iter().filter(|&&c| c == '\n').count();
+        let character: usize = chars[line_start..offset]
+            .iter()
+            .map(|c| c.len_utf16())
+            .sum();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// A random character offset, which doesn't split a "\r\n"
+    fn offset(rng: &mut StdRng, chars: &[char]) -> usize {
+        let offset = rng.gen_range(0..=chars.len());
+        if offset > 0 && chars[offset - 1] == '\r' && chars.get(offset) == Some(&'\n') {
+            offset - 1
+        } else {
+            offset
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    for _ in 0..200 {
+        let mut expected: Vec<char> = Vec::new();
+        let mut text = String::new();
+        for _ in 0..10 {
+            // Several changes per notification, like a multi-cursor edit
+            let mut changes = Vec::new();
+            for _ in 0..rng.gen_range(1..4) {
+                let (a, b) = (offset(&mut rng, &expected), offset(&mut rng, &expected));
+                let (start, end) = (a.min(b), a.max(b));
+                let insert: String = (0..rng.gen_range(0..4))
+                    .map(|_| PIECES[rng.gen_range(0..PIECES.len())])
+                    .collect();
+                let (start_pos, end_pos) = (position(&expected, start), position(&expected, end));
+                expected.splice(start..end, insert.chars());
+                changes.push(range_change(start_pos, end_pos, &insert));
+            }
+            apply_content_changes(&mut text, changes);
+            assert_eq!(text, expected.iter().collect::<String>());
+        }
+    }
+}
+
+#[test]
+fn test_semantic_tokens_utf16() {
+    let uri = test_uri();
+    let documents = create_documents(&uri, NON_ASCII_MODEL);
+    let params = SemanticTokensParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+
+    let Some(lsp_types::SemanticTokensResult::Tokens(tokens)) =
+        handle_semantic_tokens(&documents, params)
+    else {
+        panic!("Expected semantic tokens");
+    };
+
+    // Decode the absolute positions of the tokens on the second line
+    let mut line = 0;
+    let mut start = 0;
+    let mut second_line = Vec::new();
+    for token in &tokens.data {
+        if token.delta_line > 0 {
+            start = 0;
+        }
+        line += token.delta_line;
+        start += token.delta_start;
+        if line == 1 {
+            second_line.push((start, token.length));
+        }
+    }
+    assert!(
+        second_line.contains(&(18, 5)),
+        "Expected the quoted identifier at UTF-16 column 18, got: {:?}",
+        second_line
+    );
+}
+
+#[test]
+fn test_hover_equation_blocks() {
+    let uri = test_uri();
+    let text = "model Loop\n  Real x, a, b;\nequation\n  der(x) = -x + a;\n  a + b = x;\n  a - b = 1;\nend Loop;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let hover = |workspace: &mut WorkspaceState, line: u32, character: u32| {
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+        };
+        handle_hover_workspace(workspace, params).map(|hover| match hover.contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("Expected Markup hover contents"),
+        })
+    };
+
+    // Nothing to show before the class was compiled
+    assert_eq!(hover(&mut workspace, 4, 8), None);
+    compute_diagnostics(&uri, text, &mut workspace);
+
+    // Hovering the '=' of an equation of the loop
+    let value = hover(&mut workspace, 4, 8).unwrap();
+    assert!(value.starts_with("**BLT** (`Loop`)"), "{}", value);
+    assert!(
+        value.contains("in block 1 of 2, an algebraic loop of 2 equations"),
+        "{}",
+        value
+    );
+
+    // Hovering a variable of an equation shows both
+    let value = hover(&mut workspace, 3, 12).unwrap();
+    assert!(value.starts_with("**Loop.x**"), "{}", value);
+    assert!(
+        value.ends_with("- Solved for `der(x)` in block 2 of 2"),
+        "{}",
+        value
+    );
+
+    // Declarations are not equations
+    let value = hover(&mut workspace, 1, 7).unwrap();
+    assert!(!value.contains("BLT"), "{}", value);
+}
+
+#[test]
+fn test_hover_equation_description() {
+    let uri = test_uri();
+    let text = "model M\n  Real x, v[2];\nequation\n  der(x) = -x \"decay\";\n  for i in 1:2 loop\n    v[i] = i \"element\";\n  end for;\nend M;\n";
+
+    let mut workspace = WorkspaceState::new();
+    workspace.open_document(uri.clone(), text.to_string());
+    let mut hover = |line: u32, character: u32| {
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+        };
+        handle_hover_workspace(&mut workspace, params).map(|hover| match hover.contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("Expected Markup hover contents"),
+        })
+    };
+
+    // Hovering the '=' of an equation, and a variable of an equation in a for-equation
+    assert_eq!(hover(3, 9).as_deref(), Some("*decay*"));
+    let value = hover(5, 4).unwrap();
+    assert!(value.ends_with("*element*"), "{}", value);
+}
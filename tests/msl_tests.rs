@@ -750,7 +750,7 @@ fn export_failed_models_to_json(results: &CombinedTestResults, output_path: &Pat
     }
 
     let mut error_summary: Vec<_> = error_counts.into_iter().collect();
-    error_summary.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by count descending
+    error_summary.sort_by_key(|e| std::cmp::Reverse(e.1)); // Sort by count descending
 
     let report = serde_json::json!({
         "timestamp": std::time::SystemTime::now()
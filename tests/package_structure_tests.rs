@@ -128,3 +128,66 @@ fn test_modelica_path_lookup() {
         }
     }
 }
+
+#[test]
+fn test_modelica_uri_resources() {
+    use rumoca::{DiagnosticCode, Error};
+
+    let libs = tempfile::tempdir().unwrap();
+    let lib = libs.path().join("Lib");
+    std::fs::create_dir_all(lib.join("Resources")).unwrap();
+    std::fs::write(lib.join("package.mo"), "package Lib\nend Lib;\n").unwrap();
+    std::fs::write(lib.join("Resources/gain.csv"), "0,1\n1,2\n").unwrap();
+    let lib_path = libs.path().to_str().unwrap();
+
+    let source = |file: &str| {
+        format!(
+            "model M\n  parameter String file = \"modelica://Lib/Resources/{}\";\n  Real y;\nequation\n  y = table1D(file, time);\nend M;\n",
+            file
+        )
+    };
+    let result = Compiler::new()
+        .model("M")
+        .modelica_path(&[lib_path])
+        .compile_str(&source("gain.csv"), "M.mo")
+        .unwrap();
+    let path = lib.join("Resources/gain.csv");
+    assert_eq!(result.resources.len(), 1);
+    assert_eq!(result.resources[0].uri, "modelica://Lib/Resources/gain.csv");
+    assert_eq!(result.resources[0].path, path);
+    assert_eq!(
+        result.dae.p["file"].start.to_string(),
+        format!("\"{}\"", path.display())
+    );
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+    let result = Compiler::new()
+        .model("M")
+        .modelica_path(&[lib_path])
+        .compile_str(&source("missing.csv"), "M.mo")
+        .unwrap();
+    assert!(result.resources.is_empty());
+    assert_eq!(
+        result.warnings[0].to_string(),
+        format!(
+            "M.mo:2:27: resource 'modelica://Lib/Resources/missing.csv' not found at '{}' [missing-resource]",
+            lib.join("Resources/missing.csv").display()
+        )
+    );
+
+    // The package of the model is found without library paths
+    let model = lib.join("M.mo");
+    std::fs::write(&model, format!("within Lib;\n{}", source("gain.csv"))).unwrap();
+    let result = Compiler::new()
+        .model("Lib.M")
+        .compile_package(lib.to_str().unwrap())
+        .unwrap();
+    assert_eq!(result.resources[0].path, path);
+
+    let err = Compiler::new()
+        .model("M")
+        .deny(&[DiagnosticCode::MissingResource])
+        .compile_str(&source("gain.csv"), "M.mo")
+        .unwrap_err();
+    assert!(matches!(err, Error::Denied(_)), "{}", err);
+}
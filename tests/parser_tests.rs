@@ -60,3 +60,114 @@ fn test_all_models_parse_successfully() {
         parse_test_file(model).unwrap_or_else(|e| panic!("Failed to parse {}: {}", model, e));
     }
 }
+
+#[test]
+fn test_parse_normalizes_bom_and_line_endings() {
+    let source = "\u{feff}model M\r\n  /* 20°C */ Real x;\r  Real y;\r\nequation\r\n  x = 1;\n  y = 2;\r\nend M;\r\n";
+    let def = rumoca::parse_source(source, "bom.mo").unwrap();
+    let m = &def.class_list["M"];
+
+    // Lines count every newline style, columns count characters rather than bytes
+    let x = &m.components["x"].name_token.location;
+    assert_eq!((x.start_line, x.start_column), (2, 19));
+    let y = &m.components["y"].name_token.location;
+    assert_eq!((y.start_line, y.start_column), (3, 8));
+}
+
+#[test]
+fn test_read_latin1_source() {
+    let path = std::env::temp_dir().join(format!("rumoca_latin1_{}.mo", std::process::id()));
+    std::fs::write(
+        &path,
+        b"model M\r\n  // 20\xb0C\r\n  Real x;\r\nequation\r\n  x = 1;\r\nend M;\r\n",
+    )
+    .unwrap();
+    let (source, warning) = rumoca::read_source_with_warning(&path).unwrap();
+    let compiled = rumoca::Compiler::new()
+        .model("M")
+        .compile_file(&path.to_string_lossy());
+    let denied = rumoca::Compiler::new()
+        .model("M")
+        .deny(&[rumoca::DiagnosticCode::Encoding])
+        .compile_file(&path.to_string_lossy());
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        source,
+        "model M\n  // 20°C\n  Real x;\nequation\n  x = 1;\nend M;\n"
+    );
+    assert!(rumoca::parse_source(&source, "latin1.mo").is_ok());
+
+    // The fallback is reported as a warning of the compilation, not printed
+    let warning = warning.expect("latin-1 source should be reported");
+    assert_eq!(
+        warning.message,
+        format!(
+            "{} is not valid UTF-8, decoded it as ISO-8859-1",
+            path.display()
+        )
+    );
+    assert_eq!(compiled.unwrap().warnings, vec![warning]);
+    assert!(matches!(denied, Err(rumoca::Error::Denied(_))));
+}
+
+#[test]
+fn test_parse_equation_descriptions() {
+    let source = "model M\n  Real x[2], y;\nequation\n  der(y) = -y \"decay\";\n  for i in 1:2 loop\n    x[i] = i \"element\";\n  end for;\nalgorithm\n  y := 1 \"reset\";\nend M;\n";
+    let def = rumoca::parse_source(source, "desc.mo").unwrap();
+    let class = &def.class_list["M"];
+    let rumoca::ir::ast::Equation::For { equations, .. } = &class.equations[1] else {
+        panic!("expected a for-equation");
+    };
+    assert_eq!(class.equations[0].description().as_deref(), Some("decay"));
+    assert_eq!(class.equations[1].description(), None);
+    assert_eq!(equations[0].description().as_deref(), Some("element"));
+    assert_eq!(
+        class.algorithms[0][0].description().as_deref(),
+        Some("reset")
+    );
+    // The description of a when-equation is kept too
+    let source = "model W\n  Real x;\nequation\n  when time > 1 then\n    reinit(x, 0);\n  end when \"at 1 s\";\nend W;\n";
+    let def = rumoca::parse_source(source, "when.mo").unwrap();
+    assert_eq!(
+        def.class_list["W"].equations[0].description().as_deref(),
+        Some("at 1 s")
+    );
+}
+
+#[test]
+fn test_parse_source_spans() {
+    use rumoca::ir::ast::SectionKind;
+
+    let source = "package P\n  partial model M \"desc\"\n    Real x;\n  equation\n    x = 1 \"one\";\n    if x > 0 then\n      connect(a, b);\n    end if;\n  initial algorithm\n    (x) := f(1);\n  end M;\nend P;\n";
+    let def = rumoca::parse_source(source, "spans.mo").unwrap();
+    let text = |loc: &rumoca::ir::ast::Location| &source[loc.start as usize..loc.end as usize];
+    let m = &def.class_list["P"].classes["M"];
+
+    assert!(text(&m.span).starts_with("partial model M \"desc\""));
+    assert!(text(&m.span).ends_with("end M"));
+    assert_eq!(text(&def.class_list["P"].span), &source[..source.len() - 2]);
+
+    let kinds: Vec<SectionKind> = m.sections.iter().map(|s| s.kind).collect();
+    assert_eq!(
+        kinds,
+        [SectionKind::Equation, SectionKind::InitialAlgorithm]
+    );
+    let equations = &m.sections[0];
+    assert!(text(&equations.location).starts_with("equation\n"));
+    assert!(text(&equations.location).ends_with("end if;"));
+    let items: Vec<&str> = equations.items.iter().map(text).collect();
+    assert_eq!(
+        items,
+        [
+            "x = 1 \"one\";",
+            "if x > 0 then\n      connect(a, b);\n    end if;"
+        ]
+    );
+    assert_eq!(equations.items[1].start_line, 6);
+    assert_eq!(equations.items[1].start_column, 5);
+    assert_eq!(
+        text(&m.sections[1].location),
+        "initial algorithm\n    (x) := f(1);"
+    );
+}
@@ -0,0 +1,131 @@
+//! Tests for the SymPy template, examples/templates/sympy.jinja
+
+mod common;
+
+use common::compile_source;
+
+const TEMPLATE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/templates/sympy.jinja"
+);
+
+fn render(source: &str, model: &str) -> String {
+    compile_source(source, model)
+        .unwrap()
+        .render_template_to_string(TEMPLATE)
+        .unwrap()
+}
+
+#[test]
+fn test_sympy_symbolic_parameters() {
+    let source = r#"
+model Oscillator
+  parameter Real k = 2;
+  parameter Real c = 0.1;
+  parameter Real k2 = 2 * k;
+  input Real f;
+  Real x(start = 1);
+  Real v;
+  output Real y;
+equation
+  der(x) = v;
+  der(v) = -k * x - c * v + f;
+  y = k2 * x;
+end Oscillator;
+"#;
+    let code = render(source, "Oscillator");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+
+    // Parameters are symbols, their bindings are kept as expressions
+    assert!(code.contains(r#"k = sympy.Symbol("k")"#), "{}", code);
+    assert!(code.contains(r#""k": 2,"#), "{}", code);
+    assert!(code.contains(r#""k2": (2 * k),"#), "{}", code);
+
+    // Equations refer to the parameters by name
+    assert!(code.contains("y - ((k2 * x))"), "{}", code);
+    assert!(
+        code.contains("args = [self.x, self.u, self.p, self.time]"),
+        "{}",
+        code
+    );
+    assert!(code.contains("def f(self, x, p=None, t=0.0):"), "{}", code);
+    assert!(code.contains("def g(self, x, p=None, t=0.0):"), "{}", code);
+}
+
+#[test]
+fn test_sympy_conditions_and_events() {
+    let source = r#"
+model Bouncing
+  parameter Real e = 0.8;
+  Real h(start = 1);
+  Real v(start = 0);
+  Real drag;
+equation
+  der(h) = v;
+  der(v) = -9.81 - drag;
+  drag = if v > 0 then 0.1 * v else noEvent(if h > 0.5 then 0 else 0.05 * v);
+  when h < 0 then
+    reinit(v, -e * pre(v));
+  end when;
+end Bouncing;
+"#;
+    let code = render(source, "Bouncing");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+    assert!(code.contains("c0 = sympy.Gt(v, 0)"), "{}", code);
+    assert!(
+        code.contains(
+            "drag - (sympy.Piecewise(((0.1 * v), c0), (sympy.Piecewise((0, sympy.Gt(h, 0.5)), ((0.05 * v), True)), True)))"
+        ),
+        "{}",
+        code
+    );
+    assert!(code.contains(r#""c1": (h - (0), -1),"#), "{}", code);
+    assert!(
+        code.contains(r#"_x[self.x_index["v"]] = -((e * v))"#),
+        "{}",
+        code
+    );
+}
+
+#[test]
+fn test_sympy_input_sources() {
+    let source = r#"
+model LowPass
+  parameter Real tau = 0.1;
+  input Real u;
+  Real x(start = 0);
+equation
+  der(x) = (u - x) / tau;
+end LowPass;
+"#;
+    let code = render(source, "LowPass");
+    assert!(!code.contains("UNHANDLED"), "{}", code);
+    assert!(
+        code.contains("def set_input(self, name, source, interpolation='zoh'):"),
+        "{}",
+        code
+    );
+
+    // Each interpolation mode builds its own function of time
+    assert!(
+        code.contains(
+            "if interpolation == 'linear':
+            self.inputs[name] = lambda t: np.interp(t, times, values)"
+        ),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains(
+            "elif interpolation == 'zoh':
+            def zoh(t):
+                i = np.searchsorted(times, t, side='right') - 1"
+        ),
+        "{}",
+        code
+    );
+
+    // Inputs without a source are zero
+    assert!(code.contains("def input_function(self):"), "{}", code);
+    assert!(code.contains("u = np.zeros(self.u.shape[0])"), "{}", code);
+}